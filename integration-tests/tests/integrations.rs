@@ -1,121 +1,7788 @@
 //! Integration tests for zingo-Indexer.
 //! Currently uses ZCashD as ZebraD has not yet implemented Regtest Mode.
+//!
+//! Tests written with [`validator_matrix`] generate a case per validator, zebrad cases are skipped until it can
+//! be launched by the test manager.
 
 #![forbid(unsafe_code)]
 
 use std::sync::{atomic::AtomicBool, Arc};
+use zaino_fetch::jsonrpc::connector::NodeAuth;
 use zaino_testutils::{
-    drop_test_manager, get_zingo_address, start_zingo_mempool_monitor, Pool, TestManager,
+    drop_test_manager, get_zingo_address, start_zingo_mempool_monitor, validator_matrix, Pool,
+    TestCase, TestHarness, TestManager, TestManagerBuilder, ValidatorKind,
 };
 
 mod wallet_basic {
     use super::*;
+    use zingolib::lightclient::LightClient;
+
+    /// Launches a test case with a faucet client synced after mining `blocks` blocks.
+    async fn launch_synced(validator: ValidatorKind, blocks: u32) -> (TestCase, LightClient) {
+        let harness = TestCase::launch(TestManagerBuilder::new(), validator).await;
+        let zingo_client = harness.build_lightclient().await;
+        harness.regtest_manager.generate_n_blocks(blocks).unwrap();
+        zingo_client.do_sync(false).await.unwrap();
+        (harness, zingo_client)
+    }
+
+    /// Sends 250_000 zatoshis from the faucet to its own address of each of the given types.
+    async fn send_to_self(zingo_client: &LightClient, address_types: &[&str]) {
+        for address_type in address_types {
+            zingo_client
+                .do_send(vec![(
+                    &get_zingo_address(zingo_client, address_type).await,
+                    250_000,
+                    None,
+                )])
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Mines a block and syncs the client.
+    async fn mine_and_sync(harness: &TestCase, zingo_client: &LightClient) {
+        harness.regtest_manager.generate_n_blocks(1).unwrap();
+        zingo_client.do_sync(false).await.unwrap();
+    }
+
+    validator_matrix! {
+        async fn connect_to_node_get_info(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            let zingo_client = harness.build_lightclient().await;
+
+            let lightd_info = zingo_client.do_info().await;
+            println!("[TEST LOG] Lightd_info response:\n{:#?}.", lightd_info);
+
+            harness.teardown().await;
+        }
+
+        async fn grpc_client_without_indexer_port_errors(validator: ValidatorKind) {
+            let mut harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            // Point the test manager at a port nothing is listening on.
+            harness.indexer_port = std::net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+
+            let error = harness.try_build_grpc_client().await.unwrap_err();
+            println!("[TEST LOG] Client error: {}.", error);
+            assert!(error.contains("clients requested but Zaino gRPC port unavailable"));
+
+            harness.teardown().await;
+        }
+
+        async fn clients_connect_to_explicit_ipv6_uri(validator: ValidatorKind) {
+            let harness = TestCase::launch(
+                TestManagerBuilder::new()
+                    .listen_ip(std::net::IpAddr::V6(std::net::Ipv6Addr::LOCALHOST)),
+                validator,
+            )
+            .await;
+            let indexer_uri: tonic::transport::Uri =
+                format!("http://[::1]:{}", harness.indexer_port)
+                    .parse()
+                    .unwrap();
+            assert_eq!(harness.get_indexer_uri(), indexer_uri);
+
+            let mut grpc_client = harness
+                .try_build_grpc_client_for_uri(indexer_uri.clone())
+                .await
+                .unwrap();
+            let lightd_info = grpc_client
+                .get_lightd_info(zaino_proto::proto::service::Empty {})
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(lightd_info.block_height > 0);
+
+            let zingo_client = harness.build_lightclient_for_uri(indexer_uri).await;
+            mine_and_sync(&harness, &zingo_client).await;
+            println!(
+                "[TEST LOG] zingo_client balance: \n{:#?}.",
+                zingo_client.do_balance().await
+            );
+
+            harness.teardown().await;
+        }
+
+        async fn send_to_orchard(validator: ValidatorKind) {
+            let (harness, zingo_client) = launch_synced(validator, 1).await;
+            send_to_self(&zingo_client, &["unified"]).await;
+            mine_and_sync(&harness, &zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.orchard_balance.unwrap(), 1_875_000_000);
+
+            harness.teardown().await;
+        }
+
+        async fn send_to_sapling(validator: ValidatorKind) {
+            let (harness, zingo_client) = launch_synced(validator, 1).await;
+            send_to_self(&zingo_client, &["sapling"]).await;
+            mine_and_sync(&harness, &zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.sapling_balance.unwrap(), 250_000);
+
+            harness.teardown().await;
+        }
+
+        async fn send_to_transparent(validator: ValidatorKind) {
+            let (harness, zingo_client) = launch_synced(validator, 1).await;
+            send_to_self(&zingo_client, &["transparent"]).await;
+            mine_and_sync(&harness, &zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.transparent_balance.unwrap(), 250_000);
+
+            harness.teardown().await;
+        }
+
+        async fn send_to_multiple(validator: ValidatorKind) {
+            let (harness, zingo_client) = launch_synced(validator, 2).await;
+            send_to_self(&zingo_client, &["unified", "sapling", "transparent"]).await;
+            mine_and_sync(&harness, &zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.orchard_balance.unwrap(), 2_499_500_000);
+            assert_eq!(balance.sapling_balance.unwrap(), 250_000);
+            assert_eq!(balance.transparent_balance.unwrap(), 250_000);
+
+            harness.teardown().await;
+        }
+
+        async fn shield_from_sapling(validator: ValidatorKind) {
+            let (harness, zingo_client) = launch_synced(validator, 1).await;
+            send_to_self(&zingo_client, &["sapling"]).await;
+            mine_and_sync(&harness, &zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.sapling_balance.unwrap(), 250_000);
+
+            zingo_client
+                .do_shield(&[Pool::Sapling.into()], None)
+                .await
+                .unwrap();
+            mine_and_sync(&harness, &zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.sapling_balance.unwrap(), 0);
+            assert_eq!(balance.orchard_balance.unwrap(), 2_500_000_000);
+
+            harness.teardown().await;
+        }
+
+        async fn shield_from_transparent(validator: ValidatorKind) {
+            let (harness, zingo_client) = launch_synced(validator, 1).await;
+            send_to_self(&zingo_client, &["transparent"]).await;
+            mine_and_sync(&harness, &zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.transparent_balance.unwrap(), 250_000);
+
+            zingo_client
+                .do_shield(&[Pool::Transparent.into()], None)
+                .await
+                .unwrap();
+            mine_and_sync(&harness, &zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.transparent_balance.unwrap(), 0);
+            assert_eq!(balance.orchard_balance.unwrap(), 2_500_000_000);
+
+            harness.teardown().await;
+        }
+
+        async fn shield_from_multiple(validator: ValidatorKind) {
+            let (harness, zingo_client) = launch_synced(validator, 1).await;
+            send_to_self(&zingo_client, &["sapling", "transparent"]).await;
+            mine_and_sync(&harness, &zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.sapling_balance.unwrap(), 250_000);
+            assert_eq!(balance.transparent_balance.unwrap(), 250_000);
+
+            zingo_client
+                .do_shield(&[Pool::Sapling.into(), Pool::Transparent.into()], None)
+                .await
+                .unwrap();
+            mine_and_sync(&harness, &zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.sapling_balance.unwrap(), 0);
+            assert_eq!(balance.transparent_balance.unwrap(), 0);
+            assert_eq!(balance.orchard_balance.unwrap(), 2_500_000_000);
+
+            harness.teardown().await;
+        }
+
+        async fn sync_full_batch(validator: ValidatorKind) {
+            let (harness, zingo_client) = launch_synced(validator, 2).await;
+            for address_type in ["unified", "sapling", "transparent"] {
+                harness.regtest_manager.generate_n_blocks(30).unwrap();
+                send_to_self(&zingo_client, &[address_type]).await;
+            }
+            harness.regtest_manager.generate_n_blocks(30).unwrap();
+
+            println!("[TEST LOG] syncing full batch.");
+            zingo_client.do_sync(false).await.unwrap();
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.orchard_balance.unwrap(), 76_874_500_000);
+            assert_eq!(balance.sapling_balance.unwrap(), 250_000);
+            assert_eq!(balance.transparent_balance.unwrap(), 250_000);
+
+            harness.teardown().await;
+        }
+
+        async fn monitor_unverified_mempool(validator: ValidatorKind) {
+            let (harness, zingo_client) = launch_synced(validator, 1).await;
+            send_to_self(&zingo_client, &["sapling", "sapling"]).await;
+
+            start_zingo_mempool_monitor(&zingo_client).await;
+
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.unverified_sapling_balance.unwrap(), 500_000);
+
+            mine_and_sync(&harness, &zingo_client).await;
+            let balance = zingo_client.do_balance().await;
+            println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
+            assert_eq!(balance.verified_sapling_balance.unwrap(), 500_000);
+
+            harness.teardown().await;
+        }
+    }
+}
+
+mod wallet_seed {
+    use super::*;
+    use zaino_testutils::{ABANDON_ART_SEED, REG_O_ADDR_FROM_ABANDONART};
+
+    validator_matrix! {
+        async fn orchard_address_is_stable_for_fixed_seed(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+
+            let (_zingo_client, addresses) =
+                harness.build_lightclient_from_seed(ABANDON_ART_SEED, 1).await;
+            println!("[TEST LOG] Client addresses: {:#?}.", addresses);
+            assert_eq!(addresses.unified, REG_O_ADDR_FROM_ABANDONART);
+
+            // Restoring the same seed derives the same addresses.
+            let (_zingo_client, restored) =
+                harness.build_lightclient_from_seed(ABANDON_ART_SEED, 1).await;
+            assert_eq!(restored, addresses);
+
+            harness.teardown().await;
+        }
+    }
+}
+
+mod transaction_fixtures {
+    use zaino_fetch::chain::{
+        error::ParseError, transaction::FullTransaction, utils::ParseFromSlice,
+    };
+    use zaino_testutils::fixtures::{
+        INVALID_TX_HEX, VALID_ORCHARD_TX_HEX, VALID_SAPLING_TX_HEX, VALID_TRANSPARENT_TX_HEX,
+    };
+
+    /// Parses the transaction encoded in `tx_hex`, asserting it is consumed completely.
+    fn parse(tx_hex: &str) -> FullTransaction {
+        let data = hex::decode(tx_hex).unwrap();
+        let transaction =
+            FullTransaction::parse_exact(&data, Some(vec![vec![0; 32]]), None).unwrap();
+        assert_eq!(transaction.raw_bytes, data);
+        transaction
+    }
+
+    #[test]
+    fn transparent_fixture_parses() {
+        let transaction = parse(VALID_TRANSPARENT_TX_HEX).raw_transaction;
+        assert_eq!(transaction.version, 5);
+        assert_eq!(transaction.transparent_inputs.len(), 1);
+        assert_eq!(transaction.transparent_outputs.len(), 1);
+        assert_eq!(transaction.transparent_outputs[0].value, 250_000);
+        assert!(transaction.shielded_outputs.is_empty());
+        assert!(transaction.orchard_actions.is_empty());
+    }
+
+    #[test]
+    fn sapling_fixture_parses() {
+        let transaction = parse(VALID_SAPLING_TX_HEX);
+        assert!(transaction.has_shielded_elements());
+        assert_eq!(transaction.raw_transaction.shielded_outputs.len(), 1);
+        assert!(transaction.raw_transaction.orchard_actions.is_empty());
+
+        let compact = transaction.to_compact(0).unwrap();
+        assert_eq!(compact.outputs.len(), 1);
+        assert_eq!(compact.outputs[0].ciphertext.len(), 52);
+    }
+
+    #[test]
+    fn orchard_fixture_parses() {
+        let transaction = parse(VALID_ORCHARD_TX_HEX);
+        assert!(transaction.has_shielded_elements());
+        assert!(transaction.raw_transaction.shielded_outputs.is_empty());
+        assert_eq!(transaction.raw_transaction.orchard_actions.len(), 1);
+
+        let compact = transaction.to_compact(0).unwrap();
+        assert_eq!(compact.actions.len(), 1);
+        assert_eq!(compact.actions[0].ciphertext.len(), 52);
+    }
+
+    #[test]
+    fn invalid_fixture_fails_to_parse() {
+        let data = hex::decode(INVALID_TX_HEX).unwrap();
+        let error =
+            FullTransaction::parse_from_slice(&data, Some(vec![vec![0; 32]]), None).unwrap_err();
+        println!("[TEST LOG] Parse error: {}.", error);
+        assert!(error.to_string().contains("version group ID"));
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let mut data = hex::decode(VALID_SAPLING_TX_HEX).unwrap();
+        data.extend_from_slice(&[0; 3]);
+        let (remaining, _) =
+            FullTransaction::parse_from_slice(&data, Some(vec![vec![0; 32]]), None).unwrap();
+        assert_eq!(remaining.len(), 3);
+
+        let error = FullTransaction::parse_exact(&data, Some(vec![vec![0; 32]]), None).unwrap_err();
+        assert!(matches!(error, ParseError::TrailingBytes(3)));
+    }
+}
+
+mod stream_ordering {
+    use super::*;
+    use zaino_proto::proto::service::{BlockId, BlockRange, TransparentAddressBlockFilter};
+    use zaino_testutils::{assert_stream_ordered, StreamOrder};
+
+    fn block_range(start: u64, end: u64) -> BlockRange {
+        BlockRange {
+            start: Some(BlockId {
+                height: start,
+                hash: Vec::new(),
+            }),
+            end: Some(BlockId {
+                height: end,
+                hash: Vec::new(),
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_block_range_is_ordered() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(10).unwrap();
+        let mut grpc_client = test_manager.build_grpc_client().await;
+
+        // Blocks are returned from the top of the range, regardless of the order start and end are given in.
+        for range in [block_range(1, 10), block_range(10, 1)] {
+            let stream = grpc_client
+                .get_block_range(range)
+                .await
+                .unwrap()
+                .into_inner();
+            let blocks =
+                assert_stream_ordered(stream, StreamOrder::Descending, |block| block.height).await;
+            assert_eq!(blocks.len(), 10);
+        }
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn get_taddress_txids_is_ordered() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) =
+            TestManager::launch(online.clone()).await;
+        let zingo_client = test_manager.build_lightclient().await;
+        let taddr = get_zingo_address(&zingo_client, "transparent").await;
+
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+        zingo_client.do_sync(false).await.unwrap();
+        for _ in 0..3 {
+            zingo_client
+                .do_send(vec![(&taddr, 250_000, None)])
+                .await
+                .unwrap();
+            test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+            zingo_client.do_sync(false).await.unwrap();
+        }
+
+        let mut grpc_client = test_manager.build_grpc_client().await;
+        let stream = grpc_client
+            .get_taddress_txids(TransparentAddressBlockFilter {
+                address: taddr,
+                range: Some(block_range(1, 5)),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let transactions =
+            assert_stream_ordered(stream, StreamOrder::Ascending, |tx| tx.height).await;
+        assert_eq!(transactions.len(), 3);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod block_range_trailers {
+    use super::*;
+    use zaino_proto::proto::service::{BlockId, BlockRange};
+    use zaino_serve::rpc::service::{BLOCKS_SERVED_TRAILER, LAST_HEIGHT_TRAILER};
+
+    /// Returns the value of the given trailer, parsed as an integer.
+    fn trailer(trailers: &tonic::metadata::MetadataMap, key: &str) -> u64 {
+        trailers
+            .get(key)
+            .unwrap_or_else(|| panic!("Missing trailer {}.", key))
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    validator_matrix! {
+        async fn get_block_range_trailers_match_blocks_received(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            harness.regtest_manager.generate_n_blocks(10).unwrap();
+            let mut grpc_client = harness.build_grpc_client().await;
+
+            let mut stream = grpc_client
+                .get_block_range(BlockRange {
+                    start: Some(BlockId {
+                        height: 1,
+                        hash: Vec::new(),
+                    }),
+                    end: Some(BlockId {
+                        height: 10,
+                        hash: Vec::new(),
+                    }),
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            let mut blocks = Vec::new();
+            while let Some(block) = stream.message().await.unwrap() {
+                blocks.push(block);
+            }
+            let trailers = stream.trailers().await.unwrap().unwrap();
+            println!("[TEST LOG] Trailers: {:?}.", trailers);
+
+            assert_eq!(blocks.len(), 10);
+            assert_eq!(
+                trailer(&trailers, BLOCKS_SERVED_TRAILER),
+                blocks.len() as u64
+            );
+            assert_eq!(
+                trailer(&trailers, LAST_HEIGHT_TRAILER),
+                blocks.last().unwrap().height
+            );
+
+            harness.teardown().await;
+        }
+    }
+}
+
+mod block_range_validation {
+    use super::*;
+    use zaino_proto::proto::service::{
+        compact_tx_streamer_server::CompactTxStreamer, BlockId, BlockRange, ChainSpec,
+    };
+    use zaino_testutils::{GrpcClientBuilder, MockNode};
+
+    fn block_range(start: u64, end: u64) -> BlockRange {
+        BlockRange {
+            start: Some(BlockId {
+                height: start,
+                hash: Vec::new(),
+            }),
+            end: Some(BlockId {
+                height: end,
+                hash: Vec::new(),
+            }),
+        }
+    }
+
+    validator_matrix! {
+        async fn get_block_range_rejects_heights_above_tip(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            harness.regtest_manager.generate_n_blocks(5).unwrap();
+            let mut grpc_client = harness.build_grpc_client().await;
+            let tip = grpc_client
+                .get_latest_block(ChainSpec {})
+                .await
+                .unwrap()
+                .into_inner()
+                .height;
+
+            for (start, end) in [(1, tip + 1), (999_999_999, 1), (tip + 1, tip + 10)] {
+                let status = grpc_client
+                    .get_block_range(block_range(start, end))
+                    .await
+                    .unwrap_err();
+                println!("[TEST LOG] Range {}-{} status: {:?}.", start, end, status);
+                assert_eq!(status.code(), tonic::Code::InvalidArgument);
+                assert_eq!(
+                    status.message(),
+                    format!("End height {} exceeds chain tip {}", start.max(end), tip)
+                );
+
+                let status = grpc_client
+                    .get_block_range_nullifiers(block_range(start, end))
+                    .await
+                    .unwrap_err();
+                assert_eq!(status.code(), tonic::Code::InvalidArgument);
+            }
+
+            // A range ending at the tip is served in full.
+            let mut stream = grpc_client
+                .get_block_range(block_range(1, tip))
+                .await
+                .unwrap()
+                .into_inner();
+            let mut blocks = 0;
+            while let Some(_block) = stream.message().await.unwrap() {
+                blocks += 1;
+            }
+            assert_eq!(blocks, tip);
+
+            harness.teardown().await;
+        }
+
+        async fn get_block_range_rejects_genesis_start(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            let mut grpc_client = harness.build_grpc_client().await;
+
+            for (start, end) in [(0, 1), (1, 0), (0, 0)] {
+                let status = grpc_client
+                    .get_block_range(block_range(start, end))
+                    .await
+                    .unwrap_err();
+                println!("[TEST LOG] Range {}-{} status: {:?}.", start, end, status);
+                assert_eq!(status.code(), tonic::Code::InvalidArgument);
+                assert_eq!(status.message(), "Start height must be greater than 0");
+
+                let status = grpc_client
+                    .get_block_range_nullifiers(block_range(start, end))
+                    .await
+                    .unwrap_err();
+                assert_eq!(status.code(), tonic::Code::InvalidArgument);
+            }
+
+            harness.teardown().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn get_block_range_checks_the_observed_tip() {
+        let node = MockNode::with_responses(serde_json::json!({
+            "getblockchaininfo": { "result": {
+                "chain": "regtest",
+                "blocks": 10,
+                "bestblockhash": "00".repeat(32),
+                "estimatedheight": 10,
+                "upgrades": {},
+                "consensus": { "chaintip": "c2d6d0b4", "nextblock": "c2d6d0b4" },
+            } },
+        }))
+        .await;
+        let client = GrpcClientBuilder::new(node.uri()).build();
+
+        // No tip has been observed, the node is asked for it.
+        assert!(client
+            .get_block_range(tonic::Request::new(block_range(1, 5)))
+            .await
+            .is_ok());
+        assert_eq!(node.call_count("getblockchaininfo"), 1);
+
+        // Ranges ending at or below the observed tip are checked without calling the node.
+        assert!(client
+            .get_block_range(tonic::Request::new(block_range(1, 10)))
+            .await
+            .is_ok());
+        assert!(client
+            .get_block_range_nullifiers(tonic::Request::new(block_range(3, 7)))
+            .await
+            .is_ok());
+        assert_eq!(node.call_count("getblockchaininfo"), 1);
+
+        // A range ending above the observed tip is checked against the node's current tip.
+        let Err(status) = client
+            .get_block_range(tonic::Request::new(block_range(1, 11)))
+            .await
+        else {
+            panic!("Range above the tip was served.");
+        };
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(status.message(), "End height 11 exceeds chain tip 10");
+        assert_eq!(node.call_count("getblockchaininfo"), 2);
+    }
+}
+
+mod zaino_height {
+    use super::*;
+    use std::time::Duration;
+    use zaino_proto::proto::service::{BlockId, ChainSpec};
+
+    validator_matrix! {
+        async fn mined_blocks_are_served_after_polling(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            let mut grpc_client = harness.build_grpc_client().await;
+            let start_height = grpc_client
+                .get_latest_block(ChainSpec {})
+                .await
+                .unwrap()
+                .into_inner()
+                .height;
+
+            harness.regtest_manager.generate_n_blocks(5).unwrap();
+            let reached = harness
+                .poll_zaino_height(start_height + 5, Duration::from_secs(60))
+                .await
+                .unwrap();
+            println!("[TEST LOG] Zaino reached height {}.", reached);
+            assert!(reached >= start_height + 5);
+
+            // The indexer serves the mined blocks once its tip has reached them.
+            let block = grpc_client
+                .get_block(BlockId {
+                    height: start_height + 5,
+                    hash: Vec::new(),
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(block.height, start_height + 5);
+
+            harness.teardown().await;
+        }
+
+        async fn polling_an_unmined_height_times_out(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+
+            let error = harness
+                .poll_zaino_height(1_000_000, Duration::from_secs(1))
+                .await
+                .unwrap_err();
+            println!("[TEST LOG] Poll error: {}.", error);
+            assert!(error.contains("did not reach height 1000000"));
+
+            harness.teardown().await;
+        }
+    }
+}
+
+mod height_conversions {
+    use super::*;
+    use zaino_fetch::primitives::height::{
+        height_to_u64, i64_to_height, u64_to_height, ChainHeight,
+    };
+    use zaino_proto::proto::service::{
+        compact_tx_streamer_server::CompactTxStreamer, BlockId, BlockRange,
+    };
+    use zaino_serve::rpc::primitives::request_height;
+    use zaino_testutils::GrpcClientBuilder;
+
+    #[test]
+    fn heights_convert_to_u64_losslessly() {
+        assert_eq!(height_to_u64(ChainHeight::MIN), 0);
+        assert_eq!(height_to_u64(ChainHeight::MAX), (u32::MAX / 2) as u64);
+    }
+
+    #[test]
+    fn u64_heights_above_max_are_rejected() {
+        let max = height_to_u64(ChainHeight::MAX);
+        assert_eq!(u64_to_height(0).unwrap(), ChainHeight::MIN);
+        assert_eq!(u64_to_height(max).unwrap(), ChainHeight::MAX);
+        assert!(u64_to_height(max + 1).is_err());
+        assert!(u64_to_height(u32::MAX as u64).is_err());
+        // Would truncate to height 0 with an `as u32` cast.
+        assert!(u64_to_height(u32::MAX as u64 + 1).is_err());
+        assert!(u64_to_height(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn negative_node_heights_are_rejected() {
+        let max = ChainHeight::MAX.0 as i64;
+        assert_eq!(i64_to_height(0).unwrap(), ChainHeight::MIN);
+        assert_eq!(i64_to_height(max).unwrap(), ChainHeight::MAX);
+        assert!(i64_to_height(max + 1).is_err());
+        assert!(i64_to_height(-1).is_err());
+        assert!(i64_to_height(i64::MIN).is_err());
+        assert!(i64_to_height(i64::MAX).is_err());
+    }
+
+    #[test]
+    fn out_of_range_request_heights_are_invalid_arguments() {
+        assert_eq!(request_height(5).unwrap(), 5);
+        let status = request_height(u32::MAX as u64 + 5).unwrap_err();
+        println!("[TEST LOG] Status: {:?}.", status);
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status.message().contains("4294967301"));
+    }
+
+    #[tokio::test]
+    async fn block_range_rejects_truncated_heights() {
+        let grpc_client = GrpcClientBuilder::new(&"http://127.0.0.1:1".parse().unwrap()).build();
+        // Both heights would truncate to 1 with an `as u32` cast.
+        let status = grpc_client
+            .get_block_range(tonic::Request::new(BlockRange {
+                start: Some(BlockId {
+                    height: u32::MAX as u64 + 2,
+                    hash: Vec::new(),
+                }),
+                end: Some(BlockId {
+                    height: 1,
+                    hash: Vec::new(),
+                }),
+            }))
+            .await
+            .err()
+            .expect("out of range start height accepted");
+        println!("[TEST LOG] Status: {:?}.", status);
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
+
+mod get_block_verification {
+    use super::*;
+    use std::sync::Mutex;
+    use zaino_fetch::primitives::block::BlockHash;
+    use zaino_proto::proto::{
+        compact_formats::{
+            CompactBlock, CompactOrchardAction, CompactSaplingOutput, CompactSaplingSpend,
+            CompactTx,
+        },
+        service::BlockId,
+    };
+    use zaino_serve::rpc::{
+        primitives::{block_lookup, resolve_block_id, BlockLookup, ResolvedBlock},
+        service::{fetch_verified_block, nullifiers_only, BLOCK_REORG_MESSAGE},
+    };
+
+    /// Returns a compact block at `height` whose internal byte order hash is filled with `hash_byte`.
+    fn block(height: u64, hash_byte: u8) -> CompactBlock {
+        CompactBlock {
+            height,
+            hash: vec![hash_byte; 32],
+            ..Default::default()
+        }
+    }
+
+    /// Block cache that serves the next of its blocks on each lookup, whatever the height, simulating a reorg
+    /// replacing the cached block between lookups.
+    struct SwappingCache(Mutex<Vec<CompactBlock>>);
+
+    impl SwappingCache {
+        fn new(blocks: Vec<CompactBlock>) -> Self {
+            SwappingCache(Mutex::new(blocks))
+        }
+
+        async fn get_block(&self, _height: u32) -> Result<CompactBlock, tonic::Status> {
+            Ok(self.0.lock().unwrap().remove(0))
+        }
+    }
+
+    /// Returns the display order hash of a block built by [`block`].
+    fn display_hash(hash_byte: u8) -> Vec<u8> {
+        BlockHash([hash_byte; 32]).bytes_in_display_order().to_vec()
+    }
+
+    #[tokio::test]
+    async fn block_matching_request_is_served() {
+        let cache = SwappingCache::new(vec![block(5, 0xaa), block(5, 0xaa)]);
+        let by_height = fetch_verified_block(
+            BlockId {
+                height: 5,
+                hash: Vec::new(),
+            },
+            |_| async { panic!("a block given by height is not resolved") },
+            |height| cache.get_block(height),
+        )
+        .await
+        .unwrap();
+        assert_eq!(by_height, block(5, 0xaa));
+
+        let by_hash = fetch_verified_block(
+            BlockId {
+                height: 0,
+                hash: display_hash(0xaa),
+            },
+            |hash| async move {
+                assert_eq!(hash, BlockHash([0xaa; 32]));
+                Ok(5)
+            },
+            |height| cache.get_block(height),
+        )
+        .await
+        .unwrap();
+        assert_eq!(by_hash, block(5, 0xaa));
+    }
+
+    #[tokio::test]
+    async fn block_swapped_after_hash_lookup_is_aborted() {
+        // The hash resolves to height 5, but the block at height 5 is replaced before it is fetched.
+        let cache = SwappingCache::new(vec![block(5, 0xbb)]);
+        let status = fetch_verified_block(
+            BlockId {
+                height: 0,
+                hash: display_hash(0xaa),
+            },
+            |_| async { Ok(5) },
+            |height| cache.get_block(height),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Aborted);
+        assert_eq!(status.message(), BLOCK_REORG_MESSAGE);
+    }
+
+    #[tokio::test]
+    async fn block_at_other_height_is_aborted() {
+        // The hash resolves to height 5, but the block served for height 5 is at another height.
+        let cache = SwappingCache::new(vec![block(6, 0xaa)]);
+        let status = fetch_verified_block(
+            BlockId {
+                height: 5,
+                hash: display_hash(0xaa),
+            },
+            |_| async { Ok(5) },
+            |height| cache.get_block(height),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Aborted);
+        assert_eq!(status.message(), BLOCK_REORG_MESSAGE);
+    }
+
+    #[test]
+    fn non_zero_hash_takes_precedence() {
+        for (height, hash, expected) in [
+            (0, Vec::new(), Ok(BlockLookup::Height(0))),
+            (5, Vec::new(), Ok(BlockLookup::Height(5))),
+            (5, vec![0; 32], Ok(BlockLookup::Height(5))),
+            (0, vec![0; 32], Err(tonic::Code::InvalidArgument)),
+            (
+                0,
+                display_hash(0xaa),
+                Ok(BlockLookup::Hash(BlockHash([0xaa; 32]))),
+            ),
+            (
+                5,
+                display_hash(0xaa),
+                Ok(BlockLookup::Hash(BlockHash([0xaa; 32]))),
+            ),
+        ] {
+            let block_id = BlockId { height, hash };
+            assert_eq!(
+                block_lookup(&block_id).map_err(|status| status.code()),
+                expected,
+                "{:?}",
+                block_id
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn hash_is_resolved_when_height_is_also_given() {
+        for height in [0, 5] {
+            let resolved = resolve_block_id(
+                &BlockId {
+                    height,
+                    hash: display_hash(0xaa),
+                },
+                |hash| async move {
+                    assert_eq!(hash, BlockHash([0xaa; 32]));
+                    Ok(5)
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                resolved,
+                ResolvedBlock {
+                    height: 5,
+                    hash: Some(BlockHash([0xaa; 32])),
+                }
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn hash_at_other_height_is_rejected() {
+        let status = resolve_block_id(
+            &BlockId {
+                height: 6,
+                hash: display_hash(0xaa),
+            },
+            |_| async { Ok(5) },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn height_above_maximum_is_rejected_with_hash() {
+        for hash in [Vec::new(), display_hash(0xaa)] {
+            let status = resolve_block_id(
+                &BlockId {
+                    height: u64::from(u32::MAX) + 1,
+                    hash,
+                },
+                |_| async { panic!("an out of range height is rejected before resolving") },
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn hash_resolution_errors_are_returned() {
+        let status = resolve_block_id(
+            &BlockId {
+                height: 0,
+                hash: display_hash(0xaa),
+            },
+            |_| async { Err(tonic::Status::not_found("Block not found.")) },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[test]
+    fn nullifiers_only_keeps_spend_nullifiers() {
+        let tx = CompactTx {
+            index: 1,
+            hash: vec![0x11; 32],
+            fee: 0,
+            spends: vec![CompactSaplingSpend { nf: vec![0x22; 32] }],
+            outputs: vec![CompactSaplingOutput {
+                cmu: vec![0x33; 32],
+                ephemeral_key: vec![0x44; 32],
+                ciphertext: vec![0x55; 52],
+            }],
+            actions: vec![CompactOrchardAction {
+                nullifier: vec![0x66; 32],
+                cmx: vec![0x77; 32],
+                ephemeral_key: vec![0x88; 32],
+                ciphertext: vec![0x99; 52],
+            }],
+        };
+        let full = CompactBlock {
+            vtx: vec![tx.clone()],
+            ..block(5, 0xaa)
+        };
+
+        let stripped = nullifiers_only(full);
+        assert_eq!(stripped.height, 5);
+        assert_eq!(stripped.hash, vec![0xaa; 32]);
+        assert_eq!(
+            stripped.vtx,
+            vec![CompactTx {
+                outputs: Vec::new(),
+                actions: vec![CompactOrchardAction {
+                    nullifier: vec![0x66; 32],
+                    ..Default::default()
+                }],
+                ..tx
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn genesis_block_is_requested_by_height() {
+        let genesis = fetch_verified_block(
+            BlockId {
+                height: 0,
+                hash: Vec::new(),
+            },
+            |_| async { panic!("a block given by height is not resolved") },
+            |height| async move {
+                assert_eq!(height, 0);
+                Ok(block(0, 0xaa))
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(genesis, block(0, 0xaa));
+    }
+
+    #[tokio::test]
+    async fn zero_hash_requests_block_by_height() {
+        let served = fetch_verified_block(
+            BlockId {
+                height: 5,
+                hash: vec![0; 32],
+            },
+            |_| async { panic!("a block given by height is not resolved") },
+            |height| async move { Ok(block(u64::from(height), 0xaa)) },
+        )
+        .await
+        .unwrap();
+        assert_eq!(served, block(5, 0xaa));
+    }
+
+    #[tokio::test]
+    async fn invalid_hash_is_rejected() {
+        let status = fetch_verified_block(
+            BlockId {
+                height: 0,
+                hash: vec![0xaa; 31],
+            },
+            |_| async { Ok(5) },
+            |_| async { Ok(block(5, 0xaa)) },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    validator_matrix! {
+        async fn block_by_hash_matches_block_by_height(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            let mut grpc_client = harness.build_grpc_client().await;
+
+            let by_height = grpc_client
+                .get_block(BlockId {
+                    height: 1,
+                    hash: Vec::new(),
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            let mut hash = by_height.hash.clone();
+            hash.reverse();
+            let by_hash = grpc_client
+                .get_block(BlockId { height: 0, hash })
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(by_hash, by_height);
+
+            harness.teardown().await;
+        }
+    }
+}
+
+mod block_header_hash {
+    use super::*;
+    use zaino_fetch::{
+        chain::{block::BlockHeaderData, utils::ParseFromSlice},
+        jsonrpc::{connector::JsonRpcConnector, response::GetBlockResponse},
+    };
+
+    /// Display order hash of the regtest genesis block, shared by zcashd and zebrad.
+    const REGTEST_GENESIS_HASH: &str =
+        "029f11d80ef9765602235e1bc9727e3eb6ba20839319f761fee920d63401e327";
+
+    /// Fetches the raw block at `height` from the node and returns the hash computed from its parsed header.
+    async fn header_hash(node: &JsonRpcConnector, height: u32) -> [u8; 32] {
+        let GetBlockResponse::Raw(block) =
+            node.get_block(height.to_string(), Some(0)).await.unwrap()
+        else {
+            panic!("node returned a block object for verbosity 0");
+        };
+        let (_, header) = BlockHeaderData::parse_from_slice(block.as_ref(), None, None).unwrap();
+        header.hash()
+    }
+
+    validator_matrix! {
+        async fn header_hash_matches_node(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            harness.regtest_manager.generate_n_blocks(5).unwrap();
+            let zebrad_client = JsonRpcConnector::new(
+                harness.test_and_return_zebrad_uri().await,
+                NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+            )
+            .await;
+
+            let genesis_hash = header_hash(&zebrad_client, 0).await;
+            assert_eq!(genesis_hash, zebrad_client.get_block_hash(0).await.unwrap());
+            let mut display_hash = genesis_hash;
+            display_hash.reverse();
+            assert_eq!(hex::encode(display_hash), REGTEST_GENESIS_HASH);
+
+            assert_eq!(
+                header_hash(&zebrad_client, 1).await,
+                zebrad_client.get_block_hash(1).await.unwrap()
+            );
+
+            let (tip, best_block_hash) = zebrad_client.get_blockchain_info().await.unwrap().tip();
+            assert_eq!(header_hash(&zebrad_client, tip.0).await, best_block_hash.0);
+
+            harness.teardown().await;
+        }
+    }
+}
+
+mod block_time {
+    use super::*;
+    use zaino_fetch::jsonrpc::{connector::JsonRpcConnector, response::GetBlockResponse};
+    use zaino_proto::proto::service::{BlockId, BlockRange};
+
+    validator_matrix! {
+        async fn compact_blocks_carry_header_time(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            harness.regtest_manager.generate_n_blocks(10).unwrap();
+            let mut grpc_client = harness.build_grpc_client().await;
+            let zebrad_client = JsonRpcConnector::new(
+                harness.test_and_return_zebrad_uri().await,
+                NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+            )
+            .await;
+
+            let mut stream = grpc_client
+                .get_block_range(BlockRange {
+                    start: Some(BlockId {
+                        height: 1,
+                        hash: Vec::new(),
+                    }),
+                    end: Some(BlockId {
+                        height: 10,
+                        hash: Vec::new(),
+                    }),
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            let mut blocks = Vec::new();
+            while let Some(block) = stream.message().await.unwrap() {
+                blocks.push(block);
+            }
+            assert_eq!(blocks.len(), 10);
+
+            for block in &blocks {
+                assert_ne!(block.time, 0, "block {} has no time", block.height);
+                // The header nTime matches the block time reported by the node.
+                if let GetBlockResponse::Object {
+                    time: Some(time), ..
+                } = zebrad_client
+                    .get_block(block.height.to_string(), Some(1))
+                    .await
+                    .unwrap()
+                {
+                    assert_eq!(block.time as i64, time);
+                }
+            }
+            let times: Vec<u32> = blocks.iter().map(|block| block.time).collect();
+            println!("[TEST LOG] Block times: {:?}.", times);
+            assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+
+            harness.teardown().await;
+        }
+    }
+}
+
+mod send_transaction_fees {
+    use super::*;
+    use zaino_fetch::jsonrpc::{
+        connector::{FeeLimit, JsonRpcConnector},
+        error::RPC_TRANSACTION_REJECTED,
+    };
+    use zaino_proto::proto::service::RawTransaction;
+
+    /// Builds a signed transaction spending a mature coinbase output of zcashd's wallet back to its own address,
+    /// paying a fee of 1 ZEC, far above zcashd's default `maxtxfee` of 0.1 ZEC.
+    fn build_high_fee_transaction(test_manager: &TestManager) -> String {
+        let unspent: serde_json::Value =
+            serde_json::from_str(&test_manager.zcash_cli(&["listunspent"])).unwrap();
+        let utxo = &unspent[0];
+        let inputs = serde_json::json!([{ "txid": utxo["txid"], "vout": utxo["vout"] }]);
+        let amount = ((utxo["amount"].as_f64().unwrap() - 1.0) * 1e8).round() / 1e8;
+        let mut outputs = serde_json::Map::new();
+        outputs.insert(
+            utxo["address"].as_str().unwrap().to_string(),
+            serde_json::json!(amount),
+        );
+        let unsigned = test_manager.zcash_cli(&[
+            "createrawtransaction",
+            &inputs.to_string(),
+            &serde_json::Value::Object(outputs).to_string(),
+        ]);
+        let signed: serde_json::Value =
+            serde_json::from_str(&test_manager.zcash_cli(&["signrawtransaction", &unsigned]))
+                .unwrap();
+        assert!(signed["complete"].as_bool().unwrap());
+        signed["hex"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn high_fee_transaction_needs_allow_high_fees() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        // Coinbase outputs mature after 100 blocks.
+        test_manager.regtest_manager.generate_n_blocks(101).unwrap();
+        let tx_hex = build_high_fee_transaction(&test_manager);
+        let zebrad_client = JsonRpcConnector::new(
+            test_manager.test_and_return_zebrad_uri().await,
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await;
+
+        let error = zebrad_client
+            .send_raw_transaction(tx_hex.clone(), None)
+            .await
+            .unwrap_err();
+        println!("[TEST LOG] Send error: {}.", error);
+        assert!(error.is_fee_rejection());
+
+        // Zaino reports the fee rejection in the SendResponse rather than as an error status.
+        let send_response = test_manager
+            .build_grpc_client()
+            .await
+            .send_transaction(RawTransaction {
+                data: hex::decode(&tx_hex).unwrap(),
+                height: 0,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        println!("[TEST LOG] Send response: {:?}.", send_response);
+        assert_eq!(send_response.error_code, RPC_TRANSACTION_REJECTED);
+
+        zebrad_client
+            .send_raw_transaction(tx_hex, Some(FeeLimit::AllowHighFees))
+            .await
+            .unwrap();
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod latest_block {
+    use super::*;
+    use zaino_fetch::jsonrpc::connector::JsonRpcConnector;
+    use zaino_proto::proto::service::{BlockId, BlockRange, ChainSpec};
+
+    #[tokio::test]
+    async fn get_latest_block_with_confirmations_returns_tip_minus_offset() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .latest_block_confirmations(2)
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(10).unwrap();
+        let mut grpc_client = test_manager.build_grpc_client().await;
+
+        let tip = JsonRpcConnector::new(
+            test_manager.test_and_return_zebrad_uri().await,
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await
+        .get_blockchain_info()
+        .await
+        .unwrap()
+        .blocks
+        .0;
+        let latest = grpc_client
+            .get_latest_block(ChainSpec {})
+            .await
+            .unwrap()
+            .into_inner();
+        println!("[TEST LOG] Tip: {}, latest block: {}.", tip, latest.height);
+        assert_eq!(latest.height, tip as u64 - 2);
+
+        // The hash is of the block at the returned height, in display order.
+        let block_id = BlockId {
+            height: latest.height,
+            hash: Vec::new(),
+        };
+        let block = grpc_client
+            .get_block_range(BlockRange {
+                start: Some(block_id.clone()),
+                end: Some(block_id),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .message()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            latest.hash,
+            block.hash.iter().rev().copied().collect::<Vec<u8>>()
+        );
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn latest_block_hash_matches_height_while_mining() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        let mut grpc_client = test_manager.build_grpc_client().await;
+
+        // Blocks are mined one at a time on a separate thread while the latest block is requested in a loop.
+        let regtest_manager = &test_manager.regtest_manager;
+        let runtime = tokio::runtime::Handle::current();
+        let latest_blocks = tokio::task::block_in_place(|| {
+            std::thread::scope(|scope| {
+                let miner = scope.spawn(|| {
+                    for _ in 0..20 {
+                        regtest_manager.generate_n_blocks(1).unwrap();
+                    }
+                });
+                let mut latest_blocks = Vec::new();
+                while !miner.is_finished() {
+                    latest_blocks.push(
+                        runtime
+                            .block_on(grpc_client.get_latest_block(ChainSpec {}))
+                            .unwrap()
+                            .into_inner(),
+                    );
+                }
+                latest_blocks
+            })
+        });
+        println!(
+            "[TEST LOG] Requested the latest block {} times while mining.",
+            latest_blocks.len()
+        );
+
+        let zebrad_client = JsonRpcConnector::new(
+            test_manager.test_and_return_zebrad_uri().await,
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await;
+        for latest in latest_blocks {
+            let mut hash = zebrad_client
+                .get_block_hash(latest.height as u32)
+                .await
+                .unwrap();
+            // The node returns hashes in internal byte order, get_latest_block in display order.
+            hash.reverse();
+            assert_eq!(latest.hash, hash.to_vec(), "height {}", latest.height);
+        }
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod taddress_txids {
+    use super::*;
+    use zaino_proto::proto::service::{
+        BlockId, BlockRange, ChainSpec, TransparentAddressBlockFilter,
+    };
+
+    #[tokio::test]
+    async fn get_taddress_txids_includes_mempool() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .taddress_txids_include_mempool()
+            .launch(online.clone())
+            .await;
+        let zingo_client = test_manager.build_lightclient().await;
+        let taddr = get_zingo_address(&zingo_client, "transparent").await;
+
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+        zingo_client.do_sync(false).await.unwrap();
+        zingo_client
+            .do_send(vec![(&taddr, 250_000, None)])
+            .await
+            .unwrap();
+
+        let mut grpc_client = test_manager.build_grpc_client().await;
+        let tip = grpc_client
+            .get_latest_block(ChainSpec {})
+            .await
+            .unwrap()
+            .into_inner()
+            .height;
+        let mut stream = grpc_client
+            .get_taddress_txids(TransparentAddressBlockFilter {
+                address: taddr,
+                range: Some(BlockRange {
+                    start: Some(BlockId {
+                        height: 1,
+                        hash: Vec::new(),
+                    }),
+                    end: Some(BlockId {
+                        height: tip,
+                        hash: Vec::new(),
+                    }),
+                }),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut transactions = Vec::new();
+        while let Some(transaction) = stream.message().await.unwrap() {
+            transactions.push(transaction);
+        }
+        println!("[TEST LOG] Transactions: {:?}.", transactions);
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].height, 0);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod address_utxos_stream {
+    use super::*;
+    use zaino_proto::proto::service::{
+        compact_tx_streamer_client::CompactTxStreamerClient, GetAddressUtxosArg,
+        GetAddressUtxosReply,
+    };
+
+    /// Returns the UTXOs streamed by get_address_utxos_stream for `taddr` from `start_height`.
+    async fn utxo_page(
+        grpc_client: &mut CompactTxStreamerClient<tonic::transport::Channel>,
+        taddr: &str,
+        start_height: u64,
+        max_entries: u32,
+    ) -> Vec<GetAddressUtxosReply> {
+        let mut stream = grpc_client
+            .get_address_utxos_stream(GetAddressUtxosArg {
+                addresses: vec![taddr.to_string()],
+                start_height,
+                max_entries,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut utxos = Vec::new();
+        while let Some(utxo) = stream.message().await.unwrap() {
+            utxos.push(utxo);
+        }
+        utxos
+    }
+
+    #[tokio::test]
+    async fn utxos_are_paged_in_height_order() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) =
+            TestManagerBuilder::new().launch(online.clone()).await;
+        let zingo_client = test_manager.build_lightclient().await;
+        let taddr = get_zingo_address(&zingo_client, "transparent").await;
+
+        // Funds the taddr with one UTXO in each of 6 blocks.
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+        for _ in 0..6 {
+            zingo_client.do_sync(false).await.unwrap();
+            zingo_client
+                .do_send(vec![(&taddr, 10_000, None)])
+                .await
+                .unwrap();
+            test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+        }
+
+        let mut grpc_client = test_manager.build_grpc_client().await;
+        let all_utxos = utxo_page(&mut grpc_client, &taddr, 0, 0).await;
+        println!("[TEST LOG] UTXOs: {:?}.", all_utxos);
+        assert_eq!(all_utxos.len(), 6);
+        assert!(all_utxos
+            .windows(2)
+            .all(|pair| pair[0].height <= pair[1].height));
+        assert!(all_utxos.iter().all(|utxo| utxo.address == taddr));
+
+        // Pages through the UTXOs in two calls, resuming from the height of the last UTXO received.
+        let first_page = utxo_page(&mut grpc_client, &taddr, 0, 4).await;
+        assert_eq!(first_page, all_utxos[..4]);
+        let resume_height = first_page.last().unwrap().height;
+        let second_page: Vec<GetAddressUtxosReply> =
+            utxo_page(&mut grpc_client, &taddr, resume_height, 0)
+                .await
+                .into_iter()
+                .filter(|utxo| !first_page.contains(utxo))
+                .collect();
+        assert_eq!(second_page, all_utxos[4..]);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod get_transaction {
+    use super::*;
+    use zaino_fetch::jsonrpc::connector::JsonRpcConnector;
+    use zaino_proto::proto::service::TxFilter;
+
+    /// Returns a TxFilter for the transaction with the given display order hex txid.
+    fn tx_filter(txid: &str) -> TxFilter {
+        TxFilter {
+            block: None,
+            index: 0,
+            hash: hex::decode(txid).unwrap().into_iter().rev().collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_transaction_height_before_and_after_mining() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) =
+            TestManager::launch(online.clone()).await;
+        let zingo_client = test_manager.build_lightclient().await;
+        let taddr = get_zingo_address(&zingo_client, "transparent").await;
+
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+        zingo_client.do_sync(false).await.unwrap();
+        zingo_client
+            .do_send(vec![(&taddr, 250_000, None)])
+            .await
+            .unwrap();
+        let zebrad_client = JsonRpcConnector::new(
+            test_manager.test_and_return_zebrad_uri().await,
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await;
+        let txid = zebrad_client
+            .get_raw_mempool(false)
+            .await
+            .unwrap()
+            .txids()
+            .pop()
+            .unwrap();
+
+        let mut grpc_client = test_manager.build_grpc_client().await;
+        let unmined = grpc_client
+            .get_transaction(tx_filter(&txid))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(unmined.height, 0);
+
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+        let tip = zebrad_client.get_blockchain_info().await.unwrap().blocks.0 as u64;
+        let mined = grpc_client
+            .get_transaction(tx_filter(&txid))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(mined.height, tip);
+        assert_eq!(mined.data, unmined.data);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn missing_transaction_lookups_hit_negative_cache_until_new_block() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        let mut grpc_client = test_manager.build_grpc_client().await;
+        let missing_txid = "ab".repeat(32);
+
+        // The first miss is looked up on the node, the repeat is answered from the negative cache.
+        for _ in 0..2 {
+            let status = grpc_client
+                .get_transaction(tx_filter(&missing_txid))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::NotFound);
+        }
+        assert!(test_manager
+            .get_metrics()
+            .await
+            .contains("zaino_negative_cache_hits_total 1"));
+
+        // Observing a new tip drops the cached miss, so the next lookup goes back to the node.
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+        grpc_client
+            .get_latest_block(zaino_proto::proto::service::ChainSpec {})
+            .await
+            .unwrap();
+        let status = grpc_client
+            .get_transaction(tx_filter(&missing_txid))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        assert!(test_manager
+            .get_metrics()
+            .await
+            .contains("zaino_negative_cache_hits_total 1"));
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod keepalive {
+    use super::*;
+    use std::time::Duration;
+    use zaino_fetch::primitives::chain::NetworkKind;
+    use zaino_proto::proto::service::{BlockId, BlockRange};
+    use zaino_serve::server::keepalive::Keepalive;
+    use zainodlib::config::IndexerConfig;
+
+    #[tokio::test]
+    async fn keepalive_settings_apply_to_server_and_connection() {
+        let keepalive = Keepalive {
+            interval: Some(Duration::from_secs(1)),
+            timeout: Some(Duration::from_secs(1)),
+            tcp: true,
+        };
+        let _builder = keepalive.apply_http2(tonic::transport::Server::builder());
+        let _builder = Keepalive::default().apply_http2(tonic::transport::Server::builder());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = tokio::net::TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        keepalive.apply_tcp(&server).unwrap();
+        Keepalive::default().apply_tcp(&client).unwrap();
+    }
+
+    #[test]
+    fn zero_keepalive_durations_are_rejected() {
+        let mut config = IndexerConfig::default_for_network(NetworkKind::Regtest);
+        config.keepalive_interval_secs = Some(0);
+        assert!(config.check_config().is_err());
+
+        let mut config = IndexerConfig::default_for_network(NetworkKind::Regtest);
+        config.keepalive_timeout_secs = Some(0);
+        assert!(config.check_config().is_err());
+    }
+
+    #[tokio::test]
+    async fn long_block_range_completes_with_keepalive() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .keepalive(1)
+            .launch(online.clone())
+            .await;
+        test_manager
+            .regtest_manager
+            .generate_n_blocks(1000)
+            .unwrap();
+        let mut grpc_client = test_manager.build_grpc_client().await;
+
+        let mut stream = grpc_client
+            .get_block_range(BlockRange {
+                start: Some(BlockId {
+                    height: 1,
+                    hash: Vec::new(),
+                }),
+                end: Some(BlockId {
+                    height: 1000,
+                    hash: Vec::new(),
+                }),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut blocks = 0;
+        while let Some(_block) = stream.message().await.unwrap() {
+            blocks += 1;
+        }
+        println!("[TEST LOG] Blocks streamed: {}.", blocks);
+        assert_eq!(blocks, 1000);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod compression {
+    use super::*;
+    use zaino_proto::proto::{
+        compact_formats::CompactBlock,
+        service::{compact_tx_streamer_client::CompactTxStreamerClient, BlockId, BlockRange},
+    };
+    use zainodlib::config::GrpcCompression;
+
+    /// Streams blocks 1 to 10, returning the response's grpc-encoding header and the blocks received.
+    async fn stream_blocks(
+        grpc_client: &mut CompactTxStreamerClient<tonic::transport::Channel>,
+    ) -> (Option<String>, Vec<CompactBlock>) {
+        let response = grpc_client
+            .get_block_range(BlockRange {
+                start: Some(BlockId {
+                    height: 1,
+                    hash: Vec::new(),
+                }),
+                end: Some(BlockId {
+                    height: 10,
+                    hash: Vec::new(),
+                }),
+            })
+            .await
+            .unwrap();
+        let encoding = response
+            .metadata()
+            .get("grpc-encoding")
+            .map(|value| value.to_str().unwrap().to_string());
+        let mut stream = response.into_inner();
+        let mut blocks = Vec::new();
+        while let Some(block) = stream.message().await.unwrap() {
+            blocks.push(block);
+        }
+        (encoding, blocks)
+    }
+
+    #[tokio::test]
+    async fn get_block_range_is_compressed() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .grpc_compression(GrpcCompression::Zstd)
+            .grpc_compression(GrpcCompression::Gzip)
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(10).unwrap();
+
+        let (encoding, uncompressed_blocks) =
+            stream_blocks(&mut test_manager.build_grpc_client().await).await;
+        assert_eq!(encoding, None);
+        assert_eq!(uncompressed_blocks.len(), 10);
+
+        for (compression, expected_encoding) in [
+            (GrpcCompression::Gzip, "gzip"),
+            (GrpcCompression::Zstd, "zstd"),
+        ] {
+            let (encoding, blocks) =
+                stream_blocks(&mut test_manager.build_compressed_grpc_client(compression).await)
+                    .await;
+            assert_eq!(encoding.as_deref(), Some(expected_encoding));
+            assert_eq!(blocks, uncompressed_blocks);
+        }
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod compatibility {
+    use super::*;
+    use zaino_proto::proto::service::{Empty, LightdInfo};
+
+    /// Returns the names of the fields set to a non-default value.
+    fn set_fields(info: &LightdInfo) -> Vec<&'static str> {
+        [
+            ("version", !info.version.is_empty()),
+            ("vendor", !info.vendor.is_empty()),
+            ("taddr_support", info.taddr_support),
+            ("chain_name", !info.chain_name.is_empty()),
+            (
+                "sapling_activation_height",
+                info.sapling_activation_height != 0,
+            ),
+            ("consensus_branch_id", !info.consensus_branch_id.is_empty()),
+            ("block_height", info.block_height != 0),
+            ("git_commit", !info.git_commit.is_empty()),
+            ("branch", !info.branch.is_empty()),
+            ("build_date", !info.build_date.is_empty()),
+            ("build_user", !info.build_user.is_empty()),
+            ("estimated_height", info.estimated_height != 0),
+            ("zcashd_build", !info.zcashd_build.is_empty()),
+            ("zcashd_subversion", !info.zcashd_subversion.is_empty()),
+        ]
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect()
+    }
+
+    #[tokio::test]
+    async fn get_lightd_info_fields_match_lightwalletd() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+
+        let zaino_info = test_manager
+            .build_grpc_client()
+            .await
+            .get_lightd_info(Empty {})
+            .await
+            .unwrap()
+            .into_inner();
+        let lwd_info = test_manager
+            .build_lightwalletd_grpc_client()
+            .await
+            .get_lightd_info(Empty {})
+            .await
+            .unwrap()
+            .into_inner();
+        println!(
+            "[TEST LOG] Zaino lightd_info:\n{:#?}.\nLightwalletd lightd_info:\n{:#?}.",
+            zaino_info, lwd_info
+        );
+
+        let lwd_fields = set_fields(&lwd_info);
+        let extra_fields: Vec<&str> = set_fields(&zaino_info)
+            .into_iter()
+            .filter(|field| !lwd_fields.contains(field))
+            .collect();
+        assert!(
+            extra_fields.is_empty(),
+            "fields set by zaino but not lightwalletd: {:?}",
+            extra_fields
+        );
+        assert_eq!(zaino_info.chain_name, lwd_info.chain_name);
+        assert_eq!(zaino_info.consensus_branch_id, lwd_info.consensus_branch_id);
+        assert_eq!(zaino_info.block_height, lwd_info.block_height);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod chain_cache {
+    use zaino_testutils::{
+        chain_cache::{chain_cache_dir, inspect_chain_cache, REGTEST_BLOCK_FILE_MAGIC},
+        ValidatorKind,
+    };
+
+    /// Returns a zcashd block file record holding a block of `length` bytes.
+    fn block_record(length: u32) -> Vec<u8> {
+        let mut record = REGTEST_BLOCK_FILE_MAGIC.to_vec();
+        record.extend_from_slice(&length.to_le_bytes());
+        record.extend(std::iter::repeat(0xab).take(length as usize));
+        record
+    }
+
+    #[test]
+    fn block_records_are_counted() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let blocks_dir = cache_dir.path().join("regtest").join("blocks");
+        std::fs::create_dir_all(&blocks_dir).unwrap();
+        // Block files are preallocated, the records are followed by zero padding.
+        let mut blk00000 = [block_record(100), block_record(250), block_record(80)].concat();
+        blk00000.extend([0u8; 512]);
+        let blk00001 = block_record(40);
+        std::fs::write(blocks_dir.join("blk00000.dat"), &blk00000).unwrap();
+        std::fs::write(blocks_dir.join("blk00001.dat"), &blk00001).unwrap();
+        std::fs::write(blocks_dir.join("rev00000.dat"), [0u8; 64]).unwrap();
+        std::fs::write(cache_dir.path().join("zcash.conf"), "regtest=1\n").unwrap();
+
+        let info = inspect_chain_cache(cache_dir.path());
+        println!("[TEST LOG] Chain cache: {:?}.", info);
+        assert_eq!(info.block_count, 4);
+        assert_eq!(
+            info.total_size_bytes,
+            (blk00000.len() + blk00001.len() + 64 + "regtest=1\n".len()) as u64
+        );
+    }
+
+    #[test]
+    fn zebrad_chain_cache_is_larger_than_zcashd_chain_cache() {
+        let zcashd_dir = chain_cache_dir(ValidatorKind::Zcashd);
+        let zebrad_dir = chain_cache_dir(ValidatorKind::Zebrad);
+        if !zcashd_dir.is_dir() || !zebrad_dir.is_dir() {
+            println!(
+                "[TEST LOG] Skipping, chain caches not found at {} and {}.",
+                zcashd_dir.display(),
+                zebrad_dir.display()
+            );
+            return;
+        }
+
+        let zcashd = inspect_chain_cache(&zcashd_dir);
+        let zebrad = inspect_chain_cache(&zebrad_dir);
+        println!("[TEST LOG] Zcashd chain cache: {:?}.", zcashd);
+        println!("[TEST LOG] Zebrad chain cache: {:?}.", zebrad);
+        assert!(zebrad.total_size_bytes > zcashd.total_size_bytes);
+    }
+}
+
+mod golden_compact_blocks {
+    use zaino_fetch::chain::{
+        block::{BlockHeaderData, FullBlock},
+        utils::{CompactSize, ParseFromSlice},
+    };
+    use zaino_proto::proto::compact_formats::{
+        ChainMetadata, CompactBlock, CompactSaplingOutput, CompactTx,
+    };
+    use zaino_testutils::{
+        block_diff::BlockComparison,
+        chain_cache::{chain_cache_dir, read_chain_cache_blocks},
+        golden::{
+            assert_golden_blocks, compare_golden_blocks, write_golden_blocks,
+            REGENERATE_GOLDEN_COMMAND,
+        },
+        ValidatorKind,
+    };
+
+    /// Returns a compact block at `height` holding a transaction with a single sapling output.
+    fn block(height: u64) -> CompactBlock {
+        CompactBlock {
+            proto_version: 1,
+            height,
+            hash: vec![0x11; 32],
+            prev_hash: vec![0x22; 32],
+            time: 1_700_000_000,
+            header: Vec::new(),
+            vtx: vec![CompactTx {
+                index: 1,
+                hash: vec![0x33; 32],
+                fee: 0,
+                spends: Vec::new(),
+                outputs: vec![CompactSaplingOutput {
+                    cmu: vec![0x44; 32],
+                    ephemeral_key: vec![0x55; 32],
+                    ciphertext: vec![0x66; 52],
+                }],
+                actions: Vec::new(),
+            }],
+            chain_metadata: Some(ChainMetadata {
+                sapling_commitment_tree_size: 1,
+                orchard_commitment_tree_size: 0,
+            }),
+        }
+    }
+
+    /// Converts the raw blocks of a chain cache to compact blocks with Zaino's block parser.
+    ///
+    /// Txids are returned by the node rather than held in the block, placeholder txids are given so the compact
+    /// blocks depend on the parser alone.
+    fn compact_blocks(raw_blocks: &[Vec<u8>]) -> Vec<CompactBlock> {
+        raw_blocks
+            .iter()
+            .map(|raw_block| {
+                let (transactions, _) =
+                    BlockHeaderData::parse_from_slice(raw_block, None, None).unwrap();
+                let tx_count = CompactSize::read(transactions).unwrap();
+                let txids = (0..tx_count)
+                    .map(|index| (index as u32).to_le_bytes().repeat(8))
+                    .collect();
+                FullBlock::parse_to_compact(raw_block, Some(txids), 0, 0).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn differing_fields_are_reported_by_path() {
+        let expected = block(7);
+        let mut actual = block(7);
+        actual.time += 1;
+        actual.vtx[0].outputs[0].cmu[0] = 0xff;
+
+        let diff = BlockComparison::new().diff(&expected, &actual);
+        println!("[TEST LOG] Diff:\n{}", diff);
+        let paths: Vec<&str> = diff
+            .differences
+            .iter()
+            .map(|difference| difference.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["time", "vtx[0].outputs[0].cmu"]);
+        assert!(diff
+            .to_string()
+            .contains("vtx[0].outputs[0].cmu: expected 4444"));
+
+        // Missing list elements are reported as a length difference.
+        actual.vtx.clear();
+        let diff = BlockComparison::new().diff(&expected, &actual);
+        assert_eq!(diff.differences[1].path, "vtx.len");
+        assert_eq!(diff.differences[1].expected, "1");
+        assert_eq!(diff.differences[1].actual, "0");
+    }
+
+    #[test]
+    fn ignored_fields_are_tolerated() {
+        let expected = block(7);
+        let mut actual = block(7);
+        actual.proto_version = 0;
+        actual.vtx[0].fee = 1000;
+        assert_eq!(
+            BlockComparison::new()
+                .diff(&expected, &actual)
+                .differences
+                .len(),
+            2
+        );
+
+        BlockComparison::new()
+            .ignore("proto_version")
+            .ignore("vtx.fee")
+            .assert_eq(&expected, &actual);
+        assert!(BlockComparison::new().diff(&expected, &expected).is_empty());
+    }
+
+    #[test]
+    fn golden_file_changes_are_reported() {
+        let golden_dir = tempfile::tempdir().unwrap();
+        let path = golden_dir.path().join("blocks.hex");
+        let blocks = vec![block(1), block(2)];
+
+        let missing = compare_golden_blocks(&path, &blocks, &BlockComparison::new()).unwrap_err();
+        println!("[TEST LOG] Missing golden file report:\n{}", missing);
+        assert!(missing.contains(REGENERATE_GOLDEN_COMMAND));
+
+        write_golden_blocks(&path, &blocks).unwrap();
+        compare_golden_blocks(&path, &blocks, &BlockComparison::new()).unwrap();
+
+        let mut changed = blocks.clone();
+        changed[1].vtx[0].outputs[0].ciphertext.pop();
+        changed.push(block(3));
+        let report = compare_golden_blocks(&path, &changed, &BlockComparison::new()).unwrap_err();
+        println!("[TEST LOG] Changed blocks report:\n{}", report);
+        assert!(report.contains("Golden file holds 2 blocks, 3 were produced."));
+        assert!(report.contains("Block 1 (height 2) differs"));
+        assert!(report.contains("vtx[0].outputs[0].ciphertext"));
+        assert!(report.contains(REGENERATE_GOLDEN_COMMAND));
+    }
+
+    #[test]
+    fn zcashd_chain_cache_blocks_match_golden_file() {
+        let cache_dir = chain_cache_dir(ValidatorKind::Zcashd);
+        if !cache_dir.is_dir() {
+            println!(
+                "[TEST LOG] Skipping, chain cache not found at {}.",
+                cache_dir.display()
+            );
+            return;
+        }
+
+        let blocks = compact_blocks(&read_chain_cache_blocks(&cache_dir));
+        println!("[TEST LOG] Parsed {} chain cache blocks.", blocks.len());
+        assert!(!blocks.is_empty());
+        assert_golden_blocks("zcashd_chain_cache", &blocks, &BlockComparison::new());
+    }
+}
+
+mod validator_kind {
+    use zaino_testutils::ValidatorKind;
+
+    #[test]
+    fn parse_is_case_insensitive() {
+        for (name, validator) in [
+            ("zcashd", ValidatorKind::Zcashd),
+            ("zebrad", ValidatorKind::Zebrad),
+            ("Zcashd", ValidatorKind::Zcashd),
+            ("ZEBRAD", ValidatorKind::Zebrad),
+        ] {
+            assert_eq!(name.parse::<ValidatorKind>(), Ok(validator));
+        }
+    }
+
+    #[test]
+    fn parse_rejects_unknown_validators() {
+        for name in ["lightwalletd", "", "zcash d"] {
+            assert_eq!(
+                name.parse::<ValidatorKind>(),
+                Err(format!(
+                    "Invalid validator kind: {}, expected zcashd or zebrad.",
+                    name
+                ))
+            );
+        }
+    }
+}
+
+mod tree_state {
+    use super::*;
+    use zaino_fetch::{
+        jsonrpc::connector::JsonRpcConnector, primitives::transaction::CommitmentTreestate,
+    };
+    use zaino_proto::proto::service::{BlockId, ChainSpec};
+
+    #[tokio::test]
+    async fn get_tree_state_at_height_one() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+        let mut grpc_client = test_manager.build_grpc_client().await;
+
+        let tree_state = grpc_client
+            .get_tree_state(BlockId {
+                height: 1,
+                hash: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        println!("[TEST LOG] Tree state at height 1:\n{:#?}.", tree_state);
+        assert_eq!(tree_state.height, 1);
+        for tree in [&tree_state.sapling_tree, &tree_state.orchard_tree] {
+            assert!(!tree.is_empty());
+            assert!(tree.chars().all(|c| c.is_ascii_hexdigit()));
+        }
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn get_tree_state_of_genesis_block() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        let mut grpc_client = test_manager.build_grpc_client().await;
+
+        // An empty hash with height 0 requests the genesis block by height.
+        let tree_state = grpc_client
+            .get_tree_state(BlockId {
+                height: 0,
+                hash: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        println!("[TEST LOG] Tree state at genesis:\n{:#?}.", tree_state);
+        let genesis_hash = JsonRpcConnector::new(
+            test_manager.test_and_return_zebrad_uri().await,
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await
+        .get_block_hash(0)
+        .await
+        .unwrap();
+        let genesis_hash_display: Vec<u8> = genesis_hash.iter().rev().copied().collect();
+        assert_eq!(tree_state.height, 0);
+        assert_eq!(tree_state.hash, hex::encode(&genesis_hash_display));
+
+        // A non-zero hash requests the block by hash.
+        let tree_state_by_hash = grpc_client
+            .get_tree_state(BlockId {
+                height: 0,
+                hash: genesis_hash_display,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(tree_state_by_hash, tree_state);
+
+        // An all-zero hash with height 0 identifies no block.
+        let status = grpc_client
+            .get_tree_state(BlockId {
+                height: 0,
+                hash: vec![0; 32],
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn get_tree_state_by_latest_block_hash() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(2).unwrap();
+        let mut grpc_client = test_manager.build_grpc_client().await;
+
+        let latest_block = grpc_client
+            .get_latest_block(ChainSpec {})
+            .await
+            .unwrap()
+            .into_inner();
+        let tree_state = grpc_client
+            .get_tree_state(BlockId {
+                height: 0,
+                hash: latest_block.hash.clone(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        println!("[TEST LOG] Tree state at latest block:\n{:#?}.", tree_state);
+        assert_eq!(tree_state.height, latest_block.height);
+        assert_eq!(tree_state.hash, hex::encode(&latest_block.hash));
+
+        let tree_state_by_height = grpc_client
+            .get_tree_state(BlockId {
+                height: latest_block.height,
+                hash: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(tree_state_by_height.hash, tree_state.hash);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn get_tree_state_before_orchard_activation() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .nu5_activation_height(10)
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(5).unwrap();
+        let mut grpc_client = test_manager.build_grpc_client().await;
+
+        let tree_state = grpc_client
+            .get_tree_state(BlockId {
+                height: 5,
+                hash: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        println!("[TEST LOG] Tree state before NU5:\n{:#?}.", tree_state);
+
+        let node_tree_state = JsonRpcConnector::new(
+            test_manager.test_and_return_zebrad_uri().await,
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await
+        .get_treestate("5".to_string())
+        .await
+        .unwrap();
+        assert_eq!(tree_state.height, 5);
+        assert_eq!(
+            tree_state.sapling_tree,
+            node_tree_state.sapling.commitments.final_state
+        );
+        assert_eq!(tree_state.orchard_tree, CommitmentTreestate::EMPTY_TREE_HEX);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod chain_start {
+    use super::*;
+    use zaino_fetch::jsonrpc::connector::JsonRpcConnector;
+    use zaino_proto::proto::service::{BlockId, BlockRange, ChainSpec};
+
+    /// Pins the RPCs serving the chain tip on a just-launched regtest chain, whose tip is at height 1.
+    #[tokio::test]
+    async fn tip_rpcs_at_height_one() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        let mut grpc_client = test_manager.build_grpc_client().await;
+        let tip = JsonRpcConnector::new(
+            test_manager.test_and_return_zebrad_uri().await,
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await
+        .get_blockchain_info()
+        .await
+        .unwrap()
+        .blocks
+        .0;
+        assert_eq!(tip, 1);
+
+        let latest = grpc_client
+            .get_latest_block(ChainSpec {})
+            .await
+            .unwrap()
+            .into_inner();
+        println!("[TEST LOG] Latest block: {:?}.", latest);
+        assert_eq!(latest.height, 1);
+        assert_eq!(latest.hash.len(), 32);
+
+        let tree_state = grpc_client
+            .get_tree_state(BlockId {
+                height: 1,
+                hash: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        println!("[TEST LOG] Tree state at the tip:\n{:#?}.", tree_state);
+        assert_eq!(tree_state.height, 1);
+        assert_eq!(tree_state.hash, hex::encode(&latest.hash));
+        // Trees with no commitments are still returned, as their empty encoding.
+        for tree in [&tree_state.sapling_tree, &tree_state.orchard_tree] {
+            assert!(!tree.is_empty());
+            assert!(tree.chars().all(|c| c.is_ascii_hexdigit()));
+        }
+
+        let block_id = BlockId {
+            height: 1,
+            hash: Vec::new(),
+        };
+        let mut stream = grpc_client
+            .get_block_range(BlockRange {
+                start: Some(block_id.clone()),
+                end: Some(block_id),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut blocks = Vec::new();
+        while let Some(block) = stream.message().await.unwrap() {
+            blocks.push(block);
+        }
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].height, 1);
+        assert_eq!(
+            blocks[0].hash.iter().rev().copied().collect::<Vec<u8>>(),
+            latest.hash
+        );
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod node_rpc {
+    use super::*;
+    use zaino_fetch::chain::cache::BlockCache;
+    use zaino_fetch::jsonrpc::{
+        connector::{JsonRpcConnector, NodeType, NodeTypeSelection},
+        normalizer::NodeResponseNormalizer,
+        response::{GetBlockResponse, GetInfoResponse, GetTransactionResponse},
+    };
+    use zaino_fetch::primitives::{
+        chain::{NetworkUpgrade, NetworkUpgradeStatus},
+        height::ChainHeight,
+    };
+    use zaino_testutils::{MockNode, MockResponse};
+
+    #[test]
+    fn detect_node_type_from_subversion() {
+        let zcashd_info = GetInfoResponse {
+            build: "v5.9.0".to_string(),
+            subversion: "/MagicBean:5.9.0/".to_string(),
+        };
+        let zebrad_info = GetInfoResponse {
+            build: "v1.8.0".to_string(),
+            subversion: "/Zebra:1.8.0/".to_string(),
+        };
+        assert_eq!(NodeType::detect(&zcashd_info), NodeType::Zcashd);
+        assert_eq!(NodeType::detect(&zebrad_info), NodeType::Zebrad);
+    }
+
+    #[test]
+    fn parse_node_type() {
+        for (name, node_type) in [
+            ("zcashd", NodeType::Zcashd),
+            ("Zcash", NodeType::Zcashd),
+            ("zebrad", NodeType::Zebrad),
+            (" ZEBRA ", NodeType::Zebrad),
+        ] {
+            assert_eq!(name.parse::<NodeType>().unwrap(), node_type);
+        }
+        for node_type in [NodeType::Zcashd, NodeType::Zebrad] {
+            assert_eq!(
+                node_type.to_string().parse::<NodeType>().unwrap(),
+                node_type
+            );
+        }
+        assert!("lightwalletd".parse::<NodeType>().is_err());
+    }
+
+    /// Returns a connector to a mock node reporting the given `getinfo` user-agent, and the mock node.
+    async fn connector_for_subversion(subversion: &str) -> (JsonRpcConnector, MockNode) {
+        let node = MockNode::spawn().await;
+        node.respond(
+            "getinfo",
+            MockResponse::Result(serde_json::json!({
+                "build": "v0.0.0",
+                "subversion": subversion,
+            })),
+        );
+        let connector = JsonRpcConnector::new(node.uri().clone(), NodeAuth::None).await;
+        (connector, node)
+    }
+
+    #[tokio::test]
+    async fn auto_node_type_detects_both_backends() {
+        for (subversion, node_type) in [
+            ("/MagicBean:5.9.0/", NodeType::Zcashd),
+            ("/Zebra:1.8.0/", NodeType::Zebrad),
+        ] {
+            let (connector, node) = connector_for_subversion(subversion).await;
+            assert_eq!(
+                NodeTypeSelection::Auto.resolve(&connector).await.unwrap(),
+                node_type
+            );
+            assert_eq!(node.call_count("getinfo"), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn explicit_node_type_skips_detection() {
+        let (connector, node) = connector_for_subversion("/Zebra:1.8.0/").await;
+        assert_eq!(
+            NodeTypeSelection::Zcashd.resolve(&connector).await.unwrap(),
+            NodeType::Zcashd
+        );
+        assert_eq!(
+            NodeTypeSelection::Zebrad.resolve(&connector).await.unwrap(),
+            NodeType::Zebrad
+        );
+        assert_eq!(node.call_count("getinfo"), 0);
+    }
+
+    #[test]
+    fn node_type_selection_defaults_to_auto() {
+        assert_eq!(NodeTypeSelection::default(), NodeTypeSelection::Auto);
+        assert_eq!(NodeTypeSelection::Auto.explicit(), None);
+        assert_eq!(NodeTypeSelection::Zebrad.explicit(), Some(NodeType::Zebrad));
+    }
+
+    #[tokio::test]
+    async fn consensus_branch_id_matches_active_upgrade() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+
+        // All network upgrades are active from height 1 on regtest.
+        assert_eq!(
+            test_manager.get_consensus_branch_id().await,
+            NetworkUpgrade::Nu5.branch_id().unwrap()
+        );
+        test_manager.assert_consensus_branch_id().await;
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn network_upgrades_match_regtest_schedule() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .nu5_activation_height(5)
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(2).unwrap();
+        let zebrad_client = JsonRpcConnector::new(
+            test_manager.test_and_return_zebrad_uri().await,
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await;
+
+        let upgrades = zebrad_client
+            .get_blockchain_info()
+            .await
+            .unwrap()
+            .network_upgrades();
+        println!("[TEST LOG] Network upgrades: {:?}.", upgrades);
+        assert!(upgrades
+            .windows(2)
+            .all(|pair| pair[0].activation_height <= pair[1].activation_height));
+        for (name, activation_height, status) in [
+            (NetworkUpgrade::Overwinter, 1, NetworkUpgradeStatus::Active),
+            (NetworkUpgrade::Sapling, 1, NetworkUpgradeStatus::Active),
+            (NetworkUpgrade::Blossom, 1, NetworkUpgradeStatus::Active),
+            (NetworkUpgrade::Heartwood, 1, NetworkUpgradeStatus::Active),
+            (NetworkUpgrade::Canopy, 1, NetworkUpgradeStatus::Active),
+            (NetworkUpgrade::Nu5, 5, NetworkUpgradeStatus::Pending),
+        ] {
+            let upgrade = upgrades
+                .iter()
+                .find(|upgrade| upgrade.name == name)
+                .unwrap_or_else(|| panic!("{} missing from the activation schedule", name));
+            assert_eq!(upgrade.activation_height, ChainHeight(activation_height));
+            assert_eq!(upgrade.status, status);
+            assert_eq!(Some(upgrade.branch_id), name.branch_id());
+        }
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_manager_reports_node_type_and_version() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+
+        assert_eq!(test_manager.node_type().await, NodeType::Zcashd);
+        assert!(!test_manager.node_version().await.is_empty());
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn network_solution_rate_is_non_negative() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(5).unwrap();
+        let zebrad_client = JsonRpcConnector::new(
+            test_manager.test_and_return_zebrad_uri().await,
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await;
+
+        let mining_info = zebrad_client.get_mining_info().await.unwrap();
+        println!("[TEST LOG] Mining info: {:?}.", mining_info);
+        assert!(mining_info.blocks.0 >= 5);
+        assert_eq!(mining_info.chain, "regtest");
+        assert!(mining_info.network_sol_ps.is_finite() && mining_info.network_sol_ps >= 0.0);
+        assert!(mining_info.network_hash_ps.is_finite() && mining_info.network_hash_ps >= 0.0);
+        if let Some(difficulty) = mining_info.difficulty {
+            assert!(difficulty.is_finite() && difficulty >= 0.0);
+        }
+        for (blocks, height) in [(None, None), (Some(-1), None), (Some(3), Some(4))] {
+            let sol_ps = zebrad_client
+                .get_network_sol_ps(blocks, height)
+                .await
+                .unwrap();
+            println!(
+                "[TEST LOG] Network solution rate over {:?} blocks at {:?}: {}.",
+                blocks, height, sol_ps
+            );
+            assert!(sol_ps.is_finite() && sol_ps >= 0.0);
+        }
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    /// Normalizes a response from the given node type and deserializes it.
+    fn normalize<R: serde::de::DeserializeOwned>(
+        node_type: NodeType,
+        method: &str,
+        result: serde_json::Value,
+    ) -> R {
+        serde_json::from_value(NodeResponseNormalizer::new(node_type).normalize(method, result))
+            .unwrap()
+    }
+
+    #[test]
+    fn normalized_get_block_matches_across_nodes() {
+        let hash = "0000000000000000000000000000000000000000000000000000000000000001";
+        let zcashd_block = serde_json::json!({
+            "hash": hash,
+            "confirmations": 1,
+            "height": 1,
+            "time": 1_700_000_000,
+            "tx": [],
+            "trees": { "sapling": { "size": 0 }, "orchard": { "size": 0 } },
+        });
+        // Zebrad omits the tree sizes of pools that are not yet active.
+        let zebrad_block = serde_json::json!({
+            "hash": hash,
+            "confirmations": 1,
+            "height": 1,
+            "time": 1_700_000_000,
+            "tx": [],
+            "trees": {},
+        });
+        assert_eq!(
+            normalize::<GetBlockResponse>(NodeType::Zcashd, "getblock", zcashd_block),
+            normalize::<GetBlockResponse>(NodeType::Zebrad, "getblock", zebrad_block),
+        );
+    }
+
+    #[test]
+    fn normalized_mempool_transaction_matches_across_nodes() {
+        let zcashd_transaction = serde_json::json!({
+            "hex": "00",
+            "txid": "0000000000000000000000000000000000000000000000000000000000000001",
+        });
+        let zebrad_transaction = serde_json::json!({
+            "hex": "00",
+            "height": -1,
+            "confirmations": 0,
+        });
+        let zcashd_transaction: GetTransactionResponse =
+            normalize(NodeType::Zcashd, "getrawtransaction", zcashd_transaction);
+        let zebrad_transaction: GetTransactionResponse =
+            normalize(NodeType::Zebrad, "getrawtransaction", zebrad_transaction);
+        assert_eq!(zcashd_transaction, zebrad_transaction);
+        assert!(matches!(
+            zcashd_transaction,
+            GetTransactionResponse::Object {
+                height: -1,
+                confirmations: 0,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn normalized_responses_match_for_each_node_type() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(2).unwrap();
+
+        let mut responses = Vec::new();
+        for node_type in [NodeType::Zcashd, NodeType::Zebrad] {
+            let zebrad_client = JsonRpcConnector::new(
+                test_manager.test_and_return_zebrad_uri().await,
+                NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+            )
+            .await
+            .with_node_type(node_type);
+            responses.push((
+                zebrad_client.get_blockchain_info().await.unwrap(),
+                zebrad_client
+                    .get_block("1".to_string(), Some(1))
+                    .await
+                    .unwrap(),
+            ));
+        }
+        assert_eq!(responses[0], responses[1]);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn get_address_balance_multi() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) =
+            TestManager::launch(online.clone()).await;
+        let zingo_client = test_manager.build_lightclient().await;
+        let taddr = get_zingo_address(&zingo_client, "transparent").await;
+
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+        zingo_client.do_sync(false).await.unwrap();
+        zingo_client
+            .do_send(vec![(&taddr, 250_000, None)])
+            .await
+            .unwrap();
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+
+        let zebrad_client = JsonRpcConnector::new(
+            test_manager.test_and_return_zebrad_uri().await,
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await
+        .detect_node_type()
+        .await
+        .unwrap();
+        assert_eq!(zebrad_client.node_type(), NodeType::Zcashd);
+
+        // Both balance paths are checked against the same node.
+        for node_type in [NodeType::Zcashd, NodeType::Zebrad] {
+            let zebrad_client = JsonRpcConnector::new(
+                test_manager.test_and_return_zebrad_uri().await,
+                NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+            )
+            .await
+            .with_node_type(node_type);
+            let balance = zebrad_client
+                .get_address_balance_multi(vec![taddr.clone()])
+                .await
+                .unwrap();
+            assert_eq!(balance, 250_000);
+        }
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn get_block_hash_matches_compact_block() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(5).unwrap();
+        let zebrad_uri = test_manager.test_and_return_zebrad_uri().await;
+        let node_auth = test_manager.node_auth();
+        let zebrad_client = JsonRpcConnector::new(zebrad_uri.clone(), node_auth.clone()).await;
+
+        let block_cache = BlockCache::default();
+        for height in [1, 3, 5] {
+            let hash = zebrad_client.get_block_hash(height).await.unwrap();
+            let compact_block = block_cache
+                .get_block(&zebrad_uri, &node_auth, height)
+                .await
+                .unwrap();
+            assert_eq!(compact_block.hash, hash.to_vec());
+            assert_eq!(
+                block_cache
+                    .get_block_hash(&zebrad_uri, &node_auth, height)
+                    .await
+                    .unwrap(),
+                hash
+            );
+        }
+
+        // The node returns hashes in display order, the reverse of the compact block's byte order.
+        let tip = zebrad_client.get_blockchain_info().await.unwrap().blocks.0;
+        let best_block_hash = zebrad_client.get_best_block_hash().await.unwrap();
+        let mut tip_hash = zebrad_client.get_block_hash(tip).await.unwrap();
+        assert_eq!(
+            BlockCache::default()
+                .get_block_hash(&zebrad_uri, &node_auth, tip)
+                .await
+                .unwrap(),
+            tip_hash
+        );
+        tip_hash.reverse();
+        assert_eq!(best_block_hash.0.bytes_in_display_order(), tip_hash);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod address_validation {
+    use zaino_fetch::primitives::{
+        address::validate_transparent_addresses, chain::NetworkKind, error::AddressError,
+    };
+
+    const MAINNET_P2PKH: &str = "t1Hxw6JqWMnhDK5jRCieg5bFHM2qt7UtQvu";
+    const MAINNET_P2SH: &str = "t3Jex1rKwuh1bQFRrKpKGWDcDVZ8bbQuNrB";
+    const TESTNET_P2PKH: &str = "tm9ogR9KukTCiTKvrsSxQwFv2x1vhZTydav";
+    const TESTNET_P2SH: &str = "t26e94XS5n9cxwx1bFZKK3qnrc3MmURMBS5";
+
+    fn addresses(addresses: &[&str]) -> Vec<String> {
+        addresses.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn accepts_addresses_for_configured_network() {
+        assert_eq!(
+            validate_transparent_addresses(
+                &addresses(&[MAINNET_P2PKH, MAINNET_P2SH]),
+                NetworkKind::Mainnet
+            ),
+            Ok(addresses(&[MAINNET_P2PKH, MAINNET_P2SH]))
+        );
+        for network in [NetworkKind::Testnet, NetworkKind::Regtest] {
+            assert_eq!(
+                validate_transparent_addresses(&addresses(&[TESTNET_P2PKH, TESTNET_P2SH]), network),
+                Ok(addresses(&[TESTNET_P2PKH, TESTNET_P2SH]))
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_mixed_network_list() {
+        assert_eq!(
+            validate_transparent_addresses(
+                &addresses(&[MAINNET_P2PKH, TESTNET_P2PKH, MAINNET_P2SH]),
+                NetworkKind::Mainnet
+            ),
+            Err(AddressError::NetworkMismatch {
+                index: 1,
+                address: TESTNET_P2PKH.to_string(),
+                expected: NetworkKind::Mainnet,
+                found: NetworkKind::Testnet,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unparseable_address() {
+        let result = validate_transparent_addresses(
+            &addresses(&[MAINNET_P2SH, "t1NotAnAddress"]),
+            NetworkKind::Mainnet,
+        );
+        assert!(matches!(
+            result,
+            Err(AddressError::InvalidAddress { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn dedupes_addresses() {
+        assert_eq!(
+            validate_transparent_addresses(
+                &addresses(&[TESTNET_P2PKH, TESTNET_P2SH, TESTNET_P2PKH]),
+                NetworkKind::Testnet
+            ),
+            Ok(addresses(&[TESTNET_P2PKH, TESTNET_P2SH]))
+        );
+    }
+
+    #[test]
+    fn rejects_empty_list() {
+        assert_eq!(
+            validate_transparent_addresses(&[], NetworkKind::Mainnet),
+            Err(AddressError::EmptyList)
+        );
+    }
+}
+
+mod block_cache {
+    use std::time::Duration;
+    use zaino_fetch::chain::{
+        cache::{BlockCache, ChainTip, NegativeCache},
+        error::BlockCacheError,
+    };
+    use zaino_fetch::jsonrpc::connector::NodeAuth;
+    use zaino_proto::proto::compact_formats::CompactBlock;
+    use zaino_testutils::{MockNode, MockResponse};
+
+    #[tokio::test]
+    async fn negative_cache_expires_and_is_bounded() {
+        let negative_cache = NegativeCache::new(2, Duration::from_millis(100));
+        negative_cache.insert("transaction:aa".to_string()).await;
+        assert!(negative_cache.contains("transaction:aa").await);
+        assert!(!negative_cache.contains("transaction:bb").await);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(!negative_cache.contains("transaction:aa").await);
+
+        for key in ["treestate:1", "treestate:2", "treestate:3"] {
+            negative_cache.insert(key.to_string()).await;
+        }
+        assert_eq!(negative_cache.len().await, 2);
+        assert!(!negative_cache.contains("treestate:1").await);
+        assert!(negative_cache.contains("treestate:3").await);
+    }
+
+    #[tokio::test]
+    async fn negative_cache_is_cleared_when_tip_advances() {
+        let negative_cache = NegativeCache::default();
+        negative_cache.observe_tip(10).await;
+        negative_cache.insert("transaction:aa".to_string()).await;
+
+        negative_cache.observe_tip(10).await;
+        negative_cache.observe_tip(9).await;
+        assert!(negative_cache.contains("transaction:aa").await);
+
+        negative_cache.observe_tip(11).await;
+        assert!(!negative_cache.contains("transaction:aa").await);
+        assert!(negative_cache.is_empty().await);
+    }
+
+    #[test]
+    fn chain_tip_follows_reorgs_onto_a_shorter_chain() {
+        let chain_tip = ChainTip::default();
+        assert_eq!(chain_tip.get(), None);
+
+        assert!(chain_tip.observe(12, [1; 32]));
+        assert!(!chain_tip.observe(12, [1; 32]));
+        assert_eq!(chain_tip.height(), Some(12));
+
+        // The node switched to a shorter chain, the observed tip is lowered rather than kept at the highest seen.
+        assert!(chain_tip.observe(11, [2; 32]));
+        assert_eq!(chain_tip.get(), Some((11, [2; 32])));
+
+        // A different block at the same height is a new tip.
+        assert!(chain_tip.observe(11, [3; 32]));
+        assert_eq!(chain_tip.height(), Some(11));
+    }
+
+    async fn filled_cache(reorg_safe_depth: u32, tip: u32) -> BlockCache {
+        let block_cache = BlockCache::new(reorg_safe_depth);
+        for height in 1..=tip {
+            block_cache
+                .insert(
+                    height,
+                    CompactBlock {
+                        height: height as u64,
+                        ..Default::default()
+                    },
+                )
+                .await;
+        }
+        block_cache
+    }
+
+    #[tokio::test]
+    async fn gc_evicts_below_given_height() {
+        let block_cache = filled_cache(100, 20).await;
+        assert_eq!(block_cache.len().await, 20);
+
+        assert_eq!(block_cache.gc(Some(16)).await, 15);
+        assert_eq!(block_cache.len().await, 5);
+        assert_eq!(block_cache.tip_height().await, Some(20));
+    }
+
+    #[tokio::test]
+    async fn gc_keeps_reorg_safe_depth_below_tip() {
+        let block_cache = filled_cache(5, 20).await;
+
+        assert_eq!(block_cache.gc(None).await, 14);
+        assert_eq!(block_cache.len().await, 6);
+
+        let block_cache = filled_cache(100, 20).await;
+        assert_eq!(block_cache.gc(None).await, 0);
+        assert_eq!(block_cache.len().await, 20);
+    }
+
+    /// Returns a block cache holding blocks 1 to `tip`, the block at each height hashing to `[height; 32]`.
+    async fn hashed_cache(reorg_safe_depth: u32, tip: u8) -> BlockCache {
+        let block_cache = BlockCache::new(reorg_safe_depth);
+        for height in 1..=tip {
+            block_cache
+                .insert(
+                    u32::from(height),
+                    CompactBlock {
+                        height: u64::from(height),
+                        hash: vec![height; 32],
+                        ..Default::default()
+                    },
+                )
+                .await;
+        }
+        block_cache
+    }
+
+    #[tokio::test]
+    async fn height_of_follows_replaced_and_evicted_blocks() {
+        let block_cache = hashed_cache(100, 10).await;
+        assert_eq!(block_cache.height_of(&[7; 32]).await, Some(7));
+        assert_eq!(block_cache.height_of(&[11; 32]).await, None);
+
+        block_cache
+            .insert(
+                7,
+                CompactBlock {
+                    height: 7,
+                    hash: vec![0xaa; 32],
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert_eq!(block_cache.height_of(&[7; 32]).await, None);
+        assert_eq!(block_cache.height_of(&[0xaa; 32]).await, Some(7));
+
+        block_cache.evict_below(8).await;
+        assert_eq!(block_cache.height_of(&[0xaa; 32]).await, None);
+        assert_eq!(block_cache.height_of(&[8; 32]).await, Some(8));
+
+        block_cache.clear().await;
+        assert_eq!(block_cache.height_of(&[8; 32]).await, None);
+    }
+
+    #[tokio::test]
+    async fn observe_tip_evicts_below_reorg_safe_depth() {
+        let node = MockNode::spawn().await;
+        let block_cache = hashed_cache(5, 20).await;
+
+        let evicted = block_cache
+            .observe_tip(node.uri(), &NodeAuth::None, 20, [20; 32])
+            .await
+            .unwrap();
+        assert_eq!(evicted, 14);
+        assert_eq!(block_cache.len().await, 6);
+        assert_eq!(block_cache.height_of(&[14; 32]).await, None);
+        // The tip matches the highest cached block, the node is not asked for any block hash.
+        assert_eq!(node.call_count("getblockhash"), 0);
+    }
+
+    #[tokio::test]
+    async fn observe_tip_evicts_blocks_orphaned_by_a_reorg() {
+        let node = MockNode::spawn().await;
+        // The node's chain forked after height 16 and is now two blocks shorter than the cached chain.
+        for hash in [0x99, 0x98, 16] {
+            node.respond_once(
+                "getblockhash",
+                MockResponse::Result(serde_json::json!(hex::encode([hash; 32]))),
+            );
+        }
+        let block_cache = hashed_cache(100, 20).await;
+
+        let evicted = block_cache
+            .observe_tip(node.uri(), &NodeAuth::None, 18, [0x99; 32])
+            .await
+            .unwrap();
+        assert_eq!(evicted, 4);
+        assert_eq!(block_cache.tip_height().await, Some(16));
+        assert_eq!(block_cache.height_of(&[18; 32]).await, None);
+        assert_eq!(block_cache.height_of(&[16; 32]).await, Some(16));
+        assert_eq!(node.call_count("getblockhash"), 3);
+    }
+
+    #[tokio::test]
+    async fn insert_with_validation_rejects_empty_blocks_above_genesis() {
+        let block_cache = BlockCache::default();
+        let empty_block = |height: u32| CompactBlock {
+            height: height as u64,
+            ..Default::default()
+        };
+
+        block_cache
+            .insert_with_validation(0, empty_block(0))
+            .await
+            .unwrap();
+        assert!(matches!(
+            block_cache.insert_with_validation(5, empty_block(5)).await,
+            Err(BlockCacheError::EmptyBlock(5))
+        ));
+        block_cache
+            .insert_with_validation(
+                5,
+                CompactBlock {
+                    height: 5,
+                    vtx: vec![Default::default()],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(block_cache.len().await, 2);
+    }
+}
+
+mod block_prefetch {
+    use super::*;
+    use std::time::Duration;
+    use zaino_fetch::chain::cache::BlockCache;
+    use zaino_proto::proto::{
+        compact_formats::CompactBlock,
+        service::{
+            compact_tx_streamer_client::CompactTxStreamerClient,
+            compact_tx_streamer_server::CompactTxStreamerServer, BlockId, BlockRange,
+        },
+    };
+    use zaino_testutils::GrpcClientBuilder;
+
+    #[tokio::test]
+    async fn prefetch_is_bounded_and_skips_cached_blocks() {
+        let block_cache = Arc::new(BlockCache::default().with_prefetch(20, 4));
+        block_cache
+            .insert(
+                2,
+                CompactBlock {
+                    height: 2,
+                    ..Default::default()
+                },
+            )
+            .await;
+        // Nothing listens on the node port, prefetches fail and are dropped.
+        let spawned = block_cache
+            .prefetch(
+                &"http://127.0.0.1:1".parse().unwrap(),
+                &NodeAuth::None,
+                1..=10,
+            )
+            .await;
+        assert_eq!(spawned, 4);
+    }
+
+    #[tokio::test]
+    async fn sequential_block_range_is_served_from_cache() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(100).unwrap();
+        let zebrad_uri = test_manager.test_and_return_zebrad_uri().await;
+        let block_cache = Arc::new(BlockCache::default().with_prefetch(20, 8));
+        let grpc_service = GrpcClientBuilder::new(&zebrad_uri)
+            .block_cache(block_cache.clone())
+            .online(online.clone())
+            .build();
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server = tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(CompactTxStreamerServer::new(grpc_service))
+                .serve(addr),
+        );
+        let mut grpc_client = loop {
+            match CompactTxStreamerClient::connect(format!("http://{}", addr)).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+
+        let mut stream = grpc_client
+            .get_block_range(BlockRange {
+                start: Some(BlockId {
+                    height: 1,
+                    hash: Vec::new(),
+                }),
+                end: Some(BlockId {
+                    height: 100,
+                    hash: Vec::new(),
+                }),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut blocks = 0;
+        while stream.message().await.unwrap().is_some() {
+            blocks += 1;
+        }
+        assert_eq!(blocks, 100);
+
+        let (hits, misses) = (block_cache.hits(), block_cache.misses());
+        println!("[TEST LOG] Cache hits: {}, misses: {}.", hits, misses);
+        assert_eq!(hits + misses, 100);
+        // Only the first blocks of the range are requested before a prefetch of them has started.
+        assert!(hits >= 90);
+
+        server.abort();
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod block_range_nullifiers {
+    use super::*;
+    use futures::StreamExt;
+    use zaino_fetch::chain::cache::BlockCache;
+    use zaino_proto::proto::{
+        compact_formats::CompactBlock,
+        service::{compact_tx_streamer_server::CompactTxStreamer, BlockId, BlockRange},
+    };
+    use zaino_serve::rpc::service::nullifiers_only;
+    use zaino_testutils::{FaultProxy, GrpcClientBuilder};
+
+    fn block_range(start: u64, end: u64) -> tonic::Request<BlockRange> {
+        tonic::Request::new(BlockRange {
+            start: Some(BlockId {
+                height: start,
+                hash: Vec::new(),
+            }),
+            end: Some(BlockId {
+                height: end,
+                hash: Vec::new(),
+            }),
+        })
+    }
+
+    /// Collects the blocks of a block range stream, up to the status ending it.
+    async fn collect_blocks(
+        stream: impl futures::Stream<Item = Result<CompactBlock, tonic::Status>>,
+    ) -> Vec<CompactBlock> {
+        stream
+            .take_while(|block| futures::future::ready(block.is_ok()))
+            .map(Result::unwrap)
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn nullifier_ranges_are_served_from_the_block_range_cache() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(20).unwrap();
+        let zebrad_uri = test_manager.test_and_return_zebrad_uri().await;
+        // Node fetches are counted by the proxy, prefetching is disabled so none are in flight between calls.
+        let proxy = FaultProxy::spawn(&zebrad_uri).await;
+        let grpc_client = GrpcClientBuilder::new(proxy.uri())
+            .block_cache(Arc::new(BlockCache::default().with_prefetch(0, 1)))
+            .online(online.clone())
+            .build();
+
+        let blocks = collect_blocks(
+            grpc_client
+                .get_block_range(block_range(1, 20))
+                .await
+                .unwrap()
+                .into_inner(),
+        )
+        .await;
+        assert_eq!(blocks.len(), 20);
+        let fetches = proxy.call_count("getblock");
+        println!(
+            "[TEST LOG] Node block fetches warming the cache: {}.",
+            fetches
+        );
+        assert!(fetches >= 20);
+
+        let nullifier_blocks = collect_blocks(
+            grpc_client
+                .get_block_range_nullifiers(block_range(1, 20))
+                .await
+                .unwrap()
+                .into_inner(),
+        )
+        .await;
+        assert_eq!(proxy.call_count("getblock"), fetches);
+        assert_eq!(
+            nullifier_blocks,
+            blocks.into_iter().map(nullifiers_only).collect::<Vec<_>>()
+        );
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod request_logging {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Command, Stdio},
+        sync::Mutex,
+        time::Duration,
+    };
+    use zaino_proto::proto::service::{
+        compact_tx_streamer_client::CompactTxStreamerClient,
+        compact_tx_streamer_server::CompactTxStreamerServer, BlockId, BlockRange,
+    };
+    use zaino_serve::{
+        rpc::GrpcClient,
+        server::logging::{Redactable, RequestLogLevel, RequestLogger},
+    };
+    use zaino_testutils::GrpcClientBuilder;
+    use zainodlib::indexer::GRPC_ADDR_KEY;
+
+    #[test]
+    fn redactable_is_only_revealed_at_full() {
+        let address = Redactable::new("tmBsTi2xWTjUdEXnuTceL7fecEQKeWaPDJd".to_string());
+        assert_eq!(address.reveal(RequestLogLevel::Off), None);
+        assert_eq!(address.reveal(RequestLogLevel::Metadata), None);
+        assert_eq!(
+            address.reveal(RequestLogLevel::Full).map(String::as_str),
+            Some("tmBsTi2xWTjUdEXnuTceL7fecEQKeWaPDJd")
+        );
+    }
+
+    /// Serves `grpc_service` logging at `level`, streams blocks 1 to 5 and returns the captured log lines.
+    async fn log_block_range(grpc_service: GrpcClient, level: RequestLogLevel) -> Vec<String> {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink_lines = lines.clone();
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server = tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(RequestLogger::with_sink(
+                    CompactTxStreamerServer::new(grpc_service),
+                    level,
+                    Arc::new(move |line| sink_lines.lock().unwrap().push(line)),
+                ))
+                .serve(addr),
+        );
+        let mut grpc_client = loop {
+            match CompactTxStreamerClient::connect(format!("http://{}", addr)).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+
+        let mut stream = grpc_client
+            .get_block_range(BlockRange {
+                start: Some(BlockId {
+                    height: 1,
+                    hash: Vec::new(),
+                }),
+                end: Some(BlockId {
+                    height: 5,
+                    hash: Vec::new(),
+                }),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        while stream.message().await.unwrap().is_some() {}
+        // The entry is logged once the server has dropped the response body.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        server.abort();
+        let lines = lines.lock().unwrap().clone();
+        println!("[TEST LOG] {:?} request log: {:?}.", level, lines);
+        lines
+    }
+
+    #[tokio::test]
+    async fn sensitive_fields_are_only_logged_at_full() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(5).unwrap();
+        let zebrad_uri = test_manager.test_and_return_zebrad_uri().await;
+        let grpc_service = GrpcClientBuilder::new(&zebrad_uri)
+            .online(online.clone())
+            .build();
+
+        let off = log_block_range(grpc_service.clone(), RequestLogLevel::Off).await;
+        assert!(off.is_empty());
+
+        let metadata = log_block_range(grpc_service.clone(), RequestLogLevel::Metadata).await;
+        assert_eq!(metadata.len(), 1);
+        assert!(metadata[0].contains("rpc=GetBlockRange status=Ok "));
+        assert!(metadata[0].contains("duration_ms="));
+        assert!(!metadata[0].contains("request_bytes=0 "));
+        assert!(!metadata[0].contains("response_bytes=0"));
+        assert!(!metadata[0].contains("start_height="));
+        assert!(!metadata[0].contains("end_height="));
+
+        let full = log_block_range(grpc_service, RequestLogLevel::Full).await;
+        assert_eq!(full.len(), 1);
+        assert!(full[0].contains("rpc=GetBlockRange status=Ok "));
+        assert!(full[0].contains(" start_height=1"));
+        assert!(full[0].contains(" end_height=5"));
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn block_range_heights_are_not_printed_when_off() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(5).unwrap();
+        let config_path = test_manager
+            .temp_conf_dir
+            .path()
+            .join("zindexer_log_off.toml");
+        std::fs::write(
+            &config_path,
+            include_str!("../../zainod/zindexer.toml")
+                .replace("network = \"Testnet\"", "network = \"Regtest\"")
+                .replace("nym_active = true", "nym_active = false")
+                .replace("listen_port = 8137", "listen_port = 0")
+                .replace(
+                    "lightwalletd_port = 9067",
+                    &format!("lightwalletd_port = {}", test_manager.lightwalletd_port),
+                )
+                .replace(
+                    "zebrad_port = 18232",
+                    &format!("zebrad_port = {}", test_manager.zebrad_port),
+                )
+                .replace(
+                    "request_log_level = \"Metadata\"",
+                    "request_log_level = \"Off\"",
+                ),
+        )
+        .unwrap();
+
+        // zainod's stdout is only captured from a subprocess, the test harness captures the output of this one.
+        let mut zainod = Command::new(super::grpc_addr_stdout::zainod_binary())
+            .arg("--config")
+            .arg(&config_path)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (addr_tx, addr_rx) = tokio::sync::oneshot::channel();
+        let stdout = zainod.stdout.take().unwrap();
+        let reader = std::thread::spawn(move || {
+            let mut addr_tx = Some(addr_tx);
+            let mut lines = Vec::new();
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if let Some(addr) = line.strip_prefix(&format!("{}=", GRPC_ADDR_KEY)) {
+                    if let Some(addr_tx) = addr_tx.take() {
+                        addr_tx.send(addr.to_string()).ok();
+                    }
+                }
+                lines.push(line);
+            }
+            lines
+        });
+        let grpc_addr = tokio::time::timeout(Duration::from_secs(60), addr_rx)
+            .await
+            .expect("zainod did not print its gRPC address")
+            .expect("zainod exited without printing its gRPC address");
+
+        let mut grpc_client = loop {
+            match CompactTxStreamerClient::connect(format!("http://{}", grpc_addr)).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        };
+        let mut stream = grpc_client
+            .get_block_range(BlockRange {
+                start: Some(BlockId {
+                    height: 1,
+                    hash: Vec::new(),
+                }),
+                end: Some(BlockId {
+                    height: 5,
+                    hash: Vec::new(),
+                }),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut blocks = 0;
+        while stream.message().await.unwrap().is_some() {
+            blocks += 1;
+        }
+        assert_eq!(blocks, 5);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        zainod.kill().unwrap();
+        zainod.wait().unwrap();
+        let lines = reader.join().unwrap();
+        println!("[TEST LOG] zainod stdout: {:?}.", lines);
+        assert!(lines
+            .iter()
+            .all(|line| !line.to_lowercase().contains("height")));
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod block_fetcher {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+    use zaino_fetch::{
+        chain::{
+            block::get_block_from_node,
+            error::{BlockCacheError, ParseError},
+            fetcher::{stream_block_range, BlockFetcher, BlockSink, CancelSignal, FetchOutcome},
+        },
+        jsonrpc::error::JsonRpcConnectorError,
+    };
+    use zaino_proto::proto::compact_formats::CompactBlock;
+    use zaino_testutils::{MockNode, MockResponse};
+
+    fn compact_block(height: u32) -> CompactBlock {
+        CompactBlock {
+            height: height as u64,
+            ..Default::default()
+        }
+    }
+
+    async fn transient_error() -> BlockCacheError {
+        let elapsed = tokio::time::timeout(Duration::ZERO, std::future::pending::<()>())
+            .await
+            .unwrap_err();
+        BlockCacheError::JsonRpcError(JsonRpcConnectorError::TimeoutError(elapsed))
+    }
+
+    /// Runs a fetch over `heights` and returns the outcome along with the heights received by the sink.
+    async fn run<F, Fut>(
+        fetcher: &BlockFetcher,
+        heights: Vec<u32>,
+        fetch_block: F,
+        online: &AtomicBool,
+    ) -> (FetchOutcome, Vec<Result<u64, String>>)
+    where
+        F: Fn(u32) -> Fut + Clone + Send + 'static,
+        Fut: std::future::Future<Output = Result<CompactBlock, BlockCacheError>> + Send + 'static,
+    {
+        let (sink, mut rx) = tokio::sync::mpsc::channel::<Result<CompactBlock, tonic::Status>>(64);
+        let outcome = fetcher
+            .fetch(
+                heights.into_iter(),
+                fetch_block,
+                &sink,
+                online,
+                &CancelSignal::default(),
+            )
+            .await;
+        drop(sink);
+        let mut received = Vec::new();
+        while let Some(block) = rx.recv().await {
+            received.push(block.map(|b| b.height).map_err(|e| e.message().to_string()));
+        }
+        (outcome, received)
+    }
+
+    #[tokio::test]
+    async fn preserves_order_with_out_of_order_completion() {
+        let fetcher = BlockFetcher::new(4);
+        let heights: Vec<u32> = (1..=12).rev().collect();
+        // Higher blocks take longer, so later requests complete first.
+        let (outcome, received) = run(
+            &fetcher,
+            heights.clone(),
+            |height| async move {
+                tokio::time::sleep(Duration::from_millis(height as u64 * 5)).await;
+                Ok(compact_block(height))
+            },
+            &AtomicBool::new(true),
+        )
+        .await;
+
+        assert_eq!(outcome, FetchOutcome::Completed);
+        assert_eq!(
+            received,
+            heights.iter().map(|h| Ok(*h as u64)).collect::<Vec<_>>()
+        );
+        assert_eq!(fetcher.progress().fetched(), 12);
+        assert_eq!(fetcher.progress().total(), 12);
+    }
+
+    #[tokio::test]
+    async fn stops_at_mid_range_failure() {
+        let fetcher = BlockFetcher::new(4);
+        let (outcome, received) = run(
+            &fetcher,
+            (1..=10).collect(),
+            |height| async move {
+                if height == 5 {
+                    Err(BlockCacheError::ParseError(ParseError::InvalidData(
+                        "bad block".to_string(),
+                    )))
+                } else {
+                    Ok(compact_block(height))
+                }
+            },
+            &AtomicBool::new(true),
+        )
+        .await;
+
+        assert_eq!(outcome, FetchOutcome::Failed(5));
+        assert_eq!(received.len(), 5);
+        assert_eq!(received[..4], [Ok(1), Ok(2), Ok(3), Ok(4)]);
+        assert!(received[4].as_ref().unwrap_err().contains("bad block"));
+        assert_eq!(fetcher.progress().fetched(), 4);
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures() {
+        let fetcher = BlockFetcher::new(2).with_retries(2, Duration::from_millis(1));
+        let attempts = Arc::new(AtomicU32::new(0));
+        let fetch_attempts = attempts.clone();
+        let (outcome, received) = run(
+            &fetcher,
+            vec![1, 2, 3],
+            move |height| {
+                let attempts = fetch_attempts.clone();
+                async move {
+                    if height == 2 && attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                        Err(transient_error().await)
+                    } else {
+                        Ok(compact_block(height))
+                    }
+                }
+            },
+            &AtomicBool::new(true),
+        )
+        .await;
+
+        assert_eq!(outcome, FetchOutcome::Completed);
+        assert_eq!(received, [Ok(1), Ok(2), Ok(3)]);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn stops_when_cancelled() {
+        let fetcher = BlockFetcher::new(2);
+        let online = Arc::new(AtomicBool::new(true));
+        let fetch_online = online.clone();
+        let (outcome, received) = run(
+            &fetcher,
+            (1..=10).collect(),
+            move |height| {
+                let online = fetch_online.clone();
+                async move {
+                    if height == 4 {
+                        online.store(false, Ordering::SeqCst);
+                    }
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    Ok(compact_block(height))
+                }
+            },
+            &online,
+        )
+        .await;
+
+        assert_eq!(outcome, FetchOutcome::Cancelled);
+        assert!(received.len() < 10);
+        assert_eq!(fetcher.progress().fetched(), received.len());
+    }
+
+    #[tokio::test]
+    async fn cancel_signal_aborts_in_flight_fetches() {
+        let cancel = CancelSignal::default();
+        let online = AtomicBool::new(true);
+        let started = Arc::new(AtomicU32::new(0));
+        let fetch_started = started.clone();
+        let (sink, _rx) = tokio::sync::mpsc::channel::<Result<CompactBlock, tonic::Status>>(64);
+        tokio::spawn({
+            let cancel = cancel.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                cancel.cancel();
+            }
+        });
+
+        // The block at height 3 never arrives, only the request's cancel signal ends the run.
+        let outcome = tokio::time::timeout(
+            Duration::from_secs(5),
+            BlockFetcher::new(2).fetch(
+                1..=10,
+                move |height| {
+                    let started = fetch_started.clone();
+                    async move {
+                        started.fetch_add(1, Ordering::SeqCst);
+                        if height == 3 {
+                            std::future::pending::<()>().await;
+                        }
+                        Ok(compact_block(height))
+                    }
+                },
+                &sink,
+                &online,
+                &cancel,
+            ),
+        )
+        .await
+        .expect("cancelled run did not stop");
+
+        assert_eq!(outcome, FetchOutcome::Cancelled);
+        assert!(cancel.is_cancelled());
+        assert!(online.load(Ordering::SeqCst));
+        // Heights are drawn lazily, no more than the concurrency bound ahead of the stalled block were started.
+        assert!(started.load(Ordering::SeqCst) <= 4);
+    }
+
+    /// Sink collecting the heights it receives, closing after `capacity` blocks.
+    struct TestSink {
+        received: std::sync::Mutex<Vec<Result<u64, String>>>,
+        capacity: usize,
+    }
+
+    impl TestSink {
+        fn new(capacity: usize) -> Self {
+            TestSink {
+                received: std::sync::Mutex::new(Vec::new()),
+                capacity,
+            }
+        }
+
+        fn received(&self) -> Vec<Result<u64, String>> {
+            self.received.lock().unwrap().clone()
+        }
+    }
+
+    impl BlockSink<BlockCacheError> for TestSink {
+        fn send_block(
+            &self,
+            block: Result<CompactBlock, BlockCacheError>,
+        ) -> impl std::future::Future<Output = bool> + Send {
+            let mut received = self.received.lock().unwrap();
+            let accepted = received.len() < self.capacity;
+            if accepted {
+                received.push(block.map(|b| b.height).map_err(|e| e.to_string()));
+            }
+            std::future::ready(accepted)
+        }
+    }
+
+    /// Returns a block at `height` on a chain whose block hashes are derived from `fork`.
+    fn linked_block(height: u32, fork: u8) -> CompactBlock {
+        CompactBlock {
+            height: height as u64,
+            hash: vec![fork, height as u8],
+            prev_hash: vec![fork, height.saturating_sub(1) as u8],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_block_range_into_custom_sink() {
+        let sink = TestSink::new(usize::MAX);
+        let outcome = stream_block_range(
+            |height| async move { Ok(linked_block(height, 0)) },
+            3,
+            8,
+            4,
+            &sink,
+            &AtomicBool::new(true),
+            &CancelSignal::default(),
+        )
+        .await;
+        assert_eq!(outcome, FetchOutcome::Completed);
+        assert_eq!(sink.received(), (3..=8).map(Ok).collect::<Vec<_>>());
+
+        let sink = TestSink::new(usize::MAX);
+        let outcome = stream_block_range(
+            |height| async move { Ok(linked_block(height, 0)) },
+            8,
+            3,
+            4,
+            &sink,
+            &AtomicBool::new(true),
+            &CancelSignal::default(),
+        )
+        .await;
+        assert_eq!(outcome, FetchOutcome::Completed);
+        assert_eq!(sink.received(), (3..=8).rev().map(Ok).collect::<Vec<_>>());
+
+        let sink = TestSink::new(2);
+        let outcome = stream_block_range(
+            |height| async move { Ok(linked_block(height, 0)) },
+            1,
+            10,
+            4,
+            &sink,
+            &AtomicBool::new(true),
+            &CancelSignal::default(),
+        )
+        .await;
+        assert_eq!(outcome, FetchOutcome::SinkClosed);
+        assert_eq!(sink.received(), [Ok(1), Ok(2)]);
+    }
+
+    #[tokio::test]
+    async fn stream_block_range_detects_reorg() {
+        let sink = TestSink::new(usize::MAX);
+        // Blocks from height 6 come from a different fork than the blocks below them.
+        let outcome = stream_block_range(
+            |height| async move { Ok(linked_block(height, (height >= 6) as u8)) },
+            10,
+            1,
+            4,
+            &sink,
+            &AtomicBool::new(true),
+            &CancelSignal::default(),
+        )
+        .await;
+        assert_eq!(outcome, FetchOutcome::Failed(5));
+        let received = sink.received();
+        assert_eq!(received[..5], [Ok(10), Ok(9), Ok(8), Ok(7), Ok(6)]);
+        assert!(received[5].as_ref().unwrap_err().contains("reorg"));
+    }
+
+    /// Returns the hex of a raw block with an empty header holding `tx_count` transactions, the transactions
+    /// themselves are omitted as a mismatched count is rejected before they are parsed.
+    fn raw_block_hex(tx_count: u8) -> String {
+        // Version, previous block hash, merkle root, final sapling root, time, bits and nonce.
+        let mut block = vec![0u8; 4 + 32 * 3 + 4 + 4 + 32];
+        // Empty equihash solution.
+        block.push(0);
+        block.push(tx_count);
+        hex::encode(block)
+    }
+
+    #[tokio::test]
+    async fn block_with_mismatched_txid_count_is_rejected() {
+        for (txids, tx_count) in [(2, 1), (1, 2), (0, 1)] {
+            let node = MockNode::spawn().await;
+            let tx: Vec<String> = (1..=txids)
+                .map(|i| format!("{:02x}", i).repeat(32))
+                .collect();
+            // The verbose call returns `txids` txids, the raw call a block holding `tx_count` transactions.
+            node.respond_once(
+                "getblock",
+                MockResponse::Result(serde_json::json!({
+                    "hash": "11".repeat(32),
+                    "confirmations": 1,
+                    "height": 1,
+                    "time": 1_700_000_000,
+                    "tx": tx,
+                    "trees": { "sapling": { "size": 0 }, "orchard": { "size": 0 } },
+                })),
+            )
+            .respond_once(
+                "getblock",
+                MockResponse::Result(serde_json::json!(raw_block_hex(tx_count))),
+            );
+
+            let error = get_block_from_node(node.uri(), &NodeAuth::None, &1)
+                .await
+                .unwrap_err();
+            println!(
+                "[TEST LOG] {} txids, {} transactions: {}.",
+                txids, tx_count, error
+            );
+            assert!(
+                matches!(
+                    &error,
+                    BlockCacheError::ParseError(ParseError::InvalidData(message))
+                        if message.contains(&format!(
+                            "number of txids ({}) does not match tx_count ({})",
+                            txids, tx_count
+                        ))
+                ),
+                "{:?}",
+                error
+            );
+            assert_eq!(node.call_count("getblock"), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn block_cache_sink_rejects_out_of_range_heights() {
+        let cache = zaino_fetch::chain::cache::BlockCache::new(10);
+        assert!(cache.send_block(Ok(compact_block(5))).await);
+        let out_of_range = CompactBlock {
+            height: u64::from(u32::MAX) + 1,
+            ..Default::default()
+        };
+        assert!(!cache.send_block(Ok(out_of_range.clone())).await);
+        assert!(matches!(
+            cache.insert_at_block_height(out_of_range).await,
+            Err(BlockCacheError::InvalidHeight(height)) if height == u64::from(u32::MAX) + 1
+        ));
+        assert_eq!(cache.len().await, 1);
+        assert!(cache.contains(5).await);
+    }
+}
+
+mod block_range_stream {
+    use futures::StreamExt;
+    use zaino_proto::proto::compact_formats::CompactBlock;
+    use zaino_serve::rpc::service::{BlockRangeStream, CompactBlockStream};
+
+    fn compact_block(height: u64) -> CompactBlock {
+        CompactBlock {
+            height,
+            hash: vec![height as u8; 32],
+            ..Default::default()
+        }
+    }
+
+    /// Returns a BlockRangeStream yielding `items`.
+    async fn block_range_stream(
+        items: Vec<Result<CompactBlock, tonic::Status>>,
+    ) -> BlockRangeStream {
+        let (tx, rx) = tokio::sync::mpsc::channel(items.len().max(1));
+        for item in items {
+            tx.send(item).await.unwrap();
+        }
+        BlockRangeStream::new(CompactBlockStream::new(rx))
+    }
+
+    #[tokio::test]
+    async fn blocks_are_paired_with_their_heights() {
+        let items: Vec<_> = block_range_stream(vec![
+            Ok(compact_block(12)),
+            Ok(compact_block(11)),
+            Err(tonic::Status::not_found("Block not found")),
+            Ok(compact_block(10)),
+        ])
+        .await
+        .collect()
+        .await;
+        assert_eq!(items.len(), 4);
+        for (item, height) in items
+            .iter()
+            .filter_map(|item| item.as_ref().ok())
+            .zip([12, 11, 10])
+        {
+            assert_eq!(item, &(height, compact_block(height as u64)));
+        }
+        assert_eq!(items[2].as_ref().unwrap_err().code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn out_of_range_heights_are_errors() {
+        let items: Vec<_> = block_range_stream(vec![Ok(compact_block(u64::from(u32::MAX) + 1))])
+            .await
+            .collect()
+            .await;
+        assert_eq!(items[0].as_ref().unwrap_err().code(), tonic::Code::Internal);
+    }
+
+    #[tokio::test]
+    async fn stripped_stream_yields_the_blocks() {
+        let blocks: Vec<_> = block_range_stream(vec![Ok(compact_block(2)), Ok(compact_block(1))])
+            .await
+            .into_blocks()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(blocks, [compact_block(2), compact_block(1)]);
+    }
+}
+
+mod response_scheduler {
+    use zaino_serve::server::{error::QueueError, scheduler::ResponseScheduler};
+
+    #[test]
+    fn interleaves_responses_across_clients() {
+        let mut scheduler = ResponseScheduler::new(1024);
+        for chunk in 0..4u8 {
+            scheduler.push("large", vec![chunk; 100]).unwrap();
+        }
+        scheduler.push("small_1", vec![1]).unwrap();
+        scheduler.push("small_2", vec![2]).unwrap();
+        assert_eq!(scheduler.len(), 6);
+
+        let order: Vec<&str> = std::iter::from_fn(|| scheduler.pop())
+            .map(|(_, client)| client)
+            .collect();
+        assert_eq!(
+            order,
+            ["large", "small_1", "small_2", "large", "large", "large"]
+        );
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn preserves_order_per_client() {
+        let mut scheduler = ResponseScheduler::new(1024);
+        for response in 0..3u8 {
+            scheduler.push("a", vec![response]).unwrap();
+            scheduler.push("b", vec![response + 10]).unwrap();
+        }
+        let responses: Vec<(Vec<u8>, &str)> = std::iter::from_fn(|| scheduler.pop()).collect();
+        let a: Vec<u8> = responses
+            .iter()
+            .filter(|(_, client)| *client == "a")
+            .map(|(response, _)| response[0])
+            .collect();
+        assert_eq!(a, [0, 1, 2]);
+    }
+
+    #[test]
+    fn drops_responses_over_client_cap() {
+        let mut scheduler = ResponseScheduler::new(250);
+        scheduler.push("large", vec![0; 100]).unwrap();
+        scheduler.push("large", vec![0; 100]).unwrap();
+        assert!(matches!(
+            scheduler.push("large", vec![0; 100]),
+            Err(QueueError::QueueFull(response)) if response.len() == 100
+        ));
+        assert_eq!(scheduler.queued_bytes(&"large"), 200);
+
+        // Other clients are unaffected by the capped client.
+        scheduler.push("small", vec![0; 100]).unwrap();
+        assert!(scheduler.push("oversized", vec![0; 300]).is_err());
+        assert_eq!(scheduler.queued_bytes(&"oversized"), 0);
+
+        // Dispatching a response frees space under the cap.
+        assert_eq!(scheduler.pop().unwrap().1, "large");
+        scheduler.push("large", vec![0; 100]).unwrap();
+        assert_eq!(scheduler.len(), 3);
+    }
+}
+
+mod metrics {
+    use super::*;
+    use zaino_fetch::{jsonrpc::response::GetMiningInfoResponse, primitives::height::ChainHeight};
+    use zaino_proto::proto::service::Empty;
+    use zaino_serve::server::metrics::{
+        BackpressureLevel, QueueBackpressure, RpcMetrics, DEFAULT_QUEUE_ERROR_THRESHOLD,
+        DEFAULT_QUEUE_WARN_THRESHOLD,
+    };
+
+    /// Returns the value of the metric with the given name and labels.
+    fn metric_value(metrics: &str, metric: &str) -> Option<f64> {
+        metrics.lines().find_map(|line| {
+            line.strip_prefix(metric)
+                .and_then(|value| value.trim().parse().ok())
+        })
+    }
+
+    #[tokio::test]
+    async fn get_lightd_info_is_recorded() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        let mut grpc_client = test_manager.build_grpc_client().await;
+        grpc_client.get_lightd_info(Empty {}).await.unwrap();
+
+        let metrics = test_manager.get_metrics().await;
+        println!("[TEST LOG] Metrics:\n{}", metrics);
+        let total = metric_value(
+            &metrics,
+            "zaino_rpc_duration_seconds_count{method=\"GetLightdInfo\",outcome=\"success\"}",
+        )
+        .unwrap();
+        assert!(total >= 1.0);
+        let node = metric_value(
+            &metrics,
+            "zaino_rpc_node_duration_seconds_count{method=\"GetLightdInfo\"}",
+        )
+        .unwrap();
+        assert!(node >= 1.0);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn get_lightd_info_requests_are_counted() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        let mut grpc_client = test_manager.build_grpc_client().await;
+        for _ in 0..10 {
+            grpc_client.get_lightd_info(Empty {}).await.unwrap();
+        }
+
+        let metrics = test_manager.get_metrics().await;
+        assert_eq!(
+            metric_value(
+                &metrics,
+                "zaino_rpc_requests_total{method=\"GetLightdInfo\"}"
+            ),
+            Some(10.0)
+        );
+        assert_eq!(
+            metric_value(&metrics, "zaino_rpc_errors_total{method=\"GetLightdInfo\"}"),
+            Some(0.0)
+        );
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn queue_latency_is_recorded() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        let mut handles = Vec::new();
+        for _ in 0..20 {
+            let mut grpc_client = test_manager.build_grpc_client().await;
+            handles.push(tokio::spawn(async move {
+                grpc_client.get_lightd_info(Empty {}).await.unwrap();
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let metrics = test_manager.get_metrics().await;
+        let wait_count = metric_value(&metrics, "zaino_queue_wait_seconds_count").unwrap();
+        let wait_sum = metric_value(&metrics, "zaino_queue_wait_seconds_sum").unwrap();
+        let first_response_count =
+            metric_value(&metrics, "zaino_queue_first_response_seconds_count").unwrap();
+        assert!(wait_count >= 20.0);
+        assert!(first_response_count >= 20.0);
+        // The pool is idle apart from the test's requests, these should be picked up almost immediately.
+        assert!(wait_sum / wait_count < 0.1);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[test]
+    fn mining_info_is_rendered() {
+        let rpc_metrics = RpcMetrics::new();
+        assert!(!rpc_metrics.render().contains("zaino_node_network_solps"));
+
+        let mining_info = |difficulty| GetMiningInfoResponse {
+            blocks: ChainHeight(10),
+            difficulty,
+            network_sol_ps: 12.5,
+            network_hash_ps: 12.5,
+            chain: "regtest".to_string(),
+        };
+        rpc_metrics.set_mining_info(mining_info(Some(1.5)));
+        let metrics = rpc_metrics.render();
+        assert_eq!(
+            metric_value(&metrics, "zaino_node_network_solps"),
+            Some(12.5)
+        );
+        assert_eq!(metric_value(&metrics, "zaino_node_difficulty"), Some(1.5));
+
+        // Zebrad does not report the difficulty.
+        rpc_metrics.set_mining_info(mining_info(None));
+        let metrics = rpc_metrics.render();
+        assert_eq!(
+            metric_value(&metrics, "zaino_node_network_solps"),
+            Some(12.5)
+        );
+        assert!(!metrics.contains("zaino_node_difficulty"));
+    }
+
+    #[test]
+    fn queue_backpressure_events_are_debounced_and_counted() {
+        let rpc_metrics = RpcMetrics::new();
+        let mut backpressure =
+            QueueBackpressure::new(DEFAULT_QUEUE_WARN_THRESHOLD, DEFAULT_QUEUE_ERROR_THRESHOLD);
+        let start = std::time::Instant::now();
+        let mut check = |queue_length, elapsed_ms| {
+            let level = backpressure.check(
+                queue_length,
+                100,
+                start + std::time::Duration::from_millis(elapsed_ms),
+            );
+            if let Some(level) = level {
+                rpc_metrics.record_backpressure_event(level);
+            }
+            level
+        };
+
+        // 60% full warns, at most once per second.
+        assert_eq!(check(60, 0), Some(BackpressureLevel::Warn));
+        assert_eq!(check(60, 500), None);
+        assert_eq!(check(60, 1000), Some(BackpressureLevel::Warn));
+        // 80% full raises an error, debounced separately from warnings.
+        assert_eq!(check(80, 1100), Some(BackpressureLevel::Error));
+        assert_eq!(check(80, 1500), None);
+        // A drained queue raises no further events.
+        assert_eq!(check(10, 3000), None);
+        assert_eq!(check(0, 5000), None);
+
+        assert_eq!(rpc_metrics.backpressure_events(BackpressureLevel::Warn), 2);
+        assert_eq!(rpc_metrics.backpressure_events(BackpressureLevel::Error), 1);
+        let metrics = rpc_metrics.render();
+        assert_eq!(
+            metric_value(
+                &metrics,
+                "zaino_queue_backpressure_events_total{level=\"warn\"}"
+            ),
+            Some(2.0)
+        );
+        assert_eq!(
+            metric_value(
+                &metrics,
+                "zaino_queue_backpressure_events_total{level=\"error\"}"
+            ),
+            Some(1.0)
+        );
+    }
+}
+
+mod worker_scaling {
+    use std::time::{Duration, Instant};
+    use zaino_serve::server::{
+        metrics::{
+            BackpressureLevel, QueueBackpressure, DEFAULT_QUEUE_ERROR_THRESHOLD,
+            DEFAULT_QUEUE_WARN_THRESHOLD,
+        },
+        scaling::{scale_action, PoolLoad, ScaleAction},
+    };
+
+    const MAX_QUEUE_LENGTH: usize = 100;
+    /// Interval of the server's serve loop, one trace entry is observed per tick.
+    const TICK: Duration = Duration::from_millis(50);
+
+    /// Runs the scaling policy over a trace of queue lengths, starting from an idle pool, and returns the pool size
+    /// after each tick.
+    fn simulate(trace: &[usize], max_workers: u16, idle_workers: u16) -> Vec<usize> {
+        let mut workers = idle_workers as usize;
+        trace
+            .iter()
+            .map(|&queue_length| {
+                let load = PoolLoad {
+                    queue_length,
+                    max_queue_length: MAX_QUEUE_LENGTH,
+                    workers,
+                    max_workers,
+                    idle_workers,
+                };
+                match scale_action(&load) {
+                    ScaleAction::Grow => workers += 1,
+                    ScaleAction::Shrink => workers -= 1,
+                    ScaleAction::Hold => {}
+                }
+                workers
+            })
+            .collect()
+    }
+
+    #[test]
+    fn burst_grows_then_returns_to_idle() {
+        let trace = [0, 0, 50, 50, 50, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(
+            simulate(&trace, 8, 2),
+            vec![2, 2, 3, 4, 5, 4, 3, 2, 2, 2, 2, 2]
+        );
+    }
+
+    #[test]
+    fn sustained_load_grows_to_max_and_holds() {
+        let trace = [40; 12];
+        assert_eq!(
+            simulate(&trace, 8, 2),
+            vec![3, 4, 5, 6, 7, 8, 8, 8, 8, 8, 8, 8]
+        );
+    }
+
+    #[test]
+    fn sawtooth_load_oscillates_within_bounds() {
+        // The queue fills past a quarter, drains partially, then empties, four times over.
+        let trace: Vec<usize> = [30, 10, 1].repeat(4);
+        let workers = simulate(&trace, 8, 2);
+        assert_eq!(workers, vec![3, 3, 2, 3, 3, 2, 3, 3, 2, 3, 3, 2]);
+        assert!(workers.iter().all(|&w| (2..=8).contains(&w)));
+    }
+
+    #[test]
+    fn queue_below_a_quarter_holds_above_idle() {
+        // Requests trickling in below the growth threshold neither grow nor drain the pool.
+        let trace = [50, 50, 50, 10, 10, 10];
+        assert_eq!(simulate(&trace, 8, 2), vec![3, 4, 5, 5, 5, 5]);
+    }
+
+    #[test]
+    fn pool_above_max_after_resize_shrinks_under_load() {
+        let mut workers = 8;
+        let mut trajectory = Vec::new();
+        for _ in 0..6 {
+            let load = PoolLoad {
+                queue_length: 40,
+                max_queue_length: MAX_QUEUE_LENGTH,
+                workers,
+                max_workers: 4,
+                idle_workers: 2,
+            };
+            match scale_action(&load) {
+                ScaleAction::Grow => workers += 1,
+                ScaleAction::Shrink => workers -= 1,
+                ScaleAction::Hold => {}
+            }
+            trajectory.push(workers);
+        }
+        assert_eq!(trajectory, vec![7, 6, 5, 4, 4, 4]);
+    }
+
+    #[test]
+    fn sawtooth_backpressure_events_are_debounced() {
+        let mut backpressure =
+            QueueBackpressure::new(DEFAULT_QUEUE_WARN_THRESHOLD, DEFAULT_QUEUE_ERROR_THRESHOLD);
+        let start = Instant::now();
+        // Five seconds of ticks with the queue ramping from empty to full every second.
+        let events: Vec<(usize, BackpressureLevel)> = (0..100)
+            .filter_map(|tick| {
+                let queue_length = (tick % 20) * 5;
+                backpressure
+                    .check(queue_length, MAX_QUEUE_LENGTH, start + TICK * tick as u32)
+                    .map(|level| (tick, level))
+            })
+            .collect();
+        println!(
+            "[TEST LOG] Backpressure events (tick, level): {:?}.",
+            events
+        );
+
+        // Each ramp passes both thresholds, each level is raised at most once per second.
+        let count = |level| events.iter().filter(|(_, l)| *l == level).count();
+        assert_eq!(count(BackpressureLevel::Warn), 5);
+        assert_eq!(count(BackpressureLevel::Error), 5);
+        for level in [BackpressureLevel::Warn, BackpressureLevel::Error] {
+            let ticks: Vec<usize> = events
+                .iter()
+                .filter(|(_, l)| *l == level)
+                .map(|(tick, _)| *tick)
+                .collect();
+            assert!(ticks.windows(2).all(|pair| pair[1] - pair[0] >= 20));
+        }
+    }
+}
+
+mod request_age {
+    use super::*;
+    use std::time::Duration;
+    use zaino_proto::proto::service::{ChainSpec, Empty};
+
+    #[tokio::test]
+    async fn requests_queued_past_max_age_are_discarded() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .max_request_age_secs(1)
+            .worker_pool_size(1, 1)
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
+        let temp_conf_path = test_manager.temp_conf_dir.path().to_path_buf();
+        let test_manager = Arc::new(test_manager);
+
+        // The only worker serves the first client's connection until it is closed.
+        let mut client_1 = test_manager.build_grpc_client().await;
+        client_1.get_latest_block(ChainSpec {}).await.unwrap();
+
+        // Further connections wait in the queue, past the maximum request age.
+        let queued: Vec<_> = (0..2)
+            .map(|_| {
+                let test_manager = test_manager.clone();
+                tokio::spawn(async move {
+                    let mut client = test_manager.build_grpc_client().await;
+                    client.get_lightd_info(Empty {}).await
+                })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        drop(client_1);
+
+        for request in queued {
+            let status = request.await.unwrap().unwrap_err();
+            println!("[TEST LOG] Queued request status: {:?}.", status);
+            assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+        }
+
+        // A request that is picked up promptly is served.
+        let mut client_2 = test_manager.build_grpc_client().await;
+        let lightd_info = client_2
+            .get_lightd_info(Empty {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(lightd_info.chain_name, "regtest");
+        drop(client_2);
+
+        assert!(test_manager
+            .get_metrics()
+            .await
+            .contains("zaino_expired_requests_total 2"));
+
+        drop_test_manager(Some(temp_conf_path), regtest_handler, online).await;
+    }
+}
+
+mod overload_policy {
+    use super::*;
+    use std::time::Duration;
+    use zaino_proto::proto::service::{ChainSpec, Empty};
+    use zaino_serve::server::request::OverloadPolicy;
+
+    /// Deadline of each flooding client, covering both connecting and its call.
+    const DEADLINE: Duration = Duration::from_secs(1);
+
+    /// Outcome of a flooding client's call.
+    #[derive(Debug, PartialEq)]
+    enum Outcome {
+        /// Answered within the deadline.
+        Answered,
+        /// Failed with the given status within the deadline, eg. rejected or evicted.
+        Failed(tonic::Code),
+        /// Not answered within the deadline.
+        TimedOut,
+    }
+
+    /// Floods a server with a single worker and a request queue of 4 with a client every 100ms for 3s, while another
+    /// client holds the worker for the first 2s. Returns the outcome of each flooding client and the metrics.
+    async fn flood(overload_policy: OverloadPolicy) -> (Vec<Outcome>, String) {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .worker_pool_size(1, 1)
+            .max_queue_size(4)
+            .overload_policy(overload_policy)
+            .launch(online.clone())
+            .await;
+        let temp_conf_path = test_manager.temp_conf_dir.path().to_path_buf();
+        let test_manager = Arc::new(test_manager);
+
+        // The only worker serves the blocking client's connection until it is closed.
+        let mut blocker = Some(test_manager.build_grpc_client().await);
+        blocker
+            .as_mut()
+            .unwrap()
+            .get_latest_block(ChainSpec {})
+            .await
+            .unwrap();
+
+        let mut clients = Vec::new();
+        for i in 0..30 {
+            if i == 20 {
+                drop(blocker.take());
+            }
+            let test_manager = test_manager.clone();
+            clients.push(tokio::spawn(async move {
+                let call = async {
+                    let mut client = test_manager
+                        .try_build_grpc_client()
+                        .await
+                        .map_err(|_| tonic::Code::Unavailable)?;
+                    client
+                        .get_lightd_info(Empty {})
+                        .await
+                        .map_err(|status| status.code())
+                };
+                match tokio::time::timeout(DEADLINE, call).await {
+                    Ok(Ok(_)) => Outcome::Answered,
+                    Ok(Err(code)) => Outcome::Failed(code),
+                    Err(_) => Outcome::TimedOut,
+                }
+            }));
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let mut outcomes = Vec::new();
+        for client in clients {
+            outcomes.push(client.await.unwrap());
+        }
+        println!("[TEST LOG] {:?} outcomes: {:?}.", overload_policy, outcomes);
+        let metrics = test_manager.get_metrics().await;
+
+        drop_test_manager(Some(temp_conf_path), regtest_handler, online).await;
+        (outcomes, metrics)
+    }
+
+    /// Returns the number of clients answered within their deadline.
+    fn answered(outcomes: &[Outcome]) -> usize {
+        outcomes
+            .iter()
+            .filter(|outcome| **outcome == Outcome::Answered)
+            .count()
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_answers_more_clients_within_deadline() {
+        // Under reject-new the queue holds the first clients, which time out before the worker is freed, while later
+        // clients are rejected. Under drop-oldest the queue holds the most recent clients, which are still waiting.
+        let (reject_new, reject_new_metrics) = flood(OverloadPolicy::RejectNew).await;
+        let (drop_oldest, drop_oldest_metrics) = flood(OverloadPolicy::DropOldest).await;
+        println!(
+            "[TEST LOG] Answered within deadline, reject-new: {}, drop-oldest: {}.",
+            answered(&reject_new),
+            answered(&drop_oldest)
+        );
+        assert!(answered(&drop_oldest) > answered(&reject_new));
+
+        // Evicted clients are answered with UNAVAILABLE and counted.
+        assert!(drop_oldest.contains(&Outcome::Failed(tonic::Code::Unavailable)));
+        assert!(reject_new_metrics.contains("zaino_queue_evictions_total 0"));
+        assert!(!drop_oldest_metrics.contains("zaino_queue_evictions_total 0"));
+    }
+}
+
+mod token_bucket {
+    use std::time::Duration;
+    use zaino_serve::utils::ratelimit::TokenBucket;
+
+    #[test]
+    fn bucket_starts_full() {
+        let bucket = TokenBucket::new(10, 1.0);
+        assert_eq!(bucket.available(), 10);
+        assert!(bucket.try_consume(10));
+        assert_eq!(bucket.available(), 0);
+    }
+
+    #[test]
+    fn single_tokens_are_consumed() {
+        let bucket = TokenBucket::new(3, 0.0);
+        for remaining in (0..3).rev() {
+            assert!(bucket.try_consume(1));
+            assert_eq!(bucket.available(), remaining);
+        }
+        assert!(!bucket.try_consume(1));
+        // Taking no tokens succeeds from an empty bucket.
+        assert!(bucket.try_consume(0));
+    }
+
+    #[test]
+    fn multiple_tokens_are_consumed_together() {
+        let bucket = TokenBucket::new(10, 0.0);
+        assert!(bucket.try_consume(4));
+        assert!(bucket.try_consume(4));
+        // A request for more tokens than are available takes none.
+        assert!(!bucket.try_consume(4));
+        assert_eq!(bucket.available(), 2);
+        assert!(bucket.try_consume(2));
+        assert!(!bucket.try_consume(11));
+    }
+
+    #[test]
+    fn tokens_are_refilled_over_time() {
+        let bucket = TokenBucket::new(5, 100.0);
+        assert!(bucket.try_consume(5));
+        assert!(!bucket.try_consume(1));
+
+        // 100 tokens per second refill a token every 10ms.
+        std::thread::sleep(Duration::from_millis(35));
+        let available = bucket.available();
+        println!("[TEST LOG] {} tokens refilled after 35ms.", available);
+        assert!((3..=5).contains(&available), "{} tokens", available);
+
+        // The bucket does not fill past its capacity.
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(bucket.available(), 5);
+    }
+
+    #[test]
+    fn slow_refills_are_not_rounded_away() {
+        // Polling more often than a token accrues still refills the bucket.
+        let bucket = TokenBucket::new(1, 20.0);
+        assert!(bucket.try_consume(1));
+        let start = std::time::Instant::now();
+        while !bucket.try_consume(1) {
+            assert!(
+                start.elapsed() < Duration::from_secs(1),
+                "Bucket not refilled"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn concurrent_consumers_take_every_token_once() {
+        const THREADS: u64 = 16;
+        const CAPACITY: u64 = 100_000;
+        let bucket = TokenBucket::new(CAPACITY, 0.0);
+        let consumed: u64 = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|thread| {
+                    let bucket = &bucket;
+                    scope.spawn(move || {
+                        // Threads take differing numbers of tokens at a time, then single tokens once those run out.
+                        let n = thread % 4 + 1;
+                        let mut consumed = 0;
+                        while bucket.try_consume(n) {
+                            consumed += n;
+                        }
+                        while bucket.try_consume(1) {
+                            consumed += 1;
+                        }
+                        consumed
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+        assert_eq!(consumed, CAPACITY);
+        assert_eq!(bucket.available(), 0);
+    }
+
+    #[test]
+    fn concurrent_refills_are_credited_once() {
+        const THREADS: u64 = 16;
+        let bucket = TokenBucket::new(1_000, 1_000.0);
+        let start = std::time::Instant::now();
+        let consumed: u64 = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    let bucket = &bucket;
+                    scope.spawn(move || {
+                        let mut consumed = 0;
+                        while start.elapsed() < Duration::from_millis(200) {
+                            if bucket.try_consume(1) {
+                                consumed += 1;
+                            }
+                        }
+                        consumed
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+        let elapsed = start.elapsed().as_secs_f64();
+        println!(
+            "[TEST LOG] {} tokens consumed in {:.3}s.",
+            consumed, elapsed
+        );
+        // The initial burst plus the tokens refilled while the threads ran, never more.
+        assert!(consumed as f64 <= 1_000.0 + elapsed * 1_000.0 + 1.0);
+        assert!(consumed >= 1_000);
+    }
+}
+
+mod rpc_limits {
+    use super::*;
+    use zaino_proto::proto::service::{BlockId, BlockRange, Empty, RawTransaction};
+
+    fn block_range(start: u64, end: u64) -> BlockRange {
+        BlockRange {
+            start: Some(BlockId {
+                height: start,
+                hash: Vec::new(),
+            }),
+            end: Some(BlockId {
+                height: end,
+                hash: Vec::new(),
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit_serializes_and_rejects() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .rpc_concurrency_limit("GetBlockRange", 1)
+            .rpc_max_wait_ms(1000)
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(10).unwrap();
+        let mut client_1 = test_manager.build_grpc_client().await;
+        let mut client_2 = test_manager.build_grpc_client().await;
+        let mut client_3 = test_manager.build_grpc_client().await;
+
+        // The first client holds the only permit until its stream is dropped.
+        let stream_1 = client_1
+            .get_block_range(block_range(1, 10))
+            .await
+            .unwrap()
+            .into_inner();
+
+        // The second client waits past the wait cap and is rejected.
+        let start = std::time::Instant::now();
+        let status = client_2
+            .get_block_range(block_range(1, 10))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert!(start.elapsed() >= std::time::Duration::from_millis(1000));
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+        // The third client is served once the first stream is released.
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            drop(stream_1);
+        });
+        let mut stream_3 = client_3
+            .get_block_range(block_range(1, 10))
+            .await
+            .unwrap()
+            .into_inner();
+        release.await.unwrap();
+        let mut blocks = 0;
+        while stream_3.message().await.unwrap().is_some() {
+            blocks += 1;
+        }
+        assert_eq!(blocks, 10);
+
+        let metrics = test_manager.get_metrics().await;
+        assert!(metrics.contains("zaino_rpc_rejected_total{method=\"GetBlockRange\"} 1"));
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn per_client_stream_limit_rejects_excess_streams() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .max_concurrent_streams_per_client(2)
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(10).unwrap();
+
+        // Clones of a client share its connection, so their streams count against the same limit.
+        let client_1 = test_manager.build_grpc_client().await;
+        let requests: Vec<_> = (0..8)
+            .map(|_| {
+                let mut client = client_1.clone();
+                tokio::spawn(async move {
+                    client
+                        .get_block_range(block_range(1, 10))
+                        .await
+                        .map(|response| response.into_inner())
+                })
+            })
+            .collect();
+        let mut responses = Vec::new();
+        for request in requests {
+            responses.push(request.await.unwrap());
+        }
+        let rejected = responses
+            .iter()
+            .filter(|response| {
+                matches!(response, Err(status) if status.code() == tonic::Code::ResourceExhausted)
+            })
+            .count();
+        println!("[TEST LOG] Streams rejected: {}.", rejected);
+        assert!(rejected >= 1);
+        assert!(responses.iter().filter(|response| response.is_ok()).count() >= 2);
+        assert!(responses.iter().all(|response| match response {
+            Ok(_) => true,
+            Err(status) => status.code() == tonic::Code::ResourceExhausted,
+        }));
+
+        // A second client is served on its own connection while the first client's streams are open.
+        let mut client_2 = test_manager.build_grpc_client().await;
+        for _ in 0..2 {
+            let mut stream = client_2
+                .get_block_range(block_range(1, 10))
+                .await
+                .unwrap()
+                .into_inner();
+            let mut blocks = 0;
+            while stream.message().await.unwrap().is_some() {
+                blocks += 1;
+            }
+            assert_eq!(blocks, 10);
+        }
+        drop(responses);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    /// Opens a mempool stream, which stays open until a block is mined, on a new client connection.
+    async fn open_mempool_stream(
+        test_manager: &TestManager,
+    ) -> Result<tonic::Streaming<RawTransaction>, tonic::Status> {
+        test_manager
+            .build_grpc_client()
+            .await
+            .get_mempool_stream(Empty {})
+            .await
+            .map(|response| response.into_inner())
+    }
+
+    #[tokio::test]
+    async fn global_stream_limit_rejects_excess_streams() {
+        const MAX_STREAMS: usize = 4;
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .max_concurrent_streams_global(MAX_STREAMS as u16)
+            .launch(online.clone())
+            .await;
+
+        // One stream more than the limit is opened at once, each on its own client connection so only the global
+        // limit applies. No block is mined during the test, so accepted streams stay open.
+        let mut clients = Vec::new();
+        for _ in 0..=MAX_STREAMS {
+            clients.push(test_manager.build_grpc_client().await);
+        }
+        let requests: Vec<_> = clients
+            .into_iter()
+            .map(|mut client| {
+                tokio::spawn(async move {
+                    client
+                        .get_mempool_stream(Empty {})
+                        .await
+                        .map(|response| response.into_inner())
+                })
+            })
+            .collect();
+        let mut streams = Vec::new();
+        let mut rejected = Vec::new();
+        for request in requests {
+            match request.await.unwrap() {
+                Ok(stream) => streams.push(stream),
+                Err(status) => rejected.push(status),
+            }
+        }
+        println!(
+            "[TEST LOG] Streams open: {}, rejected: {:?}.",
+            streams.len(),
+            rejected
+        );
+        assert_eq!(streams.len(), MAX_STREAMS);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].code(), tonic::Code::ResourceExhausted);
+
+        // Further streams are rejected while the limit is held.
+        let status = open_mempool_stream(&test_manager).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+        // Disconnecting clients release their streams' slots, once released the limit can be reached again.
+        drop(streams);
+        let start = std::time::Instant::now();
+        let mut streams = Vec::new();
+        while streams.len() < MAX_STREAMS {
+            match open_mempool_stream(&test_manager).await {
+                Ok(stream) => streams.push(stream),
+                Err(status) => {
+                    assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+                    assert!(
+                        start.elapsed() < std::time::Duration::from_secs(10),
+                        "Stream slots not released after clients disconnected."
+                    );
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                }
+            }
+        }
+        let status = open_mempool_stream(&test_manager).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        drop(streams);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod request_size_limits {
+    use super::*;
+    use futures::StreamExt;
+    use std::{
+        convert::Infallible,
+        sync::atomic::{AtomicUsize, Ordering},
+        task::{Context, Poll},
+        time::Duration,
+    };
+    use tonic::{
+        body::BoxBody,
+        codegen::{http, BoxFuture, Service},
+        server::NamedService,
+        transport::Body,
+    };
+    use zaino_fetch::primitives::chain::NetworkKind;
+    use zaino_proto::proto::service::{
+        compact_tx_streamer_client::CompactTxStreamerClient, Address, AddressList, BlockId,
+        Exclude, GetAddressUtxosArg, RawTransaction, TxFilter,
+    };
+    use zaino_serve::server::limits::{
+        RequestSizeLimiter, RequestSizeLimits, DEFAULT_ADDRESS_LIST_LIMIT,
+        DEFAULT_MEMPOOL_EXCLUDE_LIMIT, DEFAULT_REQUEST_LIMIT, DEFAULT_SEND_TRANSACTION_LIMIT,
+        MAX_TRANSACTION_SIZE,
+    };
+    use zainodlib::config::IndexerConfig;
+
+    /// Test-only CompactTxStreamer service that counts the calls reaching it.
+    ///
+    /// Reads the whole request body, returning the status of a body error, or UNIMPLEMENTED once the body is read.
+    #[derive(Clone)]
+    struct CountingHandler(Arc<AtomicUsize>);
+
+    impl Service<http::Request<Body>> for CountingHandler {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                let mut body = request.into_body();
+                while let Some(chunk) = body.next().await {
+                    if let Err(e) = chunk {
+                        return Ok(body_error_status(&e).to_http());
+                    }
+                }
+                Ok(tonic::Status::unimplemented("reached handler").to_http())
+            })
+        }
+    }
+
+    impl NamedService for CountingHandler {
+        const NAME: &'static str = "cash.z.wallet.sdk.rpc.CompactTxStreamer";
+    }
+
+    /// Returns the status held in the source chain of a request body error.
+    fn body_error_status(error: &(dyn std::error::Error + 'static)) -> tonic::Status {
+        let mut source = Some(error);
+        while let Some(error) = source {
+            if let Some(status) = error.downcast_ref::<tonic::Status>() {
+                return status.clone();
+            }
+            source = error.source();
+        }
+        tonic::Status::unknown(error.to_string())
+    }
+
+    /// Returns the length of a bytes or string field whose message, holding only that field, encodes to `encoded_len`.
+    fn field_len(encoded_len: usize) -> usize {
+        let varint_len = |n: usize| (usize::BITS - n.leading_zeros()).max(1).div_ceil(7) as usize;
+        (encoded_len.saturating_sub(6)..encoded_len)
+            .find(|n| 1 + varint_len(*n) + n == encoded_len)
+            .expect("a field length encodes to the given message length")
+    }
+
+    /// Serves a [`CountingHandler`] behind a [`RequestSizeLimiter`] with the default limits, returns a client and the
+    /// handler's call count.
+    async fn serve_limited() -> (
+        CompactTxStreamerClient<tonic::transport::Channel>,
+        Arc<AtomicUsize>,
+        tokio::task::JoinHandle<Result<(), tonic::transport::Error>>,
+    ) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server = tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(RequestSizeLimiter::new(
+                    CountingHandler(calls.clone()),
+                    Arc::new(RequestSizeLimits::default()),
+                ))
+                .serve(addr),
+        );
+        let grpc_client = loop {
+            match CompactTxStreamerClient::connect(format!("http://{}", addr)).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        (grpc_client, calls, server)
+    }
+
+    /// Asserts a request was rejected with the limit of `rpc`, without reaching the handler.
+    fn assert_rejected(
+        result: Result<impl std::fmt::Debug, tonic::Status>,
+        rpc: &str,
+        limit: usize,
+        calls: &AtomicUsize,
+    ) {
+        let status = result.unwrap_err();
+        println!("[TEST LOG] {} status: {:?}.", rpc, status);
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert!(status.message().contains(&format!("{} byte", limit)));
+        assert!(status.message().contains(rpc));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    /// Asserts a request reached the handler.
+    fn assert_handled(result: Result<impl std::fmt::Debug, tonic::Status>, calls: &AtomicUsize) {
+        assert_eq!(result.unwrap_err().code(), tonic::Code::Unimplemented);
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 1);
+    }
+
+    fn raw_transaction(encoded_len: usize) -> RawTransaction {
+        RawTransaction {
+            data: vec![0; field_len(encoded_len)],
+            height: 0,
+        }
+    }
+
+    fn exclude(encoded_len: usize) -> Exclude {
+        Exclude {
+            txid: vec![vec![0; field_len(encoded_len)]],
+        }
+    }
+
+    fn address_list(encoded_len: usize) -> AddressList {
+        AddressList {
+            addresses: vec!["t".repeat(field_len(encoded_len))],
+        }
+    }
+
+    fn address_utxos_arg(encoded_len: usize) -> GetAddressUtxosArg {
+        GetAddressUtxosArg {
+            addresses: vec!["t".repeat(field_len(encoded_len))],
+            start_height: 0,
+            max_entries: 0,
+        }
+    }
+
+    fn address(encoded_len: usize) -> Address {
+        Address {
+            address: "t".repeat(field_len(encoded_len)),
+        }
+    }
+
+    #[test]
+    fn default_limits_and_overrides() {
+        let limits = RequestSizeLimits::default();
+        assert!(limits.limit("SendTransaction") > MAX_TRANSACTION_SIZE);
+        assert_eq!(limits.limit("GetMempoolTx"), DEFAULT_MEMPOOL_EXCLUDE_LIMIT);
+        assert_eq!(
+            limits.limit("GetTaddressBalance"),
+            DEFAULT_ADDRESS_LIST_LIMIT
+        );
+        assert_eq!(limits.limit("GetBlock"), DEFAULT_REQUEST_LIMIT);
+        assert_eq!(limits.max(), DEFAULT_SEND_TRANSACTION_LIMIT);
+
+        let limits = RequestSizeLimits::with_overrides(&std::collections::HashMap::from([
+            ("SendTransaction".to_string(), 1000),
+            ("GetBlock".to_string(), 100),
+        ]));
+        assert_eq!(limits.limit("SendTransaction"), 1000);
+        assert_eq!(limits.limit("GetBlock"), 100);
+        assert_eq!(limits.limit("GetMempoolTx"), DEFAULT_MEMPOOL_EXCLUDE_LIMIT);
+        assert_eq!(limits.max(), DEFAULT_MEMPOOL_EXCLUDE_LIMIT);
+    }
+
+    #[test]
+    fn zero_request_size_limit_is_rejected() {
+        let mut config = IndexerConfig::default_for_network(NetworkKind::Regtest);
+        config
+            .rpc_max_request_bytes
+            .insert("SendTransaction".to_string(), 0);
+        assert!(config.check_config().is_err());
+    }
+
+    #[tokio::test]
+    async fn send_transaction_over_limit_is_rejected_before_handler() {
+        let (mut grpc_client, calls, server) = serve_limited().await;
+        let limit = DEFAULT_SEND_TRANSACTION_LIMIT;
+
+        let result = grpc_client
+            .send_transaction(raw_transaction(limit + 1))
+            .await;
+        assert_rejected(result, "SendTransaction", limit, &calls);
+        let result = grpc_client.send_transaction(raw_transaction(limit)).await;
+        assert_handled(result, &calls);
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn get_mempool_tx_over_limit_is_rejected_before_handler() {
+        let (mut grpc_client, calls, server) = serve_limited().await;
+        let limit = DEFAULT_MEMPOOL_EXCLUDE_LIMIT;
+
+        let result = grpc_client.get_mempool_tx(exclude(limit + 1)).await;
+        assert_rejected(result, "GetMempoolTx", limit, &calls);
+        let result = grpc_client.get_mempool_tx(exclude(limit)).await;
+        assert_handled(result, &calls);
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn address_list_rpcs_over_limit_are_rejected_before_handler() {
+        let (mut grpc_client, calls, server) = serve_limited().await;
+        let limit = DEFAULT_ADDRESS_LIST_LIMIT;
+
+        let result = grpc_client
+            .get_taddress_balance(address_list(limit + 1))
+            .await;
+        assert_rejected(result, "GetTaddressBalance", limit, &calls);
+        let result = grpc_client.get_taddress_balance(address_list(limit)).await;
+        assert_handled(result, &calls);
+
+        let result = grpc_client
+            .get_address_utxos(address_utxos_arg(limit + 1))
+            .await;
+        assert_rejected(result, "GetAddressUtxos", limit, &calls);
+        let result = grpc_client
+            .get_address_utxos(address_utxos_arg(limit))
+            .await;
+        assert_handled(result, &calls);
+
+        let result = grpc_client
+            .get_address_utxos_stream(address_utxos_arg(limit + 1))
+            .await;
+        assert_rejected(result, "GetAddressUtxosStream", limit, &calls);
+        let result = grpc_client
+            .get_address_utxos_stream(address_utxos_arg(limit))
+            .await;
+        assert_handled(result, &calls);
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn default_limited_rpcs_over_limit_are_rejected_before_handler() {
+        let (mut grpc_client, calls, server) = serve_limited().await;
+        let limit = DEFAULT_REQUEST_LIMIT;
+
+        let result = grpc_client
+            .get_block(BlockId {
+                height: 0,
+                hash: vec![0; field_len(limit + 1)],
+            })
+            .await;
+        assert_rejected(result, "GetBlock", limit, &calls);
+        let result = grpc_client
+            .get_transaction(TxFilter {
+                block: None,
+                index: 0,
+                hash: vec![0; field_len(limit + 1)],
+            })
+            .await;
+        assert_rejected(result, "GetTransaction", limit, &calls);
+        let result = grpc_client
+            .get_transaction(TxFilter {
+                block: None,
+                index: 0,
+                hash: vec![0; field_len(limit)],
+            })
+            .await;
+        assert_handled(result, &calls);
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn balance_stream_messages_over_limit_are_rejected() {
+        let (mut grpc_client, calls, server) = serve_limited().await;
+        let limit = DEFAULT_REQUEST_LIMIT;
+
+        // An oversized first message is rejected before the handler runs.
+        let result = grpc_client
+            .get_taddress_balance_stream(futures::stream::iter(vec![address(limit + 1)]))
+            .await;
+        assert_rejected(result, "GetTaddressBalanceStream", limit, &calls);
+
+        // Later oversized messages end the request stream the handler is reading.
+        let status = grpc_client
+            .get_taddress_balance_stream(futures::stream::iter(vec![
+                address(limit),
+                address(limit + 1),
+            ]))
+            .await
+            .unwrap_err();
+        println!("[TEST LOG] GetTaddressBalanceStream status: {:?}.", status);
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn configured_limit_rejects_send_transaction() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .rpc_max_request_bytes("SendTransaction", 1000)
+            .launch(online.clone())
+            .await;
+        let mut grpc_client = test_manager.build_grpc_client().await;
+
+        let status = grpc_client
+            .send_transaction(raw_transaction(1001))
+            .await
+            .unwrap_err();
+        println!("[TEST LOG] SendTransaction status: {:?}.", status);
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert!(status.message().contains("1000 byte"));
+
+        // Requests within the limit reach the handler, which rejects the invalid transaction.
+        let status = grpc_client
+            .send_transaction(raw_transaction(1000))
+            .await
+            .unwrap_err();
+        assert_ne!(status.code(), tonic::Code::ResourceExhausted);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod node_auth {
+    use super::*;
+    use zaino_fetch::primitives::chain::NetworkKind;
+    use zaino_proto::proto::service::{compact_tx_streamer_server::CompactTxStreamer, ChainSpec};
+    use zaino_testutils::{fetch_metrics, GrpcClientBuilder, MockNode};
+    use zainodlib::{
+        config::{IndexerConfig, NodeAuthErrorPolicy},
+        indexer::Indexer,
+    };
+
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    /// Returns an indexer config for the test manager's node with the wrong node password.
+    fn wrong_credentials_config(
+        test_manager: &TestManager,
+        node_auth_error_policy: NodeAuthErrorPolicy,
+    ) -> IndexerConfig {
+        IndexerConfig {
+            network: NetworkKind::Regtest,
+            listen_port: Some(free_port()),
+            nym_active: false,
+            nym_conf_path: None,
+            lightwalletd_port: free_port(),
+            zebrad_port: test_manager.zebrad_port,
+            node_auth: NodeAuth::user_pass("xxxxxx", "wrong_password"),
+            metrics_port: Some(free_port()),
+            node_auth_error_policy,
+            ..IndexerConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn wrong_credentials_fail_fast() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+
+        let config = wrong_credentials_config(&test_manager, NodeAuthErrorPolicy::FailFast);
+        let error = Indexer::start_indexer_service(config, online.clone())
+            .await
+            .unwrap_err();
+        println!("[TEST LOG] Indexer error: {}.", error);
+        assert!(error.to_string().contains("rejected credentials"));
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn wrong_credentials_degrade() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+
+        let config = wrong_credentials_config(&test_manager, NodeAuthErrorPolicy::Degrade);
+        let metrics_port = config.metrics_port.unwrap();
+        let _degraded_handler = Indexer::start_indexer_service(config, online.clone())
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        let metrics = fetch_metrics(metrics_port).await;
+        assert!(metrics.contains("zaino_node_auth_failed 1"));
+        assert!(test_manager
+            .get_metrics()
+            .await
+            .contains("zaino_node_auth_failed 0"));
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn served_rpcs_send_the_configured_credentials() {
+        let node_auth = NodeAuth::user_pass("zaino_user", "hunter2");
+        let node = MockNode::with_responses(serde_json::json!({
+            "getblockchaininfo": { "result": {
+                "chain": "regtest",
+                "blocks": 10,
+                "bestblockhash": "00".repeat(32),
+                "estimatedheight": 10,
+                "upgrades": {},
+                "consensus": { "chaintip": "c2d6d0b4", "nextblock": "c2d6d0b4" },
+            } },
+        }))
+        .await;
+        node.require_auth(&node_auth);
+
+        let block_id = GrpcClientBuilder::new(node.uri())
+            .node_auth(node_auth.clone())
+            .build()
+            .get_latest_block(tonic::Request::new(ChainSpec {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(block_id.height, 10);
+        assert_eq!(
+            node.calls().pop().unwrap().authorization,
+            node_auth.authorization_header().unwrap()
+        );
+
+        let status = GrpcClientBuilder::new(node.uri())
+            .node_auth(NodeAuth::user_pass("zaino_user", "wrong_password"))
+            .build()
+            .get_latest_block(tonic::Request::new(ChainSpec {}))
+            .await
+            .unwrap_err();
+        println!("[TEST LOG] Status: {:?}.", status);
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+}
+
+mod node_health {
+    use super::*;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, Instant},
+    };
+    use zaino_proto::proto::service::{compact_tx_streamer_server::CompactTxStreamer, ChainSpec};
+    use zaino_serve::server::health::{supervise_node, NodeHealth};
+    use zaino_testutils::GrpcClientBuilder;
+
+    /// Polls `condition` until it holds, panicking after 5 seconds.
+    async fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(
+                Instant::now() < deadline,
+                "Timed out waiting for node health."
+            );
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[test]
+    fn consecutive_failures_within_window_mark_node_unavailable() {
+        let health = NodeHealth::new(3, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert!(!health.record_failure(start));
+        assert!(!health.record_failure(start + Duration::from_secs(1)));
+        // A success ends the run of failures.
+        assert!(!health.record_success());
+        assert!(!health.record_failure(start + Duration::from_secs(2)));
+        assert!(!health.record_failure(start + Duration::from_secs(3)));
+        assert!(health.is_available());
+        assert!(health.record_failure(start + Duration::from_secs(4)));
+        assert!(!health.is_available());
+        assert_eq!(
+            health.check_available().unwrap_err().code(),
+            tonic::Code::Unavailable
+        );
+        // Further failures do not report a new transition.
+        assert!(!health.record_failure(start + Duration::from_secs(5)));
+
+        assert!(health.record_success());
+        assert!(health.is_available());
+        assert!(health.check_available().is_ok());
+    }
+
+    #[test]
+    fn failures_outside_window_restart_the_run() {
+        let health = NodeHealth::new(3, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert!(!health.record_failure(start));
+        assert!(!health.record_failure(start + Duration::from_secs(5)));
+        // The run started more than the window ago, this failure starts a new run.
+        assert!(!health.record_failure(start + Duration::from_secs(11)));
+        assert!(!health.record_failure(start + Duration::from_secs(12)));
+        assert!(health.is_available());
+        assert!(health.record_failure(start + Duration::from_secs(13)));
+    }
+
+    #[tokio::test]
+    async fn requests_fail_fast_while_node_down_and_recover() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        let zebrad_uri = test_manager.test_and_return_zebrad_uri().await;
+        let node_health = Arc::new(NodeHealth::new(3, Duration::from_secs(1)));
+        let grpc_client = GrpcClientBuilder::new(&zebrad_uri)
+            .node_health(node_health.clone())
+            .online(online.clone())
+            .build();
+
+        // Mock connector, the node is reachable while `node_up` is set.
+        let node_up = Arc::new(AtomicBool::new(true));
+        let probes = Arc::new(AtomicUsize::new(0));
+        let supervisor_online = Arc::new(AtomicBool::new(true));
+        let supervisor = tokio::spawn(supervise_node(
+            {
+                let node_up = node_up.clone();
+                let probes = probes.clone();
+                move || {
+                    probes.fetch_add(1, Ordering::SeqCst);
+                    let up = node_up.load(Ordering::SeqCst);
+                    async move { up }
+                }
+            },
+            node_health.clone(),
+            Duration::from_millis(20),
+            supervisor_online.clone(),
+        ));
+        grpc_client
+            .get_latest_block(tonic::Request::new(ChainSpec {}))
+            .await
+            .unwrap();
+
+        node_up.store(false, Ordering::SeqCst);
+        wait_for(|| !node_health.is_available()).await;
+        let start = Instant::now();
+        let status = grpc_client
+            .get_latest_block(tonic::Request::new(ChainSpec {}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert!(start.elapsed() < Duration::from_millis(50));
+        // The supervisor keeps probing while the node is down.
+        let probes_while_down = probes.load(Ordering::SeqCst);
+        wait_for(|| probes.load(Ordering::SeqCst) > probes_while_down + 2).await;
+        assert!(!node_health.is_available());
+
+        node_up.store(true, Ordering::SeqCst);
+        wait_for(|| node_health.is_available()).await;
+        grpc_client
+            .get_latest_block(tonic::Request::new(ChainSpec {}))
+            .await
+            .unwrap();
+
+        supervisor_online.store(false, Ordering::SeqCst);
+        supervisor.await.unwrap();
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod fault_injection {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use zaino_proto::proto::service::{compact_tx_streamer_server::CompactTxStreamer, BlockId};
+    use zaino_serve::rpc::GrpcClient;
+    use zaino_testutils::{FaultProxy, GrpcClientBuilder, MockNode, MockResponse};
+
+    /// Latency injected into the node's z_gettreestate responses.
+    const LATENCY: Duration = Duration::from_millis(500);
+
+    /// Returns a mock node serving the tree state of block 1.
+    async fn tree_state_node() -> MockNode {
+        MockNode::with_responses(serde_json::json!({
+            "getblockchaininfo": { "result": {
+                "chain": "regtest",
+                "blocks": 10,
+                "bestblockhash": "00".repeat(32),
+                "estimatedheight": 10,
+                "upgrades": {},
+                "consensus": { "chaintip": "c2d6d0b4", "nextblock": "c2d6d0b4" },
+            } },
+            "z_gettreestate": { "result": {
+                "height": 1,
+                "hash": "11".repeat(32),
+                "time": 1,
+                "sapling": { "commitments": { "finalState": "000000" } },
+                "orchard": { "commitments": { "finalState": "000000" } },
+            } },
+        }))
+        .await
+    }
+
+    /// Returns a gRPC service backed by the node at `node_uri`.
+    fn client(node_uri: &http::Uri) -> GrpcClient {
+        GrpcClientBuilder::new(node_uri).build()
+    }
+
+    fn tree_state_request() -> tonic::Request<BlockId> {
+        tonic::Request::new(BlockId {
+            height: 1,
+            hash: Vec::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn get_tree_state_with_injected_latency() {
+        let node = tree_state_node().await;
+        let proxy = FaultProxy::spawn(node.uri()).await;
+        let grpc_client = client(proxy.uri());
+
+        let start = Instant::now();
+        grpc_client
+            .get_tree_state(tree_state_request())
+            .await
+            .unwrap();
+        let baseline = start.elapsed();
+        println!("[TEST LOG] Tree state without latency: {:?}.", baseline);
+        assert!(baseline < LATENCY);
+
+        proxy.delay("z_gettreestate", LATENCY);
+        // A caller with a deadline shorter than the injected latency gives up first.
+        assert!(tokio::time::timeout(
+            LATENCY / 2,
+            grpc_client.get_tree_state(tree_state_request())
+        )
+        .await
+        .is_err());
+        let start = Instant::now();
+        let tree_state = grpc_client
+            .get_tree_state(tree_state_request())
+            .await
+            .unwrap()
+            .into_inner();
+        println!("[TEST LOG] Tree state with latency: {:?}.", start.elapsed());
+        assert!(start.elapsed() >= LATENCY);
+        assert_eq!(tree_state.height, 1);
+
+        proxy.clear("z_gettreestate");
+        let start = Instant::now();
+        grpc_client
+            .get_tree_state(tree_state_request())
+            .await
+            .unwrap();
+        assert!(start.elapsed() < LATENCY);
+        // Only the call of the tree state is delayed, every call reaches the node.
+        assert_eq!(proxy.call_count("z_gettreestate"), 4);
+        assert_eq!(node.call_count("z_gettreestate"), 4);
+    }
+
+    #[tokio::test]
+    async fn injected_faults_are_returned_in_place_of_the_node() {
+        let node = tree_state_node().await;
+        let proxy = FaultProxy::spawn(node.uri()).await;
+        let grpc_client = client(proxy.uri());
+
+        proxy
+            .fail_once(
+                "z_gettreestate",
+                MockResponse::error(-1, "Injected failure"),
+            )
+            .fail_once("z_gettreestate", MockResponse::Drop);
+        for expected in [tonic::Code::Internal, tonic::Code::Unavailable] {
+            let status = grpc_client
+                .get_tree_state(tree_state_request())
+                .await
+                .unwrap_err();
+            println!("[TEST LOG] Injected fault: {}.", status.message());
+            assert_eq!(status.code(), expected);
+        }
+        // Faulted calls do not reach the node, the next call is forwarded.
+        assert_eq!(node.call_count("z_gettreestate"), 0);
+        grpc_client
+            .get_tree_state(tree_state_request())
+            .await
+            .unwrap();
+        assert_eq!(node.call_count("z_gettreestate"), 1);
+
+        // A proxy to a node that is down drops every call.
+        let proxy = FaultProxy::spawn(&node.kill().await).await;
+        let status = client(proxy.uri())
+            .get_tree_state(tree_state_request())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+    }
+}
+
+mod client_metadata {
+    use super::*;
+    use zaino_fetch::jsonrpc::connector::NODE_REQUEST_HEADERS;
+    use zaino_proto::proto::service::{
+        compact_tx_streamer_server::CompactTxStreamer, BlockId, BlockRange, ChainSpec, Empty,
+        Exclude, GetAddressUtxosArg, RawTransaction, TransparentAddressBlockFilter, TxFilter,
+    };
+    use zaino_testutils::{GrpcClientBuilder, MockNode};
+
+    const ADDRESS: &str = "tm9ogR9KukTCiTKvrsSxQwFv2x1vhZTydav";
+
+    /// Optional client headers set on every request, none of which may reach the node.
+    const CLIENT_METADATA: [(&str, &str); 7] = [
+        ("user-agent", "client-agent/7f3a"),
+        ("x-request-id", "client-request-7f3a"),
+        ("authorization", "Bearer client-token-7f3a"),
+        ("cookie", "session=client-session-7f3a"),
+        ("x-forwarded-for", "203.0.113.7"),
+        ("x-real-ip", "203.0.113.7"),
+        ("x-client-id", "client-id-7f3a"),
+    ];
+
+    /// Returns a request for `message` carrying every header of [`CLIENT_METADATA`].
+    fn client_request<T>(message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        for (name, value) in CLIENT_METADATA {
+            request.metadata_mut().insert(name, value.parse().unwrap());
+        }
+        request
+    }
+
+    /// Drains a streamed response, ignoring errors.
+    async fn drain<S, T>(response: Result<tonic::Response<S>, tonic::Status>)
+    where
+        S: futures::Stream<Item = Result<T, tonic::Status>> + Unpin,
+    {
+        if let Ok(response) = response {
+            let mut stream = response.into_inner();
+            while futures::StreamExt::next(&mut stream).await.is_some() {}
+        }
+    }
+
+    #[tokio::test]
+    async fn client_metadata_is_not_forwarded_to_node() {
+        let node = MockNode::with_responses(serde_json::json!({
+            "getinfo": { "result": { "build": "v0.0.0", "subversion": "/MockNode:0.0.0/" } },
+            "getblockchaininfo": { "result": {
+                "chain": "regtest",
+                "blocks": 10,
+                "bestblockhash": "00".repeat(32),
+                "estimatedheight": 10,
+                "upgrades": {},
+                "consensus": { "chaintip": "c2d6d0b4", "nextblock": "c2d6d0b4" },
+            } },
+            "getaddresstxids": { "result": [] },
+            "getaddressutxos": { "result": [] },
+            "getrawmempool": { "result": [] },
+        }))
+        .await;
+        let client = GrpcClientBuilder::new(node.uri()).build();
+        let block_id = |height| BlockId {
+            height,
+            hash: Vec::new(),
+        };
+
+        client
+            .get_latest_block(client_request(ChainSpec {}))
+            .await
+            .ok();
+        client.get_block(client_request(block_id(5))).await.ok();
+        client
+            .get_transaction(client_request(TxFilter {
+                block: None,
+                index: 0,
+                hash: vec![0x11; 32],
+            }))
+            .await
+            .ok();
+        client
+            .send_transaction(client_request(RawTransaction {
+                data: vec![0x00; 16],
+                height: 0,
+            }))
+            .await
+            .ok();
+        drain(
+            client
+                .get_taddress_txids(client_request(TransparentAddressBlockFilter {
+                    address: ADDRESS.to_string(),
+                    range: Some(BlockRange {
+                        start: Some(block_id(1)),
+                        end: Some(block_id(10)),
+                    }),
+                }))
+                .await,
+        )
+        .await;
+        drain(
+            client
+                .get_address_utxos_stream(client_request(GetAddressUtxosArg {
+                    addresses: vec![ADDRESS.to_string()],
+                    start_height: 0,
+                    max_entries: 0,
+                }))
+                .await,
+        )
+        .await;
+        drain(
+            client
+                .get_mempool_tx(client_request(Exclude { txid: Vec::new() }))
+                .await,
+        )
+        .await;
+        client.get_lightd_info(client_request(Empty {})).await.ok();
+
+        let calls = node.calls();
+        println!(
+            "[TEST LOG] Node calls: {:?}.",
+            calls.iter().map(|call| &call.method).collect::<Vec<_>>()
+        );
+        for method in [
+            "getblockchaininfo",
+            "getaddresstxids",
+            "getaddressutxos",
+            "getrawmempool",
+            "getinfo",
+        ] {
+            assert!(node.call_count(method) > 0, "{} not called", method);
+        }
+        for call in calls {
+            for (name, value) in &call.headers {
+                assert!(
+                    NODE_REQUEST_HEADERS.contains(&name.as_str()),
+                    "{} sent header {}",
+                    call.method,
+                    name
+                );
+                for (_, client_value) in CLIENT_METADATA {
+                    assert!(
+                        !value.contains(client_value),
+                        "{} forwarded client metadata in header {}",
+                        call.method,
+                        name
+                    );
+                }
+            }
+            assert!(call
+                .authorization
+                .as_deref()
+                .map_or(true, |authorization| authorization.starts_with("Basic ")));
+            let params = call.params.to_string();
+            for (_, client_value) in CLIENT_METADATA {
+                assert!(
+                    !params.contains(client_value),
+                    "{} forwarded client metadata in its params",
+                    call.method
+                );
+            }
+        }
+    }
+}
+
+mod lightd_info_chain_name {
+    use super::*;
+    use std::time::Duration;
+    use zaino_fetch::primitives::chain::NetworkKind;
+    use zaino_proto::proto::service::{compact_tx_streamer_server::CompactTxStreamer, Empty};
+    use zaino_serve::{rpc::GrpcClient, server::health::NodeHealth};
+    use zaino_testutils::{GrpcClientBuilder, MockNode, MockResponse};
+
+    /// Serves the JSON-RPC results held in `results`, keyed by method, on a localhost port, returning the node's uri.
+    pub(super) async fn mock_node(results: serde_json::Value) -> String {
+        let node = MockNode::spawn().await;
+        for (method, result) in results.as_object().unwrap() {
+            node.respond(method, MockResponse::Result(result.clone()));
+        }
+        node.uri().to_string()
+    }
+
+    /// Returns a gRPC service backed by a mock node reporting the given chain name.
+    async fn client_for_chain(chain: &str) -> GrpcClient {
+        let node_uri = mock_node(serde_json::json!({
+            "getinfo": { "build": "v0.0.0", "subversion": "/MockNode:0.0.0/" },
+            "getblockchaininfo": {
+                "chain": chain,
+                "blocks": 10,
+                "bestblockhash": "00".repeat(32),
+                "estimatedheight": 10,
+                "upgrades": {},
+                "consensus": { "chaintip": "c2d6d0b4", "nextblock": "c2d6d0b4" },
+            },
+        }))
+        .await;
+        GrpcClientBuilder::new(&node_uri.parse().unwrap())
+            .node_health(Arc::new(NodeHealth::new(3, Duration::from_secs(1))))
+            .build()
+    }
+
+    #[test]
+    fn chain_names_are_normalized() {
+        assert_eq!(
+            NetworkKind::from_chain_name("main"),
+            Some(NetworkKind::Mainnet)
+        );
+        assert_eq!(
+            NetworkKind::from_chain_name(" TestNet\n"),
+            Some(NetworkKind::Testnet)
+        );
+        assert_eq!(
+            NetworkKind::from_chain_name("regtest"),
+            Some(NetworkKind::Regtest)
+        );
+        assert_eq!(NetworkKind::from_chain_name(""), None);
+        assert_eq!(NetworkKind::from_chain_name("bitcoin"), None);
+        assert_eq!(NetworkKind::Mainnet.chain_name(), "main");
+    }
+
+    #[tokio::test]
+    async fn known_chain_is_reported_by_bip70_name() {
+        let grpc_client = client_for_chain(" Mainnet ").await;
+        let lightd_info = grpc_client
+            .get_lightd_info(tonic::Request::new(Empty {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(lightd_info.chain_name, "main");
+    }
+
+    #[tokio::test]
+    async fn unknown_chain_is_rejected() {
+        let grpc_client = client_for_chain("bogus").await;
+        let status = grpc_client
+            .get_lightd_info(tonic::Request::new(Empty {}))
+            .await
+            .unwrap_err();
+        println!("[TEST LOG] Status: {:?}.", status);
+        assert_eq!(status.code(), tonic::Code::Internal);
+        assert!(status.message().contains("unknown chain \"bogus\""));
+    }
+}
+
+mod broadcast_disabled {
+    use super::*;
+    use std::time::Duration;
+    use zaino_proto::proto::service::{
+        compact_tx_streamer_server::CompactTxStreamer, Empty, RawTransaction,
+    };
+    use zaino_serve::{
+        rpc::{service::BROADCAST_DISABLED_HEADER, GrpcClient, DEFAULT_BROADCAST_DISABLED_MESSAGE},
+        server::health::NodeHealth,
+    };
+    use zaino_testutils::{GrpcClientBuilder, MockNode};
+
+    /// Returns a mock node serving lightd info and accepting every transaction sent.
+    async fn node() -> MockNode {
+        MockNode::with_responses(serde_json::json!({
+            "getinfo": { "result": { "build": "v0.0.0", "subversion": "/MockNode:0.0.0/" } },
+            "getblockchaininfo": { "result": {
+                "chain": "regtest",
+                "blocks": 10,
+                "bestblockhash": "00".repeat(32),
+                "estimatedheight": 10,
+                "upgrades": {},
+                "consensus": { "chaintip": "c2d6d0b4", "nextblock": "c2d6d0b4" },
+            } },
+            "sendrawtransaction": { "result": "11".repeat(32) },
+        }))
+        .await
+    }
+
+    /// Returns a gRPC service backed by `node`, with transaction broadcast disabled by `broadcast_disabled`.
+    fn client(node: &MockNode, broadcast_disabled: Option<String>) -> GrpcClient {
+        GrpcClientBuilder::new(node.uri())
+            .node_health(Arc::new(NodeHealth::new(3, Duration::from_secs(1))))
+            .broadcast_disabled(broadcast_disabled)
+            .build()
+    }
+
+    fn raw_transaction() -> tonic::Request<RawTransaction> {
+        tonic::Request::new(RawTransaction {
+            data: vec![0xab; 32],
+            height: 0,
+        })
+    }
+
+    #[tokio::test]
+    async fn send_transaction_is_rejected_while_broadcast_is_disabled() {
+        let node = node().await;
+        let grpc_client = client(
+            &node,
+            Some("Broadcast through the wallet gateway.".to_string()),
+        );
+
+        let status = grpc_client
+            .send_transaction(raw_transaction())
+            .await
+            .unwrap_err();
+        println!("[TEST LOG] Status: {:?}.", status);
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert_eq!(status.message(), "Broadcast through the wallet gateway.");
+        assert_eq!(node.call_count("sendrawtransaction"), 0);
+
+        // Every other RPC is served normally.
+        let lightd_info = grpc_client
+            .get_lightd_info(tonic::Request::new(Empty {}))
+            .await
+            .unwrap();
+        assert_eq!(lightd_info.get_ref().chain_name, "regtest");
+    }
+
+    #[tokio::test]
+    async fn send_transaction_is_broadcast_by_default() {
+        let node = node().await;
+        let grpc_client = client(&node, None);
+
+        grpc_client
+            .send_transaction(raw_transaction())
+            .await
+            .unwrap();
+        assert_eq!(node.call_count("sendrawtransaction"), 1);
+    }
+
+    #[tokio::test]
+    async fn disabled_broadcast_is_reported_by_lightd_info() {
+        let node = node().await;
+
+        let lightd_info = client(&node, Some(DEFAULT_BROADCAST_DISABLED_MESSAGE.to_string()))
+            .get_lightd_info(tonic::Request::new(Empty {}))
+            .await
+            .unwrap();
+        assert_eq!(
+            lightd_info
+                .metadata()
+                .get(BROADCAST_DISABLED_HEADER)
+                .unwrap(),
+            "true"
+        );
+
+        let lightd_info = client(&node, None)
+            .get_lightd_info(tonic::Request::new(Empty {}))
+            .await
+            .unwrap();
+        assert!(lightd_info
+            .metadata()
+            .get(BROADCAST_DISABLED_HEADER)
+            .is_none());
+    }
+}
+
+mod chain_consistency {
+    use super::*;
+    use std::time::Duration;
+    use zaino_fetch::{
+        chain::cache::BlockCache, jsonrpc::connector::JsonRpcConnector,
+        primitives::chain::NetworkKind,
+    };
+    use zaino_proto::proto::{
+        compact_formats::CompactBlock,
+        service::{compact_tx_streamer_server::CompactTxStreamer, BlockId},
+    };
+    use zaino_serve::server::{
+        consistency::{ChainConsistency, ChainMismatch},
+        health::NodeHealth,
+    };
+    use zaino_testutils::GrpcClientBuilder;
+
+    /// Returns a mock node on the given chain whose blocks, at every height, hash to `block_hash`.
+    async fn mock_chain(chain: &str, block_hash: u8) -> JsonRpcConnector {
+        let node_uri = super::lightd_info_chain_name::mock_node(serde_json::json!({
+            "getinfo": { "build": "v0.0.0", "subversion": "/MockNode:0.0.0/" },
+            "getblockchaininfo": {
+                "chain": chain,
+                "blocks": 10,
+                "bestblockhash": hex::encode([block_hash; 32]),
+                "estimatedheight": 10,
+                "upgrades": {},
+                "consensus": { "chaintip": "c2d6d0b4", "nextblock": "c2d6d0b4" },
+            },
+            "getblockhash": hex::encode([block_hash; 32]),
+        }))
+        .await;
+        JsonRpcConnector::new(
+            node_uri.parse().unwrap(),
+            NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+        )
+        .await
+    }
+
+    /// Returns a block cache holding a block at height 1 with the given hash.
+    async fn cache_with_block(block_hash: u8) -> BlockCache {
+        let block_cache = BlockCache::new(2);
+        block_cache
+            .insert(
+                1,
+                CompactBlock {
+                    height: 1,
+                    hash: vec![block_hash; 32],
+                    ..Default::default()
+                },
+            )
+            .await;
+        block_cache
+    }
+
+    #[tokio::test]
+    async fn same_chain_is_consistent() {
+        let node = mock_chain("regtest", 0x11).await;
+        let block_cache = cache_with_block(0x11).await;
+        let node_health = NodeHealth::default();
+        let consistency = ChainConsistency::new(NetworkKind::Regtest, false);
+
+        for _ in 0..2 {
+            let mismatch = consistency
+                .verify(&node, &block_cache, &node_health)
+                .await
+                .unwrap();
+            assert_eq!(mismatch, None);
+        }
+        assert_eq!(consistency.genesis_hash(), Some([0x11; 32]));
+        assert!(node_health.check_chain_consistent().is_ok());
+    }
+
+    #[tokio::test]
+    async fn failover_to_other_chain_refuses_cached_data() {
+        let block_cache = cache_with_block(0x11).await;
+        let node_health = Arc::new(NodeHealth::default());
+        let consistency = ChainConsistency::new(NetworkKind::Regtest, false);
+        consistency
+            .verify(
+                &mock_chain("regtest", 0x11).await,
+                &block_cache,
+                &node_health,
+            )
+            .await
+            .unwrap();
+
+        // The node is replaced by one on a different regtest chain.
+        let other_node = mock_chain("regtest", 0x22).await;
+        let mismatch = consistency
+            .verify(&other_node, &block_cache, &node_health)
+            .await
+            .unwrap();
+        assert_eq!(
+            mismatch,
+            Some(ChainMismatch::GenesisHash {
+                expected: [0x11; 32],
+                found: [0x22; 32],
+            })
+        );
+        assert!(node_health.chain_mismatch().is_some());
+        assert!(block_cache.contains(1).await);
+
+        let grpc_client = GrpcClientBuilder::new(other_node.uri())
+            .block_cache(Arc::new(block_cache))
+            .node_health(node_health.clone())
+            .build();
+        let status = grpc_client
+            .get_block(tonic::Request::new(BlockId {
+                height: 1,
+                hash: Vec::new(),
+            }))
+            .await
+            .unwrap_err();
+        println!("[TEST LOG] Status: {:?}.", status);
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        // The mismatch is held until the server is restarted, even if the original node returns.
+        consistency
+            .verify(
+                &mock_chain("regtest", 0x11).await,
+                grpc_client.block_cache.as_ref(),
+                &node_health,
+            )
+            .await
+            .unwrap();
+        assert!(node_health.chain_mismatch().is_some());
+    }
+
+    #[tokio::test]
+    async fn changed_finalized_block_is_a_mismatch() {
+        let node = mock_chain("regtest", 0x11).await;
+        let block_cache = cache_with_block(0x33).await;
+        let node_health = NodeHealth::new(3, Duration::from_secs(1));
+        let consistency = ChainConsistency::new(NetworkKind::Regtest, false);
+
+        let mismatch = consistency
+            .verify(&node, &block_cache, &node_health)
+            .await
+            .unwrap();
+        assert_eq!(
+            mismatch,
+            Some(ChainMismatch::FinalizedBlock {
+                height: 1,
+                cached: [0x33; 32],
+                found: [0x11; 32],
+            })
+        );
+        assert_eq!(
+            node_health.check_chain_consistent().unwrap_err().code(),
+            tonic::Code::FailedPrecondition
+        );
+    }
+
+    #[tokio::test]
+    async fn reset_on_mismatch_clears_cache() {
+        let block_cache = cache_with_block(0x11).await;
+        let node_health = NodeHealth::default();
+        let consistency = ChainConsistency::new(NetworkKind::Regtest, true);
+        consistency
+            .verify(
+                &mock_chain("regtest", 0x11).await,
+                &block_cache,
+                &node_health,
+            )
+            .await
+            .unwrap();
+
+        let mismatch = consistency
+            .verify(
+                &mock_chain("regtest", 0x22).await,
+                &block_cache,
+                &node_health,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(mismatch, Some(ChainMismatch::GenesisHash { .. })));
+        assert!(block_cache.is_empty().await);
+        assert_eq!(consistency.genesis_hash(), Some([0x22; 32]));
+        assert!(node_health.check_chain_consistent().is_ok());
+    }
+
+    #[tokio::test]
+    async fn network_mismatch_is_never_reset() {
+        let block_cache = cache_with_block(0x11).await;
+        let node_health = NodeHealth::default();
+        let consistency = ChainConsistency::new(NetworkKind::Regtest, true);
+
+        let mismatch = consistency
+            .verify(&mock_chain("main", 0x11).await, &block_cache, &node_health)
+            .await
+            .unwrap();
+        assert_eq!(
+            mismatch,
+            Some(ChainMismatch::Network {
+                expected: NetworkKind::Regtest,
+                found: "main".to_string(),
+            })
+        );
+        assert!(block_cache.contains(1).await);
+        assert!(node_health.chain_mismatch().is_some());
+    }
+}
+
+mod node_auth_header {
+    use super::*;
+    use zaino_fetch::jsonrpc::connector::JsonRpcConnector;
+    use zaino_testutils::{MockNode, MockResponse};
+
+    /// Sends a `getinfo` request with `auth`, returning the `Authorization` header received by the node.
+    async fn authorization_sent(auth: NodeAuth) -> Option<String> {
+        let node = MockNode::spawn().await;
+        node.respond(
+            "getinfo",
+            MockResponse::Result(
+                serde_json::json!({ "build": "v0.0.0", "subversion": "/MockNode:0.0.0/" }),
+            ),
+        );
+        JsonRpcConnector::new(node.uri().clone(), auth)
+            .await
+            .get_info()
+            .await
+            .unwrap();
+        node.calls().pop().unwrap().authorization
+    }
+
+    #[tokio::test]
+    async fn no_credentials_sends_no_authorization_header() {
+        assert_eq!(authorization_sent(NodeAuth::None).await, None);
+    }
+
+    #[tokio::test]
+    async fn user_pass_sends_basic_authorization_header() {
+        assert_eq!(
+            authorization_sent(NodeAuth::user_pass("zaino_user", "hunter2")).await,
+            Some("Basic emFpbm9fdXNlcjpodW50ZXIy".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn cookie_sends_cookie_file_credentials() {
+        let path =
+            std::env::temp_dir().join(format!("zaino_node_auth_{}.cookie", std::process::id()));
+        std::fs::write(&path, "__cookie__:secret\n").unwrap();
+        let authorization = authorization_sent(NodeAuth::Cookie { path: path.clone() }).await;
+        std::fs::remove_file(&path).ok();
+        assert_eq!(
+            authorization,
+            Some("Basic X19jb29raWVfXzpzZWNyZXQ=".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_cookie_file_is_an_error() {
+        let auth = NodeAuth::Cookie {
+            path: std::env::temp_dir().join("zaino_node_auth_missing.cookie"),
+        };
+        assert!(auth.authorization_header().is_err());
+    }
+
+    #[test]
+    fn password_is_redacted() {
+        let auth = NodeAuth::user_pass("zaino_user", "hunter2");
+        assert!(!format!("{:?}", auth).contains("hunter2"));
+        assert!(!serde_json::to_string(&auth).unwrap().contains("hunter2"));
+    }
+}
+
+mod config_reload {
+    use super::*;
+    use nix::{
+        sys::signal::{kill, Signal},
+        unistd::Pid,
+    };
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Command, Stdio},
+        sync::Mutex,
+        time::Duration,
+    };
+    use zaino_fetch::primitives::chain::NetworkKind;
+    use zaino_proto::proto::service::{compact_tx_streamer_client::CompactTxStreamerClient, Empty};
+    use zaino_serve::server::logging::RequestLogLevel;
+    use zaino_testutils::fetch_metrics;
+    use zainodlib::{
+        config::{try_load_config, IndexerConfig},
+        indexer::{Indexer, GRPC_ADDR_KEY},
+    };
+
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    /// Returns the contents of an indexer config file for the test manager's node.
+    fn config_toml(
+        test_manager: &TestManager,
+        listen_port: u16,
+        lightwalletd_port: u16,
+        metrics_port: u16,
+        max_worker_pool_size: u16,
+    ) -> String {
+        format!(
+            "network = \"Regtest\"\n\
+             tcp_active = true\n\
+             listen_port = {listen_port}\n\
+             nym_active = false\n\
+             lightwalletd_port = {lightwalletd_port}\n\
+             zebrad_port = {}\n\
+             node_auth = {{ method = \"UserPass\", user = \"xxxxxx\", password = \"xxxxxx\" }}\n\
+             metrics_port = {metrics_port}\n\
+             max_queue_size = 512\n\
+             max_worker_pool_size = {max_worker_pool_size}\n\
+             idle_worker_pool_size = 4\n\
+             max_concurrent_streams_global = 5000\n",
+            test_manager.zebrad_port
+        )
+    }
+
+    #[test]
+    fn structural_changes_require_restart() {
+        let config = IndexerConfig::default_for_network(NetworkKind::Regtest);
+        let mut new_config = config.clone();
+        new_config.max_worker_pool_size = config.max_worker_pool_size / 2;
+        new_config.max_queue_size = config.max_queue_size / 2;
+        new_config.request_log_level = RequestLogLevel::Full;
+        new_config.max_requests_per_second_per_ip = 10;
+        assert!(config.restart_required_changes(&new_config).is_empty());
+
+        new_config.listen_port = Some(config.listen_port.unwrap_or(8137) + 1);
+        new_config.metrics_port = Some(9100);
+        let changes = config.restart_required_changes(&new_config);
+        assert_eq!(changes, ["listen_port", "metrics_port"]);
+
+        // Fields are restart required unless explicitly reloadable, including secrets redacted when serialized.
+        let mut new_config = config.clone();
+        new_config.node_auth = NodeAuth::user_pass("xxxxxx", "changed");
+        new_config.keepalive_tcp = !config.keepalive_tcp;
+        new_config.cache_warm_range = Some(1..=10);
+        assert_eq!(
+            config.restart_required_changes(&new_config),
+            ["cache_warm_range", "keepalive_tcp", "node_auth"]
+        );
+    }
+
+    #[tokio::test]
+    async fn reload_applies_worker_pool_size() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+
+        let listen_port = free_port();
+        let lightwalletd_port = free_port();
+        let metrics_port = free_port();
+        let config_path = test_manager.temp_conf_dir.path().join("zindexer.toml");
+        std::fs::write(
+            &config_path,
+            config_toml(
+                &test_manager,
+                listen_port,
+                lightwalletd_port,
+                metrics_port,
+                32,
+            ),
+        )
+        .unwrap();
+        let config = try_load_config(&config_path).unwrap();
+        let (_reloaded_handler, _, reload_handle) =
+            Indexer::launch_indexer_service(config, online.clone())
+                .await
+                .unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        assert!(fetch_metrics(metrics_port)
+            .await
+            .contains("zaino_worker_pool_max_size 32"));
+
+        // The listen port change requires a restart and is ignored, the worker pool size is applied.
+        std::fs::write(
+            &config_path,
+            config_toml(
+                &test_manager,
+                free_port(),
+                lightwalletd_port,
+                metrics_port,
+                16,
+            ),
+        )
+        .unwrap();
+        reload_handle.reload();
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        let metrics = fetch_metrics(metrics_port).await;
+        println!("[TEST LOG] Metrics after reload:\n{}", metrics);
+        assert!(metrics.contains("zaino_worker_pool_max_size 16"));
+        assert!(metrics.contains("zaino_worker_pool_idle_size 4"));
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    /// Waits up to ten seconds for a line containing `needle` to be read from zainod's stdout.
+    async fn wait_for_line(lines: &Mutex<Vec<String>>, needle: &str) -> bool {
+        for _ in 0..100 {
+            if lines
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|line| line.contains(needle))
+            {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn sighup_reloads_request_log_level_and_queue_size() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+
+        let lightwalletd_port = free_port();
+        let metrics_port = free_port();
+        let config_path = test_manager.temp_conf_dir.path().join("zindexer.toml");
+        let write_config = |request_log_level: &str, max_queue_size: u16| {
+            std::fs::write(
+                &config_path,
+                format!(
+                    "{}request_log_level = \"{}\"\n",
+                    config_toml(&test_manager, 0, lightwalletd_port, metrics_port, 32).replace(
+                        "max_queue_size = 512",
+                        &format!("max_queue_size = {}", max_queue_size)
+                    ),
+                    request_log_level
+                ),
+            )
+            .unwrap();
+        };
+        write_config("Off", 512);
+
+        // zainod's stdout is only captured from a subprocess, the test harness captures the output of this one.
+        let mut zainod = Command::new(super::grpc_addr_stdout::zainod_binary())
+            .arg("--config")
+            .arg(&config_path)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let lines = Arc::new(Mutex::new(Vec::<String>::new()));
+        let stdout = zainod.stdout.take().unwrap();
+        let reader_lines = lines.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                reader_lines.lock().unwrap().push(line);
+            }
+        });
+        assert!(
+            wait_for_line(&lines, &format!("{}=", GRPC_ADDR_KEY)).await,
+            "zainod did not print its gRPC address"
+        );
+        let grpc_addr = lines
+            .lock()
+            .unwrap()
+            .iter()
+            .find_map(|line| line.strip_prefix(&format!("{}=", GRPC_ADDR_KEY)))
+            .unwrap()
+            .to_string();
+        let mut grpc_client = loop {
+            match CompactTxStreamerClient::connect(format!("http://{}", grpc_addr)).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        };
+
+        grpc_client.get_lightd_info(Empty {}).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!lines
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.starts_with("[REQUEST]")));
+
+        write_config("Metadata", 256);
+        kill(Pid::from_raw(zainod.id() as i32), Signal::SIGHUP).unwrap();
+        assert!(wait_for_line(&lines, "Config reloaded from").await);
+        assert!(wait_for_line(&lines, "Resizing request queue, max size: 256.").await);
+        grpc_client.get_lightd_info(Empty {}).await.unwrap();
+        assert!(wait_for_line(&lines, "[REQUEST] rpc=GetLightdInfo status=Ok").await);
+
+        zainod.kill().unwrap();
+        zainod.wait().unwrap();
+        println!("[TEST LOG] zainod stdout: {:?}.", lines.lock().unwrap());
+        let lines = lines.lock().unwrap();
+        assert!(!lines
+            .iter()
+            .any(|line| line.contains("restart the indexer to apply this change")));
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn reload_applies_per_ip_rate_limit() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+
+        let listen_port = free_port();
+        let lightwalletd_port = free_port();
+        let metrics_port = free_port();
+        let config_path = test_manager
+            .temp_conf_dir
+            .path()
+            .join("zindexer_rate_limit.toml");
+        let write_config = |max_requests_per_second_per_ip: u32| {
+            std::fs::write(
+                &config_path,
+                format!(
+                    "{}max_requests_per_second_per_ip = {}\n",
+                    config_toml(
+                        &test_manager,
+                        listen_port,
+                        lightwalletd_port,
+                        metrics_port,
+                        32
+                    ),
+                    max_requests_per_second_per_ip
+                ),
+            )
+            .unwrap();
+        };
+        write_config(0);
+        let config = try_load_config(&config_path).unwrap();
+        let (_reloaded_handler, _, reload_handle) =
+            Indexer::launch_indexer_service(config, online.clone())
+                .await
+                .unwrap();
+        let mut grpc_client = loop {
+            match CompactTxStreamerClient::connect(format!("http://127.0.0.1:{}", listen_port))
+                .await
+            {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        };
+        for _ in 0..5 {
+            grpc_client.get_lightd_info(Empty {}).await.unwrap();
+        }
+
+        write_config(2);
+        reload_handle.reload();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        let results: Vec<_> = futures::future::join_all(
+            (0..5).map(|_| grpc_client.clone().get_lightd_info(Empty {})),
+        )
+        .await;
+        let rejected = results
+            .iter()
+            .filter(|result| {
+                result
+                    .as_ref()
+                    .is_err_and(|status| status.code() == tonic::Code::ResourceExhausted)
+            })
+            .count();
+        println!("[TEST LOG] Rejected {} of 5 requests.", rejected);
+        // The address's bucket holds two requests, refilled at two per second, the other three are rejected.
+        assert_eq!(rejected, 3);
+
+        drop_test_manager(
+            Some(test_manager.temp_conf_dir.path().to_path_buf()),
+            regtest_handler,
+            online,
+        )
+        .await;
+    }
+}
+
+mod raw_mempool {
+    use super::*;
+    use zaino_fetch::jsonrpc::{connector::JsonRpcConnector, response::GetRawMempoolResponse};
+
+    validator_matrix! {
+        async fn get_raw_mempool_lists_unmined_transactions(validator: ValidatorKind) {
+            let harness = TestCase::launch(TestManagerBuilder::new(), validator).await;
+            let zingo_client = harness.build_lightclient().await;
+            harness.regtest_manager.generate_n_blocks(1).unwrap();
+            zingo_client.do_sync(false).await.unwrap();
+            let zebrad_client = JsonRpcConnector::new(
+                harness.test_and_return_zebrad_uri().await,
+                NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+            )
+            .await;
+            assert!(zebrad_client
+                .get_raw_mempool(false)
+                .await
+                .unwrap()
+                .txids()
+                .is_empty());
+
+            zingo_client
+                .do_send(vec![(
+                    &get_zingo_address(&zingo_client, "transparent").await,
+                    250_000,
+                    None,
+                )])
+                .await
+                .unwrap();
+            let txids = zebrad_client.get_raw_mempool(false).await.unwrap().txids();
+            println!("[TEST LOG] Mempool txids: {:?}.", txids);
+            assert_eq!(txids.len(), 1);
+
+            match zebrad_client.get_raw_mempool(true).await.unwrap() {
+                GetRawMempoolResponse::Verbose(entries) => {
+                    let entry = entries.get(&txids[0]).unwrap();
+                    println!("[TEST LOG] Mempool entry: {:?}.", entry);
+                    assert!(entry.size > 0);
+                    assert!(entry.fee > 0.0);
+                }
+                GetRawMempoolResponse::Txids(_) => panic!("Expected a verbose mempool response."),
+            }
+
+            harness.regtest_manager.generate_n_blocks(1).unwrap();
+            assert!(!zebrad_client
+                .get_raw_mempool(false)
+                .await
+                .unwrap()
+                .txids()
+                .contains(&txids[0]));
+
+            harness.teardown().await;
+        }
+    }
+}
+
+mod mempool_exclude {
+    use super::*;
+    use zaino_fetch::chain::{error::MempoolError, mempool::MempoolExclude};
+    use zaino_proto::proto::service::Exclude;
+
+    const TXID_A: &str = "aa11000000000000000000000000000000000000000000000000000000000001";
+    const TXID_B: &str = "aa22000000000000000000000000000000000000000000000000000000000002";
+    const TXID_C: &str = "bb33000000000000000000000000000000000000000000000000000000000003";
+
+    fn mempool_txids() -> Vec<String> {
+        [TXID_A, TXID_B, TXID_C]
+            .iter()
+            .map(|txid| txid.to_string())
+            .collect()
+    }
+
+    /// Returns the exclude entry for a display order txid prefix, clients send entries reversed.
+    fn entry(display_prefix: &str) -> Vec<u8> {
+        let mut bytes = hex::decode(display_prefix).unwrap();
+        bytes.reverse();
+        bytes
+    }
+
+    fn filter(entries: Vec<Vec<u8>>) -> Vec<String> {
+        MempoolExclude::new(entries, 16)
+            .unwrap()
+            .filter(mempool_txids())
+    }
+
+    #[test]
+    fn empty_exclude_returns_all() {
+        assert_eq!(filter(Vec::new()), mempool_txids());
+    }
+
+    #[test]
+    fn unique_short_prefix_excludes_one() {
+        assert_eq!(filter(vec![entry("aa22")]), vec![TXID_A, TXID_C]);
+        assert_eq!(filter(vec![entry("bb")]), vec![TXID_A, TXID_B]);
+    }
+
+    #[test]
+    fn ambiguous_short_prefix_excludes_none() {
+        assert_eq!(filter(vec![entry("aa")]), mempool_txids());
+        // Other entries are still applied.
+        assert_eq!(
+            filter(vec![entry("aa"), entry("bb33")]),
+            vec![TXID_A, TXID_B]
+        );
+    }
+
+    #[test]
+    fn full_length_exclude_matches_exactly() {
+        assert_eq!(filter(vec![entry(TXID_B)]), vec![TXID_A, TXID_C]);
+        assert_eq!(
+            filter(vec![entry(TXID_A), entry(TXID_B), entry(TXID_C)]),
+            Vec::<String>::new()
+        );
+        let absent = "aa11000000000000000000000000000000000000000000000000000000000009";
+        assert_eq!(filter(vec![entry(absent)]), mempool_txids());
+    }
+
+    #[test]
+    fn invalid_entry_lengths_are_rejected() {
+        assert!(matches!(
+            MempoolExclude::new(vec![entry("aa11"), Vec::new()], 16),
+            Err(MempoolError::InvalidExcludeTxid { index: 1, len: 0 })
+        ));
+        assert!(matches!(
+            MempoolExclude::new(vec![vec![0; 33]], 16),
+            Err(MempoolError::InvalidExcludeTxid { index: 0, len: 33 })
+        ));
+    }
+
+    #[test]
+    fn too_many_entries_are_rejected() {
+        assert!(MempoolExclude::new(vec![entry("aa"); 16], 16).is_ok());
+        let error = MempoolExclude::new(vec![entry("aa"); 17], 16).unwrap_err();
+        assert!(matches!(
+            error,
+            MempoolError::ExcludeListTooLong { count: 17, max: 16 }
+        ));
+        assert_eq!(
+            tonic::Status::from(error).code(),
+            tonic::Code::InvalidArgument
+        );
+    }
+
+    validator_matrix! {
+        async fn get_mempool_tx_excludes_known_transactions(validator: ValidatorKind) {
+            let harness = TestCase::launch(TestManagerBuilder::new(), validator).await;
+            let zingo_client = harness.build_lightclient().await;
+            harness.regtest_manager.generate_n_blocks(5).unwrap();
+            zingo_client.do_sync(false).await.unwrap();
+            zingo_client
+                .do_send(vec![(
+                    &get_zingo_address(&zingo_client, "sapling").await,
+                    250_000,
+                    None,
+                )])
+                .await
+                .unwrap();
+            let mut grpc_client = harness.build_grpc_client().await;
+
+            let mut stream = grpc_client
+                .get_mempool_tx(Exclude { txid: Vec::new() })
+                .await
+                .unwrap()
+                .into_inner();
+            let mut mempool_txs = Vec::new();
+            while let Some(tx) = stream.message().await.unwrap() {
+                mempool_txs.push(tx);
+            }
+            println!("[TEST LOG] Mempool txs: {:?}.", mempool_txs);
+            assert_eq!(mempool_txs.len(), 1);
+
+            let mut stream = grpc_client
+                .get_mempool_tx(Exclude {
+                    txid: vec![mempool_txs[0].hash.clone()],
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(stream.message().await.unwrap().is_none());
+
+            let status = grpc_client
+                .get_mempool_tx(Exclude {
+                    txid: vec![Vec::new()],
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+            harness.teardown().await;
+        }
+    }
+}
+
+mod mempool_cache {
+    use super::*;
+    use zaino_fetch::chain::mempool::{CachedMempoolTx, MempoolTxCache};
+    use zaino_proto::proto::{
+        compact_formats::CompactTx,
+        service::{compact_tx_streamer_server::CompactTxStreamer, Exclude},
+    };
+    use zaino_serve::{rpc::GrpcClient, server::metrics::RpcMetrics};
+    use zaino_testutils::{
+        fixtures::VALID_TRANSPARENT_TX_HEX, GrpcClientBuilder, MockNode, MockResponse,
+    };
+
+    /// Returns a synthetic transaction of `size` bytes paying `fee` zatoshis, that entered the mempool at `time`.
+    ///
+    /// The compact form is empty, so the memory charged to the cache is exactly `size`.
+    fn tx(size: usize, fee: u64, time: i64) -> CachedMempoolTx {
+        CachedMempoolTx {
+            raw: vec![0; size],
+            compact: CompactTx::default(),
+            fee,
+            time,
+        }
+    }
+
+    #[tokio::test]
+    async fn lowest_fee_rate_then_oldest_is_evicted() {
+        let cache = MempoolTxCache::new(1000);
+        let stats = cache.stats();
+        // Fee rates, in zatoshis per byte: a 1, b 2, c 1 but older than a.
+        assert!(cache.insert("a".into(), tx(300, 300, 10)).await.is_empty());
+        assert!(cache.insert("b".into(), tx(300, 600, 5)).await.is_empty());
+        assert!(cache.insert("c".into(), tx(200, 200, 5)).await.is_empty());
+        assert_eq!(stats.size_bytes(), 800);
+
+        // Of the transactions paying the lowest fee rate the oldest is evicted first.
+        assert_eq!(cache.insert("d".into(), tx(300, 900, 20)).await, ["c"]);
+        assert_eq!(cache.insert("e".into(), tx(250, 250, 20)).await, ["a"]);
+        // A transaction paying less than every cached transaction is evicted itself.
+        assert_eq!(cache.insert("f".into(), tx(400, 100, 30)).await, ["f"]);
+
+        assert!(cache.get("a").await.is_none());
+        assert!(cache.get("c").await.is_none());
+        assert!(cache.get("f").await.is_none());
+        for txid in ["b", "d", "e"] {
+            assert!(cache.get(txid).await.is_some(), "{} was evicted", txid);
+        }
+        assert_eq!(stats.size_bytes(), 850);
+        assert_eq!(stats.transactions(), 3);
+        assert_eq!(stats.evictions(), 3);
+    }
+
+    #[tokio::test]
+    async fn evicting_one_large_transaction_makes_room_for_several() {
+        let cache = MempoolTxCache::new(1000);
+        cache.insert("large".into(), tx(900, 900, 0)).await;
+        assert!(cache
+            .insert("small_1".into(), tx(50, 500, 1))
+            .await
+            .is_empty());
+        assert_eq!(
+            cache.insert("small_2".into(), tx(100, 1000, 2)).await,
+            ["large"]
+        );
+        assert_eq!(cache.stats().size_bytes(), 150);
+    }
+
+    #[tokio::test]
+    async fn mined_and_oversized_transactions_are_not_evictions() {
+        let cache = MempoolTxCache::new(1000);
+        assert!(cache
+            .insert("huge".into(), tx(1001, 5000, 0))
+            .await
+            .is_empty());
+        assert!(cache.get("huge").await.is_none());
+
+        cache.insert("a".into(), tx(300, 300, 0)).await;
+        cache.insert("b".into(), tx(300, 300, 0)).await;
+        cache.retain(&["b".to_string()]).await;
+        assert!(cache.get("a").await.is_none());
+        assert!(cache.get("b").await.is_some());
+
+        let stats = cache.stats();
+        assert_eq!(stats.size_bytes(), 300);
+        assert_eq!(stats.transactions(), 1);
+        assert_eq!(stats.evictions(), 0);
+    }
+
+    #[tokio::test]
+    async fn cache_is_reported_by_metrics() {
+        let cache = MempoolTxCache::new(500);
+        cache.insert("a".into(), tx(300, 300, 0)).await;
+        cache.insert("b".into(), tx(300, 600, 0)).await;
+        let rpc_metrics = RpcMetrics::new();
+        rpc_metrics.set_mempool_cache_stats(cache.stats());
+
+        let metrics = rpc_metrics.render();
+        println!("[TEST LOG] Metrics:\n{}", metrics);
+        for line in [
+            "zaino_mempool_cache_bytes 300",
+            "zaino_mempool_cache_max_bytes 500",
+            "zaino_mempool_cache_transactions 1",
+            "zaino_mempool_cache_evictions_total 1",
+        ] {
+            assert!(metrics.lines().any(|l| l == line), "missing {}", line);
+        }
+    }
+
+    /// Display order txid of the mempool transaction served by [`mempool_node`].
+    const TXID: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+
+    /// Spawns a mock node whose mempool holds a single transaction, [`TXID`].
+    async fn mempool_node() -> MockNode {
+        let node = MockNode::spawn().await;
+        node.respond(
+            "getblockchaininfo",
+            MockResponse::Result(serde_json::json!({
+                "chain": "regtest",
+                "blocks": 10,
+                "bestblockhash": "00".repeat(32),
+                "estimatedheight": 10,
+                "upgrades": {},
+                "consensus": { "chaintip": "c2d6d0b4", "nextblock": "c2d6d0b4" },
+            })),
+        )
+        .respond(
+            "getrawmempool",
+            MockResponse::Result(serde_json::json!({
+                TXID: { "size": 200, "fee": 0.0001, "time": 1700000000, "height": 10 },
+            })),
+        )
+        .respond(
+            "getrawtransaction",
+            MockResponse::Result(serde_json::json!({
+                "hex": VALID_TRANSPARENT_TX_HEX,
+                "height": -1,
+                "confirmations": 0,
+            })),
+        );
+        node
+    }
+
+    /// Returns a gRPC service backed by `node`, caching at most `mempool_cache_max_bytes` of mempool transactions.
+    fn client(node: &MockNode, mempool_cache_max_bytes: usize) -> GrpcClient {
+        GrpcClientBuilder::new(node.uri())
+            .mempool_cache(Arc::new(MempoolTxCache::new(mempool_cache_max_bytes)))
+            .build()
+    }
+
+    /// Returns the transactions streamed by get_mempool_tx.
+    async fn mempool_txs(grpc_client: &GrpcClient) -> Vec<CompactTx> {
+        let mut stream = grpc_client
+            .get_mempool_tx(tonic::Request::new(Exclude { txid: Vec::new() }))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut transactions = Vec::new();
+        while let Some(transaction) = futures::StreamExt::next(&mut stream).await {
+            transactions.push(transaction.unwrap());
+        }
+        transactions
+    }
+
+    #[tokio::test]
+    async fn cached_transactions_are_served_without_the_node() {
+        let node = mempool_node().await;
+        let grpc_client = client(&node, 1024 * 1024);
+
+        let first = mempool_txs(&grpc_client).await;
+        let second = mempool_txs(&grpc_client).await;
+        assert_eq!(first.len(), 1);
+        assert_eq!(first, second);
+        assert_eq!(node.call_count("getrawtransaction"), 1);
+        let stats = grpc_client.mempool_cache.stats();
+        assert_eq!(stats.transactions(), 1);
+        let cached = grpc_client.mempool_cache.get(TXID).await.unwrap();
+        assert_eq!(cached.fee, 10_000);
+        assert_eq!(cached.time, 1700000000);
+    }
+
+    #[tokio::test]
+    async fn evicted_transactions_are_fetched_from_the_node() {
+        let node = mempool_node().await;
+        let max_bytes = hex::decode(VALID_TRANSPARENT_TX_HEX).unwrap().len() * 2;
+        let grpc_client = client(&node, max_bytes);
+
+        let first = mempool_txs(&grpc_client).await;
+        // A better paying transaction evicts the served transaction.
+        grpc_client
+            .mempool_cache
+            .insert(
+                "other".to_string(),
+                CachedMempoolTx {
+                    raw: vec![0; max_bytes],
+                    compact: CompactTx::default(),
+                    fee: u64::MAX,
+                    time: 0,
+                },
+            )
+            .await;
+        assert!(grpc_client.mempool_cache.get(TXID).await.is_none());
+
+        let second = mempool_txs(&grpc_client).await;
+        assert_eq!(first, second);
+        assert_eq!(node.call_count("getrawtransaction"), 2);
+        assert!(grpc_client.mempool_cache.stats().evictions() >= 1);
+    }
+}
+
+mod mempool_max_age {
+    use super::*;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use zaino_proto::proto::service::{
+        compact_tx_streamer_server::CompactTxStreamer, Empty, Exclude,
+    };
+    use zaino_serve::rpc::GrpcClient;
+    use zaino_testutils::{
+        fixtures::VALID_TRANSPARENT_TX_HEX, GrpcClientBuilder, MockNode, MockResponse,
+    };
+
+    /// Txid of a transaction that entered the mock node's mempool an hour ago.
+    const OLD_TXID: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+    /// Txid of a transaction that just entered the mock node's mempool.
+    const FRESH_TXID: &str = "2222222222222222222222222222222222222222222222222222222222222222";
+
+    /// Spawns a mock node whose mempool holds [`OLD_TXID`] and [`FRESH_TXID`].
+    async fn mempool_node() -> MockNode {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let node = MockNode::spawn().await;
+        node.respond(
+            "getblockchaininfo",
+            MockResponse::Result(serde_json::json!({
+                "chain": "regtest",
+                "blocks": 10,
+                "bestblockhash": "00".repeat(32),
+                "estimatedheight": 10,
+                "upgrades": {},
+                "consensus": { "chaintip": "c2d6d0b4", "nextblock": "c2d6d0b4" },
+            })),
+        )
+        .respond(
+            "getrawmempool",
+            MockResponse::Result(serde_json::json!({
+                OLD_TXID: { "size": 200, "fee": 0.0001, "time": now - 3600, "height": 10 },
+                FRESH_TXID: { "size": 200, "fee": 0.0001, "time": now, "height": 10 },
+            })),
+        )
+        .respond(
+            "getrawtransaction",
+            MockResponse::Result(serde_json::json!({
+                "hex": VALID_TRANSPARENT_TX_HEX,
+                "height": -1,
+                "confirmations": 0,
+            })),
+        );
+        node
+    }
+
+    /// Returns a gRPC service backed by `node`, serving mempool transactions up to `max_mempool_tx_age` old.
+    fn client(node: &MockNode, max_mempool_tx_age: Option<Duration>) -> GrpcClient {
+        GrpcClientBuilder::new(node.uri())
+            .max_mempool_tx_age(max_mempool_tx_age)
+            .build()
+    }
+
+    /// Returns the display order txids of the transactions streamed by get_mempool_tx.
+    async fn mempool_txids(grpc_client: &GrpcClient) -> Vec<String> {
+        let mut stream = grpc_client
+            .get_mempool_tx(tonic::Request::new(Exclude { txid: Vec::new() }))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut txids = Vec::new();
+        while let Some(transaction) = futures::StreamExt::next(&mut stream).await {
+            let mut txid = transaction.unwrap().hash;
+            txid.reverse();
+            txids.push(hex::encode(txid));
+        }
+        txids.sort();
+        txids
+    }
+
+    #[tokio::test]
+    async fn all_transactions_are_served_by_default() {
+        let node = mempool_node().await;
+        assert_eq!(
+            mempool_txids(&client(&node, None)).await,
+            [OLD_TXID, FRESH_TXID]
+        );
+    }
+
+    #[tokio::test]
+    async fn old_transactions_are_not_served() {
+        let node = mempool_node().await;
+        let grpc_client = client(&node, Some(Duration::from_secs(600)));
+        assert_eq!(mempool_txids(&grpc_client).await, [FRESH_TXID]);
+        // The old transaction is not fetched from the node.
+        assert_eq!(node.call_count("getrawtransaction"), 1);
+    }
+
+    #[tokio::test]
+    async fn old_transactions_are_not_streamed() {
+        let node = mempool_node().await;
+        let grpc_client = client(&node, Some(Duration::from_secs(600)));
+        let mut stream = grpc_client
+            .get_mempool_stream(tonic::Request::new(Empty {}))
+            .await
+            .unwrap()
+            .into_inner();
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            futures::StreamExt::next(&mut stream),
+        )
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+
+        // The stream stays open until a block is mined, give it time to poll the mempool again.
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(futures::FutureExt::now_or_never(futures::StreamExt::next(&mut stream)).is_none());
+        assert_eq!(node.call_count("getrawtransaction"), 1);
+    }
+}
+
+mod stream_error_policy {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+    use zaino_serve::rpc::stream::{stream_node_items, StreamErrorPolicy};
+
+    const FAILING_TXID: &str = "txid-2";
+
+    /// Streams txids 1 to 4 through a fetcher that fails for [`FAILING_TXID`], returning the items received and the
+    /// number skipped.
+    ///
+    /// Later txids are fetched faster, so with `concurrency` above 1 fetches complete out of order.
+    async fn stream_with_failure(
+        policy: StreamErrorPolicy,
+        concurrency: usize,
+    ) -> (Vec<Result<String, tonic::Status>>, usize) {
+        let txids: Vec<String> = (1..=4).map(|i| format!("txid-{}", i)).collect();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let skipped = stream_node_items(
+            txids,
+            |txid| async move {
+                let index: u64 = txid.trim_start_matches("txid-").parse().unwrap();
+                tokio::time::sleep(Duration::from_millis(50 * (5 - index))).await;
+                if txid == FAILING_TXID {
+                    Err(tonic::Status::internal("node error"))
+                } else {
+                    Ok(txid)
+                }
+            },
+            &tx,
+            policy,
+            concurrency,
+        )
+        .await;
+        drop(tx);
+        let mut received = Vec::new();
+        while let Some(item) = rx.recv().await {
+            received.push(item);
+        }
+        (received, skipped)
+    }
+
+    #[tokio::test]
+    async fn terminate_ends_stream_at_first_error() {
+        for concurrency in [1, 4] {
+            let (received, skipped) =
+                stream_with_failure(StreamErrorPolicy::Terminate, concurrency).await;
+            assert_eq!(skipped, 0);
+            assert_eq!(received.len(), 2);
+            assert_eq!(received[0].as_ref().unwrap(), "txid-1");
+            let status = received[1].as_ref().unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Internal);
+            assert_eq!(status.message(), "node error");
+        }
+    }
+
+    #[tokio::test]
+    async fn skip_leaves_out_failed_items() {
+        for concurrency in [1, 4] {
+            let (received, skipped) =
+                stream_with_failure(StreamErrorPolicy::Skip, concurrency).await;
+            assert_eq!(skipped, 1);
+            let received: Vec<String> = received.into_iter().map(Result::unwrap).collect();
+            assert_eq!(received, vec!["txid-1", "txid-3", "txid-4"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_fetches_are_sent_in_item_order() {
+        let txids: Vec<u64> = (0..20).collect();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(32);
+        stream_node_items(
+            txids.clone(),
+            |txid| async move {
+                tokio::time::sleep(Duration::from_millis(5 * (20 - txid))).await;
+                Ok(txid)
+            },
+            &tx,
+            StreamErrorPolicy::Terminate,
+            8,
+        )
+        .await;
+        drop(tx);
+        let mut received = Vec::new();
+        while let Some(item) = rx.recv().await {
+            received.push(item.unwrap());
+        }
+        assert_eq!(received, txids);
+    }
+
+    #[tokio::test]
+    async fn fetches_in_flight_are_bounded_by_concurrency() {
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        let (tx, mut rx) = tokio::sync::mpsc::channel(32);
+        stream_node_items(
+            0..20u32,
+            |txid| {
+                let (in_flight, max_in_flight) = (&in_flight, &max_in_flight);
+                async move {
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok(txid)
+                }
+            },
+            &tx,
+            StreamErrorPolicy::Terminate,
+            3,
+        )
+        .await;
+        drop(tx);
+        let mut received = 0;
+        while rx.recv().await.is_some() {
+            received += 1;
+        }
+        assert_eq!(received, 20);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn terminate_is_the_default() {
+        assert_eq!(StreamErrorPolicy::default(), StreamErrorPolicy::Terminate);
+    }
+}
+
+mod subtree_roots {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use zaino_proto::proto::service::{GetSubtreeRootsArg, ShieldedProtocol};
+    use zaino_serve::rpc::stream::stream_indexed_pages;
+
+    /// Number of items in the collection paged by [`page`].
+    const COLLECTION_LEN: u32 = 300;
+
+    /// Streams the test collection from `start_index` with pages of 64, returning the items received.
+    async fn page(start_index: u32, max_entries: u32) -> Vec<u32> {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        stream_indexed_pages(
+            start_index,
+            max_entries,
+            u16::MAX as u32,
+            64,
+            |index, limit| async move {
+                Ok((index..COLLECTION_LEN.min(index + limit)).collect::<Vec<_>>())
+            },
+            &tx,
+        )
+        .await;
+        drop(tx);
+        let mut received = Vec::new();
+        while let Some(item) = rx.recv().await {
+            received.push(item.unwrap());
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn resumed_halves_match_full_fetch() {
+        let full = page(0, 0).await;
+        assert_eq!(full, (0..COLLECTION_LEN).collect::<Vec<_>>());
+
+        let first_half = page(0, COLLECTION_LEN / 2).await;
+        assert_eq!(first_half.len() as u32, COLLECTION_LEN / 2);
+        let second_half = page(first_half.last().unwrap() + 1, 0).await;
+        assert_eq!([first_half, second_half].concat(), full);
+    }
+
+    #[tokio::test]
+    async fn max_entries_is_honoured_across_pages() {
+        assert_eq!(page(10, 100).await, (10..110).collect::<Vec<_>>());
+        assert_eq!(page(290, 100).await, (290..300).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn out_of_range_start_index_ends_stream_immediately() {
+        assert!(page(COLLECTION_LEN, 0).await.is_empty());
+        assert!(page(u16::MAX as u32 + 1, 0).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn pages_are_fetched_as_the_client_reads() {
+        let pages_fetched = AtomicUsize::new(0);
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let stream = stream_indexed_pages(
+            0,
+            0,
+            u16::MAX as u32,
+            4,
+            |index, limit| {
+                pages_fetched.fetch_add(1, Ordering::SeqCst);
+                async move { Ok((index..index + limit).collect::<Vec<_>>()) }
+            },
+            &tx,
+        );
+        // The client reads a single item then drops the stream.
+        let reader = async {
+            rx.recv().await.unwrap().unwrap();
+            drop(rx);
+        };
+        tokio::join!(stream, reader);
+        assert_eq!(pages_fetched.load(Ordering::SeqCst), 1);
+    }
+
+    validator_matrix! {
+        async fn get_subtree_roots_resumes(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            harness.regtest_manager.generate_n_blocks(10).unwrap();
+            let mut grpc_client = harness.build_grpc_client().await;
+
+            let fetch = |start_index: u32, max_entries: u32| {
+                let mut grpc_client = grpc_client.clone();
+                async move {
+                    let mut stream = grpc_client
+                        .get_subtree_roots(GetSubtreeRootsArg {
+                            start_index,
+                            shielded_protocol: ShieldedProtocol::Sapling as i32,
+                            max_entries,
+                        })
+                        .await
+                        .unwrap()
+                        .into_inner();
+                    let mut roots = Vec::new();
+                    while let Some(root) = stream.message().await.unwrap() {
+                        roots.push(root);
+                    }
+                    roots
+                }
+            };
+            let full = fetch(0, 0).await;
+            println!("[TEST LOG] Subtree roots: {}.", full.len());
+            // At least one root is requested, a max_entries of 0 returns all roots.
+            let half = (full.len() as u32 / 2).max(1);
+            let first_half = fetch(0, half).await;
+            let second_half = fetch(first_half.len() as u32, 0).await;
+            assert_eq!([first_half, second_half].concat(), full);
+            assert!(fetch(full.len() as u32 + 1000, 0).await.is_empty());
+
+            let status = grpc_client
+                .get_subtree_roots(GetSubtreeRootsArg {
+                    start_index: 0,
+                    shielded_protocol: 7,
+                    max_entries: 0,
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+            harness.teardown().await;
+        }
+    }
+}
+
+mod request_method_name {
+    use std::{
+        convert::Infallible,
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+    };
+    use tonic::{
+        body::BoxBody,
+        codegen::{http, BoxFuture, Service},
+        server::NamedService,
+        transport::Body,
+    };
+    use zaino_proto::proto::service::{
+        compact_tx_streamer_client::CompactTxStreamerClient, BlockId, BlockRange, Empty,
+    };
+    use zaino_serve::server::request::{
+        method_name_from_path, qualified_method_name, short_method_name, ZingoIndexerRequest,
+        TCP_CONNECTION_METHOD_NAME,
+    };
+
+    /// Test-only CompactTxStreamer service recording the method name of every call it receives.
+    #[derive(Clone, Default)]
+    struct RecordingHandler {
+        method_names: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Service<http::Request<Body>> for RecordingHandler {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+            self.method_names
+                .lock()
+                .unwrap()
+                .push(method_name_from_path(request.uri().path()).to_string());
+            Box::pin(async { Ok(tonic::Status::unimplemented("recorded").to_http()) })
+        }
+    }
+
+    impl NamedService for RecordingHandler {
+        const NAME: &'static str = "cash.z.wallet.sdk.rpc.CompactTxStreamer";
+    }
+
+    #[tokio::test]
+    async fn method_names_are_extracted_from_request_paths() {
+        let handler = RecordingHandler::default();
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server = tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(handler.clone())
+                .serve(addr),
+        );
+        let mut grpc_client = loop {
+            match CompactTxStreamerClient::connect(format!("http://{}", addr)).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+
+        grpc_client.get_lightd_info(Empty {}).await.unwrap_err();
+        grpc_client
+            .get_block_range(BlockRange {
+                start: Some(BlockId {
+                    height: 1,
+                    hash: vec![],
+                }),
+                end: Some(BlockId {
+                    height: 2,
+                    hash: vec![],
+                }),
+            })
+            .await
+            .unwrap_err();
+
+        let method_names = handler.method_names.lock().unwrap().clone();
+        println!("[TEST LOG] Method names: {:?}.", method_names);
+        assert_eq!(
+            method_names,
+            vec![
+                "cash.z.wallet.sdk.rpc.CompactTxStreamer/GetLightdInfo",
+                "cash.z.wallet.sdk.rpc.CompactTxStreamer/GetBlockRange",
+            ]
+        );
+        // Nym requests carry the short method name, qualified at ingest to match.
+        assert_eq!(qualified_method_name("GetLightdInfo"), method_names[0]);
+        assert_eq!(qualified_method_name("GetBlockRange"), method_names[1]);
+        // Per-method metrics are keyed by the short method name.
+        assert_eq!(short_method_name(&method_names[0]), "GetLightdInfo");
+        assert_eq!(short_method_name(&method_names[1]), "GetBlockRange");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn tcp_requests_are_named_by_connection() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let _client = tokio::net::TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+
+        let request = ZingoIndexerRequest::new_from_grpc(stream);
+        println!(
+            "[TEST LOG] TCP request method name: {}.",
+            request.method_name()
+        );
+        assert_eq!(
+            request.method_name(),
+            "cash.z.wallet.sdk.rpc.CompactTxStreamer/*"
+        );
+        assert_eq!(request.method_name(), TCP_CONNECTION_METHOD_NAME);
+        // The connection's calls are attributed per call, by the RPC metrics middleware.
+        assert_eq!(short_method_name(request.method_name()), "*");
+    }
+
+    #[cfg(feature = "nym")]
+    #[test]
+    fn nym_requests_are_named_by_method() {
+        use nym_sphinx_anonymous_replies::requests::AnonymousSenderTag;
+        use zaino_wallet::utils::write_nym_request_data;
+
+        for (id, method) in [(1, "GetLightdInfo"), (2, "GetBlockRange")] {
+            let bytes = write_nym_request_data(id, method.to_string(), &[]).unwrap();
+            let request =
+                ZingoIndexerRequest::new_from_nym(AnonymousSenderTag::from_bytes([0; 16]), &bytes)
+                    .unwrap();
+            println!(
+                "[TEST LOG] Nym request method name: {}.",
+                request.method_name()
+            );
+            assert_eq!(
+                request.method_name(),
+                format!("cash.z.wallet.sdk.rpc.CompactTxStreamer/{}", method)
+            );
+            assert_eq!(short_method_name(request.method_name()), method);
+        }
+    }
+}
+
+mod panic_recovery {
+    use std::{
+        convert::Infallible,
+        task::{Context, Poll},
+    };
+    use tonic::{
+        body::BoxBody,
+        codegen::{http, BoxFuture, Service},
+        server::NamedService,
+        transport::Body,
+    };
+    use zaino_proto::proto::service::{compact_tx_streamer_client::CompactTxStreamerClient, Empty};
+    use zaino_serve::server::{
+        metrics::RpcMetrics,
+        recovery::{PanicRecovery, PANIC_STATUS_MESSAGE},
+    };
+
+    /// Test-only CompactTxStreamer service whose handler panics on every call.
+    #[derive(Clone)]
+    struct PanickingHandler;
+
+    impl Service<http::Request<Body>> for PanickingHandler {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: http::Request<Body>) -> Self::Future {
+            Box::pin(panicking_handler())
+        }
+    }
+
+    async fn panicking_handler() -> Result<http::Response<BoxBody>, Infallible> {
+        panic!("deliberate handler panic")
+    }
+
+    impl NamedService for PanickingHandler {
+        const NAME: &'static str = "cash.z.wallet.sdk.rpc.CompactTxStreamer";
+    }
 
     #[tokio::test]
-    async fn connect_to_node_get_info() {
-        let online = Arc::new(AtomicBool::new(true));
-        let (test_manager, regtest_handler, _indexer_handler) =
-            TestManager::launch(online.clone()).await;
-        let zingo_client = test_manager.build_lightclient().await;
+    async fn handler_panic_returns_internal_status() {
+        let metrics = std::sync::Arc::new(RpcMetrics::new());
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server = tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(PanicRecovery::new(PanickingHandler, metrics.clone()))
+                .serve(addr),
+        );
+        let mut grpc_client = loop {
+            match CompactTxStreamerClient::connect(format!("http://{}", addr)).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
 
-        let lightd_info = zingo_client.do_info().await;
-        println!("[TEST LOG] Lightd_info response:\n{:#?}.", lightd_info);
+        // The connection survives the panic and serves the next call.
+        for _ in 0..2 {
+            let status = grpc_client.get_lightd_info(Empty {}).await.unwrap_err();
+            println!("[TEST LOG] Status: {:?}.", status);
+            assert_eq!(status.code(), tonic::Code::Internal);
+            assert_eq!(status.message(), PANIC_STATUS_MESSAGE);
+        }
+        assert_eq!(metrics.method("GetLightdInfo").unwrap().panics, 2);
+        assert!(metrics
+            .render()
+            .contains("zaino_rpc_panics_total{method=\"GetLightdInfo\"} 2"));
 
-        drop_test_manager(
-            Some(test_manager.temp_conf_dir.path().to_path_buf()),
-            regtest_handler,
-            online,
-        )
-        .await;
+        server.abort();
+    }
+}
+
+mod worker_supervision {
+    use std::time::Duration;
+    use zaino_serve::server::{
+        error::WorkerError,
+        metrics::RpcMetrics,
+        supervisor::{reap_workers, RequestContext, WorkerExit},
+    };
+
+    /// Spawns a worker stand-in that serves until aborted.
+    fn running_worker() -> tokio::task::JoinHandle<Result<(), WorkerError>> {
+        tokio::spawn(async {
+            std::future::pending::<()>().await;
+            Ok(())
+        })
+    }
+
+    /// Waits for the task to end.
+    async fn wait_finished(handle: &tokio::task::JoinHandle<Result<(), WorkerError>>) {
+        while !handle.is_finished() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
     }
 
     #[tokio::test]
-    async fn send_to_orchard() {
-        let online = Arc::new(AtomicBool::new(true));
-        let (test_manager, regtest_handler, _indexer_handler) =
-            TestManager::launch(online.clone()).await;
-        let zingo_client = test_manager.build_lightclient().await;
+    async fn panicked_worker_is_reported_and_replaced() {
+        let contexts = vec![RequestContext::default(); 3];
+        let mut handles = vec![running_worker(), running_worker(), running_worker()];
+        let context = contexts[1].clone();
+        handles[1] = tokio::spawn(async move {
+            context.set("Nym GetLightdInfo request");
+            panic!("deliberate worker panic");
+        });
+        wait_finished(&handles[1]).await;
 
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "unified").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
+        let failures = reap_workers(&mut handles, &contexts).await;
+        assert_eq!(failures.len(), 1);
+        let failure = &failures[0];
+        println!("[TEST LOG] {}.", failure);
+        assert_eq!(failure.worker_id, 1);
+        assert!(
+            matches!(&failure.exit, WorkerExit::Panicked(message) if message == "deliberate worker panic")
+        );
+        assert_eq!(
+            failure.to_string(),
+            "Worker 1 panicked while serving Nym GetLightdInfo request: deliberate worker panic"
+        );
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.orchard_balance.unwrap(), 1_875_000_000);
+        // The server replaces each reaped worker, restoring the pool size.
+        let metrics = RpcMetrics::new();
+        for failure in failures {
+            metrics.record_worker_restart();
+            handles[failure.worker_id] = running_worker();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(handles.iter().all(|handle| !handle.is_finished()));
+        assert!(reap_workers(&mut handles, &contexts).await.is_empty());
+        assert!(metrics.render().contains("zaino_worker_restarts_total 1"));
 
-        drop_test_manager(
-            Some(test_manager.temp_conf_dir.path().to_path_buf()),
-            regtest_handler,
-            online,
-        )
-        .await;
+        for handle in handles {
+            handle.abort();
+        }
     }
 
     #[tokio::test]
-    async fn send_to_sapling() {
-        let online = Arc::new(AtomicBool::new(true));
-        let (test_manager, regtest_handler, _indexer_handler) =
-            TestManager::launch(online.clone()).await;
-        let zingo_client = test_manager.build_lightclient().await;
+    async fn failed_and_idle_workers_are_reported() {
+        let contexts = vec![RequestContext::default(); 2];
+        contexts[0].set("TCP connection queued for 0ms");
+        contexts[1].set("TCP connection queued for 0ms");
+        contexts[1].clear();
+        let mut handles = vec![
+            tokio::spawn(async { Err(WorkerError::WorkerPoolFull) }),
+            tokio::spawn(async { Ok(()) }),
+        ];
+        for handle in handles.iter() {
+            wait_finished(handle).await;
+        }
 
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "sapling").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
+        let failures = reap_workers(&mut handles, &contexts).await;
+        assert_eq!(failures.len(), 2);
+        assert!(matches!(
+            failures[0].exit,
+            WorkerExit::Failed(WorkerError::WorkerPoolFull)
+        ));
+        assert_eq!(
+            failures[0].to_string(),
+            "Worker 0 failed while serving TCP connection queued for 0ms: Worker Pool Full"
+        );
+        assert!(matches!(failures[1].exit, WorkerExit::Stopped));
+        assert_eq!(failures[1].request, None);
+        assert_eq!(
+            failures[1].to_string(),
+            "Worker 1 stopped while serving no request"
+        );
+    }
+}
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.sapling_balance.unwrap(), 250_000);
+mod cache_warming {
+    use super::*;
+    use zaino_fetch::{
+        chain::{
+            cache::BlockCache,
+            fetcher::{BlockFetcher, FetchOutcome},
+        },
+        primitives::chain::NetworkKind,
+    };
+    use zaino_testutils::fetch_metrics;
+    use zainodlib::{config::IndexerConfig, indexer::Indexer};
 
-        drop_test_manager(
-            Some(test_manager.temp_conf_dir.path().to_path_buf()),
-            regtest_handler,
-            online,
-        )
-        .await;
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
     }
 
     #[tokio::test]
-    async fn send_to_transparent() {
+    async fn warm_fills_range_from_node() {
         let online = Arc::new(AtomicBool::new(true));
-        let (test_manager, regtest_handler, _indexer_handler) =
-            TestManager::launch(online.clone()).await;
-        let zingo_client = test_manager.build_lightclient().await;
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(10).unwrap();
+        let zebrad_uri = test_manager.test_and_return_zebrad_uri().await;
+        let node_auth = test_manager.node_auth();
 
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "transparent").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
+        let block_cache = BlockCache::default();
+        let fetcher = BlockFetcher::default();
+        let outcome = block_cache
+            .warm(
+                &zebrad_uri,
+                &node_auth,
+                1..=5,
+                &fetcher,
+                &AtomicBool::new(true),
+            )
+            .await;
+        assert_eq!(outcome, FetchOutcome::Completed);
+        assert_eq!(fetcher.progress().fetched(), 5);
+        for height in 1..=5 {
+            assert!(block_cache.contains(height).await);
+        }
+        assert!(!block_cache.contains(6).await);
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.transparent_balance.unwrap(), 250_000);
+        // Heights already held in the cache are not fetched again.
+        let fetcher = BlockFetcher::default();
+        let outcome = block_cache
+            .warm(
+                &zebrad_uri,
+                &node_auth,
+                3..=7,
+                &fetcher,
+                &AtomicBool::new(true),
+            )
+            .await;
+        assert_eq!(outcome, FetchOutcome::Completed);
+        assert_eq!(fetcher.progress().total(), 2);
+        assert_eq!(block_cache.len().await, 7);
 
         drop_test_manager(
             Some(test_manager.temp_conf_dir.path().to_path_buf()),
@@ -126,46 +7793,42 @@ mod wallet_basic {
     }
 
     #[tokio::test]
-    async fn send_to_multiple() {
+    async fn startup_warming_reports_progress() {
         let online = Arc::new(AtomicBool::new(true));
-        let (test_manager, regtest_handler, _indexer_handler) =
-            TestManager::launch(online.clone()).await;
-        let zingo_client = test_manager.build_lightclient().await;
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        test_manager.regtest_manager.generate_n_blocks(10).unwrap();
 
-        test_manager.regtest_manager.generate_n_blocks(2).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "unified").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "sapling").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "transparent").await,
-                250_000,
-                None,
-            )])
+        let metrics_port = free_port();
+        let config = IndexerConfig {
+            network: NetworkKind::Regtest,
+            listen_port: Some(free_port()),
+            nym_active: false,
+            nym_conf_path: None,
+            lightwalletd_port: free_port(),
+            zebrad_port: test_manager.zebrad_port,
+            metrics_port: Some(metrics_port),
+            cache_warm_range: Some(1..=5),
+            ..IndexerConfig::default()
+        };
+        let _warming_handler = Indexer::start_indexer_service(config, online.clone())
             .await
             .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.orchard_balance.unwrap(), 2_499_500_000);
-        assert_eq!(balance.sapling_balance.unwrap(), 250_000);
-        assert_eq!(balance.transparent_balance.unwrap(), 250_000);
+        let mut metrics = String::new();
+        for _ in 0..50 {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            metrics = fetch_metrics(metrics_port).await;
+            if metrics.contains("zaino_cache_warm_complete 1") {
+                break;
+            }
+        }
+        println!("[TEST LOG] Metrics after warming:\n{}", metrics);
+        assert!(metrics.contains("zaino_cache_warm_complete 1"));
+        assert!(metrics.contains("zaino_cache_warm_blocks 5"));
+        assert!(metrics.contains("zaino_cache_warm_blocks_fetched 5"));
 
         drop_test_manager(
             Some(test_manager.temp_conf_dir.path().to_path_buf()),
@@ -174,87 +7837,222 @@ mod wallet_basic {
         )
         .await;
     }
+}
+
+mod chain_utils {
+    use std::io::Cursor;
+    use zaino_fetch::chain::utils::{read_u256, read_u512};
+
+    #[test]
+    fn read_fixed_size_fields() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut cursor = Cursor::new(data.as_slice());
+        assert_eq!(read_u256(&mut cursor, "u256").unwrap()[..], data[0..32]);
+        assert_eq!(read_u512(&mut cursor, "u512").unwrap()[..], data[32..96]);
+        assert_eq!(cursor.position(), 96);
+    }
 
-    #[tokio::test]
-    async fn shield_from_sapling() {
-        let online = Arc::new(AtomicBool::new(true));
-        let (test_manager, regtest_handler, _indexer_handler) =
-            TestManager::launch(online.clone()).await;
-        let zingo_client = test_manager.build_lightclient().await;
+    #[test]
+    fn read_fixed_size_fields_past_eof() {
+        let data = [0u8; 40];
+        let mut cursor = Cursor::new(&data[..]);
+        read_u256(&mut cursor, "u256").unwrap();
+        let error = read_u256(&mut cursor, "Error reading hash").unwrap_err();
+        assert!(error.to_string().contains("Error reading hash"));
+        let mut cursor = Cursor::new(&data[..]);
+        assert!(read_u512(&mut cursor, "Error reading signature").is_err());
+    }
+}
 
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "sapling").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
+mod startup_check {
+    use super::*;
+    use zaino_fetch::primitives::chain::NetworkKind;
+    use zainodlib::{
+        check::{CheckOutcome, CheckReport},
+        config::IndexerConfig,
+    };
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.sapling_balance.unwrap(), 250_000);
+    validator_matrix! {
+        async fn check_passes_against_running_node(validator: ValidatorKind) {
+            let harness =
+                TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+            let config = IndexerConfig {
+                network: NetworkKind::Regtest,
+                nym_active: false,
+                listen_port: Some(harness.indexer_port),
+                lightwalletd_port: harness.lightwalletd_port,
+                zebrad_port: harness.zebrad_port,
+                node_auth: NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+                ..IndexerConfig::default()
+            };
 
-        zingo_client
-            .do_shield(&[Pool::Sapling.into()], None)
-            .await
-            .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
+            let report = CheckReport::for_config(&config).await;
+            println!("[TEST LOG] Check report:\n{}", report);
+            assert_eq!(report.exit_code(), 0);
+            assert!(matches!(report.outcome("node connection"), Some(CheckOutcome::Pass(_))));
+            assert!(matches!(report.outcome("node version"), Some(CheckOutcome::Pass(_))));
+            assert_eq!(
+                report.outcome("node chain"),
+                Some(&CheckOutcome::Pass("regtest".to_string()))
+            );
+            let rendered = report.to_string();
+            assert!(rendered.contains("[PASS] config: valid"));
+            assert!(rendered.contains("[PASS] node chain: regtest"));
+            assert!(rendered.ends_with("Check passed."));
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.sapling_balance.unwrap(), 0);
-        assert_eq!(balance.orchard_balance.unwrap(), 2_500_000_000);
+            // The check binds nothing, zingo-indexer keeps serving.
+            harness.build_grpc_client().await;
 
-        drop_test_manager(
-            Some(test_manager.temp_conf_dir.path().to_path_buf()),
-            regtest_handler,
-            online,
-        )
-        .await;
+            harness.teardown().await;
+        }
     }
 
     #[tokio::test]
-    async fn shield_from_transparent() {
-        let online = Arc::new(AtomicBool::new(true));
-        let (test_manager, regtest_handler, _indexer_handler) =
-            TestManager::launch(online.clone()).await;
-        let zingo_client = test_manager.build_lightclient().await;
+    async fn check_fails_on_bad_config() {
+        let config_path =
+            std::env::temp_dir().join(format!("zaino_check_bad_{}.toml", std::process::id()));
 
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "transparent").await,
-                250_000,
-                None,
-            )])
-            .await
+        // An invalid value is reported by config validation, the node is not probed.
+        std::fs::write(
+            &config_path,
+            include_str!("../../zainod/zindexer.toml")
+                .replace("nym_active = true", "nym_active = false")
+                .replace("zebrad_port = 18232", "zebrad_port = 0"),
+        )
+        .unwrap();
+        let report = CheckReport::for_file(&config_path).await;
+        println!("[TEST LOG] Check report:\n{}", report);
+        assert_eq!(report.exit_code(), 1);
+        assert!(matches!(
+            report.outcome("config file"),
+            Some(CheckOutcome::Pass(_))
+        ));
+        assert!(report
+            .to_string()
+            .contains("[FAIL] config: Configuration error: Full node port must be non-zero."));
+        assert!(matches!(
+            report.outcome("node connection"),
+            Some(CheckOutcome::Skip(_))
+        ));
+
+        // A file that does not parse fails to load.
+        std::fs::write(&config_path, "network = ").unwrap();
+        let report = CheckReport::for_file(&config_path).await;
+        println!("[TEST LOG] Check report:\n{}", report);
+        assert_eq!(report.exit_code(), 1);
+        assert!(report.to_string().contains("[FAIL] config file: "));
+        assert!(report
+            .to_string()
+            .ends_with("Check failed, 1 of 1 checks failed."));
+
+        std::fs::remove_file(config_path).unwrap();
+    }
+}
+
+mod grpc_addr_stdout {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Command, Stdio},
+        time::Duration,
+    };
+    use zaino_proto::proto::service::{compact_tx_streamer_client::CompactTxStreamerClient, Empty};
+    use zainodlib::indexer::GRPC_ADDR_KEY;
+
+    /// Builds the zainod binary and returns its path.
+    pub(super) fn zainod_binary() -> std::path::PathBuf {
+        let output = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+            .args([
+                "build",
+                "--quiet",
+                "--package",
+                "zainod",
+                "--bin",
+                "zainod",
+                "--no-default-features",
+                "--message-format=json",
+            ])
+            .output()
             .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
+        assert!(output.status.success(), "Failed to build zainod");
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .find_map(|message| message["executable"].as_str().map(std::path::PathBuf::from))
+            .expect("cargo did not report the zainod executable")
+    }
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.transparent_balance.unwrap(), 250_000);
+    #[tokio::test]
+    async fn zainod_prints_os_assigned_grpc_addr() {
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = TestManagerBuilder::new()
+            .watch_only()
+            .launch(online.clone())
+            .await;
+        let config_path = test_manager
+            .temp_conf_dir
+            .path()
+            .join("zindexer_port_0.toml");
+        std::fs::write(
+            &config_path,
+            include_str!("../../zainod/zindexer.toml")
+                .replace("network = \"Testnet\"", "network = \"Regtest\"")
+                .replace("nym_active = true", "nym_active = false")
+                .replace("listen_port = 8137", "listen_port = 0")
+                .replace(
+                    "lightwalletd_port = 9067",
+                    &format!("lightwalletd_port = {}", test_manager.lightwalletd_port),
+                )
+                .replace(
+                    "zebrad_port = 18232",
+                    &format!("zebrad_port = {}", test_manager.zebrad_port),
+                ),
+        )
+        .unwrap();
 
-        zingo_client
-            .do_shield(&[Pool::Transparent.into()], None)
-            .await
+        // Runs the zainod binary as a subprocess, as a script or harness would.
+        let mut zainod = Command::new(zainod_binary())
+            .arg("--config")
+            .arg(&config_path)
+            .stdout(Stdio::piped())
+            .spawn()
             .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
+        let stdout = zainod.stdout.take().unwrap();
+        let grpc_addr = tokio::time::timeout(
+            Duration::from_secs(60),
+            tokio::task::spawn_blocking(move || {
+                BufReader::new(stdout)
+                    .lines()
+                    .map_while(Result::ok)
+                    .find_map(|line| {
+                        line.strip_prefix(&format!("{}=", GRPC_ADDR_KEY))
+                            .map(|addr| addr.parse::<std::net::SocketAddr>().unwrap())
+                    })
+            }),
+        )
+        .await
+        .expect("zainod did not print its gRPC address")
+        .unwrap()
+        .expect("zainod exited without printing its gRPC address");
+        println!("[TEST LOG] zainod gRPC address: {}.", grpc_addr);
+        assert_ne!(grpc_addr.port(), 0);
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.transparent_balance.unwrap(), 0);
-        assert_eq!(balance.orchard_balance.unwrap(), 2_500_000_000);
+        let mut grpc_client = loop {
+            match CompactTxStreamerClient::connect(format!("http://{}", grpc_addr)).await {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        };
+        let lightd_info = grpc_client
+            .get_lightd_info(Empty {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(lightd_info.chain_name, "regtest");
 
+        zainod.kill().unwrap();
+        zainod.wait().unwrap();
         drop_test_manager(
             Some(test_manager.temp_conf_dir.path().to_path_buf()),
             regtest_handler,
@@ -262,164 +8060,407 @@ mod wallet_basic {
         )
         .await;
     }
+}
 
-    #[tokio::test]
-    async fn shield_from_multiple() {
-        let online = Arc::new(AtomicBool::new(true));
-        let (test_manager, regtest_handler, _indexer_handler) =
-            TestManager::launch(online.clone()).await;
-        let zingo_client = test_manager.build_lightclient().await;
+mod run_blocking {
+    use super::*;
+    use std::time::Duration;
+    use zaino_fetch::primitives::chain::NetworkKind;
+    use zaino_proto::proto::service::{compact_tx_streamer_client::CompactTxStreamerClient, Empty};
+    use zainodlib::{config::IndexerConfig, indexer::Indexer};
 
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "sapling").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "transparent").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
+    #[test]
+    fn run_blocking_serves_and_shuts_down_on_sigterm() {
+        // run_blocking builds its own runtime, the test manager and client run on a separate one.
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let online = Arc::new(AtomicBool::new(true));
+        let (test_manager, regtest_handler, _indexer_handler) = runtime.block_on(
+            TestManagerBuilder::new()
+                .watch_only()
+                .launch(online.clone()),
+        );
+        let listen_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let config = IndexerConfig {
+            network: NetworkKind::Regtest,
+            nym_active: false,
+            listen_port: Some(listen_port),
+            lightwalletd_port: test_manager.lightwalletd_port,
+            zebrad_port: test_manager.zebrad_port,
+            node_auth: NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+            metrics_port: None,
+            runtime_worker_threads: Some(2),
+            ..IndexerConfig::default()
+        };
+        let indexer = std::thread::spawn(move || Indexer::run_blocking(config));
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.sapling_balance.unwrap(), 250_000);
-        assert_eq!(balance.transparent_balance.unwrap(), 250_000);
+        runtime.block_on(async {
+            let mut grpc_client = loop {
+                match CompactTxStreamerClient::connect(format!("http://127.0.0.1:{}", listen_port))
+                    .await
+                {
+                    Ok(client) => break client,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+                }
+            };
+            let lightd_info = grpc_client
+                .get_lightd_info(Empty {})
+                .await
+                .unwrap()
+                .into_inner();
+            println!("[TEST LOG] Lightd info: {:?}.", lightd_info);
+            assert_eq!(lightd_info.chain_name, "regtest");
+        });
 
-        zingo_client
-            .do_shield(&[Pool::Sapling.into(), Pool::Transparent.into()], None)
-            .await
+        // The indexer's SIGTERM handler is installed before it serves, so the signal does not end the test process.
+        let status = std::process::Command::new("kill")
+            .args(["-TERM", &std::process::id().to_string()])
+            .status()
             .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
-
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.sapling_balance.unwrap(), 0);
-        assert_eq!(balance.transparent_balance.unwrap(), 0);
-        assert_eq!(balance.orchard_balance.unwrap(), 2_500_000_000);
+        assert!(status.success());
+        let result = indexer.join().unwrap();
+        println!("[TEST LOG] Indexer returned: {:?}.", result);
+        assert!(result.is_ok());
+        assert!(std::net::TcpStream::connect(("127.0.0.1", listen_port)).is_err());
 
-        drop_test_manager(
+        runtime.block_on(drop_test_manager(
             Some(test_manager.temp_conf_dir.path().to_path_buf()),
             regtest_handler,
             online,
+        ));
+    }
+}
+
+mod zainod_config {
+    use std::path::PathBuf;
+    use zaino_fetch::jsonrpc::connector::NodeAuth;
+    use zaino_fetch::primitives::chain::NetworkKind;
+    use zainodlib::{
+        config::{
+            config_file_path, default_listen_port, default_node_port,
+            migration::{migrate_config, ConfigDeprecation},
+            try_load_config, IndexerConfig, CONFIG_PATH_ENV, CONFIG_VERSION,
+        },
+        error::IndexerError,
+    };
+
+    #[test]
+    fn zaino_config_env_var_overrides_config_arg() {
+        let config_path =
+            std::env::temp_dir().join(format!("zaino_config_env_{}.toml", std::process::id()));
+        std::fs::write(
+            &config_path,
+            include_str!("../../zainod/zindexer.toml")
+                .replace("network = \"Testnet\"", "network = \"Regtest\"")
+                .replace("listen_port = 8137", "listen_port = 8237")
+                .replace("max_queue_size = 1024", "max_queue_size = 4321"),
         )
-        .await;
+        .unwrap();
+
+        std::env::set_var(CONFIG_PATH_ENV, &config_path);
+        let resolved = config_file_path(Some(PathBuf::from("./does/not/exist.toml")));
+        std::env::remove_var(CONFIG_PATH_ENV);
+        assert_eq!(resolved, config_path);
+
+        let config = try_load_config(&resolved).unwrap();
+        println!(
+            "[TEST LOG] Config loaded from {}: {:?}.",
+            resolved.display(),
+            config
+        );
+        assert_eq!(config.network, NetworkKind::Regtest);
+        assert_eq!(config.listen_port, Some(8237));
+        assert_eq!(config.max_queue_size, 4321);
+        assert_eq!(config.config_path, Some(config_path.clone()));
+
+        // Without the env var the config arg, then the default path, is used.
+        assert_eq!(
+            config_file_path(Some(PathBuf::from("./zindexer.toml"))),
+            PathBuf::from("./zindexer.toml")
+        );
+        assert_eq!(
+            config_file_path(None),
+            PathBuf::from(zainodlib::config::DEFAULT_CONFIG_PATH)
+        );
+
+        std::fs::remove_file(config_path).unwrap();
     }
 
-    #[tokio::test]
-    async fn sync_full_batch() {
-        let online = Arc::new(AtomicBool::new(true));
-        let (test_manager, regtest_handler, _indexer_handler) =
-            TestManager::launch(online.clone()).await;
-        let zingo_client = test_manager.build_lightclient().await;
+    #[test]
+    fn default_config_for_each_network_is_accepted() {
+        for network in [
+            NetworkKind::Mainnet,
+            NetworkKind::Testnet,
+            NetworkKind::Regtest,
+        ] {
+            let config = IndexerConfig::default_for_network(network);
+            assert_eq!(config.network, network);
+            assert!(config.check_config().is_ok());
+        }
+    }
 
-        test_manager.regtest_manager.generate_n_blocks(2).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
+    #[test]
+    fn node_failure_window_must_fit_the_probes() {
+        let mut config = IndexerConfig::default_for_network(NetworkKind::Regtest);
+        config.node_failure_threshold = 3;
+        config.node_probe_interval_ms = 1000;
+        config.node_failure_window_ms = 2000;
+        assert!(config.check_config().is_ok());
+        config.node_failure_window_ms = 1999;
+        assert!(config.check_config().is_err());
 
-        test_manager.regtest_manager.generate_n_blocks(30).unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "unified").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(30).unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "sapling").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(30).unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "transparent").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        test_manager.regtest_manager.generate_n_blocks(30).unwrap();
+        // Probe durations too large to compute are rejected rather than overflowing.
+        config.node_failure_window_ms = u64::MAX;
+        config.node_failure_threshold = u32::MAX;
+        config.node_probe_interval_ms = u64::MAX / 2;
+        assert!(config.check_config().is_err());
+    }
 
-        println!("[TEST LOG] syncing full batch.");
-        zingo_client.do_sync(false).await.unwrap();
+    #[test]
+    fn network_defaults_to_testnet() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("zindexer.toml");
+        std::fs::write(
+            &config_path,
+            include_str!("../../zainod/zindexer.toml").replace("network = \"Testnet\"\n", ""),
+        )
+        .unwrap();
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.orchard_balance.unwrap(), 76_874_500_000);
-        assert_eq!(balance.sapling_balance.unwrap(), 250_000);
-        assert_eq!(balance.transparent_balance.unwrap(), 250_000);
+        let config = try_load_config(&config_path).unwrap();
+        assert_eq!(config.network, NetworkKind::Testnet);
+        assert_eq!(config.zebrad_port, 18232);
+    }
 
-        drop_test_manager(
-            Some(test_manager.temp_conf_dir.path().to_path_buf()),
-            regtest_handler,
-            online,
+    #[test]
+    fn unset_ports_default_to_the_configured_network() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("zindexer.toml");
+        std::fs::write(
+            &config_path,
+            include_str!("../../zainod/zindexer.toml")
+                .replace("network = \"Testnet\"", "network = \"Mainnet\"")
+                .replace("listen_port = 8137\n", "")
+                .replace("zebrad_port = 18232\n", ""),
         )
-        .await;
+        .unwrap();
+
+        let config = try_load_config(&config_path).unwrap();
+        println!("[TEST LOG] Loaded config:\n{}", config.redacted());
+        assert_eq!(config.network, NetworkKind::Mainnet);
+        assert_eq!(
+            config.listen_port,
+            Some(default_listen_port(NetworkKind::Mainnet))
+        );
+        assert_eq!(config.zebrad_port, default_node_port(NetworkKind::Mainnet));
+        assert_eq!(
+            IndexerConfig::default().listen_port,
+            IndexerConfig::default_for_network(NetworkKind::Testnet).listen_port
+        );
     }
 
-    #[tokio::test]
-    async fn monitor_unverified_mempool() {
-        let online = Arc::new(AtomicBool::new(true));
-        let (test_manager, regtest_handler, _indexer_handler) =
-            TestManager::launch(online.clone()).await;
-        let zingo_client = test_manager.build_lightclient().await;
+    #[test]
+    fn rpc_max_wait_defaults_when_absent() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("zindexer.toml");
+        std::fs::write(
+            &config_path,
+            include_str!("../../zainod/zindexer.toml").replace("rpc_max_wait_ms = 5000\n", ""),
+        )
+        .unwrap();
 
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "sapling").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
-        zingo_client
-            .do_send(vec![(
-                &get_zingo_address(&zingo_client, "sapling").await,
-                250_000,
-                None,
-            )])
-            .await
-            .unwrap();
+        let config = try_load_config(&config_path).unwrap();
+        assert_eq!(
+            config.rpc_max_wait_ms,
+            IndexerConfig::default().rpc_max_wait_ms
+        );
+    }
 
-        start_zingo_mempool_monitor(&zingo_client).await;
+    #[test]
+    fn conflicting_ports_are_rejected() {
+        let mut config = IndexerConfig::default_for_network(NetworkKind::Regtest);
+        config.listen_port = Some(config.zebrad_port);
+        assert!(config.check_config().is_err());
 
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.unverified_sapling_balance.unwrap(), 500_000);
+        let mut config = IndexerConfig::default_for_network(NetworkKind::Regtest);
+        config.lightwalletd_port = config.zebrad_port;
+        assert!(config.check_config().is_err());
+    }
 
-        test_manager.regtest_manager.generate_n_blocks(1).unwrap();
-        zingo_client.do_sync(false).await.unwrap();
-        let balance = zingo_client.do_balance().await;
-        println!("[TEST LOG] zingo_client balance: \n{:#?}.", balance);
-        assert_eq!(balance.verified_sapling_balance.unwrap(), 500_000);
+    #[test]
+    fn redacted_config_hides_secrets() {
+        let mut config = IndexerConfig::default_for_network(NetworkKind::Regtest);
+        config.node_auth = NodeAuth::user_pass("zaino_user", "hunter2_password");
 
-        drop_test_manager(
-            Some(test_manager.temp_conf_dir.path().to_path_buf()),
-            regtest_handler,
-            online,
+        let displayed = config.redacted().to_string();
+        println!("[TEST LOG] Redacted config:\n{}", displayed);
+        assert!(!displayed.contains("hunter2_password"));
+        assert!(displayed.contains("password = \"***\""));
+        assert!(displayed.contains("zaino_user"));
+        assert!(!format!("{:?}", config).contains("hunter2_password"));
+        assert!(!format!("{:?}", config.redacted()).contains("hunter2_password"));
+        assert_eq!(
+            config.node_auth,
+            NodeAuth::user_pass("zaino_user", "hunter2_password")
+        );
+    }
+
+    /// Current config file, as shipped with zainod.
+    const CONFIG_FILE: &str = include_str!("../../zainod/zindexer.toml");
+
+    /// Returns [`CONFIG_FILE`] as written before config versions, with node credentials given as `node_user` and
+    /// `node_password`.
+    fn unversioned_config_file() -> String {
+        CONFIG_FILE.replace("config_version = 1\n", "").replace(
+            "node_auth = { method = \"UserPass\", user = \"xxxxxx\", password = \"xxxxxx\" }",
+            "node_user = \"zaino_user\"\nnode_password = \"hunter2_password\"",
         )
-        .await;
+    }
+
+    /// Writes `contents` to a config file and loads it.
+    fn load(contents: &str) -> Result<IndexerConfig, IndexerError> {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("zindexer.toml");
+        std::fs::write(&config_path, contents).unwrap();
+        try_load_config(&config_path)
+    }
+
+    /// Returns the deprecated settings replaced when migrating `contents`.
+    fn deprecations(contents: &str) -> Result<Vec<ConfigDeprecation>, IndexerError> {
+        migrate_config(&mut toml::from_str(contents).unwrap())
+    }
+
+    #[test]
+    fn current_config_file_is_not_migrated() {
+        assert!(CONFIG_FILE.contains(&format!("config_version = {}\n", CONFIG_VERSION)));
+        assert_eq!(deprecations(CONFIG_FILE).unwrap(), []);
+        assert_eq!(load(CONFIG_FILE).unwrap().config_version, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn unversioned_config_file_is_migrated() {
+        let migrated = load(&unversioned_config_file()).unwrap();
+        let current = load(&CONFIG_FILE.replace(
+            "user = \"xxxxxx\", password = \"xxxxxx\"",
+            "user = \"zaino_user\", password = \"hunter2_password\"",
+        ))
+        .unwrap();
+        assert_eq!(
+            migrated.node_auth,
+            NodeAuth::user_pass("zaino_user", "hunter2_password")
+        );
+        assert_eq!(migrated.config_version, CONFIG_VERSION);
+        // Every other setting is loaded as from the current format.
+        assert_eq!(
+            migrated.redacted().to_string(),
+            current.redacted().to_string()
+        );
+    }
+
+    /// Config file shipped with zainod before config versions, verbatim.
+    const UNVERSIONED_CONFIG_FILE: &str = include_str!("fixtures/zindexer_unversioned.toml");
+
+    #[test]
+    fn shipped_unversioned_config_file_is_migrated() {
+        let messages: Vec<String> = deprecations(UNVERSIONED_CONFIG_FILE)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        println!("[TEST LOG] Deprecations: {:#?}.", messages);
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("`node_user` and `node_password` are deprecated"));
+
+        let config = load(UNVERSIONED_CONFIG_FILE).unwrap();
+        println!("[TEST LOG] Migrated config:\n{}", config.redacted());
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        // Settings held in the file are kept.
+        assert!(config.tcp_active);
+        assert_eq!(config.listen_port, Some(8137));
+        assert!(config.nym_active);
+        assert_eq!(config.nym_conf_path.as_deref(), Some("/tmp/indexer/nym"));
+        assert_eq!(config.lightwalletd_port, 9067);
+        assert_eq!(config.zebrad_port, 18232);
+        assert_eq!(config.node_auth, NodeAuth::user_pass("xxxxxx", "xxxxxx"));
+        assert_eq!(config.max_queue_size, 1024);
+        assert_eq!(config.max_worker_pool_size, 64);
+        assert_eq!(config.idle_worker_pool_size, 4);
+        // Settings added since take their defaults.
+        let default = IndexerConfig::default();
+        assert_eq!(config.network, NetworkKind::Testnet);
+        assert_eq!(
+            config.max_concurrent_streams_global,
+            default.max_concurrent_streams_global
+        );
+        assert_eq!(config.rpc_max_wait_ms, default.rpc_max_wait_ms);
+        assert_eq!(config.request_log_level, default.request_log_level);
+    }
+
+    #[test]
+    fn deprecations_name_the_change_to_make() {
+        let messages: Vec<String> = deprecations(&unversioned_config_file())
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        println!("[TEST LOG] Deprecations: {:#?}.", messages);
+        assert_eq!(
+            messages,
+            [
+                "`node_user` and `node_password` are deprecated, replace them with \
+                 `node_auth = { method = \"UserPass\", user = \"<node_user>\", password = \"<node_password>\" }`",
+                "add `config_version = 1`",
+            ]
+        );
+        assert!(!messages.concat().contains("hunter2_password"));
+
+        // A file without deprecated fields only lacks its version.
+        assert_eq!(
+            deprecations(&CONFIG_FILE.replace("config_version = 1\n", ""))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn contradictory_or_unknown_settings_are_rejected() {
+        let old = unversioned_config_file();
+        for (case, contents) in [
+            (
+                "node_auth and node_user",
+                old.replace("node_user", "node_auth = { method = \"None\" }\nnode_user"),
+            ),
+            (
+                "node_user without node_password",
+                old.replace("node_password = \"hunter2_password\"", ""),
+            ),
+            (
+                "newer config version",
+                CONFIG_FILE.replace("config_version = 1", "config_version = 2"),
+            ),
+            (
+                "invalid config version",
+                CONFIG_FILE.replace("config_version = 1", "config_version = \"1\""),
+            ),
+            (
+                "unknown field",
+                CONFIG_FILE.replace(
+                    "max_queue_size = 1024",
+                    "max_queue_size = 1024\nmax_queue_length = 1024",
+                ),
+            ),
+        ] {
+            let error = load(&contents).unwrap_err();
+            println!("[TEST LOG] {}: {}.", case, error);
+            assert!(matches!(error, IndexerError::ConfigError(_)), "{}", case);
+        }
     }
 }
 
+#[cfg(feature = "nym")]
 mod nym {
     // TODO: Build nym enhanced zingolib version using zingo-rpc::walletrpc::service.
 }
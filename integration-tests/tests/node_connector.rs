@@ -0,0 +1,174 @@
+//! JsonRpcConnector tests against an in-process mock node.
+//!
+//! Cover the connector's error mapping, retries and timeouts without launching a validator. Run with
+//! `cargo test --test node_connector`.
+
+#![forbid(unsafe_code)]
+
+use std::time::{Duration, Instant};
+
+use zaino_fetch::jsonrpc::{
+    connector::{probe_node_uri, JsonRpcConnector, NodeAuth},
+    error::JsonRpcConnectorError,
+};
+use zaino_testutils::{MockNode, MockResponse};
+
+/// Returns the result of a node's `getinfo` call.
+fn info() -> MockResponse {
+    MockResponse::Result(serde_json::json!({ "build": "v0.0.0", "subversion": "/MockNode:0.0.0/" }))
+}
+
+/// Returns the node's response when its work queue is full.
+fn work_queue_full() -> MockResponse {
+    MockResponse::Http {
+        status: 500,
+        body: "Work queue depth exceeded".to_string(),
+    }
+}
+
+/// Returns a connector for `node`, sending no credentials.
+async fn connector(node: &MockNode) -> JsonRpcConnector {
+    JsonRpcConnector::new(node.uri().clone(), NodeAuth::None).await
+}
+
+/// Returns the error of a `getinfo` call answered with `response`.
+async fn get_info_error(response: MockResponse) -> JsonRpcConnectorError {
+    let node = MockNode::spawn().await;
+    node.respond("getinfo", response);
+    connector(&node).await.get_info().await.unwrap_err()
+}
+
+#[tokio::test]
+async fn node_errors_map_to_status_codes() {
+    for (response, expected) in [
+        (
+            MockResponse::error(-8, "Block height out of range"),
+            tonic::Code::NotFound,
+        ),
+        (
+            MockResponse::error(-5, "No such mempool or blockchain transaction."),
+            tonic::Code::NotFound,
+        ),
+        (
+            MockResponse::error(-22, "TX decode failed"),
+            tonic::Code::InvalidArgument,
+        ),
+        (
+            MockResponse::error(-26, "absurdly-high-fee"),
+            tonic::Code::Internal,
+        ),
+        (
+            MockResponse::error(-32601, "Method not found"),
+            tonic::Code::Internal,
+        ),
+        (
+            MockResponse::Http {
+                status: 401,
+                body: String::new(),
+            },
+            tonic::Code::Unauthenticated,
+        ),
+        (
+            MockResponse::Http {
+                status: 200,
+                body: "not json".to_string(),
+            },
+            tonic::Code::Internal,
+        ),
+        (MockResponse::Drop, tonic::Code::Unavailable),
+    ] {
+        let error = get_info_error(response.clone()).await;
+        println!("[TEST LOG] {:?}: {}.", response, error);
+        assert_eq!(error.to_grpc_status().code(), expected, "{:?}", response);
+    }
+}
+
+#[tokio::test]
+async fn node_errors_are_classified() {
+    assert!(
+        get_info_error(MockResponse::error(-8, "Block height out of range"))
+            .await
+            .is_not_found()
+    );
+    assert!(
+        get_info_error(MockResponse::error(-26, "absurdly-high-fee"))
+            .await
+            .is_fee_rejection()
+    );
+    assert!(get_info_error(MockResponse::Drop)
+        .await
+        .is_connectivity_error());
+    assert!(matches!(
+        get_info_error(MockResponse::Http {
+            status: 401,
+            body: String::new(),
+        })
+        .await,
+        JsonRpcConnectorError::Unauthorized
+    ));
+    assert!(matches!(
+        get_info_error(MockResponse::Http {
+            status: 200,
+            body: "not json".to_string(),
+        })
+        .await,
+        JsonRpcConnectorError::SerdeJsonError(_)
+    ));
+}
+
+#[tokio::test]
+async fn unreachable_node_is_a_connectivity_error() {
+    let node = MockNode::spawn().await;
+    node.respond("getinfo", info());
+    let connector = connector(&node).await;
+    connector.get_info().await.unwrap();
+
+    node.kill().await;
+    let error = connector.get_info().await.unwrap_err();
+    assert!(error.is_connectivity_error());
+    assert_eq!(error.to_grpc_status().code(), tonic::Code::Unavailable);
+}
+
+#[tokio::test]
+async fn full_work_queue_is_retried() {
+    let node = MockNode::spawn().await;
+    node.respond("getinfo", info())
+        .respond_once("getinfo", work_queue_full())
+        .respond_once("getinfo", work_queue_full());
+    let connector = connector(&node).await;
+    connector.get_info().await.unwrap();
+    assert_eq!(node.call_count("getinfo"), 3);
+
+    // The connector gives up after five attempts.
+    node.respond("getinfo", work_queue_full());
+    let error = connector.get_info().await.unwrap_err();
+    assert!(matches!(error, JsonRpcConnectorError::CustomError(_)));
+    assert_eq!(node.call_count("getinfo"), 8);
+}
+
+#[tokio::test]
+async fn slow_node_probe_times_out() {
+    let node = MockNode::spawn().await;
+    node.respond("getinfo", info());
+    let port = node.uri().port_u16().unwrap();
+
+    // A node answering within the probe timeout is found.
+    node.delay("getinfo", Duration::from_secs(1));
+    assert_eq!(
+        &probe_node_uri(&port, &NodeAuth::None).await.unwrap(),
+        node.uri()
+    );
+
+    // Each of the three probes of a node slower than the timeout fails.
+    node.delay("getinfo", Duration::from_secs(5));
+    let start = Instant::now();
+    let error = probe_node_uri(&port, &NodeAuth::None).await.unwrap_err();
+    println!(
+        "[TEST LOG] Probe failed after {:?}: {}.",
+        start.elapsed(),
+        error
+    );
+    assert!(matches!(error, JsonRpcConnectorError::CustomError(_)));
+    assert_eq!(node.call_count("getinfo"), 4);
+    assert!(start.elapsed() >= Duration::from_secs(9));
+}
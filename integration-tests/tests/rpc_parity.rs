@@ -0,0 +1,269 @@
+//! RPC parity tests, a test per implemented RPC run against each validator.
+//!
+//! Tests are minimal: each calls its RPC and checks the call succeeds and a meaningful field of the response is
+//! populated, so an RPC broken against one validator fails that validator's case. Run with
+//! `cargo test --test rpc_parity`, cases for validators the test manager cannot launch, or whose binary is not found,
+//! are skipped.
+
+#![forbid(unsafe_code)]
+
+use zaino_fetch::jsonrpc::connector::{JsonRpcConnector, NodeAuth};
+use zaino_proto::proto::service::{
+    BlockId, BlockRange, ChainSpec, Empty, Exclude, GetAddressUtxosArg, GetSubtreeRootsArg,
+    ShieldedProtocol, TransparentAddressBlockFilter, TxFilter,
+};
+use zaino_testutils::{
+    get_zingo_address, validator_matrix, TestCase, TestHarness, TestManagerBuilder, ValidatorKind,
+};
+
+/// Number of blocks mined before each call, so every RPC has chain data to return.
+const CHAIN_LENGTH: u64 = 5;
+
+/// Launches a test case with `CHAIN_LENGTH` blocks mined.
+async fn launch_with_blocks(validator: ValidatorKind) -> TestCase {
+    let harness = TestCase::launch(TestManagerBuilder::new().watch_only(), validator).await;
+    harness
+        .regtest_manager
+        .generate_n_blocks(CHAIN_LENGTH as u32)
+        .unwrap();
+    harness
+}
+
+/// Launches a test case with a faucet funded client, and sends a transaction from the faucet to a transparent address
+/// of the client.
+///
+/// Returns the case, the transparent address and the display order txid of the transaction, which is left in the
+/// mempool.
+async fn launch_with_transaction(validator: ValidatorKind) -> (TestCase, String, String) {
+    let harness = TestCase::launch(TestManagerBuilder::new(), validator).await;
+    let zingo_client = harness.build_lightclient().await;
+    let taddr = get_zingo_address(&zingo_client, "transparent").await;
+    harness.regtest_manager.generate_n_blocks(1).unwrap();
+    zingo_client.do_sync(false).await.unwrap();
+    zingo_client
+        .do_send(vec![(&taddr, 250_000, None)])
+        .await
+        .unwrap();
+    let zebrad_client = JsonRpcConnector::new(
+        harness.test_and_return_zebrad_uri().await,
+        NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+    )
+    .await;
+    let txid = zebrad_client
+        .get_raw_mempool(false)
+        .await
+        .unwrap()
+        .txids()
+        .pop()
+        .unwrap();
+    (harness, taddr, txid)
+}
+
+/// Returns a BlockId for the given height.
+fn block_id(height: u64) -> BlockId {
+    BlockId {
+        height,
+        hash: Vec::new(),
+    }
+}
+
+validator_matrix! {
+    async fn get_latest_block(validator: ValidatorKind) {
+        let harness = launch_with_blocks(validator).await;
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        let block = grpc_client.get_latest_block(ChainSpec {}).await.unwrap().into_inner();
+        assert!(block.height >= CHAIN_LENGTH);
+        assert_eq!(block.hash.len(), 32);
+
+        harness.teardown().await;
+    }
+
+    async fn get_block(validator: ValidatorKind) {
+        let harness = launch_with_blocks(validator).await;
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        let block = grpc_client.get_block(block_id(1)).await.unwrap().into_inner();
+        assert_eq!(block.height, 1);
+        assert_eq!(block.hash.len(), 32);
+
+        harness.teardown().await;
+    }
+
+    async fn get_block_range(validator: ValidatorKind) {
+        let harness = launch_with_blocks(validator).await;
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        let mut stream = grpc_client
+            .get_block_range(BlockRange {
+                start: Some(block_id(1)),
+                end: Some(block_id(CHAIN_LENGTH)),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut heights = Vec::new();
+        while let Some(block) = stream.message().await.unwrap() {
+            heights.push(block.height);
+        }
+        assert_eq!(heights, (1..=CHAIN_LENGTH).collect::<Vec<_>>());
+
+        harness.teardown().await;
+    }
+
+    async fn get_transaction(validator: ValidatorKind) {
+        let (harness, _taddr, txid) = launch_with_transaction(validator).await;
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        let transaction = grpc_client
+            .get_transaction(TxFilter {
+                block: None,
+                index: 0,
+                hash: hex::decode(&txid).unwrap().into_iter().rev().collect(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!transaction.data.is_empty());
+
+        harness.teardown().await;
+    }
+
+    async fn send_transaction(validator: ValidatorKind) {
+        // The client sends its transaction through the indexer's send_transaction.
+        let (harness, _taddr, txid) = launch_with_transaction(validator).await;
+
+        assert_eq!(txid.len(), 64);
+
+        harness.teardown().await;
+    }
+
+    async fn get_taddress_txids(validator: ValidatorKind) {
+        let (harness, taddr, _txid) = launch_with_transaction(validator).await;
+        harness.regtest_manager.generate_n_blocks(1).unwrap();
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        let tip = grpc_client.get_latest_block(ChainSpec {}).await.unwrap().into_inner().height;
+        let mut stream = grpc_client
+            .get_taddress_txids(TransparentAddressBlockFilter {
+                address: taddr,
+                range: Some(BlockRange {
+                    start: Some(block_id(1)),
+                    end: Some(block_id(tip)),
+                }),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut transactions = Vec::new();
+        while let Some(transaction) = stream.message().await.unwrap() {
+            transactions.push(transaction);
+        }
+        assert_eq!(transactions.len(), 1);
+        assert!(!transactions[0].data.is_empty());
+
+        harness.teardown().await;
+    }
+
+    async fn get_mempool_tx(validator: ValidatorKind) {
+        let (harness, _taddr, _txid) = launch_with_transaction(validator).await;
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        let mut stream = grpc_client
+            .get_mempool_tx(Exclude { txid: Vec::new() })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut transactions = Vec::new();
+        while let Some(transaction) = stream.message().await.unwrap() {
+            transactions.push(transaction);
+        }
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].hash.len(), 32);
+
+        harness.teardown().await;
+    }
+
+    async fn get_mempool_stream(validator: ValidatorKind) {
+        let (harness, _taddr, _txid) = launch_with_transaction(validator).await;
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        let mut stream = grpc_client.get_mempool_stream(Empty {}).await.unwrap().into_inner();
+        let transaction = stream.message().await.unwrap().unwrap();
+        assert!(!transaction.data.is_empty());
+
+        // The stream closes once a new block is mined.
+        harness.regtest_manager.generate_n_blocks(1).unwrap();
+        while stream.message().await.unwrap().is_some() {}
+
+        harness.teardown().await;
+    }
+
+    async fn get_tree_state(validator: ValidatorKind) {
+        let harness = launch_with_blocks(validator).await;
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        let tree_state = grpc_client.get_tree_state(block_id(1)).await.unwrap().into_inner();
+        assert_eq!(tree_state.height, 1);
+        assert!(!tree_state.hash.is_empty());
+
+        harness.teardown().await;
+    }
+
+    async fn get_subtree_roots(validator: ValidatorKind) {
+        let harness = launch_with_blocks(validator).await;
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        // A short regtest chain holds no completed subtrees, the stream ends without error.
+        let mut stream = grpc_client
+            .get_subtree_roots(GetSubtreeRootsArg {
+                start_index: 0,
+                shielded_protocol: ShieldedProtocol::Sapling as i32,
+                max_entries: 0,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        while let Some(root) = stream.message().await.unwrap() {
+            assert_eq!(root.root_hash.len(), 32);
+        }
+
+        harness.teardown().await;
+    }
+
+    async fn get_address_utxos_stream(validator: ValidatorKind) {
+        let (harness, taddr, _txid) = launch_with_transaction(validator).await;
+        harness.regtest_manager.generate_n_blocks(1).unwrap();
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        let mut stream = grpc_client
+            .get_address_utxos_stream(GetAddressUtxosArg {
+                addresses: vec![taddr.clone()],
+                start_height: 0,
+                max_entries: 0,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let mut utxos = Vec::new();
+        while let Some(utxo) = stream.message().await.unwrap() {
+            utxos.push(utxo);
+        }
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0].address, taddr);
+        assert_eq!(utxos[0].value_zat, 250_000);
+
+        harness.teardown().await;
+    }
+
+    async fn get_lightd_info(validator: ValidatorKind) {
+        let harness = launch_with_blocks(validator).await;
+        let mut grpc_client = harness.build_grpc_client().await;
+
+        let lightd_info = grpc_client.get_lightd_info(Empty {}).await.unwrap().into_inner();
+        assert_eq!(lightd_info.chain_name, "regtest");
+        assert!(lightd_info.block_height >= CHAIN_LENGTH);
+
+        harness.teardown().await;
+    }
+}
@@ -0,0 +1,659 @@
+//! Status code conformance tests, the compatibility contract with lightwalletd.
+//!
+//! [`contract`] lists, for every implemented CompactTxStreamer method, the status code returned in each canonical
+//! error case: a missing or malformed required field, a nonexistent entity, an out of range height and an unreachable
+//! node. The codes follow lightwalletd:
+//!
+//! - INVALID_ARGUMENT for requests that are malformed whatever the chain state, including heights above the maximum
+//!   block height.
+//! - NOT_FOUND for blocks and transactions the node does not hold.
+//! - UNAVAILABLE while the node can not be reached.
+//! - DEADLINE_EXCEEDED for streams that time out, INTERNAL only for failures of the server or node themselves.
+//!
+//! Cases run against a mock node, the status of streamed responses is the first error of the stream. Run with
+//! `cargo test --test status_codes`, the table is printed on completion.
+
+#![forbid(unsafe_code)]
+
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use zaino_proto::proto::service::{
+    compact_tx_streamer_server::CompactTxStreamer, AddressList, BlockId, BlockRange, ChainSpec,
+    Empty, Exclude, GetAddressUtxosArg, GetSubtreeRootsArg, RawTransaction,
+    TransparentAddressBlockFilter, TxFilter,
+};
+use zaino_serve::rpc::GrpcClient;
+use zaino_testutils::{GrpcClientBuilder, MockNode};
+
+/// A valid regtest transparent address.
+const ADDRESS: &str = "tm9ogR9KukTCiTKvrsSxQwFv2x1vhZTydav";
+
+/// A height above any chain tip but within the valid height range.
+const UNMINED_HEIGHT: u64 = 1_000_000;
+
+/// A height above the maximum block height.
+const INVALID_HEIGHT: u64 = u32::MAX as u64 + 1;
+
+/// Time allowed for a case to return its status.
+const CASE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// State of the mock node a case is run against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    /// Reachable, at height 10, answering lookups of any block or transaction with the node's "not found" errors.
+    Empty,
+    /// Unreachable, the mock node has been killed.
+    Down,
+}
+
+/// A call of a CompactTxStreamer method.
+#[derive(Debug)]
+enum Call {
+    GetLatestBlock,
+    GetBlock(BlockId),
+    GetBlockNullifiers(BlockId),
+    GetBlockRange(BlockRange),
+    GetBlockRangeNullifiers(BlockRange),
+    GetTransaction(TxFilter),
+    SendTransaction(RawTransaction),
+    GetTaddressTxids(TransparentAddressBlockFilter),
+    GetTaddressBalance(AddressList),
+    GetMempoolTx(Exclude),
+    GetMempoolStream,
+    GetTreeState(BlockId),
+    GetSubtreeRoots(GetSubtreeRootsArg),
+    GetAddressUtxos(GetAddressUtxosArg),
+    GetAddressUtxosStream(GetAddressUtxosArg),
+    GetLightdInfo,
+}
+
+/// A row of the contract: the status code `call` returns against `node`.
+struct Case {
+    rpc: &'static str,
+    case: &'static str,
+    node: Node,
+    call: Call,
+    expected: tonic::Code,
+}
+
+fn case(
+    rpc: &'static str,
+    case: &'static str,
+    node: Node,
+    call: Call,
+    expected: tonic::Code,
+) -> Case {
+    Case {
+        rpc,
+        case,
+        node,
+        call,
+        expected,
+    }
+}
+
+fn block_id(height: u64) -> BlockId {
+    BlockId {
+        height,
+        hash: Vec::new(),
+    }
+}
+
+/// Returns a BlockId with an all-zero hash and height, which identifies no block.
+fn zero_block_id() -> BlockId {
+    BlockId {
+        height: 0,
+        hash: vec![0; 32],
+    }
+}
+
+fn block_range(start: Option<u64>, end: Option<u64>) -> BlockRange {
+    BlockRange {
+        start: start.map(block_id),
+        end: end.map(block_id),
+    }
+}
+
+fn txid_filter(hash: Vec<u8>) -> TxFilter {
+    TxFilter {
+        block: None,
+        index: 0,
+        hash,
+    }
+}
+
+fn address_filter(address: &str, range: Option<BlockRange>) -> TransparentAddressBlockFilter {
+    TransparentAddressBlockFilter {
+        address: address.to_string(),
+        range,
+    }
+}
+
+fn utxos_arg(addresses: &[&str]) -> GetAddressUtxosArg {
+    GetAddressUtxosArg {
+        addresses: addresses.iter().map(|a| a.to_string()).collect(),
+        start_height: 0,
+        max_entries: 0,
+    }
+}
+
+/// The status code contract.
+fn contract() -> Vec<Case> {
+    use tonic::Code::*;
+    use Call::*;
+    use Node::*;
+    vec![
+        case(
+            "GetLatestBlock",
+            "node unreachable",
+            Down,
+            GetLatestBlock,
+            Unavailable,
+        ),
+        case(
+            "GetBlock",
+            "malformed hash",
+            Empty,
+            GetBlock(BlockId {
+                height: 0,
+                hash: vec![1; 3],
+            }),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlock",
+            "zero hash and height",
+            Empty,
+            GetBlock(zero_block_id()),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlock",
+            "out of range height",
+            Empty,
+            GetBlock(block_id(INVALID_HEIGHT)),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlock",
+            "nonexistent block",
+            Empty,
+            GetBlock(block_id(UNMINED_HEIGHT)),
+            NotFound,
+        ),
+        case(
+            "GetBlock",
+            "node unreachable",
+            Down,
+            GetBlock(block_id(1)),
+            Unavailable,
+        ),
+        case(
+            "GetBlockNullifiers",
+            "malformed hash",
+            Empty,
+            GetBlockNullifiers(BlockId {
+                height: 0,
+                hash: vec![1; 3],
+            }),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockNullifiers",
+            "zero hash and height",
+            Empty,
+            GetBlockNullifiers(zero_block_id()),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockNullifiers",
+            "out of range height",
+            Empty,
+            GetBlockNullifiers(block_id(INVALID_HEIGHT)),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockNullifiers",
+            "nonexistent block",
+            Empty,
+            GetBlockNullifiers(block_id(UNMINED_HEIGHT)),
+            NotFound,
+        ),
+        case(
+            "GetBlockNullifiers",
+            "node unreachable",
+            Down,
+            GetBlockNullifiers(block_id(1)),
+            Unavailable,
+        ),
+        case(
+            "GetBlockRange",
+            "missing start",
+            Empty,
+            GetBlockRange(block_range(None, Some(1))),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockRange",
+            "missing end",
+            Empty,
+            GetBlockRange(block_range(Some(1), None)),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockRange",
+            "out of range height",
+            Empty,
+            GetBlockRange(block_range(Some(1), Some(INVALID_HEIGHT))),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockRange",
+            "genesis start height",
+            Empty,
+            GetBlockRange(block_range(Some(0), Some(1))),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockRange",
+            "end above chain tip",
+            Empty,
+            GetBlockRange(block_range(Some(1), Some(UNMINED_HEIGHT))),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockRange",
+            "nonexistent block",
+            Empty,
+            GetBlockRange(block_range(Some(1), Some(1))),
+            NotFound,
+        ),
+        case(
+            "GetBlockRange",
+            "node unreachable",
+            Down,
+            GetBlockRange(block_range(Some(1), Some(1))),
+            Unavailable,
+        ),
+        case(
+            "GetBlockRangeNullifiers",
+            "missing start",
+            Empty,
+            GetBlockRangeNullifiers(block_range(None, Some(1))),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockRangeNullifiers",
+            "out of range height",
+            Empty,
+            GetBlockRangeNullifiers(block_range(Some(1), Some(INVALID_HEIGHT))),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockRangeNullifiers",
+            "genesis start height",
+            Empty,
+            GetBlockRangeNullifiers(block_range(Some(0), Some(1))),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockRangeNullifiers",
+            "end above chain tip",
+            Empty,
+            GetBlockRangeNullifiers(block_range(Some(1), Some(UNMINED_HEIGHT))),
+            InvalidArgument,
+        ),
+        case(
+            "GetBlockRangeNullifiers",
+            "nonexistent block",
+            Empty,
+            GetBlockRangeNullifiers(block_range(Some(1), Some(1))),
+            NotFound,
+        ),
+        case(
+            "GetBlockRangeNullifiers",
+            "node unreachable",
+            Down,
+            GetBlockRangeNullifiers(block_range(Some(1), Some(1))),
+            Unavailable,
+        ),
+        case(
+            "GetTransaction",
+            "missing hash",
+            Empty,
+            GetTransaction(txid_filter(Vec::new())),
+            InvalidArgument,
+        ),
+        case(
+            "GetTransaction",
+            "nonexistent transaction",
+            Empty,
+            GetTransaction(txid_filter(vec![0xab; 32])),
+            NotFound,
+        ),
+        case(
+            "GetTransaction",
+            "node unreachable",
+            Down,
+            GetTransaction(txid_filter(vec![0xab; 32])),
+            Unavailable,
+        ),
+        case(
+            "SendTransaction",
+            "missing data",
+            Empty,
+            SendTransaction(RawTransaction {
+                data: Vec::new(),
+                height: 0,
+            }),
+            InvalidArgument,
+        ),
+        case(
+            "SendTransaction",
+            "undecodable transaction",
+            Empty,
+            SendTransaction(RawTransaction {
+                data: vec![0xff; 8],
+                height: 0,
+            }),
+            InvalidArgument,
+        ),
+        case(
+            "SendTransaction",
+            "node unreachable",
+            Down,
+            SendTransaction(RawTransaction {
+                data: vec![0xff; 8],
+                height: 0,
+            }),
+            Unavailable,
+        ),
+        case(
+            "GetTaddressTxids",
+            "missing range",
+            Empty,
+            GetTaddressTxids(address_filter(ADDRESS, None)),
+            InvalidArgument,
+        ),
+        case(
+            "GetTaddressTxids",
+            "invalid address",
+            Empty,
+            GetTaddressTxids(address_filter(
+                "t1bogus",
+                Some(block_range(Some(1), Some(1))),
+            )),
+            InvalidArgument,
+        ),
+        case(
+            "GetTaddressTxids",
+            "out of range height",
+            Empty,
+            GetTaddressTxids(address_filter(
+                ADDRESS,
+                Some(block_range(Some(1), Some(INVALID_HEIGHT))),
+            )),
+            InvalidArgument,
+        ),
+        case(
+            "GetTaddressTxids",
+            "node unreachable",
+            Down,
+            GetTaddressTxids(address_filter(ADDRESS, Some(block_range(Some(1), Some(1))))),
+            Unavailable,
+        ),
+        case(
+            "GetTaddressBalance",
+            "missing addresses",
+            Empty,
+            GetTaddressBalance(AddressList {
+                addresses: Vec::new(),
+            }),
+            InvalidArgument,
+        ),
+        case(
+            "GetMempoolTx",
+            "malformed exclude txid",
+            Empty,
+            GetMempoolTx(Exclude {
+                txid: vec![Vec::new()],
+            }),
+            InvalidArgument,
+        ),
+        case(
+            "GetMempoolTx",
+            "node unreachable",
+            Down,
+            GetMempoolTx(Exclude { txid: Vec::new() }),
+            Unavailable,
+        ),
+        case(
+            "GetMempoolStream",
+            "node unreachable",
+            Down,
+            GetMempoolStream,
+            Unavailable,
+        ),
+        case(
+            "GetTreeState",
+            "zero hash and height",
+            Empty,
+            GetTreeState(zero_block_id()),
+            InvalidArgument,
+        ),
+        case(
+            "GetTreeState",
+            "out of range height",
+            Empty,
+            GetTreeState(block_id(INVALID_HEIGHT)),
+            InvalidArgument,
+        ),
+        case(
+            "GetTreeState",
+            "nonexistent block",
+            Empty,
+            GetTreeState(block_id(UNMINED_HEIGHT)),
+            NotFound,
+        ),
+        case(
+            "GetTreeState",
+            "node unreachable",
+            Down,
+            GetTreeState(block_id(1)),
+            Unavailable,
+        ),
+        case(
+            "GetSubtreeRoots",
+            "unknown protocol",
+            Empty,
+            GetSubtreeRoots(GetSubtreeRootsArg {
+                start_index: 0,
+                shielded_protocol: 7,
+                max_entries: 0,
+            }),
+            InvalidArgument,
+        ),
+        case(
+            "GetSubtreeRoots",
+            "node unreachable",
+            Down,
+            GetSubtreeRoots(GetSubtreeRootsArg {
+                start_index: 0,
+                shielded_protocol: 0,
+                max_entries: 0,
+            }),
+            Unavailable,
+        ),
+        case(
+            "GetAddressUtxos",
+            "missing addresses",
+            Empty,
+            GetAddressUtxos(utxos_arg(&[])),
+            InvalidArgument,
+        ),
+        case(
+            "GetAddressUtxosStream",
+            "missing addresses",
+            Empty,
+            GetAddressUtxosStream(utxos_arg(&[])),
+            InvalidArgument,
+        ),
+        case(
+            "GetAddressUtxosStream",
+            "invalid address",
+            Empty,
+            GetAddressUtxosStream(utxos_arg(&["t1bogus"])),
+            InvalidArgument,
+        ),
+        case(
+            "GetAddressUtxosStream",
+            "node unreachable",
+            Down,
+            GetAddressUtxosStream(utxos_arg(&[ADDRESS])),
+            Unavailable,
+        ),
+        case(
+            "GetLightdInfo",
+            "node unreachable",
+            Down,
+            GetLightdInfo,
+            Unavailable,
+        ),
+    ]
+}
+
+/// Responses of the mock node, keyed by method, for [`Node::Empty`].
+fn empty_node_responses() -> serde_json::Value {
+    let not_found_block =
+        serde_json::json!({ "error": { "code": -8, "message": "Block height out of range" } });
+    let not_found_tx = serde_json::json!({
+        "error": { "code": -5, "message": "No such mempool or blockchain transaction." }
+    });
+    serde_json::json!({
+        "getinfo": { "result": { "build": "v0.0.0", "subversion": "/MockNode:0.0.0/" } },
+        "getblockchaininfo": { "result": {
+            "chain": "regtest",
+            "blocks": 10,
+            "bestblockhash": "00".repeat(32),
+            "estimatedheight": 10,
+            "upgrades": {},
+            "consensus": { "chaintip": "c2d6d0b4", "nextblock": "c2d6d0b4" },
+        } },
+        "getblock": not_found_block,
+        "getblockhash": not_found_block,
+        "z_gettreestate": not_found_block,
+        "getrawtransaction": not_found_tx,
+        "z_gettransaction": not_found_tx,
+        "sendrawtransaction": { "error": { "code": -22, "message": "TX decode failed" } },
+        "getrawmempool": { "result": [] },
+    })
+}
+
+/// Returns a gRPC service backed by the node at `node_uri`.
+fn client(node_uri: &str) -> GrpcClient {
+    GrpcClientBuilder::new(&node_uri.parse().unwrap()).build()
+}
+
+/// Returns the status code of a unary response.
+fn unary_code<T>(response: Result<tonic::Response<T>, tonic::Status>) -> tonic::Code {
+    match response {
+        Ok(_) => tonic::Code::Ok,
+        Err(status) => status.code(),
+    }
+}
+
+/// Returns the status code of a streamed response, the code of the first error streamed or OK if the stream ends
+/// without error.
+async fn stream_code<S, T>(response: Result<tonic::Response<S>, tonic::Status>) -> tonic::Code
+where
+    S: Stream<Item = Result<T, tonic::Status>> + Unpin,
+{
+    let mut stream = match response {
+        Ok(response) => response.into_inner(),
+        Err(status) => return status.code(),
+    };
+    while let Some(item) = stream.next().await {
+        if let Err(status) = item {
+            return status.code();
+        }
+    }
+    tonic::Code::Ok
+}
+
+/// Returns the status code `call` returns from `client`.
+async fn status_code(client: &GrpcClient, call: Call) -> tonic::Code {
+    use tonic::Request;
+    match call {
+        Call::GetLatestBlock => {
+            unary_code(client.get_latest_block(Request::new(ChainSpec {})).await)
+        }
+        Call::GetBlock(id) => unary_code(client.get_block(Request::new(id)).await),
+        Call::GetBlockNullifiers(id) => {
+            unary_code(client.get_block_nullifiers(Request::new(id)).await)
+        }
+        Call::GetBlockRange(range) => {
+            stream_code(client.get_block_range(Request::new(range)).await).await
+        }
+        Call::GetBlockRangeNullifiers(range) => {
+            stream_code(client.get_block_range_nullifiers(Request::new(range)).await).await
+        }
+        Call::GetTransaction(filter) => {
+            unary_code(client.get_transaction(Request::new(filter)).await)
+        }
+        Call::SendTransaction(transaction) => {
+            unary_code(client.send_transaction(Request::new(transaction)).await)
+        }
+        Call::GetTaddressTxids(filter) => {
+            stream_code(client.get_taddress_txids(Request::new(filter)).await).await
+        }
+        Call::GetTaddressBalance(addresses) => {
+            unary_code(client.get_taddress_balance(Request::new(addresses)).await)
+        }
+        Call::GetMempoolTx(exclude) => {
+            stream_code(client.get_mempool_tx(Request::new(exclude)).await).await
+        }
+        Call::GetMempoolStream => {
+            stream_code(client.get_mempool_stream(Request::new(Empty {})).await).await
+        }
+        Call::GetTreeState(id) => unary_code(client.get_tree_state(Request::new(id)).await),
+        Call::GetSubtreeRoots(arg) => {
+            stream_code(client.get_subtree_roots(Request::new(arg)).await).await
+        }
+        Call::GetAddressUtxos(arg) => unary_code(client.get_address_utxos(Request::new(arg)).await),
+        Call::GetAddressUtxosStream(arg) => {
+            stream_code(client.get_address_utxos_stream(Request::new(arg)).await).await
+        }
+        Call::GetLightdInfo => unary_code(client.get_lightd_info(Request::new(Empty {})).await),
+    }
+}
+
+#[tokio::test]
+async fn status_codes_match_contract() {
+    let mut table = Vec::new();
+    let mut divergences = Vec::new();
+    for case in contract() {
+        let node = MockNode::with_responses(empty_node_responses()).await;
+        let node_uri = match case.node {
+            Node::Empty => node.uri().to_string(),
+            Node::Down => node.kill().await.to_string(),
+        };
+        let code = tokio::time::timeout(CASE_TIMEOUT, status_code(&client(&node_uri), case.call))
+            .await
+            .unwrap_or(tonic::Code::DeadlineExceeded);
+        let row = format!("| {} | {} | {:?} |", case.rpc, case.case, case.expected);
+        if code != case.expected {
+            divergences.push(format!("{} returned {:?}", row, code));
+        }
+        table.push(row);
+    }
+    println!("[TEST LOG] Status code contract:\n| RPC | Case | Code |\n|---|---|---|");
+    for row in &table {
+        println!("{}", row);
+    }
+    assert!(
+        divergences.is_empty(),
+        "Divergences from the status code contract:\n{}",
+        divergences.join("\n")
+    );
+}
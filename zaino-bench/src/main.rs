@@ -0,0 +1,237 @@
+//! Zaino throughput benchmark.
+//!
+//! Drives a single CompactTxStreamer method of a running Zaino server from a fixed number of concurrent callers for
+//! a fixed duration, then reports the request rate, latency percentiles and error rate.
+
+use clap::{Parser, ValueEnum};
+use std::time::{Duration, Instant};
+use tonic::transport::Channel;
+use zaino_proto::proto::service::{
+    compact_tx_streamer_client::CompactTxStreamerClient, BlockId, BlockRange, ChainSpec, Empty,
+    Exclude,
+};
+
+/// Number of blocks requested by each get_block_range call, ending at the chain tip.
+const BLOCK_RANGE_LEN: u64 = 10;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "zaino-bench",
+    about = "Drives a gRPC method of a Zaino server at a fixed concurrency and reports its throughput"
+)]
+struct Args {
+    /// Host of the Zaino gRPC server.
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+    /// Port of the Zaino gRPC server.
+    #[arg(long, default_value_t = 8137)]
+    port: u16,
+    /// gRPC method to call.
+    #[arg(long, value_enum, default_value_t = Method::GetLightdInfo)]
+    method: Method,
+    /// Number of calls in flight at a time.
+    #[arg(long, default_value_t = 16)]
+    concurrency: usize,
+    /// Time to drive the method for, in seconds.
+    #[arg(long, default_value_t = 10)]
+    duration_secs: u64,
+}
+
+/// CompactTxStreamer method driven by the benchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+enum Method {
+    /// get_lightd_info.
+    GetLightdInfo,
+    /// get_latest_block.
+    GetLatestBlock,
+    /// get_block, of the chain tip.
+    GetBlock,
+    /// get_block_range, of the last 10 blocks up to the chain tip.
+    GetBlockRange,
+    /// get_tree_state, of the chain tip.
+    GetTreeState,
+    /// get_mempool_tx, with an empty exclude list.
+    GetMempoolTx,
+}
+
+impl Method {
+    /// Returns true if calls of the method are addressed by the chain tip height.
+    fn needs_tip(self) -> bool {
+        matches!(
+            self,
+            Method::GetBlock | Method::GetBlockRange | Method::GetTreeState
+        )
+    }
+
+    /// Makes a single call of the method, reading streamed responses to the end.
+    async fn call(
+        self,
+        client: &mut CompactTxStreamerClient<Channel>,
+        tip: u64,
+    ) -> Result<(), tonic::Status> {
+        let tip_block = || BlockId {
+            height: tip,
+            hash: Vec::new(),
+        };
+        match self {
+            Method::GetLightdInfo => client.get_lightd_info(Empty {}).await.map(drop),
+            Method::GetLatestBlock => client.get_latest_block(ChainSpec {}).await.map(drop),
+            Method::GetBlock => client.get_block(tip_block()).await.map(drop),
+            Method::GetTreeState => client.get_tree_state(tip_block()).await.map(drop),
+            Method::GetBlockRange => {
+                let range = BlockRange {
+                    start: Some(BlockId {
+                        height: tip.saturating_sub(BLOCK_RANGE_LEN - 1),
+                        hash: Vec::new(),
+                    }),
+                    end: Some(tip_block()),
+                };
+                let mut stream = client.get_block_range(range).await?.into_inner();
+                while stream.message().await?.is_some() {}
+                Ok(())
+            }
+            Method::GetMempoolTx => {
+                let mut stream = client
+                    .get_mempool_tx(Exclude { txid: Vec::new() })
+                    .await?
+                    .into_inner();
+                while stream.message().await?.is_some() {}
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Calls made by a single caller.
+#[derive(Debug, Default)]
+struct CallerStats {
+    /// Latency of every call, failed calls included.
+    latencies: Vec<Duration>,
+    /// Number of calls that returned an error.
+    errors: u64,
+    /// First error returned, reported to help diagnose failing runs.
+    first_error: Option<tonic::Status>,
+}
+
+/// Calls `method` back to back until `deadline`.
+async fn run_caller(
+    mut client: CompactTxStreamerClient<Channel>,
+    method: Method,
+    tip: u64,
+    deadline: Instant,
+) -> CallerStats {
+    let mut stats = CallerStats::default();
+    while Instant::now() < deadline {
+        let start = Instant::now();
+        let result = method.call(&mut client, tip).await;
+        stats.latencies.push(start.elapsed());
+        if let Err(status) = result {
+            stats.errors += 1;
+            if stats.first_error.is_none() {
+                stats.first_error = Some(status);
+            }
+        }
+    }
+    stats
+}
+
+/// Returns the `percentile` of the sorted `latencies`, by the nearest-rank method.
+fn percentile(latencies: &[Duration], percentile: f64) -> Duration {
+    if latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (percentile / 100.0 * latencies.len() as f64).ceil() as usize;
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+/// Formats a duration in milliseconds.
+fn millis(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    if args.concurrency == 0 || args.duration_secs == 0 {
+        eprintln!("Error: --concurrency and --duration-secs must be non-zero.");
+        std::process::exit(2);
+    }
+    // IPv6 hosts are bracketed in URIs.
+    let host = if args.host.contains(':') && !args.host.starts_with('[') {
+        format!("[{}]", args.host)
+    } else {
+        args.host.clone()
+    };
+    let uri = match format!("http://{}:{}", host, args.port).parse::<http::Uri>() {
+        Ok(uri) => uri,
+        Err(e) => {
+            eprintln!("Error: Invalid server address: {}.", e);
+            std::process::exit(2);
+        }
+    };
+    // Callers share a single HTTP/2 connection, as a wallet's concurrent calls do.
+    let mut client = match CompactTxStreamerClient::connect(uri.clone()).await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Error: Failed to connect to {}: {}.", uri, e);
+            std::process::exit(1);
+        }
+    };
+    let tip = if args.method.needs_tip() {
+        match client.get_latest_block(ChainSpec {}).await {
+            Ok(response) => response.into_inner().height,
+            Err(status) => {
+                eprintln!(
+                    "Error: Failed to fetch the chain tip: {}.",
+                    status.message()
+                );
+                std::process::exit(1);
+            }
+        }
+    } else {
+        0
+    };
+
+    println!(
+        "Calling {:?} on {} from {} callers for {}s..",
+        args.method, uri, args.concurrency, args.duration_secs
+    );
+    let start = Instant::now();
+    let deadline = start + Duration::from_secs(args.duration_secs);
+    let callers: Vec<_> = (0..args.concurrency)
+        .map(|_| tokio::spawn(run_caller(client.clone(), args.method, tip, deadline)))
+        .collect();
+    let mut latencies = Vec::new();
+    let mut errors = 0;
+    let mut first_error = None;
+    for caller in callers {
+        let stats = caller.await.expect("benchmark caller panicked");
+        latencies.extend(stats.latencies);
+        errors += stats.errors;
+        first_error = first_error.or(stats.first_error);
+    }
+    let elapsed = start.elapsed();
+    latencies.sort_unstable();
+
+    let requests = latencies.len();
+    println!("Requests:      {}", requests);
+    println!(
+        "Requests/sec:  {:.1}",
+        requests as f64 / elapsed.as_secs_f64()
+    );
+    println!("Latency p50:   {}", millis(percentile(&latencies, 50.0)));
+    println!("Latency p99:   {}", millis(percentile(&latencies, 99.0)));
+    println!(
+        "Error rate:    {:.2}% ({} errors)",
+        if requests == 0 {
+            0.0
+        } else {
+            errors as f64 * 100.0 / requests as f64
+        },
+        errors
+    );
+    if let Some(status) = first_error {
+        println!("First error:   {:?}: {}", status.code(), status.message());
+    }
+}
@@ -1,7 +1,9 @@
 //! Zaino-Fetch Block, Transaction and Mempool Parser.
 
 pub mod block;
+pub mod cache;
 pub mod error;
+pub mod fetcher;
 pub mod mempool;
 pub mod transaction;
 pub mod utils;
@@ -9,7 +9,10 @@ use crate::{
             CompactSize, ParseFromSlice,
         },
     },
-    jsonrpc::{connector::JsonRpcConnector, response::GetBlockResponse},
+    jsonrpc::{
+        connector::{JsonRpcConnector, NodeAuth},
+        response::GetBlockResponse,
+    },
 };
 use sha2::{Digest, Sha256};
 use std::io::Cursor;
@@ -182,17 +185,26 @@ impl BlockHeaderData {
         Ok(buffer)
     }
 
-    /// Extracts the block hash from the block header.
-    pub fn get_hash(&self) -> Result<Vec<u8>, ParseError> {
-        let serialized_header = self.to_binary()?;
+    /// Returns the block hash, the double SHA-256 of the serialized header, in internal byte order.
+    ///
+    /// Zcash headers are hashed including their Equihash solution, so the hash covers the 140 byte fixed part of the
+    /// header followed by the length prefixed solution.
+    pub fn hash(&self) -> [u8; 32] {
+        let serialized_header = self
+            .to_binary()
+            .expect("serializing a block header into memory can not fail");
 
         let mut hasher = Sha256::new();
         hasher.update(&serialized_header);
         let digest = hasher.finalize_reset();
         hasher.update(digest);
-        let final_digest = hasher.finalize();
 
-        Ok(final_digest.to_vec())
+        hasher.finalize().into()
+    }
+
+    /// Extracts the block hash from the block header.
+    pub fn get_hash(&self) -> Result<Vec<u8>, ParseError> {
+        Ok(self.hash().to_vec())
     }
 }
 
@@ -241,9 +253,11 @@ impl ParseFromSlice for FullBlock {
             BlockHeaderData::parse_from_slice(&data[cursor.position() as usize..], None, None)?;
         cursor.set_position(data.len() as u64 - remaining_data.len() as u64);
         let tx_count = CompactSize::read(&mut cursor)?;
+        // The txids and raw block are fetched by separate node calls, a mismatch means the node returned inconsistent
+        // blocks and the block must not be served.
         if txid.len() != tx_count as usize {
             return Err(ParseError::InvalidData(format!(
-                "number of txids ({}) does not match tx_count ({})",
+                "inconsistent block: number of txids ({}) does not match tx_count ({})",
                 txid.len(),
                 tx_count
             )));
@@ -379,18 +393,16 @@ impl FullBlock {
 ///
 /// Retrieves a full block from zebrad/zcashd using 2 get_block calls.
 /// This is because a get_block verbose = 1 call is require to fetch txids.
-/// TODO: Save retrieved CompactBlock to the BlockCache.
+///
+/// Returns [`ParseError::InvalidData`] if the number of txids does not match the number of transactions in the raw
+/// block, eg. if the node returned inconsistent results for the two calls.
 /// TODO: Return more representative error type.
 pub async fn get_block_from_node(
     zebra_uri: &http::Uri,
+    node_auth: &NodeAuth,
     height: &u32,
 ) -> Result<CompactBlock, BlockCacheError> {
-    let zebrad_client = JsonRpcConnector::new(
-        zebra_uri.clone(),
-        Some("xxxxxx".to_string()),
-        Some("xxxxxx".to_string()),
-    )
-    .await;
+    let zebrad_client = JsonRpcConnector::new(zebra_uri.clone(), node_auth.clone()).await;
     let block_1 = zebrad_client.get_block(height.to_string(), Some(1)).await;
     match block_1 {
         Ok(GetBlockResponse::Object {
@@ -0,0 +1,685 @@
+//! Zingo-Indexer in-memory block cache.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{watch, RwLock, Semaphore};
+
+use zaino_proto::proto::compact_formats::CompactBlock;
+
+use crate::{
+    chain::{
+        block::get_block_from_node,
+        error::BlockCacheError,
+        fetcher::{BlockFetcher, BlockSink, CancelSignal, FetchOutcome, DEFAULT_FETCH_CONCURRENCY},
+    },
+    jsonrpc::connector::{JsonRpcConnector, NodeAuth},
+};
+
+/// Default number of blocks below the chain tip kept in the cache when garbage collecting.
+pub const DEFAULT_REORG_SAFE_DEPTH: u32 = 100;
+
+/// Default number of blocks ahead of a streamed block that are prefetched into the cache.
+pub const DEFAULT_PREFETCH_WINDOW: u32 = 20;
+
+/// Default maximum number of lookups held in a [`NegativeCache`].
+pub const DEFAULT_NEGATIVE_CACHE_SIZE: usize = 4096;
+
+/// Default time a lookup is held in a [`NegativeCache`].
+pub const DEFAULT_NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// In-memory cache of compact blocks and block hashes, keyed by block height.
+///
+/// Blocks are fetched from the node on a cache miss and kept until evicted, or ahead of time by
+/// [`BlockCache::prefetch`]. Blocks orphaned by a reorg are evicted as they are detected, see
+/// [`BlockCache::observe_tip`].
+#[derive(Debug)]
+pub struct BlockCache {
+    /// Cached blocks and block hashes.
+    chain: RwLock<CachedChain>,
+    /// Number of blocks below the chain tip kept in the cache, and searched for the fork point of a reorg.
+    reorg_safe_depth: u32,
+    /// Number of blocks ahead of a streamed block that are prefetched.
+    prefetch_window: u32,
+    /// Maximum number of blocks fetched from the node at a time, by prefetches or by a single block range stream.
+    fetch_concurrency: usize,
+    /// Bounds the number of prefetch tasks in flight.
+    prefetch_permits: Arc<Semaphore>,
+    /// Heights being prefetched, the receiver is notified once the prefetch task has finished.
+    prefetching: std::sync::Mutex<HashMap<u32, watch::Receiver<()>>>,
+    /// Number of block lookups answered from the cache.
+    hits: AtomicU64,
+    /// Number of block lookups fetched from the node.
+    misses: AtomicU64,
+}
+
+/// Blocks and block hashes held by a [`BlockCache`].
+#[derive(Debug, Default)]
+struct CachedChain {
+    /// Cached compact blocks.
+    blocks: BTreeMap<u32, CompactBlock>,
+    /// Cached block hashes of blocks not held in `blocks`, in internal byte order.
+    hashes: BTreeMap<u32, [u8; 32]>,
+    /// Heights of the cached blocks and block hashes, keyed by block hash in internal byte order.
+    heights: HashMap<[u8; 32], u32>,
+}
+
+impl CachedChain {
+    /// Returns the hash, in internal byte order, of the block or block hash cached at `height`.
+    fn hash_at(&self, height: u32) -> Option<[u8; 32]> {
+        match self.blocks.get(&height) {
+            Some(block) => block_hash(block),
+            None => self.hashes.get(&height).copied(),
+        }
+    }
+
+    /// Returns the heights and hashes of the blocks and block hashes cached within `heights`, highest first.
+    fn hashes_in(&self, heights: RangeInclusive<u32>) -> Vec<(u32, [u8; 32])> {
+        let mut cached: Vec<u32> = self
+            .blocks
+            .range(heights.clone())
+            .map(|(height, _)| *height)
+            .chain(self.hashes.range(heights).map(|(height, _)| *height))
+            .collect();
+        cached.sort_unstable_by(|a, b| b.cmp(a));
+        cached.dedup();
+        cached
+            .into_iter()
+            .filter_map(|height| self.hash_at(height).map(|hash| (height, hash)))
+            .collect()
+    }
+
+    /// Returns the height of the highest cached block or block hash.
+    fn highest(&self) -> Option<u32> {
+        let block = self.blocks.keys().next_back().copied();
+        let hash = self.hashes.keys().next_back().copied();
+        block.max(hash)
+    }
+
+    /// Adds a block at `height`, replacing any block or block hash held at that height.
+    fn insert_block(&mut self, height: u32, block: CompactBlock) {
+        self.remove(height);
+        if let Some(hash) = block_hash(&block) {
+            self.heights.insert(hash, height);
+        }
+        self.blocks.insert(height, block);
+    }
+
+    /// Adds a block hash at `height`, unless the block at that height is held.
+    fn insert_hash(&mut self, height: u32, hash: [u8; 32]) {
+        if self.blocks.contains_key(&height) {
+            return;
+        }
+        if let Some(replaced) = self.hashes.insert(height, hash) {
+            self.unindex(replaced, height);
+        }
+        self.heights.insert(hash, height);
+    }
+
+    /// Removes the block and block hash held at `height`.
+    fn remove(&mut self, height: u32) {
+        if let Some(hash) = self.blocks.remove(&height).as_ref().and_then(block_hash) {
+            self.unindex(hash, height);
+        }
+        if let Some(hash) = self.hashes.remove(&height) {
+            self.unindex(hash, height);
+        }
+    }
+
+    /// Removes the blocks and block hashes below `height`, returning the number of blocks removed.
+    fn remove_below(&mut self, height: u32) -> usize {
+        let kept_blocks = self.blocks.split_off(&height);
+        let kept_hashes = self.hashes.split_off(&height);
+        let blocks = std::mem::replace(&mut self.blocks, kept_blocks);
+        let hashes = std::mem::replace(&mut self.hashes, kept_hashes);
+        self.unindex_removed(blocks, hashes)
+    }
+
+    /// Removes the blocks and block hashes above `height`, returning the number of blocks removed.
+    fn remove_above(&mut self, height: u32) -> usize {
+        let Some(above) = height.checked_add(1) else {
+            return 0;
+        };
+        let blocks = self.blocks.split_off(&above);
+        let hashes = self.hashes.split_off(&above);
+        self.unindex_removed(blocks, hashes)
+    }
+
+    /// Removes the removed blocks and block hashes from the height index, returning the number of blocks removed.
+    fn unindex_removed(
+        &mut self,
+        blocks: BTreeMap<u32, CompactBlock>,
+        hashes: BTreeMap<u32, [u8; 32]>,
+    ) -> usize {
+        let removed = blocks.len();
+        for (height, hash) in blocks
+            .iter()
+            .filter_map(|(height, block)| block_hash(block).map(|hash| (*height, hash)))
+            .chain(hashes)
+        {
+            self.unindex(hash, height);
+        }
+        removed
+    }
+
+    /// Removes `hash` from the height index if it is indexed at `height`.
+    fn unindex(&mut self, hash: [u8; 32], height: u32) {
+        if self.heights.get(&hash) == Some(&height) {
+            self.heights.remove(&hash);
+        }
+    }
+}
+
+/// Returns the hash of a compact block in internal byte order, `None` if the block holds no valid hash.
+fn block_hash(block: &CompactBlock) -> Option<[u8; 32]> {
+    <[u8; 32]>::try_from(block.hash.as_slice()).ok()
+}
+
+impl Default for BlockCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_REORG_SAFE_DEPTH)
+    }
+}
+
+impl BlockCache {
+    /// Returns an empty block cache.
+    pub fn new(reorg_safe_depth: u32) -> Self {
+        BlockCache {
+            chain: RwLock::new(CachedChain::default()),
+            reorg_safe_depth,
+            prefetch_window: DEFAULT_PREFETCH_WINDOW,
+            fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            prefetch_permits: Arc::new(Semaphore::new(DEFAULT_FETCH_CONCURRENCY)),
+            prefetching: std::sync::Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets the number of blocks prefetched ahead of a streamed block, 0 disables prefetching, and the maximum
+    /// number of blocks fetched from the node at a time.
+    pub fn with_prefetch(mut self, prefetch_window: u32, concurrency: usize) -> Self {
+        self.prefetch_window = prefetch_window;
+        self.fetch_concurrency = concurrency.max(1);
+        self.prefetch_permits = Arc::new(Semaphore::new(self.fetch_concurrency));
+        self
+    }
+
+    /// Returns the number of blocks prefetched ahead of a streamed block.
+    pub fn prefetch_window(&self) -> u32 {
+        self.prefetch_window
+    }
+
+    /// Returns the maximum number of blocks fetched from the node at a time.
+    pub fn fetch_concurrency(&self) -> usize {
+        self.fetch_concurrency
+    }
+
+    /// Returns the number of block lookups answered from the cache, including lookups that waited for a prefetch.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of block lookups fetched from the node.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::SeqCst)
+    }
+
+    /// Returns the compact block at the given height, fetching it from the node and caching it if not present.
+    ///
+    /// If the block is being prefetched the prefetch is awaited rather than fetching the block a second time. A block
+    /// fetched from the node that does not link to the cached block below it reveals a reorg, the cached blocks above
+    /// the fork point are evicted before it is cached.
+    pub async fn get_block(
+        &self,
+        zebrad_uri: &http::Uri,
+        node_auth: &NodeAuth,
+        height: u32,
+    ) -> Result<CompactBlock, BlockCacheError> {
+        if let Some(block) = self.chain.read().await.blocks.get(&height) {
+            self.hits.fetch_add(1, Ordering::SeqCst);
+            return Ok(block.clone());
+        }
+        let prefetch = self.prefetching_lock().get(&height).cloned();
+        if let Some(mut prefetch) = prefetch {
+            // Returns once the prefetch task drops its sender.
+            prefetch.changed().await.ok();
+            if let Some(block) = self.chain.read().await.blocks.get(&height) {
+                self.hits.fetch_add(1, Ordering::SeqCst);
+                return Ok(block.clone());
+            }
+        }
+        self.misses.fetch_add(1, Ordering::SeqCst);
+        let block = get_block_from_node(zebrad_uri, node_auth, &height).await?;
+        if let Some(parent) = height.checked_sub(1) {
+            let cached_parent_hash = self.chain.read().await.hash_at(parent);
+            if cached_parent_hash.is_some_and(|hash| block.prev_hash.as_slice() != hash) {
+                self.evict_fork(zebrad_uri, node_auth, parent).await?;
+            }
+        }
+        self.insert(height, block.clone()).await;
+        Ok(block)
+    }
+
+    /// Reconciles the cache with the chain tip reported by the node, returning the number of blocks evicted.
+    ///
+    /// Blocks below `height - reorg_safe_depth` are evicted, along with blocks orphaned by a reorg: those above the
+    /// tip, and those above the fork point if the highest cached block is no longer on the node's best chain. The
+    /// fork point is searched for at most `reorg_safe_depth` blocks down, deeper reorgs are left to the chain
+    /// consistency check.
+    pub async fn observe_tip(
+        &self,
+        zebrad_uri: &http::Uri,
+        node_auth: &NodeAuth,
+        height: u32,
+        hash: [u8; 32],
+    ) -> Result<usize, BlockCacheError> {
+        let mut evicted = self
+            .evict_below(height.saturating_sub(self.reorg_safe_depth))
+            .await;
+        let highest_cached = {
+            let mut chain = self.chain.write().await;
+            evicted += chain.remove_above(height);
+            chain.highest().and_then(|cached| {
+                chain
+                    .hash_at(cached)
+                    .map(|cached_hash| (cached, cached_hash))
+            })
+        };
+        if let Some((cached, cached_hash)) = highest_cached {
+            let node_hash = if cached == height {
+                hash
+            } else {
+                JsonRpcConnector::new(zebrad_uri.clone(), node_auth.clone())
+                    .await
+                    .get_block_hash(cached)
+                    .await?
+            };
+            if node_hash != cached_hash {
+                evicted += self.evict_fork(zebrad_uri, node_auth, cached).await?;
+            }
+        }
+        Ok(evicted)
+    }
+
+    /// Evicts the cached blocks and block hashes above the highest height at or below `from` whose cached hash
+    /// matches the node's, checking at most `reorg_safe_depth` blocks down, returns the number of blocks evicted.
+    ///
+    /// If no cached hash in that window matches, every cached entry in it is evicted.
+    async fn evict_fork(
+        &self,
+        zebrad_uri: &http::Uri,
+        node_auth: &NodeAuth,
+        from: u32,
+    ) -> Result<usize, BlockCacheError> {
+        let lowest = from.saturating_sub(self.reorg_safe_depth);
+        let cached = self.chain.read().await.hashes_in(lowest..=from);
+        let node = JsonRpcConnector::new(zebrad_uri.clone(), node_auth.clone()).await;
+        let mut fork = lowest.saturating_sub(1);
+        for (height, cached_hash) in cached {
+            if node.get_block_hash(height).await? == cached_hash {
+                fork = height;
+                break;
+            }
+        }
+        Ok(self.chain.write().await.remove_above(fork))
+    }
+
+    /// Fetches the blocks at `heights` into the cache in background tasks, returning the number of tasks spawned.
+    ///
+    /// Heights already cached or being prefetched are skipped. At most the configured number of prefetch tasks are
+    /// in flight, the remaining heights are not prefetched once that bound is reached. Failed prefetches are dropped,
+    /// the block is fetched again, and its error reported, on lookup.
+    pub async fn prefetch(
+        self: &Arc<Self>,
+        zebrad_uri: &http::Uri,
+        node_auth: &NodeAuth,
+        heights: impl Iterator<Item = u32>,
+    ) -> usize {
+        let mut spawned = 0;
+        for height in heights {
+            if self.contains(height).await {
+                continue;
+            }
+            let (sender, permit) = {
+                let mut prefetching = self.prefetching_lock();
+                if prefetching.contains_key(&height) {
+                    continue;
+                }
+                let Ok(permit) = self.prefetch_permits.clone().try_acquire_owned() else {
+                    break;
+                };
+                let (sender, receiver) = watch::channel(());
+                prefetching.insert(height, receiver);
+                (sender, permit)
+            };
+            let block_cache = self.clone();
+            let zebrad_uri = zebrad_uri.clone();
+            let node_auth = node_auth.clone();
+            tokio::spawn(async move {
+                if let Ok(block) = get_block_from_node(&zebrad_uri, &node_auth, &height).await {
+                    block_cache.insert(height, block).await;
+                }
+                block_cache.prefetching_lock().remove(&height);
+                drop(sender);
+                drop(permit);
+            });
+            spawned += 1;
+        }
+        spawned
+    }
+
+    /// Locks the set of heights being prefetched.
+    fn prefetching_lock(&self) -> std::sync::MutexGuard<'_, HashMap<u32, watch::Receiver<()>>> {
+        self.prefetching.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the hash of the block at the given height in internal byte order, fetching it from the node and
+    /// caching it if not present.
+    pub async fn get_block_hash(
+        &self,
+        zebrad_uri: &http::Uri,
+        node_auth: &NodeAuth,
+        height: u32,
+    ) -> Result<[u8; 32], BlockCacheError> {
+        if let Some(hash) = self.chain.read().await.hash_at(height) {
+            return Ok(hash);
+        }
+        let hash = JsonRpcConnector::new(zebrad_uri.clone(), node_auth.clone())
+            .await
+            .get_block_hash(height)
+            .await?;
+        self.chain.write().await.insert_hash(height, hash);
+        Ok(hash)
+    }
+
+    /// Adds a compact block to the cache, replacing any block held at that height.
+    pub async fn insert(&self, height: u32, block: CompactBlock) {
+        self.chain.write().await.insert_block(height, block);
+    }
+
+    /// Adds a compact block to the cache, returning an error instead if a block above genesis holds no transactions.
+    ///
+    /// NOTE: Compact blocks omit transactions without shielded elements, so an empty block above genesis is only
+    /// suspicious where the caller knows the block should contain shielded transactions.
+    pub async fn insert_with_validation(
+        &self,
+        height: u32,
+        block: CompactBlock,
+    ) -> Result<(), BlockCacheError> {
+        if height > 0 && block.vtx.is_empty() {
+            return Err(BlockCacheError::EmptyBlock(height));
+        }
+        self.insert(height, block).await;
+        Ok(())
+    }
+
+    /// Adds a compact block to the cache at the height it records, returning an error if that height is out of range.
+    pub async fn insert_at_block_height(&self, block: CompactBlock) -> Result<(), BlockCacheError> {
+        let height = u32::try_from(block.height)
+            .map_err(|_| BlockCacheError::InvalidHeight(block.height))?;
+        self.insert(height, block).await;
+        Ok(())
+    }
+
+    /// Fetches the blocks at `heights` not already held in the cache from the node into the cache with `fetcher`.
+    ///
+    /// Progress is reported by the fetcher's [`FetchProgress`](crate::chain::fetcher::FetchProgress), whose total
+    /// only counts the blocks missing from the cache.
+    pub async fn warm(
+        &self,
+        zebrad_uri: &http::Uri,
+        node_auth: &NodeAuth,
+        heights: RangeInclusive<u32>,
+        fetcher: &BlockFetcher,
+        online: &AtomicBool,
+    ) -> FetchOutcome {
+        let heights: Vec<u32> = {
+            let chain = self.chain.read().await;
+            heights
+                .filter(|height| !chain.blocks.contains_key(height))
+                .collect()
+        };
+        let zebrad_uri = zebrad_uri.clone();
+        let node_auth = node_auth.clone();
+        fetcher
+            .fetch(
+                heights.into_iter(),
+                move |height| {
+                    let zebrad_uri = zebrad_uri.clone();
+                    let node_auth = node_auth.clone();
+                    async move { get_block_from_node(&zebrad_uri, &node_auth, &height).await }
+                },
+                self,
+                online,
+                &CancelSignal::default(),
+            )
+            .await
+    }
+
+    /// Returns the height of the cached block or block hash with the given hash, in internal byte order, `None` if the
+    /// cache holds neither.
+    pub async fn height_of(&self, hash: &[u8; 32]) -> Option<u32> {
+        self.chain.read().await.heights.get(hash).copied()
+    }
+
+    /// Returns true if the block at the given height is held in the cache.
+    pub async fn contains(&self, height: u32) -> bool {
+        self.chain.read().await.blocks.contains_key(&height)
+    }
+
+    /// Returns the number of blocks held in the cache.
+    pub async fn len(&self) -> usize {
+        self.chain.read().await.blocks.len()
+    }
+
+    /// Returns true if the cache holds no blocks.
+    pub async fn is_empty(&self) -> bool {
+        self.chain.read().await.blocks.is_empty()
+    }
+
+    /// Returns the height of the highest cached block.
+    pub async fn tip_height(&self) -> Option<u32> {
+        self.chain.read().await.blocks.keys().next_back().copied()
+    }
+
+    /// Returns the height and hash, in internal byte order, of the highest block held in the cache at or below
+    /// `node_tip - reorg_safe_depth`, the cache's finalized tip as seen from a node at `node_tip`.
+    ///
+    /// Returns `None` if the cache holds no block or block hash at or below that height.
+    pub async fn finalized_block_hash(&self, node_tip: u32) -> Option<(u32, [u8; 32])> {
+        let finalized = node_tip.saturating_sub(self.reorg_safe_depth);
+        let chain = self.chain.read().await;
+        let block = chain
+            .blocks
+            .range(..=finalized)
+            .rev()
+            .find_map(|(height, block)| block_hash(block).map(|hash| (*height, hash)));
+        let hash = chain
+            .hashes
+            .range(..=finalized)
+            .next_back()
+            .map(|(height, hash)| (*height, *hash));
+        match (block, hash) {
+            (Some(block), Some(hash)) => Some(if block.0 >= hash.0 { block } else { hash }),
+            (block, hash) => block.or(hash),
+        }
+    }
+
+    /// Evicts every block and block hash held in the cache, returns the number of blocks evicted.
+    pub async fn clear(&self) -> usize {
+        let mut chain = self.chain.write().await;
+        let evicted = chain.blocks.len();
+        *chain = CachedChain::default();
+        evicted
+    }
+
+    /// Evicts all blocks and block hashes below the given height, returns the number of blocks evicted.
+    pub async fn evict_below(&self, height: u32) -> usize {
+        self.chain.write().await.remove_below(height)
+    }
+
+    /// Evicts blocks below `keep_below_height`, or below `tip - reorg_safe_depth` if `None`.
+    ///
+    /// Returns the number of blocks evicted.
+    pub async fn gc(&self, keep_below_height: Option<u32>) -> usize {
+        let height = match keep_below_height {
+            Some(height) => height,
+            None => match self.tip_height().await {
+                Some(tip) => tip.saturating_sub(self.reorg_safe_depth),
+                None => return 0,
+            },
+        };
+        self.evict_below(height).await
+    }
+}
+
+/// Blocks delivered to the cache are inserted at their height, an error or an out of range height closes the sink.
+impl BlockSink<BlockCacheError> for BlockCache {
+    fn send_block(
+        &self,
+        block: Result<CompactBlock, BlockCacheError>,
+    ) -> impl Future<Output = bool> + Send {
+        async move {
+            match block {
+                Ok(block) => self.insert_at_block_height(block).await.is_ok(),
+                Err(_) => false,
+            }
+        }
+    }
+}
+
+/// Latest chain tip observed from the node, shared by the services reading it.
+///
+/// The tip is replaced on every observation, so a reorg onto a shorter chain lowers it.
+#[derive(Debug, Default)]
+pub struct ChainTip {
+    /// Height and hash, in internal byte order, of the latest tip observed.
+    tip: std::sync::RwLock<Option<(u32, [u8; 32])>>,
+}
+
+impl ChainTip {
+    /// Records the current chain tip, returning true if it differs from the tip previously observed.
+    pub fn observe(&self, height: u32, hash: [u8; 32]) -> bool {
+        let mut tip = self.tip.write().unwrap_or_else(|e| e.into_inner());
+        let changed = *tip != Some((height, hash));
+        *tip = Some((height, hash));
+        changed
+    }
+
+    /// Returns the height and hash of the latest tip observed, or `None` if no tip has been observed.
+    pub fn get(&self) -> Option<(u32, [u8; 32])> {
+        *self.tip.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the height of the latest tip observed, or `None` if no tip has been observed.
+    pub fn height(&self) -> Option<u32> {
+        self.get().map(|(height, _)| height)
+    }
+}
+
+/// Bounded, short lived cache of lookups the node reported as not found, keyed by a caller chosen lookup key.
+///
+/// Repeated lookups for the same missing txid, block or treestate are answered locally until the entry expires.
+/// All entries are dropped when a higher chain tip is observed, as the missing item may have just been mined.
+#[derive(Debug)]
+pub struct NegativeCache {
+    /// Missed lookups and the highest chain tip observed.
+    misses: RwLock<Misses>,
+    /// Maximum number of lookups held.
+    capacity: usize,
+    /// Time a lookup is held.
+    ttl: Duration,
+}
+
+/// Lookups held by a [`NegativeCache`].
+#[derive(Debug, Default)]
+struct Misses {
+    /// Missed lookups with the time they were recorded.
+    lookups: HashMap<String, Instant>,
+    /// Highest chain tip observed.
+    tip: u32,
+}
+
+impl Default for NegativeCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_NEGATIVE_CACHE_SIZE, DEFAULT_NEGATIVE_CACHE_TTL)
+    }
+}
+
+impl NegativeCache {
+    /// Returns an empty cache holding at most `capacity` lookups for `ttl` each.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        NegativeCache {
+            misses: RwLock::new(Misses::default()),
+            capacity,
+            ttl,
+        }
+    }
+
+    /// Returns true if the lookup was recorded as not found within the TTL.
+    pub async fn contains(&self, key: &str) -> bool {
+        self.misses
+            .read()
+            .await
+            .lookups
+            .get(key)
+            .is_some_and(|recorded| recorded.elapsed() < self.ttl)
+    }
+
+    /// Records a lookup the node reported as not found.
+    ///
+    /// Expired lookups are evicted when the cache is full, followed by the oldest lookup if none have expired.
+    pub async fn insert(&self, key: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut misses = self.misses.write().await;
+        if misses.lookups.len() >= self.capacity && !misses.lookups.contains_key(&key) {
+            let ttl = self.ttl;
+            misses
+                .lookups
+                .retain(|_, recorded| recorded.elapsed() < ttl);
+            if misses.lookups.len() >= self.capacity {
+                if let Some(oldest) = misses
+                    .lookups
+                    .iter()
+                    .min_by_key(|(_, recorded)| **recorded)
+                    .map(|(key, _)| key.clone())
+                {
+                    misses.lookups.remove(&oldest);
+                }
+            }
+        }
+        misses.lookups.insert(key, Instant::now());
+    }
+
+    /// Records the current chain tip, dropping all lookups if it is above the highest tip observed.
+    pub async fn observe_tip(&self, height: u32) {
+        if height <= self.misses.read().await.tip {
+            return;
+        }
+        let mut misses = self.misses.write().await;
+        if height > misses.tip {
+            misses.lookups.clear();
+            misses.tip = height;
+        }
+    }
+
+    /// Returns the number of lookups held, including expired lookups not yet evicted.
+    pub async fn len(&self) -> usize {
+        self.misses.read().await.lookups.len()
+    }
+
+    /// Returns true if the cache holds no lookups.
+    pub async fn is_empty(&self) -> bool {
+        self.misses.read().await.lookups.is_empty()
+    }
+}
@@ -13,6 +13,10 @@ pub enum ParseError {
     #[error("Invalid Data Error: {0}")]
     InvalidData(String),
 
+    /// Bytes were left unconsumed after parsing data expected to be consumed completely.
+    #[error("Trailing Bytes Error: {0} bytes left unconsumed")]
+    TrailingBytes(usize),
+
     // /// Errors from the JsonRPC client.
     // #[error("JsonRPC Connector Error: {0}")]
     // JsonRpcError(#[from] JsonRpcConnectorError),
@@ -42,6 +46,40 @@ pub enum BlockCacheError {
     /// Errors from the JsonRPC client.
     #[error("JsonRPC Connector Error: {0}")]
     JsonRpcError(#[from] JsonRpcConnectorError),
+    /// A block above genesis was found with no transactions.
+    #[error("Empty block at height {0}")]
+    EmptyBlock(u32),
+    /// A block fetch task panicked or was aborted.
+    #[error("Block Fetch Task Error: {0}")]
+    JoinError(#[from] tokio::task::JoinError),
+    /// A block does not link to the block streamed before it, the chain was reorganised mid-stream.
+    #[error("Block at height {0} does not link to the previous block in the range, chain reorg detected")]
+    ChainMismatch(u32),
+    /// A block's height does not fit the range of heights held by the cache.
+    #[error("Block height {0} is out of range")]
+    InvalidHeight(u64),
+}
+
+impl BlockCacheError {
+    /// Returns true if the error may not recur if the request is retried, eg. a dropped connection or timeout.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            BlockCacheError::JsonRpcError(
+                JsonRpcConnectorError::HyperError(_) | JsonRpcConnectorError::TimeoutError(_)
+            )
+        )
+    }
+}
+
+impl From<BlockCacheError> for tonic::Status {
+    fn from(err: BlockCacheError) -> Self {
+        match err {
+            BlockCacheError::JsonRpcError(e) => e.to_grpc_status(),
+            e @ BlockCacheError::ChainMismatch(_) => tonic::Status::aborted(e.to_string()),
+            e => tonic::Status::internal(e.to_string()),
+        }
+    }
 }
 
 /// Mempool Error struct.
@@ -50,4 +88,31 @@ pub enum MempoolError {
     /// Errors from the JsonRPC client.
     #[error("JsonRPC Connector Error: {0}")]
     JsonRpcError(#[from] JsonRpcConnectorError),
+
+    /// An exclude list entry is empty or longer than a txid.
+    #[error("Invalid exclude txid at index {index}: expected 1 to 32 bytes, found {len}")]
+    InvalidExcludeTxid {
+        /// Index of the entry in the exclude list.
+        index: usize,
+        /// Length of the entry in bytes.
+        len: usize,
+    },
+
+    /// The exclude list holds more entries than allowed.
+    #[error("Exclude list too long: {count} txids given, at most {max} allowed")]
+    ExcludeListTooLong {
+        /// Number of entries in the exclude list.
+        count: usize,
+        /// Maximum number of entries allowed.
+        max: usize,
+    },
+}
+
+impl From<MempoolError> for tonic::Status {
+    fn from(err: MempoolError) -> Self {
+        match err {
+            MempoolError::JsonRpcError(e) => e.to_grpc_status(),
+            e => tonic::Status::invalid_argument(e.to_string()),
+        }
+    }
 }
@@ -0,0 +1,311 @@
+//! Ordered, concurrency bounded compact block fetcher.
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::{mpsc, Notify},
+    task::JoinHandle,
+};
+
+use zaino_proto::proto::compact_formats::CompactBlock;
+
+use crate::chain::error::BlockCacheError;
+
+/// Default number of blocks fetched concurrently.
+pub const DEFAULT_FETCH_CONCURRENCY: usize = 8;
+
+/// Default number of times a block fetch is retried after a transient failure.
+pub const DEFAULT_FETCH_RETRIES: u32 = 3;
+
+/// Default delay between retries of a block fetch.
+pub const DEFAULT_FETCH_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Progress of a [`BlockFetcher`] run, shared between clones.
+#[derive(Debug, Clone, Default)]
+pub struct FetchProgress {
+    total: Arc<AtomicUsize>,
+    fetched: Arc<AtomicUsize>,
+}
+
+impl FetchProgress {
+    /// Returns the number of blocks requested in the current run, as hinted by the iterator of heights requested.
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of blocks delivered to the sink in the current run.
+    pub fn fetched(&self) -> usize {
+        self.fetched.load(Ordering::SeqCst)
+    }
+}
+
+/// Cancels a single [`BlockFetcher`] run, eg. the run serving one request, shared between clones.
+///
+/// Unlike clearing the `online` flag, which stops every run, only the runs given this signal are cancelled. The
+/// run's in flight fetches are aborted without waiting for them to complete.
+#[derive(Debug, Clone, Default)]
+pub struct CancelSignal {
+    cancelled: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl CancelSignal {
+    /// Cancels the runs given this signal.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    /// Returns true if the signal has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns once the signal is cancelled.
+    pub async fn cancelled(&self) {
+        let notified = self.notify.notified();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Destination of the blocks delivered by a [`BlockFetcher`].
+pub trait BlockSink<E>: Sync {
+    /// Sends a block, or the error that ended the run, to the sink.
+    ///
+    /// Returns false if the sink is closed and will not accept further blocks.
+    fn send_block(&self, block: Result<CompactBlock, E>) -> impl Future<Output = bool> + Send;
+}
+
+impl<E: Send> BlockSink<E> for mpsc::Sender<Result<CompactBlock, E>> {
+    fn send_block(&self, block: Result<CompactBlock, E>) -> impl Future<Output = bool> + Send {
+        async move { self.send(block).await.is_ok() }
+    }
+}
+
+/// Result of a [`BlockFetcher::fetch`] run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchOutcome {
+    /// All requested blocks were delivered to the sink.
+    Completed,
+    /// The run was cancelled before all blocks were delivered.
+    Cancelled,
+    /// The sink was closed before all blocks were delivered.
+    SinkClosed,
+    /// The block at the given height could not be fetched, the error was delivered to the sink.
+    Failed(u32),
+}
+
+/// Fetches compact blocks for a list of heights with bounded concurrency, delivering them to a sink in the
+/// order requested.
+///
+/// Transient failures (see [`BlockCacheError::is_transient`]) are retried, any other failure is delivered to the
+/// sink and ends the run. Blocks at consecutive heights must link by hash, otherwise the run ends with
+/// [`BlockCacheError::ChainMismatch`].
+#[derive(Debug, Clone)]
+pub struct BlockFetcher {
+    concurrency: usize,
+    max_retries: u32,
+    retry_delay: Duration,
+    progress: FetchProgress,
+}
+
+impl Default for BlockFetcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_FETCH_CONCURRENCY)
+    }
+}
+
+impl BlockFetcher {
+    /// Returns a new BlockFetcher fetching at most `concurrency` blocks at a time.
+    pub fn new(concurrency: usize) -> Self {
+        BlockFetcher {
+            concurrency: concurrency.max(1),
+            max_retries: DEFAULT_FETCH_RETRIES,
+            retry_delay: DEFAULT_FETCH_RETRY_DELAY,
+            progress: FetchProgress::default(),
+        }
+    }
+
+    /// Sets the number of retries after a transient failure and the delay between them.
+    pub fn with_retries(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Returns a handle to the progress of the fetcher's current run.
+    pub fn progress(&self) -> FetchProgress {
+        self.progress.clone()
+    }
+
+    /// Fetches the blocks at `heights` with `fetch_block`, sending them to `sink` in the order given.
+    ///
+    /// Heights are drawn from the iterator as fetches are started. The run is cancelled once `online` is cleared or
+    /// `cancel` is cancelled, in flight fetches are aborted.
+    pub async fn fetch<F, Fut, S, E>(
+        &self,
+        heights: impl Iterator<Item = u32>,
+        fetch_block: F,
+        sink: &S,
+        online: &AtomicBool,
+        cancel: &CancelSignal,
+    ) -> FetchOutcome
+    where
+        F: Fn(u32) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = Result<CompactBlock, BlockCacheError>> + Send + 'static,
+        S: BlockSink<E>,
+        E: From<BlockCacheError>,
+    {
+        let (min_heights, max_heights) = heights.size_hint();
+        self.progress
+            .total
+            .store(max_heights.unwrap_or(min_heights), Ordering::SeqCst);
+        self.progress.fetched.store(0, Ordering::SeqCst);
+
+        let mut heights = heights;
+        // Header fields of the last block sent, used to check the next block links to it.
+        let mut previous: Option<CompactBlock> = None;
+        let mut in_flight: VecDeque<(u32, JoinHandle<Result<CompactBlock, BlockCacheError>>)> =
+            VecDeque::new();
+        let outcome = loop {
+            while in_flight.len() < self.concurrency {
+                match heights.next() {
+                    Some(height) => {
+                        in_flight.push_back((height, self.spawn_fetch(&fetch_block, height)))
+                    }
+                    None => break,
+                }
+            }
+            let Some((height, mut handle)) = in_flight.pop_front() else {
+                break FetchOutcome::Completed;
+            };
+            if !online.load(Ordering::SeqCst) || cancel.is_cancelled() {
+                handle.abort();
+                break FetchOutcome::Cancelled;
+            }
+            let fetched = tokio::select! {
+                fetched = &mut handle => fetched,
+                _ = cancel.cancelled() => {
+                    handle.abort();
+                    break FetchOutcome::Cancelled;
+                }
+            };
+            let result =
+                fetched
+                    .unwrap_or_else(|e| Err(e.into()))
+                    .and_then(|block| match &previous {
+                        Some(previous) if !links(previous, &block) => {
+                            Err(BlockCacheError::ChainMismatch(height))
+                        }
+                        _ => Ok(block),
+                    });
+            if !online.load(Ordering::SeqCst) || cancel.is_cancelled() {
+                break FetchOutcome::Cancelled;
+            }
+            match result {
+                Ok(block) => {
+                    let header = CompactBlock {
+                        height: block.height,
+                        hash: block.hash.clone(),
+                        prev_hash: block.prev_hash.clone(),
+                        ..Default::default()
+                    };
+                    if !sink.send_block(Ok(block)).await {
+                        break FetchOutcome::SinkClosed;
+                    }
+                    previous = Some(header);
+                    self.progress.fetched.fetch_add(1, Ordering::SeqCst);
+                }
+                Err(e) => {
+                    sink.send_block(Err(E::from(e))).await;
+                    break FetchOutcome::Failed(height);
+                }
+            }
+        };
+        for (_, handle) in in_flight {
+            handle.abort();
+        }
+        outcome
+    }
+
+    /// Spawns a task fetching the block at `height`, retrying transient failures.
+    fn spawn_fetch<F, Fut>(
+        &self,
+        fetch_block: &F,
+        height: u32,
+    ) -> JoinHandle<Result<CompactBlock, BlockCacheError>>
+    where
+        F: Fn(u32) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = Result<CompactBlock, BlockCacheError>> + Send + 'static,
+    {
+        let fetch_block = fetch_block.clone();
+        let max_retries = self.max_retries;
+        let retry_delay = self.retry_delay;
+        tokio::spawn(async move {
+            let mut attempt = 0;
+            loop {
+                match fetch_block(height).await {
+                    Err(e) if e.is_transient() && attempt < max_retries => {
+                        attempt += 1;
+                        tokio::time::sleep(retry_delay).await;
+                    }
+                    result => return result,
+                }
+            }
+        })
+    }
+}
+
+/// Returns true unless `previous` and `block` are at consecutive heights and their hashes do not link.
+fn links(previous: &CompactBlock, block: &CompactBlock) -> bool {
+    if block.height + 1 == previous.height {
+        previous.prev_hash == block.hash
+    } else if previous.height + 1 == block.height {
+        block.prev_hash == previous.hash
+    } else {
+        true
+    }
+}
+
+/// Streams the blocks from `start` to `end` inclusive into `sink`, in descending order if `start` is above `end`.
+///
+/// Blocks are fetched with `fetch_block`, at most `concurrency` at a time, by a [`BlockFetcher`], which checks that
+/// consecutive blocks link so a reorg during the stream is reported rather than mixing blocks from both chains. The
+/// stream stops once `online` is cleared or `cancel` is cancelled.
+pub async fn stream_block_range<F, Fut, S, E>(
+    fetch_block: F,
+    start: u32,
+    end: u32,
+    concurrency: usize,
+    sink: &S,
+    online: &AtomicBool,
+    cancel: &CancelSignal,
+) -> FetchOutcome
+where
+    F: Fn(u32) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<CompactBlock, BlockCacheError>> + Send + 'static,
+    S: BlockSink<E>,
+    E: From<BlockCacheError>,
+{
+    let ascending = start <= end;
+    let heights = (0..=start.abs_diff(end)).map(move |offset| {
+        if ascending {
+            start + offset
+        } else {
+            start - offset
+        }
+    });
+    BlockFetcher::new(concurrency)
+        .fetch(heights, fetch_block, sink, online, cancel)
+        .await
+}
@@ -1,10 +1,21 @@
 //! Zingo-Indexer mempool state functionality.
 
-use std::{collections::HashSet, time::SystemTime};
+use hex::FromHex;
+use prost::Message;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
 use tokio::sync::{Mutex, RwLock};
+use zaino_proto::proto::compact_formats::CompactTx;
 
 use crate::{
-    chain::error::MempoolError, jsonrpc::connector::JsonRpcConnector,
+    chain::error::MempoolError,
+    jsonrpc::connector::{JsonRpcConnector, NodeAuth},
     primitives::block::BlockHash,
 };
 
@@ -38,30 +49,36 @@ impl Mempool {
     }
 
     /// Updates the mempool, returns true if the current block in the mempool has been mined.
-    pub async fn update(&self, zebrad_uri: &http::Uri) -> Result<bool, MempoolError> {
+    pub async fn update(
+        &self,
+        zebrad_uri: &http::Uri,
+        node_auth: &NodeAuth,
+    ) -> Result<bool, MempoolError> {
         self.update_last_sync_time().await?;
-        let mined = self.check_and_update_best_block_hash(zebrad_uri).await?;
+        let mined = self
+            .check_and_update_best_block_hash(zebrad_uri, node_auth)
+            .await?;
         if mined {
             self.reset_txids().await?;
-            self.update_txids(zebrad_uri).await?;
+            self.update_txids(zebrad_uri, node_auth).await?;
             Ok(true)
         } else {
-            self.update_txids(zebrad_uri).await?;
+            self.update_txids(zebrad_uri, node_auth).await?;
             Ok(false)
         }
     }
 
     /// Updates the txids in the mempool.
-    async fn update_txids(&self, zebrad_uri: &http::Uri) -> Result<(), MempoolError> {
-        let node_txids = JsonRpcConnector::new(
-            zebrad_uri.clone(),
-            Some("xxxxxx".to_string()),
-            Some("xxxxxx".to_string()),
-        )
-        .await
-        .get_raw_mempool()
-        .await?
-        .transactions;
+    async fn update_txids(
+        &self,
+        zebrad_uri: &http::Uri,
+        node_auth: &NodeAuth,
+    ) -> Result<(), MempoolError> {
+        let node_txids = JsonRpcConnector::new(zebrad_uri.clone(), node_auth.clone())
+            .await
+            .get_raw_mempool(false)
+            .await?
+            .txids();
         let mut txids_seen = self.txids_seen.lock().await;
         let mut txids = self.txids.write().await;
         for txid in node_txids {
@@ -84,16 +101,13 @@ impl Mempool {
     async fn check_and_update_best_block_hash(
         &self,
         zebrad_uri: &http::Uri,
+        node_auth: &NodeAuth,
     ) -> Result<bool, MempoolError> {
-        let node_best_block_hash = JsonRpcConnector::new(
-            zebrad_uri.clone(),
-            Some("xxxxxx".to_string()),
-            Some("xxxxxx".to_string()),
-        )
-        .await
-        .get_blockchain_info()
-        .await?
-        .best_block_hash;
+        let node_best_block_hash = JsonRpcConnector::new(zebrad_uri.clone(), node_auth.clone())
+            .await
+            .get_blockchain_info()
+            .await?
+            .best_block_hash;
 
         let mut last_best_block_hash = self.best_block_hash.write().await;
 
@@ -128,3 +142,299 @@ impl Mempool {
         Ok(*best_block_hash)
     }
 }
+
+/// Default maximum number of entries accepted in a GetMempoolTx exclude list.
+pub const DEFAULT_MAX_MEMPOOL_EXCLUDE_ENTRIES: usize = 10_000;
+
+/// Length of a full txid in bytes.
+const TXID_LEN: usize = 32;
+
+/// Validated exclude list of a GetMempoolTx request.
+///
+/// Entries are txid prefixes in the byte order of the CompactTx hash, each is reversed and matched against the
+/// start of the display order txid, as lightwalletd does. A prefix matching more than one mempool transaction is
+/// ambiguous and excludes none of them, prefixes matching no transaction are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct MempoolExclude {
+    /// Exclude prefixes, in display byte order.
+    prefixes: Vec<Vec<u8>>,
+}
+
+impl MempoolExclude {
+    /// Validates the exclude list, rejecting empty entries, entries longer than a txid and lists of more than
+    /// `max_entries` entries.
+    pub fn new(txids: Vec<Vec<u8>>, max_entries: usize) -> Result<Self, MempoolError> {
+        if txids.len() > max_entries {
+            return Err(MempoolError::ExcludeListTooLong {
+                count: txids.len(),
+                max: max_entries,
+            });
+        }
+        if let Some((index, txid)) = txids
+            .iter()
+            .enumerate()
+            .find(|(_, txid)| txid.is_empty() || txid.len() > TXID_LEN)
+        {
+            return Err(MempoolError::InvalidExcludeTxid {
+                index,
+                len: txid.len(),
+            });
+        }
+        Ok(MempoolExclude {
+            prefixes: txids
+                .into_iter()
+                .map(|mut txid| {
+                    txid.reverse();
+                    txid
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns true if the exclude list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+
+    /// Returns the display order hex `txids` not excluded, in their original order.
+    ///
+    /// The txids are sorted once and each prefix is binary searched, so filtering takes O((n + m) log n) for n
+    /// txids and m prefixes. Txids that are not valid hex are never excluded.
+    pub fn filter(&self, txids: Vec<String>) -> Vec<String> {
+        if self.is_empty() {
+            return txids;
+        }
+        let mut sorted: Vec<([u8; TXID_LEN], usize)> = txids
+            .iter()
+            .enumerate()
+            .filter_map(|(index, txid)| {
+                <[u8; TXID_LEN]>::from_hex(txid)
+                    .ok()
+                    .map(|bytes| (bytes, index))
+            })
+            .collect();
+        sorted.sort_unstable();
+
+        let mut excluded = vec![false; txids.len()];
+        for prefix in &self.prefixes {
+            let start = sorted.partition_point(|(txid, _)| txid.as_slice() < prefix.as_slice());
+            let mut matches = sorted[start..]
+                .iter()
+                .take_while(|(txid, _)| txid.starts_with(prefix));
+            if let (Some((_, index)), None) = (matches.next(), matches.next()) {
+                excluded[*index] = true;
+            }
+        }
+        txids
+            .into_iter()
+            .zip(excluded)
+            .filter_map(|(txid, excluded)| (!excluded).then_some(txid))
+            .collect()
+    }
+}
+
+/// Default maximum size of the mempool transaction cache, in bytes.
+pub const DEFAULT_MEMPOOL_CACHE_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// A mempool transaction held in the [`MempoolTxCache`], in raw and compact form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedMempoolTx {
+    /// Raw transaction bytes.
+    pub raw: Vec<u8>,
+    /// Compact form of the transaction, as returned by GetMempoolTx.
+    pub compact: CompactTx,
+    /// Fee paid by the transaction, in zatoshis.
+    pub fee: u64,
+    /// Time the transaction entered the node's mempool, in seconds since the Unix epoch.
+    pub time: i64,
+}
+
+impl CachedMempoolTx {
+    /// Returns the memory charged to the cache for the transaction, the size of its raw and compact forms.
+    pub fn size(&self) -> usize {
+        self.raw.len() + self.compact.encoded_len()
+    }
+}
+
+/// Position of a cached transaction in the eviction order, transactions ordered first are evicted first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EvictionKey {
+    /// Fee paid, in zatoshis.
+    fee: u64,
+    /// Raw transaction size in bytes, the fee rate is `fee / tx_size`.
+    tx_size: u64,
+    /// Time the transaction entered the node's mempool.
+    time: i64,
+    /// Txid, so keys of distinct transactions never compare equal.
+    txid: String,
+}
+
+impl EvictionKey {
+    fn new(txid: &str, tx: &CachedMempoolTx) -> Self {
+        EvictionKey {
+            fee: tx.fee,
+            tx_size: (tx.raw.len() as u64).max(1),
+            time: tx.time,
+            txid: txid.to_string(),
+        }
+    }
+}
+
+impl Ord for EvictionKey {
+    /// Orders by fee rate, lowest first, then by age, oldest first.
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Compares fee / tx_size exactly, without rounding.
+        (u128::from(self.fee) * u128::from(other.tx_size))
+            .cmp(&(u128::from(other.fee) * u128::from(self.tx_size)))
+            .then(self.time.cmp(&other.time))
+            .then_with(|| self.txid.cmp(&other.txid))
+    }
+}
+
+impl PartialOrd for EvictionKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Size and eviction counters of a [`MempoolTxCache`], shared between clones.
+#[derive(Debug, Clone, Default)]
+pub struct MempoolCacheStats {
+    max_bytes: usize,
+    size_bytes: Arc<AtomicUsize>,
+    transactions: Arc<AtomicUsize>,
+    evictions: Arc<AtomicU64>,
+}
+
+impl MempoolCacheStats {
+    /// Returns the configured maximum size of the cache, in bytes.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Returns the memory charged to the cached transactions, in bytes.
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of cached transactions.
+    pub fn transactions(&self) -> usize {
+        self.transactions.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of transactions evicted to keep the cache under its maximum size.
+    pub fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::SeqCst)
+    }
+}
+
+/// Cached transactions, indexed by txid and by eviction order.
+#[derive(Debug, Default)]
+struct MempoolCacheEntries {
+    transactions: HashMap<String, CachedMempoolTx>,
+    eviction_order: BTreeSet<EvictionKey>,
+    size_bytes: usize,
+}
+
+impl MempoolCacheEntries {
+    fn remove(&mut self, txid: &str) -> Option<CachedMempoolTx> {
+        let tx = self.transactions.remove(txid)?;
+        self.eviction_order.remove(&EvictionKey::new(txid, &tx));
+        self.size_bytes -= tx.size();
+        Some(tx)
+    }
+}
+
+/// Mempool transactions cached in raw and compact form, bounded in size.
+///
+/// Once the cached transactions exceed the maximum size, transactions are evicted lowest fee rate first and, at equal
+/// fee rates, oldest first, mirroring the node's own mempool eviction so transactions clients still ask for are rarely
+/// evicted. Evicted transactions are fetched from the node again on demand.
+#[derive(Debug)]
+pub struct MempoolTxCache {
+    entries: Mutex<MempoolCacheEntries>,
+    stats: MempoolCacheStats,
+}
+
+impl Default for MempoolTxCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMPOOL_CACHE_MAX_BYTES)
+    }
+}
+
+impl MempoolTxCache {
+    /// Returns an empty cache holding at most `max_bytes` of transactions, 0 disables caching.
+    pub fn new(max_bytes: usize) -> Self {
+        MempoolTxCache {
+            entries: Mutex::new(MempoolCacheEntries::default()),
+            stats: MempoolCacheStats {
+                max_bytes,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Returns the cache's size and eviction counters.
+    pub fn stats(&self) -> MempoolCacheStats {
+        self.stats.clone()
+    }
+
+    /// Returns the cached transaction with the given display order hex txid.
+    pub async fn get(&self, txid: &str) -> Option<CachedMempoolTx> {
+        self.entries.lock().await.transactions.get(txid).cloned()
+    }
+
+    /// Caches a transaction, replacing any cached transaction with the same txid, then evicts transactions until the
+    /// cache is within its maximum size. The transaction itself is evicted if it is first in the eviction order.
+    ///
+    /// Transactions larger than the cache's maximum size are not cached. Returns the txids evicted, in eviction order.
+    pub async fn insert(&self, txid: String, tx: CachedMempoolTx) -> Vec<String> {
+        if tx.size() > self.stats.max_bytes {
+            return Vec::new();
+        }
+        let mut entries = self.entries.lock().await;
+        entries.remove(&txid);
+        entries.size_bytes += tx.size();
+        entries.eviction_order.insert(EvictionKey::new(&txid, &tx));
+        entries.transactions.insert(txid, tx);
+        let mut evicted = Vec::new();
+        while entries.size_bytes > self.stats.max_bytes {
+            let Some(key) = entries.eviction_order.first().cloned() else {
+                break;
+            };
+            entries.remove(&key.txid);
+            evicted.push(key.txid);
+        }
+        self.stats
+            .evictions
+            .fetch_add(evicted.len() as u64, Ordering::SeqCst);
+        self.update_stats(&entries);
+        evicted
+    }
+
+    /// Drops cached transactions that are not in `mempool_txids`, eg. once they are mined. Dropped transactions are
+    /// not counted as evictions.
+    pub async fn retain(&self, mempool_txids: &[String]) {
+        let mempool_txids: HashSet<&str> = mempool_txids.iter().map(String::as_str).collect();
+        let mut entries = self.entries.lock().await;
+        let dropped: Vec<String> = entries
+            .transactions
+            .keys()
+            .filter(|txid| !mempool_txids.contains(txid.as_str()))
+            .cloned()
+            .collect();
+        for txid in dropped {
+            entries.remove(&txid);
+        }
+        self.update_stats(&entries);
+    }
+
+    fn update_stats(&self, entries: &MempoolCacheEntries) {
+        self.stats
+            .size_bytes
+            .store(entries.size_bytes, Ordering::SeqCst);
+        self.stats
+            .transactions
+            .store(entries.transactions.len(), Ordering::SeqCst);
+    }
+}
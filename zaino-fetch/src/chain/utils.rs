@@ -19,6 +19,31 @@ pub trait ParseFromSlice {
     ) -> Result<(&[u8], Self), ParseError>
     where
         Self: Sized;
+
+    /// Reads data from a bytestring that is expected to hold exactly one instance of self.
+    ///
+    /// Returns [`ParseError::TrailingBytes`] if any data is left unconsumed, as this indicates a parsing bug or a
+    /// malformed input rather than further data.
+    fn parse_exact(
+        data: &[u8],
+        txid: Option<Vec<Vec<u8>>>,
+        tx_version: Option<u32>,
+    ) -> Result<Self, ParseError>
+    where
+        Self: Sized,
+    {
+        let (remaining_data, parsed) = Self::parse_from_slice(data, txid, tx_version)?;
+        if !remaining_data.is_empty() {
+            eprintln!(
+                "Warning: {} of {} bytes left unconsumed parsing {}.",
+                remaining_data.len(),
+                data.len(),
+                std::any::type_name::<Self>()
+            );
+            return Err(ParseError::TrailingBytes(remaining_data.len()));
+        }
+        Ok(parsed)
+    }
 }
 
 /// Skips the next n bytes in cursor, returns error message given if eof is reached.
@@ -43,6 +68,20 @@ pub fn read_bytes(
     Ok(buf)
 }
 
+/// Reads the next 32 bytes from cursor into a fixed size array, used for hash fields, returns error message given if eof is reached..
+pub fn read_u256(cursor: &mut Cursor<&[u8]>, error_msg: &str) -> Result<[u8; 32], ParseError> {
+    let mut buf = [0; 32];
+    buf.copy_from_slice(&read_bytes(cursor, 32, error_msg)?);
+    Ok(buf)
+}
+
+/// Reads the next 64 bytes from cursor into a fixed size array, used for signature fields, returns error message given if eof is reached..
+pub fn read_u512(cursor: &mut Cursor<&[u8]>, error_msg: &str) -> Result<[u8; 64], ParseError> {
+    let mut buf = [0; 64];
+    buf.copy_from_slice(&read_bytes(cursor, 64, error_msg)?);
+    Ok(buf)
+}
+
 /// Reads the next 8 bytes from cursor into a u64, returns error message given if eof is reached..
 pub fn read_u64(cursor: &mut Cursor<&[u8]>, error_msg: &str) -> Result<u64, ParseError> {
     cursor
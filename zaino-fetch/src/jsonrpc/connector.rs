@@ -7,15 +7,27 @@ use hyper::{http, Body, Client, Request};
 use hyper_tls::HttpsConnector;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::sync::atomic::{AtomicI32, Ordering};
-
-use crate::jsonrpc::{
-    error::JsonRpcConnectorError,
-    response::{
-        BestBlockHashResponse, GetBalanceResponse, GetBlockResponse, GetBlockchainInfoResponse,
-        GetInfoResponse, GetSubtreesResponse, GetTransactionResponse, GetTreestateResponse,
-        GetUtxosResponse, SendTransactionResponse, TxidsResponse,
+use std::{
+    cell::Cell,
+    future::Future,
+    path::PathBuf,
+    sync::atomic::{AtomicI32, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    jsonrpc::{
+        error::JsonRpcConnectorError,
+        normalizer::NodeResponseNormalizer,
+        response::{
+            BestBlockHashResponse, GetAddressMempoolResponse, GetBalanceResponse, GetBlockHash,
+            GetBlockResponse, GetBlockchainInfoResponse, GetInfoResponse, GetMiningInfoResponse,
+            GetRawMempoolResponse, GetSubtreesResponse, GetTransactionResponse,
+            GetTreestateResponse, GetUtxosResponse, SendTransactionResponse, TxidsResponse,
+            ZTransactionInfo,
+        },
     },
+    primitives::secret::Secret,
 };
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -41,37 +53,254 @@ struct RpcError {
     data: Option<Value>,
 }
 
+tokio::task_local! {
+    /// Time spent waiting on node responses within a [`with_node_timer`] scope.
+    static NODE_REQUEST_TIME: Cell<Duration>;
+}
+
+/// Runs a future, returning its output along with the total time spent waiting on JsonRPC requests to the node.
+///
+/// Only requests sent from the task running the future are measured, requests sent from spawned tasks are not.
+pub async fn with_node_timer<F: Future>(future: F) -> (F::Output, Duration) {
+    NODE_REQUEST_TIME
+        .scope(Cell::new(Duration::ZERO), async {
+            let output = future.await;
+            (output, NODE_REQUEST_TIME.with(|time| time.get()))
+        })
+        .await
+}
+
+/// Full node implementation the JsonRpcConnector is connected to.
+///
+/// Used where zcashd and zebrad do not serve an RPC in the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeType {
+    /// Zcashd full node.
+    Zcashd,
+    /// Zebrad full node.
+    Zebrad,
+}
+
+impl NodeType {
+    /// Detects the node type from the `subversion` user-agent returned by `getinfo`.
+    ///
+    /// Zebrad reports a user-agent of the form "/Zebra:1.0.0/", any other user-agent is treated as zcashd.
+    pub fn detect(info: &GetInfoResponse) -> NodeType {
+        if info.subversion.to_lowercase().contains("zebra") {
+            NodeType::Zebrad
+        } else {
+            NodeType::Zcashd
+        }
+    }
+}
+
+impl std::fmt::Display for NodeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeType::Zcashd => write!(f, "zcashd"),
+            NodeType::Zebrad => write!(f, "zebrad"),
+        }
+    }
+}
+
+impl std::str::FromStr for NodeType {
+    type Err = JsonRpcConnectorError;
+
+    /// Parses a node type, accepting "zcashd"/"zcash" and "zebrad"/"zebra" in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "zcashd" | "zcash" => Ok(NodeType::Zcashd),
+            "zebrad" | "zebra" => Ok(NodeType::Zebrad),
+            _ => Err(JsonRpcConnectorError::new(format!(
+                "Unknown node type: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Configured full node implementation, either given explicitly or detected from the node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeTypeSelection {
+    /// Detect the node type from the node's `getinfo` user-agent, see [`NodeType::detect`].
+    #[default]
+    Auto,
+    /// The node is zcashd, detection is skipped.
+    Zcashd,
+    /// The node is zebrad, detection is skipped.
+    Zebrad,
+}
+
+impl NodeTypeSelection {
+    /// Returns the explicitly selected node type, `None` in `Auto` mode.
+    pub fn explicit(self) -> Option<NodeType> {
+        match self {
+            NodeTypeSelection::Auto => None,
+            NodeTypeSelection::Zcashd => Some(NodeType::Zcashd),
+            NodeTypeSelection::Zebrad => Some(NodeType::Zebrad),
+        }
+    }
+
+    /// Returns the selected node type, querying the node's `getinfo` RPC through `connector` only in `Auto` mode.
+    pub async fn resolve(
+        self,
+        connector: &JsonRpcConnector,
+    ) -> Result<NodeType, JsonRpcConnectorError> {
+        match self.explicit() {
+            Some(node_type) => Ok(node_type),
+            None => Ok(NodeType::detect(&connector.get_info().await?)),
+        }
+    }
+}
+
+/// Fee check override passed to [`JsonRpcConnector::send_raw_transaction`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeeLimit {
+    /// Accepts transactions paying absurdly high fees, zcashd's `allowhighfees` flag.
+    AllowHighFees,
+    /// Rejects transactions paying a fee rate above the given rate in ZEC/kB, the `maxfeerate` parameter.
+    ///
+    /// Not supported by zcashd, which only accepts `allowhighfees`.
+    MaxFeeRate(f64),
+}
+
+/// Credentials used to authenticate with the full node's JsonRPC server.
+///
+/// The password is redacted when debug formatted or serialized.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method")]
+pub enum NodeAuth {
+    /// Requests are sent without credentials.
+    #[default]
+    None,
+    /// HTTP basic auth with the given user and password, zcashd's `rpcuser` and `rpcpassword`.
+    UserPass {
+        /// RPC user name.
+        user: String,
+        /// RPC password.
+        password: Secret<String>,
+    },
+    /// HTTP basic auth with the `<user>:<password>` credentials the node writes to its cookie file.
+    ///
+    /// The file is read for each request, so credentials regenerated on a node restart are picked up.
+    Cookie {
+        /// Path of the node's cookie file.
+        path: PathBuf,
+    },
+}
+
+impl NodeAuth {
+    /// Returns user / password credentials.
+    pub fn user_pass(user: impl Into<String>, password: impl Into<String>) -> Self {
+        NodeAuth::UserPass {
+            user: user.into(),
+            password: Secret::new(password.into()),
+        }
+    }
+
+    /// Returns the value of the `Authorization` header sent with each request, if any.
+    pub fn authorization_header(&self) -> Result<Option<String>, JsonRpcConnectorError> {
+        let credentials = match self {
+            NodeAuth::None => return Ok(None),
+            NodeAuth::UserPass { user, password } => format!("{}:{}", user, password.expose()),
+            NodeAuth::Cookie { path } => std::fs::read_to_string(path)
+                .map_err(|e| {
+                    JsonRpcConnectorError::CustomError(format!(
+                        "Failed to read node cookie file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?
+                .trim()
+                .to_string(),
+        };
+        Ok(Some(format!("Basic {}", base64::encode(credentials))))
+    }
+}
+
+/// Headers sent with requests to the node, any other header is never sent.
+///
+/// These are the HTTP transport headers and the node credentials of the connector's [`NodeAuth`].
+pub const NODE_REQUEST_HEADERS: [&str; 4] =
+    ["host", "content-type", "content-length", "authorization"];
+
 /// JsonRPC Client config data.
+///
+/// The connector is the boundary between client requests and the node. Requests to the node are built only from the
+/// connector's own configuration and the JSON-RPC method and params of each call, there is no way to pass request
+/// metadata (client headers, addresses, request ids) through it, so nothing identifying a client reaches the node.
+/// The headers sent are listed in [`NODE_REQUEST_HEADERS`].
 #[derive(Debug)]
 pub struct JsonRpcConnector {
     uri: http::Uri,
     id_counter: AtomicI32,
-    user: Option<String>,
-    password: Option<String>,
+    auth: NodeAuth,
+    node_type: NodeType,
 }
 
 impl JsonRpcConnector {
     /// Returns a new JsonRpcConnector instance, tests uri and returns error if connection is not established.
-    pub async fn new(uri: http::Uri, user: Option<String>, password: Option<String>) -> Self {
+    ///
+    /// The node is assumed to be zcashd, use [`JsonRpcConnector::detect_node_type`] to query the node.
+    pub async fn new(uri: http::Uri, auth: NodeAuth) -> Self {
         Self {
             uri,
             id_counter: AtomicI32::new(0),
-            user,
-            password,
+            auth,
+            node_type: NodeType::Zcashd,
         }
     }
 
+    /// Sets the node type of the JsonRpcConnector.
+    pub fn with_node_type(mut self, node_type: NodeType) -> Self {
+        self.node_type = node_type;
+        self
+    }
+
+    /// Queries the node's `getinfo` RPC and sets the node type of the JsonRpcConnector from the response.
+    pub async fn detect_node_type(mut self) -> Result<Self, JsonRpcConnectorError> {
+        let info = self.get_info().await?;
+        self.node_type = NodeType::detect(&info);
+        Ok(self)
+    }
+
     /// Returns the uri the JsonRpcConnector is configured to send requests to.
     pub fn uri(&self) -> &Uri {
         &self.uri
     }
 
+    /// Returns the credentials the JsonRpcConnector authenticates with.
+    pub fn auth(&self) -> &NodeAuth {
+        &self.auth
+    }
+
+    /// Returns the node type the JsonRpcConnector is configured for.
+    pub fn node_type(&self) -> NodeType {
+        self.node_type
+    }
+
     /// Sends a jsonRPC request and returns the response.
     ///
+    /// The time spent on the request is added to the current [`with_node_timer`] scope, if any.
+    async fn send_request<T: Serialize, R: for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        params: T,
+    ) -> Result<R, JsonRpcConnectorError> {
+        let start = Instant::now();
+        let response = self.send_request_inner(method, params).await;
+        NODE_REQUEST_TIME
+            .try_with(|time| time.set(time.get() + start.elapsed()))
+            .ok();
+        response
+    }
+
+    /// Sends a jsonRPC request and returns the response, normalized for the configured node type.
+    ///
     /// TODO: This function currently resends the call up to 5 times on a server response of "Work queue depth exceeded".
     /// This is because the node's queue can become overloaded and stop servicing RPCs.
     /// This functionality is weak and should be incorporated in Zingo-Indexer's queue mechanism [WIP] that handles various errors appropriately.
-    async fn send_request<T: Serialize, R: for<'de> Deserialize<'de>>(
+    async fn send_request_inner<T: Serialize, R: for<'de> Deserialize<'de>>(
         &self,
         method: &str,
         params: T,
@@ -92,10 +321,8 @@ impl JsonRpcConnector {
                 .method("POST")
                 .uri(self.uri.clone())
                 .header("Content-Type", "application/json");
-            if let (Some(user), Some(password)) = (&self.user, &self.password) {
-                let auth = base64::encode(format!("{}:{}", user, password));
-                request_builder =
-                    request_builder.header("Authorization", format!("Basic {}", auth));
+            if let Some(authorization) = self.auth.authorization_header()? {
+                request_builder = request_builder.header("Authorization", authorization);
             }
             let request_body =
                 serde_json::to_string(&req).map_err(JsonRpcConnectorError::SerdeJsonError)?;
@@ -106,6 +333,9 @@ impl JsonRpcConnector {
                 .request(request)
                 .await
                 .map_err(JsonRpcConnectorError::HyperError)?;
+            if response.status() == http::StatusCode::UNAUTHORIZED {
+                return Err(JsonRpcConnectorError::Unauthorized);
+            }
             let body_bytes = hyper::body::to_bytes(response.into_body())
                 .await
                 .map_err(JsonRpcConnectorError::HyperError)?;
@@ -120,14 +350,17 @@ impl JsonRpcConnector {
                 tokio::time::sleep(std::time::Duration::from_millis(500)).await;
                 continue;
             }
-            let response: RpcResponse<R> = serde_json::from_slice(&body_bytes)
+            let response: RpcResponse<Value> = serde_json::from_slice(&body_bytes)
                 .map_err(JsonRpcConnectorError::SerdeJsonError)?;
             return match response.error {
-                Some(error) => Err(JsonRpcConnectorError::new(format!(
-                    "RPC Error {}: {}",
-                    error.code, error.message
-                ))),
-                None => Ok(response.result),
+                Some(error) => Err(JsonRpcConnectorError::RpcError {
+                    code: error.code,
+                    message: error.message,
+                }),
+                None => serde_json::from_value(
+                    NodeResponseNormalizer::new(self.node_type).normalize(method, response.result),
+                )
+                .map_err(JsonRpcConnectorError::SerdeJsonError),
             };
         }
     }
@@ -154,6 +387,42 @@ impl JsonRpcConnector {
             .await
     }
 
+    /// Returns mining related information, including the estimated network solution rate and difficulty.
+    ///
+    /// zcashd reference: [`getmininginfo`](https://zcash.github.io/rpc/getmininginfo.html)
+    /// method: post
+    /// tags: mining
+    pub async fn get_mining_info(&self) -> Result<GetMiningInfoResponse, JsonRpcConnectorError> {
+        self.send_request::<(), GetMiningInfoResponse>("getmininginfo", ())
+            .await
+    }
+
+    /// Returns the estimated network solution rate, in Sol/s.
+    ///
+    /// zcashd reference: [`getnetworksolps`](https://zcash.github.io/rpc/getnetworksolps.html)
+    /// method: post
+    /// tags: mining
+    ///
+    /// # Parameters
+    ///
+    /// - `blocks`: (numeric, optional, default=120) The number of blocks to average over, -1 for the blocks since the
+    ///   last difficulty change.
+    /// - `height`: (numeric, optional, default=-1) The height to estimate the rate at, -1 for the chain tip.
+    pub async fn get_network_sol_ps(
+        &self,
+        blocks: Option<i32>,
+        height: Option<i32>,
+    ) -> Result<f64, JsonRpcConnectorError> {
+        let mut params = Vec::new();
+        if blocks.is_some() || height.is_some() {
+            params.push(serde_json::to_value(blocks.unwrap_or(120))?);
+        }
+        if let Some(height) = height {
+            params.push(serde_json::to_value(height)?);
+        }
+        self.send_request("getnetworksolps", params).await
+    }
+
     /// Returns the total balance of a provided `addresses` in an [`AddressBalance`] instance.
     ///
     /// zcashd reference: [`getaddressbalance`](https://zcash.github.io/rpc/getaddressbalance.html)
@@ -164,14 +433,34 @@ impl JsonRpcConnector {
     ///
     /// - `address_strings`: (object, example={"addresses": ["tmYXBYJj1K7vhejSec5osXK2QsGa5MTisUQ"]}) A JSON map with a single entry
     ///     - `addresses`: (array of strings) A list of base-58 encoded addresses.
-    ///
-    /// NOTE: Currently unused by Zingo-Indexer and untested!
     pub async fn get_address_balance(
         &self,
         addresses: Vec<String>,
     ) -> Result<GetBalanceResponse, JsonRpcConnectorError> {
-        let params = vec![serde_json::to_value(addresses)?];
-        self.send_request("getaddressbalance", params).await
+        let params = serde_json::json!({ "addresses": addresses });
+        self.send_request("getaddressbalance", vec![params]).await
+    }
+
+    /// Returns the total transparent balance of the provided `addresses`, in zatoshis.
+    ///
+    /// Zcashd is queried with `getaddressbalance`, for zebrad the balance is summed from the addresses' `getaddressutxos` response.
+    ///
+    /// # Parameters
+    ///
+    /// - `addresses`: (array of strings) A list of base-58 encoded addresses.
+    pub async fn get_address_balance_multi(
+        &self,
+        addresses: Vec<String>,
+    ) -> Result<u64, JsonRpcConnectorError> {
+        match self.node_type {
+            NodeType::Zcashd => Ok(self.get_address_balance(addresses).await?.balance),
+            NodeType::Zebrad => Ok(self
+                .get_address_utxos(addresses)
+                .await?
+                .iter()
+                .map(|utxo| utxo.satoshis)
+                .sum()),
+        }
     }
 
     /// Sends the raw bytes of a signed transaction to the local node's mempool, if the transaction is valid.
@@ -184,11 +473,26 @@ impl JsonRpcConnector {
     /// # Parameters
     ///
     /// - `raw_transaction_hex`: (string, required, example="signedhex") The hex-encoded raw transaction bytes.
+    /// - `fee_limit`: (optional) Overrides the node's absurd fee check, see [`FeeLimit`].
+    ///
+    /// Transactions rejected for their fee return an error for which
+    /// [`JsonRpcConnectorError::is_fee_rejection`] is true.
     pub async fn send_raw_transaction(
         &self,
         raw_transaction_hex: String,
+        fee_limit: Option<FeeLimit>,
     ) -> Result<SendTransactionResponse, JsonRpcConnectorError> {
-        let params = vec![serde_json::to_value(raw_transaction_hex)?];
+        let params = match fee_limit {
+            Some(FeeLimit::AllowHighFees) => vec![
+                serde_json::to_value(raw_transaction_hex)?,
+                serde_json::to_value(true)?,
+            ],
+            Some(FeeLimit::MaxFeeRate(rate)) => vec![
+                serde_json::to_value(raw_transaction_hex)?,
+                serde_json::to_value(rate)?,
+            ],
+            None => vec![serde_json::to_value(raw_transaction_hex)?],
+        };
         self.send_request("sendrawtransaction", params).await
     }
 
@@ -236,14 +540,42 @@ impl JsonRpcConnector {
             .await
     }
 
-    /// Returns all transaction ids in the memory pool, as a JSON array.
+    /// Returns the hash of the block at the given height in the best chain.
+    ///
+    /// The hash is returned in internal (little-endian) byte order, as used by [`CompactBlock::hash`]. Reverse it
+    /// for the display order returned by the node.
+    ///
+    /// zcashd reference: [`getblockhash`](https://zcash.github.io/rpc/getblockhash.html)
+    /// method: post
+    /// tags: blockchain
+    ///
+    /// # Parameters
+    ///
+    /// - `height`: (numeric, required, example=1) The block height.
+    ///
+    /// [`CompactBlock::hash`]: zaino_proto::proto::compact_formats::CompactBlock::hash
+    pub async fn get_block_hash(&self, height: u32) -> Result<[u8; 32], JsonRpcConnectorError> {
+        let params = vec![serde_json::to_value(height)?];
+        let hash: GetBlockHash = self.send_request("getblockhash", params).await?;
+        Ok(hash.0 .0)
+    }
+
+    /// Returns all transaction ids in the memory pool, as a JSON array, or the mempool entries, keyed by txid, if
+    /// verbose is set.
     ///
     /// zcashd reference: [`getrawmempool`](https://zcash.github.io/rpc/getrawmempool.html)
     /// method: post
     /// tags: blockchain
-    pub async fn get_raw_mempool(&self) -> Result<TxidsResponse, JsonRpcConnectorError> {
-        self.send_request::<(), TxidsResponse>("getrawmempool", ())
-            .await
+    ///
+    /// # Parameters
+    ///
+    /// - `verbose`: (boolean, optional, default=false) True for a json object, false for array of transaction ids.
+    pub async fn get_raw_mempool(
+        &self,
+        verbose: bool,
+    ) -> Result<GetRawMempoolResponse, JsonRpcConnectorError> {
+        let params = vec![serde_json::to_value(verbose)?];
+        self.send_request("getrawmempool", params).await
     }
 
     /// Returns information about the given block's Sapling & Orchard tree state.
@@ -274,8 +606,6 @@ impl JsonRpcConnector {
     /// - `pool`: (string, required) The pool from which subtrees should be returned. Either "sapling" or "orchard".
     /// - `start_index`: (number, required) The index of the first 2^16-leaf subtree to return.
     /// - `limit`: (number, optional) The maximum number of subtree values to return.
-    ///
-    /// NOTE: Currently unused by Zingo-Indexer and untested!
     pub async fn get_subtrees_by_index(
         &self,
         pool: String,
@@ -319,6 +649,24 @@ impl JsonRpcConnector {
         self.send_request("getrawtransaction", params).await
     }
 
+    /// Returns metadata of a transaction, including its mined height, as a [`ZTransactionInfo`].
+    ///
+    /// method: post
+    /// tags: transaction
+    ///
+    /// # Parameters
+    ///
+    /// - `txid`: (string, required, example="mytxid") The transaction ID of the transaction to be returned.
+    ///
+    /// NOTE: Not served by all node versions, callers should treat errors as missing metadata.
+    pub async fn get_z_transaction(
+        &self,
+        txid: &str,
+    ) -> Result<ZTransactionInfo, JsonRpcConnectorError> {
+        let params = vec![serde_json::to_value(txid)?];
+        self.send_request("z_gettransaction", params).await
+    }
+
     /// Returns the transaction ids made by the provided transparent addresses.
     ///
     /// zcashd reference: [`getaddresstxids`](https://zcash.github.io/rpc/getaddresstxids.html)
@@ -356,33 +704,47 @@ impl JsonRpcConnector {
     ///
     /// - `addresses`: (array, required, example={\"addresses\": [\"tmYXBYJj1K7vhejSec5osXK2QsGa5MTisUQ\"]}) The addresses to get outputs from.
     ///
-    /// NOTE: Currently unused by Zingo-Indexer and untested!
+    /// NOTE: Requires zcashd to be run with `-insightexplorer`, not served by zebrad.
     pub async fn get_address_utxos(
         &self,
         addresses: Vec<String>,
     ) -> Result<Vec<GetUtxosResponse>, JsonRpcConnectorError> {
-        let params = vec![serde_json::to_value(addresses)?];
-        self.send_request("getaddressutxos", params).await
+        let params = serde_json::json!({ "addresses": addresses });
+        self.send_request("getaddressutxos", vec![params]).await
+    }
+
+    /// Returns the transparent balance changes of the provided addresses made by transactions in the mempool.
+    ///
+    /// zcashd reference: [`getaddressmempool`](https://zcash.github.io/rpc/getaddressmempool.html)
+    /// method: post
+    /// tags: address
+    ///
+    /// # Parameters
+    ///
+    /// - `addresses`: (array, required, example={\"addresses\": [\"tmYXBYJj1K7vhejSec5osXK2QsGa5MTisUQ\"]}) The addresses to get mempool deltas for.
+    ///
+    /// NOTE: Requires zcashd to be run with `-insightexplorer`, not served by zebrad.
+    pub async fn get_address_mempool(
+        &self,
+        addresses: Vec<String>,
+    ) -> Result<Vec<GetAddressMempoolResponse>, JsonRpcConnectorError> {
+        let params = serde_json::json!({ "addresses": addresses });
+        self.send_request("getaddressmempool", vec![params]).await
     }
 }
 
 /// Tests connection with zebrad / zebrad.
-async fn test_node_connection(
-    uri: Uri,
-    user: Option<String>,
-    password: Option<String>,
-) -> Result<(), JsonRpcConnectorError> {
+async fn test_node_connection(uri: Uri, auth: &NodeAuth) -> Result<(), JsonRpcConnectorError> {
     let client = Client::builder().build::<_, Body>(HttpsConnector::new());
 
-    let user = user.unwrap_or_else(|| "xxxxxx".to_string());
-    let password = password.unwrap_or_else(|| "xxxxxx".to_string());
-    let encoded_auth = base64::encode(format!("{}:{}", user, password));
-
-    let request = Request::builder()
+    let mut request_builder = Request::builder()
         .method("POST")
         .uri(uri.clone())
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Basic {}", encoded_auth))
+        .header("Content-Type", "application/json");
+    if let Some(authorization) = auth.authorization_header()? {
+        request_builder = request_builder.header("Authorization", authorization);
+    }
+    let request = request_builder
         .body(Body::from(
             r#"{"jsonrpc":"2.0","method":"getinfo","params":[],"id":1}"#,
         ))
@@ -391,6 +753,9 @@ async fn test_node_connection(
         tokio::time::timeout(tokio::time::Duration::from_secs(3), client.request(request))
             .await
             .map_err(JsonRpcConnectorError::TimeoutError)??;
+    if response.status() == http::StatusCode::UNAUTHORIZED {
+        return Err(JsonRpcConnectorError::Unauthorized);
+    }
     let body_bytes = hyper::body::to_bytes(response.into_body())
         .await
         .map_err(JsonRpcConnectorError::HyperError)?;
@@ -400,11 +765,26 @@ async fn test_node_connection(
 }
 
 /// Tries to connect to zebrad/zcashd using IPv4 and IPv6 and returns the correct uri type, exits program with error message if connection cannot be established.
+///
+/// A node that rejects the given credentials is reachable, its uri is returned and credentials are checked on first use.
 pub async fn test_node_and_return_uri(
     port: &u16,
-    user: Option<String>,
-    password: Option<String>,
+    auth: &NodeAuth,
 ) -> Result<Uri, JsonRpcConnectorError> {
+    match probe_node_uri(port, auth).await {
+        Err(JsonRpcConnectorError::CustomError(_)) => {
+            eprintln!("Could not establish connection with node. \nPlease check config and confirm node is listening at the correct address and the correct authorisation details have been entered. \nExiting..");
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+/// Tries to connect to zebrad/zcashd using IPv4 and IPv6 and returns the correct uri type.
+///
+/// A node that rejects the given credentials is reachable, its uri is returned and credentials are checked on first use.
+/// Returns a [`JsonRpcConnectorError::CustomError`] if no connection can be established.
+pub async fn probe_node_uri(port: &u16, auth: &NodeAuth) -> Result<Uri, JsonRpcConnectorError> {
     let ipv4_uri: Uri = format!("http://127.0.0.1:{}", port)
         .parse()
         .map_err(JsonRpcConnectorError::InvalidUriError)?;
@@ -413,25 +793,33 @@ pub async fn test_node_and_return_uri(
         .map_err(JsonRpcConnectorError::InvalidUriError)?;
     let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(500));
     for _ in 0..3 {
-        match test_node_connection(ipv4_uri.clone(), user.clone(), password.clone()).await {
+        match test_node_connection(ipv4_uri.clone(), auth).await {
             Ok(_) => {
                 println!("Connected to node using IPv4 at address {}.", ipv4_uri);
                 return Ok(ipv4_uri);
             }
-            Err(_e_ipv4) => {
-                match test_node_connection(ipv6_uri.clone(), user.clone(), password.clone()).await {
-                    Ok(_) => {
-                        println!("Connected to node using IPv6 at address {}.", ipv6_uri);
-                        return Ok(ipv6_uri);
-                    }
-                    Err(_e_ipv6) => {
-                        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-                    }
-                }
+            Err(JsonRpcConnectorError::Unauthorized) => {
+                eprintln!("Node at address {} rejected credentials.", ipv4_uri);
+                return Ok(ipv4_uri);
             }
+            Err(_e_ipv4) => match test_node_connection(ipv6_uri.clone(), auth).await {
+                Ok(_) => {
+                    println!("Connected to node using IPv6 at address {}.", ipv6_uri);
+                    return Ok(ipv6_uri);
+                }
+                Err(JsonRpcConnectorError::Unauthorized) => {
+                    eprintln!("Node at address {} rejected credentials.", ipv6_uri);
+                    return Ok(ipv6_uri);
+                }
+                Err(_e_ipv6) => {
+                    tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+                }
+            },
         }
         interval.tick().await;
     }
-    eprintln!("Could not establish connection with node. \nPlease check config and confirm node is listening at the correct address and the correct authorisation details have been entered. \nExiting..");
-    std::process::exit(1);
+    Err(JsonRpcConnectorError::CustomError(format!(
+        "Could not establish connection with node on port {}.",
+        port
+    )))
 }
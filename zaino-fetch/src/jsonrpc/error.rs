@@ -30,24 +30,89 @@ pub enum JsonRpcConnectorError {
     /// Request Timeout Errors.
     #[error("Request Timeout Error")]
     TimeoutError(#[from] tokio::time::error::Elapsed),
+
+    /// The node rejected the configured credentials.
+    #[error("Node rejected credentials (HTTP 401 Unauthorized)")]
+    Unauthorized,
+
+    /// Error returned by the node in a JsonRPC response.
+    #[error("RPC Error {code}: {message}")]
+    RpcError {
+        /// JsonRPC error code.
+        code: i32,
+        /// Error message returned by the node.
+        message: String,
+    },
 }
 
+/// JsonRPC error codes returned for unknown transactions and blocks.
+///
+/// zcashd returns `-5` (`RPC_INVALID_ADDRESS_OR_KEY`), zebrad returns `-8` for blocks not in its state.
+pub const RPC_NOT_FOUND_ERROR_CODES: [i32; 2] = [-5, -8];
+
+/// JsonRPC error code returned for submitted transactions that fail to decode (`RPC_DESERIALIZATION_ERROR`).
+pub const RPC_DESERIALIZATION_ERROR: i32 = -22;
+
+/// JsonRPC error code returned for transactions rejected by the node's mempool (`RPC_TRANSACTION_REJECTED`).
+pub const RPC_TRANSACTION_REJECTED: i32 = -26;
+
+/// Reject reasons reported by the node for transactions rejected for their fee.
+pub const FEE_REJECTION_REASONS: [&str; 5] = [
+    "absurdly-high-fee",
+    "max-fee-exceeded",
+    "insufficient fee",
+    "min relay fee not met",
+    "mempool min fee not met",
+];
+
 impl JsonRpcConnectorError {
     /// Constructor for errors without an underlying source
     pub fn new(msg: impl Into<String>) -> Self {
         JsonRpcConnectorError::CustomError(msg.into())
     }
 
+    /// Returns true if the node reported the requested transaction or block does not exist.
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            JsonRpcConnectorError::RpcError { code, .. } if RPC_NOT_FOUND_ERROR_CODES.contains(code)
+        )
+    }
+
+    /// Returns true if the node rejected a submitted transaction for paying too high or too low a fee.
+    pub fn is_fee_rejection(&self) -> bool {
+        matches!(
+            self,
+            JsonRpcConnectorError::RpcError { code, message }
+                if *code == RPC_TRANSACTION_REJECTED
+                    && FEE_REJECTION_REASONS.iter().any(|reason| message.contains(reason))
+        )
+    }
+
+    /// Returns true if the node could not be reached, as opposed to the node answering with an error.
+    pub fn is_connectivity_error(&self) -> bool {
+        matches!(
+            self,
+            JsonRpcConnectorError::HyperError(_) | JsonRpcConnectorError::TimeoutError(_)
+        )
+    }
+
     /// Maps JsonRpcConnectorError to tonic::Status
+    ///
+    /// The node being unreachable maps to UNAVAILABLE, unknown transactions and blocks to NOT_FOUND and transactions
+    /// that fail to decode to INVALID_ARGUMENT. Responses the server fails to parse are the server's or node's fault,
+    /// not the client's, and map to INTERNAL.
     pub fn to_grpc_status(&self) -> tonic::Status {
         eprintln!("Error occurred: {}.", self);
 
         match self {
-            JsonRpcConnectorError::SerdeJsonError(_) => {
-                tonic::Status::invalid_argument(self.to_string())
-            }
-            JsonRpcConnectorError::HyperError(_) => tonic::Status::unavailable(self.to_string()),
-            JsonRpcConnectorError::HttpError(_) => tonic::Status::internal(self.to_string()),
+            e if e.is_connectivity_error() => tonic::Status::unavailable(self.to_string()),
+            JsonRpcConnectorError::Unauthorized => tonic::Status::unauthenticated(self.to_string()),
+            e if e.is_not_found() => tonic::Status::not_found(self.to_string()),
+            JsonRpcConnectorError::RpcError {
+                code: RPC_DESERIALIZATION_ERROR,
+                ..
+            } => tonic::Status::invalid_argument(self.to_string()),
             _ => tonic::Status::internal(self.to_string()),
         }
     }
@@ -0,0 +1,68 @@
+//! Normalizes JsonRPC responses from zcashd and zebrad into a shared format.
+//!
+//! Zcashd and zebrad serve the same RPCs but differ in which fields they include. Responses are normalized
+//! before they are deserialized, so the response types in [`crate::jsonrpc::response`] only handle one format.
+//!
+//! Both nodes return block and transaction hashes hex-encoded in display (big-endian) order, so hashes need no
+//! normalization.
+
+use serde_json::{json, Map, Value};
+
+use crate::jsonrpc::connector::NodeType;
+
+/// Converts JsonRPC responses from the given node type into the shared response format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeResponseNormalizer {
+    node_type: NodeType,
+}
+
+impl NodeResponseNormalizer {
+    /// Returns a normalizer for responses from the given node type.
+    pub fn new(node_type: NodeType) -> Self {
+        NodeResponseNormalizer { node_type }
+    }
+
+    /// Returns the node type the normalizer is configured for.
+    pub fn node_type(&self) -> NodeType {
+        self.node_type
+    }
+
+    /// Normalizes the `result` of a response to the given JsonRPC method.
+    ///
+    /// Responses to methods without known differences are returned unchanged.
+    pub fn normalize(&self, method: &str, result: Value) -> Value {
+        match (method, result) {
+            ("getblock", Value::Object(block)) => Value::Object(self.normalize_block(block)),
+            ("getrawtransaction", Value::Object(transaction)) => {
+                Value::Object(self.normalize_transaction(transaction))
+            }
+            (_, result) => result,
+        }
+    }
+
+    /// Normalizes a verbose `getblock` response.
+    ///
+    /// Zebrad omits the note commitment tree size of pools that are not yet active, these are returned as empty trees.
+    fn normalize_block(&self, mut block: Map<String, Value>) -> Map<String, Value> {
+        if let Some(Value::Object(trees)) = block.get_mut("trees") {
+            for pool in ["sapling", "orchard"] {
+                trees
+                    .entry(pool.to_string())
+                    .or_insert_with(|| json!({ "size": 0 }));
+            }
+        }
+        block
+    }
+
+    /// Normalizes a verbose `getrawtransaction` response.
+    ///
+    /// Zcashd omits the height and confirmations of mempool transactions, these are returned as a height of -1 and
+    /// 0 confirmations, matching zebrad.
+    fn normalize_transaction(&self, mut transaction: Map<String, Value>) -> Map<String, Value> {
+        if self.node_type == NodeType::Zcashd && !transaction.contains_key("height") {
+            transaction.insert("height".to_string(), json!(-1));
+            transaction.insert("confirmations".to_string(), json!(0));
+        }
+        transaction
+    }
+}
@@ -6,7 +6,10 @@ use serde::Deserialize;
 use crate::primitives::{
     address::TransparentAddress,
     block::{BlockHash, SerializedBlock},
-    chain::{ConsensusBranchIdHex, NetworkUpgradeInfo, TipConsensusBranch},
+    chain::{
+        ConsensusBranchIdHex, NetworkUpgrade, NetworkUpgradeActivation, NetworkUpgradeInfo,
+        NetworkUpgradeStatus, TipConsensusBranch,
+    },
     height::ChainHeight,
     transaction::{
         BlockCommitmentTreeSize, CommitmentTreestate, NoteCommitmentSubtreeIndex, OrchardTreestate,
@@ -53,6 +56,74 @@ pub struct GetBlockchainInfoResponse {
     pub consensus: TipConsensusBranch,
 }
 
+impl GetBlockchainInfoResponse {
+    /// Returns the height and hash of the chain tip.
+    ///
+    /// Both are read from this single response, so they always describe the same block, where separate node calls
+    /// could straddle a new block.
+    pub fn tip(&self) -> (ChainHeight, BlockHash) {
+        (self.blocks, self.best_block_hash)
+    }
+
+    /// Returns the activation height of the given network upgrade, `None` if the upgrade is disabled.
+    pub fn activation_height(&self, upgrade: NetworkUpgrade) -> Option<ChainHeight> {
+        self.upgrades
+            .values()
+            .find(|info| info.name == upgrade && info.status != NetworkUpgradeStatus::Disabled)
+            .map(|info| info.activation_height)
+    }
+
+    /// Returns the node's network upgrade activation schedule, ordered by activation height.
+    ///
+    /// Includes every upgrade the node reports, disabled upgrades included.
+    pub fn network_upgrades(&self) -> Vec<NetworkUpgradeActivation> {
+        let mut upgrades: Vec<NetworkUpgradeActivation> = self
+            .upgrades
+            .iter()
+            .map(|(branch_id, info)| NetworkUpgradeActivation {
+                name: info.name,
+                branch_id: branch_id.0,
+                activation_height: info.activation_height,
+                status: info.status,
+            })
+            .collect();
+        upgrades.sort_by_key(|upgrade| upgrade.activation_height);
+        upgrades
+    }
+
+    /// Returns true if the given network upgrade is active at the given height.
+    pub fn is_active_at(&self, upgrade: NetworkUpgrade, height: ChainHeight) -> bool {
+        self.activation_height(upgrade)
+            .map_or(false, |activation_height| height >= activation_height)
+    }
+}
+
+/// Response to a `getmininginfo` RPC request.
+///
+/// This is used for the output parameter of [`JsonRpcConnector::get_mining_info`].
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct GetMiningInfoResponse {
+    /// The current block height of the node's best chain.
+    pub blocks: ChainHeight,
+
+    /// Proof-of-work difficulty of the chain tip, as a multiple of the minimum difficulty.
+    ///
+    /// Not returned by Zebra.
+    #[serde(default)]
+    pub difficulty: Option<f64>,
+
+    /// Estimated network solution rate, in Sol/s, over the last 120 blocks.
+    #[serde(rename = "networksolps")]
+    pub network_sol_ps: f64,
+
+    /// Estimated network hash rate, in H/s. Zcash reports the solution rate here as well.
+    #[serde(rename = "networkhashps")]
+    pub network_hash_ps: f64,
+
+    /// Current network name as defined in BIP70 (main, test, regtest)
+    pub chain: String,
+}
+
 /// The transparent balance of a set of addresses.
 ///
 /// This is used for the output parameter of [`JsonRpcConnector::get_address_balance`].
@@ -125,7 +196,7 @@ pub struct BestBlockHashResponse(#[serde(with = "hex")] pub BlockHash);
 
 /// Vec of transaction ids, as a JSON array.
 ///
-/// This is used for the output parameter of [`JsonRpcConnector::get_raw_mempool`] and [`JsonRpcConnector::get_address_txids`].
+/// This is used for the output parameter of [`JsonRpcConnector::get_address_txids`].
 #[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
 pub struct TxidsResponse {
     /// Vec of txids.
@@ -150,6 +221,47 @@ impl<'de> Deserialize<'de> for TxidsResponse {
     }
 }
 
+/// Response to a `getrawmempool` RPC request.
+///
+/// This is used for the output parameter of [`JsonRpcConnector::get_raw_mempool`].
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+pub enum GetRawMempoolResponse {
+    /// The txids of the transactions in the mempool, returned if verbose is false.
+    Txids(Vec<String>),
+    /// The mempool entries, keyed by txid, returned if verbose is true.
+    Verbose(IndexMap<String, MempoolEntry>),
+}
+
+impl GetRawMempoolResponse {
+    /// Returns the txids of the transactions in the mempool, in the order given by the node.
+    pub fn txids(self) -> Vec<String> {
+        match self {
+            GetRawMempoolResponse::Txids(txids) => txids,
+            GetRawMempoolResponse::Verbose(entries) => entries.into_keys().collect(),
+        }
+    }
+}
+
+/// A transaction in the mempool, as returned by a verbose `getrawmempool` RPC request.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct MempoolEntry {
+    /// The transaction size in bytes.
+    pub size: u64,
+    /// The transaction fee in ZEC.
+    pub fee: f64,
+    /// The transaction fee with fee deltas used for mining priority, in ZEC.
+    #[serde(default)]
+    pub modifiedfee: f64,
+    /// The time the transaction entered the mempool, in seconds since the Unix epoch.
+    pub time: i64,
+    /// The chain height when the transaction entered the mempool.
+    pub height: u32,
+    /// The txids of unconfirmed transactions used as inputs by this transaction.
+    #[serde(default)]
+    pub depends: Vec<String>,
+}
+
 /// Contains the hex-encoded Sapling & Orchard note commitment trees, and their
 /// corresponding [`block::Hash`], [`Height`], and block time.
 ///
@@ -190,27 +302,18 @@ impl<'de> Deserialize<'de> for GetTreestateResponse {
         let time = v["time"]
             .as_i64()
             .ok_or_else(|| serde::de::Error::missing_field("time"))? as u32;
-        let sapling_final_state = v["sapling"]["commitments"]["finalState"]
-            .as_str()
-            .ok_or_else(|| serde::de::Error::missing_field("sapling final state"))?
-            .to_string();
-        let orchard_final_state = v["orchard"]["commitments"]["finalState"]
-            .as_str()
-            .ok_or_else(|| serde::de::Error::missing_field("orchard final state"))?
-            .to_string();
+        // NOTE: Pre-activation commitment trees may be absent or empty, these are returned as empty trees.
+        let sapling_final_state = v["sapling"]["commitments"]["finalState"].as_str();
+        let orchard_final_state = v["orchard"]["commitments"]["finalState"].as_str();
         Ok(GetTreestateResponse {
             height,
             hash,
             time,
             sapling: SaplingTreestate {
-                commitments: CommitmentTreestate {
-                    final_state: sapling_final_state,
-                },
+                commitments: CommitmentTreestate::from_final_state(sapling_final_state),
             },
             orchard: OrchardTreestate {
-                commitments: CommitmentTreestate {
-                    final_state: orchard_final_state,
-                },
+                commitments: CommitmentTreestate::from_final_state(orchard_final_state),
             },
         })
     }
@@ -300,9 +403,9 @@ pub struct GetSubtreesResponse {
     pub subtrees: Vec<SubtreeRpcData>,
 }
 
-/// This is used for the output parameter of [`JsonRpcConnector::get_address_utxos`].
+/// An unspent transparent output of an address.
 ///
-/// *** UNTESTED - TEST BEFORE USE ***
+/// This is used for the output parameter of [`JsonRpcConnector::get_address_utxos`].
 #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct GetUtxosResponse {
     /// The transparent address, base58check encoded
@@ -326,3 +429,44 @@ pub struct GetUtxosResponse {
     /// The block height, numeric.
     pub height: ChainHeight,
 }
+
+/// A mempool transparent balance change of an address.
+///
+/// This is used for the output parameter of [`JsonRpcConnector::get_address_mempool`].
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct GetAddressMempoolResponse {
+    /// The transparent address, base58check encoded
+    pub address: TransparentAddress,
+
+    /// The mempool txid, in big-endian order, hex-encoded
+    pub txid: String,
+
+    /// The index of the transaction input or output spending from or paying to the address
+    pub index: u32,
+
+    /// The change in the address's balance, in zatoshis, negative for spends
+    pub satoshis: i64,
+
+    /// Unix time when the transaction entered the mempool
+    pub timestamp: i64,
+}
+
+/// Transaction metadata returned by `z_gettransaction`.
+///
+/// Only the fields used by Zingo-Indexer are deserialized, all are optional as they differ between node versions.
+///
+/// This is used for the output parameter of [`JsonRpcConnector::get_z_transaction`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ZTransactionInfo {
+    /// The transaction id, in big-endian order, hex-encoded
+    #[serde(default)]
+    pub txid: Option<String>,
+
+    /// The height of the block containing the transaction, absent or negative if the transaction is unmined
+    #[serde(default)]
+    pub height: Option<i64>,
+
+    /// The number of confirmations of the transaction, 0 if the transaction is unmined
+    #[serde(default)]
+    pub confirmations: Option<i64>,
+}
@@ -7,4 +7,5 @@ pub mod block;
 pub mod chain;
 pub mod error;
 pub mod height;
+pub mod secret;
 pub mod transaction;
@@ -1,6 +1,6 @@
 //! Hold primitives relating to zcash addresses.
 
-use crate::primitives::chain::NetworkKind;
+use crate::primitives::{chain::NetworkKind, error::AddressError};
 use std::fmt;
 
 /// *** THE FOLLOWING CODE IS CURRENTLY UNUSED BY ZINGO-PROXY AND UNTESTED! ***
@@ -18,8 +18,8 @@ use std::fmt;
 ///
 /// <https://zips.z.cash/protocol/protocol.pdf#transparentaddrencoding>
 ///
-/// *** UNTESTED - TEST BEFORE USE ***
-#[derive(Clone, Eq, PartialEq, Hash, serde::Deserialize, serde::Serialize)]
+/// Serialized as its Base58Check encoding, the form returned by the node's address RPCs.
+#[derive(Clone, Eq, PartialEq, Hash)]
 pub enum TransparentAddress {
     /// P2SH (Pay to Script Hash) addresses
     PayToScriptHash {
@@ -61,3 +61,141 @@ impl fmt::Debug for TransparentAddress {
         }
     }
 }
+
+/// Base58Check version prefix of mainnet P2PKH addresses ("t1").
+const MAINNET_P2PKH_PREFIX: [u8; 2] = [0x1c, 0xb8];
+/// Base58Check version prefix of mainnet P2SH addresses ("t3").
+const MAINNET_P2SH_PREFIX: [u8; 2] = [0x1c, 0xbd];
+/// Base58Check version prefix of testnet and regtest P2PKH addresses ("tm").
+const TESTNET_P2PKH_PREFIX: [u8; 2] = [0x1d, 0x25];
+/// Base58Check version prefix of testnet and regtest P2SH addresses ("t2").
+const TESTNET_P2SH_PREFIX: [u8; 2] = [0x1c, 0xba];
+
+impl TransparentAddress {
+    /// Parses a Base58Check encoded transparent address.
+    ///
+    /// Testnet and regtest share address prefixes, addresses with these prefixes are returned as [`NetworkKind::Testnet`].
+    pub fn from_encoded(address: &str) -> Result<Self, String> {
+        let bytes = bs58::decode(address)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| e.to_string())?;
+        if bytes.len() != 22 {
+            return Err(format!("expected 22 decoded bytes, found {}", bytes.len()));
+        }
+        let mut hash = [0u8; 20];
+        hash.copy_from_slice(&bytes[2..]);
+        match [bytes[0], bytes[1]] {
+            MAINNET_P2PKH_PREFIX => Ok(TransparentAddress::PayToPublicKeyHash {
+                network_kind: NetworkKind::Mainnet,
+                pub_key_hash: hash,
+            }),
+            MAINNET_P2SH_PREFIX => Ok(TransparentAddress::PayToScriptHash {
+                network_kind: NetworkKind::Mainnet,
+                script_hash: hash,
+            }),
+            TESTNET_P2PKH_PREFIX => Ok(TransparentAddress::PayToPublicKeyHash {
+                network_kind: NetworkKind::Testnet,
+                pub_key_hash: hash,
+            }),
+            TESTNET_P2SH_PREFIX => Ok(TransparentAddress::PayToScriptHash {
+                network_kind: NetworkKind::Testnet,
+                script_hash: hash,
+            }),
+            prefix => Err(format!("unknown address prefix {}", hex::encode(prefix))),
+        }
+    }
+
+    /// Returns the network the address was encoded for.
+    pub fn network_kind(&self) -> NetworkKind {
+        match self {
+            TransparentAddress::PayToScriptHash { network_kind, .. }
+            | TransparentAddress::PayToPublicKeyHash { network_kind, .. } => *network_kind,
+        }
+    }
+
+    /// Returns the Base58Check encoding of the address.
+    ///
+    /// Regtest addresses are encoded with the testnet prefixes.
+    pub fn encode(&self) -> String {
+        let (prefix, hash) = match self {
+            TransparentAddress::PayToScriptHash {
+                network_kind: NetworkKind::Mainnet,
+                script_hash,
+            } => (MAINNET_P2SH_PREFIX, script_hash),
+            TransparentAddress::PayToScriptHash { script_hash, .. } => {
+                (TESTNET_P2SH_PREFIX, script_hash)
+            }
+            TransparentAddress::PayToPublicKeyHash {
+                network_kind: NetworkKind::Mainnet,
+                pub_key_hash,
+            } => (MAINNET_P2PKH_PREFIX, pub_key_hash),
+            TransparentAddress::PayToPublicKeyHash { pub_key_hash, .. } => {
+                (TESTNET_P2PKH_PREFIX, pub_key_hash)
+            }
+        };
+        bs58::encode([prefix.as_slice(), hash.as_slice()].concat())
+            .with_check()
+            .into_string()
+    }
+}
+
+impl fmt::Display for TransparentAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.encode())
+    }
+}
+
+impl serde::Serialize for TransparentAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.encode())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for TransparentAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        TransparentAddress::from_encoded(&encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Validates a list of transparent addresses against the configured network.
+///
+/// Returns the addresses with duplicates removed, preserving the order of first occurrence.
+/// Returns an error naming the first offending address and its position if any address is
+/// unparseable or belongs to another network, or if the list is empty.
+pub fn validate_transparent_addresses(
+    addresses: &[String],
+    network: NetworkKind,
+) -> Result<Vec<String>, AddressError> {
+    if addresses.is_empty() {
+        return Err(AddressError::EmptyList);
+    }
+    // Regtest uses the testnet address prefixes.
+    let expected = match network {
+        NetworkKind::Mainnet => NetworkKind::Mainnet,
+        NetworkKind::Testnet | NetworkKind::Regtest => NetworkKind::Testnet,
+    };
+    let mut validated: Vec<String> = Vec::with_capacity(addresses.len());
+    for (index, address) in addresses.iter().enumerate() {
+        let parsed = TransparentAddress::from_encoded(address).map_err(|reason| {
+            AddressError::InvalidAddress {
+                index,
+                address: address.clone(),
+                reason,
+            }
+        })?;
+        if parsed.network_kind() != expected {
+            return Err(AddressError::NetworkMismatch {
+                index,
+                address: address.clone(),
+                expected: network,
+                found: parsed.network_kind(),
+            });
+        }
+        if !validated.contains(address) {
+            validated.push(address.clone());
+        }
+    }
+    Ok(validated)
+}
@@ -22,6 +22,30 @@ pub enum NetworkKind {
     Regtest,
 }
 
+impl NetworkKind {
+    /// Returns the network of a chain name reported by a node, `None` if the name is not a known network.
+    ///
+    /// Names are matched ignoring case and surrounding whitespace, both BIP70 names (`main`, `test`, `regtest`) and
+    /// full network names (`mainnet`, `testnet`) are accepted.
+    pub fn from_chain_name(chain: &str) -> Option<Self> {
+        match chain.trim().to_ascii_lowercase().as_str() {
+            "main" | "mainnet" => Some(NetworkKind::Mainnet),
+            "test" | "testnet" => Some(NetworkKind::Testnet),
+            "regtest" => Some(NetworkKind::Regtest),
+            _ => None,
+        }
+    }
+
+    /// Returns the BIP70 name of the network, as reported by zcashd and lightwalletd.
+    pub fn chain_name(&self) -> &'static str {
+        match self {
+            NetworkKind::Mainnet => "main",
+            NetworkKind::Testnet => "test",
+            NetworkKind::Regtest => "regtest",
+        }
+    }
+}
+
 /// The Consensus Branch Id, used to bind transactions and blocks to a
 /// particular network upgrade.
 #[derive(
@@ -39,6 +63,12 @@ impl ConsensusBranchId {
     }
 }
 
+impl From<u32> for ConsensusBranchId {
+    fn from(branch: u32) -> ConsensusBranchId {
+        ConsensusBranchId(branch)
+    }
+}
+
 impl From<ConsensusBranchId> for u32 {
     fn from(branch: ConsensusBranchId) -> u32 {
         branch.0
@@ -80,6 +110,14 @@ impl fmt::Display for ConsensusBranchId {
     }
 }
 
+impl std::str::FromStr for ConsensusBranchId {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        hex::FromHex::from_hex(s)
+    }
+}
+
 /// A hex-encoded [`ConsensusBranchId`] string.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct ConsensusBranchIdHex(#[serde(with = "hex")] pub ConsensusBranchId);
@@ -145,6 +183,25 @@ impl fmt::Display for NetworkUpgrade {
     }
 }
 
+impl NetworkUpgrade {
+    /// Returns the [`ConsensusBranchId`] of the network upgrade.
+    ///
+    /// Returns `None` for [`NetworkUpgrade::Genesis`] and [`NetworkUpgrade::BeforeOverwinter`],
+    /// which have no branch id and use the Sprout branch id of zero.
+    pub fn branch_id(&self) -> Option<ConsensusBranchId> {
+        let branch_id = match self {
+            NetworkUpgrade::Genesis | NetworkUpgrade::BeforeOverwinter => return None,
+            NetworkUpgrade::Overwinter => 0x5ba8_1b19,
+            NetworkUpgrade::Sapling => 0x76b8_09bb,
+            NetworkUpgrade::Blossom => 0x2bb4_0e60,
+            NetworkUpgrade::Heartwood => 0xf5b9_230b,
+            NetworkUpgrade::Canopy => 0xe9ff_75a6,
+            NetworkUpgrade::Nu5 => 0xc2d6_d0b4,
+        };
+        Some(ConsensusBranchId(branch_id))
+    }
+}
+
 /// Information about [`NetworkUpgrade`] activation.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct NetworkUpgradeInfo {
@@ -159,6 +216,22 @@ pub struct NetworkUpgradeInfo {
     pub status: NetworkUpgradeStatus,
 }
 
+/// Activation of a [`NetworkUpgrade`] in the node's activation schedule.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NetworkUpgradeActivation {
+    /// Name of the upgrade.
+    pub name: NetworkUpgrade,
+
+    /// Consensus branch id of the upgrade, as reported by the node.
+    pub branch_id: ConsensusBranchId,
+
+    /// Block height of activation.
+    pub activation_height: ChainHeight,
+
+    /// Status of the upgrade at the node's chain tip.
+    pub status: NetworkUpgradeStatus,
+}
+
 /// The [`ConsensusBranchId`]s for the tip and the next block.
 ///
 /// These branch IDs are different when the next block is a network upgrade activation block.
@@ -1,5 +1,7 @@
 //! Hold error types for Zingo-Indexer primitives and related functionality.
 
+use crate::primitives::chain::NetworkKind;
+
 /// A serialization error.
 #[derive(thiserror::Error, Debug)]
 pub enum SerializationError {
@@ -31,6 +33,38 @@ pub enum SerializationError {
     FromHexError(#[from] hex::FromHexError),
 }
 
+/// An error validating a list of transparent addresses.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    /// No addresses were given.
+    #[error("address list is empty")]
+    EmptyList,
+
+    /// An address could not be parsed as a transparent address.
+    #[error("invalid transparent address at position {index} ({address}): {reason}")]
+    InvalidAddress {
+        /// Position of the address in the list.
+        index: usize,
+        /// The offending address.
+        address: String,
+        /// Reason the address could not be parsed.
+        reason: String,
+    },
+
+    /// An address belongs to a different network than the one configured.
+    #[error("address at position {index} ({address}) is a {found:?} address, expected a {expected:?} address")]
+    NetworkMismatch {
+        /// Position of the address in the list.
+        index: usize,
+        /// The offending address.
+        address: String,
+        /// The configured network.
+        expected: NetworkKind,
+        /// The network the address was encoded for.
+        found: NetworkKind,
+    },
+}
+
 /// Error type alias to make working with generic errors easier.
 ///
 /// Note: the 'static lifetime bound means that the *type* cannot have any
@@ -145,6 +145,9 @@ pub enum ChainHeightError {
     /// Height underflow error.
     #[error("The resulting height would underflow Height::MIN.")]
     Underflow,
+    /// Integer height outside the valid height range.
+    #[error("Height {0} is outside the valid range of 0 to {}.", ChainHeight::MAX.0)]
+    OutOfRange(i128),
 }
 
 impl std::str::FromStr for ChainHeight {
@@ -233,6 +236,32 @@ impl TryFrom<BlockHeight> for ChainHeight {
     }
 }
 
+/// Returns the height as the `u64` used for heights by the gRPC protocol, the conversion is lossless.
+pub fn height_to_u64(height: ChainHeight) -> u64 {
+    u64::from(height.0)
+}
+
+/// Returns the height held in a gRPC `u64` height.
+///
+/// Returns an error if the height is above [`ChainHeight::MAX`], rather than truncating it.
+pub fn u64_to_height(height: u64) -> Result<ChainHeight, ChainHeightError> {
+    u32::try_from(height)
+        .ok()
+        .and_then(|height| ChainHeight::try_from(height).ok())
+        .ok_or(ChainHeightError::OutOfRange(height.into()))
+}
+
+/// Returns the height held in a signed height returned by the node.
+///
+/// Returns an error if the height is negative, eg. the -1 height of a mempool transaction, or above
+/// [`ChainHeight::MAX`].
+pub fn i64_to_height(height: i64) -> Result<ChainHeight, ChainHeightError> {
+    u32::try_from(height)
+        .ok()
+        .and_then(|height| ChainHeight::try_from(height).ok())
+        .ok_or(ChainHeightError::OutOfRange(height.into()))
+}
+
 /// A difference between two [`Height`]s, possibly negative.
 ///
 /// This can represent the difference between any height values,
@@ -30,6 +30,31 @@ pub struct CommitmentTreestate {
     pub final_state: String,
 }
 
+impl CommitmentTreestate {
+    /// Hex encoding of an empty commitment tree, as returned by zcashd and lightwalletd before the pool's activation height.
+    pub const EMPTY_TREE_HEX: &'static str = "000000";
+
+    /// Returns the treestate of an empty commitment tree.
+    pub fn empty() -> Self {
+        CommitmentTreestate {
+            final_state: Self::EMPTY_TREE_HEX.to_string(),
+        }
+    }
+
+    /// Builds a treestate from the `finalState` field of a `z_gettreestate` response.
+    ///
+    /// Before a pool's activation height the node may omit the field or return an empty string,
+    /// in this case the empty tree representation is returned.
+    pub fn from_final_state(final_state: Option<&str>) -> Self {
+        match final_state {
+            Some(state) if !state.is_empty() => CommitmentTreestate {
+                final_state: state.to_string(),
+            },
+            _ => Self::empty(),
+        }
+    }
+}
+
 /// Zingo-Indexer sapling treestate.
 ///
 /// A treestate that is included in the [`z_gettreestate`][1] RPC response.
@@ -7,5 +7,4 @@
 
 pub mod rpc;
 pub mod server;
-pub(crate) mod utils;
-
+pub mod utils;
@@ -2,11 +2,33 @@
 
 use std::sync::{atomic::AtomicBool, Arc};
 
+use zaino_fetch::{
+    chain::{
+        cache::{BlockCache, ChainTip, NegativeCache},
+        mempool::MempoolTxCache,
+    },
+    jsonrpc::connector::{NodeAuth, NodeType},
+    primitives::chain::NetworkKind,
+};
+
+use crate::{
+    rpc::stream::StreamErrorPolicy,
+    server::{health::NodeHealth, metrics::RpcMetrics},
+};
+
+pub mod primitives;
+pub mod stream;
+
+/// Default message send_transaction is rejected with while transaction broadcast is disabled.
+pub const DEFAULT_BROADCAST_DISABLED_MESSAGE: &str =
+    "Transaction broadcast is disabled on this server, submit transactions through another lightwalletd server.";
+
 #[cfg(feature = "nym_poc")]
 pub mod nymwalletservice;
 #[cfg(not(feature = "nym_poc"))]
 pub mod service;
 
+#[cfg(feature = "nym")]
 pub mod nymservice;
 
 #[derive(Debug, Clone)]
@@ -17,6 +39,40 @@ pub struct GrpcClient {
     pub lightwalletd_uri: http::Uri,
     /// Zebrad uri.
     pub zebrad_uri: http::Uri,
+    /// Network the indexer is configured for.
+    pub network: NetworkKind,
+    /// Type of the full node, selects how node responses are normalized and which RPCs are used.
+    pub node_type: NodeType,
+    /// Credentials used to authenticate with the full node.
+    pub node_auth: NodeAuth,
+    /// Block cache shared by all workers.
+    pub block_cache: Arc<BlockCache>,
+    /// Latest chain tip observed from the node, shared by all workers.
+    pub chain_tip: Arc<ChainTip>,
+    /// Lookups the node reported as not found, shared by all workers.
+    pub negative_cache: Arc<NegativeCache>,
+    /// Mempool transactions served by get_mempool_tx, shared by all workers.
+    pub mempool_cache: Arc<MempoolTxCache>,
+    /// RPC metrics shared by all workers.
+    pub rpc_metrics: Arc<RpcMetrics>,
+    /// Availability of the full node, RPCs that need the node fail immediately while it is unavailable.
+    pub node_health: Arc<NodeHealth>,
+    /// Whether get_taddress_txids also returns the address's mempool transactions.
+    pub taddress_txids_include_mempool: bool,
+    /// Maximum number of transactions fetched from the node at a time by get_taddress_txids.
+    pub taddress_txids_fetch_concurrency: usize,
+    /// Number of confirmations subtracted from the chain tip returned by get_latest_block.
+    pub latest_block_confirmations: u32,
+    /// Maximum number of txids accepted in the exclude list of get_mempool_tx.
+    pub max_mempool_exclude_entries: usize,
+    /// Maximum time since mempool transactions entered the node's mempool, older transactions are not served by
+    /// get_mempool_tx and get_mempool_stream. `None` serves all mempool transactions.
+    pub max_mempool_tx_age: Option<std::time::Duration>,
+    /// Message send_transaction is rejected with, with FAILED_PRECONDITION, while transaction broadcast is disabled.
+    /// `None` broadcasts transactions.
+    pub broadcast_disabled: Option<String>,
+    /// Behaviour when fetching a single item of a response stream from the node fails.
+    pub stream_error_policy: StreamErrorPolicy,
     /// Represents the Online status of the gRPC server.
     pub online: Arc<AtomicBool>,
 }
@@ -0,0 +1,113 @@
+//! Block identifiers received in RPC requests and their validation.
+
+use std::future::Future;
+
+use zaino_fetch::primitives::{block::BlockHash, height::u64_to_height};
+use zaino_proto::proto::service::BlockId;
+
+/// Returns the height held in a request, INVALID_ARGUMENT if it is above the maximum block height rather than
+/// truncating it.
+pub fn request_height(height: u64) -> Result<u32, tonic::Status> {
+    u64_to_height(height)
+        .map(|height| height.0)
+        .map_err(|e| tonic::Status::invalid_argument(format!("Invalid block height: {}", e)))
+}
+
+/// Block requested by a [`BlockId`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockLookup {
+    /// The block with the given hash.
+    Hash(BlockHash),
+    /// The block at the given height.
+    Height(u32),
+}
+
+impl std::fmt::Display for BlockLookup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockLookup::Hash(hash) => write!(f, "{}", hash),
+            BlockLookup::Height(height) => write!(f, "{}", height),
+        }
+    }
+}
+
+/// Returns the block requested by `block_id`, hashes are given in display order, matching lightwalletd.
+///
+/// A hash holding any non-zero byte takes precedence and requests the block by hash. A hash that is empty or all
+/// zeros requests the block at the given height, height 0 requesting the genesis block. A hash of 32 zero bytes
+/// with height 0 identifies no block and is rejected, as is a hash that is not 32 bytes long.
+pub fn block_lookup(block_id: &BlockId) -> Result<BlockLookup, tonic::Status> {
+    if block_id.hash.is_empty() {
+        return Ok(BlockLookup::Height(request_height(block_id.height)?));
+    }
+    let hash_bytes: [u8; 32] = block_id.hash.as_slice().try_into().map_err(|_| {
+        tonic::Status::invalid_argument(format!(
+            "Invalid block hash: expected 32 bytes, found {}.",
+            block_id.hash.len()
+        ))
+    })?;
+    if hash_bytes != [0; 32] {
+        Ok(BlockLookup::Hash(BlockHash::from_bytes_in_display_order(
+            &hash_bytes,
+        )))
+    } else if block_id.height != 0 {
+        Ok(BlockLookup::Height(request_height(block_id.height)?))
+    } else {
+        Err(tonic::Status::invalid_argument(
+            "Invalid block id: the block hash and height are both zero.",
+        ))
+    }
+}
+
+/// Block identified by a [`BlockId`], resolved to its height by [`resolve_block_id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolvedBlock {
+    /// Height of the block.
+    pub height: u32,
+    /// Hash of the block, if it was requested by hash.
+    pub hash: Option<BlockHash>,
+}
+
+impl ResolvedBlock {
+    /// Returns the block's hash if it was requested by hash, else its height, as accepted by node RPCs taking either.
+    ///
+    /// Looking a block up by its hash rather than the resolved height never returns a block swapped in by a reorg.
+    pub fn hash_or_height(&self) -> String {
+        match self.hash {
+            Some(hash) => hash.to_string(),
+            None => self.height.to_string(),
+        }
+    }
+}
+
+/// Resolves the block requested by `block_id` to its height, see [`block_lookup`] for whether the hash or height
+/// is used. A block given by hash is resolved to its height with `resolve_height`.
+///
+/// A non-zero height given alongside a hash must be the height of that block, a mismatch is rejected with
+/// INVALID_ARGUMENT. Heights above the maximum block height are rejected whether or not a hash is given.
+pub async fn resolve_block_id<R, RFut>(
+    block_id: &BlockId,
+    resolve_height: R,
+) -> Result<ResolvedBlock, tonic::Status>
+where
+    R: FnOnce(BlockHash) -> RFut,
+    RFut: Future<Output = Result<u32, tonic::Status>>,
+{
+    let requested_height = request_height(block_id.height)?;
+    match block_lookup(block_id)? {
+        BlockLookup::Height(height) => Ok(ResolvedBlock { height, hash: None }),
+        BlockLookup::Hash(hash) => {
+            let height = resolve_height(hash).await?;
+            if requested_height != 0 && requested_height != height {
+                return Err(tonic::Status::invalid_argument(format!(
+                    "Invalid block id: block {} is at height {}, not {}.",
+                    hash, height, requested_height
+                )));
+            }
+            Ok(ResolvedBlock {
+                height,
+                hash: Some(hash),
+            })
+        }
+    }
+}
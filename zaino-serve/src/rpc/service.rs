@@ -1,16 +1,45 @@
 //! Lightwallet service RPC implementations.
 
 use hex::FromHex;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::atomic::{AtomicU64, Ordering},
+};
 use tokio::time::timeout;
 use tokio_stream::wrappers::ReceiverStream;
 
-use crate::{rpc::GrpcClient, utils::get_build_info};
+use crate::{
+    rpc::{
+        primitives::{block_lookup, request_height, resolve_block_id},
+        stream::{stream_indexed_pages, stream_node_items},
+        GrpcClient,
+    },
+    server::logging::RequestLogFields,
+    utils::get_build_info,
+};
 use zaino_fetch::{
-    chain::{block::get_block_from_node, mempool::Mempool},
-    jsonrpc::{connector::JsonRpcConnector, response::GetTransactionResponse},
+    chain::{
+        cache::BlockCache,
+        fetcher::{stream_block_range, BlockSink, CancelSignal, FetchOutcome},
+        mempool::{CachedMempoolTx, Mempool, MempoolExclude, MempoolTxCache},
+        transaction::FullTransaction,
+        utils::{display_txids_to_server, ParseFromSlice},
+    },
+    jsonrpc::{
+        connector::{JsonRpcConnector, NodeAuth},
+        error::RPC_TRANSACTION_REJECTED,
+        response::{
+            GetBlockResponse, GetRawMempoolResponse, GetTransactionResponse, GetUtxosResponse,
+            MempoolEntry,
+        },
+    },
     primitives::{
-        chain::{ConsensusBranchId, ConsensusBranchIdHex},
-        height::ChainHeight,
+        address::validate_transparent_addresses,
+        block::BlockHash,
+        chain::{ConsensusBranchId, ConsensusBranchIdHex, NetworkKind, NetworkUpgrade},
+        height::{height_to_u64, i64_to_height, ChainHeight},
+        transaction::{CommitmentTreestate, SubtreeRpcData},
     },
 };
 use zaino_proto::proto::{
@@ -19,10 +48,75 @@ use zaino_proto::proto::{
         compact_tx_streamer_server::CompactTxStreamer, Address, AddressList, Balance, BlockId,
         BlockRange, ChainSpec, Duration, Empty, Exclude, GetAddressUtxosArg, GetAddressUtxosReply,
         GetAddressUtxosReplyList, GetSubtreeRootsArg, LightdInfo, PingResponse, RawTransaction,
-        SendResponse, SubtreeRoot, TransparentAddressBlockFilter, TreeState, TxFilter,
+        SendResponse, ShieldedProtocol, SubtreeRoot, TransparentAddressBlockFilter, TreeState,
+        TxFilter,
     },
 };
 
+/// Trailing metadata key holding the number of blocks served by a completed GetBlockRange stream.
+pub const BLOCKS_SERVED_TRAILER: &str = "x-zaino-blocks-served";
+
+/// Trailing metadata key holding the height of the last block served by a completed GetBlockRange stream.
+///
+/// Blocks are served from the top of the range down, so this is the lowest height delivered. Absent if no blocks
+/// were served.
+pub const LAST_HEIGHT_TRAILER: &str = "x-zaino-last-height";
+
+/// Response metadata key set to "true" on get_lightd_info responses while transaction broadcast is disabled, so
+/// wallets can detect a read-only server before attempting a spend.
+pub const BROADCAST_DISABLED_HEADER: &str = "x-zaino-broadcast-disabled";
+
+/// Block sink that counts the blocks sent to a GetBlockRange stream, reported to the client in the stream's
+/// trailers.
+struct BlockRangeProgress {
+    tx: tokio::sync::mpsc::Sender<Result<CompactBlock, tonic::Status>>,
+    blocks_served: AtomicU64,
+    last_height: AtomicU64,
+}
+
+impl BlockRangeProgress {
+    fn new(tx: tokio::sync::mpsc::Sender<Result<CompactBlock, tonic::Status>>) -> Self {
+        BlockRangeProgress {
+            tx,
+            blocks_served: AtomicU64::new(0),
+            last_height: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the OK status ending the stream, carrying the progress trailers.
+    fn trailers(&self) -> tonic::Status {
+        let blocks_served = self.blocks_served.load(Ordering::SeqCst);
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        metadata.insert(BLOCKS_SERVED_TRAILER, blocks_served.into());
+        if blocks_served > 0 {
+            metadata.insert(
+                LAST_HEIGHT_TRAILER,
+                self.last_height.load(Ordering::SeqCst).into(),
+            );
+        }
+        tonic::Status::with_metadata(tonic::Code::Ok, "", metadata)
+    }
+}
+
+impl BlockSink<tonic::Status> for BlockRangeProgress {
+    fn send_block(
+        &self,
+        block: Result<CompactBlock, tonic::Status>,
+    ) -> impl Future<Output = bool> + Send {
+        async move {
+            let height = block.as_ref().ok().map(|block| block.height);
+            if self.tx.send(block).await.is_err() {
+                return false;
+            }
+            if let Some(height) = height {
+                self.blocks_served.fetch_add(1, Ordering::SeqCst);
+                self.last_height.store(height, Ordering::SeqCst);
+            }
+            true
+        }
+    }
+}
+
 /// Stream of RawTransactions, output type of get_taddress_txids.
 pub struct RawTransactionStream {
     inner: ReceiverStream<Result<RawTransaction, tonic::Status>>,
@@ -85,256 +179,778 @@ impl futures::Stream for CompactBlockStream {
     }
 }
 
-impl CompactTxStreamer for GrpcClient {
-    /// Return the height of the tip of the best chain.
-    fn get_latest_block<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<ChainSpec>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<tonic::Response<BlockId>, tonic::Status>,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_latest_block.");
-        Box::pin(async {
-            let blockchain_info = JsonRpcConnector::new(
-                self.zebrad_uri.clone(),
-                Some("xxxxxx".to_string()),
-                Some("xxxxxx".to_string()),
-            )
-            .await
-            .get_blockchain_info()
-            .await
-            .map_err(|e| e.to_grpc_status())?;
+/// Stream of CompactBlocks paired with their heights, used by get_block_range.
+///
+/// The height is read from each block's `height` field, so consumers such as progress trackers can follow the stream
+/// without inspecting the blocks.
+pub struct BlockRangeStream {
+    inner: CompactBlockStream,
+}
 
-            let block_id = BlockId {
-                height: blockchain_info.blocks.0 as u64,
-                hash: blockchain_info.best_block_hash.0.to_vec(),
-            };
+impl BlockRangeStream {
+    /// Returns a BlockRangeStream yielding the blocks of `inner` with their heights.
+    pub fn new(inner: CompactBlockStream) -> Self {
+        BlockRangeStream { inner }
+    }
 
-            Ok(tonic::Response::new(block_id))
-        })
+    /// Returns the blocks of the stream without their heights.
+    pub fn into_blocks(
+        self,
+    ) -> impl futures::Stream<Item = Result<CompactBlock, tonic::Status>> + Send {
+        futures::StreamExt::map(self, |block| block.map(|(_, block)| block))
     }
+}
 
-    /// Return the compact block corresponding to the given block identifier.
-    ///
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    ///
-    /// TODO: This RPC should be implemented alongside the block cache.
-    fn get_block<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<BlockId>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<tonic::Response<CompactBlock>, tonic::Status>,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
+impl futures::Stream for BlockRangeStream {
+    type Item = Result<(u32, CompactBlock), tonic::Status>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::pin::Pin::new(&mut self.inner)
+            .poll_next(cx)
+            .map(|block| {
+                block.map(|block| {
+                    let block = block?;
+                    let height = u32::try_from(block.height).map_err(|_| {
+                        tonic::Status::internal(format!(
+                            "Block height {} out of range.",
+                            block.height
+                        ))
+                    })?;
+                    Ok((height, block))
+                })
+            })
+    }
+}
+
+/// Stream of CompactTxs, output type of get_mempool_tx.
+pub struct CompactTxStream {
+    inner: ReceiverStream<Result<CompactTx, tonic::Status>>,
+}
+
+impl CompactTxStream {
+    /// Returns new instanse of CompactTxStream.
+    pub fn new(rx: tokio::sync::mpsc::Receiver<Result<CompactTx, tonic::Status>>) -> Self {
+        CompactTxStream {
+            inner: ReceiverStream::new(rx),
+        }
+    }
+}
+
+impl futures::Stream for CompactTxStream {
+    type Item = Result<CompactTx, tonic::Status>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::pin::Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+/// Stream of SubtreeRoots, output type of get_subtree_roots.
+pub struct SubtreeRootStream {
+    inner: ReceiverStream<Result<SubtreeRoot, tonic::Status>>,
+}
+
+impl SubtreeRootStream {
+    /// Returns new instanse of SubtreeRootStream.
+    pub fn new(rx: tokio::sync::mpsc::Receiver<Result<SubtreeRoot, tonic::Status>>) -> Self {
+        SubtreeRootStream {
+            inner: ReceiverStream::new(rx),
+        }
+    }
+}
+
+impl futures::Stream for SubtreeRootStream {
+    type Item = Result<SubtreeRoot, tonic::Status>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::pin::Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+/// Stream of GetAddressUtxosReplys, output type of get_address_utxos_stream.
+pub struct AddressUtxoStream {
+    inner: ReceiverStream<Result<GetAddressUtxosReply, tonic::Status>>,
+}
+
+impl AddressUtxoStream {
+    /// Returns new instanse of AddressUtxoStream.
+    pub fn new(
+        rx: tokio::sync::mpsc::Receiver<Result<GetAddressUtxosReply, tonic::Status>>,
+    ) -> Self {
+        AddressUtxoStream {
+            inner: ReceiverStream::new(rx),
+        }
+    }
+}
+
+impl futures::Stream for AddressUtxoStream {
+    type Item = Result<GetAddressUtxosReply, tonic::Status>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::pin::Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+/// Number of subtree roots requested from the node per z_getsubtreesbyindex call while streaming GetSubtreeRoots.
+const SUBTREE_ROOTS_PAGE_SIZE: u32 = 128;
+
+/// Returns the SubtreeRoot of a subtree returned by the node, with the hash of its completing block in internal
+/// byte order, matching lightwalletd.
+async fn subtree_root(
+    block_cache: &BlockCache,
+    zebrad_uri: &http::Uri,
+    node_auth: &NodeAuth,
+    subtree: SubtreeRpcData,
+) -> Result<SubtreeRoot, tonic::Status> {
+    let root_hash = Vec::<u8>::from_hex(&subtree.root).map_err(|e| {
+        tonic::Status::internal(format!("Node returned an invalid subtree root: {}", e))
+    })?;
+    let completing_block_hash = block_cache
+        .get_block_hash(zebrad_uri, node_auth, subtree.height.0)
+        .await?;
+    Ok(SubtreeRoot {
+        root_hash,
+        completing_block_hash: completing_block_hash.to_vec(),
+        completing_block_height: height_to_u64(subtree.height),
+    })
+}
+
+/// Fetches the roots of up to `limit` complete subtrees of `pool` from `index`.
+async fn fetch_subtree_roots(
+    zebrad_client: &JsonRpcConnector,
+    block_cache: &BlockCache,
+    zebrad_uri: &http::Uri,
+    node_auth: &NodeAuth,
+    pool: ShieldedProtocol,
+    index: u32,
+    limit: u32,
+) -> Result<Vec<SubtreeRoot>, tonic::Status> {
+    let subtrees = zebrad_client
+        .get_subtrees_by_index(
+            pool.as_str_name().to_string(),
+            index as u16,
+            Some(limit as u16),
+        )
+        .await
+        .map_err(|e| e.to_grpc_status())?
+        .subtrees;
+    let mut roots = Vec::with_capacity(subtrees.len());
+    for subtree in subtrees {
+        roots.push(subtree_root(block_cache, zebrad_uri, node_auth, subtree).await?);
+    }
+    Ok(roots)
+}
+
+/// Message of the status returned when the block fetched for a request does not match the block requested.
+pub const BLOCK_REORG_MESSAGE: &str = "chain reorg during request, retry";
+
+/// Fetches the block identified by `block_id`, see [`resolve_block_id`], with `fetch_block`. A block given by hash is
+/// first resolved to its height with `resolve_height`.
+///
+/// The block fetched is checked against the resolved height and the requested hash, if given, before it is returned.
+/// A mismatch, eg. after a reorg between resolving the height and fetching the block, returns Aborted with
+/// [`BLOCK_REORG_MESSAGE`] rather than serving the wrong block.
+pub async fn fetch_verified_block<R, RFut, F, FFut>(
+    block_id: BlockId,
+    resolve_height: R,
+    fetch_block: F,
+) -> Result<CompactBlock, tonic::Status>
+where
+    R: FnOnce(BlockHash) -> RFut,
+    RFut: Future<Output = Result<u32, tonic::Status>>,
+    F: FnOnce(u32) -> FFut,
+    FFut: Future<Output = Result<CompactBlock, tonic::Status>>,
+{
+    let resolved = resolve_block_id(&block_id, resolve_height).await?;
+    let block = fetch_block(resolved.height).await?;
+    // Compact blocks hold their hash in internal byte order.
+    let hash_matches = resolved.hash.map_or(true, |hash| block.hash == hash.0);
+    if !hash_matches || block.height != u64::from(resolved.height) {
+        return Err(tonic::Status::aborted(BLOCK_REORG_MESSAGE));
+    }
+    Ok(block)
+}
+
+/// Strips `block` down to the nullifiers of its shielded spends, as served by get_block_nullifiers.
+///
+/// Sapling outputs are dropped and orchard actions keep only their nullifier, sapling spends hold only a nullifier
+/// in compact blocks already.
+pub fn nullifiers_only(mut block: CompactBlock) -> CompactBlock {
+    for tx in &mut block.vtx {
+        tx.outputs.clear();
+        for action in &mut tx.actions {
+            action.cmx.clear();
+            action.ephemeral_key.clear();
+            action.ciphertext.clear();
+        }
+    }
+    block
+}
+
+/// Returns the height of the block with the given hash, from the block cache if it holds the block or its hash, else
+/// from the node.
+async fn cached_block_height(
+    block_cache: &BlockCache,
+    zebrad_uri: &http::Uri,
+    node_auth: &NodeAuth,
+    hash: BlockHash,
+) -> Result<u32, tonic::Status> {
+    match block_cache.height_of(&hash.0).await {
+        Some(height) => Ok(height),
+        None => block_height(zebrad_uri, node_auth, hash).await,
+    }
+}
+
+/// Returns the height of the block with the given hash from the node.
+async fn block_height(
+    zebrad_uri: &http::Uri,
+    node_auth: &NodeAuth,
+    hash: BlockHash,
+) -> Result<u32, tonic::Status> {
+    let zebrad_client = JsonRpcConnector::new(zebrad_uri.clone(), node_auth.clone()).await;
+    match zebrad_client
+        .get_block(hash.to_string(), Some(1))
+        .await
+        .map_err(|e| e.to_grpc_status())?
     {
-        println!("[TEST] Received call of get_block.");
-        Box::pin(async {
-            Err(tonic::Status::unimplemented("get_block not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
+        GetBlockResponse::Object {
+            height: Some(height),
+            ..
+        } => Ok(height.0),
+        _ => Err(tonic::Status::internal(format!(
+            "Node did not return the height of block {}.",
+            hash
+        ))),
+    }
+}
+
+/// Returns the page of `utxos` at or above `start_height`, in height order, of at most `max_entries` UTXOs, or all
+/// if 0.
+///
+/// UTXOs at the same height are ordered by txid and output index, so a page ending partway through a height is
+/// resumed by requesting that height again and skipping the UTXOs already received. Txids are returned in internal
+/// byte order, matching lightwalletd.
+fn address_utxo_page(
+    mut utxos: Vec<GetUtxosResponse>,
+    start_height: u64,
+    max_entries: u32,
+) -> Vec<GetAddressUtxosReply> {
+    utxos.retain(|utxo| height_to_u64(utxo.height) >= start_height);
+    utxos.sort_by(|a, b| {
+        (a.height.0, a.txid.bytes_in_display_order(), a.output_index).cmp(&(
+            b.height.0,
+            b.txid.bytes_in_display_order(),
+            b.output_index,
+        ))
+    });
+    if max_entries != 0 {
+        utxos.truncate(max_entries as usize);
+    }
+    utxos
+        .into_iter()
+        .map(|utxo| GetAddressUtxosReply {
+            address: utxo.address.to_string(),
+            txid: utxo.txid.0.to_vec(),
+            index: utxo.output_index as i32,
+            script: utxo.script.as_raw_bytes().to_vec(),
+            value_zat: utxo.satoshis as i64,
+            height: height_to_u64(utxo.height),
         })
+        .collect()
+}
+
+/// Fetches a transaction from the node as a RawTransaction, mempool transactions are returned with a height of 0.
+async fn fetch_raw_transaction(
+    zebrad_client: &JsonRpcConnector,
+    txid: String,
+) -> Result<RawTransaction, tonic::Status> {
+    match zebrad_client
+        .get_raw_transaction(txid, Some(1))
+        .await
+        .map_err(|e| e.to_grpc_status())?
+    {
+        GetTransactionResponse::Object { hex, height, .. } => Ok(RawTransaction {
+            data: hex.bytes,
+            // Mempool transactions are returned with a height of -1.
+            height: i64_to_height(height.into()).map_or(0, height_to_u64),
+        }),
+        GetTransactionResponse::Raw(_) => Err(tonic::Status::internal(
+            "Received raw transaction type, this should not be impossible.",
+        )),
     }
+}
 
-    /// Same as GetBlock except actions contain only nullifiers.
-    ///
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    fn get_block_nullifiers<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<BlockId>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<tonic::Response<CompactBlock>, tonic::Status>,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
+/// Parses a mempool transaction, given its display order txid, into a CompactTx.
+fn mempool_compact_tx(txid: String, data: &[u8]) -> Result<CompactTx, tonic::Status> {
+    let txid =
+        display_txids_to_server(vec![txid]).map_err(|e| tonic::Status::internal(e.to_string()))?;
+    let transaction = FullTransaction::parse_exact(data, Some(txid), None)
+        .map_err(|e| tonic::Status::internal(e.to_string()))?;
+    transaction
+        .to_compact(0)
+        .map_err(|e| tonic::Status::internal(e.to_string()))
+}
+
+/// Fetches a mempool transaction, given its display order txid, from the node and caches it in `mempool_cache`.
+///
+/// The node's verbose mempool is read into `mempool_entries` on the first call, giving the fees and entry times the
+/// cache evicts by. Returns `None` if the node no longer holds the transaction.
+async fn fetch_mempool_tx(
+    zebrad_client: &JsonRpcConnector,
+    mempool_cache: &MempoolTxCache,
+    mempool_entries: &mut Option<HashMap<String, MempoolEntry>>,
+    txid: String,
+) -> Option<Result<CompactTx, tonic::Status>> {
+    let raw = match zebrad_client
+        .get_raw_transaction(txid.clone(), Some(1))
+        .await
     {
-        println!("[TEST] Received call of get_block_nullifiers.");
-        Box::pin(async {
-            Err(tonic::Status::unimplemented("get_block_nullifiers not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
-        })
+        Ok(GetTransactionResponse::Object { hex, .. }) => hex.bytes,
+        Ok(GetTransactionResponse::Raw(_)) => {
+            return Some(Err(tonic::Status::internal(
+                "Received raw transaction type, this should not be impossible.",
+            )))
+        }
+        // The transaction may have been mined or evicted since the mempool was read.
+        Err(_) => return None,
+    };
+    let compact = match mempool_compact_tx(txid.clone(), &raw) {
+        Ok(compact) => compact,
+        Err(e) => return Some(Err(e)),
+    };
+    if mempool_entries.is_none() {
+        *mempool_entries = Some(match zebrad_client.get_raw_mempool(true).await {
+            Ok(GetRawMempoolResponse::Verbose(entries)) => entries.into_iter().collect(),
+            // Nodes without a verbose mempool return txids only, their transactions are cached without a fee.
+            _ => HashMap::new(),
+        });
     }
+    let (fee, time) = mempool_entries
+        .as_ref()
+        .and_then(|entries| entries.get(&txid))
+        .map_or((0, 0), |entry| {
+            ((entry.fee * 100_000_000.0).round() as u64, entry.time)
+        });
+    mempool_cache
+        .insert(
+            txid,
+            CachedMempoolTx {
+                raw,
+                compact: compact.clone(),
+                fee,
+                time,
+            },
+        )
+        .await;
+    Some(Ok(compact))
+}
 
-    /// Server streaming response type for the GetBlockRange method.
-    #[doc = "Server streaming response type for the GetBlockRange method."]
-    type GetBlockRangeStream = std::pin::Pin<Box<CompactBlockStream>>;
+/// Returns the txids of the transactions that entered the node's mempool more than `max_age` ago.
+///
+/// Entry times are read from the node's verbose mempool, nodes that return txids only have no stale transactions.
+/// Returns an empty set without calling the node if `max_age` is `None`.
+async fn stale_mempool_txids(
+    zebrad_client: &JsonRpcConnector,
+    max_age: Option<std::time::Duration>,
+) -> Result<HashSet<String>, tonic::Status> {
+    let Some(max_age) = max_age else {
+        return Ok(HashSet::new());
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64);
+    let oldest_time = now.saturating_sub(max_age.as_secs() as i64);
+    match zebrad_client.get_raw_mempool(true).await? {
+        GetRawMempoolResponse::Verbose(entries) => Ok(entries
+            .into_iter()
+            .filter(|(_, entry)| entry.time < oldest_time)
+            .map(|(txid, _)| txid)
+            .collect()),
+        GetRawMempoolResponse::Txids(_) => Ok(HashSet::new()),
+    }
+}
+
+/// Defines a [`CompactTxStreamer`] method from its name, request and response message types and an async body.
+///
+/// Expands to the boxed future signature `async_trait` would generate, logging each call before running the body.
+/// The body is the block of an `async move` future returning `Result<tonic::Response<$ret>, tonic::Status>`.
+macro_rules! define_rpc {
+    (
+        $(#[$attr:meta])*
+        fn $name:ident(&$self:ident, $request:ident: $req:ty $(,)?) -> $ret:ty $body:block
+    ) => {
+        $(#[$attr])*
+        #[allow(clippy::type_complexity, clippy::type_repetition_in_bounds)]
+        fn $name<'life0, 'async_trait>(
+            &'life0 $self,
+            $request: tonic::Request<$req>,
+        ) -> ::core::pin::Pin<
+            Box<
+                dyn ::core::future::Future<
+                        Output = ::core::result::Result<tonic::Response<$ret>, tonic::Status>,
+                    > + ::core::marker::Send
+                    + 'async_trait,
+            >,
+        >
+        where
+            'life0: 'async_trait,
+            Self: 'async_trait,
+        {
+            println!("[TEST] Received call of {}.", stringify!($name));
+            Box::pin(async move $body)
+        }
+    };
+}
+
+/// Server streaming response type of the block range RPCs.
+type BlockRangeResponseStream =
+    std::pin::Pin<Box<dyn futures::Stream<Item = Result<CompactBlock, tonic::Status>> + Send>>;
+
+impl GrpcClient {
+    /// Returns the height and hash of the tip of the best chain, as reported by the node, recording it as the latest
+    /// observed chain tip.
+    async fn fetch_chain_tip(&self) -> Result<(u32, BlockHash), tonic::Status> {
+        let blockchain_info =
+            JsonRpcConnector::new(self.zebrad_uri.clone(), self.node_auth.clone())
+                .await
+                .with_node_type(self.node_type)
+                .get_blockchain_info()
+                .await
+                .map_err(|e| e.to_grpc_status())?;
+        // The tip height and hash are snapshotted from the one response, so they describe the same block.
+        let (ChainHeight(tip), tip_hash) = blockchain_info.tip();
+        self.observe_chain_tip(tip, tip_hash).await;
+        Ok((tip, tip_hash))
+    }
+
+    /// Records a chain tip reported by the node, reconciling the block cache with it if it differs from the tip
+    /// previously observed, so blocks orphaned by a reorg are no longer served.
+    async fn observe_chain_tip(&self, height: u32, hash: BlockHash) {
+        if self.chain_tip.observe(height, hash.0) {
+            // A failed reconciliation is not the request's error, the cache is reconciled again on the next new tip.
+            let _ = self
+                .block_cache
+                .observe_tip(&self.zebrad_uri, &self.node_auth, height, hash.0)
+                .await;
+        }
+        self.negative_cache.observe_tip(height).await;
+    }
 
-    /// Return a list of consecutive compact blocks.
+    /// Streams the compact blocks of a block range, as returned by `map_block`, for the block range RPC `rpc`.
+    ///
+    /// Blocks are served from the block cache, blocks not present are fetched from the node and cached, so every
+    /// block range RPC shares the cached blocks.
     ///
-    /// TODO: This implementation is slow. An internal block cache should be implemented that this rpc, along with the get_block rpc, can rely on.
-    ///       - add get_block function that queries the block cache for block and calls get_block_from_node to fetch block if not present.
-    fn get_block_range<'life0, 'async_trait>(
-        &'life0 self,
+    /// Returns INVALID_ARGUMENT if the range starts at the genesis block or ends above the chain tip, rather than
+    /// fetching blocks that do not exist. Ranges ending at or below the latest chain tip observed are served without
+    /// asking the node for its tip, the node is only asked when no tip is known or the range ends above it.
+    async fn serve_block_range(
+        &self,
         request: tonic::Request<BlockRange>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<
-                        tonic::Response<Self::GetBlockRangeStream>,
-                        tonic::Status,
-                    >,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_block_range.");
+        rpc: &'static str,
+        map_block: fn(CompactBlock) -> CompactBlock,
+    ) -> Result<tonic::Response<BlockRangeResponseStream>, tonic::Status> {
         let zebrad_uri = self.zebrad_uri.clone();
-        Box::pin(async move {
-            let blockrange = request.into_inner();
-            let mut start = blockrange
-                .start
-                .map(|s| s.height as u32)
-                .ok_or(tonic::Status::invalid_argument("Start block not specified"))?;
-            let mut end = blockrange
-                .end
-                .map(|e| e.height as u32)
-                .ok_or(tonic::Status::invalid_argument("End block not specified"))?;
-            if start > end {
-                (start, end) = (end, start);
+        let node_auth = self.node_auth.clone();
+        let block_cache = self.block_cache.clone();
+        let online = self.online.clone();
+        self.node_health.check_available()?;
+        self.node_health.check_chain_consistent()?;
+        let log_fields = RequestLogFields::of(&request);
+        let blockrange = request.into_inner();
+        let mut start = blockrange
+            .start
+            .ok_or(tonic::Status::invalid_argument("Start block not specified"))
+            .and_then(|s| request_height(s.height))?;
+        let mut end = blockrange
+            .end
+            .ok_or(tonic::Status::invalid_argument("End block not specified"))
+            .and_then(|e| request_height(e.height))?;
+        if start > end {
+            (start, end) = (end, start);
+        }
+        log_fields.record("start_height", start);
+        log_fields.record("end_height", end);
+        if start == 0 {
+            return Err(tonic::Status::invalid_argument(
+                "Start height must be greater than 0",
+            ));
+        }
+        if self
+            .chain_tip
+            .height()
+            .map_or(true, |observed_tip| end > observed_tip)
+        {
+            let (tip, _) = self.fetch_chain_tip().await?;
+            if end > tip {
+                return Err(tonic::Status::invalid_argument(format!(
+                    "End height {} exceeds chain tip {}",
+                    end, tip
+                )));
             }
-            println!("[TEST] Fetching blocks in range: {}-{}.", start, end);
-            let (channel_tx, channel_rx) = tokio::sync::mpsc::channel(32);
-            tokio::spawn(async move {
-                // NOTE: This timeout is so slow due to the blockcache not being implemented. This should be reduced to 30s once functionality is in place.
-                let timeout = timeout(std::time::Duration::from_secs(120), async {
-                    for height in (start..=end).rev() {
-                        println!("[TEST] Fetching block at height: {}.", height);
-                        let compact_block = get_block_from_node(&zebrad_uri, &height).await;
-                        match compact_block {
-                            Ok(block) => {
-                                if channel_tx.send(Ok(block)).await.is_err() {
-                                    break;
-                                }
-                            }
-                            Err(e) => {
-                                if channel_tx
-                                    .send(Err(tonic::Status::internal(e.to_string())))
-                                    .await
-                                    .is_err()
-                                {
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                })
-                .await;
-                match timeout {
-                    Ok(_) => {}
-                    Err(_) => {
-                        channel_tx
-                            .send(Err(tonic::Status::internal(
-                                "get_block_range gRPC request timed out",
-                            )))
+        }
+        let (channel_tx, channel_rx) = tokio::sync::mpsc::channel(32);
+        tokio::spawn(async move {
+            let progress = BlockRangeProgress::new(channel_tx);
+            // The stream is cancelled at its deadline, aborting the block fetches it has in flight.
+            let cancel = CancelSignal::default();
+            // NOTE: This timeout is so slow due to the blockcache not being implemented. This should be reduced to 30s once functionality is in place.
+            let deadline = tokio::spawn({
+                let cancel = cancel.clone();
+                async move {
+                    tokio::time::sleep(std::time::Duration::from_secs(120)).await;
+                    cancel.cancel();
+                }
+            });
+            let fetch_concurrency = block_cache.fetch_concurrency();
+            let outcome = stream_block_range(
+                move |height| {
+                    let zebrad_uri = zebrad_uri.clone();
+                    let node_auth = node_auth.clone();
+                    let block_cache = block_cache.clone();
+                    async move {
+                        // Blocks are streamed from the top of the range down.
+                        let prefetch_start = height
+                            .saturating_sub(block_cache.prefetch_window())
+                            .max(start);
+                        block_cache
+                            .prefetch(&zebrad_uri, &node_auth, (prefetch_start..height).rev())
+                            .await;
+                        block_cache
+                            .get_block(&zebrad_uri, &node_auth, height)
                             .await
-                            .ok();
+                            .map(map_block)
                     }
+                },
+                end,
+                start,
+                fetch_concurrency,
+                &progress,
+                &online,
+                &cancel,
+            )
+            .await;
+            deadline.abort();
+            match outcome {
+                // Completed streams end with the progress trailers so clients can reconcile their sync state.
+                FetchOutcome::Completed => {
+                    progress.tx.send(Err(progress.trailers())).await.ok();
                 }
-            });
-            let output_stream = CompactBlockStream::new(channel_rx);
-            let stream_boxed = Box::pin(output_stream);
-            Ok(tonic::Response::new(stream_boxed))
-        })
+                FetchOutcome::Cancelled if cancel.is_cancelled() => {
+                    progress
+                        .tx
+                        .send(Err(tonic::Status::deadline_exceeded(format!(
+                            "{} gRPC request timed out",
+                            rpc
+                        ))))
+                        .await
+                        .ok();
+                }
+                _ => {}
+            }
+        });
+        let block_range_stream = BlockRangeStream::new(CompactBlockStream::new(channel_rx));
+        let stream_boxed: BlockRangeResponseStream = Box::pin(block_range_stream.into_blocks());
+        Ok(tonic::Response::new(stream_boxed))
     }
+}
+
+impl CompactTxStreamer for GrpcClient {
+    define_rpc!(
+        /// Return the height of the tip of the best chain.
+        ///
+        /// If `latest_block_confirmations` is set the block that many blocks below the tip is returned instead, so
+        /// conservative wallets do not sync blocks likely to be reorged away.
+        fn get_latest_block(&self, _request: ChainSpec) -> BlockId {
+            self.node_health.check_available()?;
+            self.node_health.check_chain_consistent()?;
+            let (tip, tip_hash) = self.fetch_chain_tip().await?;
+
+            // Block hashes are returned in display order, matching lightwalletd.
+            let block_id = if self.latest_block_confirmations == 0 {
+                BlockId {
+                    height: u64::from(tip),
+                    hash: tip_hash.bytes_in_display_order().to_vec(),
+                }
+            } else {
+                let height = tip.saturating_sub(self.latest_block_confirmations);
+                let hash = self
+                    .block_cache
+                    .get_block_hash(&self.zebrad_uri, &self.node_auth, height)
+                    .await?;
+                BlockId {
+                    height: u64::from(height),
+                    hash: hash.iter().rev().copied().collect(),
+                }
+            };
+
+            Ok(tonic::Response::new(block_id))
+        }
+    );
+
+    define_rpc!(
+        /// Return the compact block corresponding to the given block identifier.
+        ///
+        /// Blocks are served from the block cache, blocks not present are fetched from the node and cached. A block
+        /// given by hash is first resolved to its height, hashes are given in display order, matching get_tree_state.
+        ///
+        /// Returns Aborted if the block served would not match the requested hash or height, eg. after a reorg between
+        /// resolving the block's height and fetching it.
+        fn get_block(&self, request: BlockId) -> CompactBlock {
+            self.node_health.check_available()?;
+            self.node_health.check_chain_consistent()?;
+            let log_fields = RequestLogFields::of(&request);
+            let block_id = request.into_inner();
+            if block_id.hash.is_empty() {
+                log_fields.record("height", block_id.height);
+            } else {
+                log_fields.record("block_hash", hex::encode(&block_id.hash));
+            }
+            let zebrad_uri = &self.zebrad_uri;
+            let node_auth = &self.node_auth;
+            let block_cache = &self.block_cache;
+            let block =
+                fetch_verified_block(
+                    block_id,
+                    |hash| cached_block_height(block_cache, zebrad_uri, node_auth, hash),
+                    |height| async move {
+                        Ok(block_cache.get_block(zebrad_uri, node_auth, height).await?)
+                    },
+                )
+                .await?;
+            Ok(tonic::Response::new(block))
+        }
+    );
+
+    define_rpc!(
+        /// Same as GetBlock except actions contain only nullifiers, see [`nullifiers_only`].
+        ///
+        /// Blocks are served from the block cache, the block is resolved and verified as in get_block.
+        fn get_block_nullifiers(&self, request: BlockId) -> CompactBlock {
+            self.node_health.check_available()?;
+            self.node_health.check_chain_consistent()?;
+            let log_fields = RequestLogFields::of(&request);
+            let block_id = request.into_inner();
+            if block_id.hash.is_empty() {
+                log_fields.record("height", block_id.height);
+            } else {
+                log_fields.record("block_hash", hex::encode(&block_id.hash));
+            }
+            let zebrad_uri = &self.zebrad_uri;
+            let node_auth = &self.node_auth;
+            let block_cache = &self.block_cache;
+            let block =
+                fetch_verified_block(
+                    block_id,
+                    |hash| cached_block_height(block_cache, zebrad_uri, node_auth, hash),
+                    |height| async move {
+                        Ok(block_cache.get_block(zebrad_uri, node_auth, height).await?)
+                    },
+                )
+                .await?;
+            Ok(tonic::Response::new(nullifiers_only(block)))
+        }
+    );
+
+    /// Server streaming response type for the GetBlockRange method.
+    #[doc = "Server streaming response type for the GetBlockRange method."]
+    type GetBlockRangeStream = BlockRangeResponseStream;
+
+    define_rpc!(
+        /// Return a list of consecutive compact blocks.
+        ///
+        /// Blocks are served from the block cache, blocks not present are fetched from the node and cached.
+        ///
+        /// A completed stream ends with trailing metadata holding the number of blocks served,
+        /// [`BLOCKS_SERVED_TRAILER`], and the height of the last block served, [`LAST_HEIGHT_TRAILER`].
+        fn get_block_range(&self, request: BlockRange) -> Self::GetBlockRangeStream {
+            self.serve_block_range(request, "get_block_range", std::convert::identity)
+                .await
+        }
+    );
 
     /// Server streaming response type for the GetBlockRangeNullifiers method.
     #[doc = " Server streaming response type for the GetBlockRangeNullifiers method."]
-    type GetBlockRangeNullifiersStream = tonic::Streaming<CompactBlock>;
+    type GetBlockRangeNullifiersStream = BlockRangeResponseStream;
 
-    /// Same as GetBlockRange except actions contain only nullifiers.
-    ///
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    fn get_block_range_nullifiers<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<BlockRange>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<
-                        tonic::Response<Self::GetBlockRangeNullifiersStream>,
-                        tonic::Status,
-                    >,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_block_range_nullifiers.");
-        Box::pin(async {
-            Err(tonic::Status::unimplemented("get_block_range_nullifiers not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
-        })
-    }
+    define_rpc!(
+        /// Same as GetBlockRange except actions contain only nullifiers.
+        ///
+        /// Blocks are served from the block cache shared with GetBlockRange and stripped to their nullifiers, so ranges
+        /// already synced through either RPC are not fetched from the node again.
+        fn get_block_range_nullifiers(
+            &self,
+            request: BlockRange,
+        ) -> Self::GetBlockRangeNullifiersStream {
+            self.serve_block_range(request, "get_block_range_nullifiers", nullifiers_only)
+                .await
+        }
+    );
 
-    /// Return the requested full (not compact) transaction (as from zcashd).
-    fn get_transaction<'life0, 'async_trait>(
-        &'life0 self,
-        request: tonic::Request<TxFilter>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<tonic::Response<RawTransaction>, tonic::Status>,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_transaction.");
-        Box::pin(async {
+    define_rpc!(
+        /// Return the requested full (not compact) transaction (as from zcashd).
+        fn get_transaction(&self, request: TxFilter) -> RawTransaction {
+            self.node_health.check_available()?;
+            let log_fields = RequestLogFields::of(&request);
             let hash = request.into_inner().hash;
             if hash.len() == 32 {
                 let reversed_hash = hash.iter().rev().copied().collect::<Vec<u8>>();
                 let hash_hex = hex::encode(reversed_hash);
-                let tx = JsonRpcConnector::new(
-                    self.zebrad_uri.clone(),
-                    Some("xxxxxx".to_string()),
-                    Some("xxxxxx".to_string()),
-                )
-                .await
-                .get_raw_transaction(hash_hex, Some(1))
-                .await
-                .map_err(|e| e.to_grpc_status())?;
+                log_fields.record("txid", &hash_hex);
+                let lookup_key = format!("transaction:{}", hash_hex);
+                if self.negative_cache.contains(&lookup_key).await {
+                    self.rpc_metrics.record_negative_cache_hit();
+                    return Err(tonic::Status::not_found(format!(
+                        "Transaction {} not found.",
+                        hash_hex
+                    )));
+                }
+                let zebrad_client =
+                    JsonRpcConnector::new(self.zebrad_uri.clone(), self.node_auth.clone())
+                        .await
+                        .with_node_type(self.node_type);
+                let (tx, z_tx) = tokio::join!(
+                    zebrad_client.get_raw_transaction(hash_hex.clone(), Some(1)),
+                    zebrad_client.get_z_transaction(&hash_hex)
+                );
+                let tx = match tx {
+                    Ok(tx) => tx,
+                    Err(e) => {
+                        if e.is_not_found() {
+                            self.negative_cache.insert(lookup_key).await;
+                        }
+                        return Err(e.to_grpc_status());
+                    }
+                };
 
                 let (hex, height) = if let GetTransactionResponse::Object { hex, height, .. } = tx {
                     (hex, height)
                 } else {
-                    return Err(tonic::Status::not_found("Transaction not received"));
+                    return Err(tonic::Status::internal(
+                        "Received raw transaction type, this should not be impossible.",
+                    ));
+                };
+                // Some node versions return a negative height for mined transactions, the z_gettransaction height is
+                // used as a fallback when available. Unmined transactions are returned with a height of 0.
+                let height = match i64_to_height(height.into()) {
+                    Ok(height) => height_to_u64(height),
+                    Err(_) => z_tx
+                        .ok()
+                        .and_then(|z_tx| z_tx.height)
+                        .and_then(|height| i64_to_height(height).ok())
+                        .map_or(0, height_to_u64),
                 };
-                let height: u64 = height.try_into().map_err(|_e| {
-                    tonic::Status::internal(
-                        "Invalid response from server - Height conversion failed",
-                    )
-                })?;
 
                 Ok(tonic::Response::new(RawTransaction {
                     data: hex.bytes,
@@ -345,142 +961,128 @@ impl CompactTxStreamer for GrpcClient {
                     "Transaction hash incorrect",
                 ))
             }
-        })
-    }
+        }
+    );
 
-    /// Submit the given transaction to the Zcash network.
-    fn send_transaction<'life0, 'async_trait>(
-        &'life0 self,
-        request: tonic::Request<RawTransaction>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<tonic::Response<SendResponse>, tonic::Status>,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of send_transaction.");
-        Box::pin(async {
-            let hex_tx = hex::encode(request.into_inner().data);
-            let tx_output = JsonRpcConnector::new(
-                self.zebrad_uri.clone(),
-                Some("xxxxxx".to_string()),
-                Some("xxxxxx".to_string()),
-            )
-            .await
-            .send_raw_transaction(hex_tx)
-            .await
-            .map_err(|e| e.to_grpc_status())?;
+    define_rpc!(
+        /// Submit the given transaction to the Zcash network.
+        ///
+        /// Transactions the node rejects for their fee are answered with a SendResponse holding the
+        /// `RPC_TRANSACTION_REJECTED` error code and the node's reject reason, rather than an error status.
+        ///
+        /// While transaction broadcast is disabled every transaction is rejected with FAILED_PRECONDITION.
+        fn send_transaction(&self, request: RawTransaction) -> SendResponse {
+            if let Some(message) = &self.broadcast_disabled {
+                return Err(tonic::Status::failed_precondition(message.clone()));
+            }
+            let data = request.into_inner().data;
+            if data.is_empty() {
+                return Err(tonic::Status::invalid_argument(
+                    "Transaction data not specified",
+                ));
+            }
+            self.node_health.check_available()?;
+            let hex_tx = hex::encode(data);
+            let tx_output =
+                match JsonRpcConnector::new(self.zebrad_uri.clone(), self.node_auth.clone())
+                    .await
+                    .with_node_type(self.node_type)
+                    .send_raw_transaction(hex_tx, None)
+                    .await
+                {
+                    Ok(tx_output) => tx_output,
+                    // Fee rejections are returned in the SendResponse, as lightwalletd does, so wallets can tell them
+                    // apart from transport errors.
+                    Err(e) if e.is_fee_rejection() => {
+                        return Ok(tonic::Response::new(SendResponse {
+                            error_code: RPC_TRANSACTION_REJECTED,
+                            error_message: e.to_string(),
+                        }));
+                    }
+                    Err(e) => return Err(e.to_grpc_status()),
+                };
 
             Ok(tonic::Response::new(SendResponse {
                 error_code: 0,
                 error_message: tx_output.0.to_string(),
             }))
-        })
-    }
+        }
+    );
 
     /// Server streaming response type for the GetTaddressTxids method.
     #[doc = "Server streaming response type for the GetTaddressTxids method."]
     type GetTaddressTxidsStream = std::pin::Pin<Box<RawTransactionStream>>;
 
-    /// This name is misleading, returns the full transactions that have either inputs or outputs connected to the given transparent address.
-    fn get_taddress_txids<'life0, 'async_trait>(
-        &'life0 self,
-        request: tonic::Request<TransparentAddressBlockFilter>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<
-                        tonic::Response<Self::GetTaddressTxidsStream>,
-                        tonic::Status,
-                    >,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_taddress_txids.");
-        Box::pin(async move {
+    define_rpc!(
+        /// This name is misleading, returns the full transactions that have either inputs or outputs connected to the given transparent address.
+        ///
+        /// Transactions the node fails to return are handled according to `stream_error_policy`.
+        fn get_taddress_txids(
+            &self,
+            request: TransparentAddressBlockFilter,
+        ) -> Self::GetTaddressTxidsStream {
+            self.node_health.check_available()?;
+            let log_fields = RequestLogFields::of(&request);
             let block_filter = request.into_inner();
-            let address = block_filter.address;
+            log_fields.record("address", &block_filter.address);
+            let addresses = validate_transparent_addresses(&[block_filter.address], self.network)
+                .map_err(|e| tonic::Status::invalid_argument(e.to_string()))?;
             let start = block_filter
                 .range
                 .clone()
                 .and_then(|r| r.start)
-                .map(|s| s.height as u32)
-                .ok_or(tonic::Status::invalid_argument("Start block not specified"))?;
+                .ok_or(tonic::Status::invalid_argument("Start block not specified"))
+                .and_then(|s| request_height(s.height))?;
             let end = block_filter
                 .range
                 .and_then(|r| r.end)
-                .map(|e| e.height as u32)
-                .ok_or(tonic::Status::invalid_argument("End block not specified"))?;
+                .ok_or(tonic::Status::invalid_argument("End block not specified"))
+                .and_then(|e| request_height(e.height))?;
+            log_fields.record("start_height", start);
+            log_fields.record("end_height", end);
 
-            let zebrad_client = JsonRpcConnector::new(
-                self.zebrad_uri.clone(),
-                Some("xxxxxx".to_string()),
-                Some("xxxxxx".to_string()),
-            )
-            .await;
-            let txids = zebrad_client
-                .get_address_txids(vec![address], start, end)
+            let zebrad_client =
+                JsonRpcConnector::new(self.zebrad_uri.clone(), self.node_auth.clone())
+                    .await
+                    .with_node_type(self.node_type);
+            let mut txids = zebrad_client
+                .get_address_txids(addresses.clone(), start, end)
                 .await
-                .map_err(|e| e.to_grpc_status())?;
+                .map_err(|e| e.to_grpc_status())?
+                .transactions;
+            // Unconfirmed transactions are streamed after confirmed transactions, with a height of 0.
+            if self.taddress_txids_include_mempool {
+                let mempool_deltas = zebrad_client
+                    .get_address_mempool(addresses)
+                    .await
+                    .map_err(|e| e.to_grpc_status())?;
+                for delta in mempool_deltas {
+                    if !txids.contains(&delta.txid) {
+                        txids.push(delta.txid);
+                    }
+                }
+            }
 
+            let stream_error_policy = self.stream_error_policy;
+            let fetch_concurrency = self.taddress_txids_fetch_concurrency;
             let (channel_tx, channel_rx) = tokio::sync::mpsc::channel(32);
             tokio::spawn(async move {
                 let timeout = timeout(std::time::Duration::from_secs(30), async {
-                    for txid in txids.transactions {
-                        let transaction = zebrad_client.get_raw_transaction(txid, Some(1)).await;
-                        match transaction {
-                            Ok(GetTransactionResponse::Object { hex, height, .. }) => {
-                                if channel_tx
-                                    .send(Ok(RawTransaction {
-                                        data: hex.bytes,
-                                        height: height as u64,
-                                    }))
-                                    .await
-                                    .is_err()
-                                {
-                                    break;
-                                }
-                            }
-                            Ok(GetTransactionResponse::Raw(_)) => {
-                                if channel_tx
-                                .send(Err(tonic::Status::internal(
-                                    "Received raw transaction type, this should not be impossible.",
-                                )))
-                                .await
-                                .is_err()
-                            {
-                                break;
-                            }
-                            }
-                            Err(e) => {
-                                if channel_tx
-                                    .send(Err(tonic::Status::internal(e.to_string())))
-                                    .await
-                                    .is_err()
-                                {
-                                    break;
-                                }
-                            }
-                        }
-                    }
+                    stream_node_items(
+                        txids,
+                        |txid| fetch_raw_transaction(&zebrad_client, txid),
+                        &channel_tx,
+                        stream_error_policy,
+                        fetch_concurrency,
+                    )
+                    .await
                 })
                 .await;
                 match timeout {
                     Ok(_) => {}
                     Err(_) => {
                         channel_tx
-                            .send(Err(tonic::Status::internal(
+                            .send(Err(tonic::Status::deadline_exceeded(
                                 "get_taddress_txids gRPC request timed out",
                             )))
                             .await
@@ -491,139 +1093,137 @@ impl CompactTxStreamer for GrpcClient {
             let output_stream = RawTransactionStream::new(channel_rx);
             let stream_boxed = Box::pin(output_stream);
             Ok(tonic::Response::new(stream_boxed))
-        })
-    }
+        }
+    );
 
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    fn get_taddress_balance<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<AddressList>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<tonic::Response<Balance>, tonic::Status>,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_taddress_balance.");
-        Box::pin(async {
+    define_rpc!(
+        /// This RPC has not been implemented as it is not currently used by zingolib.
+        /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
+        fn get_taddress_balance(&self, request: AddressList) -> Balance {
+            validate_transparent_addresses(&request.into_inner().addresses, self.network)
+                .map_err(|e| tonic::Status::invalid_argument(e.to_string()))?;
             Err(tonic::Status::unimplemented("get_taddress_balance not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
-        })
-    }
+        }
+    );
 
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    #[must_use]
-    #[allow(clippy::type_complexity, clippy::type_repetition_in_bounds)]
-    fn get_taddress_balance_stream<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<tonic::Streaming<Address>>,
-    ) -> ::core::pin::Pin<
-        Box<
-            dyn ::core::future::Future<Output = Result<tonic::Response<Balance>, tonic::Status>>
-                + ::core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_taddress_balance_stream.");
-        Box::pin(async {
+    define_rpc!(
+        /// This RPC has not been implemented as it is not currently used by zingolib.
+        /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
+        fn get_taddress_balance_stream(&self, _request: tonic::Streaming<Address>) -> Balance {
             Err(tonic::Status::unimplemented("get_taddress_balance_stream not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
-        })
-    }
+        }
+    );
 
     /// Server streaming response type for the GetMempoolTx method.
     #[doc = "Server streaming response type for the GetMempoolTx method."]
-    type GetMempoolTxStream = tonic::Streaming<CompactTx>;
-
-    /// Return the compact transactions currently in the mempool; the results
-    /// can be a few seconds out of date. If the Exclude list is empty, return
-    /// all transactions; otherwise return all *except* those in the Exclude list
-    /// (if any); this allows the client to avoid receiving transactions that it
-    /// already has (from an earlier call to this rpc). The transaction IDs in the
-    /// Exclude list can be shortened to any number of bytes to make the request
-    /// more bandwidth-efficient; if two or more transactions in the mempool
-    /// match a shortened txid, they are all sent (none is excluded). Transactions
-    /// in the exclude list that don't exist in the mempool are ignored.
-    ///
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    fn get_mempool_tx<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<Exclude>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<
-                        tonic::Response<Self::GetMempoolTxStream>,
-                        tonic::Status,
-                    >,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_mempool_tx.");
-        Box::pin(async {
-            Err(tonic::Status::unimplemented("get_mempool_tx not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
-        })
-    }
+    type GetMempoolTxStream = std::pin::Pin<Box<CompactTxStream>>;
+
+    define_rpc!(
+        /// Return the compact transactions currently in the mempool; the results
+        /// can be a few seconds out of date. If the Exclude list is empty, return
+        /// all transactions; otherwise return all *except* those in the Exclude list
+        /// (if any); this allows the client to avoid receiving transactions that it
+        /// already has (from an earlier call to this rpc). The transaction IDs in the
+        /// Exclude list can be shortened to any number of bytes to make the request
+        /// more bandwidth-efficient; if two or more transactions in the mempool
+        /// match a shortened txid, they are all sent (none is excluded). Transactions
+        /// in the exclude list that don't exist in the mempool are ignored.
+        ///
+        /// Exclude entries must be 1 to 32 bytes long and at most `max_mempool_exclude_entries` may be given, otherwise
+        /// the request is rejected with INVALID_ARGUMENT.
+        ///
+        /// Transactions are served from the shared [`MempoolTxCache`], transactions not cached or evicted from the cache
+        /// are fetched from the node and cached. Transactions older than `max_mempool_tx_age` are not served.
+        fn get_mempool_tx(&self, request: Exclude) -> Self::GetMempoolTxStream {
+            let exclude =
+                MempoolExclude::new(request.into_inner().txid, self.max_mempool_exclude_entries)?;
+            self.node_health.check_available()?;
+            let zebrad_client =
+                JsonRpcConnector::new(self.zebrad_uri.clone(), self.node_auth.clone())
+                    .await
+                    .with_node_type(self.node_type);
+
+            let mempool = Mempool::new();
+            mempool.update(&self.zebrad_uri, &self.node_auth).await?;
+            let mempool_txids = mempool.get_mempool_txids().await?;
+            self.mempool_cache.retain(&mempool_txids).await;
+            let stale_txids = stale_mempool_txids(&zebrad_client, self.max_mempool_tx_age).await?;
+            let txids: Vec<String> = exclude
+                .filter(mempool_txids)
+                .into_iter()
+                .filter(|txid| !stale_txids.contains(txid))
+                .collect();
+            let mempool_cache = self.mempool_cache.clone();
+
+            let (channel_tx, channel_rx) = tokio::sync::mpsc::channel(32);
+            tokio::spawn(async move {
+                let timeout = timeout(std::time::Duration::from_secs(30), async {
+                    let mut mempool_entries = None;
+                    for txid in txids {
+                        // Transactions evicted from the cache are fetched from the node again.
+                        let compact_tx = match mempool_cache.get(&txid).await {
+                            Some(cached) => Ok(cached.compact),
+                            None => match fetch_mempool_tx(
+                                &zebrad_client,
+                                &mempool_cache,
+                                &mut mempool_entries,
+                                txid,
+                            )
+                            .await
+                            {
+                                Some(compact_tx) => compact_tx,
+                                None => continue,
+                            },
+                        };
+                        if channel_tx.send(compact_tx).await.is_err() {
+                            break;
+                        }
+                    }
+                })
+                .await;
+                if timeout.is_err() {
+                    channel_tx
+                        .send(Err(tonic::Status::deadline_exceeded(
+                            "get_mempool_tx gRPC request timed out",
+                        )))
+                        .await
+                        .ok();
+                }
+            });
+            let output_stream = CompactTxStream::new(channel_rx);
+            let stream_boxed = Box::pin(output_stream);
+            Ok(tonic::Response::new(stream_boxed))
+        }
+    );
 
     /// Server streaming response type for the GetMempoolStream method.
     #[doc = "Server streaming response type for the GetMempoolStream method."]
     type GetMempoolStreamStream = std::pin::Pin<Box<RawTransactionStream>>;
 
-    /// Return a stream of current Mempool transactions. This will keep the output stream open while
-    /// there are mempool transactions. It will close the returned stream when a new block is mined.
-    ///
-    /// TODO: This implementation is slow. Zingo-Indexer's blockcache state engine should keep its own internal mempool state.
-    ///     - This RPC should query Zingo-Indexer's internal mempool state rather than creating its own mempool and directly querying zebrad.
-    fn get_mempool_stream<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<Empty>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<
-                        tonic::Response<Self::GetMempoolStreamStream>,
-                        tonic::Status,
-                    >,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_mempool_stream.");
-        Box::pin(async {
-            let zebrad_client = JsonRpcConnector::new(
-                self.zebrad_uri.clone(),
-                Some("xxxxxx".to_string()),
-                Some("xxxxxx".to_string()),
-            )
-            .await;
+    define_rpc!(
+        /// Return a stream of current Mempool transactions. This will keep the output stream open while
+        /// there are mempool transactions. It will close the returned stream when a new block is mined.
+        ///
+        /// Transactions that entered the node's mempool more than `max_mempool_tx_age` ago are not served.
+        ///
+        /// TODO: This implementation is slow. Zingo-Indexer's blockcache state engine should keep its own internal mempool state.
+        ///     - This RPC should query Zingo-Indexer's internal mempool state rather than creating its own mempool and directly querying zebrad.
+        fn get_mempool_stream(&self, _request: Empty) -> Self::GetMempoolStreamStream {
+            self.node_health.check_available()?;
+            let zebrad_client =
+                JsonRpcConnector::new(self.zebrad_uri.clone(), self.node_auth.clone())
+                    .await
+                    .with_node_type(self.node_type);
 
             let zebrad_uri = self.zebrad_uri.clone();
+            let node_auth = self.node_auth.clone();
+            let max_mempool_tx_age = self.max_mempool_tx_age;
             let (channel_tx, channel_rx) = tokio::sync::mpsc::channel(32);
             tokio::spawn(async move {
                 let timeout = timeout(std::time::Duration::from_secs(30), async {
                     let mempool = Mempool::new();
-                    if let Err(e) = mempool.update(&zebrad_uri).await {
-                        channel_tx.send(Err(tonic::Status::internal(e.to_string())))
+                    if let Err(e) = mempool.update(&zebrad_uri, &node_auth).await {
+                        channel_tx.send(Err(tonic::Status::from(e)))
                             .await
                             .ok();
                         return;
@@ -631,9 +1231,21 @@ impl CompactTxStreamer for GrpcClient {
                     let mut mined = false;
                     let mut txid_index: usize = 0;
                     while !mined {
+                        let stale_txids =
+                            match stale_mempool_txids(&zebrad_client, max_mempool_tx_age).await {
+                                Ok(stale_txids) => stale_txids,
+                                Err(e) => {
+                                    channel_tx.send(Err(e)).await.ok();
+                                    break;
+                                }
+                            };
                         match mempool.get_mempool_txids().await {
                             Ok(mempool_txids) => {
                                 for txid in &mempool_txids[txid_index..] {
+                                    if stale_txids.contains(txid) {
+                                        txid_index += 1;
+                                        continue;
+                                    }
                                     match zebrad_client
                                         .get_raw_transaction(txid.clone(), Some(1))
                                         .await {
@@ -642,7 +1254,9 @@ impl CompactTxStreamer for GrpcClient {
                                             if channel_tx
                                                 .send(Ok(RawTransaction {
                                                     data: hex.bytes,
-                                                    height: height as u64,
+                                                    // Mempool transactions are returned with a height of -1.
+                                                    height: i64_to_height(height.into())
+                                                        .map_or(0, height_to_u64),
                                                 }))
                                                 .await
                                                 .is_err()
@@ -663,7 +1277,7 @@ impl CompactTxStreamer for GrpcClient {
                                         }
                                         Err(e) => {
                                             if channel_tx
-                                                .send(Err(tonic::Status::internal(e.to_string())))
+                                                .send(Err(tonic::Status::from(e)))
                                                 .await
                                                 .is_err()
                                             {
@@ -675,7 +1289,7 @@ impl CompactTxStreamer for GrpcClient {
                             }
                             Err(e) => {
                                 if channel_tx
-                                    .send(Err(tonic::Status::internal(e.to_string())))
+                                    .send(Err(tonic::Status::from(e)))
                                     .await
                                     .is_err()
                                 {
@@ -684,10 +1298,10 @@ impl CompactTxStreamer for GrpcClient {
                             }
                         }
                         tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                        mined = match mempool.update(&zebrad_uri).await {
+                        mined = match mempool.update(&zebrad_uri, &node_auth).await {
                             Ok(mined) => mined,
                             Err(e) => {
-                                channel_tx.send(Err(tonic::Status::internal(e.to_string())))
+                                channel_tx.send(Err(tonic::Status::from(e)))
                                     .await
                                     .ok();
                                 break;
@@ -700,7 +1314,7 @@ impl CompactTxStreamer for GrpcClient {
                     Ok(_) => {}
                     Err(_) => {
                         channel_tx
-                            .send(Err(tonic::Status::internal(
+                            .send(Err(tonic::Status::deadline_exceeded(
                                 "get_mempool_stream gRPC request timed out",
                             )))
                             .await
@@ -711,203 +1325,220 @@ impl CompactTxStreamer for GrpcClient {
             let output_stream = RawTransactionStream::new(channel_rx);
             let stream_boxed = Box::pin(output_stream);
             Ok(tonic::Response::new(stream_boxed))
-        })
-    }
+        }
+    );
 
-    /// GetTreeState returns the note commitment tree state corresponding to the given block.
-    /// See section 3.7 of the Zcash protocol specification. It returns several other useful
-    /// values also (even though they can be obtained using GetBlock).
-    /// The block can be specified by either height or hash.
-    fn get_tree_state<'life0, 'async_trait>(
-        &'life0 self,
-        request: tonic::Request<BlockId>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<tonic::Response<TreeState>, tonic::Status>,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_tree_state.");
-        Box::pin(async {
+    define_rpc!(
+        /// GetTreeState returns the note commitment tree state corresponding to the given block.
+        /// See section 3.7 of the Zcash protocol specification. It returns several other useful
+        /// values also (even though they can be obtained using GetBlock).
+        /// The block can be specified by either height or hash, see [`resolve_block_id`] for which is used.
+        fn get_tree_state(&self, request: BlockId) -> TreeState {
+            self.node_health.check_available()?;
+            let log_fields = RequestLogFields::of(&request);
             let block_id = request.into_inner();
-            let hash_or_height = if block_id.height != 0 {
-                block_id.height.to_string()
-            } else {
-                hex::encode(block_id.hash)
+            // Block hashes are given and returned in display order, matching lightwalletd.
+            let hash_or_height = block_lookup(&block_id)?.to_string();
+            log_fields.record("block", &hash_or_height);
+            let lookup_key = format!("treestate:{}", hash_or_height);
+            if self.negative_cache.contains(&lookup_key).await {
+                self.rpc_metrics.record_negative_cache_hit();
+                return Err(tonic::Status::not_found(format!(
+                    "Block {} not found.",
+                    hash_or_height
+                )));
+            }
+
+            let resolved = match resolve_block_id(&block_id, |hash| {
+                cached_block_height(&self.block_cache, &self.zebrad_uri, &self.node_auth, hash)
+            })
+            .await
+            {
+                Ok(resolved) => resolved,
+                Err(status) => {
+                    if status.code() == tonic::Code::NotFound {
+                        self.negative_cache.insert(lookup_key).await;
+                    }
+                    return Err(status);
+                }
             };
 
-            let zebrad_client = JsonRpcConnector::new(
-                self.zebrad_uri.clone(),
-                Some("xxxxxx".to_string()),
-                Some("xxxxxx".to_string()),
-            )
-            .await;
+            let zebrad_client =
+                JsonRpcConnector::new(self.zebrad_uri.clone(), self.node_auth.clone())
+                    .await
+                    .with_node_type(self.node_type);
 
             // TODO: This is slow. Chain, along with other blockchain info should be saved on startup and used here [blockcache?].
-            let chain = zebrad_client
+            let blockchain_info = zebrad_client
                 .get_blockchain_info()
                 .await
-                .map_err(|e| e.to_grpc_status())?
-                .chain;
-            let treestate = zebrad_client
-                .get_treestate(hash_or_height)
-                .await
                 .map_err(|e| e.to_grpc_status())?;
+            let (ChainHeight(tip), tip_hash) = blockchain_info.tip();
+            self.observe_chain_tip(tip, tip_hash).await;
+            let treestate = match zebrad_client.get_treestate(resolved.hash_or_height()).await {
+                Ok(treestate) => treestate,
+                Err(e) => {
+                    if e.is_not_found() {
+                        self.negative_cache.insert(lookup_key).await;
+                    }
+                    return Err(e.to_grpc_status());
+                }
+            };
+            let hash: BlockHash = treestate.hash.parse().map_err(|e| {
+                tonic::Status::internal(format!("Node returned an invalid block hash: {}", e))
+            })?;
+            let height = i64_to_height(treestate.height.into()).map_err(|e| {
+                tonic::Status::internal(format!(
+                    "Node returned an invalid tree state height: {}",
+                    e
+                ))
+            })?;
+            // Orchard is only present from NU5, before activation the empty tree is returned whatever the node reports.
+            let orchard_tree = if blockchain_info.is_active_at(NetworkUpgrade::Nu5, height) {
+                treestate.orchard.commitments.final_state
+            } else {
+                CommitmentTreestate::EMPTY_TREE_HEX.to_string()
+            };
             Ok(tonic::Response::new(TreeState {
-                network: chain,
-                height: treestate.height as u64,
-                hash: treestate.hash.to_string(),
+                network: blockchain_info.chain,
+                height: height_to_u64(height),
+                hash: hash.to_string(),
                 time: treestate.time,
                 sapling_tree: treestate.sapling.commitments.final_state.to_string(),
-                orchard_tree: treestate.orchard.commitments.final_state.to_string(),
+                orchard_tree,
             }))
-        })
-    }
+        }
+    );
 
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    fn get_latest_tree_state<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<Empty>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<tonic::Response<TreeState>, tonic::Status>,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_latest_tree_state.");
-        Box::pin(async {
+    define_rpc!(
+        /// This RPC has not been implemented as it is not currently used by zingolib.
+        /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
+        fn get_latest_tree_state(&self, _request: Empty) -> TreeState {
             Err(tonic::Status::unimplemented("get_latest_tree_state not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
-        })
-    }
+        }
+    );
 
     /// Server streaming response type for the GetSubtreeRoots method.
     #[doc = " Server streaming response type for the GetSubtreeRoots method."]
-    type GetSubtreeRootsStream = tonic::Streaming<SubtreeRoot>;
+    type GetSubtreeRootsStream = std::pin::Pin<Box<SubtreeRootStream>>;
 
-    /// Returns a stream of information about roots of subtrees of the Sapling and Orchard
-    /// note commitment trees.
-    ///
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    fn get_subtree_roots<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<GetSubtreeRootsArg>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<
-                        tonic::Response<Self::GetSubtreeRootsStream>,
-                        tonic::Status,
-                    >,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_subtree_roots.");
-        Box::pin(async {
-            Err(tonic::Status::unimplemented("get_subtree_roots not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
-        })
-    }
+    define_rpc!(
+        /// Returns a stream of information about roots of subtrees of the Sapling and Orchard
+        /// note commitment trees.
+        ///
+        /// Exactly `max_entries` roots (or all roots if 0) are returned from `start_index`, so an interrupted stream is
+        /// resumed by requesting from the index after the last root received.
+        fn get_subtree_roots(&self, request: GetSubtreeRootsArg) -> Self::GetSubtreeRootsStream {
+            self.node_health.check_available()?;
+            self.node_health.check_chain_consistent()?;
+            let subtree_roots_arg = request.into_inner();
+            let pool = ShieldedProtocol::try_from(subtree_roots_arg.shielded_protocol)
+                .map_err(|_| tonic::Status::invalid_argument("Unknown shielded protocol"))?;
 
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    fn get_address_utxos<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<GetAddressUtxosArg>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<
-                        tonic::Response<GetAddressUtxosReplyList>,
-                        tonic::Status,
-                    >,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_address_utxos.");
-        Box::pin(async {
+            let zebrad_client =
+                JsonRpcConnector::new(self.zebrad_uri.clone(), self.node_auth.clone())
+                    .await
+                    .with_node_type(self.node_type);
+            let block_cache = self.block_cache.clone();
+            let zebrad_uri = self.zebrad_uri.clone();
+            let node_auth = self.node_auth.clone();
+            let (channel_tx, channel_rx) = tokio::sync::mpsc::channel(32);
+            tokio::spawn(async move {
+                // Subtree indexes are 16 bit.
+                stream_indexed_pages(
+                    subtree_roots_arg.start_index,
+                    subtree_roots_arg.max_entries,
+                    u16::MAX as u32,
+                    SUBTREE_ROOTS_PAGE_SIZE,
+                    |index, limit| {
+                        fetch_subtree_roots(
+                            &zebrad_client,
+                            &block_cache,
+                            &zebrad_uri,
+                            &node_auth,
+                            pool,
+                            index,
+                            limit,
+                        )
+                    },
+                    &channel_tx,
+                )
+                .await;
+            });
+            let output_stream = SubtreeRootStream::new(channel_rx);
+            let stream_boxed = Box::pin(output_stream);
+            Ok(tonic::Response::new(stream_boxed))
+        }
+    );
+
+    define_rpc!(
+        /// This RPC has not been implemented as it is not currently used by zingolib.
+        /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
+        fn get_address_utxos(&self, request: GetAddressUtxosArg) -> GetAddressUtxosReplyList {
+            validate_transparent_addresses(&request.into_inner().addresses, self.network)
+                .map_err(|e| tonic::Status::invalid_argument(e.to_string()))?;
             Err(tonic::Status::unimplemented("get_address_utxos not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
-        })
-    }
+        }
+    );
 
     /// Server streaming response type for the GetAddressUtxosStream method.
     #[doc = "Server streaming response type for the GetAddressUtxosStream method."]
-    type GetAddressUtxosStreamStream = tonic::Streaming<GetAddressUtxosReply>;
-
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    fn get_address_utxos_stream<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<GetAddressUtxosArg>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<
-                        tonic::Response<Self::GetAddressUtxosStreamStream>,
-                        tonic::Status,
-                    >,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_address_utxos_stream.");
-        Box::pin(async {
-            Err(tonic::Status::unimplemented("get_address_utxos_stream not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
-        })
-    }
+    type GetAddressUtxosStreamStream = std::pin::Pin<Box<AddressUtxoStream>>;
 
-    /// Return information about this lightwalletd instance and the blockchain
-    fn get_lightd_info<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<Empty>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<tonic::Response<LightdInfo>, tonic::Status>,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of get_lightd_info.");
-        // TODO: Add user and password as fields of GrpcClient and use here.
-        // TODO: Return Nym_Address in get_lightd_info response, for use by wallets.
-        Box::pin(async {
-            let zebrad_client = JsonRpcConnector::new(
-                self.zebrad_uri.clone(),
-                Some("xxxxxx".to_string()),
-                Some("xxxxxx".to_string()),
-            )
-            .await;
+    define_rpc!(
+        /// Return the UTXOs of the given transparent addresses in height order, from `start_height`, up to `max_entries`
+        /// UTXOs, or all if 0.
+        ///
+        /// `start_height` and `max_entries` form a pagination cursor: clients page through an address's UTXOs by
+        /// requesting the next page from the height of the last UTXO received, skipping the UTXOs at that height they
+        /// already hold.
+        fn get_address_utxos_stream(
+            &self,
+            request: GetAddressUtxosArg,
+        ) -> Self::GetAddressUtxosStreamStream {
+            self.node_health.check_available()?;
+            let log_fields = RequestLogFields::of(&request);
+            let utxos_arg = request.into_inner();
+            let addresses = validate_transparent_addresses(&utxos_arg.addresses, self.network)
+                .map_err(|e| tonic::Status::invalid_argument(e.to_string()))?;
+            for address in &addresses {
+                log_fields.record("address", address);
+            }
+            log_fields.record("start_height", utxos_arg.start_height);
+
+            let utxos = JsonRpcConnector::new(self.zebrad_uri.clone(), self.node_auth.clone())
+                .await
+                .with_node_type(self.node_type)
+                .get_address_utxos(addresses)
+                .await
+                .map_err(|e| e.to_grpc_status())?;
+            let page = address_utxo_page(utxos, utxos_arg.start_height, utxos_arg.max_entries);
+            let (channel_tx, channel_rx) = tokio::sync::mpsc::channel(32);
+            tokio::spawn(async move {
+                for utxo in page {
+                    if channel_tx.send(Ok(utxo)).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            let output_stream = AddressUtxoStream::new(channel_rx);
+            let stream_boxed = Box::pin(output_stream);
+            Ok(tonic::Response::new(stream_boxed))
+        }
+    );
+
+    define_rpc!(
+        /// Return information about this lightwalletd instance and the blockchain
+        ///
+        /// While transaction broadcast is disabled the response carries the [`BROADCAST_DISABLED_HEADER`] metadata.
+        fn get_lightd_info(&self, _request: Empty) -> LightdInfo {
+            // TODO: Add user and password as fields of GrpcClient and use here.
+            // TODO: Return Nym_Address in get_lightd_info response, for use by wallets.
+            self.node_health.check_available()?;
+            let zebrad_client =
+                JsonRpcConnector::new(self.zebrad_uri.clone(), self.node_auth.clone())
+                    .await
+                    .with_node_type(self.node_type);
 
             let zebra_info = zebrad_client
                 .get_info()
@@ -917,6 +1548,15 @@ impl CompactTxStreamer for GrpcClient {
                 .get_blockchain_info()
                 .await
                 .map_err(|e| e.to_grpc_status())?;
+            let chain_name = NetworkKind::from_chain_name(&blockchain_info.chain)
+                .ok_or_else(|| {
+                    tonic::Status::internal(format!(
+                        "Node reported unknown chain {:?}, check the indexer is connected to a Zcash node.",
+                        blockchain_info.chain
+                    ))
+                })?
+                .chain_name()
+                .to_string();
 
             let sapling_id_str = "76b809bb";
             let sapling_id = ConsensusBranchIdHex(
@@ -939,44 +1579,36 @@ impl CompactTxStreamer for GrpcClient {
                 version: build_info.version,
                 vendor: "ZingoLabs ZingoIndexerD".to_string(),
                 taddr_support: true,
-                chain_name: blockchain_info.chain,
-                sapling_activation_height: sapling_height.0 as u64,
+                chain_name,
+                sapling_activation_height: height_to_u64(sapling_height),
                 consensus_branch_id: blockchain_info.consensus.chain_tip.0.to_string(),
-                block_height: blockchain_info.blocks.0 as u64,
+                block_height: height_to_u64(blockchain_info.blocks),
                 git_commit: build_info.commit_hash,
                 branch: build_info.branch,
                 build_date: build_info.build_date,
                 build_user: build_info.build_user,
-                estimated_height: blockchain_info.estimated_height.0 as u64,
+                estimated_height: height_to_u64(blockchain_info.estimated_height),
                 zcashd_build: zebra_info.build,
                 zcashd_subversion: zebra_info.subversion,
             };
 
-            Ok(tonic::Response::new(lightd_info))
-        })
-    }
+            let mut response = tonic::Response::new(lightd_info);
+            if self.broadcast_disabled.is_some() {
+                response.metadata_mut().insert(
+                    BROADCAST_DISABLED_HEADER,
+                    tonic::metadata::MetadataValue::from_static("true"),
+                );
+            }
+            Ok(response)
+        }
+    );
 
     // /// Testing-only, requires lightwalletd --ping-very-insecure (do not enable in production) [from zebrad]
-    /// This RPC has not been implemented as it is not currently used by zingolib.
-    /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
-    fn ping<'life0, 'async_trait>(
-        &'life0 self,
-        _request: tonic::Request<Duration>,
-    ) -> core::pin::Pin<
-        Box<
-            dyn core::future::Future<
-                    Output = std::result::Result<tonic::Response<PingResponse>, tonic::Status>,
-                > + core::marker::Send
-                + 'async_trait,
-        >,
-    >
-    where
-        'life0: 'async_trait,
-        Self: 'async_trait,
-    {
-        println!("[TEST] Received call of ping.");
-        Box::pin(async {
+    define_rpc!(
+        /// This RPC has not been implemented as it is not currently used by zingolib.
+        /// If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer).
+        fn ping(&self, _request: Duration) -> PingResponse {
             Err(tonic::Status::unimplemented("ping not yet implemented. If you require this RPC please open an issue or PR at the Zingo-Indexer github (https://github.com/zingolabs/zingo-indexer)."))
-        })
-    }
+        }
+    );
 }
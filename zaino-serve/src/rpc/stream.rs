@@ -0,0 +1,120 @@
+//! Response stream helpers for RPCs that fetch their items from the node one at a time.
+
+use futures::StreamExt;
+use std::{fmt::Display, future::Future};
+
+/// Behaviour when fetching a single item of a response stream from the node fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StreamErrorPolicy {
+    /// End the stream with the error as its terminal status, no further items are sent.
+    #[default]
+    Terminate,
+    /// Log a warning and continue with the next item, the failed item is left out of the stream.
+    Skip,
+}
+
+/// Fetches each of `items` with `fetch` and sends the results into the response stream `tx`, applying `policy` to
+/// failed fetches.
+///
+/// Up to `concurrency` items are fetched at a time, results are sent in the order of `items` regardless of the order
+/// fetches complete in. Fetching runs at most `concurrency` items ahead of the response stream, fetches still in
+/// flight are dropped once the stream ends.
+///
+/// Returns the number of items skipped. Stops early if the client drops the stream.
+pub async fn stream_node_items<I, T, F, Fut>(
+    items: I,
+    fetch: F,
+    tx: &tokio::sync::mpsc::Sender<Result<T, tonic::Status>>,
+    policy: StreamErrorPolicy,
+    concurrency: usize,
+) -> usize
+where
+    I: IntoIterator,
+    I::Item: Display + Clone,
+    F: Fn(I::Item) -> Fut,
+    Fut: Future<Output = Result<T, tonic::Status>>,
+{
+    let mut skipped = 0;
+    let mut results = std::pin::pin!(futures::stream::iter(items)
+        .map(|item| {
+            let result = fetch(item.clone());
+            async move { (item, result.await) }
+        })
+        .buffered(concurrency.max(1)));
+    while let Some((item, result)) = results.next().await {
+        match result {
+            Ok(value) => {
+                if tx.send(Ok(value)).await.is_err() {
+                    break;
+                }
+            }
+            Err(status) => match policy {
+                StreamErrorPolicy::Terminate => {
+                    tx.send(Err(status)).await.ok();
+                    break;
+                }
+                StreamErrorPolicy::Skip => {
+                    eprintln!(
+                        "Warning: Skipping {} in response stream: {}",
+                        item,
+                        status.message()
+                    );
+                    skipped += 1;
+                }
+            },
+        }
+    }
+    skipped
+}
+
+/// Streams the items of an index addressed collection held by the node into `tx`, from `start_index` up to
+/// `max_entries` items, or all items if 0.
+///
+/// Items are requested with `fetch_page(index, limit)` a page of at most `page_size` at a time, once the previous page
+/// has been accepted by the response stream, so items are only fetched as the client consumes them. Indexes above
+/// `max_index` do not exist and are never requested. The stream ends after the first page shorter than requested,
+/// after the first failed fetch, or when the client drops the stream.
+pub async fn stream_indexed_pages<T, F, Fut>(
+    start_index: u32,
+    max_entries: u32,
+    max_index: u32,
+    page_size: u32,
+    fetch_page: F,
+    tx: &tokio::sync::mpsc::Sender<Result<T, tonic::Status>>,
+) where
+    F: Fn(u32, u32) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, tonic::Status>>,
+{
+    let mut index = start_index;
+    let mut remaining = if max_entries == 0 {
+        u32::MAX
+    } else {
+        max_entries
+    };
+    while remaining > 0 && index <= max_index {
+        let limit = remaining
+            .min(page_size)
+            .min((max_index - index).saturating_add(1));
+        let page = match fetch_page(index, limit).await {
+            Ok(page) => page,
+            Err(status) => {
+                tx.send(Err(status)).await.ok();
+                return;
+            }
+        };
+        let page_len = page.len() as u32;
+        for item in page {
+            if tx.send(Ok(item)).await.is_err() {
+                return;
+            }
+        }
+        if page_len < limit {
+            return;
+        }
+        remaining -= page_len;
+        index = match index.checked_add(page_len) {
+            Some(index) => index,
+            None => return,
+        };
+    }
+}
@@ -5,11 +5,22 @@ use std::sync::{
     Arc,
 };
 
+pub mod consistency;
 pub mod director;
 pub mod error;
+pub mod health;
 pub(crate) mod ingestor;
+pub mod keepalive;
+pub mod limits;
+pub mod logging;
+pub mod metrics;
+pub(crate) mod middleware;
 pub(crate) mod queue;
+pub mod recovery;
 pub mod request;
+pub mod scaling;
+pub mod scheduler;
+pub mod supervisor;
 pub(crate) mod worker;
 
 /// Holds a thread safe reperesentation of a StatusType.
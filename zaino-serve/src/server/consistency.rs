@@ -0,0 +1,164 @@
+//! Checks the full node is on the chain the server's cached data was built from.
+//!
+//! After the node restarts, or a different node is brought up behind the same address, the server may be talking to
+//! another chain than the one its block cache holds. The node supervisor runs [`ChainConsistency::verify`] on every
+//! successful probe, so the check also runs on the first probe after the node returns. On a mismatch the server logs
+//! an error and records the mismatch in its [`NodeHealth`], RPCs served from the block cache then fail with
+//! FAILED_PRECONDITION until the server is restarted by an operator. If `reset_on_mismatch` is set the block cache is
+//! instead cleared and rebuilt from the node's chain.
+
+use std::sync::Mutex;
+
+use zaino_fetch::{
+    chain::cache::BlockCache,
+    jsonrpc::{connector::JsonRpcConnector, error::JsonRpcConnectorError},
+    primitives::{chain::NetworkKind, height::ChainHeight},
+};
+
+use crate::server::health::NodeHealth;
+
+/// A difference between the node's chain and the chain the server's cached data was built from.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ChainMismatch {
+    /// The node reports a different network than the server is configured for.
+    #[error("Node reports chain {found:?}, the server is configured for {expected:?}")]
+    Network {
+        /// Configured network.
+        expected: NetworkKind,
+        /// Chain name reported by the node.
+        found: String,
+    },
+    /// The node's genesis block differs from the genesis block recorded on startup.
+    #[error(
+        "Node genesis block hash {} does not match the recorded genesis block hash {}",
+        hex_display(.found),
+        hex_display(.expected)
+    )]
+    GenesisHash {
+        /// Recorded genesis block hash, in internal byte order.
+        expected: [u8; 32],
+        /// Node's genesis block hash, in internal byte order.
+        found: [u8; 32],
+    },
+    /// The node's block at the cache's finalized tip differs from the cached block.
+    #[error(
+        "Node block hash {} at height {height} does not match the cached block hash {}",
+        hex_display(.found),
+        hex_display(.cached)
+    )]
+    FinalizedBlock {
+        /// Height of the cache's finalized tip.
+        height: u32,
+        /// Cached block hash, in internal byte order.
+        cached: [u8; 32],
+        /// Node's block hash, in internal byte order.
+        found: [u8; 32],
+    },
+}
+
+/// Returns a hash in display order, hex encoded.
+fn hex_display(hash: &[u8; 32]) -> String {
+    hex::encode(hash.iter().rev().copied().collect::<Vec<u8>>())
+}
+
+/// Identity of the chain the server's cached data was built from.
+#[derive(Debug)]
+pub struct ChainConsistency {
+    /// Configured network.
+    network: NetworkKind,
+    /// Genesis block hash recorded on the first successful check, in internal byte order.
+    genesis_hash: Mutex<Option<[u8; 32]>>,
+    /// Clear the block cache on a mismatch, rather than refusing to serve cached data.
+    reset_on_mismatch: bool,
+}
+
+impl ChainConsistency {
+    /// Returns a ChainConsistency for the configured `network`, the genesis block hash is recorded by the first check.
+    pub fn new(network: NetworkKind, reset_on_mismatch: bool) -> Self {
+        ChainConsistency {
+            network,
+            genesis_hash: Mutex::new(None),
+            reset_on_mismatch,
+        }
+    }
+
+    /// Returns the recorded genesis block hash, in internal byte order.
+    pub fn genesis_hash(&self) -> Option<[u8; 32]> {
+        *self.lock_genesis_hash()
+    }
+
+    /// Compares the node's chain with the configured network, its genesis block with the recorded genesis block, and
+    /// its block at the cache's finalized tip with the cached block.
+    ///
+    /// Records the node's genesis block hash if none is recorded yet. Returns the first difference found, if any.
+    pub async fn check(
+        &self,
+        node: &JsonRpcConnector,
+        block_cache: &BlockCache,
+    ) -> Result<Option<ChainMismatch>, JsonRpcConnectorError> {
+        let blockchain_info = node.get_blockchain_info().await?;
+        if NetworkKind::from_chain_name(&blockchain_info.chain) != Some(self.network) {
+            return Ok(Some(ChainMismatch::Network {
+                expected: self.network,
+                found: blockchain_info.chain,
+            }));
+        }
+        let genesis_hash = node.get_block_hash(0).await?;
+        let expected = *self.lock_genesis_hash().get_or_insert(genesis_hash);
+        if genesis_hash != expected {
+            return Ok(Some(ChainMismatch::GenesisHash {
+                expected,
+                found: genesis_hash,
+            }));
+        }
+        let (ChainHeight(tip), _) = blockchain_info.tip();
+        if let Some((height, cached)) = block_cache.finalized_block_hash(tip).await {
+            let found = node.get_block_hash(height).await?;
+            if found != cached {
+                return Ok(Some(ChainMismatch::FinalizedBlock {
+                    height,
+                    cached,
+                    found,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Checks the node's chain, see [`ChainConsistency::check`], and acts on a mismatch.
+    ///
+    /// A network mismatch is always recorded in `node_health`, refusing cached data until an operator intervenes. Other
+    /// mismatches clear the block cache and record the node's genesis block if `reset_on_mismatch` is set, and are
+    /// recorded in `node_health` otherwise. Returns the mismatch found, if any.
+    pub async fn verify(
+        &self,
+        node: &JsonRpcConnector,
+        block_cache: &BlockCache,
+        node_health: &NodeHealth,
+    ) -> Result<Option<ChainMismatch>, JsonRpcConnectorError> {
+        let Some(mismatch) = self.check(node, block_cache).await? else {
+            return Ok(None);
+        };
+        let network_mismatch = matches!(mismatch, ChainMismatch::Network { .. });
+        if self.reset_on_mismatch && !network_mismatch {
+            let evicted = block_cache.clear().await;
+            *self.lock_genesis_hash() = Some(node.get_block_hash(0).await?);
+            eprintln!(
+                "WARNING: {}. Cleared {} blocks from the block cache, serving the node's chain.",
+                mismatch, evicted
+            );
+        } else if node_health.set_chain_mismatch(Some(mismatch.to_string())) {
+            eprintln!(
+                "ERROR: {}. Refusing to serve cached chain data until the server is restarted, set \
+                reset_cache_on_chain_mismatch to clear the cache automatically.",
+                mismatch
+            );
+        }
+        Ok(Some(mismatch))
+    }
+
+    /// Returns the genesis block hash, recovering it if a holder panicked.
+    fn lock_genesis_hash(&self) -> std::sync::MutexGuard<'_, Option<[u8; 32]>> {
+        self.genesis_hash.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -1,21 +1,55 @@
 //! Zingo-Indexer gRPC server.
 
 use http::Uri;
+#[cfg(feature = "nym")]
 use nym_sphinx_anonymous_replies::requests::AnonymousSenderTag;
 use std::{
+    collections::HashMap,
     net::SocketAddr,
+    ops::RangeInclusive,
     sync::{
-        atomic::{AtomicBool, AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicU16, AtomicUsize, Ordering},
         Arc,
     },
+    time::{Duration, Instant},
+};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::Notify,
+};
+use tonic::codec::CompressionEncoding;
+
+use zaino_fetch::{
+    chain::{
+        cache::{BlockCache, ChainTip, NegativeCache},
+        fetcher::{BlockFetcher, FetchOutcome},
+        mempool::MempoolTxCache,
+    },
+    jsonrpc::{
+        connector::{JsonRpcConnector, NodeAuth, NodeType, NodeTypeSelection},
+        error::JsonRpcConnectorError,
+    },
+    primitives::chain::NetworkKind,
 };
 
+use crate::rpc::stream::StreamErrorPolicy;
+#[cfg(feature = "nym")]
+use crate::server::ingestor::NymIngestor;
 use crate::server::{
+    consistency::ChainConsistency,
     error::{IngestorError, ServerError, WorkerError},
-    ingestor::{NymIngestor, TcpIngestor},
+    health::{supervise_node, NodeHealth},
+    ingestor::{RequestEnqueuer, TcpIngestor},
+    keepalive::Keepalive,
+    limits::RequestSizeLimits,
+    logging::{RequestLogLevel, SharedRequestLogLevel},
+    metrics::{serve_metrics, BackpressureLevel, LatencyQuantiles, QueueBackpressure, RpcMetrics},
+    middleware::{IpRateLimits, RpcSemaphores},
     queue::Queue,
-    request::ZingoIndexerRequest,
-    worker::{WorkerPool, WorkerPoolStatus},
+    request::{OverloadPolicy, ZingoIndexerRequest},
+    scaling::{scale_action, PoolLoad, ScaleAction},
+    supervisor::reap_workers,
+    worker::{WorkerConfig, WorkerPool, WorkerPoolStatus, WorkerQueues},
     AtomicStatus, StatusType,
 };
 
@@ -25,11 +59,23 @@ pub struct ServerStatus {
     /// Status of the Server.
     pub server_status: AtomicStatus,
     tcp_ingestor_status: AtomicStatus,
+    #[cfg(feature = "nym")]
     nym_ingestor_status: AtomicStatus,
+    #[cfg(feature = "nym")]
     nym_dispatcher_status: AtomicStatus,
     workerpool_status: WorkerPoolStatus,
     request_queue_status: Arc<AtomicUsize>,
+    #[cfg(feature = "nym")]
     nym_response_queue_status: Arc<AtomicUsize>,
+    /// Most called gRPC methods with their request counts, updated by [`Server::statuses`].
+    top_methods: Vec<(String, u64)>,
+    /// Rolling quantiles of the time requests wait in the queue, updated by [`Server::statuses`].
+    queue_wait: LatencyQuantiles,
+    /// Number of queued requests evicted to make room for new requests, updated by [`Server::statuses`].
+    queue_evictions: u64,
+    /// Rolling quantiles of the time between requests being queued and their first response, updated by
+    /// [`Server::statuses`].
+    first_response: LatencyQuantiles,
 }
 
 impl ServerStatus {
@@ -38,11 +84,18 @@ impl ServerStatus {
         ServerStatus {
             server_status: AtomicStatus::new(5),
             tcp_ingestor_status: AtomicStatus::new(5),
+            #[cfg(feature = "nym")]
             nym_ingestor_status: AtomicStatus::new(5),
+            #[cfg(feature = "nym")]
             nym_dispatcher_status: AtomicStatus::new(5),
             workerpool_status: WorkerPoolStatus::new(max_workers),
             request_queue_status: Arc::new(AtomicUsize::new(0)),
+            #[cfg(feature = "nym")]
             nym_response_queue_status: Arc::new(AtomicUsize::new(0)),
+            top_methods: Vec::new(),
+            queue_wait: LatencyQuantiles::default(),
+            queue_evictions: 0,
+            first_response: LatencyQuantiles::default(),
         }
     }
 
@@ -50,27 +103,299 @@ impl ServerStatus {
     pub fn load(&self) -> ServerStatus {
         self.server_status.load();
         self.tcp_ingestor_status.load();
-        self.nym_ingestor_status.load();
-        self.nym_dispatcher_status.load();
+        #[cfg(feature = "nym")]
+        {
+            self.nym_ingestor_status.load();
+            self.nym_dispatcher_status.load();
+            self.nym_response_queue_status.load(Ordering::SeqCst);
+        }
         self.workerpool_status.load();
         self.request_queue_status.load(Ordering::SeqCst);
-        self.nym_response_queue_status.load(Ordering::SeqCst);
         self.clone()
     }
+
+    /// Returns the most called gRPC methods with their request counts, most called first.
+    pub fn top_methods(&self) -> &[(String, u64)] {
+        &self.top_methods
+    }
+
+    /// Returns the rolling quantiles of the time requests wait in the queue.
+    pub fn queue_wait(&self) -> LatencyQuantiles {
+        self.queue_wait
+    }
+
+    /// Returns the number of queued requests evicted to make room for new requests, see
+    /// [`OverloadPolicy::DropOldest`].
+    pub fn queue_evictions(&self) -> u64 {
+        self.queue_evictions
+    }
+
+    /// Returns the rolling quantiles of the time between requests being queued and their first response.
+    pub fn first_response(&self) -> LatencyQuantiles {
+        self.first_response
+    }
+}
+
+/// Updates server settings while the server is running, returned by [`Server::reload_handle`].
+///
+/// Worker pool and queue size changes are applied on the server's next command loop iteration, request log level and
+/// rate limit changes apply to requests received from then on. A reload of the settings from their source, eg. the
+/// indexer's config file, is requested with [`ReloadHandle::reload`].
+#[derive(Debug, Clone)]
+pub struct ReloadHandle {
+    /// Maximum workers allowed in the worker pool.
+    max_worker_pool_size: Arc<AtomicU16>,
+    /// Minimum number of workers held in the worker pool when idle.
+    idle_worker_pool_size: Arc<AtomicU16>,
+    /// Number of workers the server status can track, the worker pool can not grow beyond this.
+    worker_capacity: u16,
+    /// Maximum number of requests held in the request queue.
+    max_queue_size: Arc<AtomicU16>,
+    /// Detail of the request log, shared with the workers.
+    request_log_level: SharedRequestLogLevel,
+    /// Per client IP address request rate limit, shared with the workers.
+    ip_rate_limits: Arc<IpRateLimits>,
+    /// Notified when a reload of the settings is requested.
+    reload_requested: Arc<Notify>,
+}
+
+impl ReloadHandle {
+    fn new(
+        max_worker_pool_size: u16,
+        idle_worker_pool_size: u16,
+        worker_capacity: u16,
+        max_queue_size: u16,
+        request_log_level: SharedRequestLogLevel,
+        ip_rate_limits: Arc<IpRateLimits>,
+    ) -> Self {
+        ReloadHandle {
+            max_worker_pool_size: Arc::new(AtomicU16::new(max_worker_pool_size)),
+            idle_worker_pool_size: Arc::new(AtomicU16::new(idle_worker_pool_size)),
+            worker_capacity,
+            max_queue_size: Arc::new(AtomicU16::new(max_queue_size)),
+            request_log_level,
+            ip_rate_limits,
+            reload_requested: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Requests a reload of the settings from their source, as on SIGHUP for zainod, which re-reads its config file.
+    ///
+    /// A request made while none is awaited is held until [`ReloadHandle::reload_requested`] is next awaited.
+    pub fn reload(&self) {
+        self.reload_requested.notify_one();
+    }
+
+    /// Returns once a reload is requested with [`ReloadHandle::reload`].
+    pub async fn reload_requested(&self) {
+        self.reload_requested.notified().await;
+    }
+
+    /// Sets the maximum and idle worker pool sizes.
+    ///
+    /// Returns an error if the idle size is zero or above the max size, or the max size is above the worker pool
+    /// size the server was started with.
+    pub fn set_worker_pool_size(&self, max_size: u16, idle_size: u16) -> Result<(), ServerError> {
+        if idle_size == 0 || idle_size > max_size {
+            return Err(ServerError::ServerConfigError(format!(
+                "Idle worker pool size ({}) must be non-zero and no greater than the max worker pool size ({}).",
+                idle_size, max_size
+            )));
+        }
+        if max_size > self.worker_capacity {
+            return Err(ServerError::ServerConfigError(format!(
+                "Max worker pool size ({}) can not be raised above the size the server was started with ({}).",
+                max_size, self.worker_capacity
+            )));
+        }
+        self.max_worker_pool_size.store(max_size, Ordering::SeqCst);
+        self.idle_worker_pool_size
+            .store(idle_size, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns the maximum worker pool size.
+    pub fn max_worker_pool_size(&self) -> u16 {
+        self.max_worker_pool_size.load(Ordering::SeqCst)
+    }
+
+    /// Returns the idle worker pool size.
+    pub fn idle_worker_pool_size(&self) -> u16 {
+        self.idle_worker_pool_size.load(Ordering::SeqCst)
+    }
+
+    /// Sets the maximum number of requests held in the request queue.
+    ///
+    /// Requests already queued above a lowered size are kept, new requests are handled by the overload policy until
+    /// the queue drains. Returns an error if the size is zero.
+    pub fn set_max_queue_size(&self, max_queue_size: u16) -> Result<(), ServerError> {
+        if max_queue_size == 0 {
+            return Err(ServerError::ServerConfigError(
+                "Max queue size must be non-zero.".to_string(),
+            ));
+        }
+        self.max_queue_size.store(max_queue_size, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns the maximum request queue size.
+    pub fn max_queue_size(&self) -> u16 {
+        self.max_queue_size.load(Ordering::SeqCst)
+    }
+
+    /// Sets the detail of the request log.
+    pub fn set_request_log_level(&self, level: RequestLogLevel) {
+        self.request_log_level.store(level);
+    }
+
+    /// Returns the detail of the request log.
+    pub fn request_log_level(&self) -> RequestLogLevel {
+        self.request_log_level.load()
+    }
+
+    /// Sets the maximum requests accepted from a single client IP address per second, 0 disables the limit.
+    pub fn set_max_requests_per_second_per_ip(&self, max_requests_per_second: u32) {
+        self.ip_rate_limits
+            .set_max_requests_per_second(max_requests_per_second);
+    }
+}
+
+/// Settings a [`Server`] is spawned with, see [`Server::spawn`].
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Listen for gRPC requests over TCP.
+    pub tcp_active: bool,
+    /// Address the TCP ingestor listens at, required if `tcp_active` is set. Port 0 binds an OS assigned port.
+    pub tcp_ingestor_listen_addr: Option<SocketAddr>,
+    /// Listen for gRPC requests over the Nym mixnet, requires the `nym` feature.
+    pub nym_active: bool,
+    /// Nym client config path, required if `nym_active` is set.
+    pub nym_conf_path: Option<String>,
+    /// Maximum bytes of responses queued for a single Nym client, further responses to the client are dropped.
+    pub nym_max_client_response_bytes: usize,
+    /// Lightwalletd uri, used by grpc_passthrough to pass on unimplemented RPCs.
+    pub lightwalletd_uri: Uri,
+    /// Full node JsonRPC uri.
+    pub zebrad_uri: Uri,
+    /// Credentials used to authenticate with the full node.
+    pub node_auth: NodeAuth,
+    /// Network the full node is running on.
+    pub network: NetworkKind,
+    /// Full node implementation, detected from the node at startup unless selected explicitly.
+    pub node_type: NodeTypeSelection,
+    /// Whether get_taddress_txids also returns the address's mempool transactions.
+    pub taddress_txids_include_mempool: bool,
+    /// Maximum number of transactions fetched from the node at a time by get_taddress_txids.
+    pub taddress_txids_fetch_concurrency: usize,
+    /// Number of confirmations subtracted from the chain tip returned by get_latest_block.
+    pub latest_block_confirmations: u32,
+    /// Maximum number of txids accepted in the exclude list of get_mempool_tx.
+    pub max_mempool_exclude_entries: usize,
+    /// Maximum bytes of mempool transactions cached for get_mempool_tx, 0 disables the cache.
+    pub mempool_cache_max_bytes: usize,
+    /// Maximum time since mempool transactions entered the node's mempool, older transactions are not served.
+    /// `None` serves all mempool transactions.
+    pub max_mempool_tx_age: Option<Duration>,
+    /// Message send_transaction is rejected with, `None` broadcasts transactions.
+    pub broadcast_disabled: Option<String>,
+    /// Behaviour when fetching a single item of a response stream from the node fails.
+    pub stream_error_policy: StreamErrorPolicy,
+    /// Maximum requests held in the request queue.
+    pub max_queue_size: u16,
+    /// Request queue fill percentage at which backpressure warnings are raised.
+    pub queue_warn_threshold: u8,
+    /// Request queue fill percentage at which backpressure errors are raised.
+    pub queue_error_threshold: u8,
+    /// Behaviour when a request arrives while the request queue is full.
+    pub overload_policy: OverloadPolicy,
+    /// Maximum time a request waits in the request queue, `None` disables the limit.
+    pub max_request_age: Option<Duration>,
+    /// Maximum workers allowed in the worker pool.
+    pub max_worker_pool_size: u16,
+    /// Minimum number of workers held in the worker pool when idle.
+    pub idle_worker_pool_size: u16,
+    /// Maximum number of concurrent gRPC streams allowed across the server.
+    pub max_concurrent_streams: u16,
+    /// Maximum number of concurrent gRPC streams allowed on a single client connection.
+    pub max_streams_per_connection: u16,
+    /// Maximum requests accepted from a single client IP address per second, 0 disables the limit.
+    pub max_requests_per_second_per_ip: u32,
+    /// Maximum concurrent executions of individual RPCs, keyed by gRPC method name.
+    pub rpc_concurrency_limits: HashMap<String, u16>,
+    /// Maximum time a request waits for a concurrency limited RPC.
+    pub rpc_max_wait: Duration,
+    /// Maximum request message size of individual RPCs, keyed by gRPC method name, replacing their default limits.
+    pub rpc_max_request_bytes: HashMap<String, usize>,
+    /// gRPC compression encodings supported, in order of preference.
+    pub grpc_compression: Vec<CompressionEncoding>,
+    /// Detail of the request log.
+    pub request_log_level: RequestLogLevel,
+    /// HTTP/2 and TCP keepalive settings applied to client connections.
+    pub keepalive: Keepalive,
+    /// Metrics endpoint listen address, the endpoint is disabled if `None`.
+    pub metrics_listen_addr: Option<SocketAddr>,
+    /// Start in a degraded state, rather than failing, if the node rejects the credentials.
+    pub degrade_on_auth_error: bool,
+    /// Number of consecutive failed node probes after which the node is considered unavailable.
+    pub node_failure_threshold: u32,
+    /// Window the consecutive failed node probes must fall within.
+    pub node_failure_window: Duration,
+    /// Delay between full node probes.
+    pub node_probe_interval: Duration,
+    /// Shut the server down if the full node becomes unavailable.
+    pub exit_on_node_loss: bool,
+    /// Clear the block cache if the node is found on a different chain than the cached blocks.
+    pub reset_cache_on_chain_mismatch: bool,
+    /// Heights fetched into the block cache in the background once serving, if set.
+    pub cache_warm_range: Option<RangeInclusive<u32>>,
+    /// Number of blocks below the block being streamed by get_block_range that are prefetched into the block cache.
+    pub block_cache_prefetch_window: u32,
+    /// Maximum number of blocks fetched from the node at a time by block prefetching and cache warming.
+    pub block_fetch_concurrency: usize,
 }
 
 /// LightWallet server capable of servicing clients over both http and nym.
+///
+/// Nym support is only built with the `nym` feature, without it the server only listens over http.
 pub struct Server {
     /// Listens for incoming gRPC requests over HTTP.
     tcp_ingestor: Option<TcpIngestor>,
+    /// Address the tcp ingestor is bound to, if tcp is active.
+    grpc_addr: Option<SocketAddr>,
     /// Listens for incoming gRPC requests over Nym Mixnet, also sends responses back to clients.
+    #[cfg(feature = "nym")]
     nym_ingestor: Option<NymIngestor>,
     /// Dynamically sized pool of workers.
     worker_pool: WorkerPool,
     /// Request queue.
     request_queue: Queue<ZingoIndexerRequest>,
     /// Nym response queue.
+    #[cfg(feature = "nym")]
     nym_response_queue: Queue<(Vec<u8>, AnonymousSenderTag)>,
+    /// Block cache shared by all workers.
+    block_cache: Arc<BlockCache>,
+    /// RPC latency metrics shared by all workers.
+    rpc_metrics: Arc<RpcMetrics>,
+    /// Metrics endpoint listen address, the endpoint is disabled if `None`.
+    metrics_listen_addr: Option<SocketAddr>,
+    /// Settings that can be updated while the server is running.
+    reload_handle: ReloadHandle,
+    /// Raises backpressure events as the request queue fills.
+    queue_backpressure: QueueBackpressure,
+    /// JsonRPC client used to probe the full node.
+    node_connector: Arc<JsonRpcConnector>,
+    /// Availability of the full node, shared with the workers.
+    node_health: Arc<NodeHealth>,
+    /// Delay between full node probes.
+    node_probe_interval: Duration,
+    /// Shut the server down if the full node becomes unavailable.
+    exit_on_node_loss: bool,
+    /// Identity of the chain the block cache was built from, checked on every successful node probe.
+    chain_consistency: Arc<ChainConsistency>,
+    /// Heights fetched into the block cache in the background once serving, if set.
+    cache_warm_range: Option<RangeInclusive<u32>>,
+    /// Maximum number of blocks fetched from the node at a time while warming the block cache.
+    block_fetch_concurrency: usize,
     /// Servers current status.
     status: ServerStatus,
     /// Represents the Online status of the Server.
@@ -78,20 +403,94 @@ pub struct Server {
 }
 
 impl Server {
-    /// Spawns a new Server.
+    /// Spawns a new Server with the settings in `config`.
+    ///
+    /// Responses to each Nym client are capped at `nym_max_client_response_bytes` queued bytes, responses over the cap
+    /// are dropped.
+    ///
+    /// gRPC responses are compressed with the first encoding in `grpc_compression` that the client accepts, and
+    /// requests compressed with any of these encodings are accepted. Requests are logged at `request_log_level`.
+    /// Requests received over TCP from a client IP address that has sent `max_requests_per_second_per_ip` requests in
+    /// the last second are rejected with RESOURCE_EXHAUSTED, 0 disables the limit.
+    /// Client connections are kept alive with the HTTP/2 pings and TCP keepalive probes set by `keepalive`.
+    /// Connections that waited in the request queue longer than `max_request_age` have their calls answered with
+    /// DEADLINE_EXCEEDED when dequeued, then are closed. Requests arriving while the queue is full are handled by
+    /// `overload_policy`.
+    /// Request messages larger than the [`RequestSizeLimits`] of their RPC, with the limits of the RPCs in
+    /// `rpc_max_request_bytes` replaced, are rejected with RESOURCE_EXHAUSTED before reaching the handler.
+    ///
+    /// Checks the node credentials, `node_auth`, with a `getinfo` call before launching components. If the node rejects the
+    /// credentials spawning fails, unless `degrade_on_auth_error` is set, in which case the server starts in a
+    /// degraded state that is reported by the metrics endpoint. The same call detects the node type unless `node_type`
+    /// selects it explicitly, a node that rejected the credentials is assumed to be zcashd.
+    ///
+    /// The node is probed every `node_probe_interval` once serving. After `node_failure_threshold` consecutive
+    /// connectivity failures within `node_failure_window` RPCs that need the node fail with UNAVAILABLE until the
+    /// node returns, or if `exit_on_node_loss` is set the server shuts down with [`ServerError::NodeUnavailable`].
+    /// Each successful probe also checks the node is still on the chain the block cache was built from, see
+    /// [`ChainConsistency`]. On a mismatch RPCs served from the block cache fail with FAILED_PRECONDITION until the
+    /// server is restarted, or if `reset_cache_on_chain_mismatch` is set the block cache is cleared instead. The
+    /// network solution rate and difficulty the node reports are refreshed on each probe for the metrics endpoint.
+    ///
+    /// If `cache_warm_range` is set the blocks at those heights are fetched into the block cache in the background
+    /// once serving, without delaying readiness. get_block_range prefetches up to `block_cache_prefetch_window`
+    /// blocks below the block being streamed. Prefetching and warming fetch at most `block_fetch_concurrency` blocks
+    /// at a time. get_mempool_tx caches up to `mempool_cache_max_bytes` of mempool transactions, see
+    /// [`MempoolTxCache`]. Mempool transactions that entered the node's mempool more than `max_mempool_tx_age` ago
+    /// are not served. get_taddress_txids fetches up to `taddress_txids_fetch_concurrency` transactions at a time.
+    /// If `broadcast_disabled` is set send_transaction is rejected with that message.
+    #[cfg_attr(not(feature = "nym"), allow(unused_variables))]
     pub async fn spawn(
-        tcp_active: bool,
-        tcp_ingestor_listen_addr: Option<SocketAddr>,
-        nym_active: bool,
-        nym_conf_path: Option<String>,
-        lightwalletd_uri: Uri,
-        zebrad_uri: Uri,
-        max_queue_size: u16,
-        max_worker_pool_size: u16,
-        idle_worker_pool_size: u16,
+        config: ServerConfig,
         status: ServerStatus,
         online: Arc<AtomicBool>,
     ) -> Result<Self, ServerError> {
+        let ServerConfig {
+            tcp_active,
+            tcp_ingestor_listen_addr,
+            nym_active,
+            nym_conf_path,
+            nym_max_client_response_bytes,
+            lightwalletd_uri,
+            zebrad_uri,
+            node_auth,
+            network,
+            node_type,
+            taddress_txids_include_mempool,
+            taddress_txids_fetch_concurrency,
+            latest_block_confirmations,
+            max_mempool_exclude_entries,
+            mempool_cache_max_bytes,
+            max_mempool_tx_age,
+            broadcast_disabled,
+            stream_error_policy,
+            max_queue_size,
+            queue_warn_threshold,
+            queue_error_threshold,
+            overload_policy,
+            max_request_age,
+            max_worker_pool_size,
+            idle_worker_pool_size,
+            max_concurrent_streams,
+            max_streams_per_connection,
+            max_requests_per_second_per_ip,
+            rpc_concurrency_limits,
+            rpc_max_wait,
+            rpc_max_request_bytes,
+            grpc_compression,
+            request_log_level,
+            keepalive,
+            metrics_listen_addr,
+            degrade_on_auth_error,
+            node_failure_threshold,
+            node_failure_window,
+            node_probe_interval,
+            exit_on_node_loss,
+            reset_cache_on_chain_mismatch,
+            cache_warm_range,
+            block_cache_prefetch_window,
+            block_fetch_concurrency,
+        } = config;
         if (!tcp_active) && (!nym_active) {
             return Err(ServerError::ServerConfigError(
                 "Cannot start server with no ingestors selected, at least one of either nym or tcp must be set to active in conf.".to_string(),
@@ -107,23 +506,53 @@ impl Server {
                 "NYM is active but no conf path provided.".to_string(),
             ));
         }
+        #[cfg(not(feature = "nym"))]
+        if nym_active {
+            return Err(ServerError::ServerConfigError(
+                "NYM is active but the server was built without the nym feature.".to_string(),
+            ));
+        }
+        let mut node_auth_failed = false;
+        let node_connector = JsonRpcConnector::new(zebrad_uri.clone(), node_auth.clone()).await;
+        let node_info = match node_connector.get_info().await {
+            Ok(info) => Some(info),
+            Err(JsonRpcConnectorError::Unauthorized) if degrade_on_auth_error => {
+                eprintln!("Node rejected credentials, starting server in degraded state.");
+                node_auth_failed = true;
+                None
+            }
+            Err(e) => return Err(ServerError::JsonRpcConnectorError(e)),
+        };
+        let node_type = node_type
+            .explicit()
+            .or_else(|| node_info.as_ref().map(NodeType::detect))
+            .unwrap_or_else(|| {
+                eprintln!("Warning: Node type could not be detected, assuming zcashd.");
+                NodeType::Zcashd
+            });
+        println!("Node type: {}.", node_type);
+        let node_connector = Arc::new(node_connector.with_node_type(node_type));
         println!("Launching Server!\n");
         status.server_status.store(0);
         let request_queue: Queue<ZingoIndexerRequest> =
             Queue::new(max_queue_size as usize, status.request_queue_status.clone());
         status.request_queue_status.store(0, Ordering::SeqCst);
+        #[cfg(feature = "nym")]
         let nym_response_queue: Queue<(Vec<u8>, AnonymousSenderTag)> = Queue::new(
             max_queue_size as usize,
             status.nym_response_queue_status.clone(),
         );
+        #[cfg(feature = "nym")]
         status.nym_response_queue_status.store(0, Ordering::SeqCst);
+        let rpc_metrics = Arc::new(RpcMetrics::new());
         let tcp_ingestor = if tcp_active {
             println!("Launching TcpIngestor..");
             Some(
                 TcpIngestor::spawn(
                     tcp_ingestor_listen_addr
                         .expect("tcp_ingestor_listen_addr returned none when used."),
-                    request_queue.tx().clone(),
+                    RequestEnqueuer::new(&request_queue, overload_policy, rpc_metrics.clone()),
+                    keepalive,
                     status.tcp_ingestor_status.clone(),
                     online.clone(),
                 )
@@ -132,6 +561,7 @@ impl Server {
         } else {
             None
         };
+        #[cfg(feature = "nym")]
         let nym_ingestor = if nym_active {
             println!("Launching NymIngestor..");
             let nym_conf_path_string =
@@ -139,9 +569,10 @@ impl Server {
             Some(
                 NymIngestor::spawn(
                     nym_conf_path_string.clone().as_str(),
-                    request_queue.tx().clone(),
+                    RequestEnqueuer::new(&request_queue, overload_policy, rpc_metrics.clone()),
                     nym_response_queue.rx().clone(),
                     nym_response_queue.tx().clone(),
+                    nym_max_client_response_bytes,
                     status.nym_ingestor_status.clone(),
                     online.clone(),
                 )
@@ -151,25 +582,97 @@ impl Server {
             None
         };
 
+        let block_cache = Arc::new(
+            BlockCache::default()
+                .with_prefetch(block_cache_prefetch_window, block_fetch_concurrency),
+        );
+        let mempool_cache = Arc::new(MempoolTxCache::new(mempool_cache_max_bytes));
+        rpc_metrics.set_node_auth_failed(node_auth_failed);
+        rpc_metrics.set_mempool_cache_stats(mempool_cache.stats());
+        rpc_metrics.set_worker_pool_size(max_worker_pool_size, idle_worker_pool_size);
+        let request_log_level = SharedRequestLogLevel::new(request_log_level);
+        let ip_rate_limits = Arc::new(IpRateLimits::new(max_requests_per_second_per_ip));
+        let reload_handle = ReloadHandle::new(
+            max_worker_pool_size,
+            idle_worker_pool_size,
+            status.workerpool_status.capacity() as u16,
+            max_queue_size,
+            request_log_level.clone(),
+            ip_rate_limits.clone(),
+        );
+        let rpc_semaphores = Arc::new(RpcSemaphores::new(&rpc_concurrency_limits, rpc_max_wait));
+        let node_health = Arc::new(NodeHealth::new(node_failure_threshold, node_failure_window));
         println!("Launching WorkerPool..");
         let worker_pool = WorkerPool::spawn(
             max_worker_pool_size,
             idle_worker_pool_size,
-            request_queue.rx().clone(),
-            request_queue.tx().clone(),
-            nym_response_queue.tx().clone(),
-            lightwalletd_uri,
-            zebrad_uri,
+            WorkerQueues {
+                queue: request_queue.rx().clone(),
+                _requeue: request_queue.tx().clone(),
+                #[cfg(feature = "nym")]
+                nym_response_queue: nym_response_queue.tx().clone(),
+            },
+            WorkerConfig {
+                lightwalletd_uri,
+                zebrad_uri,
+                network,
+                node_type,
+                node_auth,
+                block_cache: block_cache.clone(),
+                chain_tip: Arc::new(ChainTip::default()),
+                negative_cache: Arc::new(NegativeCache::default()),
+                mempool_cache,
+                taddress_txids_include_mempool,
+                taddress_txids_fetch_concurrency,
+                latest_block_confirmations,
+                max_mempool_exclude_entries,
+                max_mempool_tx_age,
+                broadcast_disabled,
+                stream_error_policy,
+                open_streams: Arc::new(AtomicUsize::new(0)),
+                max_concurrent_streams: max_concurrent_streams as usize,
+                max_streams_per_connection: max_streams_per_connection as usize,
+                rpc_metrics: rpc_metrics.clone(),
+                rpc_semaphores,
+                request_size_limits: Arc::new(RequestSizeLimits::with_overrides(
+                    &rpc_max_request_bytes,
+                )),
+                ip_rate_limits,
+                node_health: node_health.clone(),
+                grpc_compression,
+                request_log_level,
+                keepalive,
+                max_request_age,
+            },
             status.workerpool_status.clone(),
             online.clone(),
         )
         .await;
+        let grpc_addr = tcp_ingestor.as_ref().map(TcpIngestor::local_addr);
         Ok(Server {
             tcp_ingestor,
+            #[cfg(feature = "nym")]
             nym_ingestor,
+            grpc_addr,
             worker_pool,
             request_queue,
+            #[cfg(feature = "nym")]
             nym_response_queue,
+            block_cache,
+            rpc_metrics,
+            metrics_listen_addr,
+            reload_handle,
+            queue_backpressure: QueueBackpressure::new(queue_warn_threshold, queue_error_threshold),
+            node_connector,
+            node_health,
+            node_probe_interval,
+            exit_on_node_loss,
+            chain_consistency: Arc::new(ChainConsistency::new(
+                network,
+                reset_cache_on_chain_mismatch,
+            )),
+            cache_warm_range,
+            block_fetch_concurrency,
             status: status.clone(),
             online,
         })
@@ -178,52 +681,140 @@ impl Server {
     /// Starts the gRPC service.
     ///
     /// Launches all components then enters command loop:
+    /// - Applies worker pool and queue size changes made through the [`ReloadHandle`].
     /// - Checks request queue and workerpool to spawn / despawn workers as required.
+    /// - Replaces workers whose task panicked or failed, logging the request they were serving.
+    /// - Reports request queue backpressure as the queue fills.
     /// - Updates the ServerStatus.
+    /// - Garbage collects the block cache on receiving SIGUSR1.
+    /// - Serves RPC metrics if a metrics listen address is configured.
+    /// - Probes the full node, shutting down if it is lost and the server is configured to exit on node loss.
+    /// - Warms the block cache if a warm range is configured.
     /// - Checks for shutdown signal, shutting down server if received.
     pub async fn serve(mut self) -> tokio::task::JoinHandle<Result<(), ServerError>> {
         tokio::task::spawn(async move {
             // NOTE: This interval may need to be reduced or removed / moved once scale testing begins.
             let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(50));
-            let mut nym_ingestor_handle = None;
+            let mut gc_signal = signal(SignalKind::user_defined1())?;
             let mut tcp_ingestor_handle = None;
             let mut worker_handles;
-            if let Some(ingestor) = self.nym_ingestor.take() {
-                nym_ingestor_handle = Some(ingestor.serve().await);
-            }
+            #[cfg(feature = "nym")]
+            let nym_ingestor_handle = match self.nym_ingestor.take() {
+                Some(ingestor) => Some(ingestor.serve().await),
+                None => None,
+            };
+            #[cfg(not(feature = "nym"))]
+            let nym_ingestor_handle = None;
             if let Some(ingestor) = self.tcp_ingestor.take() {
                 tcp_ingestor_handle = Some(ingestor.serve().await);
             }
+            if let Some(listen_addr) = self.metrics_listen_addr {
+                println!("Serving metrics at http://{}/metrics.", listen_addr);
+                tokio::task::spawn(serve_metrics(
+                    listen_addr,
+                    self.rpc_metrics.clone(),
+                    self.node_health.clone(),
+                    self.online.clone(),
+                ));
+            }
+            let node_connector = self.node_connector.clone();
+            let block_cache = self.block_cache.clone();
+            let node_health = self.node_health.clone();
+            let chain_consistency = self.chain_consistency.clone();
+            let rpc_metrics = self.rpc_metrics.clone();
+            tokio::task::spawn(supervise_node(
+                move || {
+                    let node_connector = node_connector.clone();
+                    let block_cache = block_cache.clone();
+                    let node_health = node_health.clone();
+                    let chain_consistency = chain_consistency.clone();
+                    let rpc_metrics = rpc_metrics.clone();
+                    async move {
+                        match node_connector.get_info().await {
+                            Ok(_) => {
+                                // A failed check is retried on the next probe, connectivity is judged by getinfo.
+                                let _ = chain_consistency
+                                    .verify(&node_connector, &block_cache, &node_health)
+                                    .await;
+                                if let Ok(mining_info) = node_connector.get_mining_info().await {
+                                    rpc_metrics.set_mining_info(mining_info);
+                                }
+                                true
+                            }
+                            // The node answered, even if with an error.
+                            Err(e) => !e.is_connectivity_error(),
+                        }
+                    }
+                },
+                self.node_health.clone(),
+                self.node_probe_interval,
+                self.online.clone(),
+            ));
+            if let Some(heights) = self.cache_warm_range.clone() {
+                tokio::task::spawn(warm_block_cache(
+                    self.block_cache.clone(),
+                    self.node_connector.uri().clone(),
+                    self.node_connector.auth().clone(),
+                    heights,
+                    BlockFetcher::new(self.block_fetch_concurrency),
+                    self.rpc_metrics.clone(),
+                    self.online.clone(),
+                ));
+            }
             worker_handles = self.worker_pool.clone().serve().await;
             self.status.server_status.store(1);
             loop {
-                if self.request_queue.queue_length() >= (self.request_queue.max_length() / 4)
-                    && (self.worker_pool.workers() < self.worker_pool.max_size() as usize)
+                let (max_size, idle_size) = (
+                    self.reload_handle.max_worker_pool_size(),
+                    self.reload_handle.idle_worker_pool_size(),
+                );
+                if (max_size, idle_size)
+                    != (self.worker_pool.max_size(), self.worker_pool.idle_size())
                 {
-                    match self.worker_pool.push_worker().await {
+                    println!(
+                        "Resizing WorkerPool, max size: {}, idle size: {}.",
+                        max_size, idle_size
+                    );
+                    self.worker_pool.resize(max_size, idle_size);
+                    self.rpc_metrics.set_worker_pool_size(max_size, idle_size);
+                }
+                let max_queue_size = self.reload_handle.max_queue_size() as usize;
+                if max_queue_size != self.request_queue.max_length() {
+                    println!("Resizing request queue, max size: {}.", max_queue_size);
+                    self.request_queue.set_max_length(max_queue_size);
+                    #[cfg(feature = "nym")]
+                    self.nym_response_queue.set_max_length(max_queue_size);
+                }
+                self.check_queue_backpressure();
+                match scale_action(&self.pool_load()) {
+                    ScaleAction::Grow => match self.worker_pool.push_worker().await {
                         Ok(handle) => {
                             worker_handles.push(handle);
                         }
                         Err(_e) => {
                             eprintln!("WorkerPool at capacity");
                         }
-                    }
-                } else if (self.request_queue.queue_length() <= 1)
-                    && (self.worker_pool.workers() > self.worker_pool.idle_size() as usize)
-                {
-                    let worker_index = self.worker_pool.workers() - 1;
-                    let worker_handle = worker_handles.remove(worker_index);
-                    match self.worker_pool.pop_worker(worker_handle).await {
-                        Ok(_) => {}
-                        Err(e) => {
-                            eprintln!("Failed to pop worker from pool: {}", e);
-                            // TODO: Handle this error.
+                    },
+                    ScaleAction::Shrink => {
+                        let worker_index = self.worker_pool.workers() - 1;
+                        let worker_handle = worker_handles.remove(worker_index);
+                        match self.worker_pool.pop_worker(worker_handle).await {
+                            Ok(_) => {}
+                            Err(e) => {
+                                eprintln!("Failed to pop worker from pool: {}", e);
+                                // TODO: Handle this error.
+                            }
                         }
                     }
+                    ScaleAction::Hold => {}
                 }
                 self.statuses();
                 // TODO: Implement check_statuses() and run here.
-                if self.check_for_shutdown().await {
+                let node_lost = self.exit_on_node_loss && !self.node_health.is_available();
+                if node_lost {
+                    eprintln!("Full node lost, shutting down server.");
+                }
+                if node_lost || self.check_for_shutdown().await {
                     self.status.server_status.store(4);
                     let worker_handle_options: Vec<
                         Option<tokio::task::JoinHandle<Result<(), WorkerError>>>,
@@ -235,13 +826,102 @@ impl Server {
                     )
                     .await;
                     self.status.server_status.store(5);
+                    if node_lost {
+                        return Err(ServerError::NodeUnavailable);
+                    }
                     return Ok(());
                 }
-                interval.tick().await;
+                self.replace_failed_workers(&mut worker_handles).await;
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = gc_signal.recv() => {
+                        let evicted = self.force_gc(None).await;
+                        println!("Received SIGUSR1, evicted {} blocks from the block cache.", evicted);
+                    }
+                }
             }
         })
     }
 
+    /// Evicts blocks from the block cache, returns the number of blocks evicted.
+    ///
+    /// Blocks below `keep_below_height` are evicted, or below `tip - reorg_safe_depth` if `None`.
+    pub async fn force_gc(&self, keep_below_height: Option<u32>) -> usize {
+        self.block_cache.gc(keep_below_height).await
+    }
+
+    /// Logs and replaces the workers whose task has ended while the server is running.
+    async fn replace_failed_workers(
+        &mut self,
+        worker_handles: &mut [tokio::task::JoinHandle<Result<(), WorkerError>>],
+    ) {
+        let contexts = self.worker_pool.request_contexts();
+        for failure in reap_workers(worker_handles, &contexts).await {
+            eprintln!("{}, replacing worker.", failure);
+            self.rpc_metrics.record_worker_restart();
+            worker_handles[failure.worker_id] =
+                self.worker_pool.replace_worker(failure.worker_id).await;
+        }
+    }
+
+    /// Returns the current load on the worker pool, the input of the scaling policy.
+    fn pool_load(&self) -> PoolLoad {
+        PoolLoad {
+            queue_length: self.request_queue.queue_length(),
+            max_queue_length: self.request_queue.max_length(),
+            workers: self.worker_pool.workers(),
+            max_workers: self.worker_pool.max_size(),
+            idle_workers: self.worker_pool.idle_size(),
+        }
+    }
+
+    /// Reports a backpressure event if the request queue is filling up.
+    fn check_queue_backpressure(&mut self) {
+        let (queue_length, max_length) = (
+            self.request_queue.queue_length(),
+            self.request_queue.max_length(),
+        );
+        let Some(level) = self
+            .queue_backpressure
+            .check(queue_length, max_length, Instant::now())
+        else {
+            return;
+        };
+        match level {
+            BackpressureLevel::Warn => eprintln!(
+                "Warning: Request queue filling up ({}/{}).",
+                queue_length, max_length
+            ),
+            BackpressureLevel::Error => eprintln!(
+                "Error: Request queue near capacity ({}/{}), requests may be rejected.",
+                queue_length, max_length
+            ),
+        }
+        self.rpc_metrics.record_backpressure_event(level);
+    }
+
+    /// Returns the address the gRPC server is listening at, with the OS assigned port if configured with port 0.
+    ///
+    /// Returns `None` if tcp is not active.
+    pub fn grpc_addr(&self) -> Option<SocketAddr> {
+        self.grpc_addr
+    }
+
+    /// Returns a handle used to update server settings while the server is running.
+    pub fn reload_handle(&self) -> ReloadHandle {
+        self.reload_handle.clone()
+    }
+
+    /// Returns the servers RPC latency metrics.
+    pub fn rpc_metrics(&self) -> Arc<RpcMetrics> {
+        self.rpc_metrics.clone()
+    }
+
+    /// Returns the servers block cache.
+    pub fn block_cache(&self) -> Arc<BlockCache> {
+        self.block_cache.clone()
+    }
+
     /// Checks indexers online status and servers internal status for closure signal.
     pub async fn check_for_shutdown(&self) -> bool {
         if self.status() >= 4 {
@@ -270,12 +950,18 @@ impl Server {
             handle.await.ok();
         }
         if let Some(handle) = nym_ingestor_handle {
+            #[cfg(feature = "nym")]
             self.status.nym_ingestor_status.store(4);
             handle.await.ok();
         }
         self.worker_pool.shutdown(&mut worker_handles).await;
     }
 
+    /// Returns the number of gRPC requests served for each method, keyed by gRPC method name.
+    pub fn request_counts(&self) -> HashMap<String, u64> {
+        self.worker_pool.request_counts()
+    }
+
     /// Returns the servers current status usize.
     pub fn status(&self) -> usize {
         self.status.server_status.load()
@@ -290,15 +976,23 @@ impl Server {
     pub fn statuses(&mut self) -> ServerStatus {
         self.status.server_status.load();
         self.status.tcp_ingestor_status.load();
-        self.status.nym_ingestor_status.load();
-        self.status.nym_dispatcher_status.load();
         self.status
             .request_queue_status
             .store(self.request_queue.queue_length(), Ordering::SeqCst);
-        self.status
-            .nym_response_queue_status
-            .store(self.nym_response_queue.queue_length(), Ordering::SeqCst);
+        #[cfg(feature = "nym")]
+        {
+            self.status.nym_ingestor_status.load();
+            self.status.nym_dispatcher_status.load();
+            self.status
+                .nym_response_queue_status
+                .store(self.nym_response_queue.queue_length(), Ordering::SeqCst);
+        }
         self.worker_pool.status();
+        self.status.top_methods = self.worker_pool.top_methods(5);
+        let queue = self.rpc_metrics.queue();
+        self.status.queue_wait = queue.wait_quantiles();
+        self.status.queue_evictions = self.rpc_metrics.queue_evictions();
+        self.status.first_response = queue.first_response_quantiles();
         self.status.clone()
     }
 
@@ -312,3 +1006,34 @@ impl Server {
         self.online.load(Ordering::SeqCst)
     }
 }
+
+/// Fetches the blocks at `heights` into the block cache, reporting progress in the RPC metrics.
+async fn warm_block_cache(
+    block_cache: Arc<BlockCache>,
+    zebrad_uri: Uri,
+    node_auth: NodeAuth,
+    heights: RangeInclusive<u32>,
+    fetcher: BlockFetcher,
+    rpc_metrics: Arc<RpcMetrics>,
+    online: Arc<AtomicBool>,
+) {
+    rpc_metrics.set_cache_warm_progress(fetcher.progress());
+    println!(
+        "Warming block cache, heights {}-{}.",
+        heights.start(),
+        heights.end()
+    );
+    match block_cache
+        .warm(&zebrad_uri, &node_auth, heights, &fetcher, &online)
+        .await
+    {
+        FetchOutcome::Completed => {
+            rpc_metrics.set_cache_warm_complete();
+            println!(
+                "Block cache warmed, {} blocks fetched.",
+                fetcher.progress().fetched()
+            );
+        }
+        outcome => eprintln!("Block cache warming stopped: {:?}.", outcome),
+    }
+}
@@ -4,6 +4,8 @@ use std::io;
 use tokio::sync::mpsc::error::TrySendError;
 
 use crate::server::request::ZingoIndexerRequest;
+use zaino_fetch::jsonrpc::error::JsonRpcConnectorError;
+#[cfg(feature = "nym")]
 use zaino_nym::error::NymError;
 
 /// Zingo-Indexer queue errors.
@@ -30,6 +32,7 @@ pub enum RequestError {
     #[error("System time error: {0}")]
     SystemTimeError(#[from] std::time::SystemTimeError),
     /// Nym Related Errors
+    #[cfg(feature = "nym")]
     #[error("Nym error: {0}")]
     NymError(#[from] NymError),
 }
@@ -41,6 +44,7 @@ pub enum IngestorError {
     #[error("Request error: {0}")]
     RequestError(#[from] RequestError),
     /// Nym based errors.
+    #[cfg(feature = "nym")]
     #[error("Nym error: {0}")]
     NymError(#[from] NymError),
     /// Tcp listener based error.
@@ -75,6 +79,7 @@ pub enum ServerError {
     #[error("Request error: {0}")]
     RequestError(#[from] RequestError),
     /// Nym based errors.
+    #[cfg(feature = "nym")]
     #[error("Nym error: {0}")]
     NymError(#[from] NymError),
     /// Ingestor based errors.
@@ -86,4 +91,16 @@ pub enum ServerError {
     /// Server configuration errors.
     #[error("Server configuration error: {0}")]
     ServerConfigError(String),
+    /// Std IO errors.
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    /// Metrics endpoint errors.
+    #[error("Hyper error: {0}")]
+    HyperError(#[from] hyper::Error),
+    /// Errors from the JsonRPC client, returned when the node fails startup checks.
+    #[error("JsonRPC connector error: {0}")]
+    JsonRpcConnectorError(#[from] JsonRpcConnectorError),
+    /// The full node became unreachable and the server is configured to exit on node loss.
+    #[error("Full node unavailable")]
+    NodeUnavailable,
 }
@@ -0,0 +1,179 @@
+//! Tracks the availability of the backing full node.
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Default number of consecutive failed probes after which the node is marked unavailable.
+pub const DEFAULT_NODE_FAILURE_THRESHOLD: u32 = 5;
+
+/// Default window the consecutive failed probes must fall within.
+pub const DEFAULT_NODE_FAILURE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Default delay between node probes.
+pub const DEFAULT_NODE_PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Run of consecutive connectivity failures.
+#[derive(Debug, Default)]
+struct FailureRun {
+    /// Number of consecutive failures.
+    failures: u32,
+    /// Time of the first failure of the run.
+    started: Option<Instant>,
+}
+
+/// Availability of the backing full node, shared by the node supervisor and every worker in the pool.
+///
+/// The node is marked unavailable once `failure_threshold` consecutive connectivity failures are recorded within
+/// `failure_window`, and available again on the next success. While unavailable, RPCs that need the node fail
+/// immediately with UNAVAILABLE rather than waiting on a dead node.
+///
+/// A node found on a different chain than the server's cached data, see
+/// [`ChainConsistency`](crate::server::consistency::ChainConsistency), is recorded as a chain mismatch. RPCs served
+/// from the block cache fail with FAILED_PRECONDITION while a mismatch is held.
+#[derive(Debug)]
+pub struct NodeHealth {
+    /// Number of consecutive failures after which the node is marked unavailable.
+    failure_threshold: u32,
+    /// Window the consecutive failures must fall within, a run older than this is restarted.
+    failure_window: Duration,
+    /// Set while the node is considered available.
+    available: AtomicBool,
+    /// Current run of consecutive failures.
+    run: Mutex<FailureRun>,
+    /// Description of the chain mismatch found, if any.
+    chain_mismatch: Mutex<Option<String>>,
+}
+
+impl Default for NodeHealth {
+    fn default() -> Self {
+        Self::new(DEFAULT_NODE_FAILURE_THRESHOLD, DEFAULT_NODE_FAILURE_WINDOW)
+    }
+}
+
+impl NodeHealth {
+    /// Returns a NodeHealth, initially available, that marks the node unavailable after `failure_threshold`
+    /// consecutive failures within `failure_window`.
+    pub fn new(failure_threshold: u32, failure_window: Duration) -> Self {
+        NodeHealth {
+            failure_threshold: failure_threshold.max(1),
+            failure_window,
+            available: AtomicBool::new(true),
+            run: Mutex::new(FailureRun::default()),
+            chain_mismatch: Mutex::new(None),
+        }
+    }
+
+    /// Returns true if the node is considered available.
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::SeqCst)
+    }
+
+    /// Returns an UNAVAILABLE status if the node is considered unavailable.
+    pub fn check_available(&self) -> Result<(), tonic::Status> {
+        if self.is_available() {
+            Ok(())
+        } else {
+            Err(tonic::Status::unavailable(
+                "Full node unavailable, please try again later.",
+            ))
+        }
+    }
+
+    /// Records a chain mismatch between the node and the server's cached data, or clears it if `None`.
+    ///
+    /// Returns true if no mismatch was held before.
+    pub fn set_chain_mismatch(&self, mismatch: Option<String>) -> bool {
+        let mut held = self.lock_chain_mismatch();
+        let newly_set = held.is_none() && mismatch.is_some();
+        *held = mismatch;
+        newly_set
+    }
+
+    /// Returns the chain mismatch held, if any.
+    pub fn chain_mismatch(&self) -> Option<String> {
+        self.lock_chain_mismatch().clone()
+    }
+
+    /// Returns a FAILED_PRECONDITION status if the node is on a different chain than the server's cached data.
+    pub fn check_chain_consistent(&self) -> Result<(), tonic::Status> {
+        match self.chain_mismatch() {
+            None => Ok(()),
+            Some(mismatch) => Err(tonic::Status::failed_precondition(format!(
+                "Full node chain does not match cached chain data, refusing to serve cached data: {}",
+                mismatch
+            ))),
+        }
+    }
+
+    /// Records a successful contact with the node.
+    ///
+    /// Returns true if the node was unavailable and has now recovered.
+    pub fn record_success(&self) -> bool {
+        *self.lock_run() = FailureRun::default();
+        !self.available.swap(true, Ordering::SeqCst)
+    }
+
+    /// Records a connectivity failure at `now`.
+    ///
+    /// Returns true if the node was available and is now marked unavailable.
+    pub fn record_failure(&self, now: Instant) -> bool {
+        let mut run = self.lock_run();
+        match run.started {
+            Some(started) if now.duration_since(started) <= self.failure_window => {
+                run.failures += 1;
+            }
+            _ => {
+                *run = FailureRun {
+                    failures: 1,
+                    started: Some(now),
+                };
+            }
+        }
+        run.failures >= self.failure_threshold && self.available.swap(false, Ordering::SeqCst)
+    }
+
+    /// Returns the failure run, recovering it if a holder panicked.
+    fn lock_run(&self) -> std::sync::MutexGuard<'_, FailureRun> {
+        self.run.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the chain mismatch, recovering it if a holder panicked.
+    fn lock_chain_mismatch(&self) -> std::sync::MutexGuard<'_, Option<String>> {
+        self.chain_mismatch
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Probes the node with `probe` every `probe_interval` until `online` is cleared, recording the results in `health`.
+///
+/// `probe` returns false on a connectivity failure. Probing continues while the node is unavailable so the server
+/// recovers as soon as the node returns.
+pub async fn supervise_node<F, Fut>(
+    probe: F,
+    health: Arc<NodeHealth>,
+    probe_interval: Duration,
+    online: Arc<AtomicBool>,
+) where
+    F: Fn() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let mut interval = tokio::time::interval(probe_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    while online.load(Ordering::SeqCst) {
+        interval.tick().await;
+        if probe().await {
+            if health.record_success() {
+                println!("Full node reachable again, resuming service.");
+            }
+        } else if health.record_failure(Instant::now()) {
+            eprintln!("Full node unreachable, failing requests until it returns.");
+        }
+    }
+}
@@ -1,6 +1,8 @@
 //! Holds the server ingestor (listener) implementations.
 
+#[cfg(feature = "nym")]
 use nym_sdk::mixnet::MixnetMessageSender;
+#[cfg(feature = "nym")]
 use nym_sphinx_anonymous_replies::requests::AnonymousSenderTag;
 use std::{
     net::SocketAddr,
@@ -9,22 +11,129 @@ use std::{
         Arc,
     },
 };
-use tokio::net::TcpListener;
+use tokio::{net::TcpListener, sync::Notify};
+use tonic::transport::Server;
 
+#[cfg(feature = "nym")]
+use crate::server::scheduler::ResponseScheduler;
 use crate::server::{
     error::{IngestorError, QueueError},
-    queue::{QueueReceiver, QueueSender},
-    request::ZingoIndexerRequest,
+    keepalive::Keepalive,
+    metrics::RpcMetrics,
+    middleware::EvictedRequestRejector,
+    queue::{Queue, QueueReceiver, QueueSender},
+    request::{OverloadPolicy, ZingoIndexerRequest},
+    worker::EXPIRED_CONNECTION_TIMEOUT,
     AtomicStatus, StatusType,
 };
+#[cfg(feature = "nym")]
 use zaino_nym::{client::NymClient, error::NymError};
 
+/// Sends requests to the request queue, applying the server's [`OverloadPolicy`] when the queue is full.
+#[derive(Debug, Clone)]
+pub(crate) struct RequestEnqueuer {
+    /// Used to send requests to the queue.
+    queue: QueueSender<ZingoIndexerRequest>,
+    /// Used to evict the oldest queued request, set under [`OverloadPolicy::DropOldest`].
+    evict_from: Option<QueueReceiver<ZingoIndexerRequest>>,
+    /// Metrics counting evicted requests.
+    rpc_metrics: Arc<RpcMetrics>,
+}
+
+impl RequestEnqueuer {
+    /// Creates an enqueuer sending requests to `queue` under `overload_policy`.
+    pub(crate) fn new(
+        queue: &Queue<ZingoIndexerRequest>,
+        overload_policy: OverloadPolicy,
+        rpc_metrics: Arc<RpcMetrics>,
+    ) -> Self {
+        RequestEnqueuer {
+            queue: queue.tx(),
+            evict_from: (overload_policy == OverloadPolicy::DropOldest).then(|| queue.rx()),
+            rpc_metrics,
+        }
+    }
+
+    /// Tries to add a request to the queue. If the queue is full the request is returned in
+    /// [`QueueError::QueueFull`], unless the oldest queued request is evicted to make room for it.
+    pub(crate) fn try_send(
+        &self,
+        request: ZingoIndexerRequest,
+    ) -> Result<(), QueueError<ZingoIndexerRequest>> {
+        let Some(evict_from) = &self.evict_from else {
+            return self.queue.try_send(request);
+        };
+        if let Some(evicted) = self.queue.try_send_evicting(request, evict_from)? {
+            self.rpc_metrics.record_queue_eviction();
+            answer_evicted(evicted);
+        }
+        Ok(())
+    }
+
+    /// Returns the current length of the queue.
+    pub(crate) fn queue_length(&self) -> usize {
+        self.queue.queue_length()
+    }
+}
+
+/// Answers a request evicted from the request queue.
+///
+/// The calls of an evicted TCP connection are answered with UNAVAILABLE, then the connection is closed so the
+/// client's retries are queued afresh. Nym clients are not sent errors, evicted Nym requests are dropped.
+fn answer_evicted(request: ZingoIndexerRequest) {
+    let queue_wait = request.duration().unwrap_or_default();
+    let method_name = request.method_name().to_string();
+    match request {
+        ZingoIndexerRequest::TcpServerRequest(request) => {
+            eprintln!(
+                "Request queue full, evicting TCP connection ({}) queued for {}ms.",
+                method_name,
+                queue_wait.as_millis()
+            );
+            tokio::spawn(async move {
+                let answered = Arc::new(Notify::new());
+                let shutdown = answered.clone();
+                if let Err(e) = Server::builder()
+                    .add_service(EvictedRequestRejector::new(queue_wait, answered))
+                    .serve_with_incoming_shutdown(
+                        async_stream::stream! {
+                            yield Ok::<_, std::io::Error>(request.get_request().get_stream());
+                        },
+                        async move {
+                            let _ = tokio::time::timeout(
+                                EXPIRED_CONNECTION_TIMEOUT,
+                                shutdown.notified(),
+                            )
+                            .await;
+                        },
+                    )
+                    .await
+                {
+                    eprintln!("Failed to answer evicted TCP connection: {}", e);
+                }
+            });
+        }
+        #[cfg(feature = "nym")]
+        ZingoIndexerRequest::NymServerRequest(request) => {
+            eprintln!(
+                "Request queue full, evicting Nym {} request queued for {}ms.",
+                method_name,
+                queue_wait.as_millis()
+            );
+        }
+    }
+}
+
 /// Listens for incoming gRPC requests over HTTP.
 pub(crate) struct TcpIngestor {
     /// Tcp Listener.
     ingestor: TcpListener,
+    /// Address the listener is bound to, the OS assigned port if bound to port 0.
+    local_addr: SocketAddr,
     /// Used to send requests to the queue.
-    queue: QueueSender<ZingoIndexerRequest>,
+    queue: RequestEnqueuer,
+    /// Keepalive settings, TCP keepalive is applied to each accepted connection.
+    keepalive: Keepalive,
     /// Current status of the ingestor.
     status: AtomicStatus,
     /// Represents the Online status of the gRPC server.
@@ -35,21 +144,30 @@ impl TcpIngestor {
     /// Creates a Tcp Ingestor.
     pub(crate) async fn spawn(
         listen_addr: SocketAddr,
-        queue: QueueSender<ZingoIndexerRequest>,
+        queue: RequestEnqueuer,
+        keepalive: Keepalive,
         status: AtomicStatus,
         online: Arc<AtomicBool>,
     ) -> Result<Self, IngestorError> {
         status.store(0);
         let listener = TcpListener::bind(listen_addr).await?;
-        println!("TcpIngestor listening at: {}.", listen_addr);
+        let local_addr = listener.local_addr()?;
+        println!("TcpIngestor listening at: {}.", local_addr);
         Ok(TcpIngestor {
             ingestor: listener,
+            local_addr,
             queue,
+            keepalive,
             online,
             status,
         })
     }
 
+    /// Returns the address the listener is bound to.
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
     /// Starts Tcp service.
     pub(crate) async fn serve(self) -> tokio::task::JoinHandle<Result<(), IngestorError>> {
         tokio::task::spawn(async move {
@@ -73,12 +191,15 @@ impl TcpIngestor {
                         }
                         match incoming {
                             Ok((stream, _)) => {
+                                if let Err(e) = self.keepalive.apply_tcp(&stream) {
+                                    eprintln!("Failed to enable TCP keepalive on client connection: {}", e);
+                                }
                                 match self.queue.try_send(ZingoIndexerRequest::new_from_grpc(stream)) {
                                     Ok(_) => {
                                         println!("[TEST] Requests in Queue: {}", self.queue.queue_length());
                                     }
-                                    Err(QueueError::QueueFull(_request)) => {
-                                        eprintln!("Queue Full.");
+                                    Err(QueueError::QueueFull(request)) => {
+                                        eprintln!("Queue Full, rejecting {} request.", request.method_name());
                                         // TODO: Return queue full tonic status over tcpstream and close (that TcpStream..).
                                     }
                                     Err(e) => {
@@ -130,28 +251,33 @@ impl TcpIngestor {
 }
 
 /// Listens for incoming gRPC requests over Nym Mixnet.
+#[cfg(feature = "nym")]
 pub(crate) struct NymIngestor {
     /// Nym Client
     ingestor: NymClient,
     /// Used to send requests to the queue.
-    queue: QueueSender<ZingoIndexerRequest>,
+    queue: RequestEnqueuer,
     /// Used to send requests to the queue.
     response_queue: QueueReceiver<(Vec<u8>, AnonymousSenderTag)>,
     /// Used to send requests to the queue.
     response_requeue: QueueSender<(Vec<u8>, AnonymousSenderTag)>,
+    /// Orders responses round-robin across clients, bounding the bytes queued per client.
+    response_scheduler: ResponseScheduler<AnonymousSenderTag>,
     /// Current status of the ingestor.
     status: AtomicStatus,
     /// Represents the Online status of the gRPC server.
     online: Arc<AtomicBool>,
 }
 
+#[cfg(feature = "nym")]
 impl NymIngestor {
     /// Creates a Nym Ingestor
     pub(crate) async fn spawn(
         nym_conf_path: &str,
-        queue: QueueSender<ZingoIndexerRequest>,
+        queue: RequestEnqueuer,
         response_queue: QueueReceiver<(Vec<u8>, AnonymousSenderTag)>,
         response_requeue: QueueSender<(Vec<u8>, AnonymousSenderTag)>,
+        max_client_response_bytes: usize,
         status: AtomicStatus,
         online: Arc<AtomicBool>,
     ) -> Result<Self, IngestorError> {
@@ -164,11 +290,26 @@ impl NymIngestor {
             queue,
             response_queue,
             response_requeue,
+            response_scheduler: ResponseScheduler::new(max_client_response_bytes),
             online,
             status,
         })
     }
 
+    /// Adds a response to the response scheduler, dropping it if the client's queued responses are over the cap.
+    fn schedule_response(&mut self, (response, sender_tag): (Vec<u8>, AnonymousSenderTag)) {
+        let queued_bytes = self.response_scheduler.queued_bytes(&sender_tag);
+        if let Err(QueueError::QueueFull(response)) =
+            self.response_scheduler.push(sender_tag, response)
+        {
+            eprintln!(
+                "Dropped {} byte response for nym client with {} bytes of responses queued, per client limit exceeded.",
+                response.len(),
+                queued_bytes
+            );
+        }
+    }
+
     /// Starts Nym service.
     pub(crate) async fn serve(mut self) -> tokio::task::JoinHandle<Result<(), IngestorError>> {
         tokio::task::spawn(async move {
@@ -206,8 +347,8 @@ impl NymIngestor {
                                     ZingoIndexerRequest::new_from_nym(return_recipient, request_vu8.as_ref())?;
                                 match self.queue.try_send(zingo_proxy_request) {
                                     Ok(_) => {}
-                                    Err(QueueError::QueueFull(_request)) => {
-                                        eprintln!("Queue Full.");
+                                    Err(QueueError::QueueFull(request)) => {
+                                        eprintln!("Queue Full, rejecting {} request.", request.method_name());
                                         // TODO: Return queue full tonic status over mixnet.
                                     }
                                     Err(e) => {
@@ -225,33 +366,10 @@ impl NymIngestor {
                     outgoing = self.response_queue.listen() => {
                         match outgoing {
                             Ok(response) => {
-                                println!("[TEST] Dispatcher received response: {:?}", response);
-                                // NOTE: This may need to be removed / moved for scale use.
-                                if self.check_for_shutdown().await {
-                                    self.status.store(5);
-                                    return Ok(());
-                                }
-                                if let Err(nym_e) = self.ingestor
-                                        .client
-                                        .send_reply(response.1, response.0.clone())
-                                        .await.map_err(NymError::from) {
-                                    eprintln!("Failed to send response over Nym Mixnet: {}", nym_e);
-                                    match self.response_requeue.try_send(response) {
-                                        Ok(_) => {
-                                            eprintln!("Failed to send response over nym: {}\nResponse requeued, restarting nym dispatcher.", nym_e);
-                                            // TODO: Handle error. Restart nym dispatcher.
-                                        }
-                                        Err(QueueError::QueueFull(_request)) => {
-                                            eprintln!("Failed to send response over nym: {}\nAnd failed to requeue response due to full response queue.\nFatal error! Restarting nym dispatcher.", nym_e);
-                                            // TODO: Handle queue full error here (start up second dispatcher?). Restart nym dispatcher
-                                        }
-                                        Err(_e) => {
-                                            eprintln!("Failed to send response over nym: {}\nAnd failed to requeue response due to the queue being closed.\nFatal error! Nym dispatcher shutting down..", nym_e);
-                                            // TODO: Handle queue closed error here. (return correct error type?)
-                                            self.status.store(6);
-                                            return Ok(()); //Return Err!
-                                        }
-                                    }
+                                // Responses waiting in the queue are dispatched round-robin across clients.
+                                self.schedule_response(response);
+                                while let Ok(response) = self.response_queue.try_recv() {
+                                    self.schedule_response(response);
                                 }
                             }
                             Err(_e) => {
@@ -261,6 +379,36 @@ impl NymIngestor {
                                 return Ok(()); // Return Err!
                             }
                         }
+                        while let Some(response) = self.response_scheduler.pop() {
+                            println!("[TEST] Dispatcher received response: {:?}", response);
+                            // NOTE: This may need to be removed / moved for scale use.
+                            if self.check_for_shutdown().await {
+                                self.status.store(5);
+                                return Ok(());
+                            }
+                            if let Err(nym_e) = self.ingestor
+                                    .client
+                                    .send_reply(response.1, response.0.clone())
+                                    .await.map_err(NymError::from) {
+                                eprintln!("Failed to send response over Nym Mixnet: {}", nym_e);
+                                match self.response_requeue.try_send(response) {
+                                    Ok(_) => {
+                                        eprintln!("Failed to send response over nym: {}\nResponse requeued, restarting nym dispatcher.", nym_e);
+                                        // TODO: Handle error. Restart nym dispatcher.
+                                    }
+                                    Err(QueueError::QueueFull(_request)) => {
+                                        eprintln!("Failed to send response over nym: {}\nAnd failed to requeue response due to full response queue.\nFatal error! Restarting nym dispatcher.", nym_e);
+                                        // TODO: Handle queue full error here (start up second dispatcher?). Restart nym dispatcher
+                                    }
+                                    Err(_e) => {
+                                        eprintln!("Failed to send response over nym: {}\nAnd failed to requeue response due to the queue being closed.\nFatal error! Nym dispatcher shutting down..", nym_e);
+                                        // TODO: Handle queue closed error here. (return correct error type?)
+                                        self.status.store(6);
+                                        return Ok(()); //Return Err!
+                                    }
+                                }
+                            }
+                    }
                     }
                 }
             }
@@ -0,0 +1,42 @@
+//! Keepalive settings for client connections.
+//!
+//! Long lived streams, eg. a wallet streaming blocks during its initial sync, can sit idle long enough for firewalls
+//! and NAT gateways to drop the connection. HTTP/2 pings and TCP keepalive probes keep these connections active.
+
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+use tonic::transport::Server;
+
+/// TCP keepalive idle time used when TCP keepalive is enabled without a keepalive interval.
+pub const DEFAULT_TCP_KEEPALIVE_TIME: Duration = Duration::from_secs(60);
+
+/// Keepalive settings applied to each client connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Keepalive {
+    /// Interval between HTTP/2 keepalive pings, also used as the TCP keepalive idle time. Pings are not sent if unset.
+    pub interval: Option<Duration>,
+    /// Time to wait for a ping to be acknowledged before the connection is closed, tonic's default if unset.
+    pub timeout: Option<Duration>,
+    /// Whether TCP keepalive probes are sent on idle client connections.
+    pub tcp: bool,
+}
+
+impl Keepalive {
+    /// Applies the HTTP/2 keepalive settings to a tonic server builder.
+    pub fn apply_http2<L>(&self, builder: Server<L>) -> Server<L> {
+        builder
+            .http2_keepalive_interval(self.interval)
+            .http2_keepalive_timeout(self.timeout)
+    }
+
+    /// Enables TCP keepalive probes on an accepted client connection, if set.
+    pub fn apply_tcp(&self, stream: &TcpStream) -> std::io::Result<()> {
+        if !self.tcp {
+            return Ok(());
+        }
+        let keepalive = socket2::TcpKeepalive::new()
+            .with_time(self.interval.unwrap_or(DEFAULT_TCP_KEEPALIVE_TIME));
+        socket2::SockRef::from(stream).set_tcp_keepalive(&keepalive)
+    }
+}
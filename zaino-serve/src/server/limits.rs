@@ -0,0 +1,230 @@
+//! Request size limits enforced before a request reaches its RPC handler.
+//!
+//! Each gRPC message is sent with a 5 byte prefix holding its length. The [`RequestSizeLimiter`] reads the prefix of
+//! every request message and rejects messages declared larger than the limit of their RPC with RESOURCE_EXHAUSTED,
+//! before the message body is received or decoded. Unary requests over the limit never reach the handler.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{stream, StreamExt};
+use tonic::{
+    body::BoxBody,
+    codegen::{http, BoxFuture, Service},
+    server::NamedService,
+    transport::Body,
+};
+
+use crate::server::middleware::rpc_method_name;
+
+/// Maximum size of a transaction allowed by consensus rules, in bytes.
+pub const MAX_TRANSACTION_SIZE: usize = 2_000_000;
+
+/// Default request size limit of SendTransaction, the maximum transaction size plus room for the message framing.
+pub const DEFAULT_SEND_TRANSACTION_LIMIT: usize = MAX_TRANSACTION_SIZE + 64 * 1024;
+
+/// Default request size limit of GetMempoolTx, fits an Exclude list of well over 10,000 full txids.
+pub const DEFAULT_MEMPOOL_EXCLUDE_LIMIT: usize = 512 * 1024;
+
+/// Default request size limit of RPCs taking a list of addresses.
+pub const DEFAULT_ADDRESS_LIST_LIMIT: usize = 64 * 1024;
+
+/// Default request size limit of every other RPC, whose requests hold a few heights, hashes or a single address.
+pub const DEFAULT_REQUEST_LIMIT: usize = 4 * 1024;
+
+/// Size of the prefix sent before each gRPC message, a compression flag followed by the message length.
+const MESSAGE_PREFIX_LEN: usize = 5;
+
+/// Maximum request message size of each RPC, in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestSizeLimits {
+    /// Limits keyed by gRPC method name, RPCs not present use the default limit.
+    limits: HashMap<String, usize>,
+    /// Limit of RPCs not present in `limits`.
+    default: usize,
+}
+
+impl Default for RequestSizeLimits {
+    fn default() -> Self {
+        let mut limits = HashMap::new();
+        limits.insert(
+            "SendTransaction".to_string(),
+            DEFAULT_SEND_TRANSACTION_LIMIT,
+        );
+        limits.insert("GetMempoolTx".to_string(), DEFAULT_MEMPOOL_EXCLUDE_LIMIT);
+        for rpc in [
+            "GetTaddressBalance",
+            "GetAddressUtxos",
+            "GetAddressUtxosStream",
+        ] {
+            limits.insert(rpc.to_string(), DEFAULT_ADDRESS_LIST_LIMIT);
+        }
+        RequestSizeLimits {
+            limits,
+            default: DEFAULT_REQUEST_LIMIT,
+        }
+    }
+}
+
+impl RequestSizeLimits {
+    /// Returns the default limits with the limits of the RPCs in `overrides` replaced, keyed by gRPC method name
+    /// (e.g. "SendTransaction").
+    pub fn with_overrides(overrides: &HashMap<String, usize>) -> Self {
+        let mut limits = Self::default();
+        limits
+            .limits
+            .extend(overrides.iter().map(|(rpc, limit)| (rpc.clone(), *limit)));
+        limits
+    }
+
+    /// Returns the request size limit of the given RPC.
+    pub fn limit(&self, method: &str) -> usize {
+        self.limits.get(method).copied().unwrap_or(self.default)
+    }
+
+    /// Returns the largest limit of any RPC, used to bound the decoded size of compressed messages.
+    pub fn max(&self) -> usize {
+        self.limits.values().copied().fold(self.default, usize::max)
+    }
+}
+
+/// Rejects request messages larger than the limit of their RPC with RESOURCE_EXHAUSTED.
+///
+/// The prefix of the first request message is read before the wrapped service is called, so unary requests over the
+/// limit are rejected without running the handler. Later messages of client streaming RPCs are checked as they are
+/// received and end the request stream with the same status.
+#[derive(Debug, Clone)]
+pub struct RequestSizeLimiter<S> {
+    /// Wrapped tonic service.
+    inner: S,
+    /// Request size limits of each RPC.
+    limits: Arc<RequestSizeLimits>,
+}
+
+impl<S> RequestSizeLimiter<S> {
+    /// Wraps a tonic service with per-RPC request size limits.
+    pub fn new(inner: S, limits: Arc<RequestSizeLimits>) -> Self {
+        RequestSizeLimiter { inner, limits }
+    }
+}
+
+impl<S> Service<http::Request<Body>> for RequestSizeLimiter<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let method = rpc_method_name(&request);
+        let mut scanner = MessageScanner::new(self.limits.limit(&method), method);
+        // The inner service is always ready, take the readied service and leave a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let (parts, mut body) = request.into_parts();
+            let mut received = Vec::new();
+            while scanner.messages == 0 {
+                match body.next().await {
+                    Some(Ok(chunk)) => {
+                        if let Err(status) = scanner.scan(&chunk) {
+                            return Ok(status.to_http());
+                        }
+                        received.push(Ok(chunk));
+                    }
+                    Some(Err(e)) => {
+                        received.push(Err(BoxError::from(e)));
+                        break;
+                    }
+                    None => break,
+                }
+            }
+            let remaining = body.map(move |chunk| {
+                let chunk = chunk?;
+                scanner.scan(&chunk)?;
+                Ok::<_, BoxError>(chunk)
+            });
+            let body = Body::wrap_stream(stream::iter(received).chain(remaining));
+            inner.call(http::Request::from_parts(parts, body)).await
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for RequestSizeLimiter<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// Error type of the request body passed to the wrapped service.
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Reads the message prefixes of a request body as it is received and checks each declared length against the limit.
+struct MessageScanner {
+    /// Request size limit of the RPC.
+    limit: usize,
+    /// gRPC method name, included in the rejection message.
+    method: String,
+    /// Bytes of the current message prefix received so far.
+    prefix: Vec<u8>,
+    /// Bytes of the current message body still to be received.
+    remaining: usize,
+    /// Number of message prefixes read.
+    messages: usize,
+}
+
+impl MessageScanner {
+    fn new(limit: usize, method: String) -> Self {
+        MessageScanner {
+            limit,
+            method,
+            prefix: Vec::with_capacity(MESSAGE_PREFIX_LEN),
+            remaining: 0,
+            messages: 0,
+        }
+    }
+
+    /// Reads a chunk of the request body, returns RESOURCE_EXHAUSTED if it holds the prefix of an oversized message.
+    fn scan(&mut self, mut chunk: &[u8]) -> Result<(), tonic::Status> {
+        while !chunk.is_empty() {
+            if self.remaining > 0 {
+                let skipped = self.remaining.min(chunk.len());
+                self.remaining -= skipped;
+                chunk = &chunk[skipped..];
+                continue;
+            }
+            let taken = (MESSAGE_PREFIX_LEN - self.prefix.len()).min(chunk.len());
+            self.prefix.extend_from_slice(&chunk[..taken]);
+            chunk = &chunk[taken..];
+            if self.prefix.len() == MESSAGE_PREFIX_LEN {
+                let len = u32::from_be_bytes([
+                    self.prefix[1],
+                    self.prefix[2],
+                    self.prefix[3],
+                    self.prefix[4],
+                ]) as usize;
+                self.prefix.clear();
+                self.messages += 1;
+                if len > self.limit {
+                    return Err(tonic::Status::resource_exhausted(format!(
+                        "Request message of {} bytes exceeds the {} byte request size limit of {}.",
+                        len, self.limit, self.method
+                    )));
+                }
+                self.remaining = len;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+//! Request logging with privacy controls.
+//!
+//! Request metadata (RPC name, duration, status and sizes) is logged by the [`RequestLogger`] middleware. Fields
+//! that link a request to a wallet, such as addresses, txids and heights, are recorded by RPC handlers as
+//! [`Redactable`] values, which can only be formatted for a given [`RequestLogLevel`] and are left out of the log
+//! below [`RequestLogLevel::Full`].
+
+use std::{
+    convert::Infallible,
+    fmt::{self, Display},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use futures::StreamExt;
+use tonic::{
+    body::BoxBody,
+    codegen::{http, BoxFuture, Service},
+    server::NamedService,
+    transport::Body,
+};
+
+use crate::server::middleware::rpc_method_name;
+
+/// Detail of the request log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RequestLogLevel {
+    /// Requests are not logged.
+    Off,
+    /// RPC name, duration, status and request and response sizes are logged.
+    #[default]
+    Metadata,
+    /// Metadata and request fields, including addresses, txids and heights, are logged.
+    Full,
+}
+
+impl RequestLogLevel {
+    /// Returns the level stored as `value` by [`SharedRequestLogLevel`].
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => RequestLogLevel::Off,
+            1 => RequestLogLevel::Metadata,
+            _ => RequestLogLevel::Full,
+        }
+    }
+}
+
+/// Request log level shared by the request loggers of a server, so it can be changed while the server is running.
+#[derive(Debug, Clone)]
+pub struct SharedRequestLogLevel(Arc<AtomicU8>);
+
+impl SharedRequestLogLevel {
+    /// Creates a shared level set to `level`.
+    pub fn new(level: RequestLogLevel) -> Self {
+        SharedRequestLogLevel(Arc::new(AtomicU8::new(level as u8)))
+    }
+
+    /// Returns the current level.
+    pub fn load(&self) -> RequestLogLevel {
+        RequestLogLevel::from_u8(self.0.load(Ordering::SeqCst))
+    }
+
+    /// Sets the level, applied to requests received from then on.
+    pub fn store(&self, level: RequestLogLevel) {
+        self.0.store(level as u8, Ordering::SeqCst);
+    }
+}
+
+impl From<RequestLogLevel> for SharedRequestLogLevel {
+    fn from(level: RequestLogLevel) -> Self {
+        SharedRequestLogLevel::new(level)
+    }
+}
+
+/// A request field that links a request to a wallet, eg. an address, txid or height.
+///
+/// Does not implement `Display` or `Debug`, the value is only reachable through [`Redactable::reveal`] for a given
+/// log level.
+pub struct Redactable<T>(T);
+
+impl<T> Redactable<T> {
+    /// Wraps a sensitive value.
+    pub fn new(value: T) -> Self {
+        Redactable(value)
+    }
+
+    /// Returns the value if it may be logged at `level`.
+    pub fn reveal(&self, level: RequestLogLevel) -> Option<&T> {
+        match level {
+            RequestLogLevel::Full => Some(&self.0),
+            RequestLogLevel::Off | RequestLogLevel::Metadata => None,
+        }
+    }
+}
+
+/// Request fields recorded by an RPC handler, logged by the [`RequestLogger`] once the call completes.
+///
+/// Shared through the request extensions, fields recorded on a request not received through a [`RequestLogger`]
+/// are dropped.
+#[derive(Clone, Default)]
+pub struct RequestLogFields(Arc<Mutex<Vec<(&'static str, Redactable<String>)>>>);
+
+impl RequestLogFields {
+    /// Returns the fields of the given request.
+    pub fn of<T>(request: &tonic::Request<T>) -> Self {
+        request
+            .extensions()
+            .get::<RequestLogFields>()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Records a sensitive request field.
+    pub fn record(&self, name: &'static str, value: impl Display) {
+        self.lock().push((name, Redactable::new(value.to_string())));
+    }
+
+    /// Locks the recorded fields.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(&'static str, Redactable<String>)>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Destination of request log lines.
+pub type RequestLogSink = Arc<dyn Fn(String) + Send + Sync>;
+
+/// Logs every call served by the wrapped service once its response has been sent or dropped.
+///
+/// Duration and response size cover the whole response, including the body of streaming RPCs.
+#[derive(Clone)]
+pub struct RequestLogger<S> {
+    /// Wrapped tonic service.
+    inner: S,
+    /// Detail of the request log, read as each request is received.
+    level: SharedRequestLogLevel,
+    /// Destination of the log lines.
+    sink: RequestLogSink,
+}
+
+impl<S> RequestLogger<S> {
+    /// Wraps a tonic service with request logging to stdout.
+    pub fn new(inner: S, level: impl Into<SharedRequestLogLevel>) -> Self {
+        Self::with_sink(inner, level, Arc::new(|line| println!("{}", line)))
+    }
+
+    /// Wraps a tonic service with request logging to the given sink.
+    pub fn with_sink(
+        inner: S,
+        level: impl Into<SharedRequestLogLevel>,
+        sink: RequestLogSink,
+    ) -> Self {
+        RequestLogger {
+            inner,
+            level: level.into(),
+            sink,
+        }
+    }
+}
+
+impl<S> Service<http::Request<Body>> for RequestLogger<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let level = self.level.load();
+        if level == RequestLogLevel::Off {
+            return Box::pin(self.inner.call(request));
+        }
+        let fields = RequestLogFields::default();
+        let request_bytes = Arc::new(AtomicU64::new(0));
+        let mut entry = RequestLogEntry {
+            method: rpc_method_name(&request),
+            start: Instant::now(),
+            status: None,
+            request_bytes: request_bytes.clone(),
+            response_bytes: 0,
+            fields: fields.clone(),
+            level,
+            sink: self.sink.clone(),
+        };
+        let (mut parts, body) = request.into_parts();
+        parts.extensions.insert(fields);
+        let body = Body::wrap_stream(body.inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                request_bytes.fetch_add(chunk.len() as u64, Ordering::SeqCst);
+            }
+        }));
+        let response = self.inner.call(http::Request::from_parts(parts, body));
+        Box::pin(async move {
+            let (parts, body) = response.await?.into_parts();
+            // Errors returned by the handler are sent as a trailers-only response, with the status in the headers.
+            entry.status = grpc_status(&parts.headers);
+            Ok(http::Response::from_parts(
+                parts,
+                BoxBody::new(LoggedBody { inner: body, entry }),
+            ))
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for RequestLogger<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// Returns the gRPC status code held in the given headers or trailers.
+fn grpc_status(headers: &http::HeaderMap) -> Option<tonic::Code> {
+    headers
+        .get("grpc-status")
+        .and_then(|status| status.to_str().ok())
+        .and_then(|status| status.parse::<i32>().ok())
+        .map(tonic::Code::from_i32)
+}
+
+/// A call being logged, written to the sink when dropped with its response body.
+struct RequestLogEntry {
+    method: String,
+    start: Instant,
+    status: Option<tonic::Code>,
+    request_bytes: Arc<AtomicU64>,
+    response_bytes: u64,
+    fields: RequestLogFields,
+    level: RequestLogLevel,
+    sink: RequestLogSink,
+}
+
+impl Display for RequestLogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[REQUEST] rpc={} status={} duration_ms={} request_bytes={} response_bytes={}",
+            self.method,
+            // A response dropped before its trailers were sent was cancelled by the client.
+            self.status
+                .map_or("Cancelled".to_string(), |code| format!("{:?}", code)),
+            self.start.elapsed().as_millis(),
+            self.request_bytes.load(Ordering::SeqCst),
+            self.response_bytes
+        )?;
+        for (name, value) in self.fields.lock().iter() {
+            if let Some(value) = value.reveal(self.level) {
+                write!(f, " {}={}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RequestLogEntry {
+    fn drop(&mut self) {
+        (self.sink)(self.to_string());
+    }
+}
+
+/// Response body that counts the bytes sent and records the status from the trailers in its log entry.
+struct LoggedBody {
+    inner: BoxBody,
+    entry: RequestLogEntry,
+}
+
+impl http_body::Body for LoggedBody {
+    type Data = <BoxBody as http_body::Body>::Data;
+    type Error = <BoxBody as http_body::Body>::Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_data(cx);
+        if let Poll::Ready(Some(Ok(data))) = &poll {
+            self.entry.response_bytes += data.len() as u64;
+        }
+        poll
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let poll = Pin::new(&mut self.inner).poll_trailers(cx);
+        if let Poll::Ready(Ok(Some(trailers))) = &poll {
+            if let Some(status) = grpc_status(trailers) {
+                self.entry.status = Some(status);
+            }
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
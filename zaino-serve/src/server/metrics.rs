@@ -0,0 +1,836 @@
+//! Holds the server's RPC metrics and the metrics endpoint.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    convert::Infallible,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU16, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, StatusCode,
+};
+
+use zaino_fetch::{
+    chain::{fetcher::FetchProgress, mempool::MempoolCacheStats},
+    jsonrpc::response::GetMiningInfoResponse,
+};
+
+use crate::server::{error::ServerError, health::NodeHealth};
+
+/// Upper bounds, in seconds, of the latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 13] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+/// Cumulative latency histogram with fixed buckets.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    /// Observation counts, one per bucket in [`LATENCY_BUCKETS`].
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    /// Sum of all observations.
+    sum: Duration,
+    /// Number of observations.
+    count: u64,
+}
+
+impl LatencyHistogram {
+    /// Records an observation.
+    pub fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += duration;
+        self.count += 1;
+    }
+
+    /// Returns the number of observations recorded.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the sum of all observations recorded.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Writes the histogram in the Prometheus text format.
+    fn render(&self, output: &mut String, name: &str, labels: &str) {
+        let bucket_labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{labels},")
+        };
+        for (bucket, bound) in self.buckets.iter().zip(LATENCY_BUCKETS) {
+            writeln!(
+                output,
+                "{name}_bucket{{{bucket_labels}le=\"{bound}\"}} {bucket}"
+            )
+            .ok();
+        }
+        writeln!(
+            output,
+            "{name}_bucket{{{bucket_labels}le=\"+Inf\"}} {}",
+            self.count
+        )
+        .ok();
+        if labels.is_empty() {
+            writeln!(output, "{name}_sum {}", self.sum.as_secs_f64()).ok();
+            writeln!(output, "{name}_count {}", self.count).ok();
+        } else {
+            writeln!(output, "{name}_sum{{{labels}}} {}", self.sum.as_secs_f64()).ok();
+            writeln!(output, "{name}_count{{{labels}}} {}", self.count).ok();
+        }
+    }
+}
+
+/// Number of recent observations used to compute rolling latency quantiles.
+const ROLLING_WINDOW_SIZE: usize = 1024;
+
+/// Latency quantiles over the most recent observations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyQuantiles {
+    /// Median latency.
+    pub p50: Duration,
+    /// 95th percentile latency.
+    pub p95: Duration,
+    /// 99th percentile latency.
+    pub p99: Duration,
+}
+
+/// Holds the most recent latency observations, used to compute rolling quantiles.
+#[derive(Debug, Clone, Default)]
+struct RollingWindow {
+    samples: VecDeque<Duration>,
+}
+
+impl RollingWindow {
+    /// Records an observation, dropping the oldest observation if the window is full.
+    fn observe(&mut self, duration: Duration) {
+        if self.samples.len() == ROLLING_WINDOW_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration);
+    }
+
+    /// Returns the latency quantiles of the observations in the window, all zero if the window is empty.
+    fn quantiles(&self) -> LatencyQuantiles {
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let quantile = |q: f64| {
+            sorted
+                .get(((sorted.len() as f64 * q).ceil() as usize).saturating_sub(1))
+                .copied()
+                .unwrap_or_default()
+        };
+        LatencyQuantiles {
+            p50: quantile(0.50),
+            p95: quantile(0.95),
+            p99: quantile(0.99),
+        }
+    }
+}
+
+/// Request queue latency metrics.
+#[derive(Debug, Clone, Default)]
+pub struct QueueMetrics {
+    /// Time between a request being queued and a worker picking it up.
+    pub wait: LatencyHistogram,
+    /// Time between a request being queued and its first response.
+    pub first_response: LatencyHistogram,
+    /// Most recent queue wait times.
+    wait_window: RollingWindow,
+    /// Most recent times to first response.
+    first_response_window: RollingWindow,
+}
+
+impl QueueMetrics {
+    /// Returns the rolling quantiles of the time requests wait in the queue.
+    pub fn wait_quantiles(&self) -> LatencyQuantiles {
+        self.wait_window.quantiles()
+    }
+
+    /// Returns the rolling quantiles of the time between requests being queued and their first response.
+    pub fn first_response_quantiles(&self) -> LatencyQuantiles {
+        self.first_response_window.quantiles()
+    }
+}
+
+/// Latency histograms of a single RPC method.
+#[derive(Debug, Clone, Default)]
+pub struct MethodMetrics {
+    /// Total handler duration of successful calls.
+    pub success: LatencyHistogram,
+    /// Total handler duration of failed calls.
+    pub error: LatencyHistogram,
+    /// Time spent waiting on the node, for all calls.
+    pub node: LatencyHistogram,
+    /// Time spent waiting for a concurrency permit, only recorded for RPCs with a concurrency limit.
+    pub wait: LatencyHistogram,
+    /// Number of calls rejected after waiting too long for a concurrency permit.
+    pub rejected: u64,
+    /// Total number of completed calls.
+    pub requests: u64,
+    /// Number of completed calls that returned an error status.
+    pub errors: u64,
+    /// Number of calls whose handler panicked, these are also counted as errors.
+    pub panics: u64,
+}
+
+/// Default request queue fill percentage at which backpressure warnings are raised.
+pub const DEFAULT_QUEUE_WARN_THRESHOLD: u8 = 50;
+
+/// Default request queue fill percentage at which backpressure errors are raised.
+pub const DEFAULT_QUEUE_ERROR_THRESHOLD: u8 = 75;
+
+/// Minimum time between backpressure events of the same level.
+pub const BACKPRESSURE_EVENT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Severity of a request queue backpressure event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressureLevel {
+    /// The queue is filling, requests may soon be rejected.
+    Warn,
+    /// The queue is close to capacity, requests are likely to be rejected.
+    Error,
+}
+
+impl BackpressureLevel {
+    /// Returns the metrics label of the level.
+    pub fn label(self) -> &'static str {
+        match self {
+            BackpressureLevel::Warn => "warn",
+            BackpressureLevel::Error => "error",
+        }
+    }
+}
+
+/// Raises debounced backpressure events as the request queue fills.
+///
+/// A warning is raised while the queue is at least `warn_threshold` percent full, and an error while it is at least
+/// `error_threshold` percent full. Each level is raised at most once per [`BACKPRESSURE_EVENT_INTERVAL`].
+#[derive(Debug, Clone)]
+pub struct QueueBackpressure {
+    /// Queue fill percentage at which warnings are raised.
+    warn_threshold: u8,
+    /// Queue fill percentage at which errors are raised.
+    error_threshold: u8,
+    /// Time the last warning was raised.
+    last_warn: Option<Instant>,
+    /// Time the last error was raised.
+    last_error: Option<Instant>,
+}
+
+impl QueueBackpressure {
+    /// Returns a monitor raising events at the given queue fill percentages.
+    pub fn new(warn_threshold: u8, error_threshold: u8) -> Self {
+        QueueBackpressure {
+            warn_threshold,
+            error_threshold,
+            last_warn: None,
+            last_error: None,
+        }
+    }
+
+    /// Returns the event to raise for the current queue length, if any.
+    pub fn check(
+        &mut self,
+        queue_length: usize,
+        max_length: usize,
+        now: Instant,
+    ) -> Option<BackpressureLevel> {
+        if max_length == 0 {
+            return None;
+        }
+        let fill_percent = queue_length * 100 / max_length;
+        let (level, last_event) = if fill_percent >= self.error_threshold as usize {
+            (BackpressureLevel::Error, &mut self.last_error)
+        } else if fill_percent >= self.warn_threshold as usize {
+            (BackpressureLevel::Warn, &mut self.last_warn)
+        } else {
+            return None;
+        };
+        if last_event.is_some_and(|last| now.duration_since(last) < BACKPRESSURE_EVENT_INTERVAL) {
+            return None;
+        }
+        *last_event = Some(now);
+        Some(level)
+    }
+}
+
+/// Per-RPC latency metrics, shared by every worker in the pool.
+#[derive(Debug, Default)]
+pub struct RpcMetrics {
+    methods: Mutex<BTreeMap<String, MethodMetrics>>,
+    /// Request queue latency metrics.
+    queue: Mutex<QueueMetrics>,
+    /// Set if the node rejected the server's credentials at startup.
+    node_auth_failed: AtomicBool,
+    /// Network solution rate and difficulty last reported by the node, `None` until the node is first probed.
+    mining_info: Mutex<Option<GetMiningInfoResponse>>,
+    /// Configured maximum number of workers in the worker pool.
+    worker_pool_max_size: AtomicU16,
+    /// Configured number of workers kept in the worker pool when idle.
+    worker_pool_idle_size: AtomicU16,
+    /// Number of lookups answered from the negative cache.
+    negative_cache_hits: AtomicU64,
+    /// Number of workers replaced after their task panicked or failed.
+    worker_restarts: AtomicU64,
+    /// Number of requests discarded after waiting in the queue longer than the maximum request age.
+    expired_requests: AtomicU64,
+    /// Number of queued requests evicted to make room for new requests under the drop-oldest overload policy.
+    queue_evictions: AtomicU64,
+    /// Number of request queue backpressure warnings raised.
+    backpressure_warn_events: AtomicU64,
+    /// Number of request queue backpressure errors raised.
+    backpressure_error_events: AtomicU64,
+    /// Progress of block cache warming, `None` if the cache is not warmed on startup.
+    cache_warm_progress: Mutex<Option<FetchProgress>>,
+    /// Set once block cache warming has completed.
+    cache_warm_complete: AtomicBool,
+    /// Size and eviction counters of the mempool transaction cache, `None` until the cache is created.
+    mempool_cache_stats: Mutex<Option<MempoolCacheStats>>,
+}
+
+impl RpcMetrics {
+    /// Returns an empty metrics registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the node rejected the server's credentials.
+    pub fn set_node_auth_failed(&self, failed: bool) {
+        self.node_auth_failed.store(failed, Ordering::SeqCst);
+    }
+
+    /// Returns true if the node rejected the server's credentials.
+    pub fn node_auth_failed(&self) -> bool {
+        self.node_auth_failed.load(Ordering::SeqCst)
+    }
+
+    /// Sets the network solution rate and difficulty reported by the metrics endpoint.
+    pub fn set_mining_info(&self, mining_info: GetMiningInfoResponse) {
+        *self.mining_info.lock().unwrap_or_else(|e| e.into_inner()) = Some(mining_info);
+    }
+
+    /// Sets the configured worker pool size limits.
+    pub fn set_worker_pool_size(&self, max_size: u16, idle_size: u16) {
+        self.worker_pool_max_size.store(max_size, Ordering::SeqCst);
+        self.worker_pool_idle_size
+            .store(idle_size, Ordering::SeqCst);
+    }
+
+    /// Returns the configured maximum and idle worker pool sizes.
+    pub fn worker_pool_size(&self) -> (u16, u16) {
+        (
+            self.worker_pool_max_size.load(Ordering::SeqCst),
+            self.worker_pool_idle_size.load(Ordering::SeqCst),
+        )
+    }
+
+    /// Records a worker replaced after its task panicked or failed.
+    pub fn record_worker_restart(&self) {
+        self.worker_restarts.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns the number of workers replaced after their task panicked or failed.
+    pub fn worker_restarts(&self) -> u64 {
+        self.worker_restarts.load(Ordering::SeqCst)
+    }
+
+    /// Records a request discarded after waiting in the queue longer than the maximum request age.
+    pub fn record_expired_request(&self) {
+        self.expired_requests.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns the number of requests discarded after waiting in the queue longer than the maximum request age.
+    pub fn expired_requests(&self) -> u64 {
+        self.expired_requests.load(Ordering::SeqCst)
+    }
+
+    /// Records a queued request evicted to make room for a new request, see
+    /// [`OverloadPolicy::DropOldest`](crate::server::request::OverloadPolicy::DropOldest).
+    pub fn record_queue_eviction(&self) {
+        self.queue_evictions.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns the number of queued requests evicted to make room for new requests.
+    pub fn queue_evictions(&self) -> u64 {
+        self.queue_evictions.load(Ordering::SeqCst)
+    }
+
+    /// Records a lookup answered from the negative cache instead of the node.
+    pub fn record_negative_cache_hit(&self) {
+        self.negative_cache_hits.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns the number of lookups answered from the negative cache.
+    pub fn negative_cache_hits(&self) -> u64 {
+        self.negative_cache_hits.load(Ordering::SeqCst)
+    }
+
+    /// Sets the progress handle of the block cache warming run.
+    pub fn set_cache_warm_progress(&self, progress: FetchProgress) {
+        *self
+            .cache_warm_progress
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(progress);
+    }
+
+    /// Marks block cache warming as completed.
+    pub fn set_cache_warm_complete(&self) {
+        self.cache_warm_complete.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true once block cache warming has completed.
+    pub fn cache_warm_complete(&self) -> bool {
+        self.cache_warm_complete.load(Ordering::SeqCst)
+    }
+
+    /// Sets the counters of the mempool transaction cache reported by the metrics endpoint.
+    pub fn set_mempool_cache_stats(&self, stats: MempoolCacheStats) {
+        *self
+            .mempool_cache_stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(stats);
+    }
+
+    /// Records a request queue backpressure event.
+    pub fn record_backpressure_event(&self, level: BackpressureLevel) {
+        self.backpressure_counter(level)
+            .fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns the number of request queue backpressure events raised at the given level.
+    pub fn backpressure_events(&self, level: BackpressureLevel) -> u64 {
+        self.backpressure_counter(level).load(Ordering::SeqCst)
+    }
+
+    /// Returns the backpressure event counter of the given level.
+    fn backpressure_counter(&self, level: BackpressureLevel) -> &AtomicU64 {
+        match level {
+            BackpressureLevel::Warn => &self.backpressure_warn_events,
+            BackpressureLevel::Error => &self.backpressure_error_events,
+        }
+    }
+
+    /// Records a completed call of the given RPC method.
+    pub fn record(&self, method: &str, success: bool, total: Duration, node: Duration) {
+        let mut methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        let metrics = methods.entry(method.to_string()).or_default();
+        metrics.requests += 1;
+        if success {
+            metrics.success.observe(total);
+        } else {
+            metrics.errors += 1;
+            metrics.error.observe(total);
+        }
+        metrics.node.observe(node);
+    }
+
+    /// Records the time a call of the given RPC method waited for a concurrency permit.
+    pub fn record_wait(&self, method: &str, wait: Duration, rejected: bool) {
+        let mut methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        let metrics = methods.entry(method.to_string()).or_default();
+        metrics.wait.observe(wait);
+        if rejected {
+            metrics.rejected += 1;
+        }
+    }
+
+    /// Records a call of the given RPC method whose handler panicked.
+    pub fn record_panic(&self, method: &str) {
+        let mut methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        methods.entry(method.to_string()).or_default().panics += 1;
+    }
+
+    /// Records the time a request waited in the queue before being picked up by a worker.
+    pub fn record_queue_wait(&self, wait: Duration) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        queue.wait.observe(wait);
+        queue.wait_window.observe(wait);
+    }
+
+    /// Records the time between a request being queued and its first response.
+    pub fn record_first_response(&self, elapsed: Duration) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        queue.first_response.observe(elapsed);
+        queue.first_response_window.observe(elapsed);
+    }
+
+    /// Returns the request queue latency metrics.
+    pub fn queue(&self) -> QueueMetrics {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Returns the metrics recorded for the given RPC method.
+    pub fn method(&self, method: &str) -> Option<MethodMetrics> {
+        self.methods
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(method)
+            .cloned()
+    }
+
+    /// Returns the number of completed calls of each RPC method, keyed by gRPC method name.
+    pub fn request_counts(&self) -> HashMap<String, u64> {
+        self.methods
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(method, metrics)| (method.clone(), metrics.requests))
+            .collect()
+    }
+
+    /// Returns up to `n` of the most called RPC methods with their call counts, most called first.
+    pub fn top_methods(&self, n: usize) -> Vec<(String, u64)> {
+        let mut counts: Vec<(String, u64)> = self.request_counts().into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// Returns all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let methods = self.methods.lock().unwrap_or_else(|e| e.into_inner());
+        let mut output = String::new();
+        output.push_str(
+            "# HELP zaino_node_auth_failed Set if the node rejected the server's credentials.\n",
+        );
+        output.push_str("# TYPE zaino_node_auth_failed gauge\n");
+        writeln!(
+            output,
+            "zaino_node_auth_failed {}",
+            self.node_auth_failed() as u8
+        )
+        .ok();
+        if let Some(mining_info) = self
+            .mining_info
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+        {
+            output.push_str(
+                "# HELP zaino_node_network_solps Estimated network solution rate reported by the node, in Sol/s.\n",
+            );
+            output.push_str("# TYPE zaino_node_network_solps gauge\n");
+            writeln!(
+                output,
+                "zaino_node_network_solps {}",
+                mining_info.network_sol_ps
+            )
+            .ok();
+            if let Some(difficulty) = mining_info.difficulty {
+                output.push_str(
+                    "# HELP zaino_node_difficulty Proof-of-work difficulty of the chain tip reported by the node.\n",
+                );
+                output.push_str("# TYPE zaino_node_difficulty gauge\n");
+                writeln!(output, "zaino_node_difficulty {}", difficulty).ok();
+            }
+        }
+        let (worker_pool_max_size, worker_pool_idle_size) = self.worker_pool_size();
+        output.push_str(
+            "# HELP zaino_worker_pool_max_size Configured maximum number of workers in the worker pool.\n",
+        );
+        output.push_str("# TYPE zaino_worker_pool_max_size gauge\n");
+        writeln!(
+            output,
+            "zaino_worker_pool_max_size {}",
+            worker_pool_max_size
+        )
+        .ok();
+        output.push_str(
+            "# HELP zaino_worker_pool_idle_size Configured number of workers kept in the worker pool when idle.\n",
+        );
+        output.push_str("# TYPE zaino_worker_pool_idle_size gauge\n");
+        writeln!(
+            output,
+            "zaino_worker_pool_idle_size {}",
+            worker_pool_idle_size
+        )
+        .ok();
+        output.push_str(
+            "# HELP zaino_worker_restarts_total Workers replaced after their task panicked or failed.\n",
+        );
+        output.push_str("# TYPE zaino_worker_restarts_total counter\n");
+        writeln!(
+            output,
+            "zaino_worker_restarts_total {}",
+            self.worker_restarts()
+        )
+        .ok();
+        output.push_str(
+            "# HELP zaino_expired_requests_total Requests discarded after waiting in the queue longer than the maximum request age.\n",
+        );
+        output.push_str("# TYPE zaino_expired_requests_total counter\n");
+        writeln!(
+            output,
+            "zaino_expired_requests_total {}",
+            self.expired_requests()
+        )
+        .ok();
+        output.push_str(
+            "# HELP zaino_queue_evictions_total Queued requests evicted to make room for new requests when the queue was full.\n",
+        );
+        output.push_str("# TYPE zaino_queue_evictions_total counter\n");
+        writeln!(
+            output,
+            "zaino_queue_evictions_total {}",
+            self.queue_evictions()
+        )
+        .ok();
+        output.push_str(
+            "# HELP zaino_negative_cache_hits_total Lookups of missing items answered without querying the node.\n",
+        );
+        output.push_str("# TYPE zaino_negative_cache_hits_total counter\n");
+        writeln!(
+            output,
+            "zaino_negative_cache_hits_total {}",
+            self.negative_cache_hits()
+        )
+        .ok();
+        output.push_str(
+            "# HELP zaino_queue_backpressure_events_total Request queue backpressure events raised, by level.\n",
+        );
+        output.push_str("# TYPE zaino_queue_backpressure_events_total counter\n");
+        for level in [BackpressureLevel::Warn, BackpressureLevel::Error] {
+            writeln!(
+                output,
+                "zaino_queue_backpressure_events_total{{level=\"{}\"}} {}",
+                level.label(),
+                self.backpressure_events(level)
+            )
+            .ok();
+        }
+        if let Some(progress) = self
+            .cache_warm_progress
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+        {
+            output.push_str(
+                "# HELP zaino_cache_warm_blocks Blocks to fetch while warming the block cache on startup.\n",
+            );
+            output.push_str("# TYPE zaino_cache_warm_blocks gauge\n");
+            writeln!(output, "zaino_cache_warm_blocks {}", progress.total()).ok();
+            output.push_str(
+                "# HELP zaino_cache_warm_blocks_fetched Blocks fetched into the block cache while warming.\n",
+            );
+            output.push_str("# TYPE zaino_cache_warm_blocks_fetched gauge\n");
+            writeln!(
+                output,
+                "zaino_cache_warm_blocks_fetched {}",
+                progress.fetched()
+            )
+            .ok();
+            output.push_str(
+                "# HELP zaino_cache_warm_complete Set once block cache warming has completed.\n",
+            );
+            output.push_str("# TYPE zaino_cache_warm_complete gauge\n");
+            writeln!(
+                output,
+                "zaino_cache_warm_complete {}",
+                self.cache_warm_complete() as u8
+            )
+            .ok();
+        }
+        if let Some(stats) = self
+            .mempool_cache_stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+        {
+            output.push_str(
+                "# HELP zaino_mempool_cache_bytes Memory held by cached mempool transactions, in bytes.\n",
+            );
+            output.push_str("# TYPE zaino_mempool_cache_bytes gauge\n");
+            writeln!(output, "zaino_mempool_cache_bytes {}", stats.size_bytes()).ok();
+            output.push_str(
+                "# HELP zaino_mempool_cache_max_bytes Configured maximum size of the mempool transaction cache, in bytes.\n",
+            );
+            output.push_str("# TYPE zaino_mempool_cache_max_bytes gauge\n");
+            writeln!(
+                output,
+                "zaino_mempool_cache_max_bytes {}",
+                stats.max_bytes()
+            )
+            .ok();
+            output.push_str(
+                "# HELP zaino_mempool_cache_transactions Mempool transactions held in the cache.\n",
+            );
+            output.push_str("# TYPE zaino_mempool_cache_transactions gauge\n");
+            writeln!(
+                output,
+                "zaino_mempool_cache_transactions {}",
+                stats.transactions()
+            )
+            .ok();
+            output.push_str(
+                "# HELP zaino_mempool_cache_evictions_total Mempool transactions evicted to keep the cache under its maximum size.\n",
+            );
+            output.push_str("# TYPE zaino_mempool_cache_evictions_total counter\n");
+            writeln!(
+                output,
+                "zaino_mempool_cache_evictions_total {}",
+                stats.evictions()
+            )
+            .ok();
+        }
+        let queue = self.queue();
+        output.push_str(
+            "# HELP zaino_queue_wait_seconds Time requests waited in the queue before being picked up by a worker.\n",
+        );
+        output.push_str("# TYPE zaino_queue_wait_seconds histogram\n");
+        queue
+            .wait
+            .render(&mut output, "zaino_queue_wait_seconds", "");
+        output.push_str(
+            "# HELP zaino_queue_first_response_seconds Time between requests being queued and their first response.\n",
+        );
+        output.push_str("# TYPE zaino_queue_first_response_seconds histogram\n");
+        queue
+            .first_response
+            .render(&mut output, "zaino_queue_first_response_seconds", "");
+        output.push_str("# HELP zaino_rpc_requests_total Completed RPC calls.\n");
+        output.push_str("# TYPE zaino_rpc_requests_total counter\n");
+        for (method, metrics) in methods.iter() {
+            writeln!(
+                output,
+                "zaino_rpc_requests_total{{method=\"{method}\"}} {}",
+                metrics.requests
+            )
+            .ok();
+        }
+        output.push_str(
+            "# HELP zaino_rpc_errors_total Completed RPC calls that returned an error.\n",
+        );
+        output.push_str("# TYPE zaino_rpc_errors_total counter\n");
+        for (method, metrics) in methods.iter() {
+            writeln!(
+                output,
+                "zaino_rpc_errors_total{{method=\"{method}\"}} {}",
+                metrics.errors
+            )
+            .ok();
+        }
+        output.push_str("# HELP zaino_rpc_panics_total RPC calls whose handler panicked.\n");
+        output.push_str("# TYPE zaino_rpc_panics_total counter\n");
+        for (method, metrics) in methods.iter() {
+            writeln!(
+                output,
+                "zaino_rpc_panics_total{{method=\"{method}\"}} {}",
+                metrics.panics
+            )
+            .ok();
+        }
+        output.push_str("# HELP zaino_rpc_duration_seconds Total RPC handler duration.\n");
+        output.push_str("# TYPE zaino_rpc_duration_seconds histogram\n");
+        for (method, metrics) in methods.iter() {
+            for (outcome, histogram) in [("success", &metrics.success), ("error", &metrics.error)] {
+                histogram.render(
+                    &mut output,
+                    "zaino_rpc_duration_seconds",
+                    &format!("method=\"{method}\",outcome=\"{outcome}\""),
+                );
+            }
+        }
+        output.push_str(
+            "# HELP zaino_rpc_node_duration_seconds Time RPC handlers spent waiting on the node.\n",
+        );
+        output.push_str("# TYPE zaino_rpc_node_duration_seconds histogram\n");
+        for (method, metrics) in methods.iter() {
+            metrics.node.render(
+                &mut output,
+                "zaino_rpc_node_duration_seconds",
+                &format!("method=\"{method}\""),
+            );
+        }
+        output.push_str(
+            "# HELP zaino_rpc_wait_duration_seconds Time RPC calls waited for a concurrency permit.\n",
+        );
+        output.push_str("# TYPE zaino_rpc_wait_duration_seconds histogram\n");
+        for (method, metrics) in methods.iter().filter(|(_, m)| m.wait.count() > 0) {
+            metrics.wait.render(
+                &mut output,
+                "zaino_rpc_wait_duration_seconds",
+                &format!("method=\"{method}\""),
+            );
+        }
+        output.push_str(
+            "# HELP zaino_rpc_rejected_total RPC calls rejected by their concurrency limit.\n",
+        );
+        output.push_str("# TYPE zaino_rpc_rejected_total counter\n");
+        for (method, metrics) in methods.iter().filter(|(_, m)| m.wait.count() > 0) {
+            writeln!(
+                output,
+                "zaino_rpc_rejected_total{{method=\"{method}\"}} {}",
+                metrics.rejected
+            )
+            .ok();
+        }
+        output
+    }
+}
+
+/// Serves the metrics in the Prometheus text format at `/metrics` until the server goes offline.
+///
+/// `/readyz` returns 200 while the server can serve requests, and 503 while the full node is unavailable or
+/// rejected the server's credentials, or while the node is on a different chain than the server's cached data.
+pub(crate) async fn serve_metrics(
+    listen_addr: SocketAddr,
+    metrics: Arc<RpcMetrics>,
+    node_health: Arc<NodeHealth>,
+    online: Arc<AtomicBool>,
+) -> Result<(), ServerError> {
+    let make_service = make_service_fn(move |_conn| {
+        let metrics = metrics.clone();
+        let node_health = node_health.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let metrics = metrics.clone();
+                let node_health = node_health.clone();
+                async move {
+                    let response = match request.uri().path() {
+                        "/metrics" => Response::new(Body::from(metrics.render())),
+                        "/readyz"
+                            if node_health.is_available()
+                                && node_health.chain_mismatch().is_none()
+                                && !metrics.node_auth_failed() =>
+                        {
+                            Response::new(Body::from("ready"))
+                        }
+                        "/readyz" => {
+                            let mut response = Response::new(Body::from("not ready"));
+                            *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+                            response
+                        }
+                        _ => {
+                            let mut response = Response::new(Body::empty());
+                            *response.status_mut() = StatusCode::NOT_FOUND;
+                            response
+                        }
+                    };
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+    hyper::Server::try_bind(&listen_addr)?
+        .serve(make_service)
+        .with_graceful_shutdown(async move {
+            while online.load(Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,616 @@
+//! Holds the tonic service middleware used by the server's workers.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::IpAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+use tonic::{
+    body::BoxBody,
+    codegen::{http, BoxFuture, Service},
+    server::NamedService,
+    transport::{server::TcpConnectInfo, Body},
+};
+use zaino_fetch::jsonrpc::connector::with_node_timer;
+
+use crate::{
+    server::{
+        metrics::RpcMetrics,
+        request::{method_name_from_path, short_method_name, COMPACT_TX_STREAMER_SERVICE},
+    },
+    utils::ratelimit::TokenBucket,
+};
+
+/// Records the latency of every call served by the wrapped service in the shared [`RpcMetrics`].
+///
+/// Latency is measured up to the response headers. For streaming RPCs this does not include the time
+/// spent streaming the response body, and node time is only recorded for requests sent before the response.
+#[derive(Debug, Clone)]
+pub(crate) struct RpcTimer<S> {
+    /// Wrapped tonic service.
+    inner: S,
+    /// Metrics shared by every worker in the pool.
+    metrics: Arc<RpcMetrics>,
+}
+
+impl<S> RpcTimer<S> {
+    /// Wraps a tonic service with RPC latency recording.
+    pub(crate) fn new(inner: S, metrics: Arc<RpcMetrics>) -> Self {
+        RpcTimer { inner, metrics }
+    }
+}
+
+impl<S> Service<http::Request<Body>> for RpcTimer<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let method = rpc_method_name(&request);
+        let metrics = self.metrics.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let start = Instant::now();
+            let (response, node_time) = with_node_timer(response).await;
+            let response = response?;
+            // Errors returned by the handler are sent as a trailers-only response, with the status in the headers.
+            let success = response
+                .headers()
+                .get("grpc-status")
+                .map_or(true, |status| status == "0");
+            metrics.record(&method, success, start.elapsed(), node_time);
+            Ok(response)
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for RpcTimer<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// Records the time between a queued connection being received and its first response in the shared [`RpcMetrics`].
+///
+/// A new timer wraps the worker's service for each connection taken from the queue, the time is recorded once per
+/// connection, when the response headers of its first call are ready.
+#[derive(Debug, Clone)]
+pub(crate) struct FirstResponseTimer<S> {
+    /// Wrapped tonic service.
+    inner: S,
+    /// Time the connection was received by the ingestor.
+    received_at: Instant,
+    /// Set once the first response has been recorded, shared by all clones of the timer.
+    recorded: Arc<AtomicBool>,
+    /// Metrics shared by every worker in the pool.
+    metrics: Arc<RpcMetrics>,
+}
+
+impl<S> FirstResponseTimer<S> {
+    /// Wraps a tonic service for a connection received at `received_at`.
+    pub(crate) fn new(inner: S, received_at: Instant, metrics: Arc<RpcMetrics>) -> Self {
+        FirstResponseTimer {
+            inner,
+            received_at,
+            recorded: Arc::new(AtomicBool::new(false)),
+            metrics,
+        }
+    }
+}
+
+impl<S> Service<http::Request<Body>> for FirstResponseTimer<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let received_at = self.received_at;
+        let recorded = self.recorded.clone();
+        let metrics = self.metrics.clone();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            if !recorded.swap(true, Ordering::SeqCst) {
+                metrics.record_first_response(received_at.elapsed());
+            }
+            Ok(response)
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for FirstResponseTimer<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// Answers every call on a connection that waited in the request queue longer than the maximum request age with
+/// DEADLINE_EXCEEDED, without calling the wrapped service.
+///
+/// `answered` is notified once a call has been answered, the worker then shuts the connection down gracefully so the
+/// client reconnects and its retries are queued afresh.
+#[derive(Debug, Clone)]
+pub(crate) struct ExpiredRequestRejector<S> {
+    /// Wrapped tonic service, never called.
+    inner: S,
+    /// Time the connection waited in the request queue.
+    queue_wait: Duration,
+    /// Notified each time a call is answered.
+    answered: Arc<Notify>,
+}
+
+impl<S> ExpiredRequestRejector<S> {
+    /// Wraps a tonic service for a connection that waited `queue_wait` in the request queue.
+    pub(crate) fn new(inner: S, queue_wait: Duration, answered: Arc<Notify>) -> Self {
+        ExpiredRequestRejector {
+            inner,
+            queue_wait,
+            answered,
+        }
+    }
+}
+
+impl<S> Service<http::Request<Body>> for ExpiredRequestRejector<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let message = format!(
+            "{} request expired after waiting {}ms in the request queue, the server is overloaded.",
+            rpc_method_name(&request),
+            self.queue_wait.as_millis()
+        );
+        self.answered.notify_one();
+        Box::pin(async move { Ok(tonic::Status::deadline_exceeded(message).to_http()) })
+    }
+}
+
+impl<S: NamedService> NamedService for ExpiredRequestRejector<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// Answers every call on a connection evicted from the full request queue with UNAVAILABLE, see
+/// [`OverloadPolicy::DropOldest`](crate::server::request::OverloadPolicy::DropOldest).
+///
+/// `answered` is notified once a call has been answered, the connection is then shut down gracefully as for
+/// [`ExpiredRequestRejector`].
+#[derive(Debug, Clone)]
+pub(crate) struct EvictedRequestRejector {
+    /// Time the connection waited in the request queue before it was evicted.
+    queue_wait: Duration,
+    /// Notified each time a call is answered.
+    answered: Arc<Notify>,
+}
+
+impl EvictedRequestRejector {
+    /// Creates a rejector for a connection evicted after waiting `queue_wait` in the request queue.
+    pub(crate) fn new(queue_wait: Duration, answered: Arc<Notify>) -> Self {
+        EvictedRequestRejector {
+            queue_wait,
+            answered,
+        }
+    }
+}
+
+impl Service<http::Request<Body>> for EvictedRequestRejector {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let message = format!(
+            "{} request evicted from the full request queue after waiting {}ms, the server is overloaded.",
+            rpc_method_name(&request),
+            self.queue_wait.as_millis()
+        );
+        self.answered.notify_one();
+        Box::pin(async move { Ok(tonic::Status::unavailable(message).to_http()) })
+    }
+}
+
+impl NamedService for EvictedRequestRejector {
+    // The evicted connection is never served, calls are routed as for the CompactTxStreamer service.
+    const NAME: &'static str = COMPACT_TX_STREAMER_SERVICE;
+}
+
+/// Returns the gRPC method name of a request, e.g. "GetLatestBlock".
+pub(crate) fn rpc_method_name(request: &http::Request<Body>) -> String {
+    short_method_name(method_name_from_path(request.uri().path())).to_string()
+}
+
+/// Per-RPC semaphores capping the number of concurrent executions of each limited RPC.
+#[derive(Debug)]
+pub(crate) struct RpcSemaphores {
+    /// Semaphores keyed by gRPC method name, RPCs not present are not limited.
+    semaphores: HashMap<String, Arc<Semaphore>>,
+    /// Maximum time a request waits for a permit before being rejected.
+    max_wait: Duration,
+}
+
+impl RpcSemaphores {
+    /// Creates a semaphore for each RPC in `limits`, keyed by gRPC method name (e.g. "GetSubtreeRoots").
+    pub(crate) fn new(limits: &HashMap<String, u16>, max_wait: Duration) -> Self {
+        RpcSemaphores {
+            semaphores: limits
+                .iter()
+                .map(|(rpc, max)| (rpc.clone(), Arc::new(Semaphore::new(*max as usize))))
+                .collect(),
+            max_wait,
+        }
+    }
+}
+
+/// Caps the number of concurrent executions of individual RPCs across all workers.
+///
+/// Requests over the cap wait up to the configured maximum wait for a permit, then fail with RESOURCE_EXHAUSTED.
+/// Permits are held until the response body is dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct RpcConcurrencyLimiter<S> {
+    /// Wrapped tonic service.
+    inner: S,
+    /// Per-RPC semaphores shared by every worker in the pool.
+    semaphores: Arc<RpcSemaphores>,
+    /// Metrics shared by every worker in the pool.
+    metrics: Arc<RpcMetrics>,
+}
+
+impl<S> RpcConcurrencyLimiter<S> {
+    /// Wraps a tonic service with per-RPC concurrency limits.
+    pub(crate) fn new(inner: S, semaphores: Arc<RpcSemaphores>, metrics: Arc<RpcMetrics>) -> Self {
+        RpcConcurrencyLimiter {
+            inner,
+            semaphores,
+            metrics,
+        }
+    }
+}
+
+impl<S> Service<http::Request<Body>> for RpcConcurrencyLimiter<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let method = rpc_method_name(&request);
+        let semaphore = match self.semaphores.semaphores.get(&method) {
+            Some(semaphore) => semaphore.clone(),
+            None => return Box::pin(self.inner.call(request)),
+        };
+        // The inner service is always ready, take the readied service and leave a clone in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let max_wait = self.semaphores.max_wait;
+        let metrics = self.metrics.clone();
+        Box::pin(async move {
+            let start = Instant::now();
+            let permit: OwnedSemaphorePermit =
+                match tokio::time::timeout(max_wait, semaphore.acquire_owned()).await {
+                    Ok(Ok(permit)) => permit,
+                    _ => {
+                        metrics.record_wait(&method, start.elapsed(), true);
+                        return Ok(tonic::Status::resource_exhausted(format!(
+                            "Maximum concurrent {} requests reached, please try again later.",
+                            method
+                        ))
+                        .to_http());
+                    }
+                };
+            metrics.record_wait(&method, start.elapsed(), false);
+            let (parts, body) = inner.call(request).await?.into_parts();
+            Ok(http::Response::from_parts(
+                parts,
+                BoxBody::new(GuardedBody {
+                    inner: body,
+                    _guard: permit,
+                }),
+            ))
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for RpcConcurrencyLimiter<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// Per client IP address request rate limit, shared by every worker in the pool.
+///
+/// Each address is given a [`TokenBucket`] holding one second's worth of requests, refilled at the limit, so an
+/// address may burst up to the limit and is then held to it.
+#[derive(Debug)]
+pub(crate) struct IpRateLimits {
+    /// Maximum requests accepted from a single IP address per second, 0 disables the limit.
+    max_requests_per_second: AtomicU32,
+    /// Token bucket of each client IP address seen since its bucket was last full.
+    buckets: RwLock<HashMap<IpAddr, Arc<TokenBucket>>>,
+}
+
+impl IpRateLimits {
+    /// Creates a rate limit of `max_requests_per_second` requests per client IP address, 0 disables the limit.
+    pub(crate) fn new(max_requests_per_second: u32) -> Self {
+        IpRateLimits {
+            max_requests_per_second: AtomicU32::new(max_requests_per_second),
+            buckets: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the maximum requests accepted from a single IP address per second, 0 disables the limit.
+    ///
+    /// Every address is given a new, full bucket at the new limit.
+    pub(crate) fn set_max_requests_per_second(&self, max_requests_per_second: u32) {
+        let mut buckets = self.buckets.write().unwrap_or_else(|e| e.into_inner());
+        self.max_requests_per_second
+            .store(max_requests_per_second, Ordering::SeqCst);
+        buckets.clear();
+    }
+
+    /// Takes a token from the bucket of `ip`, returns false if the address has reached its limit.
+    fn try_acquire(&self, ip: IpAddr) -> bool {
+        if let Some(bucket) = self
+            .buckets
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&ip)
+        {
+            return bucket.try_consume(1);
+        }
+        let mut buckets = self.buckets.write().unwrap_or_else(|e| e.into_inner());
+        let max_requests = self.max_requests_per_second.load(Ordering::SeqCst);
+        if max_requests == 0 {
+            return true;
+        }
+        if !buckets.contains_key(&ip) {
+            // A full bucket limits an address no more than a new one, addresses are only tracked until refilled.
+            buckets.retain(|_, bucket| bucket.available() < bucket.capacity());
+        }
+        buckets
+            .entry(ip)
+            .or_insert_with(|| {
+                Arc::new(TokenBucket::new(
+                    u64::from(max_requests),
+                    f64::from(max_requests),
+                ))
+            })
+            .try_consume(1)
+    }
+}
+
+/// Rejects requests from client IP addresses over the shared [`IpRateLimits`] with RESOURCE_EXHAUSTED.
+///
+/// Requests without a client address, such as requests served over Nym, are not limited.
+#[derive(Debug, Clone)]
+pub(crate) struct IpRateLimiter<S> {
+    /// Wrapped tonic service.
+    inner: S,
+    /// Rate limits shared by every worker in the pool.
+    limits: Arc<IpRateLimits>,
+}
+
+impl<S> IpRateLimiter<S> {
+    /// Wraps a tonic service with a per client IP address request rate limit.
+    pub(crate) fn new(inner: S, limits: Arc<IpRateLimits>) -> Self {
+        IpRateLimiter { inner, limits }
+    }
+}
+
+impl<S> Service<http::Request<Body>> for IpRateLimiter<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let client_ip = request
+            .extensions()
+            .get::<TcpConnectInfo>()
+            .and_then(TcpConnectInfo::remote_addr)
+            .map(|addr| addr.ip());
+        if let Some(ip) = client_ip {
+            if !self.limits.try_acquire(ip) {
+                return Box::pin(async move {
+                    Ok(tonic::Status::resource_exhausted(
+                        "Request rate limit for this address reached, please try again later.",
+                    )
+                    .to_http())
+                });
+            }
+        }
+        Box::pin(self.inner.call(request))
+    }
+}
+
+impl<S: NamedService> NamedService for IpRateLimiter<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// Caps the number of concurrent gRPC streams open across all workers, or on a single client connection.
+///
+/// Every call served by the wrapped service holds a slot until its response body is dropped,
+/// this covers streams that run to completion as well as streams closed by client disconnect.
+#[derive(Debug, Clone)]
+pub(crate) struct ConcurrentStreamLimiter<S> {
+    /// Wrapped tonic service.
+    inner: S,
+    /// Number of streams currently open, shared by every service counted against the same limit.
+    open_streams: Arc<AtomicUsize>,
+    /// Maximum number of concurrent streams allowed.
+    max_streams: usize,
+    /// Message returned to the client when the limit is reached.
+    exhausted_message: &'static str,
+}
+
+impl<S> ConcurrentStreamLimiter<S> {
+    /// Wraps a tonic service with a global concurrent stream limit.
+    pub(crate) fn new(inner: S, open_streams: Arc<AtomicUsize>, max_streams: usize) -> Self {
+        ConcurrentStreamLimiter {
+            inner,
+            open_streams,
+            max_streams,
+            exhausted_message: "Maximum concurrent streams reached, please try again later.",
+        }
+    }
+
+    /// Wraps a tonic service serving a single client connection with a per-connection concurrent stream limit.
+    pub(crate) fn per_connection(inner: S, max_streams: usize) -> Self {
+        ConcurrentStreamLimiter {
+            inner,
+            open_streams: Arc::new(AtomicUsize::new(0)),
+            max_streams,
+            exhausted_message: "Maximum concurrent streams for this connection reached, close an open stream and try again.",
+        }
+    }
+}
+
+impl<S> Service<http::Request<Body>> for ConcurrentStreamLimiter<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let guard = match StreamGuard::try_acquire(self.open_streams.clone(), self.max_streams) {
+            Some(guard) => guard,
+            None => {
+                let message = self.exhausted_message;
+                return Box::pin(async move {
+                    Ok(tonic::Status::resource_exhausted(message).to_http())
+                });
+            }
+        };
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let (parts, body) = response.await?.into_parts();
+            Ok(http::Response::from_parts(
+                parts,
+                BoxBody::new(GuardedBody {
+                    inner: body,
+                    _guard: guard,
+                }),
+            ))
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for ConcurrentStreamLimiter<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+/// Holds a slot in the open stream count, releasing it when dropped.
+#[derive(Debug)]
+struct StreamGuard(Arc<AtomicUsize>);
+
+impl StreamGuard {
+    /// Takes a slot if fewer than `max_streams` streams are open.
+    fn try_acquire(open_streams: Arc<AtomicUsize>, max_streams: usize) -> Option<Self> {
+        open_streams
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                (open < max_streams).then_some(open + 1)
+            })
+            .ok()
+            .map(|_| StreamGuard(open_streams))
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Response body that holds a guard, such as a stream slot or semaphore permit, until the body is finished or dropped.
+struct GuardedBody<G> {
+    inner: BoxBody,
+    _guard: G,
+}
+
+impl<G: Unpin> http_body::Body for GuardedBody<G> {
+    type Data = <BoxBody as http_body::Body>::Data;
+    type Error = <BoxBody as http_body::Body>::Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_data(cx)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
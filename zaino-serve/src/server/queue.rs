@@ -1,17 +1,20 @@
 //! Zingo-Indexer queue implementation.
 
 use crate::server::error::QueueError;
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{unbounded, Receiver, Sender};
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
     Arc,
 };
 
 /// Queue with max length.
+///
+/// The max length can be changed while the queue is in use, lowering it below the current length rejects new
+/// messages until the queue drains below the new max length.
 #[derive(Debug, Clone)]
 pub(crate) struct Queue<T> {
     /// Max number of messages allowed in the queue.
-    max_length: usize,
+    max_length: Arc<AtomicUsize>,
     /// Used to track current messages in the queue.
     queue_status: Arc<AtomicUsize>,
     /// Queue sender.
@@ -23,13 +26,15 @@ pub(crate) struct Queue<T> {
 impl<T> Queue<T> {
     /// Creates a new queue with a maximum size.
     pub(crate) fn new(max_length: usize, queue_status: Arc<AtomicUsize>) -> Self {
-        let (queue_tx, queue_rx) = bounded(max_length);
+        let (queue_tx, queue_rx) = unbounded();
+        let max_length = Arc::new(AtomicUsize::new(max_length));
         queue_status.store(0, Ordering::SeqCst);
         Queue {
-            max_length,
+            max_length: max_length.clone(),
             queue_status: queue_status.clone(),
             queue_tx: QueueSender {
                 inner: queue_tx,
+                max_length,
                 queue_status: queue_status.clone(),
             },
             queue_rx: QueueReceiver {
@@ -51,7 +56,12 @@ impl<T> Queue<T> {
 
     /// Returns the max length of the queue.
     pub(crate) fn max_length(&self) -> usize {
-        self.max_length
+        self.max_length.load(Ordering::SeqCst)
+    }
+
+    /// Sets the max length of the queue, messages already queued above the new max length are kept.
+    pub(crate) fn set_max_length(&self, max_length: usize) {
+        self.max_length.store(max_length, Ordering::SeqCst);
     }
 
     /// Returns the current length of the queue.
@@ -65,6 +75,8 @@ impl<T> Queue<T> {
 pub(crate) struct QueueSender<T> {
     /// Crossbeam_Channel Sender.
     inner: Sender<T>,
+    /// Max number of messages allowed in the queue.
+    max_length: Arc<AtomicUsize>,
     /// Used to track current messages in the queue.
     queue_status: Arc<AtomicUsize>,
 }
@@ -73,6 +85,7 @@ impl<T> Clone for QueueSender<T> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            max_length: Arc::clone(&self.max_length),
             queue_status: Arc::clone(&self.queue_status),
         }
     }
@@ -81,13 +94,48 @@ impl<T> Clone for QueueSender<T> {
 impl<T> QueueSender<T> {
     /// Tries to add a request to the queue, updating the queue size.
     pub(crate) fn try_send(&self, message: T) -> Result<(), QueueError<T>> {
+        // A slot is reserved before sending so concurrent senders can not overfill the queue.
+        let max_length = self.max_length.load(Ordering::SeqCst);
+        if self
+            .queue_status
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |length| {
+                (length < max_length).then_some(length + 1)
+            })
+            .is_err()
+        {
+            return Err(QueueError::QueueFull(message));
+        }
         match self.inner.try_send(message) {
-            Ok(_) => {
-                self.queue_status.fetch_add(1, Ordering::SeqCst);
-                Ok(())
+            Ok(_) => Ok(()),
+            Err(crossbeam_channel::TrySendError::Full(t)) => {
+                self.queue_status.fetch_sub(1, Ordering::SeqCst);
+                Err(QueueError::QueueFull(t))
+            }
+            Err(crossbeam_channel::TrySendError::Disconnected(_)) => {
+                self.queue_status.fetch_sub(1, Ordering::SeqCst);
+                Err(QueueError::QueueClosed)
+            }
+        }
+    }
+
+    /// Tries to add a request to the queue, first evicting the oldest message through `queue_rx` if the queue is full.
+    /// Returns the evicted message, if any.
+    ///
+    /// If another sender fills the slot freed by the eviction first, the message is returned in
+    /// [`QueueError::QueueFull`] and the evicted message is dropped.
+    pub(crate) fn try_send_evicting(
+        &self,
+        message: T,
+        queue_rx: &QueueReceiver<T>,
+    ) -> Result<Option<T>, QueueError<T>> {
+        match self.try_send(message) {
+            Ok(()) => Ok(None),
+            Err(QueueError::QueueFull(message)) => {
+                // A worker may take the oldest message first, leaving room without an eviction.
+                let evicted = queue_rx.try_recv().ok();
+                self.try_send(message).map(|()| evicted)
             }
-            Err(crossbeam_channel::TrySendError::Full(t)) => Err(QueueError::QueueFull(t)),
-            Err(crossbeam_channel::TrySendError::Disconnected(_)) => Err(QueueError::QueueClosed),
+            Err(e) => Err(e),
         }
     }
 
@@ -0,0 +1,123 @@
+//! Converts panics in RPC handlers into INTERNAL error responses.
+
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::RefCell,
+    convert::Infallible,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Once},
+    task::{Context, Poll},
+};
+
+use futures::FutureExt;
+use tonic::{
+    body::BoxBody,
+    codegen::{http, BoxFuture, Service},
+    server::NamedService,
+    transport::Body,
+};
+
+use crate::server::{metrics::RpcMetrics, middleware::rpc_method_name};
+
+/// Message returned to clients whose request panicked the handler, details are only logged.
+pub const PANIC_STATUS_MESSAGE: &str =
+    "Internal error while handling request, please report this to the server operator.";
+
+thread_local! {
+    /// Backtrace of the last panic on this thread, set by the hook installed by [`install_backtrace_hook`].
+    static PANIC_BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+}
+
+/// Guards installation of the backtrace capturing panic hook.
+static BACKTRACE_HOOK: Once = Once::new();
+
+/// Installs a panic hook that records the backtrace of each panic before running the previous hook.
+///
+/// A handler future panics on the thread polling it, so the backtrace is read back on the same thread once the panic
+/// is caught.
+fn install_backtrace_hook() {
+    BACKTRACE_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            PANIC_BACKTRACE.with(|backtrace| {
+                *backtrace.borrow_mut() = Some(Backtrace::force_capture());
+            });
+            previous(info);
+        }));
+    });
+}
+
+/// Returns the message of a panic payload.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "<non-string panic payload>"
+    }
+}
+
+/// Catches panics raised while a call is handled by the wrapped service.
+///
+/// A panicking call is answered with an INTERNAL status carrying a generic message, rather than dropping the
+/// client's connection, and the panic is logged with its backtrace and counted in the shared [`RpcMetrics`]. Panics
+/// in tasks spawned by streaming handlers are not caught, their streams end when the task's sender is dropped.
+#[derive(Debug, Clone)]
+pub struct PanicRecovery<S> {
+    /// Wrapped tonic service.
+    inner: S,
+    /// Metrics shared by every worker in the pool.
+    metrics: Arc<RpcMetrics>,
+}
+
+impl<S> PanicRecovery<S> {
+    /// Wraps a tonic service with panic recovery.
+    pub fn new(inner: S, metrics: Arc<RpcMetrics>) -> Self {
+        install_backtrace_hook();
+        PanicRecovery { inner, metrics }
+    }
+}
+
+impl<S> Service<http::Request<Body>> for PanicRecovery<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let method = rpc_method_name(&request);
+        let metrics = self.metrics.clone();
+        let response = AssertUnwindSafe(self.inner.call(request)).catch_unwind();
+        Box::pin(async move {
+            match response.await {
+                Ok(response) => response,
+                Err(payload) => {
+                    let backtrace = PANIC_BACKTRACE.with(|backtrace| backtrace.borrow_mut().take());
+                    eprintln!(
+                        "Handler for {} panicked: {}\nBacktrace:\n{}",
+                        method,
+                        panic_message(payload.as_ref()),
+                        backtrace.map_or_else(|| "<unavailable>".to_string(), |b| b.to_string())
+                    );
+                    metrics.record_panic(&method);
+                    Ok(tonic::Status::internal(PANIC_STATUS_MESSAGE).to_http())
+                }
+            }
+        })
+    }
+}
+
+impl<S: NamedService> NamedService for PanicRecovery<S> {
+    const NAME: &'static str = S::NAME;
+}
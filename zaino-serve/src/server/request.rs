@@ -1,11 +1,57 @@
 //! Request types.
 
 use crate::server::error::RequestError;
+#[cfg(feature = "nym")]
 use nym_sphinx_anonymous_replies::requests::AnonymousSenderTag;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use tokio::net::TcpStream;
+#[cfg(feature = "nym")]
 use zaino_nym::utils::read_nym_request_data;
 
+/// Default maximum time a request waits in the request queue, older requests are discarded when dequeued.
+pub const DEFAULT_MAX_REQUEST_AGE: Duration = Duration::from_secs(60);
+
+/// Fully-qualified name of the gRPC service served by Zaino.
+pub const COMPACT_TX_STREAMER_SERVICE: &str = "cash.z.wallet.sdk.rpc.CompactTxStreamer";
+
+/// Method name of TCP requests, which hold a whole client connection rather than a single call.
+///
+/// The calls carried by a connection are only read once a worker serves it, and a connection may carry calls to any
+/// method of the service, so TCP calls are attributed to their method per call, by the RPC metrics middleware.
+pub const TCP_CONNECTION_METHOD_NAME: &str = "cash.z.wallet.sdk.rpc.CompactTxStreamer/*";
+
+/// Returns the fully-qualified gRPC method name of a CompactTxStreamer method, e.g.
+/// "cash.z.wallet.sdk.rpc.CompactTxStreamer/GetLatestBlock" for "GetLatestBlock".
+pub fn qualified_method_name(method: &str) -> String {
+    format!("{}/{}", COMPACT_TX_STREAMER_SERVICE, method)
+}
+
+/// Returns the fully-qualified gRPC method name of a request from its HTTP/2 path, gRPC paths take the form
+/// "/<package>.<service>/<method>".
+pub fn method_name_from_path(path: &str) -> &str {
+    path.strip_prefix('/').unwrap_or(path)
+}
+
+/// Returns the method of a fully-qualified gRPC method name, e.g. "GetLatestBlock", as used by the per-method
+/// metrics.
+pub fn short_method_name(method_name: &str) -> &str {
+    method_name.rsplit('/').next().unwrap_or_default()
+}
+
+/// Behaviour when a request arrives while the request queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum OverloadPolicy {
+    /// Reject the new request, its connection is closed.
+    #[default]
+    RejectNew,
+    /// Evict the oldest queued request to make room for the new request, the evicted request's calls are answered
+    /// with UNAVAILABLE.
+    ///
+    /// Under sustained overload the oldest requests are the most likely to belong to clients that have already timed
+    /// out. Queued requests have not started any call, so eviction never cuts a response stream short.
+    DropOldest,
+}
+
 /// Requests queuing metadata.
 #[derive(Debug, Clone)]
 struct QueueData {
@@ -13,15 +59,18 @@ struct QueueData {
     // request_id: u64, // TODO: implement with request queue (implement exlusive request_id generator in queue object).
     /// Time which the request was received.
     time_received: SystemTime,
+    /// Fully-qualified gRPC method name of the request.
+    method_name: String,
     /// Number of times the request has been requeued.
     requeue_attempts: u32,
 }
 
 impl QueueData {
     /// Returns a new instance of QueueData.
-    fn new() -> Self {
+    fn new(method_name: String) -> Self {
         QueueData {
             time_received: SystemTime::now(),
+            method_name,
             requeue_attempts: 0,
         }
     }
@@ -43,6 +92,7 @@ impl QueueData {
 }
 
 /// Nym request data.
+#[cfg(feature = "nym")]
 #[derive(Debug, Clone)]
 pub struct NymRequest {
     id: u64,
@@ -51,6 +101,7 @@ pub struct NymRequest {
     body: Vec<u8>,
 }
 
+#[cfg(feature = "nym")]
 impl NymRequest {
     /// Returns the client assigned id for this request, only used to construct response.
     pub fn client_id(&self) -> u64 {
@@ -85,12 +136,14 @@ impl TcpRequest {
 }
 
 /// Requests originating from the Nym server.
+#[cfg(feature = "nym")]
 #[derive(Debug, Clone)]
 pub struct NymServerRequest {
     queuedata: QueueData,
     request: NymRequest,
 }
 
+#[cfg(feature = "nym")]
 impl NymServerRequest {
     /// Returns the underlying request.
     pub fn get_request(&self) -> NymRequest {
@@ -116,6 +169,7 @@ impl TcpServerRequest {
 #[derive(Debug)]
 pub enum ZingoIndexerRequest {
     /// Requests originating from the Nym server.
+    #[cfg(feature = "nym")]
     NymServerRequest(NymServerRequest),
     /// Requests originating from the gRPC server.
     TcpServerRequest(TcpServerRequest),
@@ -123,10 +177,11 @@ pub enum ZingoIndexerRequest {
 
 impl ZingoIndexerRequest {
     /// Creates a ZingoIndexerRequest from an encoded gRPC service call, recieved by the Nym server.
+    #[cfg(feature = "nym")]
     pub fn new_from_nym(metadata: AnonymousSenderTag, bytes: &[u8]) -> Result<Self, RequestError> {
         let (id, method, body) = read_nym_request_data(bytes)?;
         Ok(ZingoIndexerRequest::NymServerRequest(NymServerRequest {
-            queuedata: QueueData::new(),
+            queuedata: QueueData::new(qualified_method_name(&method)),
             request: NymRequest {
                 id,
                 method,
@@ -141,7 +196,7 @@ impl ZingoIndexerRequest {
     /// TODO: implement proper functionality along with queue.
     pub fn new_from_grpc(stream: TcpStream) -> Self {
         ZingoIndexerRequest::TcpServerRequest(TcpServerRequest {
-            queuedata: QueueData::new(),
+            queuedata: QueueData::new(TCP_CONNECTION_METHOD_NAME.to_string()),
             request: TcpRequest(stream),
         })
     }
@@ -149,6 +204,7 @@ impl ZingoIndexerRequest {
     /// Increases the requeue attempts for the request.
     pub fn increase_requeues(&mut self) {
         match self {
+            #[cfg(feature = "nym")]
             ZingoIndexerRequest::NymServerRequest(ref mut req) => req.queuedata.increase_requeues(),
             ZingoIndexerRequest::TcpServerRequest(ref mut req) => req.queuedata.increase_requeues(),
         }
@@ -157,6 +213,7 @@ impl ZingoIndexerRequest {
     /// Returns the duration sunce the request was received.
     pub fn duration(&self) -> Result<std::time::Duration, RequestError> {
         match self {
+            #[cfg(feature = "nym")]
             ZingoIndexerRequest::NymServerRequest(ref req) => req.queuedata.duration(),
             ZingoIndexerRequest::TcpServerRequest(ref req) => req.queuedata.duration(),
         }
@@ -165,8 +222,24 @@ impl ZingoIndexerRequest {
     /// Returns the number of times the request has been requeued.
     pub fn requeues(&self) -> u32 {
         match self {
+            #[cfg(feature = "nym")]
             ZingoIndexerRequest::NymServerRequest(ref req) => req.queuedata.requeues(),
             ZingoIndexerRequest::TcpServerRequest(ref req) => req.queuedata.requeues(),
         }
     }
+
+    /// Returns the fully-qualified gRPC method name of the request, e.g.
+    /// "cash.z.wallet.sdk.rpc.CompactTxStreamer/GetLatestBlock", read from the request metadata at ingest.
+    ///
+    /// Returns [`TCP_CONNECTION_METHOD_NAME`] for TCP requests: a TCP request holds a whole client connection, whose
+    /// calls are not read until a worker serves it, so the method of each call is not known while it is queued.
+    /// Queue level metrics and logs can only tell TCP requests apart by their transport; per method metrics for TCP
+    /// calls are recorded by the RPC metrics middleware instead.
+    pub fn method_name(&self) -> &str {
+        match self {
+            #[cfg(feature = "nym")]
+            ZingoIndexerRequest::NymServerRequest(ref req) => &req.queuedata.method_name,
+            ZingoIndexerRequest::TcpServerRequest(ref req) => &req.queuedata.method_name,
+        }
+    }
 }
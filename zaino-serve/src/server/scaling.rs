@@ -0,0 +1,47 @@
+//! Worker pool scaling policy.
+//!
+//! The scaling decision is a pure function of the current load, so the policy run by the server's serve loop can be
+//! tested by feeding it synthetic load traces.
+
+/// Load on the worker pool, observed once per tick of the server's serve loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolLoad {
+    /// Number of requests waiting in the request queue.
+    pub queue_length: usize,
+    /// Capacity of the request queue.
+    pub max_queue_length: usize,
+    /// Current number of workers in the pool.
+    pub workers: usize,
+    /// Maximum number of workers in the pool.
+    pub max_workers: u16,
+    /// Number of workers kept in the pool when idle.
+    pub idle_workers: u16,
+}
+
+/// Change to the worker pool size decided for a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleAction {
+    /// Spawn a worker.
+    Grow,
+    /// Close the most recently spawned worker.
+    Shrink,
+    /// Keep the current pool size.
+    Hold,
+}
+
+/// Returns the change to make to the worker pool for the observed load.
+///
+/// The pool grows by one worker per tick while the request queue is at least a quarter full, up to `max_workers`. It
+/// shrinks by one worker per tick while the queue holds at most one request and there are more than `idle_workers`
+/// workers, or while the pool is above `max_workers` after being resized.
+pub fn scale_action(load: &PoolLoad) -> ScaleAction {
+    if load.queue_length >= load.max_queue_length / 4 && load.workers < load.max_workers as usize {
+        ScaleAction::Grow
+    } else if (load.queue_length <= 1 && load.workers > load.idle_workers as usize)
+        || load.workers > load.max_workers as usize
+    {
+        ScaleAction::Shrink
+    } else {
+        ScaleAction::Hold
+    }
+}
@@ -0,0 +1,96 @@
+//! Fair scheduling of responses sent back to Nym clients.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+use crate::server::error::QueueError;
+
+/// Default maximum bytes of responses queued for a single client.
+pub const DEFAULT_MAX_CLIENT_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Responses queued for a single client.
+#[derive(Debug, Default)]
+struct ClientResponses {
+    /// Queued responses, oldest first.
+    responses: VecDeque<Vec<u8>>,
+    /// Total bytes of the queued responses.
+    bytes: usize,
+}
+
+/// Schedules responses round-robin across clients, bounding the bytes queued for each client.
+///
+/// Each call to [`ResponseScheduler::pop`] returns a response for the next client with queued responses, so a client
+/// receiving many or large responses does not delay the responses of other clients.
+#[derive(Debug)]
+pub struct ResponseScheduler<K> {
+    /// Maximum bytes of responses queued for a single client.
+    max_client_bytes: usize,
+    /// Queued responses, keyed by client.
+    clients: HashMap<K, ClientResponses>,
+    /// Clients with queued responses, in dispatch order.
+    order: VecDeque<K>,
+}
+
+impl<K: Clone + Eq + Hash> ResponseScheduler<K> {
+    /// Returns an empty scheduler queuing at most `max_client_bytes` of responses for each client.
+    pub fn new(max_client_bytes: usize) -> Self {
+        ResponseScheduler {
+            max_client_bytes,
+            clients: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Queues a response for the given client.
+    ///
+    /// Returns the response in [`QueueError::QueueFull`] if it would take the client's queued bytes above the cap.
+    pub fn push(&mut self, client: K, response: Vec<u8>) -> Result<(), QueueError<Vec<u8>>> {
+        let queued = self.clients.entry(client.clone()).or_default();
+        if queued.bytes + response.len() > self.max_client_bytes {
+            if queued.responses.is_empty() {
+                self.clients.remove(&client);
+            }
+            return Err(QueueError::QueueFull(response));
+        }
+        if queued.responses.is_empty() {
+            self.order.push_back(client);
+        }
+        queued.bytes += response.len();
+        queued.responses.push_back(response);
+        Ok(())
+    }
+
+    /// Returns the oldest response of the next client in round-robin order.
+    pub fn pop(&mut self) -> Option<(Vec<u8>, K)> {
+        let client = self.order.pop_front()?;
+        let queued = self.clients.get_mut(&client)?;
+        let response = queued.responses.pop_front()?;
+        queued.bytes -= response.len();
+        if queued.responses.is_empty() {
+            self.clients.remove(&client);
+        } else {
+            self.order.push_back(client.clone());
+        }
+        Some((response, client))
+    }
+
+    /// Returns the bytes of responses queued for the given client.
+    pub fn queued_bytes(&self, client: &K) -> usize {
+        self.clients.get(client).map_or(0, |queued| queued.bytes)
+    }
+
+    /// Returns the number of responses queued across all clients.
+    pub fn len(&self) -> usize {
+        self.clients
+            .values()
+            .map(|queued| queued.responses.len())
+            .sum()
+    }
+
+    /// Returns true if no responses are queued.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
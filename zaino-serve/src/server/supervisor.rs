@@ -0,0 +1,123 @@
+//! Detects worker tasks that panicked or failed while the server is running.
+//!
+//! Handler panics are caught by [`crate::server::recovery::PanicRecovery`], but a panic or error in the worker's own
+//! serve loop ends the worker task. The server's command loop reaps these tasks with [`reap_workers`], logs the
+//! request the worker was serving and replaces the worker, rather than the pool silently shrinking.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use tokio::task::JoinHandle;
+
+use crate::server::{error::WorkerError, recovery::panic_message};
+
+/// Description of the request a worker is serving, shared between the worker and the server supervising it.
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext(Arc<Mutex<Option<String>>>);
+
+impl RequestContext {
+    /// Records the request the worker has started serving.
+    pub fn set(&self, description: impl Into<String>) {
+        *self.lock() = Some(description.into());
+    }
+
+    /// Clears the request once the worker has finished serving it.
+    pub fn clear(&self) {
+        *self.lock() = None;
+    }
+
+    /// Returns the request the worker is serving, if any.
+    pub fn get(&self) -> Option<String> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<String>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// How a worker task ended.
+#[derive(Debug)]
+pub enum WorkerExit {
+    /// The worker panicked, holds the panic message.
+    Panicked(String),
+    /// The worker returned an error.
+    Failed(WorkerError),
+    /// The worker task was cancelled.
+    Cancelled,
+    /// The worker returned without an error, eg. after its queue was closed.
+    Stopped,
+}
+
+/// A worker task that ended while the server was running.
+#[derive(Debug)]
+pub struct WorkerFailure {
+    /// ID of the worker, its index in the worker pool.
+    pub worker_id: usize,
+    /// Request the worker was serving when it ended, if any.
+    pub request: Option<String>,
+    /// How the worker ended.
+    pub exit: WorkerExit,
+}
+
+impl fmt::Display for WorkerFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let request = self.request.as_deref().unwrap_or("no request");
+        match &self.exit {
+            WorkerExit::Panicked(message) => write!(
+                f,
+                "Worker {} panicked while serving {}: {}",
+                self.worker_id, request, message
+            ),
+            WorkerExit::Failed(e) => write!(
+                f,
+                "Worker {} failed while serving {}: {}",
+                self.worker_id, request, e
+            ),
+            WorkerExit::Cancelled => write!(
+                f,
+                "Worker {} was cancelled while serving {}",
+                self.worker_id, request
+            ),
+            WorkerExit::Stopped => write!(
+                f,
+                "Worker {} stopped while serving {}",
+                self.worker_id, request
+            ),
+        }
+    }
+}
+
+/// Awaits the worker tasks in `handles` that have finished and returns how each ended, with the request held in the
+/// worker's entry of `contexts`.
+///
+/// Handles still running are not awaited. Reaped handles are left in place and must be replaced by the caller, as a
+/// finished handle can not be awaited again.
+pub async fn reap_workers(
+    handles: &mut [JoinHandle<Result<(), WorkerError>>],
+    contexts: &[RequestContext],
+) -> Vec<WorkerFailure> {
+    let mut failures = Vec::new();
+    for (worker_id, handle) in handles.iter_mut().enumerate() {
+        if !handle.is_finished() {
+            continue;
+        }
+        let exit = match handle.await {
+            Ok(Ok(())) => WorkerExit::Stopped,
+            Ok(Err(e)) => WorkerExit::Failed(e),
+            Err(e) if e.is_panic() => {
+                WorkerExit::Panicked(panic_message(e.into_panic().as_ref()).to_string())
+            }
+            Err(_) => WorkerExit::Cancelled,
+        };
+        let request = contexts.get(worker_id).and_then(RequestContext::get);
+        failures.push(WorkerFailure {
+            worker_id,
+            request,
+            exit,
+        });
+    }
+    failures
+}
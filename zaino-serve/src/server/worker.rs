@@ -1,30 +1,136 @@
 //! Holds the server worker implementation.
 
-use std::sync::{
-    atomic::{AtomicBool, AtomicUsize, Ordering},
-    Arc,
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
 use http::Uri;
+#[cfg(feature = "nym")]
 use nym_sphinx_anonymous_replies::requests::AnonymousSenderTag;
-use tonic::transport::Server;
+use tokio::sync::Notify;
+use tonic::{codec::CompressionEncoding, transport::Server};
+use zaino_fetch::{
+    chain::{
+        cache::{BlockCache, ChainTip, NegativeCache},
+        mempool::MempoolTxCache,
+    },
+    jsonrpc::connector::{NodeAuth, NodeType},
+    primitives::chain::NetworkKind,
+};
 
 use crate::{
-    rpc::GrpcClient,
+    rpc::{stream::StreamErrorPolicy, GrpcClient},
     server::{
-        error::{QueueError, WorkerError},
+        error::WorkerError,
+        health::NodeHealth,
+        keepalive::Keepalive,
+        limits::{RequestSizeLimiter, RequestSizeLimits},
+        logging::{RequestLogger, SharedRequestLogLevel},
+        metrics::RpcMetrics,
+        middleware::{
+            ConcurrentStreamLimiter, ExpiredRequestRejector, FirstResponseTimer, IpRateLimiter,
+            IpRateLimits, RpcConcurrencyLimiter, RpcSemaphores, RpcTimer,
+        },
         queue::{QueueReceiver, QueueSender},
+        recovery::PanicRecovery,
         request::ZingoIndexerRequest,
+        supervisor::RequestContext,
         AtomicStatus,
     },
 };
 
+#[cfg(feature = "nym")]
+use crate::server::{error::QueueError, request::short_method_name};
+#[cfg(feature = "nym")]
+use zaino_fetch::jsonrpc::connector::with_node_timer;
+
 #[cfg(not(feature = "nym_poc"))]
 use zaino_proto::proto::service::compact_tx_streamer_server::CompactTxStreamerServer;
 
 #[cfg(feature = "nym_poc")]
 use zcash_client_backend::proto::service::compact_tx_streamer_server::CompactTxStreamerServer;
 
+/// Maximum time an expired connection is held open waiting for its first call to be answered.
+pub(crate) const EXPIRED_CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Queues a worker receives requests from and sends responses to.
+#[derive(Debug, Clone)]
+pub(crate) struct WorkerQueues {
+    /// Used to pop requests from the queue.
+    pub(crate) queue: QueueReceiver<ZingoIndexerRequest>,
+    /// Used to requeue requests.
+    pub(crate) _requeue: QueueSender<ZingoIndexerRequest>,
+    /// Used to send responses to the nym_dispatcher.
+    #[cfg(feature = "nym")]
+    pub(crate) nym_response_queue: QueueSender<(Vec<u8>, AnonymousSenderTag)>,
+}
+
+/// Settings and shared state every worker in a pool is spawned with.
+#[derive(Debug, Clone)]
+pub(crate) struct WorkerConfig {
+    /// Lightwalletd uri, used by grpc_passthrough to pass on unimplemented RPCs.
+    pub(crate) lightwalletd_uri: Uri,
+    /// Full node JsonRPC uri.
+    pub(crate) zebrad_uri: Uri,
+    /// Network the indexer is configured for.
+    pub(crate) network: NetworkKind,
+    /// Type of the full node, selects how node responses are normalized and which RPCs are used.
+    pub(crate) node_type: NodeType,
+    /// Credentials used to authenticate with the full node.
+    pub(crate) node_auth: NodeAuth,
+    /// Block cache shared across the worker pool.
+    pub(crate) block_cache: Arc<BlockCache>,
+    /// Latest chain tip observed from the node, shared across the worker pool.
+    pub(crate) chain_tip: Arc<ChainTip>,
+    /// Lookups the node reported as not found, shared across the worker pool.
+    pub(crate) negative_cache: Arc<NegativeCache>,
+    /// Mempool transactions served by get_mempool_tx, shared across the worker pool.
+    pub(crate) mempool_cache: Arc<MempoolTxCache>,
+    /// Whether get_taddress_txids also returns the address's mempool transactions.
+    pub(crate) taddress_txids_include_mempool: bool,
+    /// Maximum number of transactions fetched from the node at a time by get_taddress_txids.
+    pub(crate) taddress_txids_fetch_concurrency: usize,
+    /// Number of confirmations subtracted from the chain tip returned by get_latest_block.
+    pub(crate) latest_block_confirmations: u32,
+    /// Maximum number of txids accepted in the exclude list of get_mempool_tx.
+    pub(crate) max_mempool_exclude_entries: usize,
+    /// Maximum time since mempool transactions entered the node's mempool, older transactions are not served.
+    pub(crate) max_mempool_tx_age: Option<Duration>,
+    /// Message send_transaction is rejected with while transaction broadcast is disabled.
+    pub(crate) broadcast_disabled: Option<String>,
+    /// Behaviour when fetching a single item of a response stream from the node fails.
+    pub(crate) stream_error_policy: StreamErrorPolicy,
+    /// Number of gRPC streams currently open across the worker pool.
+    pub(crate) open_streams: Arc<AtomicUsize>,
+    /// Maximum number of concurrent gRPC streams allowed across the worker pool.
+    pub(crate) max_concurrent_streams: usize,
+    /// Maximum number of concurrent gRPC streams allowed on a single client connection.
+    pub(crate) max_streams_per_connection: usize,
+    /// RPC latency metrics shared across the worker pool.
+    pub(crate) rpc_metrics: Arc<RpcMetrics>,
+    /// Per-RPC concurrency limits shared across the worker pool.
+    pub(crate) rpc_semaphores: Arc<RpcSemaphores>,
+    /// Per-RPC request size limits.
+    pub(crate) request_size_limits: Arc<RequestSizeLimits>,
+    /// Per client IP address request rate limit shared across the worker pool.
+    pub(crate) ip_rate_limits: Arc<IpRateLimits>,
+    /// Availability of the full node, shared across the worker pool.
+    pub(crate) node_health: Arc<NodeHealth>,
+    /// gRPC compression encodings supported, in order of preference.
+    pub(crate) grpc_compression: Vec<CompressionEncoding>,
+    /// Detail of the request log, shared across the worker pool.
+    pub(crate) request_log_level: SharedRequestLogLevel,
+    /// Keepalive settings, HTTP/2 keepalive is applied to each client connection served.
+    pub(crate) keepalive: Keepalive,
+    /// Maximum time a request waits in the queue, older requests are discarded when dequeued.
+    pub(crate) max_request_age: Option<Duration>,
+}
+
 /// A queue working is the entity that takes requests from the queue and processes them.
 ///
 /// TODO: - Add JsonRpcConnector to worker and pass to underlying RPC services.
@@ -33,14 +139,14 @@ use zcash_client_backend::proto::service::compact_tx_streamer_server::CompactTxS
 pub(crate) struct Worker {
     /// Worker ID.
     _worker_id: usize,
-    /// Used to pop requests from the queue
-    queue: QueueReceiver<ZingoIndexerRequest>,
-    /// Used to requeue requests.
-    requeue: QueueSender<ZingoIndexerRequest>,
-    /// Used to send responses to the nym_dispatcher.
-    nym_response_queue: QueueSender<(Vec<u8>, AnonymousSenderTag)>,
+    /// Used to pop and requeue requests, and to send responses to the nym_dispatcher.
+    queues: WorkerQueues,
     /// gRPC client used for processing requests received over http.
     grpc_client: GrpcClient,
+    /// Settings and state shared across the worker pool.
+    config: WorkerConfig,
+    /// Request currently being served, read by the server if the worker panics.
+    request_context: RequestContext,
     /// Thread safe worker status.
     atomic_status: AtomicStatus,
     /// Represents the Online status of the Worker.
@@ -51,25 +157,38 @@ impl Worker {
     /// Creates a new queue worker.
     pub(crate) async fn spawn(
         _worker_id: usize,
-        queue: QueueReceiver<ZingoIndexerRequest>,
-        requeue: QueueSender<ZingoIndexerRequest>,
-        nym_response_queue: QueueSender<(Vec<u8>, AnonymousSenderTag)>,
-        lightwalletd_uri: Uri,
-        zebrad_uri: Uri,
+        queues: WorkerQueues,
+        config: WorkerConfig,
         atomic_status: AtomicStatus,
         online: Arc<AtomicBool>,
     ) -> Self {
         let grpc_client = GrpcClient {
-            lightwalletd_uri,
-            zebrad_uri,
+            lightwalletd_uri: config.lightwalletd_uri.clone(),
+            zebrad_uri: config.zebrad_uri.clone(),
+            network: config.network,
+            node_type: config.node_type,
+            node_auth: config.node_auth.clone(),
+            block_cache: config.block_cache.clone(),
+            chain_tip: config.chain_tip.clone(),
+            negative_cache: config.negative_cache.clone(),
+            mempool_cache: config.mempool_cache.clone(),
+            rpc_metrics: config.rpc_metrics.clone(),
+            node_health: config.node_health.clone(),
+            taddress_txids_include_mempool: config.taddress_txids_include_mempool,
+            taddress_txids_fetch_concurrency: config.taddress_txids_fetch_concurrency,
+            latest_block_confirmations: config.latest_block_confirmations,
+            max_mempool_exclude_entries: config.max_mempool_exclude_entries,
+            max_mempool_tx_age: config.max_mempool_tx_age,
+            broadcast_disabled: config.broadcast_disabled.clone(),
+            stream_error_policy: config.stream_error_policy,
             online: online.clone(),
         };
         Worker {
             _worker_id,
-            queue,
-            requeue,
-            nym_response_queue,
+            queues,
             grpc_client,
+            config,
+            request_context: RequestContext::default(),
             atomic_status,
             online,
         }
@@ -82,7 +201,38 @@ impl Worker {
         tokio::task::spawn(async move {
             // NOTE: This interval may need to be reduced or removed / moved once scale testing begins.
             let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(100));
-            let svc = CompactTxStreamerServer::new(self.grpc_client.clone());
+            // Compressed messages are checked against their compressed size, the largest limit bounds their decoded size.
+            let mut grpc_server = CompactTxStreamerServer::new(self.grpc_client.clone())
+                .max_decoding_message_size(self.config.request_size_limits.max());
+            for encoding in self.config.grpc_compression.iter().copied() {
+                grpc_server = grpc_server
+                    .accept_compressed(encoding)
+                    .send_compressed(encoding);
+            }
+            let svc = RequestLogger::new(
+                RpcTimer::new(
+                    IpRateLimiter::new(
+                        RequestSizeLimiter::new(
+                            RpcConcurrencyLimiter::new(
+                                ConcurrentStreamLimiter::new(
+                                    PanicRecovery::new(
+                                        grpc_server,
+                                        self.config.rpc_metrics.clone(),
+                                    ),
+                                    self.config.open_streams.clone(),
+                                    self.config.max_concurrent_streams,
+                                ),
+                                self.config.rpc_semaphores.clone(),
+                                self.config.rpc_metrics.clone(),
+                            ),
+                            self.config.request_size_limits.clone(),
+                        ),
+                        self.config.ip_rate_limits.clone(),
+                    ),
+                    self.config.rpc_metrics.clone(),
+                ),
+                self.config.request_log_level.clone(),
+            );
             // TODO: create tonic server here for use within loop.
             self.atomic_status.store(1);
             loop {
@@ -92,13 +242,73 @@ impl Worker {
                             return Ok(());
                         }
                     }
-                    incoming = self.queue.listen() => {
+                    incoming = self.queues.queue.listen() => {
                         match incoming {
                             Ok(request) => {
                                 self.atomic_status.store(2);
+                                let queue_wait = request.duration().unwrap_or_default();
+                                self.config.rpc_metrics.record_queue_wait(queue_wait);
+                                let received_at = Instant::now()
+                                    .checked_sub(queue_wait)
+                                    .unwrap_or_else(Instant::now);
+                                let expired = self
+                                    .config
+                                    .max_request_age
+                                    .is_some_and(|max_request_age| queue_wait > max_request_age);
+                                if expired {
+                                    self.config.rpc_metrics.record_expired_request();
+                                }
+                                let method_name = request.method_name().to_string();
                                     match request {
+                                        ZingoIndexerRequest::TcpServerRequest(request) if expired => {
+                                            eprintln!(
+                                                "TCP connection ({}) expired after {}ms in the request queue, answering with DEADLINE_EXCEEDED.",
+                                                method_name,
+                                                queue_wait.as_millis()
+                                            );
+                                            // Calls sent while queued are answered, then the connection is closed so
+                                            // the client's retries are queued afresh.
+                                            let answered = Arc::new(Notify::new());
+                                            let shutdown = answered.clone();
+                                            Server::builder()
+                                                .add_service(ExpiredRequestRejector::new(
+                                                    svc.clone(),
+                                                    queue_wait,
+                                                    answered,
+                                                ))
+                                                .serve_with_incoming_shutdown(
+                                                    async_stream::stream! {
+                                                        yield Ok::<_, std::io::Error>(
+                                                            request.get_request().get_stream()
+                                                        );
+                                                    },
+                                                    async move {
+                                                        let _ = tokio::time::timeout(
+                                                            EXPIRED_CONNECTION_TIMEOUT,
+                                                            shutdown.notified(),
+                                                        )
+                                                        .await;
+                                                    },
+                                                )
+                                                .await?;
+                                        }
                                         ZingoIndexerRequest::TcpServerRequest(request) => {
-                                            Server::builder().add_service(svc.clone())
+                                            self.request_context.set(format!(
+                                                "TCP connection ({}) queued for {}ms",
+                                                method_name,
+                                                queue_wait.as_millis()
+                                            ));
+                                            // Each request holds a whole client connection, streams opened on it
+                                            // share a fresh per-connection limit.
+                                            self.config.keepalive.apply_http2(Server::builder())
+                                                .add_service(FirstResponseTimer::new(
+                                                    ConcurrentStreamLimiter::per_connection(
+                                                        svc.clone(),
+                                                        self.config.max_streams_per_connection,
+                                                    ),
+                                                    received_at,
+                                                    self.config.rpc_metrics.clone(),
+                                                ))
                                                 .serve_with_incoming( async_stream::stream! {
                                                     yield Ok::<_, std::io::Error>(
                                                         request.get_request().get_stream()
@@ -107,12 +317,37 @@ impl Worker {
                                             )
                                             .await?;
                                         }
+                                        #[cfg(feature = "nym")]
+                                        ZingoIndexerRequest::NymServerRequest(request) if expired => {
+                                            // Nym clients are not sent errors, the request is dropped.
+                                            eprintln!(
+                                                "Nym {} request expired after {}ms in the request queue, discarding.",
+                                                method_name,
+                                                queue_wait.as_millis()
+                                            );
+                                        }
+                                        #[cfg(feature = "nym")]
                                         ZingoIndexerRequest::NymServerRequest(request) => {
-                                            match self.grpc_client
-                                                .process_nym_request(&request)
-                                                .await {
+                                            self.request_context.set(format!(
+                                                "Nym {} request",
+                                                method_name
+                                            ));
+                                            // Nym requests are not served through the RPC middleware, so their calls
+                                            // are recorded here.
+                                            let start = Instant::now();
+                                            let (response, node_time) = with_node_timer(
+                                                self.grpc_client.process_nym_request(&request)
+                                            ).await;
+                                            self.config.rpc_metrics.record(
+                                                short_method_name(&method_name),
+                                                response.is_ok(),
+                                                start.elapsed(),
+                                                node_time,
+                                            );
+                                            match response {
                                                 Ok(response) => {
-                                                    match self.nym_response_queue.try_send((response, request.get_request().metadata())) {
+                                                    self.config.rpc_metrics.record_first_response(received_at.elapsed());
+                                                    match self.queues.nym_response_queue.try_send((response, request.get_request().metadata())) {
                                                         Ok(_) => {}
                                                         Err(QueueError::QueueFull(_request)) => {
                                                             eprintln!("Response Queue Full.");
@@ -135,6 +370,7 @@ impl Worker {
                                             }
                                         }
                                     }
+                                self.request_context.clear();
                                 // NOTE: This may need to be removed for scale use.
                                 if self.check_for_shutdown().await {
                                     self.atomic_status.store(5);
@@ -207,6 +443,11 @@ impl WorkerPoolStatus {
         }
     }
 
+    /// Returns the maximum number of workers the status can track.
+    pub(crate) fn capacity(&self) -> usize {
+        self.statuses.len()
+    }
+
     /// Returns the WorkerPoolStatus.
     pub(crate) fn load(&self) -> WorkerPoolStatus {
         self.workers.load(Ordering::SeqCst);
@@ -224,6 +465,10 @@ pub(crate) struct WorkerPool {
     max_size: u16,
     /// Minimum number of workers kept running on stanby.
     idle_size: u16,
+    /// Queues the workers in the pool receive requests from and send responses to.
+    queues: WorkerQueues,
+    /// Settings and state shared by the workers in the pool, holds the RPC metrics and per-method request counts.
+    config: WorkerConfig,
     /// Workers currently in the pool
     workers: Vec<Worker>,
     /// Status of the workerpool and its workers.
@@ -237,11 +482,8 @@ impl WorkerPool {
     pub(crate) async fn spawn(
         max_size: u16,
         idle_size: u16,
-        queue: QueueReceiver<ZingoIndexerRequest>,
-        _requeue: QueueSender<ZingoIndexerRequest>,
-        nym_response_queue: QueueSender<(Vec<u8>, AnonymousSenderTag)>,
-        lightwalletd_uri: Uri,
-        zebrad_uri: Uri,
+        queues: WorkerQueues,
+        config: WorkerConfig,
         status: WorkerPoolStatus,
         online: Arc<AtomicBool>,
     ) -> Self {
@@ -250,11 +492,8 @@ impl WorkerPool {
             workers.push(
                 Worker::spawn(
                     workers.len(),
-                    queue.clone(),
-                    _requeue.clone(),
-                    nym_response_queue.clone(),
-                    lightwalletd_uri.clone(),
-                    zebrad_uri.clone(),
+                    queues.clone(),
+                    config.clone(),
                     status.statuses[workers.len()].clone(),
                     online.clone(),
                 )
@@ -265,6 +504,8 @@ impl WorkerPool {
         WorkerPool {
             max_size,
             idle_size,
+            queues,
+            config,
             workers,
             status,
             online,
@@ -288,24 +529,45 @@ impl WorkerPool {
             Err(WorkerError::WorkerPoolFull)
         } else {
             let worker_index = self.workers();
-            self.workers.push(
-                Worker::spawn(
-                    worker_index,
-                    self.workers[0].queue.clone(),
-                    self.workers[0].requeue.clone(),
-                    self.workers[0].nym_response_queue.clone(),
-                    self.workers[0].grpc_client.lightwalletd_uri.clone(),
-                    self.workers[0].grpc_client.zebrad_uri.clone(),
-                    self.status.statuses[worker_index].clone(),
-                    self.online.clone(),
-                )
-                .await,
-            );
+            let worker = self.spawn_worker(worker_index).await;
+            self.workers.push(worker);
             self.status.workers.fetch_add(1, Ordering::SeqCst);
             Ok(self.workers[worker_index].clone().serve().await)
         }
     }
 
+    /// Replaces the worker at `worker_index`, whose task has ended, with a new worker and starts it.
+    ///
+    /// The worker's status is set to errored until the new worker starts serving.
+    pub(crate) async fn replace_worker(
+        &mut self,
+        worker_index: usize,
+    ) -> tokio::task::JoinHandle<Result<(), WorkerError>> {
+        self.status.statuses[worker_index].store(6);
+        self.workers[worker_index] = self.spawn_worker(worker_index).await;
+        self.workers[worker_index].clone().serve().await
+    }
+
+    /// Creates a worker for the given index, sharing the configuration and state of the pool.
+    async fn spawn_worker(&self, worker_index: usize) -> Worker {
+        Worker::spawn(
+            worker_index,
+            self.queues.clone(),
+            self.config.clone(),
+            self.status.statuses[worker_index].clone(),
+            self.online.clone(),
+        )
+        .await
+    }
+
+    /// Returns the request context of each worker, indexed by worker ID.
+    pub(crate) fn request_contexts(&self) -> Vec<RequestContext> {
+        self.workers
+            .iter()
+            .map(|worker| worker.request_context.clone())
+            .collect()
+    }
+
     /// Removes a worker from the worker pool, returns error if the pool is already at idle size.
     pub(crate) async fn pop_worker(
         &mut self,
@@ -343,6 +605,12 @@ impl WorkerPool {
         }
     }
 
+    /// Sets the max and idle size of the pool, workers are added or removed by the server as the queue changes.
+    pub(crate) fn resize(&mut self, max_size: u16, idle_size: u16) {
+        self.max_size = max_size;
+        self.idle_size = idle_size;
+    }
+
     /// Returns the max size of the pool
     pub(crate) fn max_size(&self) -> u16 {
         self.max_size
@@ -358,6 +626,16 @@ impl WorkerPool {
         self.workers.len()
     }
 
+    /// Returns the number of gRPC requests served by the pool for each method, keyed by gRPC method name.
+    pub(crate) fn request_counts(&self) -> HashMap<String, u64> {
+        self.config.rpc_metrics.request_counts()
+    }
+
+    /// Returns up to `n` of the most called gRPC methods with their request counts, most called first.
+    pub(crate) fn top_methods(&self, n: usize) -> Vec<(String, u64)> {
+        self.config.rpc_metrics.top_methods(n)
+    }
+
     /// Fetches and returns the status of the workerpool and its workers.
     pub(crate) fn status(&self) -> WorkerPoolStatus {
         self.status.workers.load(Ordering::SeqCst);
@@ -1,5 +1,7 @@
 //! Utility functions for Zingo-RPC.
 
+pub mod ratelimit;
+
 /// Zingo-Indexer build info.
 pub(crate) struct BuildInfo {
     /// Git commit hash.
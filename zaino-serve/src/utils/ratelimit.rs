@@ -0,0 +1,149 @@
+//! Lock-free token bucket rate limiter, backing the per client IP address request rate limit.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+/// Nanoseconds per second, refill rates are given per second.
+const NANOS_PER_SEC: f64 = 1_000_000_000.0;
+
+/// Token bucket holding up to `capacity` tokens, refilled at `refill_rate` tokens per second.
+///
+/// Callers take tokens with [`TokenBucket::try_consume`], which never blocks: a request for more tokens than are
+/// available fails and takes none. The bucket starts full, so bursts of up to `capacity` tokens are allowed.
+///
+/// The bucket is updated with compare-exchange loops rather than a lock and is shared between threads by reference or
+/// `Arc`. Refills are credited in whole tokens, the time towards the next token is carried over so slow refill rates
+/// are not rounded away.
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Maximum number of tokens held.
+    capacity: u64,
+    /// Tokens added per second.
+    refill_rate: f64,
+    /// Tokens currently held.
+    tokens: AtomicU64,
+    /// Time up to which refills have been credited, in nanoseconds since `start`.
+    last_refill: AtomicU64,
+    /// Creation time of the bucket, the epoch of `last_refill`.
+    start: Instant,
+}
+
+impl TokenBucket {
+    /// Returns a full bucket holding up to `capacity` tokens, refilled at `refill_rate` tokens per second.
+    ///
+    /// A `refill_rate` of 0, or a negative or non-finite rate, never refills.
+    pub fn new(capacity: u64, refill_rate: f64) -> Self {
+        TokenBucket {
+            capacity,
+            refill_rate: if refill_rate.is_finite() && refill_rate > 0.0 {
+                refill_rate
+            } else {
+                0.0
+            },
+            tokens: AtomicU64::new(capacity),
+            last_refill: AtomicU64::new(0),
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns the maximum number of tokens held.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Returns the number of tokens added per second.
+    pub fn refill_rate(&self) -> f64 {
+        self.refill_rate
+    }
+
+    /// Returns the number of tokens currently available.
+    pub fn available(&self) -> u64 {
+        self.refill();
+        self.tokens.load(Ordering::Acquire)
+    }
+
+    /// Takes `n` tokens from the bucket, returns false and takes none if fewer than `n` are available.
+    ///
+    /// Taking 0 tokens always succeeds.
+    pub fn try_consume(&self, n: u64) -> bool {
+        self.refill();
+        let mut tokens = self.tokens.load(Ordering::Acquire);
+        loop {
+            let Some(remaining) = tokens.checked_sub(n) else {
+                return false;
+            };
+            match self.tokens.compare_exchange_weak(
+                tokens,
+                remaining,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return true,
+                Err(current) => tokens = current,
+            }
+        }
+    }
+
+    /// Credits the tokens accrued since the last refill, up to `capacity`.
+    ///
+    /// The thread that advances `last_refill` credits the tokens, so each interval is credited once however many
+    /// threads refill concurrently.
+    fn refill(&self) {
+        if self.refill_rate == 0.0 {
+            return;
+        }
+        let now = self.start.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+        let mut last_refill = self.last_refill.load(Ordering::Acquire);
+        loop {
+            if now <= last_refill {
+                return;
+            }
+            let accrued = (now - last_refill) as f64 * self.refill_rate / NANOS_PER_SEC;
+            let (new_tokens, refilled_to) = if accrued >= self.capacity as f64 {
+                // The bucket fills regardless of the remainder, nothing carries over.
+                (self.capacity, now)
+            } else {
+                let new_tokens = accrued as u64;
+                if new_tokens == 0 {
+                    return;
+                }
+                let elapsed = (new_tokens as f64 * NANOS_PER_SEC / self.refill_rate).ceil() as u64;
+                (new_tokens, (last_refill + elapsed).min(now))
+            };
+            match self.last_refill.compare_exchange_weak(
+                last_refill,
+                refilled_to,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    self.add_tokens(new_tokens);
+                    return;
+                }
+                Err(current) => last_refill = current,
+            }
+        }
+    }
+
+    /// Adds `n` tokens, up to `capacity`.
+    fn add_tokens(&self, n: u64) {
+        let mut tokens = self.tokens.load(Ordering::Acquire);
+        loop {
+            let filled = tokens.saturating_add(n).min(self.capacity);
+            if filled == tokens {
+                return;
+            }
+            match self.tokens.compare_exchange_weak(
+                tokens,
+                filled,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return,
+                Err(current) => tokens = current,
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Field by field comparison of compact blocks, for parity and golden file tests.
+//!
+//! [`BlockComparison`] reports every field that differs between two compact blocks by its path in the block, eg.
+//! `vtx[1].outputs[0].cmu`, so a failing test shows what changed rather than two opaque blocks. Fields with known,
+//! intentional differences, such as the `proto_version` reported by different servers, can be ignored.
+
+use std::collections::HashSet;
+
+use zaino_proto::proto::compact_formats::{
+    CompactBlock, CompactOrchardAction, CompactSaplingOutput, CompactSaplingSpend, CompactTx,
+};
+
+/// A field whose value differs between two compact blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDifference {
+    /// Path of the field in the block, eg. `vtx[1].outputs[0].cmu`. List lengths are given as `<list>.len`.
+    pub path: String,
+    /// Value in the expected block, byte fields are hex encoded.
+    pub expected: String,
+    /// Value in the actual block, byte fields are hex encoded.
+    pub actual: String,
+}
+
+/// Differences found between two compact blocks, displayed as a report with a line per differing field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockDiff {
+    /// Differing fields, in field order.
+    pub differences: Vec<FieldDifference>,
+}
+
+impl BlockDiff {
+    /// Returns true if no compared field differs.
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl std::fmt::Display for BlockDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "blocks are equal");
+        }
+        for difference in &self.differences {
+            writeln!(
+                f,
+                "  {}: expected {}, found {}",
+                difference.path, difference.expected, difference.actual
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares compact blocks field by field, skipping ignored fields.
+#[derive(Debug, Clone, Default)]
+pub struct BlockComparison {
+    /// Ignored fields, by path without list indices.
+    ignored: HashSet<String>,
+}
+
+impl BlockComparison {
+    /// Returns a comparison of every field.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ignores differences in `field`, given by its path without list indices, eg. `proto_version` or `vtx.fee`.
+    pub fn ignore(mut self, field: &str) -> Self {
+        self.ignored.insert(field.to_string());
+        self
+    }
+
+    /// Returns the differences between `expected` and `actual`.
+    ///
+    /// Elements of lists are compared pairwise up to the length of the shorter list, a length difference is reported
+    /// as its own field.
+    pub fn diff(&self, expected: &CompactBlock, actual: &CompactBlock) -> BlockDiff {
+        let mut differ = Differ {
+            ignored: &self.ignored,
+            differences: Vec::new(),
+        };
+        differ.value(
+            "proto_version",
+            &expected.proto_version,
+            &actual.proto_version,
+        );
+        differ.value("height", &expected.height, &actual.height);
+        differ.bytes("hash", &expected.hash, &actual.hash);
+        differ.bytes("prev_hash", &expected.prev_hash, &actual.prev_hash);
+        differ.value("time", &expected.time, &actual.time);
+        differ.bytes("header", &expected.header, &actual.header);
+        differ.list("vtx", &expected.vtx, &actual.vtx, Differ::tx);
+        differ.value(
+            "chain_metadata.sapling_commitment_tree_size",
+            &expected
+                .chain_metadata
+                .as_ref()
+                .map(|metadata| metadata.sapling_commitment_tree_size),
+            &actual
+                .chain_metadata
+                .as_ref()
+                .map(|metadata| metadata.sapling_commitment_tree_size),
+        );
+        differ.value(
+            "chain_metadata.orchard_commitment_tree_size",
+            &expected
+                .chain_metadata
+                .as_ref()
+                .map(|metadata| metadata.orchard_commitment_tree_size),
+            &actual
+                .chain_metadata
+                .as_ref()
+                .map(|metadata| metadata.orchard_commitment_tree_size),
+        );
+        BlockDiff {
+            differences: differ.differences,
+        }
+    }
+
+    /// Panics with a report of the differing fields if `expected` and `actual` differ.
+    pub fn assert_eq(&self, expected: &CompactBlock, actual: &CompactBlock) {
+        let diff = self.diff(expected, actual);
+        assert!(
+            diff.is_empty(),
+            "Compact blocks at height {} differ:\n{}",
+            expected.height,
+            diff
+        );
+    }
+}
+
+/// Collects the differences of a single comparison.
+struct Differ<'a> {
+    ignored: &'a HashSet<String>,
+    differences: Vec<FieldDifference>,
+}
+
+impl Differ<'_> {
+    fn tx(&mut self, path: &str, expected: &CompactTx, actual: &CompactTx) {
+        self.value(&format!("{}.index", path), &expected.index, &actual.index);
+        self.bytes(&format!("{}.hash", path), &expected.hash, &actual.hash);
+        self.value(&format!("{}.fee", path), &expected.fee, &actual.fee);
+        self.list(
+            &format!("{}.spends", path),
+            &expected.spends,
+            &actual.spends,
+            Differ::spend,
+        );
+        self.list(
+            &format!("{}.outputs", path),
+            &expected.outputs,
+            &actual.outputs,
+            Differ::output,
+        );
+        self.list(
+            &format!("{}.actions", path),
+            &expected.actions,
+            &actual.actions,
+            Differ::action,
+        );
+    }
+
+    fn spend(&mut self, path: &str, expected: &CompactSaplingSpend, actual: &CompactSaplingSpend) {
+        self.bytes(&format!("{}.nf", path), &expected.nf, &actual.nf);
+    }
+
+    fn output(
+        &mut self,
+        path: &str,
+        expected: &CompactSaplingOutput,
+        actual: &CompactSaplingOutput,
+    ) {
+        self.bytes(&format!("{}.cmu", path), &expected.cmu, &actual.cmu);
+        self.bytes(
+            &format!("{}.ephemeral_key", path),
+            &expected.ephemeral_key,
+            &actual.ephemeral_key,
+        );
+        self.bytes(
+            &format!("{}.ciphertext", path),
+            &expected.ciphertext,
+            &actual.ciphertext,
+        );
+    }
+
+    fn action(
+        &mut self,
+        path: &str,
+        expected: &CompactOrchardAction,
+        actual: &CompactOrchardAction,
+    ) {
+        self.bytes(
+            &format!("{}.nullifier", path),
+            &expected.nullifier,
+            &actual.nullifier,
+        );
+        self.bytes(&format!("{}.cmx", path), &expected.cmx, &actual.cmx);
+        self.bytes(
+            &format!("{}.ephemeral_key", path),
+            &expected.ephemeral_key,
+            &actual.ephemeral_key,
+        );
+        self.bytes(
+            &format!("{}.ciphertext", path),
+            &expected.ciphertext,
+            &actual.ciphertext,
+        );
+    }
+
+    fn list<T>(
+        &mut self,
+        path: &str,
+        expected: &[T],
+        actual: &[T],
+        compare: fn(&mut Self, &str, &T, &T),
+    ) {
+        self.value(&format!("{}.len", path), &expected.len(), &actual.len());
+        for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+            compare(self, &format!("{}[{}]", path, index), expected, actual);
+        }
+    }
+
+    fn value<T: PartialEq + std::fmt::Debug>(&mut self, path: &str, expected: &T, actual: &T) {
+        if expected != actual {
+            self.push(path, format!("{:?}", expected), format!("{:?}", actual));
+        }
+    }
+
+    fn bytes(&mut self, path: &str, expected: &[u8], actual: &[u8]) {
+        if expected != actual {
+            self.push(path, hex::encode(expected), hex::encode(actual));
+        }
+    }
+
+    fn push(&mut self, path: &str, expected: String, actual: String) {
+        if !self.ignored.contains(&field_key(path)) {
+            self.differences.push(FieldDifference {
+                path: path.to_string(),
+                expected,
+                actual,
+            });
+        }
+    }
+}
+
+/// Returns the path of a field without list indices, eg. `vtx.outputs.cmu` for `vtx[1].outputs[0].cmu`.
+fn field_key(path: &str) -> String {
+    let mut depth = 0;
+    path.chars()
+        .filter(|c| match c {
+            '[' => {
+                depth += 1;
+                false
+            }
+            ']' => {
+                depth -= 1;
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect()
+}
@@ -0,0 +1,157 @@
+//! Inspection of validator chain cache directories, for debugging tests that fail on unexpected chain state.
+//!
+//! Chain caches are validator data directories holding a pre-mined regtest chain, kept under
+//! `zaino-testutils/chain_cache/<validator binary name>`. Inspecting a cache reads the directory only, no validator is
+//! launched.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::ValidatorKind;
+
+/// Network magic prefixing each block record in zcashd's regtest block files.
+pub const REGTEST_BLOCK_FILE_MAGIC: [u8; 4] = [0xaa, 0xe8, 0x3f, 0x5f];
+
+/// Size and contents of a chain cache directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainCacheInfo {
+    /// Total size of the files in the directory and its subdirectories.
+    pub total_size_bytes: u64,
+    /// Number of blocks held, counted from the block records of zcashd's `blk*.dat` block files. Caches without block
+    /// files, such as zebrad's RocksDB state, report 0.
+    pub block_count: u32,
+    /// Creation time of the directory, if the platform records it.
+    pub creation_time: Option<SystemTime>,
+}
+
+/// Returns the directory the chain cache of `validator` is kept in.
+pub fn chain_cache_dir(validator: ValidatorKind) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("chain_cache")
+        .join(validator.binary_name())
+}
+
+/// Returns the size, block count and creation time of the chain cache at `path`.
+///
+/// Symbolic links are not followed. Panics if `path` or any directory or block file beneath it can not be read.
+pub fn inspect_chain_cache(path: &Path) -> ChainCacheInfo {
+    try_inspect_chain_cache(path)
+        .unwrap_or_else(|e| panic!("Failed to inspect chain cache {}: {}", path.display(), e))
+}
+
+fn try_inspect_chain_cache(path: &Path) -> std::io::Result<ChainCacheInfo> {
+    let creation_time = std::fs::metadata(path)?.created().ok();
+    let mut info = ChainCacheInfo {
+        total_size_bytes: 0,
+        block_count: 0,
+        creation_time,
+    };
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                info.total_size_bytes += entry.metadata()?.len();
+                if is_block_file(&entry.file_name().to_string_lossy()) {
+                    info.block_count += count_block_records(&entry.path())?;
+                }
+            }
+        }
+    }
+    Ok(info)
+}
+
+/// Returns true if `file_name` names a zcashd block file, eg. `blk00000.dat`.
+fn is_block_file(file_name: &str) -> bool {
+    file_name.starts_with("blk") && file_name.ends_with(".dat")
+}
+
+/// Returns the raw blocks held in the zcashd block files of the chain cache at `path`, in block file order.
+///
+/// Blocks are stored in the order the node received them, which for a chain cache mined by a single regtest node is
+/// height order. Panics if `path` or any directory or block file beneath it can not be read.
+pub fn read_chain_cache_blocks(path: &Path) -> Vec<Vec<u8>> {
+    try_read_chain_cache_blocks(path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read chain cache blocks {}: {}",
+            path.display(),
+            e
+        )
+    })
+}
+
+fn try_read_chain_cache_blocks(path: &Path) -> std::io::Result<Vec<Vec<u8>>> {
+    let mut block_files = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() && is_block_file(&entry.file_name().to_string_lossy()) {
+                block_files.push(entry.path());
+            }
+        }
+    }
+    block_files.sort();
+    let mut blocks = Vec::new();
+    for block_file in block_files {
+        let mut file = BufReader::new(File::open(block_file)?);
+        while let Some(length) = read_record_header(&mut file)? {
+            let mut block = Vec::with_capacity(length as usize);
+            file.by_ref()
+                .take(u64::from(length))
+                .read_to_end(&mut block)?;
+            if block.len() < length as usize {
+                break;
+            }
+            blocks.push(block);
+        }
+    }
+    Ok(blocks)
+}
+
+/// Returns the number of block records in a zcashd block file.
+fn count_block_records(path: &Path) -> std::io::Result<u32> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut count = 0;
+    while let Some(length) = read_record_header(&mut file)? {
+        let skipped = std::io::copy(
+            &mut file.by_ref().take(u64::from(length)),
+            &mut std::io::sink(),
+        )?;
+        if skipped < u64::from(length) {
+            return Ok(count);
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Reads the header of the next block record in a zcashd block file, returning the length of its block, or `None`
+/// after the last record.
+///
+/// Each record is the network magic, the block's length as a little endian u32, then the block. Block files are
+/// preallocated, the zero filled remainder after the last record is skipped.
+fn read_record_header(file: &mut impl Read) -> std::io::Result<Option<u32>> {
+    let mut header = [0u8; 8];
+    match file.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    if header[..4] != REGTEST_BLOCK_FILE_MAGIC {
+        return Ok(None);
+    }
+    Ok(Some(u32::from_le_bytes(
+        header[4..].try_into().expect("slice is 4 bytes"),
+    )))
+}
@@ -0,0 +1,190 @@
+//! Fault-injecting JSON-RPC proxy, for deterministic tests of Zaino's timeout, retry and degradation behaviour.
+//!
+//! A [`FaultProxy`] sits between Zaino and a node, either a validator or a [`MockNode`](crate::MockNode), and
+//! forwards each JSON-RPC call to the node unchanged. Faults are injected per method on demand: calls may be delayed,
+//! or answered with a JSON-RPC error, a raw HTTP response or a dropped connection in place of the node's response.
+//! Clearing a method's faults restores the node's behaviour mid-test.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::mock_node::{read_request, write_response, MockResponse};
+
+/// Faults and recorded calls, shared between the proxy's handle and its server task.
+#[derive(Debug, Default)]
+struct FaultState {
+    /// Responses returned in place of the node's on every call, keyed by method.
+    faults: HashMap<String, MockResponse>,
+    /// Responses returned once each in place of the node's, before the method's standing fault, keyed by method.
+    queued: HashMap<String, VecDeque<MockResponse>>,
+    /// Delays before forwarding or faulting, keyed by method.
+    delays: HashMap<String, Duration>,
+    /// Methods called, in order.
+    calls: Vec<String>,
+}
+
+impl FaultState {
+    /// Records a call of `method` and returns the fault to respond with, `None` to forward the call, and the delay
+    /// before responding.
+    fn fault(&mut self, method: &str) -> (Option<MockResponse>, Option<Duration>) {
+        let fault = self
+            .queued
+            .get_mut(method)
+            .and_then(VecDeque::pop_front)
+            .or_else(|| self.faults.get(method).cloned());
+        let delay = self.delays.get(method).copied();
+        self.calls.push(method.to_string());
+        (fault, delay)
+    }
+}
+
+/// A JSON-RPC proxy on a localhost port, forwarding calls to a node with faults injected on demand.
+///
+/// The proxy serves until it is killed or the test's runtime shuts down.
+#[derive(Debug)]
+pub struct FaultProxy {
+    /// URI the proxy is served at.
+    uri: http::Uri,
+    /// Injected faults and recorded calls.
+    state: Arc<Mutex<FaultState>>,
+    /// Server task.
+    handle: JoinHandle<()>,
+}
+
+impl FaultProxy {
+    /// Spawns a proxy to the node at `node_uri` on a free localhost port. Calls are forwarded unchanged until faults
+    /// are injected.
+    pub async fn spawn(node_uri: &http::Uri) -> Self {
+        let node_addr = node_uri
+            .authority()
+            .expect("node uri must hold a host and port")
+            .to_string();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let state = Arc::new(Mutex::new(FaultState::default()));
+        let server_state = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(
+                    socket,
+                    node_addr.clone(),
+                    server_state.clone(),
+                ));
+            }
+        });
+        FaultProxy { uri, state, handle }
+    }
+
+    /// Returns the URI the proxy is served at, to be used in place of the node's.
+    pub fn uri(&self) -> &http::Uri {
+        &self.uri
+    }
+
+    /// Waits `delay` before forwarding or faulting each call of `method`.
+    pub fn delay(&self, method: &str, delay: Duration) -> &Self {
+        self.lock().delays.insert(method.to_string(), delay);
+        self
+    }
+
+    /// Returns `response` in place of the node's on every call of `method`.
+    pub fn fail(&self, method: &str, response: MockResponse) -> &Self {
+        self.lock().faults.insert(method.to_string(), response);
+        self
+    }
+
+    /// Returns `response` in place of the node's on the next call of `method` only. Faults queued this way are
+    /// returned in order, before the method's standing fault.
+    pub fn fail_once(&self, method: &str, response: MockResponse) -> &Self {
+        self.lock()
+            .queued
+            .entry(method.to_string())
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    /// Removes every fault and delay injected for `method`, so its calls are forwarded unchanged.
+    pub fn clear(&self, method: &str) -> &Self {
+        let mut state = self.lock();
+        state.faults.remove(method);
+        state.queued.remove(method);
+        state.delays.remove(method);
+        self
+    }
+
+    /// Returns the number of calls of `method` received, whether forwarded or faulted.
+    pub fn call_count(&self, method: &str) -> usize {
+        self.lock()
+            .calls
+            .iter()
+            .filter(|call| *call == method)
+            .count()
+    }
+
+    /// Stops the proxy and closes its port, so connections to it are refused. Returns the proxy's URI.
+    pub async fn kill(self) -> http::Uri {
+        self.handle.abort();
+        self.handle.await.ok();
+        self.uri
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FaultState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Answers a single JSON-RPC call on `socket`, with its injected fault or by forwarding it to the node at
+/// `node_addr`, then closes the connection.
+async fn serve_connection(mut socket: TcpStream, node_addr: String, state: Arc<Mutex<FaultState>>) {
+    let Some((head, body)) = read_request(&mut socket).await else {
+        return;
+    };
+    let Ok(request) = serde_json::from_slice::<serde_json::Value>(&body) else {
+        return;
+    };
+    let (fault, delay) = state
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .fault(request["method"].as_str().unwrap_or_default());
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+    match fault {
+        Some(response) => write_response(&mut socket, &request["id"], response).await,
+        // A node that cannot be reached drops the call's connection, as the node itself would.
+        None => {
+            if let Some(response) = forward(&node_addr, &head, &body).await {
+                socket.write_all(&response).await.ok();
+            }
+        }
+    }
+}
+
+/// Sends the request with `head` and `body` to the node at `node_addr` on a new connection and returns the node's
+/// raw HTTP response, or `None` if the node cannot be reached.
+async fn forward(node_addr: &str, head: &str, body: &[u8]) -> Option<Vec<u8>> {
+    let mut node = TcpStream::connect(node_addr).await.ok()?;
+    // The node closes the connection after responding, so its response is read to the end.
+    let mut request = head
+        .lines()
+        .filter(|line| !line.to_lowercase().starts_with("connection:"))
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    request.push_str("\r\nConnection: close\r\n\r\n");
+    node.write_all(request.as_bytes()).await.ok()?;
+    node.write_all(body).await.ok()?;
+    let mut response = Vec::new();
+    node.read_to_end(&mut response).await.ok()?;
+    Some(response)
+}
@@ -0,0 +1,79 @@
+//! Known transaction encodings for tests that need transaction data without running the wallet stack.
+//!
+//! The valid fixtures are well formed NU5 (v5) transactions that [`FullTransaction`] parses completely, built field
+//! by field following the Zcash protocol specification. Their proofs, signatures and note data are deterministic
+//! filler rather than valid cryptographic values, so a node rejects them on submission. Use them for parsing and
+//! error handling tests, tests that need a transaction a node accepts must still build one with the wallet.
+//!
+//! [`FullTransaction`]: zaino_fetch::chain::transaction::FullTransaction
+
+/// A v5 transaction with one transparent input, one P2PKH transparent output of 250_000 zatoshis and no shielded
+/// components.
+pub const VALID_TRANSPARENT_TX_HEX: &str = "\
+    050000800a27a726b4d0d6c2000000000000000001e4f0dc9c4b0a8c2eedfedf5a367b5fb6f4fc9ce210078593565b54\
+    d85378f5aa000000006a579d9a1adcd0795d4e607a59fa69946dfd570fa8f32f238c4f1036fe9569280e7d4c10a82761\
+    9013aa1ab6c9a7626ea5349a60d5ed971b323fcc077a5caf3158330ac0ac3de1f6c7cc8aec94b14c07207a64f9c0fd67\
+    ac9c88e368d7c20a829235c66b1dc4dbd5d080aeffffffff0190d00300000000001976a9148117134bba93770d14dd6a\
+    f28d6c4f1c1e368e3a88ac000000";
+
+/// A v5 transaction with a single Sapling output and no transparent or Orchard components.
+pub const VALID_SAPLING_TX_HEX: &str = "\
+    050000800a27a726b4d0d6c2000000000000000000000001e11389d06bc471dbafbbcdc1b93f0566718d9352dc6d531a\
+    ce76619a253cb8b55eb5b5c561150eb06631bdd6f9f0c286ada032eea50cf65f97d1e92ce83c7827978fbeeb223a0b11\
+    a8d272c8ffc376eb75265d38289e61269e42b19c99577719416f2da8231b9ac7652b8e6157891aa951493e412152903f\
+    cd67fa5068f85a3b6c63cc9347d59e9e50514f26dd60dee66f40a51583d4ae39341fbaf3f276f8d4f7c4f6b848ccbe00\
+    b6a855e032524c229c54c28103c6db6bae8e2c23e02cd3c317cfe161447b273c84626e5588538dc7285451942a919ca5\
+    0625a2fd5d2a9010eb059f857453e0eef3018293d654412d3260cf725d7c5f7da26e09ec2850074246323cbdcbe33a8f\
+    a5ac182e6899ac311ef956d580281bf7945f8999d8fcfa4b998944925db8d07b22fd52075305cb822acde25e7e6f9e3e\
+    da9b7f0a3f3ebc354a642372693f60f3e6f036b4acc334dc76c11cda18e43353c11271bae5b6507a7ebea7f8c5c22d8e\
+    725c49df178e02526ab1e60dce07cde1621d950586d0c38e6a8a80944965301607b4084d9b308d5b94155157117f2eed\
+    d50b787120861d686209e79fe52309b26dfa759ead6b2e6f50fed1325111aede7e0adebad86432e42209dff1e4dcd6ae\
+    e3a88d844521ea17a43ab5700460e9e58639e744ec9c7c4c7810e05a908342ac9549c1f7ab9191652fa3aa88b0d90eea\
+    6e328734e4550dceed0a5b0e4e5a12998b0eca166bc583e05f7864ba4d4ed5e25adb079e2177cdbbe241bf50fc115c1f\
+    4b930e9db309a8afbc1c3c8669197b196f660104ee935082675a6c482778e07c91ea9bfd49db6601031010e0ed87080c\
+    4011ecee8186bfa3743b47859fea40d895ecf688ae17dd6dbf9447426b156c2d281e198b8b0d363714c1af9547ca37e4\
+    be36dd5d2c462b9628923a233d05b9f912418061d0512997d837e5ab5378a6a5c830f8089da0334888ffb1dbf162ab6d\
+    05b18431833de1a2f719792f55118ceedeffe825cd702da34bfe0daeb75a70b662be23b2db49cdcc00d0df4c1634395f\
+    baad9f194c6ffb729949ddf0702ffcfffffffffffeb5f39928d976ccc84fa15693e88a417756bdbc4b68d594c7d8d34b\
+    2900be07365a96c2e26097a4d2698018881ef0342d2d110defdebbbca52c86b8e0f31b60fa65576a67d2310e47289800\
+    87ec8209f90eb326f2991b35cb5b52f74fea19505ddf487d430f976652d33dd911e2e84921f27089d5c8daf66060735e\
+    a08d90570efd7a8448dfed7157c5629285e0626fcc58da3fd0427f11c7ce4ba046fc7d3e785bc858cd23c44ed38a9d37\
+    cd959e4d64c102cfc4b24c7ace299403134715abba88ad8dbf6681d625e9831e16abdce670d5fb34937d92cee3d7b9c9\
+    6f5f69fd3339cdfca3946a14344b3fcec38b82dabea9350ee420c07c734234e11f191d4b00";
+
+/// A v5 transaction with a single Orchard action and no transparent or Sapling components.
+pub const VALID_ORCHARD_TX_HEX: &str = "\
+    050000800a27a726b4d0d6c20000000000000000000000000170a35f70db7b264a92b4849037c0b7811075ddaca2eba4\
+    b1ee14e464e646cdb8a07a166d3daf1eedf556956cbd628f28d1924767d0d8a6d31d0573ae375f1b68be7d2321971cd4\
+    e0ce47ad7fde38ac45ed814ce6cbd8d2f3d81fb8a8fa7d5a3d497cc7cd5e296210cf3cd826b4cf06002e796c921feb34\
+    73e59019c61fbd1fdddcf921d0a7584719c4f2f5946604d2050d4a03927ba60a3442c7b0ddb88b442f14eefd5c5e3ea4\
+    709f1d9f1e2ed79c822675f06915c36124c3af2f3ff5d2e306a44a79b33b3cd4cc4b4c50d359a2a0fd8127ab4982f171\
+    d452b688550b0d5439045922931732b0a7dff030a375d223f3c59fb5304aed6d4f122ee02dae254c91ad422cfa7c6aeb\
+    6219c3df6d26df2f4826d4c6c1296864d5d2471dc947391cbf81d0c71328eb54f3aff0cd07f589a1ee7ae9b1b7f13d9d\
+    85fd65a89cd0a4676920344e8d2026ae008bfe32fb1cdbf0e76b0676e56ac2b1874d1b6d8bad6e3462eb394eb77b6d62\
+    ad3bf4f468af6c8e8d6a07062701964c603be0f9ba27fff8f57777b916c461c07f2c71fbe65f0b2edd5216295908d312\
+    4ad1c584455224ea304f6392b6c2095c132b4734de3c2bce94411a393211cb1d07e148239bc75296696443e67fb547fe\
+    33d65d60da3e9f8590e9a437f57fc20e421ffd0e247cf931399cc8609de6711a9ac4fcbacaa99bc4073897998bbe99ff\
+    789c104a333c6c9c7782acb59649b3b6aee695a286d360db87322169721f0c56d45ddb4e4a9b929166d305109e20ea17\
+    cbba1b970a6920f8b24acaa475596b61a491ee4c40f8484c02541b110b3949518ace76b799acb4f836f83846e0a4c9a1\
+    242fae0571ba0cd6877b362049d9c9f90f30b0330d7f37a228d55e63ac64ffcb771cc28dbc824f2de28a02ff987cef0f\
+    ceedde365a5f4675f9eb5be2ef650867538c88c80fc4e0f0acd54c704fe69d5e0cca987114ffb3b2bcf5a310c0deb3ea\
+    ae44cb9392d12e2308004a9faf1a271fbbf0535a32be5d8c9ac1d5a9eeb2909253256c29c6abc4ff102e02c4d48a896f\
+    6cf2992862be7a25265b185d0e378582d468a1575e5eaddce0cd40cba77b87a8f1958358eb0a51b87cb6d1ff5a97d992\
+    d474390c4290aa688d70174ae1ce1f2693e5daf2e014ee4ed4a9f26f1803702ffcffffffffff4cc605764cb4979dd03d\
+    4d9428800c4324c5255b1ebb633c1677d468e63fa0ecc05361acac2da74477ad4cfc31b41cb429e3a1a5629a89f17417\
+    b1cabc8723c9e60d6e60dc7165cd5e789d4273d4287a683ca46b7d325e342e11969e2102b44e10a59a676a2e1da0eb65\
+    b526686f34646e8821f6df207586830be61809d342345c0208926e76f3dd2fa8192bc421344e875a96d1b6c58bd2a31c\
+    6c85684a9b8a0cf9d9ecdf85d051f34885e075b81c70594835e392775138fe89c438c0ad4947a6adc78bd79972111d8e\
+    fe4b9b8617a99f5bde4b681cf485d1ae366f194f4a8360634f2012dc78516e6c23070e62fa83cd69a7ed46e48f95767b\
+    6979bdc08646662943ea5f87244769588b20fdb08c266405995ae8b0922438780a98b3d569cb5fd5f91b6bc81edab25f\
+    d6904da4556624ad0498e8ffd886db30ecbd8ea7c1f7a833cbd7a8164302550cf2e8d1f358bc4e4f24ace294700af042\
+    62861f71bf84af";
+
+/// [`VALID_TRANSPARENT_TX_HEX`] with the v4 version group id in its v5 header, fails to parse.
+pub const INVALID_TX_HEX: &str = "\
+    0500008085202f89b4d0d6c2000000000000000001e4f0dc9c4b0a8c2eedfedf5a367b5fb6f4fc9ce210078593565b54\
+    d85378f5aa000000006a579d9a1adcd0795d4e607a59fa69946dfd570fa8f32f238c4f1036fe9569280e7d4c10a82761\
+    9013aa1ab6c9a7626ea5349a60d5ed971b323fcc077a5caf3158330ac0ac3de1f6c7cc8aec94b14c07207a64f9c0fd67\
+    ac9c88e368d7c20a829235c66b1dc4dbd5d080aeffffffff0190d00300000000001976a9148117134bba93770d14dd6a\
+    f28d6c4f1c1e368e3a88ac000000";
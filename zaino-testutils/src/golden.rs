@@ -0,0 +1,123 @@
+//! Golden files of compact blocks, guarding the block parser and compact block encoding against silent changes.
+//!
+//! A golden file holds a sequence of compact blocks, a line per block holding the hex of its protobuf encoding.
+//! Golden files are kept under `zaino-testutils/golden`. After an intentional change to the blocks Zaino produces,
+//! regenerate them with
+//!
+//! ```text
+//! ZAINO_UPDATE_GOLDEN=1 cargo test --test integrations golden_compact_blocks
+//! ```
+//!
+//! then review the regenerated files' diff before committing them.
+
+use std::path::{Path, PathBuf};
+
+use prost::Message;
+use zaino_proto::proto::compact_formats::CompactBlock;
+
+use crate::block_diff::BlockComparison;
+
+/// Environment variable that, when set, makes [`assert_golden_blocks`] rewrite golden files instead of checking them.
+pub const UPDATE_GOLDEN_ENV: &str = "ZAINO_UPDATE_GOLDEN";
+
+/// Command regenerating the golden files, included in mismatch reports.
+pub const REGENERATE_GOLDEN_COMMAND: &str =
+    "ZAINO_UPDATE_GOLDEN=1 cargo test --test integrations golden_compact_blocks";
+
+/// Returns the path of the golden file named `name`.
+pub fn golden_file_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("golden")
+        .join(format!("{}.hex", name))
+}
+
+/// Writes `blocks` to the golden file at `path`, creating its directory if needed.
+pub fn write_golden_blocks(path: &Path, blocks: &[CompactBlock]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut contents = String::new();
+    for block in blocks {
+        contents.push_str(&hex::encode(block.encode_to_vec()));
+        contents.push('\n');
+    }
+    std::fs::write(path, contents)
+}
+
+/// Reads the blocks held in the golden file at `path`.
+pub fn read_golden_blocks(path: &Path) -> std::io::Result<Vec<CompactBlock>> {
+    std::fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let bytes = hex::decode(line)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            CompactBlock::decode(bytes.as_slice())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })
+        .collect()
+}
+
+/// Compares `blocks` with the blocks held in the golden file at `path`.
+///
+/// Returns a report of every differing block, or of a missing or unreadable golden file, on mismatch.
+pub fn compare_golden_blocks(
+    path: &Path,
+    blocks: &[CompactBlock],
+    comparison: &BlockComparison,
+) -> Result<(), String> {
+    let golden = read_golden_blocks(path).map_err(|e| {
+        format!(
+            "Failed to read golden file {}: {}\nGenerate it with `{}`.",
+            path.display(),
+            e,
+            REGENERATE_GOLDEN_COMMAND
+        )
+    })?;
+    let mut report = String::new();
+    if golden.len() != blocks.len() {
+        report.push_str(&format!(
+            "Golden file holds {} blocks, {} were produced.\n",
+            golden.len(),
+            blocks.len()
+        ));
+    }
+    for (index, (expected, actual)) in golden.iter().zip(blocks).enumerate() {
+        let diff = comparison.diff(expected, actual);
+        if !diff.is_empty() {
+            report.push_str(&format!(
+                "Block {} (height {}) differs:\n{}",
+                index, expected.height, diff
+            ));
+        }
+    }
+    if report.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Blocks differ from golden file {}:\n{}If the change is intended, regenerate the golden files with `{}`.",
+        path.display(),
+        report,
+        REGENERATE_GOLDEN_COMMAND
+    ))
+}
+
+/// Panics with a report if `blocks` differ from the golden file named `name`.
+///
+/// If [`UPDATE_GOLDEN_ENV`] is set the golden file is rewritten with `blocks` instead.
+pub fn assert_golden_blocks(name: &str, blocks: &[CompactBlock], comparison: &BlockComparison) {
+    let path = golden_file_path(name);
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        write_golden_blocks(&path, blocks)
+            .unwrap_or_else(|e| panic!("Failed to write golden file {}: {}", path.display(), e));
+        println!(
+            "[TEST LOG] Wrote {} blocks to golden file {}.",
+            blocks.len(),
+            path.display()
+        );
+        return;
+    }
+    if let Err(report) = compare_golden_blocks(&path, blocks, comparison) {
+        panic!("{}", report);
+    }
+}
@@ -0,0 +1,108 @@
+//! Builder for gRPC services called directly by tests, without launching a server.
+//!
+//! A [`GrpcClientBuilder`] holds the defaults shared by such tests, a regtest zcashd node with the test node's
+//! credentials, empty caches and default limits, so each test only sets the fields it exercises and new
+//! [`GrpcClient`] fields are defaulted in one place.
+
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use zaino_fetch::{
+    chain::{
+        cache::{BlockCache, ChainTip, NegativeCache},
+        mempool::MempoolTxCache,
+    },
+    jsonrpc::connector::{NodeAuth, NodeType},
+    primitives::chain::NetworkKind,
+};
+use zaino_serve::{
+    rpc::{stream::StreamErrorPolicy, GrpcClient},
+    server::{health::NodeHealth, metrics::RpcMetrics},
+};
+
+/// Builder for a [`GrpcClient`] backed by a node, used to call RPCs directly in tests.
+#[derive(Debug, Clone)]
+pub struct GrpcClientBuilder {
+    /// Client built, holding the defaults until overridden.
+    client: GrpcClient,
+}
+
+impl GrpcClientBuilder {
+    /// Returns a builder for a service backed by the regtest zcashd node at `node_uri`, also used as the lightwalletd
+    /// uri, authenticating with the credentials set in the test manager's zcash.conf, with empty caches, a node
+    /// initially available and transaction broadcast enabled.
+    pub fn new(node_uri: &http::Uri) -> Self {
+        GrpcClientBuilder {
+            client: GrpcClient {
+                lightwalletd_uri: node_uri.clone(),
+                zebrad_uri: node_uri.clone(),
+                network: NetworkKind::Regtest,
+                node_type: NodeType::Zcashd,
+                node_auth: NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+                block_cache: Arc::new(BlockCache::default()),
+                chain_tip: Arc::new(ChainTip::default()),
+                negative_cache: Arc::new(NegativeCache::default()),
+                mempool_cache: Arc::new(MempoolTxCache::default()),
+                rpc_metrics: Arc::new(RpcMetrics::new()),
+                node_health: Arc::new(NodeHealth::default()),
+                taddress_txids_include_mempool: false,
+                taddress_txids_fetch_concurrency: 8,
+                latest_block_confirmations: 0,
+                max_mempool_exclude_entries: 10000,
+                max_mempool_tx_age: None,
+                broadcast_disabled: None,
+                stream_error_policy: StreamErrorPolicy::Terminate,
+                online: Arc::new(AtomicBool::new(true)),
+            },
+        }
+    }
+
+    /// Sets the credentials used to authenticate with the node.
+    pub fn node_auth(mut self, node_auth: NodeAuth) -> Self {
+        self.client.node_auth = node_auth;
+        self
+    }
+
+    /// Sets the block cache, eg. one shared with the test or configured for prefetching.
+    pub fn block_cache(mut self, block_cache: Arc<BlockCache>) -> Self {
+        self.client.block_cache = block_cache;
+        self
+    }
+
+    /// Sets the mempool transaction cache.
+    pub fn mempool_cache(mut self, mempool_cache: Arc<MempoolTxCache>) -> Self {
+        self.client.mempool_cache = mempool_cache;
+        self
+    }
+
+    /// Sets the node health, eg. one shared with a health supervisor run by the test.
+    pub fn node_health(mut self, node_health: Arc<NodeHealth>) -> Self {
+        self.client.node_health = node_health;
+        self
+    }
+
+    /// Sets the maximum age of mempool transactions served, `None` serves all mempool transactions.
+    pub fn max_mempool_tx_age(mut self, max_mempool_tx_age: Option<Duration>) -> Self {
+        self.client.max_mempool_tx_age = max_mempool_tx_age;
+        self
+    }
+
+    /// Disables transaction broadcast if `broadcast_disabled` is set, send_transaction is rejected with the message.
+    pub fn broadcast_disabled(mut self, broadcast_disabled: Option<String>) -> Self {
+        self.client.broadcast_disabled = broadcast_disabled;
+        self
+    }
+
+    /// Sets the online status of the service, shared with the test.
+    pub fn online(mut self, online: Arc<AtomicBool>) -> Self {
+        self.client.online = online;
+        self
+    }
+
+    /// Returns the service.
+    pub fn build(self) -> GrpcClient {
+        self.client
+    }
+}
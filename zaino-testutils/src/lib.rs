@@ -3,10 +3,31 @@
 #![warn(missing_docs)]
 #![forbid(unsafe_code)]
 
+pub mod block_diff;
+pub mod chain_cache;
+pub mod fault_proxy;
+pub mod fixtures;
+pub mod golden;
+pub mod grpc_client;
+pub mod matrix;
+pub mod mock_node;
+
+pub use fault_proxy::FaultProxy;
+pub use grpc_client::GrpcClientBuilder;
+pub use matrix::{TestCase, TestHarness};
+pub use mock_node::{MockNode, MockResponse};
+
 use std::io::Write;
 
 static CTRL_C_ONCE: std::sync::Once = std::sync::Once::new();
 
+/// Seed phrase of the zingolib faucet, twenty three "abandon"s followed by "art".
+pub const ABANDON_ART_SEED: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art";
+
+/// Regtest Orchard unified address derived from [`ABANDON_ART_SEED`], receives the mining rewards unless the test
+/// manager is watch-only.
+pub const REG_O_ADDR_FROM_ABANDONART: &str = "uregtest1zkuzfv5m3yhv2j4fmvq5rjurkxenxyq8r7h4daun2zkznrjaa8ra8asgdm8wwgwjvlwwrxx7347r8w0ee6dqyw4rufw4wg9djwcr6frzkezmdw6dud3wsm99eany5r8wgsctlxquu009nzd6hsme2tcsk0v3sgjvxa70er7h27z5epr67p5q767s2z5gt88paru56mxpm6pwz0cu35m";
+
 /// Configuration data for Zingo-Indexer Tests.
 pub struct TestManager {
     /// Temporary Directory for nym, zcashd and lightwalletd configuration and regtest data.
@@ -16,14 +37,24 @@ pub struct TestManager {
     pub regtest_manager: zingo_testutils::regtest::RegtestManager,
     /// Zingolib regtest network.
     pub regtest_network: zingoconfig::RegtestNetwork,
+    /// Zingo-Indexer gRPC listen IP address.
+    pub indexer_ip: std::net::IpAddr,
     /// Zingo-Indexer gRPC listen port.
     pub indexer_port: u16,
+    /// Zingo-Indexer metrics listen port.
+    pub metrics_port: u16,
+    /// LightWalletD gRPC listen port, lightwalletd is launched by the regtest manager against the same node.
+    pub lightwalletd_port: u16,
     /// Zingo-Indexer Nym listen address.
     pub nym_addr: Option<String>,
     /// Zebrad/Zcashd JsonRpc listen port.
     pub zebrad_port: u16,
     /// Online status of Zingo-Indexer.
     pub online: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Set if the faucet was not funded by mining rewards, see [`TestManagerBuilder::watch_only`].
+    pub watch_only: bool,
+    /// Full node validator the test manager was launched against.
+    pub validator: ValidatorKind,
 }
 
 impl TestManager {
@@ -35,17 +66,494 @@ impl TestManager {
         zingo_testutils::regtest::ChildProcessHandler,
         tokio::task::JoinHandle<Result<(), zainodlib::error::IndexerError>>,
     ) {
+        TestManagerBuilder::new().launch(online).await
+    }
+
+    /// Returns zingo-indexer listen address.
+    pub fn get_indexer_uri(&self) -> http::Uri {
+        http::Uri::builder()
+            .scheme("http")
+            .authority(std::net::SocketAddr::new(self.indexer_ip, self.indexer_port).to_string())
+            .path_and_query("")
+            .build()
+            .unwrap()
+    }
+
+    /// Returns lightwalletd listen address.
+    pub fn get_lightwalletd_uri(&self) -> http::Uri {
+        http::Uri::builder()
+            .scheme("http")
+            .authority(format!("127.0.0.1:{0}", self.lightwalletd_port))
+            .path_and_query("")
+            .build()
+            .unwrap()
+    }
+
+    /// Runs zcash-cli against the test manager's zcashd and returns its trimmed output.
+    ///
+    /// Used for wallet RPCs the JsonRpcConnector does not implement, panics if the command fails.
+    pub fn zcash_cli(&self, args: &[&str]) -> String {
+        let output = self
+            .regtest_manager
+            .get_cli_handle()
+            .args(args)
+            .output()
+            .expect("Failed to run zcash-cli");
+        assert!(
+            output.status.success(),
+            "zcash-cli {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    /// Fetches and returns zingo-indexer's metrics, in the Prometheus text format.
+    pub async fn get_metrics(&self) -> String {
+        fetch_metrics(self.metrics_port).await
+    }
+
+    /// Returns the credentials of the test manager's node, set in its zcash.conf.
+    pub fn node_auth(&self) -> zaino_fetch::jsonrpc::connector::NodeAuth {
+        zaino_fetch::jsonrpc::connector::NodeAuth::user_pass("xxxxxx", "xxxxxx")
+    }
+
+    /// Returns zebrad listen address.
+    pub async fn test_and_return_zebrad_uri(&self) -> http::Uri {
+        zaino_fetch::jsonrpc::connector::test_node_and_return_uri(
+            &self.zebrad_port,
+            &self.node_auth(),
+        )
+        .await
+        .unwrap()
+    }
+
+    /// Builds and returns a JsonRpc client connected to the node.
+    async fn build_node_client(&self) -> zaino_fetch::jsonrpc::connector::JsonRpcConnector {
+        zaino_fetch::jsonrpc::connector::JsonRpcConnector::new(
+            self.test_and_return_zebrad_uri().await,
+            self.node_auth(),
+        )
+        .await
+    }
+
+    /// Queries the node's `getinfo` RPC.
+    async fn get_node_info(&self) -> zaino_fetch::jsonrpc::response::GetInfoResponse {
+        self.build_node_client().await.get_info().await.unwrap()
+    }
+
+    /// Returns the type of full node the test manager is running against.
+    ///
+    /// Used to skip checks of behaviour only one node type supports.
+    pub async fn node_type(&self) -> zaino_fetch::jsonrpc::connector::NodeType {
+        zaino_fetch::jsonrpc::connector::NodeType::detect(&self.get_node_info().await)
+    }
+
+    /// Returns the version of the full node the test manager is running against, as reported by `getinfo`.
+    pub async fn node_version(&self) -> String {
+        self.get_node_info().await.build
+    }
+
+    /// Fetches and returns the consensus branch id reported by zingo-indexer's `GetLightdInfo`.
+    pub async fn get_consensus_branch_id(
+        &self,
+    ) -> zaino_fetch::primitives::chain::ConsensusBranchId {
+        let lightd_info = self
+            .build_grpc_client()
+            .await
+            .get_lightd_info(zaino_proto::proto::service::Empty {})
+            .await
+            .unwrap()
+            .into_inner();
+        lightd_info.consensus_branch_id.parse().unwrap()
+    }
+
+    /// Asserts zingo-indexer reports the consensus branch id of the network upgrade active at the node's chain tip.
+    ///
+    /// The active network upgrade is taken from the activation heights reported by the node's `getblockchaininfo`.
+    pub async fn assert_consensus_branch_id(&self) {
+        let blockchain_info = self
+            .build_node_client()
+            .await
+            .get_blockchain_info()
+            .await
+            .unwrap();
+        let expected = blockchain_info
+            .upgrades
+            .values()
+            .filter(|upgrade| upgrade.activation_height <= blockchain_info.blocks)
+            .max_by_key(|upgrade| upgrade.activation_height)
+            .and_then(|upgrade| upgrade.name.branch_id())
+            .unwrap_or_default();
+        assert_eq!(self.get_consensus_branch_id().await, expected);
+    }
+
+    /// Builds aand returns Zingolib lightclient.
+    pub async fn build_lightclient(&self) -> zingolib::lightclient::LightClient {
+        self.build_lightclient_for_uri(self.get_indexer_uri()).await
+    }
+
+    /// Builds and returns a Zingolib lightclient connected to the indexer at the given URI.
+    ///
+    /// Used to reach indexers not listening on IPv4 localhost, eg. an IPv6 or external listen address.
+    pub async fn build_lightclient_for_uri(
+        &self,
+        indexer_uri: http::Uri,
+    ) -> zingolib::lightclient::LightClient {
+        let mut client_builder = zingo_testutils::scenarios::setup::ClientBuilder::new(
+            indexer_uri,
+            self.temp_conf_dir.path().to_path_buf(),
+        );
+        client_builder
+            .build_faucet(false, self.regtest_network)
+            .await
+    }
+
+    /// Builds and returns a Zingolib lightclient restored from `seed_phrase`, scanning from `birthday`, along with
+    /// its addresses.
+    ///
+    /// Keys are derived deterministically from the seed, so tests can assert on known addresses. Any wallet
+    /// previously built in the client's data directory is overwritten.
+    pub async fn build_lightclient_from_seed(
+        &self,
+        seed_phrase: &str,
+        birthday: u64,
+    ) -> (zingolib::lightclient::LightClient, ClientAddresses) {
+        let mut client_builder = zingo_testutils::scenarios::setup::ClientBuilder::new(
+            self.get_indexer_uri(),
+            self.temp_conf_dir.path().to_path_buf(),
+        );
+        let zingo_client = client_builder
+            .build_client(
+                seed_phrase.to_string(),
+                birthday,
+                true,
+                self.regtest_network,
+            )
+            .await;
+        let addresses = ClientAddresses {
+            unified: get_zingo_address(&zingo_client, "unified").await,
+            sapling: get_zingo_address(&zingo_client, "sapling").await,
+            transparent: get_zingo_address(&zingo_client, "transparent").await,
+        };
+        (zingo_client, addresses)
+    }
+
+    /// Builds and returns a gRPC client connected to zingo-indexer.
+    ///
+    /// Panics if zingo-indexer's gRPC port is unreachable, see [`TestManager::try_build_grpc_client`].
+    pub async fn build_grpc_client(
+        &self,
+    ) -> zaino_proto::proto::service::compact_tx_streamer_client::CompactTxStreamerClient<
+        tonic::transport::Channel,
+    > {
+        self.try_build_grpc_client().await.unwrap()
+    }
+
+    /// Builds and returns a gRPC client connected to zingo-indexer.
+    ///
+    /// Returns an error if zingo-indexer's gRPC port is unreachable.
+    pub async fn try_build_grpc_client(
+        &self,
+    ) -> Result<
+        zaino_proto::proto::service::compact_tx_streamer_client::CompactTxStreamerClient<
+            tonic::transport::Channel,
+        >,
+        String,
+    > {
+        self.try_build_grpc_client_for_uri(self.get_indexer_uri())
+            .await
+    }
+
+    /// Builds and returns a gRPC client connected to the indexer at the given URI.
+    ///
+    /// Returns an error if the URI is unreachable.
+    pub async fn try_build_grpc_client_for_uri(
+        &self,
+        indexer_uri: http::Uri,
+    ) -> Result<
+        zaino_proto::proto::service::compact_tx_streamer_client::CompactTxStreamerClient<
+            tonic::transport::Channel,
+        >,
+        String,
+    > {
+        zaino_proto::proto::service::compact_tx_streamer_client::CompactTxStreamerClient::connect(
+            indexer_uri.clone(),
+        )
+        .await
+        .map_err(|e| {
+            format!(
+                "clients requested but Zaino gRPC port unavailable ({}): {}",
+                indexer_uri, e
+            )
+        })
+    }
+
+    /// Polls zingo-indexer's `get_latest_block` until its chain tip reaches `target`, returning the height reached.
+    ///
+    /// Unlike waiting on the node's height this waits on the indexer's view of the chain, so tests that mine blocks
+    /// can query zingo-indexer for them afterwards. Returns an error if the height is not reached within `timeout`.
+    pub async fn poll_zaino_height(
+        &self,
+        target: u64,
+        timeout: std::time::Duration,
+    ) -> Result<u64, String> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut last_seen = None;
+        loop {
+            if let Ok(mut client) = self.try_build_grpc_client().await {
+                if let Ok(block) = client
+                    .get_latest_block(zaino_proto::proto::service::ChainSpec {})
+                    .await
+                {
+                    let height = block.into_inner().height;
+                    if height >= target {
+                        return Ok(height);
+                    }
+                    last_seen = Some(height);
+                }
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(format!(
+                    "zingo-indexer did not reach height {} within {:?}, last seen height: {:?}",
+                    target, timeout, last_seen
+                ));
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Builds and returns a gRPC client connected to lightwalletd, used to compare zingo-indexer's responses against.
+    pub async fn build_lightwalletd_grpc_client(
+        &self,
+    ) -> zaino_proto::proto::service::compact_tx_streamer_client::CompactTxStreamerClient<
+        tonic::transport::Channel,
+    > {
+        zaino_proto::proto::service::compact_tx_streamer_client::CompactTxStreamerClient::connect(
+            self.get_lightwalletd_uri(),
+        )
+        .await
+        .unwrap()
+    }
+
+    /// Builds a gRPC client connected to zingo-indexer that compresses requests with, and accepts responses
+    /// compressed with, the given encoding.
+    pub async fn build_compressed_grpc_client(
+        &self,
+        compression: zainodlib::config::GrpcCompression,
+    ) -> zaino_proto::proto::service::compact_tx_streamer_client::CompactTxStreamerClient<
+        tonic::transport::Channel,
+    > {
+        self.build_grpc_client()
+            .await
+            .send_compressed(compression.encoding())
+            .accept_compressed(compression.encoding())
+    }
+}
+
+/// Builder for [`TestManager`], used to launch test managers with non-default options.
+#[derive(Debug, Clone, Default)]
+pub struct TestManagerBuilder {
+    /// Launch without funding the faucet.
+    watch_only: bool,
+    /// External address that receives the mining rewards, if given.
+    watch_address: Option<String>,
+    /// Per-RPC concurrency limits passed to zingo-indexer.
+    rpc_concurrency_limits: std::collections::HashMap<String, u16>,
+    /// Per-RPC request size limits passed to zingo-indexer.
+    rpc_max_request_bytes: std::collections::HashMap<String, usize>,
+    /// Maximum wait for a concurrency limited RPC, in milliseconds.
+    rpc_max_wait_ms: Option<u64>,
+    /// gRPC compression encodings supported by zingo-indexer.
+    grpc_compression: Vec<zainodlib::config::GrpcCompression>,
+    /// Return mempool transactions from get_taddress_txids.
+    taddress_txids_include_mempool: bool,
+    /// NU5 (Orchard) activation height, 1 if not set.
+    nu5_activation_height: Option<u32>,
+    /// Maximum concurrent gRPC streams across the server, 5000 if not set.
+    max_concurrent_streams_global: Option<u16>,
+    /// Maximum concurrent gRPC streams on a single client connection.
+    max_concurrent_streams_per_client: Option<u16>,
+    /// Zingo-indexer gRPC listen IP address, IPv4 localhost if not set.
+    listen_ip: Option<std::net::IpAddr>,
+    /// Confirmations subtracted from the tip returned by get_latest_block.
+    latest_block_confirmations: u32,
+    /// Keepalive interval of client connections, in seconds, keepalive is disabled if not set.
+    keepalive_interval_secs: Option<u64>,
+    /// Maximum time a request waits in the request queue, in seconds, 60 if not set.
+    max_request_age_secs: Option<u64>,
+    /// Maximum requests allowed in the request queue, 512 if not set.
+    max_queue_size: Option<u16>,
+    /// Behaviour when a request arrives while the request queue is full.
+    overload_policy: zaino_serve::server::request::OverloadPolicy,
+    /// Maximum and idle worker pool sizes, 96 and 48 if not set.
+    worker_pool_size: Option<(u16, u16)>,
+    /// Full node validator to launch, zcashd if not set.
+    validator: ValidatorKind,
+}
+
+impl TestManagerBuilder {
+    /// Returns a builder for the default [`TestManager`] configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Launches the test manager in watch-only mode.
+    ///
+    /// Mining rewards are not paid to the faucet's address, so the faucet holds no funds and
+    /// clients have no mining rewards to sync. Used by tests that only read chain data.
+    pub fn watch_only(mut self) -> Self {
+        self.watch_only = true;
+        self
+    }
+
+    /// Launches the test manager in watch-only mode, paying mining rewards to the given external address.
+    pub fn read_only_client(mut self, address: &str) -> Self {
+        self.watch_only = true;
+        self.watch_address = Some(address.to_string());
+        self
+    }
+
+    /// Caps the number of concurrent executions of the given RPC, keyed by gRPC method name.
+    pub fn rpc_concurrency_limit(mut self, rpc: &str, max_concurrent: u16) -> Self {
+        self.rpc_concurrency_limits
+            .insert(rpc.to_string(), max_concurrent);
+        self
+    }
+
+    /// Sets the maximum request message size of the given RPC in bytes, keyed by gRPC method name.
+    pub fn rpc_max_request_bytes(mut self, rpc: &str, max_bytes: usize) -> Self {
+        self.rpc_max_request_bytes
+            .insert(rpc.to_string(), max_bytes);
+        self
+    }
+
+    /// Sets the maximum time a request waits for a concurrency limited RPC, in milliseconds.
+    pub fn rpc_max_wait_ms(mut self, max_wait_ms: u64) -> Self {
+        self.rpc_max_wait_ms = Some(max_wait_ms);
+        self
+    }
+
+    /// Enables the given gRPC compression encoding on zingo-indexer, encodings added first are preferred.
+    pub fn grpc_compression(mut self, compression: zainodlib::config::GrpcCompression) -> Self {
+        self.grpc_compression.push(compression);
+        self
+    }
+
+    /// Returns unconfirmed transactions from the mempool in get_taddress_txids.
+    pub fn taddress_txids_include_mempool(mut self) -> Self {
+        self.taddress_txids_include_mempool = true;
+        self
+    }
+
+    /// Sets the NU5 (Orchard) activation height, all other network upgrades activate at height 1.
+    pub fn nu5_activation_height(mut self, height: u32) -> Self {
+        self.nu5_activation_height = Some(height);
+        self
+    }
+
+    /// Sets the IP address zingo-indexer listens for gRPC requests on.
+    pub fn listen_ip(mut self, listen_ip: std::net::IpAddr) -> Self {
+        self.listen_ip = Some(listen_ip);
+        self
+    }
+
+    /// Caps the number of concurrent gRPC streams open across the server.
+    pub fn max_concurrent_streams_global(mut self, max_streams: u16) -> Self {
+        self.max_concurrent_streams_global = Some(max_streams);
+        self
+    }
+
+    /// Caps the number of concurrent gRPC streams a single client connection may open.
+    pub fn max_concurrent_streams_per_client(mut self, max_streams: u16) -> Self {
+        self.max_concurrent_streams_per_client = Some(max_streams);
+        self
+    }
+
+    /// Returns the block `confirmations` below the chain tip from get_latest_block.
+    pub fn latest_block_confirmations(mut self, confirmations: u32) -> Self {
+        self.latest_block_confirmations = confirmations;
+        self
+    }
+
+    /// Enables HTTP/2 and TCP keepalive on client connections, with the given interval in seconds.
+    pub fn keepalive(mut self, interval_secs: u64) -> Self {
+        self.keepalive_interval_secs = Some(interval_secs);
+        self
+    }
+
+    /// Sets the maximum time, in seconds, a request waits in the request queue before being discarded.
+    pub fn max_request_age_secs(mut self, max_age_secs: u64) -> Self {
+        self.max_request_age_secs = Some(max_age_secs);
+        self
+    }
+
+    /// Sets the maximum number of requests allowed in the request queue.
+    pub fn max_queue_size(mut self, max_queue_size: u16) -> Self {
+        self.max_queue_size = Some(max_queue_size);
+        self
+    }
+
+    /// Sets the behaviour when a request arrives while the request queue is full.
+    pub fn overload_policy(
+        mut self,
+        overload_policy: zaino_serve::server::request::OverloadPolicy,
+    ) -> Self {
+        self.overload_policy = overload_policy;
+        self
+    }
+
+    /// Sets the maximum and idle number of workers in zingo-indexer's worker pool.
+    pub fn worker_pool_size(mut self, max_size: u16, idle_size: u16) -> Self {
+        self.worker_pool_size = Some((max_size, idle_size));
+        self
+    }
+
+    /// Sets the full node validator to launch.
+    pub fn validator(mut self, validator: ValidatorKind) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Launches a zingo regtest manager and zingo-indexer, created TempDir for configuration and log files.
+    ///
+    /// Panics if the validator is not supported, see [`ValidatorKind::is_supported`].
+    pub async fn launch(
+        self,
+        online: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    ) -> (
+        TestManager,
+        zingo_testutils::regtest::ChildProcessHandler,
+        tokio::task::JoinHandle<Result<(), zainodlib::error::IndexerError>>,
+    ) {
+        assert!(
+            self.validator.is_supported(),
+            "{} regtest is not supported by the test manager.",
+            self.validator
+        );
         let lwd_port = portpicker::pick_unused_port().expect("No ports free");
         let zebrad_port = portpicker::pick_unused_port().expect("No ports free");
-        let indexer_port = portpicker::pick_unused_port().expect("No ports free");
+        let indexer_ip = self
+            .listen_ip
+            .unwrap_or(std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST));
+        let metrics_port = portpicker::pick_unused_port().expect("No ports free");
 
-        let temp_conf_dir = create_temp_conf_files(lwd_port, zebrad_port).unwrap();
+        let miner_address = if self.watch_only {
+            MinerAddress::External(self.watch_address.clone())
+        } else {
+            MinerAddress::Faucet
+        };
+        let nu5_activation_height = self.nu5_activation_height.unwrap_or(1);
+        let temp_conf_dir =
+            create_temp_conf_files(lwd_port, zebrad_port, &miner_address, nu5_activation_height)
+                .unwrap();
         let temp_conf_path = temp_conf_dir.path().to_path_buf();
         let _nym_conf_path = temp_conf_path.join("nym");
 
         set_custom_drops(online.clone(), Some(temp_conf_path.clone()));
 
-        let regtest_network = zingoconfig::RegtestNetwork::new(1, 1, 1, 1, 1, 1);
+        let regtest_network =
+            zingoconfig::RegtestNetwork::new(1, 1, 1, 1, 1, nu5_activation_height.into());
 
         let regtest_manager = zingo_testutils::regtest::RegtestManager::new(temp_conf_path.clone());
         let regtest_handler = regtest_manager
@@ -55,22 +563,68 @@ impl TestManager {
         // TODO: This turns nym functionality off. for nym tests we will need to add option to include nym in test manager.
         // - queue and workerpool sizes may need to be changed here.
         let indexer_config = zainodlib::config::IndexerConfig {
+            config_version: zainodlib::config::CONFIG_VERSION,
+            network: zaino_fetch::primitives::chain::NetworkKind::Regtest,
+            node_type: zaino_fetch::jsonrpc::connector::NodeTypeSelection::Auto,
             tcp_active: true,
-            listen_port: Some(indexer_port),
+            // Bound to an OS assigned port, read back from the launched server.
+            listen_port: Some(0),
+            listen_ip: indexer_ip,
             nym_active: false,
             nym_conf_path: None,
+            nym_max_client_response_bytes:
+                zaino_serve::server::scheduler::DEFAULT_MAX_CLIENT_RESPONSE_BYTES,
             lightwalletd_port: lwd_port,
             zebrad_port,
-            node_user: Some("xxxxxx".to_string()),
-            node_password: Some("xxxxxx".to_string()),
-            max_queue_size: 512,
-            max_worker_pool_size: 96,
-            idle_worker_pool_size: 48,
+            node_auth: zaino_fetch::jsonrpc::connector::NodeAuth::user_pass("xxxxxx", "xxxxxx"),
+            max_queue_size: self.max_queue_size.unwrap_or(512),
+            queue_warn_threshold: 50,
+            queue_error_threshold: 75,
+            overload_policy: self.overload_policy,
+            max_request_age_secs: self.max_request_age_secs.unwrap_or(60),
+            max_worker_pool_size: self.worker_pool_size.map_or(96, |(max, _)| max),
+            idle_worker_pool_size: self.worker_pool_size.map_or(48, |(_, idle)| idle),
+            max_concurrent_streams_global: self.max_concurrent_streams_global.unwrap_or(5000),
+            max_concurrent_streams_per_client: self.max_concurrent_streams_per_client.unwrap_or(64),
+            max_requests_per_second_per_ip: 0,
+            metrics_port: Some(metrics_port),
+            rpc_concurrency_limits: self.rpc_concurrency_limits.clone(),
+            rpc_max_wait_ms: self.rpc_max_wait_ms.unwrap_or(5000),
+            rpc_max_request_bytes: self.rpc_max_request_bytes.clone(),
+            node_auth_error_policy: zainodlib::config::NodeAuthErrorPolicy::FailFast,
+            node_failure_threshold: 5,
+            node_failure_window_ms: 30000,
+            node_probe_interval_ms: 5000,
+            exit_on_node_loss: false,
+            reset_cache_on_chain_mismatch: false,
+            grpc_compression: self.grpc_compression.clone(),
+            taddress_txids_include_mempool: self.taddress_txids_include_mempool,
+            taddress_txids_fetch_concurrency: 8,
+            latest_block_confirmations: self.latest_block_confirmations,
+            max_mempool_exclude_entries: 10000,
+            mempool_cache_max_bytes: zaino_fetch::chain::mempool::DEFAULT_MEMPOOL_CACHE_MAX_BYTES,
+            max_mempool_tx_age_secs: 0,
+            disable_broadcast: false,
+            disable_broadcast_message: zaino_serve::rpc::DEFAULT_BROADCAST_DISABLED_MESSAGE
+                .to_string(),
+            stream_error_policy: zaino_serve::rpc::stream::StreamErrorPolicy::Terminate,
+            cache_warm_range: None,
+            block_cache_prefetch_window: 20,
+            block_fetch_concurrency: 8,
+            request_log_level: zaino_serve::server::logging::RequestLogLevel::Metadata,
+            runtime_worker_threads: None,
+            keepalive_interval_secs: self.keepalive_interval_secs,
+            keepalive_timeout_secs: None,
+            keepalive_tcp: self.keepalive_interval_secs.is_some(),
+            config_path: None,
         };
-        let indexer_handler =
-            zainodlib::indexer::Indexer::start_indexer_service(indexer_config, online.clone())
+        let (indexer_handler, grpc_addr, _reload_handle) =
+            zainodlib::indexer::Indexer::launch_indexer_service(indexer_config, online.clone())
                 .await
                 .unwrap();
+        let indexer_port = grpc_addr
+            .expect("zingo-indexer launched without a gRPC listener")
+            .port();
         // NOTE: This is required to give the server time to launch, this is not used in production code but could be rewritten to improve testing efficiency.
         tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
         (
@@ -78,46 +632,84 @@ impl TestManager {
                 temp_conf_dir,
                 regtest_manager,
                 regtest_network,
+                indexer_ip,
                 indexer_port,
+                metrics_port,
+                lightwalletd_port: lwd_port,
                 nym_addr: None,
                 zebrad_port,
                 online,
+                watch_only: self.watch_only,
+                validator: self.validator,
             },
             regtest_handler,
             indexer_handler,
         )
     }
+}
 
-    /// Returns zingo-indexer listen address.
-    pub fn get_indexer_uri(&self) -> http::Uri {
-        http::Uri::builder()
-            .scheme("http")
-            .authority(format!("127.0.0.1:{0}", self.indexer_port))
-            .path_and_query("")
-            .build()
-            .unwrap()
+/// Full node validator a [`TestManager`] is launched against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidatorKind {
+    /// Zcashd.
+    #[default]
+    Zcashd,
+    /// Zebrad.
+    Zebrad,
+}
+
+impl ValidatorKind {
+    /// Returns true if the test manager can launch the validator in regtest mode.
+    ///
+    /// Zebrad has not yet implemented regtest mode, so only zcashd is supported.
+    pub fn is_supported(&self) -> bool {
+        matches!(self, ValidatorKind::Zcashd)
     }
 
-    /// Returns zebrad listen address.
-    pub async fn test_and_return_zebrad_uri(&self) -> http::Uri {
-        zaino_fetch::jsonrpc::connector::test_node_and_return_uri(
-            &self.zebrad_port,
-            Some("xxxxxx".to_string()),
-            Some("xxxxxx".to_string()),
-        )
-        .await
-        .unwrap()
+    /// Returns the file name of the validator's binary.
+    pub fn binary_name(&self) -> &'static str {
+        match self {
+            ValidatorKind::Zcashd => "zcashd",
+            ValidatorKind::Zebrad => "zebrad",
+        }
     }
 
-    /// Builds aand returns Zingolib lightclient.
-    pub async fn build_lightclient(&self) -> zingolib::lightclient::LightClient {
-        let mut client_builder = zingo_testutils::scenarios::setup::ClientBuilder::new(
-            self.get_indexer_uri(),
-            self.temp_conf_dir.path().to_path_buf(),
-        );
-        client_builder
-            .build_faucet(false, self.regtest_network)
-            .await
+    /// Returns true if the validator's binary is found in `test_binaries/bins` or on the `PATH`.
+    pub fn binary_available(&self) -> bool {
+        let test_binaries = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_binaries")
+            .join("bins");
+        std::iter::once(test_binaries)
+            .chain(
+                std::env::var_os("PATH")
+                    .iter()
+                    .flat_map(std::env::split_paths),
+            )
+            .any(|dir| dir.join(self.binary_name()).is_file())
+    }
+}
+
+impl std::fmt::Display for ValidatorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidatorKind::Zcashd => write!(f, "zcashd"),
+            ValidatorKind::Zebrad => write!(f, "zebrad"),
+        }
+    }
+}
+
+impl std::str::FromStr for ValidatorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "zcashd" => Ok(ValidatorKind::Zcashd),
+            "zebrad" => Ok(ValidatorKind::Zebrad),
+            _ => Err(format!(
+                "Invalid validator kind: {}, expected zcashd or zebrad.",
+                s
+            )),
+        }
     }
 }
 
@@ -230,7 +822,21 @@ fn write_lightwalletd_yml(
     Ok(())
 }
 
-fn write_zcash_conf(dir: &std::path::Path, rpcport: u16) -> Result<(), Box<dyn std::error::Error>> {
+/// Address written to zcash.conf to receive mining rewards.
+#[derive(Debug, Clone)]
+enum MinerAddress {
+    /// The zingolib faucet's unified address.
+    Faucet,
+    /// An external address, or zcashd's own wallet if none is given.
+    External(Option<String>),
+}
+
+fn write_zcash_conf(
+    dir: &std::path::Path,
+    rpcport: u16,
+    miner_address: &MinerAddress,
+    nu5_activation_height: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
     let file_path = dir.join("zcash.conf");
     let mut file = std::fs::File::create(file_path)?;
     writeln!(file, "regtest=1")?;
@@ -239,7 +845,7 @@ fn write_zcash_conf(dir: &std::path::Path, rpcport: u16) -> Result<(), Box<dyn s
     writeln!(file, "nuparams=2bb40e60:1 # Blossom")?;
     writeln!(file, "nuparams=f5b9230b:1 # Heartwood")?;
     writeln!(file, "nuparams=e9ff75a6:1 # Canopy")?;
-    writeln!(file, "nuparams=c2d6d0b4:1 # NU5")?;
+    writeln!(file, "nuparams=c2d6d0b4:{} # NU5", nu5_activation_height)?;
     writeln!(file, "txindex=1")?;
     writeln!(file, "insightexplorer=1")?;
     writeln!(file, "experimentalfeatures=1")?;
@@ -248,9 +854,18 @@ fn write_zcash_conf(dir: &std::path::Path, rpcport: u16) -> Result<(), Box<dyn s
     writeln!(file, "rpcport={}", rpcport)?;
     writeln!(file, "rpcallowip=127.0.0.1")?;
     writeln!(file, "listen=0")?;
-    writeln!(file, "minetolocalwallet=0")?;
-    // writeln!(file, "mineraddress=zregtestsapling1fmq2ufux3gm0v8qf7x585wj56le4wjfsqsj27zprjghntrerntggg507hxh2ydcdkn7sx8kya7p")?; // USE FOR SAPLING.
-    writeln!(file, "mineraddress=uregtest1zkuzfv5m3yhv2j4fmvq5rjurkxenxyq8r7h4daun2zkznrjaa8ra8asgdm8wwgwjvlwwrxx7347r8w0ee6dqyw4rufw4wg9djwcr6frzkezmdw6dud3wsm99eany5r8wgsctlxquu009nzd6hsme2tcsk0v3sgjvxa70er7h27z5epr67p5q767s2z5gt88paru56mxpm6pwz0cu35m")?;
+    match miner_address {
+        MinerAddress::Faucet => {
+            writeln!(file, "minetolocalwallet=0")?;
+            // writeln!(file, "mineraddress=zregtestsapling1fmq2ufux3gm0v8qf7x585wj56le4wjfsqsj27zprjghntrerntggg507hxh2ydcdkn7sx8kya7p")?; // USE FOR SAPLING.
+            writeln!(file, "mineraddress={}", REG_O_ADDR_FROM_ABANDONART)?;
+        }
+        MinerAddress::External(Some(address)) => {
+            writeln!(file, "minetolocalwallet=0")?;
+            writeln!(file, "mineraddress={}", address)?;
+        }
+        MinerAddress::External(None) => {}
+    }
 
     Ok(())
 }
@@ -258,6 +873,8 @@ fn write_zcash_conf(dir: &std::path::Path, rpcport: u16) -> Result<(), Box<dyn s
 fn create_temp_conf_files(
     lwd_port: u16,
     rpcport: u16,
+    miner_address: &MinerAddress,
+    nu5_activation_height: u32,
 ) -> Result<tempfile::TempDir, Box<dyn std::error::Error>> {
     let temp_dir = tempfile::Builder::new()
         .prefix("zingoindexertest")
@@ -265,10 +882,40 @@ fn create_temp_conf_files(
     let conf_dir = temp_dir.path().join("conf");
     std::fs::create_dir(&conf_dir)?;
     write_lightwalletd_yml(&conf_dir, lwd_port)?;
-    write_zcash_conf(&conf_dir, rpcport)?;
+    write_zcash_conf(&conf_dir, rpcport, miner_address, nu5_activation_height)?;
     Ok(temp_dir)
 }
 
+/// Fetches and returns the metrics served on the given localhost port, in the Prometheus text format.
+pub async fn fetch_metrics(metrics_port: u16) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", metrics_port))
+        .await
+        .expect("Failed to connect to metrics endpoint");
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+        .split_once("\r\n\r\n")
+        .map(|(_headers, body)| body.to_string())
+        .unwrap_or_default()
+}
+
+/// Addresses of a Zingolib lightclient, see [`TestManager::build_lightclient_from_seed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientAddresses {
+    /// Orchard only unified address.
+    pub unified: String,
+    /// Sapling address.
+    pub sapling: String,
+    /// Transparent address.
+    pub transparent: String,
+}
+
 /// Returns the zcash address of the Zingolib::lightclient.
 pub async fn get_zingo_address(
     zingo_client: &zingolib::lightclient::LightClient,
@@ -323,3 +970,53 @@ impl From<zingolib::wallet::Pool> for Pool {
         }
     }
 }
+
+/// Expected ordering of the items returned by a streaming RPC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamOrder {
+    /// Each key is greater than or equal to the previous key.
+    Ascending,
+    /// Each key is less than or equal to the previous key.
+    Descending,
+}
+
+/// Consumes a gRPC response stream, asserting that the keys returned by `key` are monotonic in the given order.
+///
+/// Returns every item received so the caller can make further assertions. Panics on the first out of order item
+/// or if the stream returns an error.
+pub async fn assert_stream_ordered<T, K, F>(
+    mut stream: tonic::Streaming<T>,
+    order: StreamOrder,
+    key: F,
+) -> Vec<T>
+where
+    K: Ord + std::fmt::Debug,
+    F: Fn(&T) -> K,
+{
+    let mut items: Vec<T> = Vec::new();
+    let mut last_key: Option<K> = None;
+    while let Some(item) = stream
+        .message()
+        .await
+        .expect("Stream returned an error status")
+    {
+        let item_key = key(&item);
+        if let Some(last_key) = &last_key {
+            let in_order = match order {
+                StreamOrder::Ascending => item_key >= *last_key,
+                StreamOrder::Descending => item_key <= *last_key,
+            };
+            assert!(
+                in_order,
+                "Stream item {} out of {:?} order: key {:?} followed key {:?}.",
+                items.len(),
+                order,
+                item_key,
+                last_key
+            );
+        }
+        last_key = Some(item_key);
+        items.push(item);
+    }
+    items
+}
@@ -0,0 +1,148 @@
+//! Harness for running a single test body across a matrix of validators.
+//!
+//! Tests are written once, as an async body taking the [`ValidatorKind`] of the case, and the
+//! [`validator_matrix`](crate::validator_matrix) macro generates an individually named `#[tokio::test]` per
+//! validator so CI reports each case separately. Further axes (eg. transports) are added as further cases here.
+
+use std::{
+    future::Future,
+    ops::{Deref, DerefMut},
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use crate::{drop_test_manager, TestManager, TestManagerBuilder, ValidatorKind};
+
+/// A launched test environment that matrix test bodies are written against.
+pub trait TestHarness: Sized {
+    /// Launches the harness against `validator`, with the options set on `builder`.
+    fn launch(builder: TestManagerBuilder, validator: ValidatorKind) -> impl Future<Output = Self>;
+
+    /// Returns the harness's test manager.
+    fn manager(&self) -> &TestManager;
+
+    /// Shuts down the harness, closing child processes and removing its configuration and log files.
+    fn teardown(self) -> impl Future<Output = ()>;
+}
+
+/// A [`TestManager`] launched for a single matrix case, with the handles needed to shut it down.
+///
+/// Derefs to the test manager.
+pub struct TestCase {
+    /// The launched test manager.
+    pub manager: TestManager,
+    /// Handle of zingo-indexer's service task.
+    pub indexer_handler: tokio::task::JoinHandle<Result<(), zainodlib::error::IndexerError>>,
+    /// Handle of the regtest child processes, dropped on teardown.
+    regtest_handler: zingo_testutils::regtest::ChildProcessHandler,
+}
+
+impl TestHarness for TestCase {
+    async fn launch(builder: TestManagerBuilder, validator: ValidatorKind) -> Self {
+        let online = Arc::new(AtomicBool::new(true));
+        let (manager, regtest_handler, indexer_handler) =
+            builder.validator(validator).launch(online).await;
+        TestCase {
+            manager,
+            indexer_handler,
+            regtest_handler,
+        }
+    }
+
+    fn manager(&self) -> &TestManager {
+        &self.manager
+    }
+
+    async fn teardown(self) {
+        drop_test_manager(
+            Some(self.manager.temp_conf_dir.path().to_path_buf()),
+            self.regtest_handler,
+            self.manager.online.clone(),
+        )
+        .await;
+    }
+}
+
+impl Deref for TestCase {
+    type Target = TestManager;
+
+    fn deref(&self) -> &Self::Target {
+        &self.manager
+    }
+}
+
+impl DerefMut for TestCase {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.manager
+    }
+}
+
+/// Runs a single matrix case of `body` against `validator`.
+///
+/// The case is skipped, and passes, if the test manager cannot launch the validator or its binary is not found.
+pub async fn run_case<F, Fut>(validator: ValidatorKind, body: F)
+where
+    F: FnOnce(ValidatorKind) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    if !validator.is_supported() {
+        println!(
+            "[TEST LOG] Skipping case, {} is not supported by the test manager.",
+            validator
+        );
+        return;
+    }
+    if !validator.binary_available() {
+        println!(
+            "[TEST LOG] Skipping case, {} binary not found in test_binaries/bins or on the PATH.",
+            validator.binary_name()
+        );
+        return;
+    }
+    println!("[TEST LOG] Running case against {}.", validator);
+    body(validator).await;
+}
+
+/// Generates a module per test body holding an individually named `#[tokio::test]` for each validator.
+///
+/// Each body is an `async fn` taking the [`ValidatorKind`] of the case, attributes given on the body are applied
+/// to every case. The generated module imports its parent's items.
+///
+/// ```ignore
+/// validator_matrix! {
+///     async fn get_info(validator: ValidatorKind) {
+///         let harness = TestCase::launch(TestManagerBuilder::new(), validator).await;
+///         // ...
+///         harness.teardown().await;
+///     }
+/// }
+/// ```
+///
+/// Generates the tests `get_info::zcashd` and `get_info::zebrad`.
+#[macro_export]
+macro_rules! validator_matrix {
+    ($(
+        $(#[$meta:meta])*
+        async fn $name:ident($validator:ident: ValidatorKind) $body:block
+    )*) => {
+        $(
+            mod $name {
+                #[allow(unused_imports)]
+                use super::*;
+
+                async fn body($validator: $crate::ValidatorKind) $body
+
+                $(#[$meta])*
+                #[tokio::test]
+                async fn zcashd() {
+                    $crate::matrix::run_case($crate::ValidatorKind::Zcashd, body).await;
+                }
+
+                $(#[$meta])*
+                #[tokio::test]
+                async fn zebrad() {
+                    $crate::matrix::run_case($crate::ValidatorKind::Zebrad, body).await;
+                }
+            }
+        )*
+    };
+}
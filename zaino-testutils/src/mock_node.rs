@@ -0,0 +1,361 @@
+//! Programmable in-process JSON-RPC node, for tests of node behaviour that do not need a zcashd or zebrad binary.
+//!
+//! A [`MockNode`] serves JSON-RPC over HTTP on a localhost port, compatible with
+//! [`JsonRpcConnector`](zaino_fetch::jsonrpc::connector::JsonRpcConnector). Responses are registered per method and
+//! may be delayed, JSON-RPC errors, raw HTTP responses or a dropped connection. The node may require credentials, as
+//! zcashd's `rpcuser` and `rpcpassword` do. Every call received is recorded, and killing the node closes its port so
+//! further connections are refused.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use zaino_fetch::jsonrpc::connector::NodeAuth;
+
+/// JSON-RPC error code returned for methods without a registered response.
+pub const METHOD_NOT_FOUND_ERROR_CODE: i32 = -32601;
+
+/// Response of the mock node to a JSON-RPC call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockResponse {
+    /// A successful call, returning the given result.
+    Result(serde_json::Value),
+    /// A JSON-RPC error.
+    Error {
+        /// JSON-RPC error code.
+        code: i32,
+        /// Error message.
+        message: String,
+    },
+    /// A raw HTTP response with the given status code and body, eg. a 401 Unauthorized or a malformed body.
+    Http {
+        /// HTTP status code.
+        status: u16,
+        /// Response body.
+        body: String,
+    },
+    /// The connection is closed without a response.
+    Drop,
+}
+
+impl MockResponse {
+    /// Returns a JSON-RPC error response.
+    pub fn error(code: i32, message: impl Into<String>) -> Self {
+        MockResponse::Error {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Parses a response of the form `{ "result": .. }` or `{ "error": { "code": .., "message": .. } }`.
+    ///
+    /// Panics if `response` holds neither.
+    pub fn from_json(response: &serde_json::Value) -> Self {
+        if let Some(error) = response.get("error") {
+            MockResponse::error(
+                error["code"]
+                    .as_i64()
+                    .expect("error code must be an integer") as i32,
+                error["message"].as_str().unwrap_or_default(),
+            )
+        } else {
+            MockResponse::Result(
+                response
+                    .get("result")
+                    .expect("response must hold a result or an error")
+                    .clone(),
+            )
+        }
+    }
+}
+
+/// A JSON-RPC call received by the mock node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockCall {
+    /// JSON-RPC method called.
+    pub method: String,
+    /// Parameters of the call.
+    pub params: serde_json::Value,
+    /// `Authorization` header the call was sent with, if any.
+    pub authorization: Option<String>,
+    /// Every header the call was sent with, as lowercase name and value pairs in the order received.
+    pub headers: Vec<(String, String)>,
+}
+
+/// Responses and recorded calls, shared between the node's handle and its server task.
+#[derive(Debug, Default)]
+struct MockState {
+    /// Responses returned on every call, keyed by method.
+    responses: HashMap<String, MockResponse>,
+    /// Responses returned once each, before the method's standing response, keyed by method.
+    queued: HashMap<String, VecDeque<MockResponse>>,
+    /// Delays before responding, keyed by method.
+    delays: HashMap<String, Duration>,
+    /// `Authorization` header required of every call, calls without it are answered with 401 Unauthorized.
+    required_authorization: Option<String>,
+    /// Calls received, in order.
+    calls: Vec<MockCall>,
+}
+
+impl MockState {
+    /// Records `call` and returns the response to send and the delay before sending it.
+    fn respond(&mut self, call: MockCall) -> (MockResponse, Option<Duration>) {
+        if self.required_authorization.is_some()
+            && call.authorization != self.required_authorization
+        {
+            self.calls.push(call);
+            return (
+                MockResponse::Http {
+                    status: 401,
+                    body: String::new(),
+                },
+                None,
+            );
+        }
+        let response = self
+            .queued
+            .get_mut(&call.method)
+            .and_then(VecDeque::pop_front)
+            .or_else(|| self.responses.get(&call.method).cloned())
+            .unwrap_or_else(|| {
+                MockResponse::error(METHOD_NOT_FOUND_ERROR_CODE, "Method not found")
+            });
+        let delay = self.delays.get(&call.method).copied();
+        self.calls.push(call);
+        (response, delay)
+    }
+}
+
+/// A JSON-RPC node serving programmed responses on a localhost port.
+///
+/// The node serves until it is killed or the test's runtime shuts down.
+#[derive(Debug)]
+pub struct MockNode {
+    /// URI the node is served at.
+    uri: http::Uri,
+    /// Programmed responses and recorded calls.
+    state: Arc<Mutex<MockState>>,
+    /// Server task.
+    handle: JoinHandle<()>,
+}
+
+impl MockNode {
+    /// Spawns a node on a free localhost port. Methods without a registered response are answered with the node's
+    /// "method not found" error.
+    pub async fn spawn() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let server_state = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(socket, server_state.clone()));
+            }
+        });
+        MockNode { uri, state, handle }
+    }
+
+    /// Spawns a node serving the responses held in `responses`, keyed by method, see [`MockResponse::from_json`].
+    pub async fn with_responses(responses: serde_json::Value) -> Self {
+        let node = Self::spawn().await;
+        for (method, response) in responses
+            .as_object()
+            .expect("responses must be keyed by method")
+        {
+            node.respond(method, MockResponse::from_json(response));
+        }
+        node
+    }
+
+    /// Returns the URI the node is served at.
+    pub fn uri(&self) -> &http::Uri {
+        &self.uri
+    }
+
+    /// Returns `response` on every call of `method`.
+    pub fn respond(&self, method: &str, response: MockResponse) -> &Self {
+        self.lock().responses.insert(method.to_string(), response);
+        self
+    }
+
+    /// Returns `response` on the next call of `method` only. Responses queued this way are returned in order, before
+    /// the method's standing response.
+    pub fn respond_once(&self, method: &str, response: MockResponse) -> &Self {
+        self.lock()
+            .queued
+            .entry(method.to_string())
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    /// Answers calls not authenticated with `auth` with 401 Unauthorized, as a node with `rpcuser` and `rpcpassword`
+    /// set does.
+    pub fn require_auth(&self, auth: &NodeAuth) -> &Self {
+        self.lock().required_authorization = auth
+            .authorization_header()
+            .expect("required credentials must be readable");
+        self
+    }
+
+    /// Waits `delay` before responding to each call of `method`.
+    pub fn delay(&self, method: &str, delay: Duration) -> &Self {
+        self.lock().delays.insert(method.to_string(), delay);
+        self
+    }
+
+    /// Returns the number of calls of `method` received.
+    pub fn call_count(&self, method: &str) -> usize {
+        self.lock()
+            .calls
+            .iter()
+            .filter(|call| call.method == method)
+            .count()
+    }
+
+    /// Returns every call received, in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.lock().calls.clone()
+    }
+
+    /// Stops the node and closes its port, so connections to it are refused. Returns the node's URI.
+    pub async fn kill(self) -> http::Uri {
+        self.handle.abort();
+        self.handle.await.ok();
+        self.uri
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Answers a single JSON-RPC call on `socket`, then closes the connection.
+async fn serve_connection(mut socket: TcpStream, state: Arc<Mutex<MockState>>) {
+    let Some((head, body)) = read_request(&mut socket).await else {
+        return;
+    };
+    let Ok(request) = serde_json::from_slice::<serde_json::Value>(&body) else {
+        return;
+    };
+    let headers = parse_headers(&head);
+    let call = MockCall {
+        method: request["method"].as_str().unwrap_or_default().to_string(),
+        params: request["params"].clone(),
+        authorization: headers
+            .iter()
+            .find(|(name, _)| name == "authorization")
+            .map(|(_, value)| value.clone()),
+        headers,
+    };
+    let (response, delay) = state
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .respond(call);
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+    write_response(&mut socket, &request["id"], response).await;
+}
+
+/// Returns the headers of an HTTP request head as lowercase name and value pairs, in order.
+pub(crate) fn parse_headers(head: &str) -> Vec<(String, String)> {
+    head.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_lowercase(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Writes `response` to the call with JSON-RPC id `id` on `socket`. A [`MockResponse::Drop`] writes nothing, the
+/// connection is closed when `socket` is dropped.
+pub(crate) async fn write_response(
+    socket: &mut TcpStream,
+    id: &serde_json::Value,
+    response: MockResponse,
+) {
+    let (status, body) = match response {
+        MockResponse::Result(result) => (
+            200,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": result,
+                "error": null,
+            })
+            .to_string(),
+        ),
+        MockResponse::Error { code, message } => (
+            200,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": null,
+                "error": { "code": code, "message": message },
+            })
+            .to_string(),
+        ),
+        MockResponse::Http { status, body } => (status, body),
+        MockResponse::Drop => return,
+    };
+    socket
+        .write_all(
+            format!(
+                "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                http::StatusCode::from_u16(status)
+                    .ok()
+                    .and_then(|status| status.canonical_reason())
+                    .unwrap_or("Unknown"),
+                body.len(),
+                body
+            )
+            .as_bytes(),
+        )
+        .await
+        .ok();
+}
+
+/// Reads an HTTP request until the whole body, sized by its content-length header, is received. Returns the request
+/// head and body, or `None` if the connection closes first.
+pub(crate) async fn read_request(socket: &mut TcpStream) -> Option<(String, Vec<u8>)> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = socket.read(&mut buf).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let head = text[..header_end].to_string();
+            let content_length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            let body_start = header_end + 4;
+            if request.len() >= body_start + content_length {
+                return Some((
+                    head,
+                    request[body_start..body_start + content_length].to_vec(),
+                ));
+            }
+        }
+    }
+}
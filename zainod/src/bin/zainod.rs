@@ -2,23 +2,49 @@
 
 use clap::Parser;
 use std::path::PathBuf;
-use zainodlib::{config::load_config, indexer::Indexer};
+use zainodlib::{
+    check::CheckReport,
+    config::{config_file_path, try_load_config},
+    indexer::Indexer,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "zindexer", about = "A server for Zingo-Indexer")]
 struct Args {
-    /// Path to the configuration file
+    /// Path to the configuration file, overridden by the ZAINO_CONFIG environment variable
     #[arg(short, long, value_name = "FILE")]
     config: Option<PathBuf>,
+    /// Print the effective configuration, with secrets redacted, and exit.
+    #[arg(long)]
+    print_config: bool,
+    /// Check the config and the connection to the node, print a report and exit, without serving.
+    ///
+    /// Exits with status 0 if every check passed and 1 otherwise.
+    #[arg(long)]
+    check: bool,
 }
 
-#[tokio::main]
-async fn main() {
-    Indexer::start(load_config(
-        &Args::parse()
-            .config
-            .unwrap_or_else(|| PathBuf::from("./zainod/zindexer.toml")),
-    ))
-    .await
-    .unwrap();
+fn main() {
+    let args = Args::parse();
+    let config_path = config_file_path(args.config);
+    if args.check {
+        let report = tokio::runtime::Runtime::new()
+            .expect("Failed to build runtime")
+            .block_on(CheckReport::for_file(&config_path));
+        println!("{}", report);
+        std::process::exit(report.exit_code());
+    }
+    // An unreadable or invalid config file is fatal, rather than replaced by the default config.
+    let config = match try_load_config(&config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if args.print_config {
+        println!("{}", config.redacted());
+        return;
+    }
+    Indexer::run_blocking(config).unwrap();
 }
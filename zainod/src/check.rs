@@ -0,0 +1,250 @@
+//! Startup self-test, run with `zainod --check`.
+//!
+//! Runs the config validation and node probes zainod runs on startup, without binding any ports or serving requests,
+//! and reports the outcome of each check.
+
+use std::{fmt, path::PathBuf};
+
+use zaino_fetch::{
+    jsonrpc::{
+        connector::{probe_node_uri, JsonRpcConnector},
+        error::JsonRpcConnectorError,
+    },
+    primitives::chain::NetworkKind,
+};
+
+use crate::config::{try_load_config, IndexerConfig, NodeAuthErrorPolicy};
+
+/// Outcome of a single startup check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    /// The check passed.
+    Pass(String),
+    /// The check found a problem that does not prevent zainod from starting.
+    Warn(String),
+    /// The check found a problem that prevents zainod from starting.
+    Fail(String),
+    /// The check was not run.
+    Skip(String),
+}
+
+/// A startup check and its outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    /// Name of the check.
+    pub name: &'static str,
+    /// Outcome of the check.
+    pub outcome: CheckOutcome,
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (label, detail) = match &self.outcome {
+            CheckOutcome::Pass(detail) => ("PASS", detail),
+            CheckOutcome::Warn(detail) => ("WARN", detail),
+            CheckOutcome::Fail(detail) => ("FAIL", detail),
+            CheckOutcome::Skip(detail) => ("SKIP", detail),
+        };
+        write!(f, "[{}] {}: {}", label, self.name, detail)
+    }
+}
+
+/// Human-readable report of the startup self-test.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    /// Checks run, in order.
+    pub checks: Vec<CheckResult>,
+}
+
+impl CheckReport {
+    /// Runs the startup checks against the config file at `config_path`.
+    pub async fn for_file(config_path: &PathBuf) -> Self {
+        match try_load_config(config_path) {
+            Ok(config) => {
+                let mut report = CheckReport::default();
+                report.push(
+                    "config file",
+                    CheckOutcome::Pass(format!("loaded {}", config_path.display())),
+                );
+                report.run(&config).await;
+                report
+            }
+            Err(e) => {
+                let mut report = CheckReport::default();
+                report.push("config file", CheckOutcome::Fail(e.to_string()));
+                report
+            }
+        }
+    }
+
+    /// Runs the startup checks against `config`.
+    pub async fn for_config(config: &IndexerConfig) -> Self {
+        let mut report = CheckReport::default();
+        report.run(config).await;
+        report
+    }
+
+    /// Returns true if no check failed.
+    pub fn passed(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|check| matches!(check.outcome, CheckOutcome::Fail(_)))
+    }
+
+    /// Returns the process exit code for the report, 0 if no check failed and 1 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        if self.passed() {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Returns the outcome of the named check, if it was run.
+    pub fn outcome(&self, name: &str) -> Option<&CheckOutcome> {
+        self.checks
+            .iter()
+            .find(|check| check.name == name)
+            .map(|check| &check.outcome)
+    }
+
+    /// Records the outcome of a check.
+    fn push(&mut self, name: &'static str, outcome: CheckOutcome) {
+        self.checks.push(CheckResult { name, outcome });
+    }
+
+    /// Runs each check against `config`, checks depending on a failed check are skipped.
+    async fn run(&mut self, config: &IndexerConfig) {
+        if let Err(e) = config.check_config() {
+            self.push("config", CheckOutcome::Fail(e.to_string()));
+            for name in [
+                "node connection",
+                "node credentials",
+                "node version",
+                "node chain",
+            ] {
+                self.push(name, CheckOutcome::Skip("config is invalid".to_string()));
+            }
+            return;
+        }
+        self.push("config", CheckOutcome::Pass("valid".to_string()));
+        self.check_nym_conf_path(config);
+
+        let zebrad_uri = match probe_node_uri(&config.zebrad_port, &config.node_auth).await {
+            Ok(uri) => {
+                self.push("node connection", CheckOutcome::Pass(uri.to_string()));
+                uri
+            }
+            Err(e) => {
+                self.push("node connection", CheckOutcome::Fail(e.to_string()));
+                for name in ["node credentials", "node version", "node chain"] {
+                    self.push(name, CheckOutcome::Skip("node is unreachable".to_string()));
+                }
+                return;
+            }
+        };
+
+        // The same getinfo call the server makes before launching its components.
+        let node_connector = JsonRpcConnector::new(zebrad_uri, config.node_auth.clone()).await;
+        match node_connector.get_info().await {
+            Ok(info) => {
+                self.push(
+                    "node credentials",
+                    CheckOutcome::Pass("accepted".to_string()),
+                );
+                self.push(
+                    "node version",
+                    CheckOutcome::Pass(format!("{} (build {})", info.subversion, info.build)),
+                );
+            }
+            Err(JsonRpcConnectorError::Unauthorized) => {
+                let outcome = if config.node_auth_error_policy == NodeAuthErrorPolicy::Degrade {
+                    CheckOutcome::Warn(
+                        "node rejected credentials, zainod will start in a degraded state"
+                            .to_string(),
+                    )
+                } else {
+                    CheckOutcome::Fail("node rejected credentials".to_string())
+                };
+                self.push("node credentials", outcome);
+                for name in ["node version", "node chain"] {
+                    self.push(
+                        name,
+                        CheckOutcome::Skip("node rejected credentials".to_string()),
+                    );
+                }
+                return;
+            }
+            Err(e) => {
+                self.push("node credentials", CheckOutcome::Fail(e.to_string()));
+                for name in ["node version", "node chain"] {
+                    self.push(name, CheckOutcome::Skip("getinfo failed".to_string()));
+                }
+                return;
+            }
+        }
+
+        let outcome = match node_connector.get_blockchain_info().await {
+            Ok(info) => match NetworkKind::from_chain_name(&info.chain) {
+                Some(network) if network == config.network => {
+                    CheckOutcome::Pass(network.chain_name().to_string())
+                }
+                Some(network) => CheckOutcome::Fail(format!(
+                    "node is on {}, config network is {}",
+                    network.chain_name(),
+                    config.network.chain_name()
+                )),
+                None => CheckOutcome::Fail(format!("node reported unknown chain {}", info.chain)),
+            },
+            Err(e) => CheckOutcome::Fail(e.to_string()),
+        };
+        self.push("node chain", outcome);
+    }
+
+    /// Checks the nym client's data directory can be created and written to, if nym is active.
+    fn check_nym_conf_path(&mut self, config: &IndexerConfig) {
+        let Some(nym_conf_path) = config.nym_conf_path.as_ref().filter(|_| config.nym_active)
+        else {
+            self.push(
+                "nym data directory",
+                CheckOutcome::Skip("nym is not active".to_string()),
+            );
+            return;
+        };
+        let probe = PathBuf::from(nym_conf_path).join(".zainod_check");
+        let outcome = std::fs::create_dir_all(nym_conf_path)
+            .and_then(|_| std::fs::write(&probe, b"zainod check"))
+            .and_then(|_| std::fs::remove_file(&probe));
+        self.push(
+            "nym data directory",
+            match outcome {
+                Ok(()) => CheckOutcome::Pass(format!("{} is writable", nym_conf_path)),
+                Err(e) => CheckOutcome::Fail(format!("{} is not writable: {}", nym_conf_path, e)),
+            },
+        );
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{}", check)?;
+        }
+        let failed = self
+            .checks
+            .iter()
+            .filter(|check| matches!(check.outcome, CheckOutcome::Fail(_)))
+            .count();
+        if failed == 0 {
+            write!(f, "Check passed.")
+        } else {
+            write!(
+                f,
+                "Check failed, {} of {} checks failed.",
+                failed,
+                self.checks.len()
+            )
+        }
+    }
+}
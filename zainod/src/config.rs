@@ -1,48 +1,286 @@
 //! Zaino config.
 
 use crate::error::IndexerError;
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+use zaino_fetch::{
+    chain::{
+        cache::DEFAULT_PREFETCH_WINDOW,
+        fetcher::DEFAULT_FETCH_CONCURRENCY,
+        mempool::{DEFAULT_MAX_MEMPOOL_EXCLUDE_ENTRIES, DEFAULT_MEMPOOL_CACHE_MAX_BYTES},
+    },
+    jsonrpc::connector::{NodeAuth, NodeTypeSelection},
+    primitives::chain::NetworkKind,
+};
+use zaino_serve::{
+    rpc::{stream::StreamErrorPolicy, DEFAULT_BROADCAST_DISABLED_MESSAGE},
+    server::{
+        health::{
+            DEFAULT_NODE_FAILURE_THRESHOLD, DEFAULT_NODE_FAILURE_WINDOW,
+            DEFAULT_NODE_PROBE_INTERVAL,
+        },
+        logging::RequestLogLevel,
+        metrics::{DEFAULT_QUEUE_ERROR_THRESHOLD, DEFAULT_QUEUE_WARN_THRESHOLD},
+        request::{OverloadPolicy, DEFAULT_MAX_REQUEST_AGE},
+        scheduler::DEFAULT_MAX_CLIENT_RESPONSE_BYTES,
+    },
+};
+
+pub mod migration;
+
+use migration::migrate_config;
+
+pub use zaino_fetch::primitives::secret::{Secret, REDACTED};
+
+/// Version of the config file format, written as `config_version` in config files.
+///
+/// Bumped when config fields are renamed or restructured, files written for earlier versions are migrated on load,
+/// see [`migration`].
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Behaviour when the node rejects Zaino's credentials at startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum NodeAuthErrorPolicy {
+    /// Refuse to start the server.
+    #[default]
+    FailFast,
+    /// Start the server in a degraded state, reported by the metrics endpoint.
+    Degrade,
+}
+
+/// gRPC message compression encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GrpcCompression {
+    /// Gzip compression.
+    Gzip,
+    /// Zstandard compression.
+    Zstd,
+}
+
+impl GrpcCompression {
+    /// Returns the tonic compression encoding.
+    pub fn encoding(self) -> tonic::codec::CompressionEncoding {
+        match self {
+            GrpcCompression::Gzip => tonic::codec::CompressionEncoding::Gzip,
+            GrpcCompression::Zstd => tonic::codec::CompressionEncoding::Zstd,
+        }
+    }
+}
 
 /// Config information required for Zaino.
-#[derive(Debug, Clone, serde::Deserialize)]
+///
+/// Secret fields are held as [`Secret`]s, or types that redact themselves like [`NodeAuth`], so they are redacted
+/// wherever the config is printed or serialized.
+///
+/// Unknown fields are rejected, deprecated fields are replaced by [`migration::migrate_config`] before deserializing.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct IndexerConfig {
+    /// Version of the config file format, see [`CONFIG_VERSION`].
+    #[serde(default)]
+    pub config_version: u32,
+    /// Network the full node / validator is running on, defaults to Testnet.
+    #[serde(default = "default_network")]
+    pub network: NetworkKind,
+    /// Full node implementation, "Zcashd", "Zebrad" or "Auto" to detect it from the node once at startup.
+    #[serde(default)]
+    pub node_type: NodeTypeSelection,
     /// Sets the TcpIngestor's status.
     pub tcp_active: bool,
-    /// TcpIngestors listen port
+    /// TcpIngestors listen port, defaults to the standard port of the configured network.
     pub listen_port: Option<u16>,
+    /// TcpIngestors listen IP address, IPv4 or IPv6.
+    #[serde(default = "default_listen_ip")]
+    pub listen_ip: IpAddr,
     /// Sets the NymIngestor's and NymDispatchers status.
     pub nym_active: bool,
     /// Nym conf path used for micnet client conf.
     pub nym_conf_path: Option<String>,
+    /// Maximum bytes of responses queued for a single Nym client, further responses to the client are dropped.
+    #[serde(default = "default_nym_max_client_response_bytes")]
+    pub nym_max_client_response_bytes: usize,
     /// LightWalletD listen port [DEPRECATED].
     /// Used by nym_poc and zingo-testutils.
     pub lightwalletd_port: u16,
-    /// Full node / validator listen port.
+    /// Full node / validator listen port, defaults to the standard JsonRPC port of the configured network.
     pub zebrad_port: u16,
-    /// Full node Username.
-    pub node_user: Option<String>,
-    /// full node Password.
-    pub node_password: Option<String>,
+    /// Credentials used to authenticate with the full node, no credentials are sent if unset.
+    #[serde(default)]
+    pub node_auth: NodeAuth,
     /// Maximum requests allowed in the request queue.
     pub max_queue_size: u16,
+    /// Request queue fill percentage at which backpressure warnings are raised.
+    #[serde(default = "default_queue_warn_threshold")]
+    pub queue_warn_threshold: u8,
+    /// Request queue fill percentage at which backpressure errors are raised.
+    #[serde(default = "default_queue_error_threshold")]
+    pub queue_error_threshold: u8,
+    /// Behaviour when a request arrives while the request queue is full, the new request is rejected by default.
+    ///
+    /// `DropOldest` evicts the oldest queued request instead and answers its calls with UNAVAILABLE. Evictions are
+    /// counted by the metrics endpoint.
+    #[serde(default)]
+    pub overload_policy: OverloadPolicy,
+    /// Maximum time, in seconds, a request waits in the request queue, 0 disables the limit.
+    ///
+    /// Requests that waited longer are answered with DEADLINE_EXCEEDED when dequeued rather than served, a server-side
+    /// limit independent of client deadlines that sheds load once the queue backs up.
+    #[serde(default = "default_max_request_age_secs")]
+    pub max_request_age_secs: u64,
     /// Maximum workers allowed in the worker pool
     pub max_worker_pool_size: u16,
     /// Minimum number of workers held in the workerpool when idle.
     pub idle_worker_pool_size: u16,
+    /// Maximum number of concurrent gRPC streams allowed across the server.
+    #[serde(default = "default_max_concurrent_streams_global")]
+    pub max_concurrent_streams_global: u16,
+    /// Maximum number of concurrent gRPC streams allowed on a single client connection.
+    #[serde(default = "default_max_concurrent_streams_per_client")]
+    pub max_concurrent_streams_per_client: u16,
+    /// Maximum requests accepted from a single client IP address per second, 0 disables the limit.
+    ///
+    /// Requests over the limit are rejected with RESOURCE_EXHAUSTED. Requests received over Nym are not limited.
+    #[serde(default)]
+    pub max_requests_per_second_per_ip: u32,
+    /// Metrics endpoint listen port, the endpoint is only served on localhost.
+    pub metrics_port: Option<u16>,
+    /// Maximum concurrent executions of individual RPCs, keyed by gRPC method name (e.g. "GetSubtreeRoots").
+    ///
+    /// RPCs not present are only limited by the worker pool and global stream limit.
+    #[serde(default)]
+    pub rpc_concurrency_limits: HashMap<String, u16>,
+    /// Maximum time, in milliseconds, a request waits for a concurrency limited RPC before failing with RESOURCE_EXHAUSTED.
+    #[serde(default = "default_rpc_max_wait_ms")]
+    pub rpc_max_wait_ms: u64,
+    /// Maximum request message size, in bytes, of individual RPCs, keyed by gRPC method name (e.g. "SendTransaction").
+    ///
+    /// Replaces the default limit of the RPCs present, SendTransaction defaults to the maximum transaction size plus a
+    /// margin and other RPCs to a few KiB. Larger requests are rejected with RESOURCE_EXHAUSTED before being handled.
+    #[serde(default)]
+    pub rpc_max_request_bytes: HashMap<String, usize>,
+    /// Behaviour when the node rejects the configured credentials at startup.
+    #[serde(default)]
+    pub node_auth_error_policy: NodeAuthErrorPolicy,
+    /// Number of consecutive failed node probes after which RPCs that need the node fail with UNAVAILABLE.
+    #[serde(default = "default_node_failure_threshold")]
+    pub node_failure_threshold: u32,
+    /// Window, in milliseconds, the consecutive failed node probes must fall within.
+    #[serde(default = "default_node_failure_window_ms")]
+    pub node_failure_window_ms: u64,
+    /// Delay, in milliseconds, between node probes.
+    #[serde(default = "default_node_probe_interval_ms")]
+    pub node_probe_interval_ms: u64,
+    /// Exit the process once the node is considered unavailable, rather than waiting for it to return.
+    #[serde(default)]
+    pub exit_on_node_loss: bool,
+    /// Clear the block cache if the node is found on a different chain than the cached blocks, eg. after the node is
+    /// replaced, rather than refusing to serve cached data until the process is restarted.
+    #[serde(default)]
+    pub reset_cache_on_chain_mismatch: bool,
+    /// Whether get_taddress_txids also returns unconfirmed transactions from the mempool, with a height of 0.
+    ///
+    /// Requires a zcashd node run with `-insightexplorer`.
+    #[serde(default)]
+    pub taddress_txids_include_mempool: bool,
+    /// Maximum number of transactions fetched from the node at a time by get_taddress_txids, transactions are still
+    /// returned in block order.
+    #[serde(default = "default_taddress_txids_fetch_concurrency")]
+    pub taddress_txids_fetch_concurrency: usize,
+    /// Number of confirmations subtracted from the chain tip returned by get_latest_block, 0 returns the tip.
+    #[serde(default)]
+    pub latest_block_confirmations: u32,
+    /// Maximum number of txids accepted in the exclude list of a get_mempool_tx request.
+    #[serde(default = "default_max_mempool_exclude_entries")]
+    pub max_mempool_exclude_entries: usize,
+    /// Maximum bytes of mempool transactions cached for get_mempool_tx, 0 disables the cache.
+    ///
+    /// Once full, the transactions with the lowest fee rate, then the oldest, are evicted, and fetched from the node
+    /// again when requested.
+    #[serde(default = "default_mempool_cache_max_bytes")]
+    pub mempool_cache_max_bytes: usize,
+    /// Maximum time, in seconds, since a transaction entered the node's mempool for it to be served by get_mempool_tx
+    /// and get_mempool_stream, 0 serves all mempool transactions.
+    ///
+    /// Transactions that linger in the mempool are likely to be dropped, wallets may prefer not to see them. Entry
+    /// times are read from the node's verbose mempool, nodes that do not report them have no transactions filtered.
+    #[serde(default)]
+    pub max_mempool_tx_age_secs: u64,
+    /// Disables transaction broadcast, send_transaction is rejected with FAILED_PRECONDITION while every other RPC is
+    /// served normally.
+    ///
+    /// For read replicas whose transactions are broadcast through another server. Wallets can detect this before
+    /// attempting a spend from the `x-zaino-broadcast-disabled` header of get_lightd_info responses.
+    #[serde(default)]
+    pub disable_broadcast: bool,
+    /// Message send_transaction is rejected with while `disable_broadcast` is set.
+    #[serde(default = "default_disable_broadcast_message")]
+    pub disable_broadcast_message: String,
+    /// Behaviour when the node fails to return a single item of a response stream, eg. one of the transactions of
+    /// get_taddress_txids.
+    #[serde(default)]
+    pub stream_error_policy: StreamErrorPolicy,
+    /// Heights of blocks fetched into the block cache in the background on startup, eg. `{ start = 0, end = 1000 }`.
+    ///
+    /// Readiness is not delayed, warming progress is reported by the metrics endpoint.
+    #[serde(default)]
+    pub cache_warm_range: Option<RangeInclusive<u32>>,
+    /// Number of blocks below the block being streamed by get_block_range that are prefetched into the block cache,
+    /// 0 disables prefetching.
+    #[serde(default = "default_block_cache_prefetch_window")]
+    pub block_cache_prefetch_window: u32,
+    /// Maximum number of blocks fetched from the node at a time by block prefetching and cache warming.
+    #[serde(default = "default_block_fetch_concurrency")]
+    pub block_fetch_concurrency: usize,
+    /// Detail of the request log: "Off", "Metadata" (RPC name, duration, status and sizes) or "Full" (also logs
+    /// addresses, txids and heights).
+    #[serde(default)]
+    pub request_log_level: RequestLogLevel,
+    /// Number of worker threads of the runtime built by `Indexer::run_blocking`, defaults to the number of CPU cores.
+    #[serde(default)]
+    pub runtime_worker_threads: Option<usize>,
+    /// gRPC compression encodings supported, in order of preference.
+    ///
+    /// Responses are compressed with the first encoding the client accepts, compression is disabled if empty.
+    #[serde(default)]
+    pub grpc_compression: Vec<GrpcCompression>,
+    /// Interval between HTTP/2 keepalive pings sent on client connections, also used as the TCP keepalive idle time.
+    /// Pings are not sent if unset.
+    #[serde(default)]
+    pub keepalive_interval_secs: Option<u64>,
+    /// Seconds to wait for a keepalive ping to be acknowledged before closing the connection, defaults to 20.
+    #[serde(default)]
+    pub keepalive_timeout_secs: Option<u64>,
+    /// Enables TCP keepalive probes on client connections.
+    #[serde(default)]
+    pub keepalive_tcp: bool,
+    /// Path of the config file the config was loaded from, re-read on SIGHUP.
+    #[serde(skip)]
+    pub config_path: Option<PathBuf>,
 }
 
 impl IndexerConfig {
     /// Performs checks on config data.
     ///
     /// - Checks that at least 1 of nym or tpc is active.
+    /// - Checks nym is not active if zainod was built without the nym feature.
     /// - Checks listen port is given is tcp is active.
     /// - Checks nym_conf_path is given if nym is active and holds a valid utf8 string.
+    /// - Checks that the listen, lightwalletd and node ports do not conflict.
     pub fn check_config(&self) -> Result<(), IndexerError> {
         if (!self.tcp_active) && (!self.nym_active) {
             return Err(IndexerError::ConfigError(
                 "Cannot start server with no ingestors selected, at least one of either nym or tcp must be set to active in conf.".to_string(),
             ));
         }
+        if self.nym_active && !cfg!(feature = "nym") {
+            return Err(IndexerError::ConfigError(
+                "Nym is active but zainod was built without the nym feature.".to_string(),
+            ));
+        }
         if self.tcp_active && self.listen_port.is_none() {
             return Err(IndexerError::ConfigError(
                 "TCP is active but no address provided.".to_string(),
@@ -61,25 +299,370 @@ impl IndexerConfig {
                 ));
             }
         }
+        if self.zebrad_port == 0 {
+            return Err(IndexerError::ConfigError(
+                "Full node port must be non-zero.".to_string(),
+            ));
+        }
+        if let Some(listen_port) = self.listen_port {
+            if listen_port == self.zebrad_port {
+                return Err(IndexerError::ConfigError(format!(
+                    "Listen port ({}) conflicts with the full node port.",
+                    listen_port
+                )));
+            }
+            if listen_port == self.lightwalletd_port {
+                return Err(IndexerError::ConfigError(format!(
+                    "Listen port ({}) conflicts with the lightwalletd port.",
+                    listen_port
+                )));
+            }
+        }
+        if self.lightwalletd_port == self.zebrad_port {
+            return Err(IndexerError::ConfigError(format!(
+                "Lightwalletd port ({}) conflicts with the full node port.",
+                self.lightwalletd_port
+            )));
+        }
+        if self.queue_warn_threshold == 0
+            || self.queue_warn_threshold >= self.queue_error_threshold
+            || self.queue_error_threshold > 100
+        {
+            return Err(IndexerError::ConfigError(format!(
+                "Queue backpressure thresholds must satisfy 0 < warn ({}) < error ({}) <= 100.",
+                self.queue_warn_threshold, self.queue_error_threshold
+            )));
+        }
+        if self.node_failure_threshold == 0 || self.node_probe_interval_ms == 0 {
+            return Err(IndexerError::ConfigError(
+                "Node failure threshold and node probe interval must be non-zero.".to_string(),
+            ));
+        }
+        if self.block_fetch_concurrency == 0 {
+            return Err(IndexerError::ConfigError(
+                "Block fetch concurrency must be non-zero.".to_string(),
+            ));
+        }
+        if self.taddress_txids_fetch_concurrency == 0 {
+            return Err(IndexerError::ConfigError(
+                "Taddress txids fetch concurrency must be non-zero.".to_string(),
+            ));
+        }
+        if self.keepalive_interval_secs == Some(0) {
+            return Err(IndexerError::ConfigError(
+                "Keepalive interval must be non-zero.".to_string(),
+            ));
+        }
+        if self.keepalive_timeout_secs == Some(0) {
+            return Err(IndexerError::ConfigError(
+                "Keepalive timeout must be non-zero.".to_string(),
+            ));
+        }
+        if self.runtime_worker_threads == Some(0) {
+            return Err(IndexerError::ConfigError(
+                "Runtime worker thread count must be non-zero.".to_string(),
+            ));
+        }
+        if let Some(heights) = &self.cache_warm_range {
+            if heights.is_empty() {
+                return Err(IndexerError::ConfigError(format!(
+                    "Cache warm range start ({}) is above its end ({}).",
+                    heights.start(),
+                    heights.end()
+                )));
+            }
+        }
+        // Probes spanning more than u64::MAX milliseconds can not fit in the window either.
+        let probes_fit_window = self
+            .node_probe_interval_ms
+            .checked_mul(self.node_failure_threshold as u64 - 1)
+            .is_some_and(|probes_duration_ms| probes_duration_ms <= self.node_failure_window_ms);
+        if !probes_fit_window {
+            return Err(IndexerError::ConfigError(format!(
+                "Node failure window ({}ms) is too short to fit {} probes {}ms apart.",
+                self.node_failure_window_ms,
+                self.node_failure_threshold,
+                self.node_probe_interval_ms
+            )));
+        }
+        if let Some((rpc, _)) = self
+            .rpc_concurrency_limits
+            .iter()
+            .find(|(_, max)| **max == 0)
+        {
+            return Err(IndexerError::ConfigError(format!(
+                "Concurrency limit for {} must be non-zero.",
+                rpc
+            )));
+        }
+        if let Some((rpc, _)) = self
+            .rpc_max_request_bytes
+            .iter()
+            .find(|(_, max)| **max == 0)
+        {
+            return Err(IndexerError::ConfigError(format!(
+                "Request size limit for {} must be non-zero.",
+                rpc
+            )));
+        }
+        if let Some(metrics_port) = self.metrics_port {
+            if [
+                Some(self.zebrad_port),
+                Some(self.lightwalletd_port),
+                self.listen_port,
+            ]
+            .contains(&Some(metrics_port))
+            {
+                return Err(IndexerError::ConfigError(format!(
+                    "Metrics port ({}) conflicts with another configured port.",
+                    metrics_port
+                )));
+            }
+        }
         Ok(())
     }
+
+    /// Returns the names of the fields changed in `new` that can not be updated without restarting the indexer.
+    ///
+    /// Only the worker pool sizes, request queue size, request log level and per client IP request rate limit can be
+    /// updated while running, every other field requires a restart, including fields added in future.
+    pub fn restart_required_changes(&self, new: &IndexerConfig) -> Vec<String> {
+        let current = config_table(&self.without_reloadable_fields());
+        let new_table = config_table(&new.without_reloadable_fields());
+        let mut changes: Vec<String> = current
+            .keys()
+            .chain(
+                new_table
+                    .keys()
+                    .filter(|field| !current.contains_key(*field)),
+            )
+            .filter(|field| current.get(*field) != new_table.get(*field))
+            .cloned()
+            .collect();
+        // Credentials are redacted when serialized, so are compared directly.
+        if self.node_auth != new.node_auth && !changes.iter().any(|field| field == "node_auth") {
+            changes.push("node_auth".to_string());
+        }
+        changes
+    }
+
+    /// Returns the config with the fields that can be updated while running, and the fields that do not configure the
+    /// indexer, reset to their defaults.
+    fn without_reloadable_fields(&self) -> IndexerConfig {
+        let defaults = IndexerConfig::default();
+        IndexerConfig {
+            config_version: defaults.config_version,
+            max_queue_size: defaults.max_queue_size,
+            max_worker_pool_size: defaults.max_worker_pool_size,
+            idle_worker_pool_size: defaults.idle_worker_pool_size,
+            max_requests_per_second_per_ip: defaults.max_requests_per_second_per_ip,
+            request_log_level: defaults.request_log_level,
+            config_path: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the effective config with all secret values redacted, for logging and display.
+    pub fn redacted(&self) -> RedactedConfig {
+        RedactedConfig(self.clone())
+    }
+
+    /// Returns the default config for the given network, using that network's standard ports.
+    pub fn default_for_network(network: NetworkKind) -> Self {
+        IndexerConfig {
+            network,
+            listen_port: Some(default_listen_port(network)),
+            zebrad_port: default_node_port(network),
+            ..Default::default()
+        }
+    }
+}
+
+/// Indexer config with all secret values redacted.
+///
+/// Serializes to the same fields as [`IndexerConfig`] and displays as TOML.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(transparent)]
+pub struct RedactedConfig(IndexerConfig);
+
+impl std::fmt::Display for RedactedConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Serialized through a toml::Value so tables are written after plain values, regardless of field order.
+        let value = toml::Value::try_from(self).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", value)
+    }
+}
+
+/// Returns the fields of a config serialized as a TOML table, keyed by field name.
+fn config_table(config: &IndexerConfig) -> toml::value::Table {
+    // A struct always serializes to a table, as relied on when displaying a RedactedConfig.
+    match toml::Value::try_from(config) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => toml::value::Table::new(),
+    }
+}
+
+/// Returns the default network, Testnet, as assumed by config files written before the network was configurable.
+fn default_network() -> NetworkKind {
+    NetworkKind::Testnet
+}
+
+/// Returns the standard full node JsonRPC port for the given network.
+pub fn default_node_port(network: NetworkKind) -> u16 {
+    match network {
+        NetworkKind::Mainnet => 8232,
+        NetworkKind::Testnet | NetworkKind::Regtest => 18232,
+    }
+}
+
+/// Returns the default maximum number of concurrent gRPC streams allowed across the server.
+fn default_max_concurrent_streams_global() -> u16 {
+    5000
+}
+
+/// Returns the default maximum number of concurrent gRPC streams allowed on a single client connection.
+fn default_max_concurrent_streams_per_client() -> u16 {
+    64
+}
+
+/// Returns the default maximum time, in milliseconds, a request waits for a concurrency limited RPC.
+fn default_rpc_max_wait_ms() -> u64 {
+    5000
+}
+
+/// Returns the default request queue fill percentage at which backpressure warnings are raised.
+fn default_queue_warn_threshold() -> u8 {
+    DEFAULT_QUEUE_WARN_THRESHOLD
+}
+
+/// Returns the default request queue fill percentage at which backpressure errors are raised.
+fn default_queue_error_threshold() -> u8 {
+    DEFAULT_QUEUE_ERROR_THRESHOLD
+}
+
+/// Returns the default maximum time, in seconds, a request waits in the request queue.
+fn default_max_request_age_secs() -> u64 {
+    DEFAULT_MAX_REQUEST_AGE.as_secs()
+}
+
+/// Returns the default number of consecutive failed node probes after which the node is considered unavailable.
+fn default_node_failure_threshold() -> u32 {
+    DEFAULT_NODE_FAILURE_THRESHOLD
+}
+
+/// Returns the default window, in milliseconds, the consecutive failed node probes must fall within.
+fn default_node_failure_window_ms() -> u64 {
+    DEFAULT_NODE_FAILURE_WINDOW.as_millis() as u64
+}
+
+/// Returns the default delay, in milliseconds, between node probes.
+fn default_node_probe_interval_ms() -> u64 {
+    DEFAULT_NODE_PROBE_INTERVAL.as_millis() as u64
+}
+
+/// Returns the default TcpIngestor listen IP address, localhost.
+fn default_listen_ip() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::LOCALHOST)
+}
+
+/// Returns the default maximum bytes of responses queued for a single Nym client.
+fn default_nym_max_client_response_bytes() -> usize {
+    DEFAULT_MAX_CLIENT_RESPONSE_BYTES
+}
+
+/// Returns the default maximum number of txids in a get_mempool_tx exclude list.
+fn default_max_mempool_exclude_entries() -> usize {
+    DEFAULT_MAX_MEMPOOL_EXCLUDE_ENTRIES
+}
+
+/// Returns the default message send_transaction is rejected with while transaction broadcast is disabled.
+fn default_disable_broadcast_message() -> String {
+    DEFAULT_BROADCAST_DISABLED_MESSAGE.to_string()
+}
+
+/// Returns the default maximum bytes of mempool transactions cached for get_mempool_tx.
+fn default_mempool_cache_max_bytes() -> usize {
+    DEFAULT_MEMPOOL_CACHE_MAX_BYTES
+}
+
+/// Returns the default number of blocks prefetched by get_block_range.
+fn default_block_cache_prefetch_window() -> u32 {
+    DEFAULT_PREFETCH_WINDOW
+}
+
+/// Returns the default number of blocks fetched from the node at a time.
+fn default_block_fetch_concurrency() -> usize {
+    DEFAULT_FETCH_CONCURRENCY
+}
+
+/// Returns the default number of transactions fetched from the node at a time by get_taddress_txids.
+fn default_taddress_txids_fetch_concurrency() -> usize {
+    DEFAULT_FETCH_CONCURRENCY
+}
+
+/// Returns the default Zaino gRPC listen port for the given network.
+pub fn default_listen_port(network: NetworkKind) -> u16 {
+    match network {
+        NetworkKind::Mainnet => 8137,
+        NetworkKind::Testnet | NetworkKind::Regtest => 18137,
+    }
 }
 
 #[cfg(not(feature = "nym_poc"))]
 impl Default for IndexerConfig {
     fn default() -> Self {
         Self {
+            config_version: CONFIG_VERSION,
+            network: default_network(),
+            node_type: NodeTypeSelection::Auto,
             tcp_active: true,
-            listen_port: Some(8080),
-            nym_active: true,
+            listen_port: Some(default_listen_port(default_network())),
+            listen_ip: default_listen_ip(),
+            nym_active: cfg!(feature = "nym"),
             nym_conf_path: Some("/tmp/indexer/nym".to_string()),
+            nym_max_client_response_bytes: DEFAULT_MAX_CLIENT_RESPONSE_BYTES,
             lightwalletd_port: 9067,
-            zebrad_port: 18232,
-            node_user: Some("xxxxxx".to_string()),
-            node_password: Some("xxxxxx".to_string()),
+            zebrad_port: default_node_port(default_network()),
+            node_auth: NodeAuth::user_pass("xxxxxx", "xxxxxx"),
             max_queue_size: 1024,
+            queue_warn_threshold: DEFAULT_QUEUE_WARN_THRESHOLD,
+            queue_error_threshold: DEFAULT_QUEUE_ERROR_THRESHOLD,
+            overload_policy: OverloadPolicy::RejectNew,
+            max_request_age_secs: DEFAULT_MAX_REQUEST_AGE.as_secs(),
             max_worker_pool_size: 32,
             idle_worker_pool_size: 4,
+            max_concurrent_streams_global: default_max_concurrent_streams_global(),
+            max_concurrent_streams_per_client: 64,
+            max_requests_per_second_per_ip: 0,
+            metrics_port: None,
+            rpc_concurrency_limits: HashMap::new(),
+            rpc_max_wait_ms: default_rpc_max_wait_ms(),
+            rpc_max_request_bytes: HashMap::new(),
+            node_auth_error_policy: NodeAuthErrorPolicy::FailFast,
+            node_failure_threshold: DEFAULT_NODE_FAILURE_THRESHOLD,
+            node_failure_window_ms: DEFAULT_NODE_FAILURE_WINDOW.as_millis() as u64,
+            node_probe_interval_ms: DEFAULT_NODE_PROBE_INTERVAL.as_millis() as u64,
+            exit_on_node_loss: false,
+            reset_cache_on_chain_mismatch: false,
+            grpc_compression: Vec::new(),
+            taddress_txids_include_mempool: false,
+            taddress_txids_fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            latest_block_confirmations: 0,
+            max_mempool_exclude_entries: DEFAULT_MAX_MEMPOOL_EXCLUDE_ENTRIES,
+            mempool_cache_max_bytes: DEFAULT_MEMPOOL_CACHE_MAX_BYTES,
+            max_mempool_tx_age_secs: 0,
+            disable_broadcast: false,
+            disable_broadcast_message: DEFAULT_BROADCAST_DISABLED_MESSAGE.to_string(),
+            stream_error_policy: StreamErrorPolicy::Terminate,
+            cache_warm_range: None,
+            block_cache_prefetch_window: DEFAULT_PREFETCH_WINDOW,
+            block_fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            request_log_level: RequestLogLevel::Metadata,
+            runtime_worker_threads: None,
+            keepalive_interval_secs: None,
+            keepalive_timeout_secs: None,
+            keepalive_tcp: false,
+            config_path: None,
         }
     }
 }
@@ -88,42 +671,172 @@ impl Default for IndexerConfig {
 impl Default for IndexerConfig {
     fn default() -> Self {
         Self {
+            config_version: CONFIG_VERSION,
+            network: default_network(),
+            node_type: NodeTypeSelection::Auto,
             tcp_active: true,
             listen_port: Some(8088),
+            listen_ip: default_listen_ip(),
             nym_active: false,
             nym_conf_path: None,
+            nym_max_client_response_bytes: DEFAULT_MAX_CLIENT_RESPONSE_BYTES,
             lightwalletd_port: 8080,
-            zebrad_port: 18232,
-            node_user: Some("xxxxxx".to_string()),
-            node_password: Some("xxxxxx".to_string()),
+            zebrad_port: default_node_port(default_network()),
+            node_auth: NodeAuth::user_pass("xxxxxx", "xxxxxx"),
             max_queue_size: 1024,
+            queue_warn_threshold: DEFAULT_QUEUE_WARN_THRESHOLD,
+            queue_error_threshold: DEFAULT_QUEUE_ERROR_THRESHOLD,
+            overload_policy: OverloadPolicy::RejectNew,
+            max_request_age_secs: DEFAULT_MAX_REQUEST_AGE.as_secs(),
             max_worker_pool_size: 32,
             idle_worker_pool_size: 4,
+            max_concurrent_streams_global: default_max_concurrent_streams_global(),
+            max_concurrent_streams_per_client: 64,
+            max_requests_per_second_per_ip: 0,
+            metrics_port: None,
+            rpc_concurrency_limits: HashMap::new(),
+            rpc_max_wait_ms: default_rpc_max_wait_ms(),
+            rpc_max_request_bytes: HashMap::new(),
+            node_auth_error_policy: NodeAuthErrorPolicy::FailFast,
+            node_failure_threshold: DEFAULT_NODE_FAILURE_THRESHOLD,
+            node_failure_window_ms: DEFAULT_NODE_FAILURE_WINDOW.as_millis() as u64,
+            node_probe_interval_ms: DEFAULT_NODE_PROBE_INTERVAL.as_millis() as u64,
+            exit_on_node_loss: false,
+            reset_cache_on_chain_mismatch: false,
+            grpc_compression: Vec::new(),
+            taddress_txids_include_mempool: false,
+            taddress_txids_fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            latest_block_confirmations: 0,
+            max_mempool_exclude_entries: DEFAULT_MAX_MEMPOOL_EXCLUDE_ENTRIES,
+            mempool_cache_max_bytes: DEFAULT_MEMPOOL_CACHE_MAX_BYTES,
+            max_mempool_tx_age_secs: 0,
+            disable_broadcast: false,
+            disable_broadcast_message: DEFAULT_BROADCAST_DISABLED_MESSAGE.to_string(),
+            stream_error_policy: StreamErrorPolicy::Terminate,
+            cache_warm_range: None,
+            block_cache_prefetch_window: DEFAULT_PREFETCH_WINDOW,
+            block_fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            request_log_level: RequestLogLevel::Metadata,
+            runtime_worker_threads: None,
+            keepalive_interval_secs: None,
+            keepalive_timeout_secs: None,
+            keepalive_tcp: false,
+            config_path: None,
         }
     }
 }
 
-/// Attempts to load config data from a toml file at the specified path.
-pub fn load_config(file_path: &std::path::PathBuf) -> IndexerConfig {
-    let mut config = IndexerConfig::default();
-
-    if let Ok(contents) = std::fs::read_to_string(file_path) {
-        if let Ok(parsed_config) = toml::from_str::<IndexerConfig>(&contents) {
-            config = IndexerConfig {
-                tcp_active: parsed_config.tcp_active,
-                listen_port: parsed_config.listen_port.or(config.listen_port),
-                nym_active: parsed_config.nym_active,
-                nym_conf_path: parsed_config.nym_conf_path.or(config.nym_conf_path),
-                lightwalletd_port: parsed_config.lightwalletd_port,
-                zebrad_port: parsed_config.zebrad_port,
-                node_user: parsed_config.node_user.or(config.node_user),
-                node_password: parsed_config.node_password.or(config.node_password),
-                max_queue_size: parsed_config.max_queue_size,
-                max_worker_pool_size: parsed_config.max_worker_pool_size,
-                idle_worker_pool_size: parsed_config.idle_worker_pool_size,
-            };
-        }
-    }
+/// Environment variable holding the config file path, takes priority over the `--config` arg.
+pub const CONFIG_PATH_ENV: &str = "ZAINO_CONFIG";
+
+/// Config file path used when neither `ZAINO_CONFIG` nor `--config` is set.
+pub const DEFAULT_CONFIG_PATH: &str = "./zainod/zindexer.toml";
 
-    config
+/// Returns the path of the config file to load, in order of priority:
+/// 1. the `ZAINO_CONFIG` environment variable, if set and non-empty,
+/// 2. `config_arg`, the `--config` arg,
+/// 3. [`DEFAULT_CONFIG_PATH`].
+pub fn config_file_path(config_arg: Option<PathBuf>) -> PathBuf {
+    std::env::var_os(CONFIG_PATH_ENV)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or(config_arg)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH))
+}
+
+/// Loads config data from a toml file at the specified path, returning an error if the file can not be read or parsed.
+///
+/// Files written for an earlier [`CONFIG_VERSION`] are migrated, a warning naming the change to make is printed for
+/// each deprecated setting replaced. Unset ports default to the standard ports of the configured network.
+pub fn try_load_config(file_path: &std::path::PathBuf) -> Result<IndexerConfig, IndexerError> {
+    let config = IndexerConfig::default();
+    let contents = std::fs::read_to_string(file_path).map_err(|e| {
+        IndexerError::ConfigError(format!(
+            "Failed to read config file {}: {}",
+            file_path.display(),
+            e
+        ))
+    })?;
+    let parse_error = |e: toml::de::Error| {
+        IndexerError::ConfigError(format!(
+            "Failed to parse config file {}: {}",
+            file_path.display(),
+            e
+        ))
+    };
+    let mut table = toml::from_str::<toml::value::Table>(&contents).map_err(parse_error)?;
+    for deprecation in migrate_config(&mut table)? {
+        eprintln!(
+            "Warning: config file {}: {}.",
+            file_path.display(),
+            deprecation
+        );
+    }
+    // Ports left unset default to the standard ports of the configured network, rather than of the default network.
+    let network: NetworkKind = match table.get("network") {
+        Some(network) => network.clone().try_into().map_err(parse_error)?,
+        None => default_network(),
+    };
+    table
+        .entry("listen_port")
+        .or_insert_with(|| toml::Value::Integer(default_listen_port(network).into()));
+    table
+        .entry("zebrad_port")
+        .or_insert_with(|| toml::Value::Integer(default_node_port(network).into()));
+    let parsed_config = toml::Value::Table(table)
+        .try_into::<IndexerConfig>()
+        .map_err(parse_error)?;
+    Ok(IndexerConfig {
+        config_version: parsed_config.config_version,
+        network: parsed_config.network,
+        node_type: parsed_config.node_type,
+        tcp_active: parsed_config.tcp_active,
+        listen_port: parsed_config.listen_port,
+        listen_ip: parsed_config.listen_ip,
+        nym_active: parsed_config.nym_active,
+        nym_conf_path: parsed_config.nym_conf_path.or(config.nym_conf_path),
+        nym_max_client_response_bytes: parsed_config.nym_max_client_response_bytes,
+        lightwalletd_port: parsed_config.lightwalletd_port,
+        zebrad_port: parsed_config.zebrad_port,
+        node_auth: parsed_config.node_auth,
+        max_queue_size: parsed_config.max_queue_size,
+        queue_warn_threshold: parsed_config.queue_warn_threshold,
+        queue_error_threshold: parsed_config.queue_error_threshold,
+        overload_policy: parsed_config.overload_policy,
+        max_request_age_secs: parsed_config.max_request_age_secs,
+        max_worker_pool_size: parsed_config.max_worker_pool_size,
+        idle_worker_pool_size: parsed_config.idle_worker_pool_size,
+        max_concurrent_streams_global: parsed_config.max_concurrent_streams_global,
+        max_concurrent_streams_per_client: parsed_config.max_concurrent_streams_per_client,
+        max_requests_per_second_per_ip: parsed_config.max_requests_per_second_per_ip,
+        metrics_port: parsed_config.metrics_port.or(config.metrics_port),
+        rpc_concurrency_limits: parsed_config.rpc_concurrency_limits,
+        rpc_max_wait_ms: parsed_config.rpc_max_wait_ms,
+        rpc_max_request_bytes: parsed_config.rpc_max_request_bytes,
+        node_auth_error_policy: parsed_config.node_auth_error_policy,
+        node_failure_threshold: parsed_config.node_failure_threshold,
+        node_failure_window_ms: parsed_config.node_failure_window_ms,
+        node_probe_interval_ms: parsed_config.node_probe_interval_ms,
+        exit_on_node_loss: parsed_config.exit_on_node_loss,
+        reset_cache_on_chain_mismatch: parsed_config.reset_cache_on_chain_mismatch,
+        grpc_compression: parsed_config.grpc_compression,
+        taddress_txids_include_mempool: parsed_config.taddress_txids_include_mempool,
+        taddress_txids_fetch_concurrency: parsed_config.taddress_txids_fetch_concurrency,
+        latest_block_confirmations: parsed_config.latest_block_confirmations,
+        max_mempool_exclude_entries: parsed_config.max_mempool_exclude_entries,
+        mempool_cache_max_bytes: parsed_config.mempool_cache_max_bytes,
+        max_mempool_tx_age_secs: parsed_config.max_mempool_tx_age_secs,
+        disable_broadcast: parsed_config.disable_broadcast,
+        disable_broadcast_message: parsed_config.disable_broadcast_message,
+        stream_error_policy: parsed_config.stream_error_policy,
+        cache_warm_range: parsed_config.cache_warm_range,
+        block_cache_prefetch_window: parsed_config.block_cache_prefetch_window,
+        block_fetch_concurrency: parsed_config.block_fetch_concurrency,
+        request_log_level: parsed_config.request_log_level,
+        runtime_worker_threads: parsed_config.runtime_worker_threads,
+        keepalive_interval_secs: parsed_config.keepalive_interval_secs,
+        keepalive_timeout_secs: parsed_config.keepalive_timeout_secs,
+        keepalive_tcp: parsed_config.keepalive_tcp,
+        config_path: Some(file_path.clone()),
+    })
 }
@@ -0,0 +1,129 @@
+//! Migration of config files written for earlier config versions.
+//!
+//! Config files are migrated as parsed TOML, before being deserialized into an [`IndexerConfig`]. Each deprecated
+//! setting found is mapped to its replacement and reported as a [`ConfigDeprecation`] telling the operator what to
+//! change in the file. Loading only fails for settings that can not be mapped, eg. a deprecated field given alongside
+//! its replacement. Settings added since a file was written are left out of it and take their default values, so
+//! every field added to the config must have a serde default.
+//!
+//! [`IndexerConfig`]: crate::config::IndexerConfig
+
+use toml::{value::Table, Value};
+
+use crate::{config::CONFIG_VERSION, error::IndexerError};
+
+/// Migration from a config version to the next.
+type Migration = fn(&mut Table) -> Result<Vec<ConfigDeprecation>, IndexerError>;
+
+/// Config migrations, the migration at index `n` upgrades a config from version `n` to `n + 1`.
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize] = [migrate_v0];
+
+/// Deprecated setting found in a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDeprecation {
+    /// Fields of the config file to remove, empty if the setting is only missing.
+    pub fields: Vec<&'static str>,
+    /// TOML to write in their place.
+    pub replacement: String,
+}
+
+impl std::fmt::Display for ConfigDeprecation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.fields.is_empty() {
+            write!(f, "add `{}`", self.replacement)
+        } else {
+            let fields: Vec<String> = self
+                .fields
+                .iter()
+                .map(|field| format!("`{}`", field))
+                .collect();
+            let (verb, pronoun) = if self.fields.len() == 1 {
+                ("is", "it")
+            } else {
+                ("are", "them")
+            };
+            write!(
+                f,
+                "{} {} deprecated, replace {} with `{}`",
+                fields.join(" and "),
+                verb,
+                pronoun,
+                self.replacement
+            )
+        }
+    }
+}
+
+/// Migrates `config`, a parsed config file, to [`CONFIG_VERSION`], returning the deprecated settings replaced.
+///
+/// Files without a `config_version` field are version 0. Fails if the file was written for a newer config version or
+/// holds settings that contradict each other.
+pub fn migrate_config(config: &mut Table) -> Result<Vec<ConfigDeprecation>, IndexerError> {
+    let version = match config.get("config_version") {
+        None => 0,
+        Some(Value::Integer(version)) if (0..=CONFIG_VERSION as i64).contains(version) => {
+            *version as u32
+        }
+        Some(Value::Integer(version)) if *version > CONFIG_VERSION as i64 => {
+            return Err(IndexerError::ConfigError(format!(
+                "Config version {} was written for a newer Zaino, this build supports config version {} or older.",
+                version, CONFIG_VERSION
+            )))
+        }
+        Some(version) => {
+            return Err(IndexerError::ConfigError(format!(
+                "Invalid config_version ({}), expected a version number.",
+                version
+            )))
+        }
+    };
+    let mut deprecations = Vec::new();
+    for migration in &MIGRATIONS[version as usize..] {
+        deprecations.extend(migration(config)?);
+    }
+    if version < CONFIG_VERSION {
+        config.insert(
+            "config_version".to_string(),
+            Value::Integer(CONFIG_VERSION as i64),
+        );
+        deprecations.push(ConfigDeprecation {
+            fields: Vec::new(),
+            replacement: format!("config_version = {}", CONFIG_VERSION),
+        });
+    }
+    Ok(deprecations)
+}
+
+/// Migrates an unversioned config file.
+///
+/// Replaces the `node_user` and `node_password` fields with `node_auth` user / password credentials.
+fn migrate_v0(config: &mut Table) -> Result<Vec<ConfigDeprecation>, IndexerError> {
+    let user = config.remove("node_user");
+    let password = config.remove("node_password");
+    if user.is_none() && password.is_none() {
+        return Ok(Vec::new());
+    }
+    if config.contains_key("node_auth") {
+        return Err(IndexerError::ConfigError(
+            "Both node_auth and the deprecated node_user / node_password are set, remove node_user and node_password."
+                .to_string(),
+        ));
+    }
+    let (Some(user), Some(password)) = (user, password) else {
+        return Err(IndexerError::ConfigError(
+            "Only one of the deprecated node_user and node_password is set, replace both with node_auth.".to_string(),
+        ));
+    };
+    let mut node_auth = Table::new();
+    node_auth.insert("method".to_string(), Value::String("UserPass".to_string()));
+    node_auth.insert("user".to_string(), user);
+    node_auth.insert("password".to_string(), password);
+    config.insert("node_auth".to_string(), Value::Table(node_auth));
+    // The password is left out of the message, which may be logged.
+    Ok(vec![ConfigDeprecation {
+        fields: vec!["node_user", "node_password"],
+        replacement:
+            "node_auth = { method = \"UserPass\", user = \"<node_user>\", password = \"<node_password>\" }"
+                .to_string(),
+    }])
+}
@@ -10,14 +10,22 @@ use std::{
 };
 
 use http::Uri;
+use tokio::signal::unix::{signal, SignalKind};
 use zaino_fetch::jsonrpc::connector::test_node_and_return_uri;
 use zaino_serve::server::{
-    director::{Server, ServerStatus},
+    director::{ReloadHandle, Server, ServerConfig, ServerStatus},
     error::ServerError,
+    keepalive::Keepalive,
     AtomicStatus, StatusType,
 };
 
-use crate::{config::IndexerConfig, error::IndexerError};
+use crate::{
+    config::{try_load_config, IndexerConfig, NodeAuthErrorPolicy},
+    error::IndexerError,
+};
+
+/// Key of the line `<key>=<ip>:<port>` printed to stdout once the gRPC listener is bound, before serving.
+pub const GRPC_ADDR_KEY: &str = "ZAINO_GRPC_ADDR";
 
 /// Holds the status of the server and all its components.
 #[derive(Debug, Clone)]
@@ -47,9 +55,11 @@ impl IndexerStatus {
 /// Zingo-Indexer.
 pub struct Indexer {
     /// Indexer configuration data.
-    _config: IndexerConfig,
+    config: IndexerConfig,
     /// GRPC server.
     server: Option<Server>,
+    /// Updates the server's settings on config reload.
+    reload_handle: ReloadHandle,
     // /// Internal block cache.
     // block_cache: BlockCache,
     /// Indexers status.
@@ -71,22 +81,78 @@ impl Indexer {
             .await?
     }
 
+    /// Runs the indexer to completion on a new multi-threaded runtime, the production entrypoint of zainod.
+    ///
+    /// The runtime has `runtime_worker_threads` worker threads, or one per CPU core if not set. The indexer shuts down
+    /// gracefully on SIGINT or SIGTERM, returning once its components have closed.
+    pub fn run_blocking(config: IndexerConfig) -> Result<(), IndexerError> {
+        config.check_config()?;
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all().thread_name("zainod-worker");
+        if let Some(worker_threads) = config.runtime_worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        let runtime = builder.build().map_err(|e| {
+            IndexerError::MiscIndexerError(format!("Failed to build runtime: {}", e))
+        })?;
+        runtime.block_on(async move {
+            let online = Arc::new(AtomicBool::new(true));
+            let signal_error = |e: std::io::Error| {
+                IndexerError::MiscIndexerError(format!("Failed to listen for signals: {}", e))
+            };
+            let mut interrupt = signal(SignalKind::interrupt()).map_err(signal_error)?;
+            let mut terminate = signal(SignalKind::terminate()).map_err(signal_error)?;
+            startup_message();
+            let mut indexer_handle = Indexer::start_indexer_service(config, online.clone()).await?;
+            tokio::select! {
+                result = &mut indexer_handle => return result?,
+                _ = interrupt.recv() => println!("Received SIGINT, shutting down."),
+                _ = terminate.recv() => println!("Received SIGTERM, shutting down."),
+            }
+            online.store(false, Ordering::SeqCst);
+            indexer_handle.await?
+        })
+    }
+
     /// Launches an Indexer service.
     ///
-    /// Spawns an indexer service in a new task.
+    /// Spawns an indexer service in a new task. The config file is re-read on SIGHUP, or when requested through the
+    /// [`ReloadHandle`] returned by [`Indexer::launch_indexer_service`], see [`Indexer::reload_config`].
     pub async fn start_indexer_service(
         config: IndexerConfig,
         online: Arc<AtomicBool>,
     ) -> Result<tokio::task::JoinHandle<Result<(), IndexerError>>, IndexerError> {
+        Ok(Self::launch_indexer_service(config, online).await?.0)
+    }
+
+    /// Launches an Indexer service, returning its task handle, the address its gRPC server is listening at and a handle
+    /// requesting config reloads, as on SIGHUP.
+    ///
+    /// The address holds the OS assigned port if the listen port is configured as 0, it is `None` if tcp is not active.
+    pub async fn launch_indexer_service(
+        config: IndexerConfig,
+        online: Arc<AtomicBool>,
+    ) -> Result<
+        (
+            tokio::task::JoinHandle<Result<(), IndexerError>>,
+            Option<SocketAddr>,
+            ReloadHandle,
+        ),
+        IndexerError,
+    > {
         // NOTE: This interval may need to be reduced or removed / moved once scale testing begins.
         let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(50));
+        #[cfg(feature = "nym")]
         if config.nym_active {
             nym_bin_common::logging::setup_logging();
         }
         println!("Launching Zingdexer!");
         let mut indexer: Indexer = Indexer::new(config, online.clone()).await?;
-        Ok(tokio::task::spawn(async move {
-            let server_handle = if let Some(server) = indexer.server.take() {
+        let grpc_addr = indexer.server.as_ref().and_then(Server::grpc_addr);
+        let reload_handle = indexer.reload_handle.clone();
+        let reload_requests = reload_handle.clone();
+        let handle = tokio::task::spawn(async move {
+            let mut server_handle = if let Some(server) = indexer.server.take() {
                 Some(server.serve().await)
             } else {
                 return Err(IndexerError::MiscIndexerError(
@@ -94,19 +160,45 @@ impl Indexer {
                 ));
             };
 
+            let mut reload_signal = signal(SignalKind::hangup()).map_err(|e| {
+                IndexerError::MiscIndexerError(format!("Failed to listen for SIGHUP: {}", e))
+            })?;
             indexer.status.indexer_status.store(2);
             loop {
                 indexer.status.load();
                 // indexer.log_status();
+                if server_handle
+                    .as_ref()
+                    .is_some_and(|handle| handle.is_finished())
+                {
+                    // The server stopped on its own, either shutting down or on a fatal error such as losing
+                    // the node with exit_on_node_loss set, which is returned to the caller.
+                    indexer.status.indexer_status.store(5);
+                    if let Some(handle) = server_handle.take() {
+                        handle.await??;
+                    }
+                    return Ok(());
+                }
                 if indexer.check_for_shutdown() {
                     indexer.status.indexer_status.store(4);
                     indexer.shutdown_components(server_handle).await;
                     indexer.status.indexer_status.store(5);
                     return Ok(());
                 }
-                interval.tick().await;
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = reload_signal.recv() => {
+                        println!("Received SIGHUP, reloading config.");
+                        indexer.reload_config();
+                    }
+                    _ = reload_requests.reload_requested() => {
+                        println!("Config reload requested, reloading config.");
+                        indexer.reload_config();
+                    }
+                }
             }
-        }))
+        });
+        Ok((handle, grpc_addr, reload_handle))
     }
 
     /// Creates a new Indexer.
@@ -114,9 +206,13 @@ impl Indexer {
     /// Currently only takes an IndexerConfig.
     async fn new(config: IndexerConfig, online: Arc<AtomicBool>) -> Result<Self, IndexerError> {
         config.check_config()?;
+        println!("Effective config:\n{}", config.redacted());
         let status = IndexerStatus::new(config.max_worker_pool_size);
         let tcp_ingestor_listen_addr: Option<SocketAddr> = config
             .listen_port
+            .map(|port| SocketAddr::new(config.listen_ip, port));
+        let metrics_listen_addr: Option<SocketAddr> = config
+            .metrics_port
             .map(|port| SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST), port));
         let lightwalletd_uri = Uri::builder()
             .scheme("http")
@@ -124,38 +220,147 @@ impl Indexer {
             .path_and_query("/")
             .build()?;
         println!("Checking connection with node..");
-        let zebrad_uri = test_node_and_return_uri(
-            &config.zebrad_port,
-            config.node_user.clone(),
-            config.node_password.clone(),
-        )
-        .await?;
+        let zebrad_uri = test_node_and_return_uri(&config.zebrad_port, &config.node_auth).await?;
         status.indexer_status.store(0);
-        let server = Some(
-            Server::spawn(
-                config.tcp_active,
+        let server = Server::spawn(
+            ServerConfig {
+                tcp_active: config.tcp_active,
                 tcp_ingestor_listen_addr,
-                config.nym_active,
-                config.nym_conf_path.clone(),
+                nym_active: config.nym_active,
+                nym_conf_path: config.nym_conf_path.clone(),
+                nym_max_client_response_bytes: config.nym_max_client_response_bytes,
                 lightwalletd_uri,
                 zebrad_uri,
-                config.max_queue_size,
-                config.max_worker_pool_size,
-                config.idle_worker_pool_size,
-                status.server_status.clone(),
-                online.clone(),
-            )
-            .await?,
-        );
+                node_auth: config.node_auth.clone(),
+                network: config.network,
+                node_type: config.node_type,
+                taddress_txids_include_mempool: config.taddress_txids_include_mempool,
+                taddress_txids_fetch_concurrency: config.taddress_txids_fetch_concurrency,
+                latest_block_confirmations: config.latest_block_confirmations,
+                max_mempool_exclude_entries: config.max_mempool_exclude_entries,
+                mempool_cache_max_bytes: config.mempool_cache_max_bytes,
+                max_mempool_tx_age: (config.max_mempool_tx_age_secs > 0)
+                    .then(|| std::time::Duration::from_secs(config.max_mempool_tx_age_secs)),
+                broadcast_disabled: config
+                    .disable_broadcast
+                    .then(|| config.disable_broadcast_message.clone()),
+                stream_error_policy: config.stream_error_policy,
+                max_queue_size: config.max_queue_size,
+                queue_warn_threshold: config.queue_warn_threshold,
+                queue_error_threshold: config.queue_error_threshold,
+                overload_policy: config.overload_policy,
+                max_request_age: (config.max_request_age_secs > 0)
+                    .then(|| std::time::Duration::from_secs(config.max_request_age_secs)),
+                max_worker_pool_size: config.max_worker_pool_size,
+                idle_worker_pool_size: config.idle_worker_pool_size,
+                max_concurrent_streams: config.max_concurrent_streams_global,
+                max_streams_per_connection: config.max_concurrent_streams_per_client,
+                max_requests_per_second_per_ip: config.max_requests_per_second_per_ip,
+                rpc_concurrency_limits: config.rpc_concurrency_limits.clone(),
+                rpc_max_wait: std::time::Duration::from_millis(config.rpc_max_wait_ms),
+                rpc_max_request_bytes: config.rpc_max_request_bytes.clone(),
+                grpc_compression: config
+                    .grpc_compression
+                    .iter()
+                    .map(|compression| compression.encoding())
+                    .collect(),
+                request_log_level: config.request_log_level,
+                keepalive: Keepalive {
+                    interval: config
+                        .keepalive_interval_secs
+                        .map(std::time::Duration::from_secs),
+                    timeout: config
+                        .keepalive_timeout_secs
+                        .map(std::time::Duration::from_secs),
+                    tcp: config.keepalive_tcp,
+                },
+                metrics_listen_addr,
+                degrade_on_auth_error: config.node_auth_error_policy
+                    == NodeAuthErrorPolicy::Degrade,
+                node_failure_threshold: config.node_failure_threshold,
+                node_failure_window: std::time::Duration::from_millis(
+                    config.node_failure_window_ms,
+                ),
+                node_probe_interval: std::time::Duration::from_millis(
+                    config.node_probe_interval_ms,
+                ),
+                exit_on_node_loss: config.exit_on_node_loss,
+                reset_cache_on_chain_mismatch: config.reset_cache_on_chain_mismatch,
+                cache_warm_range: config.cache_warm_range.clone(),
+                block_cache_prefetch_window: config.block_cache_prefetch_window,
+                block_fetch_concurrency: config.block_fetch_concurrency,
+            },
+            status.server_status.clone(),
+            online.clone(),
+        )
+        .await?;
+        let reload_handle = server.reload_handle();
+        let grpc_addr = server.grpc_addr();
+        if let Some(addr) = grpc_addr {
+            // Machine readable, for scripts that start zainod with an OS assigned port.
+            println!("{}={}", GRPC_ADDR_KEY, addr);
+        }
         println!("Server Ready.");
         Ok(Indexer {
-            _config: config,
-            server,
+            config,
+            server: Some(server),
+            reload_handle,
             status,
             online,
         })
     }
 
+    /// Re-reads the config file the indexer was started from and applies the changes that can be made while running.
+    ///
+    /// The worker pool sizes, request queue size, request log level and per client IP request rate limit are updated,
+    /// changes to other fields are logged and take effect on restart.
+    /// The current config is kept if the file can not be loaded or fails validation.
+    fn reload_config(&mut self) {
+        let Some(config_path) = self.config.config_path.clone() else {
+            eprintln!("Indexer was not started from a config file, ignoring config reload.");
+            return;
+        };
+        let new_config = match try_load_config(&config_path)
+            .and_then(|config| config.check_config().map(|_| config))
+        {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Failed to reload config, keeping current config: {}", e);
+                return;
+            }
+        };
+        for field in self.config.restart_required_changes(&new_config) {
+            eprintln!(
+                "Config reload: {} changed, restart the indexer to apply this change.",
+                field
+            );
+        }
+        match self.reload_handle.set_worker_pool_size(
+            new_config.max_worker_pool_size,
+            new_config.idle_worker_pool_size,
+        ) {
+            Ok(()) => {
+                self.config.max_worker_pool_size = new_config.max_worker_pool_size;
+                self.config.idle_worker_pool_size = new_config.idle_worker_pool_size;
+            }
+            Err(e) => eprintln!("Failed to apply reloaded worker pool size: {}", e),
+        }
+        match self
+            .reload_handle
+            .set_max_queue_size(new_config.max_queue_size)
+        {
+            Ok(()) => self.config.max_queue_size = new_config.max_queue_size,
+            Err(e) => eprintln!("Failed to apply reloaded queue size: {}", e),
+        }
+        self.reload_handle
+            .set_request_log_level(new_config.request_log_level);
+        self.config.request_log_level = new_config.request_log_level;
+        self.reload_handle
+            .set_max_requests_per_second_per_ip(new_config.max_requests_per_second_per_ip);
+        self.config.max_requests_per_second_per_ip = new_config.max_requests_per_second_per_ip;
+        println!("Config reloaded from {}.", config_path.display());
+    }
+
     /// Checks indexers online status and servers internal status for closure signal.
     fn check_for_shutdown(&self) -> bool {
         if self.status() >= 4 {